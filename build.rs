@@ -0,0 +1,74 @@
+//! Embeds build metadata served by `GET /version`.
+//!
+//! - `X402_GIT_COMMIT` — taken from the `GIT_COMMIT` env var if set (e.g. in Docker builds without `.git`),
+//!   otherwise from `git rev-parse HEAD`; `unknown` if neither is available.
+//! - `X402_BUILD_TIMESTAMP` — Unix seconds, honouring `SOURCE_DATE_EPOCH` for reproducible builds.
+//! - `X402_FEATURES` — comma-separated Cargo features enabled, from the `CARGO_FEATURE_*` variables set by Cargo.
+//!
+//! With the `grpc` feature, also generates the gRPC service of `proto/facilitator.proto`, and with the `lightning`
+//! feature the gRPC clients of LND and Core Lightning in `proto/lnd.proto` and `proto/cln.proto`; both require `protoc`.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rerun-if-env-changed=GIT_COMMIT");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+
+    let git_commit = std::env::var("GIT_COMMIT")
+        .ok()
+        .filter(|s| !s.is_empty())
+        .or_else(|| {
+            Command::new("git")
+                .args(["rev-parse", "HEAD"])
+                .output()
+                .ok()
+                .filter(|output| output.status.success())
+                .and_then(|output| String::from_utf8(output.stdout).ok())
+                .map(|s| s.trim().to_string())
+        })
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=X402_GIT_COMMIT={git_commit}");
+
+    let build_timestamp = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=X402_BUILD_TIMESTAMP={build_timestamp}");
+
+    // Cargo upper-cases feature names and replaces `-` with `_`; features of this crate only use `-`.
+    let mut features: Vec<String> = std::env::vars()
+        .filter_map(|(name, _)| {
+            name.strip_prefix("CARGO_FEATURE_")
+                .map(|feature| feature.to_lowercase().replace('_', "-"))
+        })
+        .filter(|feature| feature != "default")
+        .collect();
+    features.sort();
+    println!("cargo:rustc-env=X402_FEATURES={}", features.join(","));
+
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/facilitator.proto");
+        tonic_build::configure()
+            .build_client(false)
+            .compile_protos(&["proto/facilitator.proto"], &["proto"])
+            .expect("Failed to compile proto/facilitator.proto");
+    }
+
+    #[cfg(feature = "lightning")]
+    {
+        println!("cargo:rerun-if-changed=proto/lnd.proto");
+        println!("cargo:rerun-if-changed=proto/cln.proto");
+        tonic_build::configure()
+            .build_server(false)
+            .compile_protos(&["proto/lnd.proto", "proto/cln.proto"], &["proto"])
+            .expect("Failed to compile the Lightning node protos");
+    }
+}
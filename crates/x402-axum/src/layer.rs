@@ -37,24 +37,26 @@
 //!
 //! - **[`X402Middleware::with_price_tag`]** sets the assets and amounts accepted for payment.
 //! - **[`X402Middleware::with_description`]** and **[`X402Middleware::with_mime_type`]** are optional but help the payer understand what is being paid for.
+//!   If unset, they are derived from each request: `Access to <path>`, and the type the request `Accept`s.
 //! - **[`X402Middleware::with_resource`]** explicitly sets the full URI of the protected resource.
-//!   This avoids recomputing [`PaymentRequirements`] on every request and should be preferred when possible.
+//!   Together with a description and a MIME type, this avoids recomputing [`PaymentRequirements`] on every request.
 //! - If `with_resource` is **not** used, the middleware will compute the resource URI dynamically from the request
 //!   and a base URL set via **[`X402Middleware::with_base_url`]**.
-//! - If no base URL is provided, the default is `http://localhost/` (⚠️ avoid this in production).
+//! - If no base URL is provided either, the scheme and host are taken from the request's forwarding headers or `Host`.
+//!
+//! See [`crate::resource`] for how the resource fields are derived.
 //!
 //! ## Best Practices (Production)
 //!
 //! - Use [`X402Middleware::with_resource`] when the full resource URL is known.
-//! - Set[`X402Middleware::with_base_url`] to support dynamic resource resolution.
-//! - ⚠️ Avoid relying on fallback `resource` value in production.
+//! - Set [`X402Middleware::with_base_url`] if the facilitator sits behind a proxy not setting `X-Forwarded-Host`.
 
 use axum_core::body::Body;
 use axum_core::{
     extract::Request,
     response::{IntoResponse, Response},
 };
-use http::{HeaderMap, HeaderValue, StatusCode, Uri};
+use http::{HeaderMap, HeaderValue, StatusCode};
 use once_cell::sync::Lazy;
 use serde_json::json;
 use std::collections::HashSet;
@@ -72,9 +74,9 @@ use url::Url;
 use x402_rs::facilitator::Facilitator;
 use x402_rs::network::Network;
 use x402_rs::types::{
-    Base64Bytes, FacilitatorErrorReason, MixedAddress, PaymentPayload, PaymentRequiredResponse,
-    PaymentRequirements, Scheme, SettleRequest, SettleResponse, TokenAmount, VerifyRequest,
-    VerifyResponse, X402Version,
+    Base64Bytes, FacilitatorErrorReason, MixedAddress, PaymentId, PaymentPayload,
+    PaymentRequiredResponse, PaymentRequirements, ResourceInfo, Scheme, SettleRequest,
+    SettleResponse, TokenAmount, VerifyRequest, VerifyResponse, X402Version,
 };
 
 #[cfg(feature = "telemetry")]
@@ -82,6 +84,7 @@ use tracing::{Instrument, Level, instrument};
 
 use crate::facilitator_client::{FacilitatorClient, FacilitatorClientError};
 use crate::price::PriceTag;
+use crate::resource::ResourceTemplate;
 
 /// Middleware layer that enforces x402 payment verification and settlement.
 ///
@@ -92,14 +95,8 @@ use crate::price::PriceTag;
 pub struct X402Middleware<F> {
     /// The facilitator used to verify and settle payments.
     facilitator: Arc<F>,
-    /// Optional description string passed along with payment requirements. Empty string by default.
-    description: Option<String>,
-    /// Optional MIME type of the protected resource. `application/json` by default.
-    mime_type: Option<String>,
-    /// Optional resource URL. If not set, it will be derived from a request URI.
-    resource: Option<Url>,
-    /// Optional base URL for computing full resource URLs if `resource` is not set, see [`X402Middleware::resource`].
-    base_url: Option<Url>,
+    /// Configured `resource`, `description` and `mimeType`; unset ones are derived from each request.
+    resource: ResourceTemplate,
     /// List of price tags accepted for this endpoint.
     price_tag: Vec<PriceTag>,
     /// Timeout in seconds for payment settlement.
@@ -107,9 +104,8 @@ pub struct X402Middleware<F> {
     /// Cached set of payment offers for this middleware instance.
     ///
     /// This field holds either:
-    /// - a fully constructed list of [`PaymentRequirements`] (if [`X402Middleware::with_resource`] was used),
-    /// - or a partial list without `resource`, in which case the resource URL will be computed dynamically per request.
-    ///   In this case, please add `base_url` via [`X402Middleware::with_base_url`].
+    /// - a fully constructed list of [`PaymentRequirements`] (if the resource, description and MIME type are all set),
+    /// - or a partial list without resource fields, completed per request by the [`ResourceTemplate`].
     payment_offers: Arc<PaymentOffers>,
 }
 
@@ -135,10 +131,7 @@ impl<F> X402Middleware<F> {
     pub fn new(facilitator: F) -> Self {
         Self {
             facilitator: Arc::new(facilitator),
-            description: None,
-            mime_type: None,
-            resource: None,
-            base_url: None,
+            resource: ResourceTemplate::default(),
             max_timeout_seconds: 300,
             price_tag: Vec::new(),
             payment_offers: Arc::new(PaymentOffers::Ready(Arc::new(Vec::new()))),
@@ -147,7 +140,8 @@ impl<F> X402Middleware<F> {
 
     /// Returns the configured base URL for x402-protected resources, or `http://localhost/` if not set.
     pub fn base_url(&self) -> Url {
-        self.base_url
+        self.resource
+            .base_url
             .clone()
             .unwrap_or(Url::parse("http://localhost/").unwrap())
     }
@@ -160,7 +154,7 @@ where
     /// Sets the description field on all generated payment requirements.
     pub fn with_description(&self, description: &str) -> Self {
         let mut this = self.clone();
-        this.description = Some(description.to_string());
+        this.resource.description = Some(description.to_string());
         this.recompute_offers()
     }
 
@@ -168,7 +162,7 @@ where
     /// This is exposed as a part of [`PaymentRequirements`] passed to the client.
     pub fn with_mime_type(&self, mime: &str) -> Self {
         let mut this = self.clone();
-        this.mime_type = Some(mime.to_string());
+        this.resource.mime_type = Some(mime.to_string());
         this.recompute_offers()
     }
 
@@ -176,20 +170,18 @@ where
     #[allow(dead_code)] // Public for consumption by downstream crates.
     pub fn with_resource(&self, resource: Url) -> Self {
         let mut this = self.clone();
-        this.resource = Some(resource);
+        this.resource.resource = Some(resource);
         this.recompute_offers()
     }
 
     /// Sets the base URL used to construct resource URLs dynamically.
     ///
     /// Note: If [`with_resource`] is not called, this base URL is combined with
-    /// each request's path/query to compute the resource. If not set, defaults to `http://localhost/`.
-    ///
-    /// ⚠️ In production, prefer calling `with_resource` or setting a precise `base_url` to avoid accidental localhost fallback.
+    /// each request's path/query to compute the resource. If not set, the request's scheme and host are used.
     #[allow(dead_code)] // Public for consumption by downstream crates.
     pub fn with_base_url(&self, base_url: Url) -> Self {
         let mut this = self.clone();
-        this.base_url = Some(base_url);
+        this.resource.base_url = Some(base_url);
         this.recompute_offers()
     }
 
@@ -223,73 +215,40 @@ where
     }
 
     fn recompute_offers(mut self) -> Self {
-        let base_url = self.base_url();
-        let description = self.description.clone().unwrap_or_default();
-        let mime_type = self
-            .mime_type
-            .clone()
-            .unwrap_or("application/json".to_string());
         let max_timeout_seconds = self.max_timeout_seconds;
-        let payment_offers = if let Some(resource) = self.resource.clone() {
-            let payment_requirements = self
-                .price_tag
-                .iter()
-                .map(|price_tag| {
-                    let extra = if let Some(eip712) = price_tag.token.eip712.clone() {
-                        Some(json!({
-                            "name": eip712.name,
-                            "version": eip712.version
-                        }))
-                    } else {
-                        None
-                    };
-                    PaymentRequirements {
-                        scheme: Scheme::Exact,
-                        network: price_tag.token.network(),
-                        max_amount_required: price_tag.amount,
-                        resource: resource.clone(),
-                        description: description.clone(),
-                        mime_type: mime_type.clone(),
-                        pay_to: price_tag.pay_to.clone(),
-                        max_timeout_seconds,
-                        asset: price_tag.token.address(),
-                        extra,
-                        output_schema: None,
-                    }
-                })
-                .collect::<Vec<_>>();
-            PaymentOffers::Ready(Arc::new(payment_requirements))
-        } else {
-            let no_resource = self
-                .price_tag
-                .iter()
-                .map(|price_tag| {
-                    let extra = if let Some(eip712) = price_tag.token.eip712.clone() {
-                        Some(json!({
-                            "name": eip712.name,
-                            "version": eip712.version
-                        }))
-                    } else {
-                        None
-                    };
-                    PaymentRequirementsNoResource {
-                        scheme: Scheme::Exact,
-                        network: price_tag.token.network(),
-                        max_amount_required: price_tag.amount,
-                        description: description.clone(),
-                        mime_type: mime_type.clone(),
-                        pay_to: price_tag.pay_to.clone(),
-                        max_timeout_seconds,
-                        asset: price_tag.token.address(),
-                        extra,
-                        output_schema: None,
-                    }
-                })
-                .collect::<Vec<_>>();
-            PaymentOffers::NoResource {
-                partial: no_resource,
-                base_url,
-            }
+        let partial = self
+            .price_tag
+            .iter()
+            .map(|price_tag| {
+                let extra = price_tag.token.eip712.clone().map(|eip712| {
+                    json!({
+                        "name": eip712.name,
+                        "version": eip712.version
+                    })
+                });
+                PaymentRequirementsNoResource {
+                    scheme: Scheme::Exact,
+                    network: price_tag.token.network(),
+                    max_amount_required: price_tag.amount,
+                    pay_to: price_tag.pay_to.clone(),
+                    max_timeout_seconds,
+                    asset: price_tag.token.address(),
+                    extra,
+                    output_schema: None,
+                }
+            })
+            .collect::<Vec<_>>();
+        let payment_offers = match self.resource.fixed() {
+            Some(resource) => PaymentOffers::Ready(Arc::new(
+                partial
+                    .iter()
+                    .map(|partial| partial.to_payment_requirements(resource.clone()))
+                    .collect(),
+            )),
+            None => PaymentOffers::NoResource {
+                partial,
+                template: Box::new(self.resource.clone()),
+            },
         };
         self.payment_offers = Arc::new(payment_offers);
         self
@@ -322,12 +281,6 @@ where
     type Service = X402MiddlewareService<F>;
 
     fn layer(&self, inner: S) -> Self::Service {
-        if self.base_url.is_none() && self.resource.is_none() {
-            #[cfg(feature = "telemetry")]
-            tracing::warn!(
-                "X402Middleware base_url is not configured; defaulting to http://localhost/ for resource resolution"
-            );
-        }
         X402MiddlewareService {
             facilitator: self.facilitator.clone(),
             payment_offers: self.payment_offers.clone(),
@@ -351,8 +304,7 @@ where
 
     /// Intercepts the request, injects payment enforcement logic, and forwards to the wrapped service.
     fn call(&mut self, req: Request) -> Self::Future {
-        let payment_requirements =
-            gather_payment_requirements(self.payment_offers.as_ref(), req.uri());
+        let payment_requirements = gather_payment_requirements(self.payment_offers.as_ref(), &req);
         let gate = X402Paygate {
             facilitator: self.facilitator.clone(),
             payment_requirements,
@@ -437,7 +389,14 @@ impl X402Error {
 }
 
 impl IntoResponse for X402Error {
-    fn into_response(self) -> Response {
+    /// Renders the 402 response, issuing one fresh [`PaymentId`] for all the offered requirements.
+    fn into_response(mut self) -> Response {
+        let payment_id = PaymentId::random();
+        for requirements in &mut self.0.accepts {
+            requirements
+                .payment_id
+                .get_or_insert_with(|| payment_id.clone());
+        }
         let payment_required_response_bytes =
             serde_json::to_vec(&self.0).expect("serialization failed");
         let body = Body::from(payment_required_response_bytes);
@@ -536,15 +495,19 @@ where
         &self,
         payment_payload: PaymentPayload,
     ) -> Result<VerifyRequest, X402Error> {
-        let selected = self
+        let mut selected = self
             .find_matching_payment_requirements(&payment_payload)
             .ok_or(X402Error::no_payment_matching(
                 self.payment_requirements.as_ref().clone(),
             ))?;
+        selected.payment_id = payment_payload.payment_id.clone();
         let verify_request = VerifyRequest {
             x402_version: payment_payload.x402_version,
             payment_payload,
             payment_requirements: selected,
+            settle_amount: None,
+            allow_partial: false,
+            execute_after: None,
         };
         let verify_response = self
             .facilitator
@@ -684,16 +647,14 @@ where
     }
 }
 
-/// A variant of [`PaymentRequirements`] without the `resource` field.
+/// A variant of [`PaymentRequirements`] without the `resource`, `description` and `mimeType` fields.
 /// This allows resources to be dynamically inferred per request.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub struct PaymentRequirementsNoResource {
     pub scheme: Scheme,
     pub network: Network,
     pub max_amount_required: TokenAmount,
-    // no resource: Url,
-    pub description: String,
-    pub mime_type: String,
+    // no resource: Url, description: String, mime_type: String
     pub pay_to: MixedAddress,
     pub max_timeout_seconds: u64,
     pub asset: MixedAddress,
@@ -703,34 +664,37 @@ pub struct PaymentRequirementsNoResource {
 
 impl PaymentRequirementsNoResource {
     /// Converts this partial requirement into a full [`PaymentRequirements`]
-    /// using the provided resource URL.
-    pub fn to_payment_requirements(&self, resource: Url) -> PaymentRequirements {
+    /// for the provided resource.
+    pub fn to_payment_requirements(&self, resource: ResourceInfo) -> PaymentRequirements {
         PaymentRequirements {
             scheme: self.scheme,
             network: self.network,
             max_amount_required: self.max_amount_required,
-            resource,
-            description: self.description.clone(),
-            mime_type: self.mime_type.clone(),
+            resource: resource.url,
+            description: resource.description,
+            mime_type: resource.mime_type,
             pay_to: self.pay_to.clone(),
             max_timeout_seconds: self.max_timeout_seconds,
             asset: self.asset.clone(),
             extra: self.extra.clone(),
             output_schema: self.output_schema.clone(),
+            payment_id: None,
+            price_usd: None,
+            facilitator_fee: None,
         }
     }
 }
 
-/// Enum capturing either fully constructed [`PaymentRequirements`] (with `resource`)
+/// Enum capturing either fully constructed [`PaymentRequirements`] (with resource fields)
 /// or resource-less variants that must be completed at runtime.
 #[derive(Clone, Debug, Eq, PartialEq)]
 pub enum PaymentOffers {
-    /// [`PaymentRequirements`] with static `resource` field.
+    /// [`PaymentRequirements`] with static resource fields.
     Ready(Arc<Vec<PaymentRequirements>>),
-    /// [`PaymentRequirements`] lacking `resource`, to be added per request.
+    /// [`PaymentRequirements`] lacking resource fields, to be completed per request by `template`.
     NoResource {
         partial: Vec<PaymentRequirementsNoResource>,
-        base_url: Url,
+        template: Box<ResourceTemplate>,
     },
 }
 
@@ -740,34 +704,29 @@ pub enum PaymentOffers {
 /// based on the provided [`PaymentOffers`].
 ///
 /// - If `payment_offers` is [`PaymentOffers::Ready`], it returns an Arc clone of the precomputed requirements.
-/// - If `payment_offers` is [`PaymentOffers::NoResource`], it dynamically constructs the `resource` URI
-///   by combining the `base_url` with the request's path and query, and completes each
-///   partial `PaymentRequirementsNoResource` into a full `PaymentRequirements`.
+/// - If `payment_offers` is [`PaymentOffers::NoResource`], it resolves the resource fields of the request
+///   with [`ResourceTemplate::resolve`], and completes each partial `PaymentRequirementsNoResource`
+///   into a full `PaymentRequirements`.
 ///
 /// # Arguments
 ///
 /// * `payment_offers` - The current payment offer configuration, either precomputed or partial.
-/// * `req_uri` - The incoming request URI used to construct the full resource path if needed.
+/// * `req` - The incoming request the resource fields are derived from if needed.
 ///
 /// # Returns
 ///
 /// An `Arc<Vec<PaymentRequirements>>` ready to be passed to a facilitator for verification.
-fn gather_payment_requirements(
+fn gather_payment_requirements<B>(
     payment_offers: &PaymentOffers,
-    req_uri: &Uri,
+    req: &http::Request<B>,
 ) -> Arc<Vec<PaymentRequirements>> {
     match payment_offers {
         PaymentOffers::Ready(requirements) => {
             // requirements is &Arc<Vec<PaymentRequirements>>
             Arc::clone(requirements)
         }
-        PaymentOffers::NoResource { partial, base_url } => {
-            let resource = {
-                let mut resource_url = base_url.clone();
-                resource_url.set_path(req_uri.path());
-                resource_url.set_query(req_uri.query());
-                resource_url
-            };
+        PaymentOffers::NoResource { partial, template } => {
+            let resource = template.resolve(req);
             let payment_requirements = partial
                 .iter()
                 .map(|partial| partial.to_payment_requirements(resource.clone()))
@@ -37,10 +37,16 @@
 //! To define price tags for your protected routes, see the [`price`] module.
 //! It provides builder-style helpers like [`IntoPriceTag`] and types like [`PriceTag`]
 //! for working with tokens, networks, and payment amounts.
+//!
+//! ## Describing Resources
+//!
+//! The `resource`, `description` and `mimeType` of payment requirements not configured on the middleware are
+//! derived from each request, see the [`resource`] module.
 
 pub mod facilitator_client;
 pub mod layer;
 pub mod price;
+pub mod resource;
 
 pub use layer::X402Middleware;
 pub use price::*;
@@ -0,0 +1,126 @@
+//! Resource fields of [`PaymentRequirements`](x402_rs::types::PaymentRequirements) derived from the request.
+//!
+//! Every requirement names the paid resource with `resource`, `description` and `mimeType`, which clients show to
+//! the payer and discovery services index. [`ResourceTemplate`] holds what was configured on the middleware and
+//! completes the rest from the request being paid for:
+//! - `resource` — the configured resource, or the request path and query on the configured base URL. Without a
+//!   base URL, the scheme and host are taken from `X-Forwarded-Proto`/`X-Forwarded-Host`, then the request URI or
+//!   its `Host` header, falling back to `http://localhost/`;
+//! - `description` — the configured description, or `Access to <path>`;
+//! - `mimeType` — the configured MIME type, or the first concrete type of the request's `Accept` header, falling
+//!   back to `application/json`.
+//!
+//! ```rust
+//! use http::Request;
+//! use x402_axum::resource::ResourceTemplate;
+//!
+//! let request = Request::get("/weather?city=Berlin")
+//!     .header("Host", "api.example.com")
+//!     .header("Accept", "text/html, */*")
+//!     .body(())
+//!     .unwrap();
+//! let resource = ResourceTemplate::default().resolve(&request);
+//! assert_eq!(resource.url.as_str(), "http://api.example.com/weather?city=Berlin");
+//! assert_eq!(resource.description, "Access to /weather");
+//! assert_eq!(resource.mime_type, "text/html");
+//! ```
+
+use http::{HeaderMap, Request, Uri, header};
+use url::Url;
+use x402_rs::types::ResourceInfo;
+
+/// MIME type of resources whose requests accept anything.
+pub const DEFAULT_MIME_TYPE: &str = "application/json";
+
+/// Resource fields configured on the middleware; unset ones are derived from each request.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ResourceTemplate {
+    /// Full URL of the resource, the same for every request.
+    pub resource: Option<Url>,
+    /// Base URL the request path and query are appended to, if `resource` is unset.
+    pub base_url: Option<Url>,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+impl ResourceTemplate {
+    /// Resource info not depending on the request, if every field is configured.
+    pub fn fixed(&self) -> Option<ResourceInfo> {
+        Some(ResourceInfo {
+            url: self.resource.clone()?,
+            description: self.description.clone()?,
+            mime_type: self.mime_type.clone()?,
+        })
+    }
+
+    /// Resource info of `request`, completing the configured fields.
+    pub fn resolve<B>(&self, request: &Request<B>) -> ResourceInfo {
+        let uri = request.uri();
+        let headers = request.headers();
+        let url = match (&self.resource, &self.base_url) {
+            (Some(resource), _) => resource.clone(),
+            (None, Some(base_url)) => with_path_and_query(base_url.clone(), uri),
+            (None, None) => {
+                request_url(uri, headers).unwrap_or_else(|| with_path_and_query(localhost(), uri))
+            }
+        };
+        let description = self
+            .description
+            .clone()
+            .unwrap_or_else(|| format!("Access to {}", uri.path()));
+        let mime_type = self
+            .mime_type
+            .clone()
+            .or_else(|| accepted_mime_type(headers))
+            .unwrap_or_else(|| DEFAULT_MIME_TYPE.to_string());
+        ResourceInfo {
+            url,
+            description,
+            mime_type,
+        }
+    }
+}
+
+fn localhost() -> Url {
+    Url::parse("http://localhost/").expect("valid URL")
+}
+
+fn with_path_and_query(mut url: Url, uri: &Uri) -> Url {
+    url.set_path(uri.path());
+    url.set_query(uri.query());
+    url
+}
+
+/// URL the client requested, from forwarding headers, an absolute request URI or the `Host` header.
+pub fn request_url(uri: &Uri, headers: &HeaderMap) -> Option<Url> {
+    let header = |name: &str| {
+        headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            // Proxies chaining forwarding headers list the original value first.
+            .and_then(|value| value.split(',').next())
+            .map(str::trim)
+            .filter(|value| !value.is_empty())
+    };
+    let scheme = header("x-forwarded-proto")
+        .or(uri.scheme_str())
+        .unwrap_or("http");
+    let host = header("x-forwarded-host")
+        .or(uri.authority().map(|authority| authority.as_str()))
+        .or(header(header::HOST.as_str()))?;
+    let url = Url::parse(&format!("{scheme}://{host}/")).ok()?;
+    Some(with_path_and_query(url, uri))
+}
+
+/// First media type of the `Accept` header that is not a wildcard, without its parameters.
+pub fn accepted_mime_type(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|range| range.split(';').next())
+        .map(str::trim)
+        .find(|range| !range.is_empty() && !range.contains('*'))
+        .map(str::to_ascii_lowercase)
+}
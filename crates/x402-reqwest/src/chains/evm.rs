@@ -57,6 +57,9 @@ impl SenderWallet for EvmSenderWallet {
         match network_family {
             NetworkFamily::Evm => true,
             NetworkFamily::Solana => false,
+            NetworkFamily::Lightning => false,
+            NetworkFamily::Near => false,
+            NetworkFamily::Tron => false,
         }
     }
 
@@ -130,6 +133,7 @@ impl SenderWallet for EvmSenderWallet {
                 signature: EvmSignature::from(signature.as_bytes()),
                 authorization,
             }),
+            payment_id: selected.payment_id,
         };
         Ok(payment_payload)
     }
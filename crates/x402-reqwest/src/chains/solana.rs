@@ -82,6 +82,9 @@ impl SenderWallet for SolanaSenderWallet {
         match network_family {
             NetworkFamily::Evm => false,
             NetworkFamily::Solana => true,
+            NetworkFamily::Lightning => false,
+            NetworkFamily::Near => false,
+            NetworkFamily::Tron => false,
         }
     }
 
@@ -250,6 +253,7 @@ impl SenderWallet for SolanaSenderWallet {
             payload: ExactPaymentPayload::Solana(ExactSolanaPayload {
                 transaction: tx_b64,
             }),
+            payment_id: selected.payment_id,
         };
         Ok(payment_payload)
     }
@@ -204,8 +204,8 @@ impl X402Payments {
 
         // Try to find a USDC requirement
         let usdc_requirement = sorted.iter().find(|req| {
-            let usdc = USDCDeployment::by_network(req.network);
-            req.asset == usdc.address()
+            USDCDeployment::try_by_network(req.network)
+                .is_some_and(|usdc| req.asset == usdc.address())
         });
 
         let selected = usdc_requirement
@@ -0,0 +1,516 @@
+//! Built-in HTTPS with certificates from an ACME certificate authority, such as Let's Encrypt, with the `acme`
+//! feature only.
+//!
+//! Small operators can serve the facilitator over HTTPS without a reverse proxy: with `ACME_DOMAIN` set, the
+//! public listener terminates TLS itself. A certificate for the domain is ordered from the CA on startup, proving
+//! control of the domain with HTTP-01 challenges answered on a plain HTTP port, and renewed in the background
+//! [`RENEW_BEFORE`] its `notAfter`. The ACME protocol is spoken by [`instant_acme`], and certificate signing
+//! requests are made with [`rcgen`]. The account credentials, certificate and its key are cached on disk, readable
+//! by the owner only, so restarts reuse them instead of hitting the CA's rate limits. A cached certificate is only
+//! served if its names are exactly the domains of `ACME_DOMAIN`; otherwise a new one is ordered. Until the first
+//! certificate is issued, TLS handshakes fail.
+//!
+//! Environment variables used:
+//! - `ACME_DOMAIN` — comma-separated domains of the certificate; HTTPS is not served if unset,
+//! - `ACME_EMAIL` — contact address of the ACME account, optional,
+//! - `ACME_DIRECTORY_URL` — directory of the CA (default: Let's Encrypt production), subject to the outbound
+//!   policy,
+//! - `ACME_CACHE_DIR` — directory of the cached credentials and certificate (default: `acme`),
+//! - `ACME_HTTP_PORT` — port HTTP-01 challenges are answered on, which the CA reaches as port 80 (default: `80`).
+
+use axum::Router;
+use axum::extract::{Path as UrlPath, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use dashmap::DashMap;
+use instant_acme::{
+    Account, AccountCredentials, AuthorizationStatus, ChallengeType, Identifier, NewAccount,
+    NewOrder, OrderStatus,
+};
+use std::collections::BTreeSet;
+use std::io::Write;
+use std::net::{IpAddr, SocketAddr};
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::server::{ClientHello, ResolvesServerCert};
+use tokio_rustls::rustls::sign::CertifiedKey;
+use tokio_rustls::rustls::{ServerConfig, crypto};
+use tokio_rustls::server::TlsStream;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+use x509_parser::extensions::GeneralName;
+
+use crate::from_env;
+use crate::outbound::OutboundPolicy;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Let's Encrypt production directory.
+const DEFAULT_DIRECTORY_URL: &str = "https://acme-v02.api.letsencrypt.org/directory";
+const DEFAULT_CACHE_DIR: &str = "acme";
+const DEFAULT_HTTP_PORT: u16 = 80;
+/// Time before its `notAfter` a certificate is renewed; Let's Encrypt certificates are valid for 90 days.
+pub const RENEW_BEFORE: Duration = Duration::from_secs(30 * 24 * 3600);
+/// Delay between checks of the certificate validity, and between retries of a failed order.
+const CHECK_INTERVAL: Duration = Duration::from_secs(3600);
+/// Delay between polls of a pending order.
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+/// Polls of a pending order before giving up.
+const POLL_ATTEMPTS: usize = 60;
+/// Longest a client may take to complete a TLS handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Certificate currently served, swapped on renewal.
+#[derive(Debug, Default)]
+struct CertStore {
+    current: RwLock<Option<Arc<CertifiedKey>>>,
+}
+
+impl CertStore {
+    fn set(&self, certified_key: CertifiedKey) {
+        *self.current.write().expect("certificate lock poisoned") = Some(Arc::new(certified_key));
+    }
+}
+
+impl ResolvesServerCert for CertStore {
+    fn resolve(&self, _client_hello: ClientHello<'_>) -> Option<Arc<CertifiedKey>> {
+        self.current
+            .read()
+            .expect("certificate lock poisoned")
+            .clone()
+    }
+}
+
+/// Pending HTTP-01 challenges: key authorizations by token.
+type Challenges = Arc<DashMap<String, String>>;
+
+/// HTTPS listener of the public endpoints, with certificates from an ACME CA.
+#[derive(Debug, Clone)]
+pub struct AcmeServer {
+    domains: Vec<String>,
+    contact: Option<String>,
+    directory_url: Url,
+    cache_dir: PathBuf,
+    challenge_addr: SocketAddr,
+}
+
+impl AcmeServer {
+    /// Reads the configuration; `None` if `ACME_DOMAIN` is unset.
+    pub fn from_env(host: IpAddr) -> Result<Option<Self>, String> {
+        let Ok(domains) = std::env::var(from_env::ENV_ACME_DOMAIN) else {
+            return Ok(None);
+        };
+        let domains: Vec<String> = domains
+            .split(',')
+            .map(|domain| domain.trim().to_ascii_lowercase())
+            .filter(|domain| !domain.is_empty())
+            .collect();
+        if domains.is_empty() {
+            return Err(format!("{}: no domain", from_env::ENV_ACME_DOMAIN));
+        }
+        let directory_url = std::env::var(from_env::ENV_ACME_DIRECTORY_URL)
+            .unwrap_or_else(|_| DEFAULT_DIRECTORY_URL.to_string());
+        let directory_url = Url::parse(&directory_url)
+            .map_err(|e| format!("{}: {e}", from_env::ENV_ACME_DIRECTORY_URL))?;
+        OutboundPolicy::from_env()
+            .map_err(|e| e.to_string())?
+            .validate_url(&directory_url)
+            .map_err(|e| format!("{}: {e}", from_env::ENV_ACME_DIRECTORY_URL))?;
+        let http_port = match std::env::var(from_env::ENV_ACME_HTTP_PORT) {
+            Ok(port) => port
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_ACME_HTTP_PORT))?,
+            Err(_) => DEFAULT_HTTP_PORT,
+        };
+        Ok(Some(Self {
+            domains,
+            contact: std::env::var(from_env::ENV_ACME_EMAIL).ok(),
+            directory_url,
+            cache_dir: std::env::var(from_env::ENV_ACME_CACHE_DIR)
+                .unwrap_or_else(|_| DEFAULT_CACHE_DIR.to_string())
+                .into(),
+            challenge_addr: SocketAddr::new(host, http_port),
+        }))
+    }
+
+    /// Binds the HTTPS listener on `addr` and the challenge listener, then keeps the certificate issued and
+    /// renewed until `cancellation_token` is cancelled.
+    pub async fn listen(
+        self,
+        addr: SocketAddr,
+        cancellation_token: CancellationToken,
+    ) -> Result<TlsListener, BoxError> {
+        std::fs::create_dir_all(&self.cache_dir)?;
+        let store = Arc::new(CertStore::default());
+        match self.load_cached() {
+            Ok(Some(certified_key)) => store.set(certified_key),
+            Ok(None) => {}
+            Err(e) => tracing::warn!(error = %e, "Ignoring unreadable cached certificate"),
+        }
+        let challenges: Challenges = Arc::new(DashMap::new());
+        let challenge_listener = TcpListener::bind(self.challenge_addr).await?;
+        let challenge_router = Router::new()
+            .route("/.well-known/acme-challenge/{token}", get(get_challenge))
+            .with_state(challenges.clone());
+        let token = cancellation_token.clone();
+        tokio::spawn(async move {
+            if let Err(e) = axum::serve(challenge_listener, challenge_router)
+                .with_graceful_shutdown(async move { token.cancelled().await })
+                .await
+            {
+                tracing::error!(error = %e, "ACME challenge listener failed");
+            }
+        });
+        tracing::info!(addr = %self.challenge_addr, "Answering ACME challenges");
+
+        let renewal_store = store.clone();
+        let token = cancellation_token.clone();
+        tokio::spawn(async move {
+            loop {
+                if self.needs_renewal() {
+                    match self.order(&challenges).await {
+                        Ok(certified_key) => {
+                            renewal_store.set(certified_key);
+                            tracing::info!(domains = ?self.domains, "Certificate issued");
+                        }
+                        Err(e) => {
+                            tracing::error!(domains = ?self.domains, error = %e, "Certificate order failed");
+                        }
+                    }
+                }
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tokio::time::sleep(CHECK_INTERVAL) => {}
+                }
+            }
+        });
+
+        let mut config =
+            ServerConfig::builder_with_provider(Arc::new(crypto::ring::default_provider()))
+                .with_safe_default_protocol_versions()?
+                .with_no_client_auth()
+                .with_cert_resolver(store);
+        config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+        TlsListener::bind(
+            addr,
+            TlsAcceptor::from(Arc::new(config)),
+            cancellation_token,
+        )
+        .await
+        .map_err(Into::into)
+    }
+
+    fn account_path(&self) -> PathBuf {
+        self.cache_dir.join("account.json")
+    }
+
+    fn cert_path(&self) -> PathBuf {
+        self.cache_dir.join("cert.pem")
+    }
+
+    fn key_path(&self) -> PathBuf {
+        self.cache_dir.join("key.pk8")
+    }
+
+    /// Whether the cached certificate is missing, for other domains, or within [`RENEW_BEFORE`] of its expiry.
+    fn needs_renewal(&self) -> bool {
+        let not_after = std::fs::read(self.cert_path())
+            .map_err(BoxError::from)
+            .and_then(|chain| certificate_expiry(&chain, &self.domains));
+        // Unreadable and foreign certificates are renewed right away.
+        !not_after.is_ok_and(|not_after| {
+            not_after
+                .duration_since(SystemTime::now())
+                .is_ok_and(|left| left > RENEW_BEFORE)
+        })
+    }
+
+    /// Cached certificate, if it is for the domains and not expired.
+    fn load_cached(&self) -> Result<Option<CertifiedKey>, BoxError> {
+        let (Ok(chain), Ok(key)) = (
+            std::fs::read(self.cert_path()),
+            std::fs::read(self.key_path()),
+        ) else {
+            return Ok(None);
+        };
+        if certificate_expiry(&chain, &self.domains)? <= SystemTime::now() {
+            return Ok(None);
+        }
+        certified_key(&chain, key).map(Some)
+    }
+
+    /// ACME account of the cached credentials, or a new one.
+    async fn account(&self) -> Result<Account, BoxError> {
+        if let Ok(credentials) = std::fs::read(self.account_path()) {
+            let credentials: AccountCredentials = serde_json::from_slice(&credentials)?;
+            return Ok(Account::from_credentials(credentials).await?);
+        }
+        let contact = self
+            .contact
+            .iter()
+            .map(|contact| format!("mailto:{contact}"))
+            .collect::<Vec<_>>();
+        let contact = contact.iter().map(String::as_str).collect::<Vec<_>>();
+        let (account, credentials) = Account::create(
+            &NewAccount {
+                contact: &contact,
+                terms_of_service_agreed: true,
+                only_return_existing: false,
+            },
+            self.directory_url.as_str(),
+            None,
+        )
+        .await?;
+        write_private(&self.account_path(), &serde_json::to_vec(&credentials)?)?;
+        Ok(account)
+    }
+
+    /// Orders a certificate for the domains, answering their HTTP-01 challenges, and caches it.
+    async fn order(&self, challenges: &Challenges) -> Result<CertifiedKey, BoxError> {
+        let account = self.account().await?;
+        let identifiers: Vec<_> = self
+            .domains
+            .iter()
+            .map(|domain| Identifier::Dns(domain.clone()))
+            .collect();
+        let mut order = account
+            .new_order(&NewOrder {
+                identifiers: &identifiers,
+            })
+            .await?;
+        let mut tokens = Vec::new();
+        for authorization in order.authorizations().await? {
+            match authorization.status {
+                AuthorizationStatus::Pending => {}
+                AuthorizationStatus::Valid => continue,
+                status => return Err(format!("authorization is {status:?}").into()),
+            }
+            let challenge = authorization
+                .challenges
+                .iter()
+                .find(|challenge| challenge.r#type == ChallengeType::Http01)
+                .ok_or("no HTTP-01 challenge offered")?;
+            let key_authorization = order.key_authorization(challenge);
+            challenges.insert(
+                challenge.token.clone(),
+                key_authorization.as_str().to_string(),
+            );
+            tokens.push(challenge.token.clone());
+            order.set_challenge_ready(&challenge.url).await?;
+        }
+        let issued = self.finalize(&mut order).await;
+        for token in tokens {
+            challenges.remove(&token);
+        }
+        let (chain, key) = issued?;
+        let certified_key = certified_key(chain.as_bytes(), key.clone())?;
+        write_private(&self.key_path(), &key)?;
+        write_private(&self.cert_path(), chain.as_bytes())?;
+        Ok(certified_key)
+    }
+
+    /// Waits for the challenges to be validated, then finalizes `order` with a new key: the certificate chain in
+    /// PEM, and its PKCS#8 key.
+    async fn finalize(
+        &self,
+        order: &mut instant_acme::Order,
+    ) -> Result<(String, Vec<u8>), BoxError> {
+        let mut ready = false;
+        for _ in 0..POLL_ATTEMPTS {
+            match order.refresh().await?.status {
+                OrderStatus::Ready => {
+                    ready = true;
+                    break;
+                }
+                OrderStatus::Invalid => return Err("order is invalid".into()),
+                _ => tokio::time::sleep(POLL_INTERVAL).await,
+            }
+        }
+        if !ready {
+            return Err("challenges were not validated in time".into());
+        }
+        let key = rcgen::KeyPair::generate()?;
+        let mut params = rcgen::CertificateParams::new(self.domains.clone())?;
+        params.distinguished_name = rcgen::DistinguishedName::new();
+        let csr = params.serialize_request(&key)?;
+        order.finalize(csr.der()).await?;
+        for _ in 0..POLL_ATTEMPTS {
+            if let Some(chain) = order.certificate().await? {
+                return Ok((chain, key.serialize_der()));
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+        Err("order was not issued in time".into())
+    }
+}
+
+/// `GET /.well-known/acme-challenge/{token}`: Key authorization of a pending HTTP-01 challenge.
+async fn get_challenge(
+    State(challenges): State<Challenges>,
+    UrlPath(token): UrlPath<String>,
+) -> Response {
+    match challenges.get(&token) {
+        Some(key_authorization) => key_authorization.clone().into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+/// `notAfter` of the leaf certificate of the PEM `chain`; an error if its DNS names are not exactly `domains`.
+fn certificate_expiry(chain: &[u8], domains: &[String]) -> Result<SystemTime, BoxError> {
+    let leaf = CertificateDer::pem_slice_iter(chain)
+        .next()
+        .ok_or("empty certificate chain")??;
+    let (_, certificate) = x509_parser::parse_x509_certificate(&leaf)?;
+    let names: BTreeSet<String> = certificate
+        .subject_alternative_name()?
+        .map(|extension| {
+            extension
+                .value
+                .general_names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DNSName(name) => Some(name.to_ascii_lowercase()),
+                    _ => None,
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    if names != domains.iter().cloned().collect() {
+        return Err(format!("certificate is for {names:?}").into());
+    }
+    let not_after = certificate.validity().not_after.timestamp();
+    Ok(SystemTime::UNIX_EPOCH + Duration::from_secs(not_after.max(0) as u64))
+}
+
+/// Writes `contents` to `path`, readable by the owner only.
+fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    // Write next to the target and rename, so a crash never leaves a truncated key file.
+    let tmp_path = path.with_extension("tmp");
+    let mut tmp = std::fs::OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .mode(0o600)
+        .open(&tmp_path)?;
+    tmp.write_all(contents)?;
+    tmp.sync_all()?;
+    std::fs::rename(&tmp_path, path)
+}
+
+fn certified_key(chain: &[u8], key: Vec<u8>) -> Result<CertifiedKey, BoxError> {
+    let chain = CertificateDer::pem_slice_iter(chain).collect::<Result<Vec<_>, _>>()?;
+    if chain.is_empty() {
+        return Err("empty certificate chain".into());
+    }
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key));
+    let signing_key = crypto::ring::sign::any_ecdsa_type(&key)?;
+    Ok(CertifiedKey::new(chain, signing_key))
+}
+
+/// TLS listener handing completed handshakes to `axum::serve`.
+///
+/// Handshakes run in their own tasks, with a timeout, so a slow client does not hold up the others.
+pub struct TlsListener {
+    incoming: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub async fn bind(
+        addr: SocketAddr,
+        acceptor: TlsAcceptor,
+        cancellation_token: CancellationToken,
+    ) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let (sender, incoming) = mpsc::channel(64);
+        tokio::spawn(async move {
+            loop {
+                let accepted = tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    accepted = listener.accept() => accepted,
+                };
+                let (stream, peer) = match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        tracing::warn!(error = %e, "Failed to accept connection");
+                        tokio::time::sleep(Duration::from_millis(100)).await;
+                        continue;
+                    }
+                };
+                let acceptor = acceptor.clone();
+                let sender = sender.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = sender.send((stream, peer)).await;
+                        }
+                        Ok(Err(e)) => {
+                            tracing::debug!(peer = %peer, error = %e, "TLS handshake failed")
+                        }
+                        Err(_) => tracing::debug!(peer = %peer, "TLS handshake timed out"),
+                    }
+                });
+            }
+        });
+        Ok(Self {
+            incoming,
+            local_addr,
+        })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.incoming.recv().await {
+            Some(accepted) => accepted,
+            // Stopped accepting: wait for the graceful shutdown.
+            None => std::future::pending().await,
+        }
+    }
+
+    fn local_addr(&self) -> std::io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn self_signed(domains: &[&str], (year, month, day): (i32, u8, u8)) -> Vec<u8> {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let mut params = rcgen::CertificateParams::new(
+            domains.iter().map(|d| d.to_string()).collect::<Vec<_>>(),
+        )
+        .unwrap();
+        params.not_after = rcgen::date_time_ymd(year, month, day);
+        params.self_signed(&key).unwrap().pem().into_bytes()
+    }
+
+    #[test]
+    fn certificates_expire_at_not_after_for_their_domains_only() {
+        let domains = vec!["pay.example.com".to_string()];
+        let chain = self_signed(&["pay.example.com"], (2040, 1, 1));
+        let not_after = certificate_expiry(&chain, &domains).unwrap();
+        assert_eq!(
+            not_after,
+            SystemTime::UNIX_EPOCH + Duration::from_secs(2_208_988_800)
+        );
+        let other = vec!["pay.example.org".to_string()];
+        assert!(certificate_expiry(&chain, &other).is_err());
+        let more = vec!["pay.example.com".to_string(), "api.example.com".to_string()];
+        assert!(certificate_expiry(&chain, &more).is_err());
+    }
+}
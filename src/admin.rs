@@ -0,0 +1,465 @@
+//! Operator-facing admin API with role-based access control.
+//!
+//! All admin routes live under `/admin` and require an API key passed as `Authorization: Bearer <key>`.
+//! Every key is bound to a [`Role`]; a route registered for a role accepts that role and any higher one:
+//! - [`Role::Viewer`] — read-only inspection (queues, records, configuration),
+//! - [`Role::Operator`] — day-to-day operations (re-driving and discarding dead letters, injecting faults),
+//! - [`Role::Admin`] — security-sensitive changes (signers, networks, budgets, identity key, payload store).
+//!
+//! Keys are kept hashed, and compared with the presented one in constant time.
+//!
+//! Environment variables used:
+//! - `ADMIN_API_KEYS` — comma-separated `key:role` pairs, e.g. `k1:viewer,k2:admin`.
+//!   The admin API rejects every request if unset.
+
+use alloy::primitives::{B256, keccak256};
+use axum::extract::{Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::approval::{ApprovalRecord, Approvals, constant_time_eq};
+use crate::chain::block_tracker::BlockTag;
+use crate::chain::failover::EndpointHealth;
+use crate::chain::signers::SignerGenerations;
+use crate::chain::{NetworkProvider, NetworkProviderOps};
+use crate::config::NetworkConfig;
+use crate::from_env;
+use crate::identity::FacilitatorIdentity;
+use crate::network::{CustomNetwork, Network};
+use crate::provider_cache::{ProviderCache, ProviderMap};
+use crate::types::{ErrorResponse, MixedAddress, Page, PageRequest};
+
+/// Access level of an admin API key. Ordered from least to most privileged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Viewer,
+    Operator,
+    Admin,
+}
+
+impl Display for Role {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Role::Viewer => write!(f, "viewer"),
+            Role::Operator => write!(f, "operator"),
+            Role::Admin => write!(f, "admin"),
+        }
+    }
+}
+
+impl FromStr for Role {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "viewer" => Ok(Role::Viewer),
+            "operator" => Ok(Role::Operator),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("Unknown admin role: {other}")),
+        }
+    }
+}
+
+/// Admin API keys, hashed, and the roles they are bound to.
+#[derive(Debug, Clone, Default)]
+pub struct AdminAuth {
+    keys: Arc<Vec<(B256, Role)>>,
+}
+
+impl AdminAuth {
+    pub fn new(keys: HashMap<String, Role>) -> Self {
+        let keys = keys
+            .into_iter()
+            .map(|(key, role)| (keccak256(key), role))
+            .collect();
+        Self {
+            keys: Arc::new(keys),
+        }
+    }
+
+    /// Parses `ADMIN_API_KEYS`; no keys (admin API disabled) if unset.
+    pub fn from_env() -> Result<Self, String> {
+        let Ok(value) = std::env::var(from_env::ENV_ADMIN_API_KEYS) else {
+            return Ok(Self::default());
+        };
+        let mut keys = HashMap::new();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (key, role) = entry.rsplit_once(':').ok_or_else(|| {
+                format!("{} entries must be key:role", from_env::ENV_ADMIN_API_KEYS)
+            })?;
+            keys.insert(key.trim().to_string(), role.parse()?);
+        }
+        Ok(Self::new(keys))
+    }
+
+    /// Role of the key presented in the `Authorization` header, if any.
+    pub fn role_of(&self, request: &Request) -> Option<Role> {
+        let value = request
+            .headers()
+            .get(header::AUTHORIZATION)?
+            .to_str()
+            .ok()?;
+        let presented = keccak256(value.strip_prefix("Bearer ")?);
+        // Every key is compared, so that the time taken does not tell which one matched.
+        self.keys.iter().fold(None, |found, (key, role)| {
+            if constant_time_eq(*key, presented) {
+                Some(*role)
+            } else {
+                found
+            }
+        })
+    }
+}
+
+/// Role of the caller, available to admin handlers as an [`Extension`].
+#[derive(Debug, Clone, Copy)]
+pub struct CallerRole(pub Role);
+
+async fn authorize(
+    State((auth, required)): State<(AdminAuth, Role)>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    match auth.role_of(&request) {
+        Some(role) if role >= required => {
+            request.extensions_mut().insert(CallerRole(role));
+            next.run(request).await
+        }
+        Some(role) => {
+            tracing::warn!(role = %role, required = %required, uri = %request.uri(), "Admin access denied");
+            error_response(StatusCode::FORBIDDEN, "Forbidden")
+        }
+        None => error_response(StatusCode::UNAUTHORIZED, "Unauthorized"),
+    }
+}
+
+pub(crate) fn error_response(status: StatusCode, error: &str) -> Response {
+    (
+        status,
+        Json(ErrorResponse {
+            error: error.to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Builder of the `/admin` router, gating each group of routes behind a minimum [`Role`].
+pub struct AdminRouter {
+    auth: AdminAuth,
+    router: Router,
+}
+
+impl AdminRouter {
+    pub fn new(auth: AdminAuth) -> Self {
+        let router = Router::new();
+        let admin_router = Self { auth, router };
+        admin_router.with_routes(
+            Role::Viewer,
+            Router::new().route("/whoami", get(get_whoami)),
+            (),
+        )
+    }
+
+    /// Adds `routes`, served with `state`, accessible to keys with at least `role`.
+    pub fn with_routes<S>(mut self, role: Role, routes: Router<S>, state: S) -> Self
+    where
+        S: Clone + Send + Sync + 'static,
+    {
+        let routes = routes
+            .with_state(state)
+            .route_layer(middleware::from_fn_with_state(
+                (self.auth.clone(), role),
+                authorize,
+            ));
+        self.router = self.router.merge(routes);
+        self
+    }
+
+    /// The finished router, with all routes nested under `/admin`.
+    pub fn into_router(self) -> Router {
+        Router::new().nest("/admin", self.router)
+    }
+}
+
+/// `GET /admin/whoami`: Role of the presented API key.
+#[instrument(skip_all)]
+pub async fn get_whoami(Extension(CallerRole(role)): Extension<CallerRole>) -> impl IntoResponse {
+    Json(serde_json::json!({ "role": role }))
+}
+
+/// Viewer routes inspecting the human-approval queue.
+pub fn approval_routes<F>() -> Router<Approvals<F>>
+where
+    F: Send + Sync + 'static,
+{
+    Router::new().route("/approvals", get(get_approvals::<F>))
+}
+
+/// `GET /admin/approvals`: Settlements routed through human approval, oldest first.
+#[instrument(skip_all)]
+pub async fn get_approvals<F>(
+    State(approvals): State<Approvals<F>>,
+    Query(page_request): Query<PageRequest>,
+) -> Response {
+    let mut records = approvals.list();
+    records.sort_by(|a, b| (a.created_at, &a.id).cmp(&(b.created_at, &b.id)));
+    let page: Result<Page<ApprovalRecord>, _> =
+        page_request.paginate(records, |record| record.id.clone());
+    match page {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    }
+}
+
+/// Settlement signers of a single network, as served by the signer admin routes.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkSigners {
+    pub network: Network,
+    #[serde(flatten)]
+    pub signers: SignerGenerations,
+}
+
+/// RPC endpoints of a single network, in order of preference, as served by `GET /admin/rpc`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkRpcEndpoints {
+    pub network: Network,
+    pub endpoints: Vec<EndpointHealth>,
+}
+
+/// Request body of `POST /admin/signers/rotate`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RotateSignersRequest {
+    pub network: Network,
+}
+
+/// Viewer routes inspecting settlement signers.
+pub fn signer_routes<P>() -> Router<P>
+where
+    P: ProviderMap<Value = NetworkProvider> + Clone + Send + Sync + 'static,
+{
+    Router::new().route("/signers", get(get_signers::<P>))
+}
+
+/// Viewer routes inspecting RPC endpoints.
+pub fn rpc_routes<P>() -> Router<P>
+where
+    P: ProviderMap<Value = NetworkProvider> + Clone + Send + Sync + 'static,
+{
+    Router::new().route("/rpc", get(get_rpc_endpoints::<P>))
+}
+
+/// Admin routes rotating settlement signers.
+pub fn signer_rotation_routes<P>() -> Router<P>
+where
+    P: ProviderMap<Value = NetworkProvider> + Clone + Send + Sync + 'static,
+{
+    Router::new().route("/signers/rotate", post(post_rotate_signers::<P>))
+}
+
+/// `GET /admin/signers`: Active, next and draining signers of every network.
+#[instrument(skip_all)]
+pub async fn get_signers<P>(State(providers): State<P>) -> impl IntoResponse
+where
+    P: ProviderMap<Value = NetworkProvider>,
+{
+    let mut signers: Vec<NetworkSigners> = providers
+        .values()
+        .map(|provider| NetworkSigners {
+            network: provider.network(),
+            signers: provider.signer_generations(),
+        })
+        .collect();
+    signers.sort_by_key(|s| s.network.to_string());
+    Json(signers)
+}
+
+/// `GET /admin/rpc`: State of the RPC endpoints of every EVM network failing over between several.
+#[instrument(skip_all)]
+pub async fn get_rpc_endpoints<P>(State(providers): State<P>) -> impl IntoResponse
+where
+    P: ProviderMap<Value = NetworkProvider>,
+{
+    let mut endpoints: Vec<NetworkRpcEndpoints> = providers
+        .values()
+        .filter_map(|provider| match provider {
+            NetworkProvider::Evm(provider) => {
+                let failover = provider.rpc_failover()?;
+                Some(NetworkRpcEndpoints {
+                    network: provider.network(),
+                    endpoints: failover.health(),
+                })
+            }
+            _ => None,
+        })
+        .collect();
+    endpoints.sort_by_key(|e| e.network.to_string());
+    Json(endpoints)
+}
+
+/// `POST /admin/signers/rotate`: Promotes the next signers of a network to active.
+///
+/// Previously active signers keep draining their in-flight transactions and are retired afterwards.
+#[instrument(skip_all, fields(network = %request.network))]
+pub async fn post_rotate_signers<P>(
+    State(providers): State<P>,
+    Extension(CallerRole(role)): Extension<CallerRole>,
+    Json(request): Json<RotateSignersRequest>,
+) -> Response
+where
+    P: ProviderMap<Value = NetworkProvider>,
+{
+    let Some(provider) = providers.by_network(request.network) else {
+        return error_response(StatusCode::NOT_FOUND, "Network not configured");
+    };
+    match provider.rotate_signers() {
+        Ok(signers) => {
+            tracing::info!(role = %role, "Settlement signers rotated");
+            let signers = NetworkSigners {
+                network: request.network,
+                signers,
+            };
+            (StatusCode::OK, Json(signers)).into_response()
+        }
+        Err(e) => error_response(StatusCode::CONFLICT, &e.to_string()),
+    }
+}
+
+/// Admin routes rotating the facilitator identity key.
+pub fn identity_routes() -> Router<FacilitatorIdentity> {
+    Router::new().route("/identity/rotate", post(post_rotate_identity))
+}
+
+/// `POST /admin/identity/rotate`: Replaces the identity key, keeping the old address published.
+#[instrument(skip_all)]
+pub async fn post_rotate_identity(
+    State(identity): State<FacilitatorIdentity>,
+    Extension(CallerRole(role)): Extension<CallerRole>,
+) -> Response {
+    match identity.rotate() {
+        Ok(keys) => {
+            tracing::info!(role = %role, "Identity key rotated");
+            (StatusCode::OK, Json(keys)).into_response()
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Identity key rotation failed");
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &e.to_string())
+        }
+    }
+}
+
+/// Request body of `POST /admin/networks`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RegisterNetworkRequest {
+    #[serde(flatten)]
+    pub network: CustomNetwork,
+    pub rpc_url: String,
+    /// Assets accepted in payment requirements; any asset if unset.
+    #[serde(default)]
+    pub tokens: Option<Vec<MixedAddress>>,
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+    #[serde(default)]
+    pub block_tag: Option<BlockTag>,
+}
+
+/// Admin routes registering custom EVM networks.
+pub fn network_routes() -> Router<Arc<ProviderCache>> {
+    Router::new().route("/networks", post(post_network))
+}
+
+/// `POST /admin/networks`: Registers a custom EVM network and starts serving it.
+///
+/// Settlements on it are signed with the keys from the environment.
+#[instrument(skip_all, fields(network = %request.network.name, chain_id = request.network.chain_id))]
+pub async fn post_network(
+    State(providers): State<Arc<ProviderCache>>,
+    Extension(CallerRole(role)): Extension<CallerRole>,
+    Json(request): Json<RegisterNetworkRequest>,
+) -> Response {
+    let config = NetworkConfig {
+        rpc_url: request.rpc_url,
+        chain_id: Some(request.network.chain_id),
+        tokens: request.tokens,
+        confirmations: request.confirmations,
+        block_tag: request.block_tag,
+        ..Default::default()
+    };
+    let custom = request.network;
+    match providers.register(custom.clone(), &config).await {
+        Ok(network) => {
+            tracing::info!(role = %role, "Custom network registered through admin API");
+            let fee_payer = providers
+                .by_network(network)
+                .map(|provider| provider.signer_address());
+            (
+                StatusCode::CREATED,
+                Json(serde_json::json!({ "network": custom, "feePayer": fee_payer })),
+            )
+                .into_response()
+        }
+        Err(e) => error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use tower::ServiceExt;
+
+    async fn status(router: &Router, method: &str, path: &str, key: Option<&str>) -> StatusCode {
+        let mut request = axum::http::Request::builder().method(method).uri(path);
+        if let Some(key) = key {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {key}"));
+        }
+        let request = request.body(Body::empty()).unwrap();
+        router.clone().oneshot(request).await.unwrap().status()
+    }
+
+    #[test]
+    fn roles_are_ordered_by_privilege() {
+        assert!(Role::Viewer < Role::Operator);
+        assert!(Role::Operator < Role::Admin);
+        assert_eq!(" Operator".parse::<Role>(), Ok(Role::Operator));
+        assert!("root".parse::<Role>().is_err());
+    }
+
+    #[tokio::test]
+    async fn routes_require_a_key_of_their_role() {
+        let auth = AdminAuth::new(HashMap::from([
+            ("viewer-key".to_string(), Role::Viewer),
+            ("operator-key".to_string(), Role::Operator),
+            ("admin-key".to_string(), Role::Admin),
+        ]));
+        let router = AdminRouter::new(auth)
+            .with_routes(
+                Role::Operator,
+                Router::new().route("/redrive", post(|| async { StatusCode::NO_CONTENT })),
+                (),
+            )
+            .into_router();
+        let redrive = |key| status(&router, "POST", "/admin/redrive", key);
+        assert_eq!(redrive(None).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(redrive(Some("other-key")).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(redrive(Some("viewer-key")).await, StatusCode::FORBIDDEN);
+        assert_eq!(redrive(Some("operator-key")).await, StatusCode::NO_CONTENT);
+        assert_eq!(redrive(Some("admin-key")).await, StatusCode::NO_CONTENT);
+        assert_eq!(
+            status(&router, "GET", "/admin/whoami", Some("viewer-key")).await,
+            StatusCode::OK
+        );
+    }
+}
@@ -0,0 +1,995 @@
+//! Human approval of high-value settlements.
+//!
+//! [`ApprovalGate`] wraps a [`Facilitator`] and, for payments at or above a configured threshold,
+//! does not sign the settlement itself. Instead it verifies the payment, proposes it to an external
+//! transaction service (a WalletConnect bridge, a Safe transaction service adapter, or any HTTP
+//! endpoint accepting [`ApprovalProposal`]), and records it as pending approval. `/settle` answers
+//! with `success: false` and the `pending_approval` error reason.
+//!
+//! The transaction service reports the decision back via `POST /approvals/{id}`:
+//! - approved with a transaction hash: the approver submitted the transfer themselves. The transaction must be mined,
+//!   on an EVM network, and transfer at least the amount of the payment from the payer to `payTo`;
+//! - approved without a transaction hash: the facilitator settles the payment now;
+//! - rejected: the payment is not settled.
+//!
+//! The state of a proposal is available at `GET /approvals/{id}`.
+//!
+//! Environment variables used:
+//! - `APPROVAL_THRESHOLD` — minimum amount, in whole tokens (e.g. `1000` or `0.5`), that requires approval. It is
+//!   scaled by the decimals of the asset paid; payments in an asset of unknown decimals always require approval,
+//! - `APPROVAL_SERVICE_URL` — endpoint receiving proposals as JSON `POST` requests,
+//! - `APPROVAL_CALLBACK_TOKEN` — bearer token the service must present on `POST /approvals/{id}`; required,
+//! - `APPROVAL_STORE_DIR` — directory keeping approval records across restarts; in memory only if unset.
+//!
+//! Approval is disabled unless both `APPROVAL_THRESHOLD` and `APPROVAL_SERVICE_URL` are set.
+
+use alloy::primitives::utils::parse_units;
+use alloy::primitives::{B256, U256, keccak256};
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::instrument;
+use url::Url;
+
+use crate::chain::evm::USDC;
+use crate::chain::{FacilitatorLocalError, NetworkProvider};
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::{Network, USDCDeployment};
+use crate::outbound::OutboundPolicy;
+use crate::provider_cache::{ProviderCache, ProviderMap};
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ErrorResponse, FacilitatorErrorReason, MixedAddress, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, TokenAmount, TransactionHash, VerifyRequest, VerifyResponse,
+};
+
+/// Lifecycle of a settlement proposed for human approval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ApprovalStatus {
+    PendingApproval,
+    /// A decision is being applied, e.g. the facilitator is settling the approved payment.
+    Deciding,
+    Approved,
+    Rejected,
+    /// Approved, but the facilitator failed to settle the payment afterwards.
+    Failed,
+}
+
+/// Settlement record of a payment routed through human approval.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalRecord {
+    pub id: String,
+    pub status: ApprovalStatus,
+    pub network: Network,
+    pub payer: Option<MixedAddress>,
+    pub amount: TokenAmount,
+    pub created_at: UnixTimestamp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionHash>,
+}
+
+/// Body sent to the transaction service for every payment requiring approval.
+///
+/// The signed x402 authorization in `settle_request` is enough for an approver to submit the transfer
+/// from any wallet, or to approve it and let the facilitator settle.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalProposal {
+    pub id: String,
+    pub network: Network,
+    pub payer: Option<MixedAddress>,
+    pub pay_to: MixedAddress,
+    pub asset: MixedAddress,
+    pub amount: TokenAmount,
+    pub settle_request: SettleRequest,
+}
+
+/// Decision reported by the transaction service on `POST /approvals/{id}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalDecision {
+    pub approved: bool,
+    /// Hash of the transaction submitted by the approver, if they settled the payment themselves.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionHash>,
+}
+
+/// Why a decision of the transaction service was not applied.
+#[derive(Debug, thiserror::Error)]
+pub enum DecisionError {
+    /// The record was decided already, or a decision is being applied.
+    #[error("Approval is not pending: {0:?}")]
+    NotPending(ApprovalStatus),
+    /// The transaction reported by the approver does not settle the payment.
+    #[error("Reported transaction does not settle the payment: {0}")]
+    UnsettledTransaction(String),
+    /// The facilitator failed to settle the approved payment.
+    #[error(transparent)]
+    Settlement(#[from] FacilitatorLocalError),
+}
+
+impl IntoResponse for DecisionError {
+    fn into_response(self) -> axum::response::Response {
+        match self {
+            DecisionError::NotPending(_) => (
+                StatusCode::CONFLICT,
+                Json(ErrorResponse {
+                    error: self.to_string(),
+                }),
+            )
+                .into_response(),
+            DecisionError::UnsettledTransaction(_) => (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                Json(ErrorResponse {
+                    error: self.to_string(),
+                }),
+            )
+                .into_response(),
+            DecisionError::Settlement(e) => e.into_response(),
+        }
+    }
+}
+
+/// Amount of tokens from which settlements require approval, independent of the decimals of the asset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ApprovalThreshold {
+    /// The amount as written, without its decimal point.
+    units: U256,
+    /// Digits after the decimal point.
+    scale: u8,
+}
+
+impl ApprovalThreshold {
+    /// The threshold in the base units of an asset with `decimals`, rounded up.
+    pub fn in_base_units(&self, decimals: u8) -> U256 {
+        let ten = U256::from(10);
+        if decimals >= self.scale {
+            self.units
+                .saturating_mul(ten.saturating_pow(U256::from(decimals - self.scale)))
+        } else {
+            self.units
+                .div_ceil(ten.saturating_pow(U256::from(self.scale - decimals)))
+        }
+    }
+
+    /// Whether `request` pays at least the threshold; always for an asset of unknown decimals.
+    fn is_reached_by(&self, request: &SettleRequest) -> bool {
+        let requirements = &request.payment_requirements;
+        let decimals = USDCDeployment::all_by_network(request.network())
+            .into_iter()
+            .find(|usdc| usdc.address() == requirements.asset)
+            .map(|usdc| usdc.decimals);
+        match decimals {
+            Some(decimals) => requirements.max_amount_required.0 >= self.in_base_units(decimals),
+            None => true,
+        }
+    }
+}
+
+impl FromStr for ApprovalThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let scale = s.split_once('.').map_or(0, |(_, fraction)| fraction.len());
+        let scale = u8::try_from(scale).map_err(|_| "too many decimals".to_string())?;
+        let units = parse_units(s, scale)
+            .map_err(|e| e.to_string())?
+            .get_absolute();
+        Ok(Self { units, scale })
+    }
+}
+
+/// When and where to propose settlements for approval.
+#[derive(Debug, Clone)]
+pub struct ApprovalPolicy {
+    pub threshold: ApprovalThreshold,
+    pub service_url: Url,
+    pub callback_token: String,
+    /// Restrictions on calls to `service_url`.
+    pub outbound: OutboundPolicy,
+}
+
+impl ApprovalPolicy {
+    /// Reads the policy from `APPROVAL_*` variables; `Ok(None)` if approval is not configured.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let threshold = std::env::var(from_env::ENV_APPROVAL_THRESHOLD).ok();
+        let service_url = std::env::var(from_env::ENV_APPROVAL_SERVICE_URL).ok();
+        let (Some(threshold), Some(service_url)) = (threshold, service_url) else {
+            return Ok(None);
+        };
+        let threshold = ApprovalThreshold::from_str(&threshold)
+            .map_err(|e| format!("{}: {e}", from_env::ENV_APPROVAL_THRESHOLD))?;
+        let service_url = Url::parse(&service_url)
+            .map_err(|e| format!("{}: {e}", from_env::ENV_APPROVAL_SERVICE_URL))?;
+        let outbound = OutboundPolicy::from_env()?;
+        outbound
+            .validate_url(&service_url)
+            .map_err(|e| format!("{}: {e}", from_env::ENV_APPROVAL_SERVICE_URL))?;
+        let callback_token = std::env::var(from_env::ENV_APPROVAL_CALLBACK_TOKEN)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "env {} not set, required with approval",
+                    from_env::ENV_APPROVAL_CALLBACK_TOKEN
+                )
+            })?;
+        Ok(Some(Self {
+            threshold,
+            service_url,
+            callback_token,
+            outbound,
+        }))
+    }
+}
+
+/// An approval record with the request it settles, as kept in an [`ApprovalStore`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApprovalEntry {
+    pub record: ApprovalRecord,
+    pub request: SettleRequest,
+}
+
+/// Persistence of approval records. Records are saved when proposed and once decided.
+pub trait ApprovalStore: Send + Sync {
+    fn save(&self, entry: &ApprovalEntry) -> std::io::Result<()>;
+    /// Entries saved before a restart.
+    fn load(&self) -> std::io::Result<Vec<ApprovalEntry>>;
+}
+
+/// Store keeping nothing: records live in memory only.
+#[derive(Debug, Default)]
+pub struct MemoryStore;
+
+impl ApprovalStore for MemoryStore {
+    fn save(&self, _entry: &ApprovalEntry) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn load(&self) -> std::io::Result<Vec<ApprovalEntry>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Store writing one JSON file per record into a directory.
+#[derive(Debug)]
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+}
+
+impl ApprovalStore for DirStore {
+    fn save(&self, entry: &ApprovalEntry) -> std::io::Result<()> {
+        let path = self.path(&entry.record.id);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    fn load(&self) -> std::io::Result<Vec<ApprovalEntry>> {
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match serde_json::from_slice(&std::fs::read(&path)?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping unreadable approval record")
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Shared store of approval records, also used as the state of [`routes`].
+pub struct Approvals<F> {
+    facilitator: Arc<F>,
+    /// Token of the decision callback; every decision is refused without one.
+    callback_token: Option<String>,
+    /// Providers reading back the transactions reported by approvers.
+    providers: Arc<ProviderCache>,
+    records: Arc<DashMap<String, ApprovalEntry>>,
+    store: Arc<dyn ApprovalStore>,
+}
+
+impl<F> Clone for Approvals<F> {
+    fn clone(&self) -> Self {
+        Self {
+            facilitator: self.facilitator.clone(),
+            callback_token: self.callback_token.clone(),
+            providers: self.providers.clone(),
+            records: self.records.clone(),
+            store: self.store.clone(),
+        }
+    }
+}
+
+impl<F> Approvals<F> {
+    pub fn get(&self, id: &str) -> Option<ApprovalRecord> {
+        self.records.get(id).map(|entry| entry.record.clone())
+    }
+
+    /// All approval records, in no particular order.
+    pub fn list(&self) -> Vec<ApprovalRecord> {
+        self.records
+            .iter()
+            .map(|entry| entry.record.clone())
+            .collect()
+    }
+
+    fn save(&self, entry: &ApprovalEntry) {
+        if let Err(e) = self.store.save(entry) {
+            tracing::warn!(id = entry.record.id, error = %e, "Failed to save approval record");
+        }
+    }
+
+    /// Whether `headers` carry the callback token, compared in constant time.
+    fn authorizes(&self, headers: &HeaderMap) -> bool {
+        let Some(token) = self.callback_token.as_deref() else {
+            return false;
+        };
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        constant_time_eq(keccak256(presented), keccak256(token))
+    }
+
+    /// Checks that `transaction`, reported by the approver, settles the payment of `record`: mined successfully,
+    /// with a transfer of at least its amount of its asset from its payer to `payTo`.
+    async fn check_transaction(
+        &self,
+        record: &ApprovalRecord,
+        request: &SettleRequest,
+        transaction: &TransactionHash,
+    ) -> Result<(), String> {
+        let requirements = &request.payment_requirements;
+        let (
+            TransactionHash::Evm(hash),
+            Some(MixedAddress::Evm(payer)),
+            MixedAddress::Evm(pay_to),
+            MixedAddress::Evm(asset),
+        ) = (
+            transaction,
+            &record.payer,
+            &requirements.pay_to,
+            &requirements.asset,
+        )
+        else {
+            return Err("only EVM transactions can be checked".to_string());
+        };
+        let Some(NetworkProvider::Evm(provider)) = self.providers.by_network(record.network) else {
+            return Err(format!("network {} not available", record.network));
+        };
+        let receipt = provider
+            .transaction_receipt(B256::from(*hash))
+            .await
+            .map_err(|e| e.to_string())?
+            .ok_or_else(|| "transaction not mined".to_string())?;
+        let (payer, pay_to, asset) = (payer.0, pay_to.0, asset.0);
+        let paid = receipt.status()
+            && receipt
+                .inner
+                .logs()
+                .iter()
+                .filter(|log| log.address() == asset)
+                .filter_map(|log| log.log_decode::<USDC::Transfer>().ok())
+                .any(|event| {
+                    event.inner.data.from == payer
+                        && event.inner.data.to == pay_to
+                        && event.inner.data.value >= record.amount.0
+                });
+        if paid {
+            Ok(())
+        } else {
+            Err("transaction does not transfer the payment to payTo".to_string())
+        }
+    }
+}
+
+impl<F> Approvals<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError>,
+{
+    /// Applies the decision of the transaction service to a pending record.
+    ///
+    /// The record leaves [`ApprovalStatus::PendingApproval`] under the lock of its entry, before anything is
+    /// awaited, so that concurrent decisions can not both settle the payment: all but the first are refused.
+    pub async fn decide(
+        &self,
+        id: &str,
+        decision: ApprovalDecision,
+    ) -> Option<Result<ApprovalRecord, DecisionError>> {
+        let (record, request) = {
+            let mut entry = self.records.get_mut(id)?;
+            if entry.record.status != ApprovalStatus::PendingApproval {
+                return Some(Err(DecisionError::NotPending(entry.record.status)));
+            }
+            entry.record.status = ApprovalStatus::Deciding;
+            (entry.record.clone(), entry.request.clone())
+        };
+        if decision.approved
+            && let Some(transaction) = &decision.transaction
+            && let Err(e) = self.check_transaction(&record, &request, transaction).await
+        {
+            // Left for another decision.
+            if let Some(mut entry) = self.records.get_mut(id) {
+                entry.record.status = ApprovalStatus::PendingApproval;
+            }
+            return Some(Err(DecisionError::UnsettledTransaction(e)));
+        }
+        let (status, transaction, result) = match decision {
+            ApprovalDecision {
+                approved: false, ..
+            } => (ApprovalStatus::Rejected, None, Ok(())),
+            ApprovalDecision {
+                approved: true,
+                transaction: Some(transaction),
+            } => (ApprovalStatus::Approved, Some(transaction), Ok(())),
+            ApprovalDecision {
+                approved: true,
+                transaction: None,
+            } => match self.facilitator.settle(&request).await {
+                Ok(response) if response.success => {
+                    (ApprovalStatus::Approved, response.transaction, Ok(()))
+                }
+                Ok(response) => (ApprovalStatus::Failed, response.transaction, Ok(())),
+                Err(e) => (ApprovalStatus::Failed, None, Err(e.into())),
+            },
+        };
+        let mut entry = self.records.get_mut(id)?;
+        entry.record.status = status;
+        entry.record.transaction = transaction;
+        self.save(&entry);
+        tracing::info!(id, status = ?status, "Settlement approval decided");
+        Some(result.map(|_| entry.record.clone()))
+    }
+}
+
+/// [`Facilitator`] decorator routing high-value settlements through human approval.
+///
+/// Passes every call through to the inner facilitator when no [`ApprovalPolicy`] is configured.
+pub struct ApprovalGate<F> {
+    policy: Option<ApprovalPolicy>,
+    http: reqwest::Client,
+    approvals: Approvals<F>,
+}
+
+impl<F> ApprovalGate<F> {
+    /// Creates the gate with the records saved in `store`.
+    ///
+    /// A record whose decision was being applied when the facilitator stopped is pending approval again: the
+    /// settlement it started either went through, and settling it again fails, or it did not.
+    pub fn new(
+        facilitator: F,
+        policy: Option<ApprovalPolicy>,
+        providers: Arc<ProviderCache>,
+        store: Box<dyn ApprovalStore>,
+    ) -> std::io::Result<Self> {
+        let callback_token = policy.as_ref().map(|p| p.callback_token.clone());
+        let http = policy
+            .as_ref()
+            .map(|p| p.outbound.http_client())
+            .unwrap_or_default();
+        let records = DashMap::new();
+        for mut entry in store.load()? {
+            if entry.record.status == ApprovalStatus::Deciding {
+                entry.record.status = ApprovalStatus::PendingApproval;
+            }
+            records.insert(entry.record.id.clone(), entry);
+        }
+        if !records.is_empty() {
+            tracing::info!(records = records.len(), "Loaded approval records");
+        }
+        Ok(Self {
+            policy,
+            http,
+            approvals: Approvals {
+                facilitator: Arc::new(facilitator),
+                callback_token,
+                providers,
+                records: Arc::new(records),
+                store: Arc::from(store),
+            },
+        })
+    }
+
+    /// Reads the policy and the store from the environment.
+    pub fn from_env(
+        facilitator: F,
+        providers: Arc<ProviderCache>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let store: Box<dyn ApprovalStore> = match std::env::var(from_env::ENV_APPROVAL_STORE_DIR) {
+            Ok(dir) => Box::new(
+                DirStore::new(dir)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_APPROVAL_STORE_DIR))?,
+            ),
+            Err(_) => Box::new(MemoryStore),
+        };
+        Ok(Self::new(
+            facilitator,
+            ApprovalPolicy::from_env()?,
+            providers,
+            store,
+        )?)
+    }
+
+    /// Handle to the approval records, for serving [`routes`].
+    pub fn approvals(&self) -> Approvals<F> {
+        self.approvals.clone()
+    }
+}
+
+impl<F> ApprovalGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    async fn propose(
+        &self,
+        policy: &ApprovalPolicy,
+        request: &SettleRequest,
+        verification: &VerifyResponse,
+    ) -> Result<SettleResponse, FacilitatorLocalError> {
+        let payer = match verification {
+            VerifyResponse::Valid { payer, .. } => Some(payer.clone()),
+            VerifyResponse::Invalid { payer, reason } => {
+                return Ok(SettleResponse {
+                    success: false,
+                    error_reason: Some(reason.clone()),
+                    payer: payer.clone(),
+                    transaction: None,
+                    network: request.network(),
+                    facilitator_version: None,
+                    batch: None,
+                    payment_id: None,
+                    partial: None,
+                    revert_reason: None,
+                    signer: None,
+                    settlement_id: None,
+                    simulated: false,
+                });
+            }
+        };
+        let requirements = &request.payment_requirements;
+        let id = serde_json::to_vec(request)
+            .map(|bytes| keccak256(bytes).to_string())
+            .map_err(|e| FacilitatorLocalError::DecodingError(e.to_string()))?;
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        let record = ApprovalRecord {
+            id: id.clone(),
+            status: ApprovalStatus::PendingApproval,
+            network: request.network(),
+            payer: payer.clone(),
+            amount: requirements.max_amount_required,
+            created_at: now,
+            transaction: None,
+        };
+        if !self.approvals.records.contains_key(&id) {
+            let proposal = ApprovalProposal {
+                id: id.clone(),
+                network: request.network(),
+                payer: payer.clone(),
+                pay_to: requirements.pay_to.clone(),
+                asset: requirements.asset.clone(),
+                amount: requirements.max_amount_required,
+                settle_request: request.clone(),
+            };
+            self.http
+                .post(policy.service_url.clone())
+                .json(&proposal)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status())
+                .map_err(|e| FacilitatorLocalError::ApprovalService(e.to_string()))?;
+            tracing::info!(id, network = %record.network, amount = %record.amount, "Settlement proposed for approval");
+            let entry = ApprovalEntry {
+                record,
+                request: request.clone(),
+            };
+            self.approvals.save(&entry);
+            self.approvals.records.insert(id.clone(), entry);
+        }
+        Ok(SettleResponse {
+            success: false,
+            error_reason: Some(FacilitatorErrorReason::PendingApproval),
+            payer,
+            transaction: None,
+            network: request.network(),
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: false,
+        })
+    }
+}
+
+impl<F> Facilitator for ApprovalGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.approvals.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        match &self.policy {
+            Some(policy) if policy.threshold.is_reached_by(request) => {
+                let verification = self.approvals.facilitator.verify(request).await?;
+                self.propose(policy, request, &verification).await
+            }
+            _ => self.approvals.facilitator.settle(request).await,
+        }
+    }
+
+    async fn settle_verified(
+        &self,
+        request: &SettleRequest,
+        verification: &VerifyResponse,
+    ) -> Result<SettleResponse, Self::Error> {
+        match &self.policy {
+            Some(policy) if policy.threshold.is_reached_by(request) => {
+                self.propose(policy, request, verification).await
+            }
+            _ => {
+                self.approvals
+                    .facilitator
+                    .settle_verified(request, verification)
+                    .await
+            }
+        }
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.approvals.facilitator.supported().await
+    }
+}
+
+/// Routes exposing approval records and the decision callback.
+pub fn routes<F>() -> Router<Approvals<F>>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    Router::new().route(
+        "/approvals/{id}",
+        get(get_approval::<F>).post(post_approval::<F>),
+    )
+}
+
+/// `GET /approvals/{id}`: State of a settlement proposed for approval.
+#[instrument(skip_all)]
+pub async fn get_approval<F>(
+    State(approvals): State<Approvals<F>>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    match approvals.get(&id) {
+        Some(record) => (StatusCode::OK, Json(record)).into_response(),
+        None => not_found(),
+    }
+}
+
+/// `POST /approvals/{id}`: Decision callback of the transaction service.
+#[instrument(skip_all)]
+pub async fn post_approval<F>(
+    State(approvals): State<Approvals<F>>,
+    Path(id): Path<String>,
+    headers: HeaderMap,
+    Json(decision): Json<ApprovalDecision>,
+) -> impl IntoResponse
+where
+    F: Facilitator<Error = FacilitatorLocalError>,
+{
+    if !approvals.authorizes(&headers) {
+        return (
+            StatusCode::UNAUTHORIZED,
+            Json(ErrorResponse {
+                error: "Unauthorized".to_string(),
+            }),
+        )
+            .into_response();
+    }
+    match approvals.decide(&id, decision).await {
+        Some(Ok(record)) => (StatusCode::OK, Json(record)).into_response(),
+        Some(Err(error)) => error.into_response(),
+        None => not_found(),
+    }
+}
+
+/// Equality of two hashes, in a time independent of where they differ.
+pub(crate) fn constant_time_eq(a: B256, b: B256) -> bool {
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn not_found() -> axum::response::Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Approval not found".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Facilitator counting its settlements, which all succeed.
+    #[derive(Default)]
+    struct Counting {
+        settlements: AtomicU32,
+    }
+
+    impl Facilitator for Counting {
+        type Error = FacilitatorLocalError;
+
+        async fn verify(&self, _request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            unimplemented!()
+        }
+
+        async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            self.settlements.fetch_add(1, Ordering::SeqCst);
+            tokio::task::yield_now().await;
+            Ok(SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: Some(payer()),
+                transaction: Some(TransactionHash::Evm([1; 32])),
+                network: request.network(),
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: None,
+                settlement_id: None,
+                simulated: false,
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            unimplemented!()
+        }
+    }
+
+    fn payer() -> MixedAddress {
+        MixedAddress::Evm(Address::repeat_byte(3).into())
+    }
+
+    fn request(asset: &str, amount: &str) -> SettleRequest {
+        serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": amount,
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": asset
+            }
+        }))
+        .unwrap()
+    }
+
+    fn entry(id: &str) -> ApprovalEntry {
+        let request = request("0x0000000000000000000000000000000000000002", "100");
+        ApprovalEntry {
+            record: ApprovalRecord {
+                id: id.to_string(),
+                status: ApprovalStatus::PendingApproval,
+                network: Network::Base,
+                payer: Some(payer()),
+                amount: request.payment_requirements.max_amount_required,
+                created_at: UnixTimestamp(100),
+                transaction: None,
+            },
+            request,
+        }
+    }
+
+    /// Gate without a policy, holding a pending record `id` and no network to read transactions from.
+    async fn approvals(id: &str) -> Approvals<Counting> {
+        let providers = ProviderCache::from_networks(std::iter::empty())
+            .await
+            .unwrap();
+        let gate = ApprovalGate::new(
+            Counting::default(),
+            None,
+            Arc::new(providers),
+            Box::new(MemoryStore),
+        )
+        .unwrap();
+        gate.approvals.records.insert(id.to_string(), entry(id));
+        gate.approvals()
+    }
+
+    #[test]
+    fn thresholds_scale_with_decimals() {
+        let threshold = ApprovalThreshold::from_str("1000").unwrap();
+        assert_eq!(threshold.in_base_units(6), U256::from(1_000_000_000u64));
+        assert_eq!(
+            threshold.in_base_units(18),
+            U256::from(10).pow(U256::from(21))
+        );
+        let threshold = ApprovalThreshold::from_str("0.5").unwrap();
+        assert_eq!(threshold.in_base_units(6), U256::from(500_000));
+        // Rounded up: a payment of fewer base units never reaches it.
+        let threshold = ApprovalThreshold::from_str("0.0000001").unwrap();
+        assert_eq!(threshold.in_base_units(6), U256::from(1));
+        assert!(ApprovalThreshold::from_str("ten").is_err());
+
+        let usdc = "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913";
+        let threshold = ApprovalThreshold::from_str("1").unwrap();
+        assert!(!threshold.is_reached_by(&request(usdc, "999999")));
+        assert!(threshold.is_reached_by(&request(usdc, "1000000")));
+        // Unknown decimals: every payment requires approval.
+        let unknown = "0x0000000000000000000000000000000000000002";
+        assert!(threshold.is_reached_by(&request(unknown, "1")));
+    }
+
+    #[tokio::test]
+    async fn rejections_are_final() {
+        let approvals = approvals("a").await;
+        let rejected = ApprovalDecision {
+            approved: false,
+            transaction: None,
+        };
+        let record = approvals
+            .decide("a", rejected.clone())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(record.status, ApprovalStatus::Rejected);
+        assert!(matches!(
+            approvals.decide("a", rejected).await,
+            Some(Err(DecisionError::NotPending(ApprovalStatus::Rejected)))
+        ));
+        assert!(
+            approvals
+                .decide(
+                    "b",
+                    ApprovalDecision {
+                        approved: true,
+                        transaction: None
+                    }
+                )
+                .await
+                .is_none()
+        );
+        assert_eq!(approvals.facilitator.settlements.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn concurrent_approvals_settle_once() {
+        let approvals = approvals("a").await;
+        let approved = ApprovalDecision {
+            approved: true,
+            transaction: None,
+        };
+        let (first, second) = tokio::join!(
+            approvals.decide("a", approved.clone()),
+            approvals.decide("a", approved)
+        );
+        let (settled, refused) = match (first.unwrap(), second.unwrap()) {
+            (Ok(record), Err(error)) | (Err(error), Ok(record)) => (record, error),
+            results => panic!("expected one settlement, got {results:?}"),
+        };
+        assert_eq!(settled.status, ApprovalStatus::Approved);
+        assert_eq!(settled.transaction, Some(TransactionHash::Evm([1; 32])));
+        assert!(matches!(
+            refused,
+            DecisionError::NotPending(ApprovalStatus::Deciding)
+        ));
+        assert_eq!(approvals.facilitator.settlements.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn reported_transactions_are_checked() {
+        let approvals = approvals("a").await;
+        let entry = entry("a");
+        let result = approvals
+            .check_transaction(
+                &entry.record,
+                &entry.request,
+                &TransactionHash::Near([1; 32]),
+            )
+            .await;
+        assert_eq!(result.unwrap_err(), "only EVM transactions can be checked");
+        let transaction = TransactionHash::Evm([2; 32]);
+        let result = approvals
+            .check_transaction(&entry.record, &entry.request, &transaction)
+            .await;
+        assert_eq!(result.unwrap_err(), "network base not available");
+
+        // A transaction that can not be checked leaves the record for another decision.
+        let decision = ApprovalDecision {
+            approved: true,
+            transaction: Some(transaction),
+        };
+        assert!(matches!(
+            approvals.decide("a", decision).await,
+            Some(Err(DecisionError::UnsettledTransaction(_)))
+        ));
+        assert_eq!(
+            approvals.get("a").unwrap().status,
+            ApprovalStatus::PendingApproval
+        );
+        assert_eq!(approvals.facilitator.settlements.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn records_survive_restarts() {
+        let dir = std::env::temp_dir().join(format!("x402-approvals-{}", std::process::id()));
+        let store = DirStore::new(&dir).unwrap();
+        let mut deciding = entry("a");
+        deciding.record.status = ApprovalStatus::Deciding;
+        store.save(&deciding).unwrap();
+        let mut rejected = entry("b");
+        rejected.record.status = ApprovalStatus::Rejected;
+        store.save(&rejected).unwrap();
+
+        let providers = ProviderCache::from_networks(std::iter::empty())
+            .await
+            .unwrap();
+        let gate = ApprovalGate::new(
+            Counting::default(),
+            None,
+            Arc::new(providers),
+            Box::new(DirStore::new(&dir).unwrap()),
+        )
+        .unwrap();
+        let approvals = gate.approvals();
+        assert_eq!(
+            approvals.get("a").unwrap().status,
+            ApprovalStatus::PendingApproval
+        );
+        assert_eq!(approvals.get("b").unwrap().status, ApprovalStatus::Rejected);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
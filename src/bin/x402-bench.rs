@@ -0,0 +1,348 @@
+//! Load generator for capacity planning: sends signed payments to a facilitator at a fixed rate and reports
+//! throughput and latency percentiles.
+//!
+//! Run it against a sandbox or devnet deployment, never a production one, to size RPC plans and hardware:
+//!
+//! ```text
+//! BENCH_PRIVATE_KEY=0x… x402-bench --facilitator http://localhost:8080 --rate 50 --duration 60
+//! ```
+//!
+//! Every request carries a fresh ERC-3009 `TransferWithAuthorization` signed by `BENCH_PRIVATE_KEY`, for
+//! `--amount` of the USDC deployment of `--network` (or `--asset`, with `--name` and `--version` of its EIP-712
+//! domain), paid to `--pay-to` or back to the payer. Requests go to `/verify`, or to `/settle` with `--settle`,
+//! which spends the payer's balance and the facilitator's gas: the payer needs funds on the network either way.
+//!
+//! Requests are sent on schedule whether earlier ones answered or not, so latencies include queueing in the
+//! facilitator. The exit status is `1` if any request failed or was answered invalid.
+
+use alloy::primitives::{Address, FixedBytes, keccak256};
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol_types::{SolStruct, eip712_domain};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
+use url::Url;
+use x402_rs::chain::evm::EvmChain;
+use x402_rs::network::{Network, USDCDeployment};
+use x402_rs::timestamp::UnixTimestamp;
+use x402_rs::types::{
+    EvmAddress, EvmSignature, ExactEvmPayload, ExactEvmPayloadAuthorization, ExactPaymentPayload,
+    HexEncodedNonce, MixedAddress, PaymentPayload, PaymentRequirements, Scheme, TokenAmount,
+    TransferWithAuthorization, VerifyRequest, X402Version,
+};
+
+const USAGE: &str = "Usage: x402-bench --facilitator <url> --rate <requests per second> [--duration <secs>] [--network <network>] [--asset <address> --name <eip712 name> --version <eip712 version>] [--amount <units>] [--pay-to <address>] [--settle]";
+
+/// Environment variable holding the key payments are signed with.
+const ENV_BENCH_PRIVATE_KEY: &str = "BENCH_PRIVATE_KEY";
+const DEFAULT_DURATION_SECS: u64 = 30;
+const DEFAULT_AMOUNT: u64 = 1_000;
+
+#[derive(Debug)]
+struct Args {
+    facilitator: Option<Url>,
+    rate: Option<f64>,
+    duration: Duration,
+    network: Network,
+    asset: Option<Address>,
+    name: Option<String>,
+    version: Option<String>,
+    amount: u64,
+    pay_to: Option<Address>,
+    settle: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            facilitator: None,
+            rate: None,
+            duration: Duration::from_secs(DEFAULT_DURATION_SECS),
+            network: Network::BaseSepolia,
+            asset: None,
+            name: None,
+            version: None,
+            amount: DEFAULT_AMOUNT,
+            pay_to: None,
+            settle: false,
+        }
+    }
+}
+
+fn parse_args() -> Result<Args, Box<dyn Error>> {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("{arg} expects a value"));
+        match arg.as_str() {
+            "--facilitator" => args.facilitator = Some(Url::parse(&value()?)?),
+            "--rate" => args.rate = Some(value()?.parse()?),
+            "--duration" => args.duration = Duration::from_secs(value()?.parse()?),
+            "--network" => args.network = value()?.parse()?,
+            "--asset" => args.asset = Some(value()?.parse()?),
+            "--name" => args.name = Some(value()?),
+            "--version" => args.version = Some(value()?),
+            "--amount" => args.amount = value()?.parse()?,
+            "--pay-to" => args.pay_to = Some(value()?.parse()?),
+            "--settle" => args.settle = true,
+            "-h" | "--help" => return Err(USAGE.into()),
+            other => return Err(format!("unknown argument {other}\n{USAGE}").into()),
+        }
+    }
+    Ok(args)
+}
+
+/// What is paid, and how the payments are signed.
+struct Payment {
+    signer: PrivateKeySigner,
+    chain: EvmChain,
+    asset: Address,
+    name: String,
+    version: String,
+    pay_to: Address,
+    amount: u64,
+}
+
+impl Payment {
+    fn from_args(args: &Args) -> Result<Self, Box<dyn Error>> {
+        let signer: PrivateKeySigner = std::env::var(ENV_BENCH_PRIVATE_KEY)
+            .map_err(|_| format!("{ENV_BENCH_PRIVATE_KEY} is required"))?
+            .parse()?;
+        let chain = EvmChain::try_from(args.network)
+            .map_err(|e| format!("{} is not an EVM network: {e:?}", args.network))?;
+        let usdc = USDCDeployment::try_by_network(args.network);
+        let asset = match (args.asset, usdc) {
+            (Some(asset), _) => asset,
+            (None, Some(usdc)) => Address::try_from(usdc.address())
+                .map_err(|e| format!("invalid USDC address: {e:?}"))?,
+            (None, None) => {
+                return Err(format!("no known USDC on {}: set --asset", args.network).into());
+            }
+        };
+        let domain = usdc
+            .filter(|usdc| {
+                args.asset
+                    .is_none_or(|asset| usdc.address() == MixedAddress::from(asset))
+            })
+            .and_then(|usdc| usdc.eip712.clone());
+        let name = args
+            .name
+            .clone()
+            .or_else(|| domain.as_ref().map(|domain| domain.name.clone()))
+            .ok_or("--name is required with --asset")?;
+        let version = args
+            .version
+            .clone()
+            .or_else(|| domain.as_ref().map(|domain| domain.version.clone()))
+            .ok_or("--version is required with --asset")?;
+        let pay_to = args.pay_to.unwrap_or(signer.address());
+        Ok(Self {
+            signer,
+            chain,
+            asset,
+            name,
+            version,
+            pay_to,
+            amount: args.amount,
+        })
+    }
+
+    /// A freshly signed request, its nonce derived from `seed` and `index`.
+    fn request(&self, seed: u64, index: u64) -> Result<VerifyRequest, Box<dyn Error>> {
+        let nonce = keccak256([seed.to_be_bytes(), index.to_be_bytes()].concat());
+        let now = UnixTimestamp::try_now()?;
+        let authorization = ExactEvmPayloadAuthorization {
+            from: EvmAddress(self.signer.address()),
+            to: EvmAddress(self.pay_to),
+            value: TokenAmount::from(self.amount),
+            valid_after: UnixTimestamp(now.seconds_since_epoch() - 10 * 60),
+            valid_before: now + 300,
+            nonce: HexEncodedNonce(nonce.0),
+        };
+        let message = TransferWithAuthorization {
+            from: authorization.from.into(),
+            to: authorization.to.into(),
+            value: authorization.value.into(),
+            validAfter: authorization.valid_after.into(),
+            validBefore: authorization.valid_before.into(),
+            nonce: FixedBytes(nonce.0),
+        };
+        let domain = eip712_domain! {
+            name: self.name.clone(),
+            version: self.version.clone(),
+            chain_id: self.chain.chain_id,
+            verifying_contract: self.asset,
+        };
+        let signature = self
+            .signer
+            .sign_hash_sync(&message.eip712_signing_hash(&domain))?;
+        let network = self.chain.network;
+        Ok(VerifyRequest {
+            x402_version: X402Version::V1,
+            payment_payload: PaymentPayload {
+                x402_version: X402Version::V1,
+                scheme: Scheme::Exact,
+                network,
+                payload: ExactPaymentPayload::Evm(ExactEvmPayload {
+                    signature: EvmSignature::from(signature.as_bytes()),
+                    authorization,
+                }),
+                payment_id: None,
+            },
+            payment_requirements: PaymentRequirements {
+                scheme: Scheme::Exact,
+                network,
+                max_amount_required: TokenAmount::from(self.amount),
+                resource: Url::parse("https://bench.x402.invalid/resource")?,
+                description: "x402-bench".to_string(),
+                mime_type: "application/json".to_string(),
+                output_schema: None,
+                pay_to: MixedAddress::from(self.pay_to),
+                max_timeout_seconds: 300,
+                asset: MixedAddress::from(self.asset),
+                extra: Some(serde_json::json!({ "name": self.name, "version": self.version })),
+                payment_id: None,
+                price_usd: None,
+                facilitator_fee: None,
+            },
+            settle_amount: None,
+            allow_partial: false,
+            execute_after: None,
+        })
+    }
+}
+
+/// How a request went.
+#[derive(Debug)]
+enum Outcome {
+    /// Valid, or settled.
+    Ok,
+    /// Answered, but invalid or not settled, with the reason.
+    Rejected(String),
+    /// No answer, or an error status.
+    Failed(String),
+}
+
+async fn send(http: &reqwest::Client, url: Url, request: VerifyRequest, settle: bool) -> Outcome {
+    let response = match http.post(url).json(&request).send().await {
+        Ok(response) => response,
+        Err(e) => return Outcome::Failed(e.to_string()),
+    };
+    let status = response.status();
+    let body: serde_json::Value = match response.json().await {
+        Ok(body) => body,
+        Err(e) => return Outcome::Failed(format!("{status}: {e}")),
+    };
+    if !status.is_success() {
+        return Outcome::Failed(format!("{status}: {body}"));
+    }
+    let (ok, reason) = match settle {
+        true => (body["success"].as_bool(), &body["errorReason"]),
+        false => (body["isValid"].as_bool(), &body["invalidReason"]),
+    };
+    match ok {
+        Some(true) => Outcome::Ok,
+        _ => Outcome::Rejected(reason.as_str().unwrap_or("unknown").to_string()),
+    }
+}
+
+/// The `rank` percentile (0 to 100) of sorted `latencies`.
+fn percentile(latencies: &[Duration], rank: usize) -> Duration {
+    match latencies.len() {
+        0 => Duration::ZERO,
+        len => latencies[((len - 1) * rank).div_ceil(100)],
+    }
+}
+
+async fn run(args: Args) -> Result<bool, Box<dyn Error>> {
+    let facilitator = args.facilitator.clone().ok_or(USAGE)?;
+    let rate = args.rate.filter(|rate| *rate > 0.0).ok_or(USAGE)?;
+    let url = facilitator.join(if args.settle { "./settle" } else { "./verify" })?;
+    let payment = Payment::from_args(&args)?;
+    println!(
+        "{} {} payments of {} to {url} at {rate}/s for {}s, from {}",
+        if args.settle { "Settling" } else { "Verifying" },
+        args.network,
+        args.amount,
+        args.duration.as_secs(),
+        payment.signer.address()
+    );
+    let http = reqwest::Client::new();
+    let seed = UnixTimestamp::try_now()?.seconds_since_epoch();
+    let mut ticker = tokio::time::interval(Duration::from_secs_f64(1.0 / rate));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Burst);
+    let mut tasks = tokio::task::JoinSet::new();
+    let started = Instant::now();
+    let mut index = 0;
+    while started.elapsed() < args.duration {
+        ticker.tick().await;
+        let request = payment.request(seed, index)?;
+        index += 1;
+        let (http, url, settle) = (http.clone(), url.clone(), args.settle);
+        tasks.spawn(async move {
+            let sent_at = Instant::now();
+            let outcome = send(&http, url, request, settle).await;
+            (sent_at.elapsed(), outcome)
+        });
+    }
+    let mut latencies = Vec::new();
+    let mut rejected: BTreeMap<String, usize> = BTreeMap::new();
+    let mut failed: BTreeMap<String, usize> = BTreeMap::new();
+    while let Some(result) = tasks.join_next().await {
+        let (latency, outcome) = result?;
+        latencies.push(latency);
+        match outcome {
+            Outcome::Ok => {}
+            Outcome::Rejected(reason) => *rejected.entry(reason).or_default() += 1,
+            Outcome::Failed(error) => *failed.entry(error).or_default() += 1,
+        }
+    }
+    let elapsed = started.elapsed();
+    latencies.sort();
+    let total = latencies.len();
+    let rejected_count: usize = rejected.values().sum();
+    let failed_count: usize = failed.values().sum();
+    let ok = total - rejected_count - failed_count;
+    println!(
+        "{total} requests in {:.1}s: {ok} ok, {rejected_count} rejected, {failed_count} failed",
+        elapsed.as_secs_f64()
+    );
+    println!(
+        "throughput: {:.1} ok/s, {:.1} requests/s",
+        ok as f64 / elapsed.as_secs_f64(),
+        total as f64 / elapsed.as_secs_f64()
+    );
+    println!(
+        "latency: p50 {:?}, p90 {:?}, p99 {:?}, max {:?}",
+        percentile(&latencies, 50),
+        percentile(&latencies, 90),
+        percentile(&latencies, 99),
+        latencies.last().copied().unwrap_or_default()
+    );
+    for (reason, count) in &rejected {
+        println!("  rejected {count}: {reason}");
+    }
+    for (error, count) in &failed {
+        println!("  failed {count}: {error}");
+    }
+    Ok(rejected_count == 0 && failed_count == 0)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    let result = match parse_args() {
+        Ok(args) => run(args).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(2)
+        }
+    }
+}
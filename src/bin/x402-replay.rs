@@ -0,0 +1,175 @@
+//! Replays archived `/verify` requests against a facilitator build and reports where its answers differ.
+//!
+//! A safety net for refactors of the verification pipeline: run the current and the new build side by side, both
+//! against the same forked chain state (e.g. `anvil --fork-url <rpc> --fork-block-number <block>`, set as their
+//! `RPC_URL_*`), and replay real traffic through both:
+//!
+//! ```text
+//! x402-replay --candidate http://localhost:8081 --baseline http://localhost:8080 --store
+//! ```
+//!
+//! Requests come from the forensics payload store (`--store`, read with `PAYLOAD_STORE_DIR` and
+//! `PAYLOAD_STORE_KEY`), and from files or directories of raw request bodies given as arguments. Archived `/settle`
+//! bodies are replayed as `/verify` requests, so nothing is settled. Answers can also be saved with `--save` and
+//! compared in a later run with `--expected`, instead of running a baseline.
+//!
+//! Both builds read the wall clock: authorizations expired since they were archived are reported invalid by both.
+//! The exit status is `1` if any answer differs.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use url::Url;
+use x402_rs::payload_store::{PayloadStore, payload_hash};
+use x402_rs::types::VerifyRequest;
+
+const USAGE: &str = "Usage: x402-replay --candidate <url> [--baseline <url>] [--expected <file>] [--save <file>] [--store] [<file or directory>...]";
+
+/// Answer of a facilitator to a replayed request.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Answer {
+    status: u16,
+    body: serde_json::Value,
+}
+
+#[derive(Debug, Default)]
+struct Args {
+    candidate: Option<Url>,
+    baseline: Option<Url>,
+    expected: Option<PathBuf>,
+    save: Option<PathBuf>,
+    store: bool,
+    paths: Vec<PathBuf>,
+}
+
+fn parse_args() -> Result<Args, Box<dyn Error>> {
+    let mut args = Args::default();
+    let mut iter = std::env::args().skip(1);
+    while let Some(arg) = iter.next() {
+        let mut value = || iter.next().ok_or(format!("{arg} expects a value"));
+        match arg.as_str() {
+            "--candidate" => args.candidate = Some(Url::parse(&value()?)?),
+            "--baseline" => args.baseline = Some(Url::parse(&value()?)?),
+            "--expected" => args.expected = Some(value()?.into()),
+            "--save" => args.save = Some(value()?.into()),
+            "--store" => args.store = true,
+            "-h" | "--help" => return Err(USAGE.into()),
+            flag if flag.starts_with("--") => return Err(format!("unknown option {flag}").into()),
+            path => args.paths.push(path.into()),
+        }
+    }
+    Ok(args)
+}
+
+/// Request bodies by hash, from the payload store and the given paths.
+async fn load_payloads(args: &Args) -> Result<BTreeMap<String, Vec<u8>>, Box<dyn Error>> {
+    let mut payloads = BTreeMap::new();
+    if args.store {
+        let store = PayloadStore::from_env()?.ok_or("--store requires PAYLOAD_STORE_DIR")?;
+        for hash in store.hashes().await? {
+            if let Some(bytes) = store.get(&hash).await? {
+                payloads.insert(hash, bytes);
+            }
+        }
+    }
+    for path in &args.paths {
+        let files = if path.is_dir() {
+            let mut files = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            files.retain(|file| file.is_file());
+            files
+        } else {
+            vec![path.clone()]
+        };
+        for file in files {
+            let bytes = std::fs::read(&file)?;
+            payloads.insert(payload_hash(&bytes), bytes);
+        }
+    }
+    Ok(payloads)
+}
+
+async fn verify(
+    http: &reqwest::Client,
+    facilitator: &Url,
+    body: &[u8],
+) -> Result<Answer, Box<dyn Error>> {
+    let response = http
+        .post(facilitator.join("./verify")?)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(body.to_vec())
+        .send()
+        .await?;
+    let status = response.status().as_u16();
+    let bytes = response.bytes().await?;
+    let body = serde_json::from_slice(&bytes)
+        .unwrap_or_else(|_| serde_json::Value::String(String::from_utf8_lossy(&bytes).into()));
+    Ok(Answer { status, body })
+}
+
+fn read_answers(path: &Path) -> Result<BTreeMap<String, Answer>, Box<dyn Error>> {
+    Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+}
+
+async fn run(args: Args) -> Result<bool, Box<dyn Error>> {
+    let candidate = args.candidate.clone().ok_or(USAGE)?;
+    let expected = match &args.expected {
+        Some(path) => read_answers(path)?,
+        None => BTreeMap::new(),
+    };
+    let payloads = load_payloads(&args).await?;
+    let http = reqwest::Client::new();
+    let mut answers = BTreeMap::new();
+    let (mut same, mut differing, mut skipped) = (0, 0, 0);
+    for (hash, body) in &payloads {
+        if serde_json::from_slice::<VerifyRequest>(body).is_err() {
+            println!("{hash}: skipped, not a verify or settle request");
+            skipped += 1;
+            continue;
+        }
+        let answer = verify(&http, &candidate, body).await?;
+        let reference = match &args.baseline {
+            Some(baseline) => Some(verify(&http, baseline, body).await?),
+            None => expected.get(hash).cloned(),
+        };
+        match reference {
+            Some(reference) if reference != answer => {
+                println!("{hash}: differs");
+                println!("  expected: {} {}", reference.status, reference.body);
+                println!("  actual:   {} {}", answer.status, answer.body);
+                differing += 1;
+            }
+            Some(_) => same += 1,
+            None => println!("{hash}: {} {}", answer.status, answer.body),
+        }
+        answers.insert(hash.clone(), answer);
+    }
+    if let Some(path) = &args.save {
+        std::fs::write(path, serde_json::to_vec_pretty(&answers)?)?;
+    }
+    println!(
+        "{} replayed: {same} same, {differing} different, {skipped} skipped",
+        payloads.len()
+    );
+    Ok(differing == 0)
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    dotenvy::dotenv().ok();
+    let result = match parse_args() {
+        Ok(args) => run(args).await,
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("{e}");
+            ExitCode::from(2)
+        }
+    }
+}
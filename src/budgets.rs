@@ -0,0 +1,573 @@
+//! Daily spend caps per payer, enforced by the facilitator.
+//!
+//! Operators register a budget for a payer key on a network and asset, for example the wallet of an autonomous
+//! agent. Payments of that payer are verified as usual, but a settlement is refused with the `budget_exceeded`
+//! error reason once the amounts settled during the current UTC day would exceed the budget. `/verify` already
+//! rejects payments that do not fit in what is left.
+//!
+//! Budgets are managed through the admin API:
+//! - `GET /admin/budgets` — registered budgets with today's spending,
+//! - `PUT /admin/budgets/{network}/{payer}/{asset}` — sets a budget, body `{"maxPerDay": "1000000"}`,
+//! - `DELETE /admin/budgets/{network}/{payer}/{asset}` — removes it.
+//!
+//! Payers without a budget are not limited. Budgets and their spending are kept in memory, and also in
+//! `BUDGET_STORE_DIR` if set, so that neither is lost on restart.
+
+use alloy::primitives::{U256, keccak256};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, put};
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ErrorResponse, MixedAddress, SettleRequest, SettleResponse, SupportedPaymentKindsResponse,
+    TokenAmount, VerifyRequest, VerifyResponse,
+};
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Payer, network and asset a budget applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct BudgetKey {
+    pub network: Network,
+    pub payer: MixedAddress,
+    pub asset: MixedAddress,
+}
+
+impl BudgetKey {
+    /// Name of the key safe for use as a file name.
+    fn file_name(&self) -> String {
+        let key = format!("{}/{}/{}", self.network, self.payer, self.asset);
+        format!("{}.json", keccak256(key))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetState {
+    pub max_per_day: U256,
+    /// UTC day, in days since the epoch, `spent` is counted for.
+    pub day: u64,
+    pub spent: U256,
+}
+
+impl BudgetState {
+    /// Amount spent on `day`, zero once the day the spending was counted for is over.
+    fn spent_on(&self, day: u64) -> U256 {
+        if self.day == day {
+            self.spent
+        } else {
+            U256::ZERO
+        }
+    }
+}
+
+/// Body of `PUT /admin/budgets/{network}/{payer}/{asset}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetLimit {
+    /// Most the payer may settle per UTC day, in the asset's smallest unit.
+    pub max_per_day: TokenAmount,
+}
+
+/// A registered budget, as served by the admin API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BudgetRecord {
+    pub network: Network,
+    pub payer: MixedAddress,
+    pub asset: MixedAddress,
+    pub max_per_day: TokenAmount,
+    /// Amount settled since the start of the current UTC day.
+    pub spent_today: TokenAmount,
+}
+
+/// A budget with its spending, as kept in a [`BudgetStore`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BudgetEntry {
+    pub key: BudgetKey,
+    pub state: BudgetState,
+}
+
+/// Persistence of budgets. Every change of a budget or of its spending is saved.
+pub trait BudgetStore: Send + Sync {
+    fn save(&self, entry: &BudgetEntry) -> std::io::Result<()>;
+    fn remove(&self, key: &BudgetKey) -> std::io::Result<()>;
+    /// Entries saved before a restart.
+    fn load(&self) -> std::io::Result<Vec<BudgetEntry>>;
+}
+
+/// Store keeping nothing: budgets live in memory only.
+#[derive(Debug, Default)]
+pub struct MemoryStore;
+
+impl BudgetStore for MemoryStore {
+    fn save(&self, _entry: &BudgetEntry) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&self, _key: &BudgetKey) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn load(&self) -> std::io::Result<Vec<BudgetEntry>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Store writing one JSON file per budget into a directory.
+#[derive(Debug)]
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+}
+
+impl BudgetStore for DirStore {
+    fn save(&self, entry: &BudgetEntry) -> std::io::Result<()> {
+        let path = self.dir.join(entry.key.file_name());
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    fn remove(&self, key: &BudgetKey) -> std::io::Result<()> {
+        match std::fs::remove_file(self.dir.join(key.file_name())) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn load(&self) -> std::io::Result<Vec<BudgetEntry>> {
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match serde_json::from_slice(&std::fs::read(&path)?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping unreadable budget")
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Registered budgets and the spending counted against them.
+#[derive(Clone)]
+pub struct Budgets {
+    budgets: Arc<DashMap<BudgetKey, BudgetState>>,
+    store: Arc<dyn BudgetStore>,
+}
+
+impl Default for Budgets {
+    fn default() -> Self {
+        Self {
+            budgets: Arc::new(DashMap::new()),
+            store: Arc::new(MemoryStore),
+        }
+    }
+}
+
+impl Budgets {
+    /// Budgets saved in `store`.
+    pub fn new(store: Box<dyn BudgetStore>) -> std::io::Result<Self> {
+        let budgets = DashMap::new();
+        for entry in store.load()? {
+            budgets.insert(entry.key, entry.state);
+        }
+        Ok(Self {
+            budgets: Arc::new(budgets),
+            store: Arc::from(store),
+        })
+    }
+
+    /// Reads the store from `BUDGET_STORE_DIR`; budgets live in memory only if unset.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let store: Box<dyn BudgetStore> = match std::env::var(from_env::ENV_BUDGET_STORE_DIR) {
+            Ok(dir) => Box::new(
+                DirStore::new(dir)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_BUDGET_STORE_DIR))?,
+            ),
+            Err(_) => Box::new(MemoryStore),
+        };
+        Ok(Self::new(store)?)
+    }
+
+    fn save(&self, key: &BudgetKey, state: &BudgetState) {
+        let entry = BudgetEntry {
+            key: key.clone(),
+            state: state.clone(),
+        };
+        if let Err(e) = self.store.save(&entry) {
+            tracing::warn!(network = %key.network, payer = %key.payer, error = %e, "Failed to save budget");
+        }
+    }
+
+    /// Sets the daily budget of `payer` for `asset` on `network`, keeping today's spending.
+    pub fn set(
+        &self,
+        network: Network,
+        payer: MixedAddress,
+        asset: MixedAddress,
+        max: TokenAmount,
+    ) {
+        let key = BudgetKey {
+            network,
+            payer,
+            asset,
+        };
+        let state = self
+            .budgets
+            .entry(key.clone())
+            .and_modify(|state| state.max_per_day = max.0)
+            .or_insert(BudgetState {
+                max_per_day: max.0,
+                day: 0,
+                spent: U256::ZERO,
+            });
+        self.save(&key, &state);
+    }
+
+    /// Removes a budget; `false` if there was none.
+    pub fn remove(&self, network: Network, payer: MixedAddress, asset: MixedAddress) -> bool {
+        let key = BudgetKey {
+            network,
+            payer,
+            asset,
+        };
+        let removed = self.budgets.remove(&key).is_some();
+        if let Err(e) = self.store.remove(&key) {
+            tracing::warn!(network = %key.network, payer = %key.payer, error = %e, "Failed to remove budget");
+        }
+        removed
+    }
+
+    /// All registered budgets, in no particular order.
+    pub fn list(&self) -> Result<Vec<BudgetRecord>, FacilitatorLocalError> {
+        let today = today()?;
+        Ok(self
+            .budgets
+            .iter()
+            .map(|entry| BudgetRecord {
+                network: entry.key().network,
+                payer: entry.key().payer.clone(),
+                asset: entry.key().asset.clone(),
+                max_per_day: entry.max_per_day.into(),
+                spent_today: entry.spent_on(today).into(),
+            })
+            .collect())
+    }
+
+    fn key(request: &VerifyRequest, payer: MixedAddress) -> BudgetKey {
+        BudgetKey {
+            network: request.network(),
+            payer,
+            asset: request.payment_requirements.asset.clone(),
+        }
+    }
+
+    /// Checks that `amount` fits in what is left of the payer's budget on `today`, and counts it if `reserve` is set.
+    fn spend(
+        &self,
+        key: &BudgetKey,
+        amount: U256,
+        today: u64,
+        reserve: bool,
+    ) -> Result<(), FacilitatorLocalError> {
+        let Some(mut state) = self.budgets.get_mut(key) else {
+            return Ok(());
+        };
+        let spent = state.spent_on(today);
+        let total = spent.saturating_add(amount);
+        if total > state.max_per_day {
+            tracing::info!(
+                monotonic_counter.x402.budgets.rejections = 1,
+                network = %key.network,
+                payer = %key.payer,
+                "Payment exceeds the payer's daily budget"
+            );
+            return Err(FacilitatorLocalError::BudgetExceeded(key.payer.clone()));
+        }
+        if reserve {
+            state.day = today;
+            state.spent = total;
+            self.save(key, &state);
+        }
+        Ok(())
+    }
+
+    /// Gives back an amount counted by [`Budgets::spend`] on `day` for a settlement that did not go through.
+    ///
+    /// Nothing is given back once `day` is over: the spending of the new day does not include the amount.
+    fn refund(&self, key: &BudgetKey, amount: U256, day: u64) {
+        if let Some(mut state) = self.budgets.get_mut(key)
+            && state.day == day
+        {
+            state.spent = state.spent.saturating_sub(amount);
+            self.save(key, &state);
+        }
+    }
+}
+
+fn today() -> Result<u64, FacilitatorLocalError> {
+    let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+    Ok(now.seconds_since_epoch() / SECONDS_PER_DAY)
+}
+
+/// Amount a request moves: `settleAmount` of an `upto` settlement, `maxAmountRequired` otherwise.
+fn amount(request: &VerifyRequest) -> U256 {
+    request
+        .settle_amount
+        .unwrap_or(request.payment_requirements.max_amount_required)
+        .0
+}
+
+/// [`Facilitator`] wrapper enforcing [`Budgets`].
+pub struct BudgetGate<F> {
+    facilitator: F,
+    budgets: Budgets,
+}
+
+impl<F> BudgetGate<F> {
+    pub fn new(facilitator: F, budgets: Budgets) -> Self {
+        Self {
+            facilitator,
+            budgets,
+        }
+    }
+}
+
+impl<F> Facilitator for BudgetGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let response = self.facilitator.verify(request).await?;
+        if let VerifyResponse::Valid { payer, .. } = &response {
+            let key = Budgets::key(request, payer.clone());
+            self.budgets.spend(&key, amount(request), today()?, false)?;
+        }
+        Ok(response)
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        if self.budgets.budgets.is_empty() {
+            return self.facilitator.settle(request).await;
+        }
+        // The payer is only known for sure once the payment is verified.
+        let verification = self.facilitator.verify(request).await?;
+        self.settle_verified(request, &verification).await
+    }
+
+    async fn settle_verified(
+        &self,
+        request: &SettleRequest,
+        verification: &VerifyResponse,
+    ) -> Result<SettleResponse, Self::Error> {
+        let VerifyResponse::Valid { payer, .. } = verification else {
+            return self
+                .facilitator
+                .settle_verified(request, verification)
+                .await;
+        };
+        let key = Budgets::key(request, payer.clone());
+        let amount = amount(request);
+        let day = today()?;
+        self.budgets.spend(&key, amount, day, true)?;
+        let response = self
+            .facilitator
+            .settle_verified(request, verification)
+            .await;
+        if !matches!(&response, Ok(response) if response.success) {
+            self.budgets.refund(&key, amount, day);
+        }
+        response
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+/// Admin routes listing budgets.
+pub fn routes() -> Router<Budgets> {
+    Router::new().route("/budgets", get(get_budgets))
+}
+
+/// Admin routes setting and removing budgets.
+pub fn config_routes() -> Router<Budgets> {
+    Router::new().route(
+        "/budgets/{network}/{payer}/{asset}",
+        put(put_budget).delete(delete_budget),
+    )
+}
+
+/// `GET /admin/budgets`: Registered budgets with today's spending.
+#[instrument(skip_all)]
+pub async fn get_budgets(State(budgets): State<Budgets>) -> Response {
+    match budgets.list() {
+        Ok(records) => (StatusCode::OK, Json(records)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+/// `PUT /admin/budgets/{network}/{payer}/{asset}`: Sets the daily budget of a payer.
+#[instrument(skip_all, fields(network = %network, payer = %payer))]
+pub async fn put_budget(
+    State(budgets): State<Budgets>,
+    Path((network, payer, asset)): Path<(Network, MixedAddress, MixedAddress)>,
+    Json(limit): Json<BudgetLimit>,
+) -> Response {
+    budgets.set(network, payer.clone(), asset.clone(), limit.max_per_day);
+    tracing::info!(max_per_day = %limit.max_per_day, "Payer budget set");
+    (
+        StatusCode::OK,
+        Json(serde_json::json!({
+            "network": network,
+            "payer": payer,
+            "asset": asset,
+            "maxPerDay": limit.max_per_day,
+        })),
+    )
+        .into_response()
+}
+
+/// `DELETE /admin/budgets/{network}/{payer}/{asset}`: Removes the budget of a payer.
+#[instrument(skip_all, fields(network = %network, payer = %payer))]
+pub async fn delete_budget(
+    State(budgets): State<Budgets>,
+    Path((network, payer, asset)): Path<(Network, MixedAddress, MixedAddress)>,
+) -> Response {
+    if budgets.remove(network, payer, asset) {
+        tracing::info!("Payer budget removed");
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Budget not found".to_string(),
+            }),
+        )
+            .into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn caps_daily_spending_per_payer() {
+        let budgets = Budgets::default();
+        let payer = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(1).into());
+        let asset = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(2).into());
+        let key = BudgetKey {
+            network: Network::Base,
+            payer: payer.clone(),
+            asset: asset.clone(),
+        };
+        let today = today().unwrap();
+        // Unregistered payers are not limited.
+        assert!(budgets.spend(&key, U256::MAX, today, true).is_ok());
+
+        budgets.set(Network::Base, payer, asset, TokenAmount::from(100u64));
+        assert!(budgets.spend(&key, U256::from(60), today, true).is_ok());
+        assert!(budgets.spend(&key, U256::from(40), today, false).is_ok());
+        assert!(matches!(
+            budgets.spend(&key, U256::from(41), today, true),
+            Err(FacilitatorLocalError::BudgetExceeded(_))
+        ));
+        budgets.refund(&key, U256::from(60), today);
+        assert!(budgets.spend(&key, U256::from(100), today, true).is_ok());
+        assert_eq!(
+            budgets.list().unwrap()[0].spent_today,
+            TokenAmount::from(100u64)
+        );
+    }
+
+    #[test]
+    fn refunds_apply_to_the_day_reserved() {
+        let budgets = Budgets::default();
+        let key = BudgetKey {
+            network: Network::Base,
+            payer: MixedAddress::Evm(alloy::primitives::Address::repeat_byte(1).into()),
+            asset: MixedAddress::Evm(alloy::primitives::Address::repeat_byte(2).into()),
+        };
+        budgets.set(
+            key.network,
+            key.payer.clone(),
+            key.asset.clone(),
+            TokenAmount::from(100u64),
+        );
+        budgets.spend(&key, U256::from(60), 10, true).unwrap();
+        budgets.spend(&key, U256::from(30), 11, true).unwrap();
+        // The settlement reserved on day 10 failed after midnight: day 11 keeps its spending.
+        budgets.refund(&key, U256::from(60), 10);
+        assert!(matches!(
+            budgets.spend(&key, U256::from(71), 11, false),
+            Err(FacilitatorLocalError::BudgetExceeded(_))
+        ));
+        budgets.refund(&key, U256::from(30), 11);
+        budgets.spend(&key, U256::from(100), 11, false).unwrap();
+    }
+
+    #[test]
+    fn budgets_survive_restarts() {
+        let dir = std::env::temp_dir().join(format!("x402-budgets-{}", std::process::id()));
+        let budgets = Budgets::new(Box::new(DirStore::new(&dir).unwrap())).unwrap();
+        let payer = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(1).into());
+        let asset = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(2).into());
+        let key = BudgetKey {
+            network: Network::Base,
+            payer: payer.clone(),
+            asset: asset.clone(),
+        };
+        budgets.set(
+            Network::Base,
+            payer.clone(),
+            asset.clone(),
+            TokenAmount::from(100u64),
+        );
+        budgets.spend(&key, U256::from(60), 10, true).unwrap();
+        let other = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(3).into());
+        budgets.set(
+            Network::Base,
+            other.clone(),
+            asset.clone(),
+            TokenAmount::from(1u64),
+        );
+        assert!(budgets.remove(Network::Base, other, asset));
+
+        let restarted = Budgets::new(Box::new(DirStore::new(&dir).unwrap())).unwrap();
+        assert_eq!(restarted.budgets.len(), 1);
+        assert!(matches!(
+            restarted.spend(&key, U256::from(41), 10, false),
+            Err(FacilitatorLocalError::BudgetExceeded(_))
+        ));
+        restarted.spend(&key, U256::from(40), 10, false).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,53 @@
+//! Build and protocol metadata of the running facilitator.
+//!
+//! Served by `GET /version` and, in compact form, attached to settlement receipts so clients
+//! and operators can pinpoint which facilitator build produced a given response.
+//! Git commit, build timestamp and enabled features are injected by the crate's `build.rs`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::timestamp::UnixTimestamp;
+use crate::types::X402Version;
+
+/// Version and build metadata of this facilitator binary.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildInfo {
+    /// Crate name, i.e. `x402-rs`.
+    pub name: String,
+    /// Crate version from `Cargo.toml`.
+    pub version: String,
+    /// Full git commit hash the binary was built from, or `unknown`.
+    pub git_commit: String,
+    pub build_timestamp: UnixTimestamp,
+    /// Cargo features enabled at compile time.
+    pub features: Vec<String>,
+    /// x402 protocol versions accepted by `/verify` and `/settle`.
+    pub x402_versions: Vec<X402Version>,
+}
+
+impl BuildInfo {
+    /// Metadata of the currently running binary.
+    pub fn current() -> Self {
+        Self {
+            name: env!("CARGO_PKG_NAME").to_string(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            git_commit: env!("X402_GIT_COMMIT").to_string(),
+            build_timestamp: UnixTimestamp(
+                env!("X402_BUILD_TIMESTAMP").parse().unwrap_or_default(),
+            ),
+            features: env!("X402_FEATURES")
+                .split(',')
+                .filter(|feature| !feature.is_empty())
+                .map(String::from)
+                .collect(),
+            x402_versions: vec![X402Version::V1, X402Version::V2],
+        }
+    }
+
+    /// Compact `version+commit` identifier, e.g. `0.9.0+4b29d0f`, embedded in receipts.
+    pub fn version_tag(&self) -> String {
+        let commit: String = self.git_commit.chars().take(7).collect();
+        format!("{}+{}", self.version, commit)
+    }
+}
@@ -0,0 +1,179 @@
+//! Embedding of the facilitator in another application.
+//!
+//! The `x402-rs` binary wires the facilitator from the environment and serves it on its own listener.
+//! Applications with an existing axum server can instead build one in code with [`FacilitatorBuilder`] and
+//! mount its [`Router`] under a sub-path:
+//!
+//! ```no_run
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! use x402_rs::builder::FacilitatorBuilder;
+//! use x402_rs::network::Network;
+//!
+//! let embedded = FacilitatorBuilder::new()
+//!     .network(Network::BaseSepolia)
+//!     .rpc("https://sepolia.base.org")
+//!     .signer("0x…")
+//!     .build()
+//!     .await?;
+//! let app = axum::Router::<()>::new().nest("/x402", embedded.router);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The router serves `/verify`, `/settle` and `/supported`. The [`Facilitator`](crate::facilitator::Facilitator)
+//! is returned too, to verify and settle payments in-process. Settings not covered by the builder, such as the signer type, are still
+//! read from the environment, as for the binary.
+
+use axum::Router;
+use std::sync::Arc;
+
+use crate::config::NetworkConfig;
+use crate::facilitator_local::FacilitatorLocal;
+use crate::handlers::{self, LoadShedder, RequestTimeouts, RunMode, SettleMode};
+use crate::identity::FacilitatorIdentity;
+use crate::network::Network;
+use crate::provider_cache::ProviderCache;
+
+/// Facilitator over the networks of a [`FacilitatorBuilder`].
+pub type EmbeddedFacilitatorLocal = Arc<FacilitatorLocal<Arc<ProviderCache>>>;
+
+/// Builds a facilitator, one network at a time: [`Self::rpc`] and [`Self::signer`] apply to the network
+/// last added with [`Self::network`].
+#[derive(Debug, Default)]
+pub struct FacilitatorBuilder {
+    networks: Vec<(Network, NetworkConfig)>,
+    timeouts: RequestTimeouts,
+    load_limit: Option<usize>,
+    /// First misuse of the builder, reported by [`Self::build`].
+    error: Option<String>,
+}
+
+/// A built facilitator and the routes serving it.
+pub struct EmbeddedFacilitator {
+    /// The facilitator, to verify and settle payments without going through HTTP.
+    pub facilitator: EmbeddedFacilitatorLocal,
+    /// `/verify`, `/settle` and `/supported`, to be merged or nested into the application's router.
+    pub router: Router,
+}
+
+impl FacilitatorBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `network`, to be configured by the calls that follow.
+    pub fn network(mut self, network: Network) -> Self {
+        self.networks
+            .push((network, NetworkConfig::from_rpc_url(String::new())));
+        self
+    }
+
+    /// Adds `network` with a complete configuration, as in a `CONFIG_FILE`.
+    pub fn network_config(mut self, network: Network, config: NetworkConfig) -> Self {
+        self.networks.push((network, config));
+        self
+    }
+
+    /// JSON-RPC endpoint of the current network.
+    pub fn rpc(mut self, rpc_url: impl Into<String>) -> Self {
+        let rpc_url = rpc_url.into();
+        if let Some(config) = self.current("rpc") {
+            config.rpc_url = rpc_url;
+        }
+        self
+    }
+
+    /// Adds a private key signing settlements on the current network, in the format of its family.
+    /// Keys from the environment are used for networks without any.
+    pub fn signer(mut self, key: impl Into<String>) -> Self {
+        let key = key.into();
+        if let Some(config) = self.current("signer") {
+            config.signer_keys.get_or_insert_with(Vec::new).push(key);
+        }
+        self
+    }
+
+    /// Time limits of `/verify` and `/settle`.
+    pub fn timeouts(mut self, timeouts: RequestTimeouts) -> Self {
+        self.timeouts = timeouts;
+        self
+    }
+
+    /// Most `/verify` and `/settle` requests in flight; further ones are answered `503`.
+    pub fn load_limit(mut self, limit: usize) -> Self {
+        self.load_limit = Some(limit);
+        self
+    }
+
+    fn current(&mut self, method: &str) -> Option<&mut NetworkConfig> {
+        let current = self.networks.last_mut().map(|(_, config)| config);
+        if current.is_none() && self.error.is_none() {
+            self.error = Some(format!("{method}() called before network()"));
+        }
+        current
+    }
+
+    /// Connects to every network and assembles the facilitator and its routes.
+    ///
+    /// Fails if the builder was misused, a network has no RPC URL, or a provider can not be initialized.
+    pub async fn build(self) -> Result<EmbeddedFacilitator, Box<dyn std::error::Error>> {
+        if let Some(error) = self.error {
+            return Err(error.into());
+        }
+        if self.networks.is_empty() {
+            return Err("no network configured".into());
+        }
+        if let Some((network, _)) = self
+            .networks
+            .iter()
+            .find(|(_, config)| config.rpc_url.is_empty())
+        {
+            return Err(format!("{network}: no RPC URL").into());
+        }
+        let providers = ProviderCache::from_networks(
+            self.networks
+                .iter()
+                .map(|(network, config)| (network, config)),
+        )
+        .await?;
+        let facilitator = Arc::new(FacilitatorLocal::new(Arc::new(providers)));
+        let router = handlers::routes(
+            self.timeouts,
+            LoadShedder::new(self.load_limit),
+            RunMode::Active,
+            SettleMode::Sync,
+            FacilitatorIdentity::from_env()?,
+        )
+        .with_state(facilitator.clone());
+        Ok(EmbeddedFacilitator {
+            facilitator,
+            router,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn build_reports_misuse_before_connecting() {
+        let error = FacilitatorBuilder::new()
+            .rpc("http://localhost:8545")
+            .network(Network::BaseSepolia)
+            .build()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "rpc() called before network()");
+
+        let error = FacilitatorBuilder::new()
+            .network(Network::BaseSepolia)
+            .signer("0x01")
+            .build()
+            .await
+            .err()
+            .unwrap();
+        assert_eq!(error.to_string(), "base-sepolia: no RPC URL");
+    }
+}
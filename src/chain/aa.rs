@@ -0,0 +1,675 @@
+//! x402 `erc4337` scheme: payments from ERC-4337 smart accounts, settled through a bundler.
+//!
+//! The payer's smart account signs a UserOperation (EntryPoint v0.7) whose call makes the account
+//! `transfer` the token to `payTo`. The facilitator sends no transaction itself: it hands the UserOperation
+//! to the network's bundler, which pays gas and is refunded by the account, or by a paymaster sponsoring it.
+//!
+//! - **Verify**: check network, scheme and entry point, decode the call as `execute(token, 0, transfer(payTo, amount))`,
+//!   check balance, pause and blacklist, then have the bundler validate the operation with `eth_estimateUserOperationGas`.
+//! - **Settle**: re-run the checks, `eth_sendUserOperation`, and poll `eth_getUserOperationReceipt`.
+//!
+//! Paymaster fields are covered by the account signature, so sponsorship happens before signing:
+//! with a paymaster configured, `POST /userop/sponsor` fills them in (ERC-7677 `pm_getPaymasterData`)
+//! for an unsigned UserOperation paying given requirements, and nothing else. The endpoint is unauthenticated,
+//! so it only sponsors payments in an accepted asset to one of the recipients the paymaster is configured for,
+//! and at most `PAYMASTER_SPONSOR_RATE` UserOperations of one sender per minute.
+//!
+//! Environment variables used:
+//! - `BUNDLER_URL_<NETWORK>` — bundler JSON-RPC endpoint; the scheme is not offered on the network if unset,
+//! - `PAYMASTER_URL_<NETWORK>` — ERC-7677 paymaster service, enabling `POST /userop/sponsor`,
+//! - `PAYMASTER_PAY_TO_<NETWORK>` — comma-separated recipients whose payments are sponsored; required with a
+//!   paymaster,
+//! - `PAYMASTER_SPONSOR_RATE` — sponsorships of one sender per minute (default: `10`).
+
+use alloy::primitives::{Address, B256, U256, address};
+use alloy::providers::Provider;
+use alloy::rpc::client::RpcClient;
+use alloy::sol;
+use alloy::sol_types::SolCall;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{Instrument, instrument};
+
+use crate::chain::evm::{
+    EvmChain, MetaEvmProvider, assert_accepted_asset, assert_token_transferable,
+};
+use crate::chain::{FacilitatorLocalError, NetworkProvider};
+use crate::from_env;
+use crate::provider_cache::ProviderMap;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ErrorResponse, EvmAddress, ExactPaymentPayload, MixedAddress, PaymentPayload,
+    PaymentRequirements, Scheme, UserOperation,
+};
+
+/// EntryPoint v0.7, the only one accepted.
+pub const ENTRY_POINT_V07: Address = address!("0x0000000071727De22E5E9d8BAf0edAc6f37da032");
+
+/// Delay between polls of a UserOperation receipt.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// How long to wait for a bundler to include a UserOperation.
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(120);
+/// Default bound on sponsorships of one sender per minute.
+const DEFAULT_SPONSOR_RATE: u32 = 10;
+/// Length of a sponsorship rate window, in seconds.
+const SPONSOR_WINDOW_SECS: u64 = 60;
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    interface ISmartAccount {
+        function execute(address dest, uint256 value, bytes calldata func) external;
+    }
+
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IERC20Transfer {
+        function transfer(address to, uint256 amount) external returns (bool);
+        function balanceOf(address owner) external view returns (uint256);
+    }
+}
+
+/// Bundler, and optionally paymaster, of a network.
+#[derive(Debug)]
+pub struct Bundler {
+    client: RpcClient,
+    paymaster: Option<Paymaster>,
+}
+
+/// ERC-7677 paymaster service, and the recipients whose payments it sponsors.
+#[derive(Debug)]
+struct Paymaster {
+    client: RpcClient,
+    pay_to: Vec<Address>,
+}
+
+/// Result of `eth_getUserOperationReceipt`, reduced to what settlement reports.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationReceipt {
+    pub success: bool,
+    pub receipt: UserOperationTransaction,
+}
+
+/// Transaction that included a UserOperation.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationTransaction {
+    pub transaction_hash: B256,
+}
+
+/// Paymaster fields returned by ERC-7677 `pm_getPaymasterData`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymasterData {
+    paymaster: Address,
+    paymaster_data: alloy::primitives::Bytes,
+    #[serde(default)]
+    paymaster_verification_gas_limit: Option<U256>,
+    #[serde(default)]
+    paymaster_post_op_gas_limit: Option<U256>,
+}
+
+impl Bundler {
+    /// Connects to the bundler, and to a paymaster sponsoring payments to `sponsored_pay_to`.
+    pub async fn connect(
+        bundler_url: &str,
+        paymaster_url: Option<&str>,
+        sponsored_pay_to: Vec<Address>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let client = RpcClient::builder().connect(bundler_url).await?;
+        let paymaster = match paymaster_url {
+            Some(url) => Some(Paymaster {
+                client: RpcClient::builder().connect(url).await?,
+                pay_to: sponsored_pay_to,
+            }),
+            None => None,
+        };
+        Ok(Self { client, paymaster })
+    }
+
+    /// Recipients whose payments the paymaster sponsors; `None` without a paymaster.
+    pub fn sponsored_pay_to(&self) -> Option<&[Address]> {
+        self.paymaster
+            .as_ref()
+            .map(|paymaster| paymaster.pay_to.as_slice())
+    }
+
+    /// Has the bundler validate `user_operation`, signature and gas payment included.
+    pub async fn simulate(
+        &self,
+        user_operation: &UserOperation,
+    ) -> Result<(), FacilitatorLocalError> {
+        self.client
+            .request::<_, serde_json::Value>(
+                "eth_estimateUserOperationGas",
+                (user_operation.clone(), ENTRY_POINT_V07),
+            )
+            .instrument(tracing::info_span!("eth_estimateUserOperationGas",
+                sender = %user_operation.sender,
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        Ok(())
+    }
+
+    /// Submits `user_operation` and waits until a bundle including it is mined.
+    pub async fn submit(
+        &self,
+        user_operation: &UserOperation,
+    ) -> Result<UserOperationReceipt, FacilitatorLocalError> {
+        let hash: B256 = self
+            .client
+            .request(
+                "eth_sendUserOperation",
+                (user_operation.clone(), ENTRY_POINT_V07),
+            )
+            .instrument(tracing::info_span!("eth_sendUserOperation",
+                sender = %user_operation.sender,
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        let deadline = tokio::time::Instant::now() + RECEIPT_TIMEOUT;
+        loop {
+            let receipt: Option<UserOperationReceipt> = self
+                .client
+                .request("eth_getUserOperationReceipt", (hash,))
+                .await
+                .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+            if let Some(receipt) = receipt {
+                return Ok(receipt);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(FacilitatorLocalError::ContractCall(format!(
+                    "UserOperation {hash} not included after {}s",
+                    RECEIPT_TIMEOUT.as_secs()
+                )));
+            }
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+        }
+    }
+
+    /// Fills in the paymaster fields of an unsigned `user_operation`; `None` without a paymaster.
+    pub async fn sponsor(
+        &self,
+        mut user_operation: UserOperation,
+        chain_id: u64,
+    ) -> Option<Result<UserOperation, FacilitatorLocalError>> {
+        let paymaster = self.paymaster.as_ref()?;
+        let result = paymaster
+            .client
+            .request::<_, PaymasterData>(
+                "pm_getPaymasterData",
+                (
+                    user_operation.clone(),
+                    ENTRY_POINT_V07,
+                    format!("{chain_id:#x}"),
+                    serde_json::json!({}),
+                ),
+            )
+            .instrument(tracing::info_span!("pm_getPaymasterData",
+                sender = %user_operation.sender,
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+            .map(|data| {
+                user_operation.paymaster = Some(data.paymaster);
+                user_operation.paymaster_data = Some(data.paymaster_data);
+                if data.paymaster_verification_gas_limit.is_some() {
+                    user_operation.paymaster_verification_gas_limit =
+                        data.paymaster_verification_gas_limit;
+                }
+                if data.paymaster_post_op_gas_limit.is_some() {
+                    user_operation.paymaster_post_op_gas_limit = data.paymaster_post_op_gas_limit;
+                }
+                user_operation
+            });
+        Some(result)
+    }
+}
+
+/// A validated UserOperation payment, ready to be submitted.
+#[derive(Debug, Clone)]
+pub struct UserOperationPayment {
+    /// Smart account paying.
+    pub sender: Address,
+    pub token: Address,
+    pub amount: U256,
+    pub user_operation: UserOperation,
+}
+
+/// Decodes the call of `user_operation` as a transfer of the required asset to `payTo`.
+///
+/// Returns the token and the transferred amount.
+fn assert_transfer_call(
+    user_operation: &UserOperation,
+    requirements: &PaymentRequirements,
+) -> Result<(Address, U256), FacilitatorLocalError> {
+    let payer: MixedAddress = user_operation.sender.into();
+    let execute = ISmartAccount::executeCall::abi_decode(&user_operation.call_data)
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+    let transfer = IERC20Transfer::transferCall::abi_decode(&execute.func)
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+    let asset: EvmAddress = requirements
+        .asset
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    if execute.dest != asset.0 || !execute.value.is_zero() {
+        return Err(FacilitatorLocalError::DecodingError(format!(
+            "UserOperation must call transfer on {asset} without value"
+        )));
+    }
+    let pay_to: EvmAddress = requirements
+        .pay_to
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    if transfer.to != pay_to.0 {
+        return Err(FacilitatorLocalError::ReceiverMismatch(
+            payer,
+            transfer.to.to_string(),
+            pay_to.to_string(),
+        ));
+    }
+    if transfer.amount < requirements.max_amount_required.0 {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+    Ok((asset.0, transfer.amount))
+}
+
+/// Whether the paymaster sponsoring payments to `sponsored_pay_to` sponsors those paying `requirements`.
+fn is_sponsored(sponsored_pay_to: &[Address], requirements: &PaymentRequirements) -> bool {
+    Address::try_from(requirements.pay_to.clone())
+        .is_ok_and(|pay_to| sponsored_pay_to.contains(&pay_to))
+}
+
+/// Runs all preconditions of a UserOperation payment:
+/// - UserOperation payload for EntryPoint v0.7, matching scheme and network.
+/// - Call transferring at least `maxAmountRequired` of the asset to `payTo`.
+/// - Balance of the smart account covers the amount.
+/// - Token not paused, account and recipient not blacklisted.
+#[instrument(skip_all, err)]
+pub async fn assert_valid_payment<P: Provider>(
+    provider: &P,
+    chain: &EvmChain,
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<UserOperationPayment, FacilitatorLocalError> {
+    let user_operation_payload = match &payload.payload {
+        ExactPaymentPayload::UserOperation(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                None,
+                Scheme::Erc4337,
+                payload.scheme,
+            ));
+        }
+    };
+    let user_operation = &user_operation_payload.user_operation;
+    let sender = user_operation.sender;
+    let payer: MixedAddress = sender.into();
+    for network in [payload.network, requirements.network] {
+        if network != chain.network() {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                Some(payer),
+                chain.network(),
+                network,
+            ));
+        }
+    }
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != Scheme::Erc4337 {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                Some(payer),
+                Scheme::Erc4337,
+                scheme,
+            ));
+        }
+    }
+    if user_operation_payload.entry_point.0 != ENTRY_POINT_V07 {
+        return Err(FacilitatorLocalError::InvalidAddress(format!(
+            "Entry point {} is not EntryPoint v0.7 {ENTRY_POINT_V07}",
+            user_operation_payload.entry_point
+        )));
+    }
+    let (token, amount) = assert_transfer_call(user_operation, requirements)?;
+    let pay_to: EvmAddress = requirements
+        .pay_to
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let balance = IERC20Transfer::new(token, provider)
+        .balanceOf(sender)
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!(
+            "fetch_token_balance",
+            token_contract = %token,
+            sender = %sender,
+            otel.kind = "client"
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if balance < amount {
+        return Err(FacilitatorLocalError::InsufficientFunds(payer));
+    }
+    assert_token_transferable(provider, token, sender, pay_to.0).await?;
+    Ok(UserOperationPayment {
+        sender,
+        token,
+        amount,
+        user_operation: user_operation.clone(),
+    })
+}
+
+/// Body of `POST /userop/sponsor`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorRequest {
+    pub payment_requirements: PaymentRequirements,
+    /// Unsigned UserOperation paying `paymentRequirements`.
+    pub user_operation: UserOperation,
+}
+
+/// Answer of `POST /userop/sponsor`: the UserOperation to sign, paymaster fields filled in.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SponsorResponse {
+    pub user_operation: UserOperation,
+}
+
+/// Sponsorships handed out per sender, bounded to a rate per minute.
+///
+/// Cheap to clone: all clones share the same windows.
+#[derive(Debug, Clone)]
+pub struct SponsorLimiter {
+    rate: u32,
+    /// Start of the current window of each sender, and sponsorships handed out to it in the window.
+    windows: Arc<DashMap<Address, (UnixTimestamp, u32)>>,
+}
+
+impl SponsorLimiter {
+    /// At most `rate` sponsorships of one sender per minute.
+    pub fn new(rate: u32) -> Self {
+        Self {
+            rate,
+            windows: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Reads the rate from `PAYMASTER_SPONSOR_RATE`.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let rate = match std::env::var(from_env::ENV_PAYMASTER_SPONSOR_RATE) {
+            Ok(rate) => rate
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_PAYMASTER_SPONSOR_RATE))?,
+            Err(_) => DEFAULT_SPONSOR_RATE,
+        };
+        Ok(Self::new(rate))
+    }
+
+    /// Counts a sponsorship of `sender` at `now`; `false` if over the rate, dropping ended windows on the way.
+    fn allow(&self, sender: Address, now: UnixTimestamp) -> bool {
+        self.windows
+            .retain(|_, window| window.0 + SPONSOR_WINDOW_SECS > now);
+        let mut window = self.windows.entry(sender).or_insert((now, 0));
+        if window.1 >= self.rate {
+            return false;
+        }
+        window.1 += 1;
+        true
+    }
+}
+
+/// State of [`routes`]: the providers of the networks, and the sponsorships handed out.
+#[derive(Debug, Clone)]
+pub struct Sponsorship<P> {
+    providers: P,
+    limiter: SponsorLimiter,
+}
+
+impl<P> Sponsorship<P> {
+    pub fn new(providers: P, limiter: SponsorLimiter) -> Self {
+        Self { providers, limiter }
+    }
+}
+
+/// Routes sponsoring UserOperations with the configured paymasters.
+pub fn routes<P>() -> Router<Sponsorship<P>>
+where
+    P: ProviderMap<Value = NetworkProvider> + Clone + Send + Sync + 'static,
+{
+    Router::new().route("/userop/sponsor", post(post_sponsor::<P>))
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
+
+/// `POST /userop/sponsor`: Adds paymaster sponsorship to a UserOperation paying the given requirements.
+#[instrument(skip_all, fields(network = %request.payment_requirements.network))]
+pub async fn post_sponsor<P>(
+    State(sponsorship): State<Sponsorship<P>>,
+    Json(request): Json<SponsorRequest>,
+) -> Response
+where
+    P: ProviderMap<Value = NetworkProvider>,
+{
+    let network = request.payment_requirements.network;
+    let Some(NetworkProvider::Evm(provider)) = sponsorship.providers.by_network(network) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            format!("{network} is not configured"),
+        );
+    };
+    let Some(bundler) = provider.bundler() else {
+        return error_response(StatusCode::NOT_FOUND, format!("No bundler on {network}"));
+    };
+    if request.payment_requirements.scheme != Scheme::Erc4337 {
+        return error_response(
+            StatusCode::BAD_REQUEST,
+            format!("Scheme must be {}", Scheme::Erc4337),
+        );
+    }
+    let Some(sponsored_pay_to) = bundler.sponsored_pay_to() else {
+        return error_response(StatusCode::NOT_FOUND, format!("No paymaster on {network}"));
+    };
+    if let Err(e) = assert_accepted_asset(
+        provider.accepted_assets(),
+        provider.quarantine(),
+        &request.payment_requirements,
+    ) {
+        return error_response(StatusCode::BAD_REQUEST, e.to_string());
+    }
+    if let Err(e) = assert_transfer_call(&request.user_operation, &request.payment_requirements) {
+        return error_response(StatusCode::BAD_REQUEST, e.to_string());
+    }
+    if !is_sponsored(sponsored_pay_to, &request.payment_requirements) {
+        return error_response(
+            StatusCode::FORBIDDEN,
+            format!(
+                "Payments to {} are not sponsored on {network}",
+                request.payment_requirements.pay_to
+            ),
+        );
+    }
+    let now = match UnixTimestamp::try_now() {
+        Ok(now) => now,
+        Err(e) => return error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    };
+    if !sponsorship
+        .limiter
+        .allow(request.user_operation.sender, now)
+    {
+        tracing::info!(monotonic_counter.x402.userop.sponsor_rate_limited = 1);
+        return error_response(
+            StatusCode::TOO_MANY_REQUESTS,
+            "Too many sponsorships for this sender".to_string(),
+        );
+    }
+    match bundler
+        .sponsor(request.user_operation, provider.chain().chain_id)
+        .await
+    {
+        Some(Ok(user_operation)) => {
+            tracing::info!(monotonic_counter.x402.userop.sponsored = 1);
+            (StatusCode::OK, Json(SponsorResponse { user_operation })).into_response()
+        }
+        Some(Err(e)) => error_response(StatusCode::BAD_GATEWAY, e.to_string()),
+        None => error_response(StatusCode::NOT_FOUND, format!("No paymaster on {network}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+    use crate::types::TokenAmount;
+    use url::Url;
+
+    fn token() -> Address {
+        Address::repeat_byte(1)
+    }
+
+    fn pay_to() -> Address {
+        Address::repeat_byte(2)
+    }
+
+    fn requirements() -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: Scheme::Erc4337,
+            network: Network::BaseSepolia,
+            max_amount_required: TokenAmount::from(1000u64),
+            resource: Url::parse("https://example.com/resource").unwrap(),
+            description: String::new(),
+            mime_type: "application/json".to_string(),
+            output_schema: None,
+            pay_to: pay_to().into(),
+            max_timeout_seconds: 300,
+            asset: token().into(),
+            extra: None,
+            payment_id: None,
+            price_usd: None,
+            facilitator_fee: None,
+        }
+    }
+
+    /// UserOperation calling `execute(dest, value, func)`.
+    fn user_operation(dest: Address, value: u64, func: Vec<u8>) -> UserOperation {
+        let call_data = ISmartAccount::executeCall {
+            dest,
+            value: U256::from(value),
+            func: func.into(),
+        }
+        .abi_encode();
+        UserOperation {
+            sender: Address::repeat_byte(3),
+            nonce: U256::ZERO,
+            factory: None,
+            factory_data: None,
+            call_data: call_data.into(),
+            call_gas_limit: U256::ZERO,
+            verification_gas_limit: U256::ZERO,
+            pre_verification_gas: U256::ZERO,
+            max_fee_per_gas: U256::ZERO,
+            max_priority_fee_per_gas: U256::ZERO,
+            paymaster: None,
+            paymaster_verification_gas_limit: None,
+            paymaster_post_op_gas_limit: None,
+            paymaster_data: None,
+            signature: Default::default(),
+        }
+    }
+
+    fn transfer(to: Address, amount: u64) -> Vec<u8> {
+        IERC20Transfer::transferCall {
+            to,
+            amount: U256::from(amount),
+        }
+        .abi_encode()
+    }
+
+    #[test]
+    fn decodes_transfer_calls() {
+        let user_operation = user_operation(token(), 0, transfer(pay_to(), 1500));
+        let (asset, amount) = assert_transfer_call(&user_operation, &requirements()).unwrap();
+        assert_eq!(asset, token());
+        assert_eq!(amount, U256::from(1500));
+    }
+
+    #[test]
+    fn rejects_other_calls() {
+        let requirements = requirements();
+        let other_token = user_operation(Address::repeat_byte(9), 0, transfer(pay_to(), 1000));
+        assert!(matches!(
+            assert_transfer_call(&other_token, &requirements),
+            Err(FacilitatorLocalError::DecodingError(_))
+        ));
+        let with_value = user_operation(token(), 1, transfer(pay_to(), 1000));
+        assert!(matches!(
+            assert_transfer_call(&with_value, &requirements),
+            Err(FacilitatorLocalError::DecodingError(_))
+        ));
+        let other_receiver = user_operation(token(), 0, transfer(Address::repeat_byte(9), 1000));
+        assert!(matches!(
+            assert_transfer_call(&other_receiver, &requirements),
+            Err(FacilitatorLocalError::ReceiverMismatch(..))
+        ));
+        let too_little = user_operation(token(), 0, transfer(pay_to(), 999));
+        assert!(matches!(
+            assert_transfer_call(&too_little, &requirements),
+            Err(FacilitatorLocalError::InsufficientValue(_))
+        ));
+        let mut not_execute = user_operation(token(), 0, transfer(pay_to(), 1000));
+        not_execute.call_data = transfer(pay_to(), 1000).into();
+        assert!(matches!(
+            assert_transfer_call(&not_execute, &requirements),
+            Err(FacilitatorLocalError::DecodingError(_))
+        ));
+        let not_transfer = user_operation(token(), 0, vec![0xde, 0xad, 0xbe, 0xef]);
+        assert!(matches!(
+            assert_transfer_call(&not_transfer, &requirements),
+            Err(FacilitatorLocalError::DecodingError(_))
+        ));
+    }
+
+    #[test]
+    fn sponsors_configured_recipients_only() {
+        let requirements = requirements();
+        assert!(is_sponsored(&[pay_to()], &requirements));
+        assert!(!is_sponsored(&[Address::repeat_byte(9)], &requirements));
+        assert!(!is_sponsored(&[], &requirements));
+    }
+
+    #[test]
+    fn sponsorships_are_rate_limited_per_sender() {
+        let limiter = SponsorLimiter::new(2);
+        let (a, b) = (Address::repeat_byte(1), Address::repeat_byte(2));
+        let now = UnixTimestamp(1_000);
+        assert!(limiter.allow(a, now));
+        assert!(limiter.allow(a, now + 1));
+        assert!(!limiter.allow(a, now + 59));
+        assert!(limiter.allow(b, now + 59));
+        assert!(limiter.allow(a, now + SPONSOR_WINDOW_SECS));
+    }
+}
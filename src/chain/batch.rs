@@ -0,0 +1,207 @@
+//! Batch settlement of ERC-3009 payments through Multicall3.
+//!
+//! Under load, many payments on the same network are settled within milliseconds of each other, each
+//! paying the base transaction cost and waiting for its own inclusion. A [`SettlementBatcher`] holds
+//! `transferWithAuthorization` calls for a short window and sends them together as a single Multicall3
+//! `aggregate3` transaction, each call allowed to fail on its own.
+//!
+//! The first settlement of a window leads the batch: it waits for the window to end, or for the batch to
+//! be full, sends the transaction, and hands every other settlement its own outcome. A transfer succeeded
+//! if the token emitted `AuthorizationUsed` for its authorizer and nonce in the receipt.
+//!
+//! Environment variables used:
+//! - `SETTLEMENT_BATCH_WINDOW_MS` — how long settlements are held to be batched (default: `0`, batching disabled).
+
+use alloy::primitives::{Address, B256, Bytes};
+use alloy::providers::bindings::IMulticall3;
+use alloy::rpc::types::TransactionReceipt;
+use alloy::sol_types::SolCall;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::{Notify, oneshot};
+
+use crate::chain::FacilitatorLocalError;
+use crate::chain::evm::USDC;
+use crate::from_env;
+use crate::types::BatchPosition;
+
+/// Largest number of transfers sent in one transaction; a full batch is sent before its window ends.
+const MAX_BATCH_SIZE: usize = 32;
+
+/// A `transferWithAuthorization` call waiting for its batch.
+#[derive(Debug, Clone)]
+pub struct BatchedTransfer {
+    /// Token contract the call is sent to.
+    pub token: Address,
+    /// Encoded `transferWithAuthorization` call, settlement tag included.
+    pub calldata: Bytes,
+    /// Payer who signed the authorization.
+    pub authorizer: Address,
+    /// Nonce of the authorization.
+    pub nonce: B256,
+}
+
+impl BatchedTransfer {
+    /// Whether the token consumed this authorization in the batch transaction.
+    fn succeeded(&self, receipt: &TransactionReceipt) -> bool {
+        receipt.status()
+            && receipt
+                .inner
+                .logs()
+                .iter()
+                .filter(|log| log.address() == self.token)
+                .filter_map(|log| log.log_decode::<USDC::AuthorizationUsed>().ok())
+                .any(|event| {
+                    event.inner.data.authorizer == self.authorizer
+                        && event.inner.data.nonce == self.nonce
+                })
+    }
+}
+
+/// Outcome of a transfer settled in a batch.
+#[derive(Debug, Clone)]
+pub struct BatchOutcome {
+    /// Receipt of the batch transaction.
+    pub receipt: TransactionReceipt,
+    /// Whether this transfer went through; others in the batch may have failed independently.
+    pub success: bool,
+    pub position: BatchPosition,
+}
+
+struct Pending {
+    transfer: BatchedTransfer,
+    reply: oneshot::Sender<Result<BatchOutcome, String>>,
+}
+
+/// Groups settlements arriving within a window into Multicall3 transactions.
+pub struct SettlementBatcher {
+    window: Duration,
+    pending: Mutex<Vec<Pending>>,
+    full: Notify,
+}
+
+impl std::fmt::Debug for SettlementBatcher {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SettlementBatcher")
+            .field("window", &self.window)
+            .finish_non_exhaustive()
+    }
+}
+
+impl SettlementBatcher {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            pending: Mutex::new(Vec::new()),
+            full: Notify::new(),
+        }
+    }
+
+    /// Batcher with the given window in milliseconds, or from `SETTLEMENT_BATCH_WINDOW_MS`; `None` if disabled.
+    pub fn from_window_or_env(window_ms: Option<u64>) -> Result<Option<Self>, String> {
+        let window_ms = match (
+            window_ms,
+            std::env::var(from_env::ENV_SETTLEMENT_BATCH_WINDOW_MS),
+        ) {
+            (Some(window_ms), _) => window_ms,
+            (None, Ok(value)) => value
+                .parse::<u64>()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_SETTLEMENT_BATCH_WINDOW_MS))?,
+            (None, Err(_)) => 0,
+        };
+        Ok((window_ms > 0).then(|| Self::new(Duration::from_millis(window_ms))))
+    }
+
+    /// Settles `transfer` in the next batch, `send` sending the Multicall3 calldata of the batch it leads.
+    ///
+    /// Only the leader's `send` is called; the future of every other settlement resolves when the leader
+    /// hands out the outcomes. A leader dropped before that fails the whole batch.
+    pub async fn settle<F, Fut>(
+        &self,
+        transfer: BatchedTransfer,
+        send: F,
+    ) -> Result<BatchOutcome, FacilitatorLocalError>
+    where
+        F: FnOnce(Bytes) -> Fut,
+        Fut: Future<Output = Result<TransactionReceipt, FacilitatorLocalError>>,
+    {
+        let (reply, outcome) = oneshot::channel();
+        let leads = {
+            let mut pending = self.pending.lock().expect("batch queue poisoned");
+            pending.push(Pending { transfer, reply });
+            if pending.len() >= MAX_BATCH_SIZE {
+                self.full.notify_waiters();
+            }
+            pending.len() == 1
+        };
+        if leads {
+            tokio::select! {
+                _ = tokio::time::sleep(self.window) => {}
+                _ = self.full.notified() => {}
+            }
+            let batch = std::mem::take(&mut *self.pending.lock().expect("batch queue poisoned"));
+            let calls = batch
+                .iter()
+                .map(|pending| IMulticall3::Call3 {
+                    allowFailure: true,
+                    target: pending.transfer.token,
+                    callData: pending.transfer.calldata.clone(),
+                })
+                .collect();
+            let calldata = IMulticall3::aggregate3Call { calls }.abi_encode().into();
+            let size = batch.len() as u32;
+            tracing::info!(
+                monotonic_counter.x402.settle.batches = 1,
+                histogram.x402.settle.batch_size = size as u64,
+            );
+            let result = send(calldata).await.map_err(|e| e.to_string());
+            for (index, pending) in batch.into_iter().enumerate() {
+                let outcome = result
+                    .as_ref()
+                    .map_err(Clone::clone)
+                    .map(|receipt| BatchOutcome {
+                        receipt: receipt.clone(),
+                        success: pending.transfer.succeeded(receipt),
+                        position: BatchPosition {
+                            index: index as u32,
+                            size,
+                        },
+                    });
+                let _ = pending.reply.send(outcome);
+            }
+        }
+        outcome
+            .await
+            .map_err(|_| {
+                FacilitatorLocalError::ContractCall("Settlement batch abandoned".to_string())
+            })?
+            .map_err(FacilitatorLocalError::ContractCall)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[tokio::test]
+    async fn concurrent_settlements_share_one_transaction() {
+        let batcher = SettlementBatcher::new(Duration::from_millis(50));
+        let sends = AtomicUsize::new(0);
+        let transfer = BatchedTransfer {
+            token: Address::ZERO,
+            calldata: Bytes::new(),
+            authorizer: Address::ZERO,
+            nonce: B256::ZERO,
+        };
+        let settle = || {
+            batcher.settle(transfer.clone(), |_| async {
+                sends.fetch_add(1, Ordering::SeqCst);
+                Err(FacilitatorLocalError::ContractCall("reverted".to_string()))
+            })
+        };
+        let (first, second) = tokio::join!(settle(), settle());
+        assert!(first.is_err() && second.is_err());
+        assert_eq!(sends.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,103 @@
+//! Protection against reading state from a lagging RPC backend.
+//!
+//! An RPC URL is often a load balancer over several nodes, which are not always at the same height.
+//! Right after a payer funds their wallet, a lagging node still reports the old balance, and
+//! verification flakily fails with "insufficient funds".
+//!
+//! A [`BlockTracker`] remembers the highest block number any backend reported for a network.
+//! Verification reads state at that block instead of `latest`; a node that has not reached it yet
+//! fails the read, which is then retried with [`with_lag_retries`] until a node that has the block answers.
+//!
+//! Operators preferring reorg safety over freshness can choose another [`BlockTag`] per network instead.
+
+use alloy::rpc::types::BlockId;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Attempts of a pinned read before giving up, the first one included.
+const LAG_ATTEMPTS: u32 = 3;
+/// Delay between attempts of a pinned read.
+const LAG_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Block payment state (balances, authorizations) is read at.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BlockTag {
+    /// Most recent block, never older than the highest one seen through a [`BlockTracker`].
+    #[default]
+    Latest,
+    /// Most recent block considered safe from reorgs by the consensus layer.
+    Safe,
+    /// Most recent finalized block.
+    Finalized,
+}
+
+impl BlockTag {
+    /// The block to read at; `highest` is the highest block seen, only used for [`BlockTag::Latest`].
+    pub fn block_id(&self, highest: Option<u64>) -> BlockId {
+        match (self, highest) {
+            (BlockTag::Latest, Some(highest)) => BlockId::number(highest),
+            (BlockTag::Latest, None) => BlockId::latest(),
+            (BlockTag::Safe, _) => BlockId::safe(),
+            (BlockTag::Finalized, _) => BlockId::finalized(),
+        }
+    }
+}
+
+impl FromStr for BlockTag {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "latest" => Ok(BlockTag::Latest),
+            "safe" => Ok(BlockTag::Safe),
+            "finalized" => Ok(BlockTag::Finalized),
+            _ => Err(format!(
+                "Unknown block tag {s}, expected latest, safe or finalized"
+            )),
+        }
+    }
+}
+
+/// Highest block number seen on a network.
+#[derive(Debug, Default)]
+pub struct BlockTracker {
+    highest: AtomicU64,
+}
+
+impl BlockTracker {
+    /// Records a block number reported by a backend and returns the highest one seen so far.
+    pub fn observe(&self, block: u64) -> u64 {
+        let previous = self.highest.fetch_max(block, Ordering::Relaxed);
+        if previous > block {
+            tracing::info!(
+                monotonic_counter.x402.rpc.lagging_reads = 1,
+                block,
+                highest = previous,
+                "RPC backend behind highest seen block"
+            );
+        }
+        previous.max(block)
+    }
+}
+
+/// Runs `read` until it succeeds, at most a few times, waiting for lagging backends to catch up.
+pub async fn with_lag_retries<T, E, F, Fut>(mut read: F) -> Result<T, E>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, E>>,
+{
+    let mut attempt = 1;
+    loop {
+        match read().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt >= LAG_ATTEMPTS => return Err(e),
+            Err(_) => {
+                attempt += 1;
+                tokio::time::sleep(LAG_RETRY_DELAY).await;
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! Cancellation of unsettled ERC-3009 authorizations.
+//!
+//! A payer who signed a `transferWithAuthorization` they no longer want honored can void it by signing an
+//! ERC-3009 `CancelAuthorization(address authorizer,bytes32 nonce)` for its nonce, under the token's EIP-712
+//! domain. `POST /cancel` submits it with `cancelAuthorization`, the facilitator paying gas:
+//! - the token must be accepted on the network;
+//! - the nonce must still be unused: authorizations already settled, or canceled, are answered `409 Conflict`;
+//! - the call is simulated first, so an invalid signature is answered `400 Bad Request` without sending anything.
+
+use alloy::primitives::{B256, Bytes};
+use alloy::sol;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use tracing::{Instrument, instrument};
+
+use crate::chain::evm::{EvmProvider, MetaEvmProvider, MetaTransaction};
+use crate::chain::revert;
+use crate::chain::{FacilitatorLocalError, NetworkProvider};
+use crate::network::Network;
+use crate::provider_cache::ProviderMap;
+use crate::types::{
+    ErrorResponse, EvmAddress, EvmSignature, HexEncodedNonce, MixedAddress, TransactionHash,
+};
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IERC3009Cancel {
+        function authorizationState(address authorizer, bytes32 nonce) external view returns (bool);
+        function cancelAuthorization(address authorizer, bytes32 nonce, bytes signature) external;
+    }
+}
+
+/// Body of `POST /cancel`: a signed ERC-3009 cancellation.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelRequest {
+    pub network: Network,
+    /// Token the authorization was signed for.
+    pub asset: EvmAddress,
+    /// Payer who signed the authorization and its cancellation.
+    pub authorizer: EvmAddress,
+    pub nonce: HexEncodedNonce,
+    /// Signature of the `CancelAuthorization` by the authorizer.
+    pub signature: EvmSignature,
+}
+
+/// Answer of `POST /cancel`: the transaction canceling the authorization.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelResponse {
+    pub network: Network,
+    pub transaction: TransactionHash,
+}
+
+/// Route canceling authorizations on the configured EVM networks.
+pub fn routes<P>() -> Router<P>
+where
+    P: ProviderMap<Value = NetworkProvider> + Clone + Send + Sync + 'static,
+{
+    Router::new().route("/cancel", post(post_cancel::<P>))
+}
+
+fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error })).into_response()
+}
+
+/// `POST /cancel`: Submits a signed `cancelAuthorization` for an unused ERC-3009 nonce.
+#[instrument(skip_all, fields(network = %request.network, authorizer = %request.authorizer))]
+pub async fn post_cancel<P>(
+    State(providers): State<P>,
+    Json(request): Json<CancelRequest>,
+) -> Response
+where
+    P: ProviderMap<Value = NetworkProvider>,
+{
+    let network = request.network;
+    let Some(NetworkProvider::Evm(provider)) = providers.by_network(network) else {
+        return error_response(
+            StatusCode::NOT_FOUND,
+            format!("{network} is not configured"),
+        );
+    };
+    match cancel(provider, &request).await {
+        Ok(tx_hash) => {
+            tracing::info!(monotonic_counter.x402.authorization.canceled = 1);
+            let response = CancelResponse {
+                network,
+                transaction: TransactionHash::Evm(tx_hash.0),
+            };
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => {
+            let status = match e {
+                FacilitatorLocalError::NonceReused(_) => StatusCode::CONFLICT,
+                FacilitatorLocalError::UnsupportedAsset(_)
+                | FacilitatorLocalError::InvalidSignature(..)
+                | FacilitatorLocalError::Reverted(..) => StatusCode::BAD_REQUEST,
+                _ => StatusCode::BAD_GATEWAY,
+            };
+            error_response(status, e.to_string())
+        }
+    }
+}
+
+/// Checks that the authorization is still unused, simulates its cancellation, then sends it.
+async fn cancel(
+    provider: &EvmProvider,
+    request: &CancelRequest,
+) -> Result<B256, FacilitatorLocalError> {
+    let authorizer: MixedAddress = request.authorizer.into();
+    let asset = request.asset.0;
+    if let Some(accepted) = provider.accepted_assets()
+        && !accepted.contains(&asset)
+    {
+        return Err(FacilitatorLocalError::UnsupportedAsset(
+            request.asset.into(),
+        ));
+    }
+    let token = IERC3009Cancel::new(asset, provider.inner());
+    let nonce = B256::from(request.nonce.0);
+    let used = token
+        .authorizationState(request.authorizer.0, nonce)
+        .call()
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if used {
+        // ERC-3009 does not tell settled and canceled nonces apart.
+        return Err(FacilitatorLocalError::NonceReused(authorizer));
+    }
+    let cancel_call = token.cancelAuthorization(
+        request.authorizer.0,
+        nonce,
+        Bytes::from(request.signature.0.clone()),
+    );
+    cancel_call
+        .call()
+        .await
+        .map_err(|e| revert::contract_error(Some(authorizer.clone()), e))?;
+    let receipt = provider
+        .send_transaction(MetaTransaction {
+            to: asset,
+            calldata: cancel_call.calldata().clone(),
+            confirmations: provider.confirmations(),
+            sender: None,
+        })
+        .instrument(tracing::info_span!("call_cancelAuthorization",
+            authorizer = %request.authorizer,
+            nonce = %nonce,
+            token_contract = %asset,
+            otel.kind = "client",
+        ))
+        .await?;
+    if !receipt.status() {
+        return Err(FacilitatorLocalError::ContractCall(format!(
+            "cancelAuthorization failed in {}",
+            receipt.transaction_hash
+        )));
+    }
+    Ok(receipt.transaction_hash)
+}
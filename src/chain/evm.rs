@@ -10,16 +10,22 @@
 //! - Target tokens implement ERC-3009 and support ERC-1271 for contract signers.
 //! - The validator contract exists at [`VALIDATOR_ADDRESS`] on supported chains.
 //!
+//! Payments of the `permit2`, `permit` and `native` schemes are delegated to [`crate::chain::permit2`],
+//! [`crate::chain::permit`] and [`crate::chain::native`].
+//!
 //! Invariants:
 //! - Settlement is atomic: deploy (if needed) + transfer happen in a single user flow.
 //! - Verification does not persist state.
 
+use alloy::consensus::Transaction as _;
 use alloy::contract::SolCallBuilder;
 use alloy::dyn_abi::SolType;
+use alloy::eips::eip2718::Encodable2718;
 use alloy::network::{
     Ethereum as AlloyEthereum, EthereumWallet, NetworkWallet, TransactionBuilder,
 };
-use alloy::primitives::{Address, Bytes, FixedBytes, U256, address};
+use alloy::primitives::utils::format_units;
+use alloy::primitives::{Address, B256, Bytes, FixedBytes, U256, address, keccak256};
 use alloy::providers::ProviderBuilder;
 use alloy::providers::bindings::IMulticall3;
 use alloy::providers::fillers::NonceManager;
@@ -27,29 +33,58 @@ use alloy::providers::fillers::{
     BlobGasFiller, ChainIdFiller, FillProvider, GasFiller, JoinFill, NonceFiller, WalletFiller,
 };
 use alloy::providers::{
-    Identity, MULTICALL3_ADDRESS, MulticallItem, Provider, RootProvider, WalletProvider,
+    Identity, MULTICALL3_ADDRESS, MulticallItem, PendingTransactionBuilder, Provider, RootProvider,
+    WalletProvider,
 };
 use alloy::rpc::client::RpcClient;
-use alloy::rpc::types::{TransactionReceipt, TransactionRequest};
-use alloy::sol_types::{Eip712Domain, SolCall, SolStruct, eip712_domain};
+use alloy::rpc::types::{BlockId, BlockNumberOrTag, TransactionReceipt, TransactionRequest};
+use alloy::sol_types::{
+    Eip712Domain, SolCall, SolEvent, SolStruct, decode_revert_reason, eip712_domain,
+};
+use alloy::transports::TransportResult;
 use alloy::{hex, sol};
 use async_trait::async_trait;
 use dashmap::DashMap;
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
 use tokio::sync::Mutex;
 use tracing::{Instrument, instrument};
 use tracing_core::Level;
 
-use crate::chain::{FacilitatorLocalError, FromEnvByNetworkBuild, NetworkProviderOps};
+use crate::chain::aa::{self, Bundler};
+use crate::chain::batch::{BatchedTransfer, SettlementBatcher};
+use crate::chain::block_tracker::{BlockTag, BlockTracker, with_lag_retries};
+use crate::chain::failover::{self, RpcFailover};
+use crate::chain::fee_on_transfer::FeeOnTransfer;
+use crate::chain::fee_strategy::{FeeStrategy, GasPrice};
+use crate::chain::gas_oracle::{GasOracle, GasOracleConfig};
+use crate::chain::heads::{BlockTicker, HeadSubscription};
+use crate::chain::kms;
+use crate::chain::recovery::{JournalEntry, TxJournal};
+use crate::chain::relayer::Relayers;
+use crate::chain::reorg::{ReorgOutcome, ReorgWatch, WatchedSettlement};
+use crate::chain::revert::{self, Revert};
+use crate::chain::rpc_budget::{RpcBudget, RpcBudgetLayer};
+use crate::chain::rpc_retry::{RetryPolicy, RpcRetryLayer};
+use crate::chain::signers::SignerPool;
+use crate::chain::speed_up::{PendingSettlements, SpeedUp};
+use crate::chain::token_probe::{self, Probe, Quarantine};
+use crate::chain::warm_cache::WarmCache;
+use crate::chain::{
+    FacilitatorLocalError, FromConfigByNetworkBuild, FromEnvByNetworkBuild, NetworkProviderOps,
+};
+use crate::chain::{native, permit, permit2, stream};
+use crate::config::NetworkConfig;
+use crate::dry_run;
 use crate::facilitator::Facilitator;
 use crate::from_env;
-use crate::network::{Network, USDCDeployment};
+use crate::network::{CustomNetwork, NativeToken, Network, USDCDeployment};
+use crate::outbound::OutboundPolicy;
 use crate::timestamp::UnixTimestamp;
 use crate::types::{
-    EvmAddress, EvmSignature, ExactPaymentPayload, FacilitatorErrorReason, HexEncodedNonce,
-    MixedAddress, PaymentPayload, PaymentRequirements, Scheme, SettleRequest, SettleResponse,
-    SupportedPaymentKind, SupportedPaymentKindsResponse, TokenAmount, TransactionHash,
+    Erc3009Authorization, EvmAddress, EvmSignature, ExactPaymentPayload, FacilitatorErrorReason,
+    HexEncodedNonce, MixedAddress, PartialSettlement, PaymentPayload, PaymentRequirements,
+    ReceiveWithAuthorization, Scheme, SettleRequest, SettleResponse, SupportedPaymentKind,
+    SupportedPaymentKindExtra, SupportedPaymentKindsResponse, TokenAmount, TransactionHash,
     TransferWithAuthorization, VerifyRequest, VerifyResponse, X402Version,
 };
 
@@ -71,11 +106,25 @@ sol! {
     "abi/Validator6492.json"
 }
 
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface ISafe {
+        function execTransactionFromModule(address to, uint256 value, bytes data, uint8 operation) external returns (bool success);
+        function isModuleEnabled(address module) external view returns (bool);
+        event ExecutionFromModuleFailure(address indexed module);
+    }
+}
+
 /// Signature verifier for EIP-6492, EIP-1271, EOA, universally deployed on the supported EVM chains
 /// If absent on a target chain, verification will fail; you should deploy the validator there.
 const VALIDATOR_ADDRESS: alloy::primitives::Address =
     address!("0xdAcD51A54883eb67D95FAEb2BBfdC4a9a6BD2a3B");
 
+/// Interval between receipt checks of a settlement that may be replaced, see [`crate::chain::speed_up`].
+const PENDING_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+
 /// Combined filler type for gas, blob gas, nonce, and chain ID.
 type InnerFiller = JoinFill<
     GasFiller,
@@ -112,6 +161,26 @@ impl EvmChain {
     pub fn network(&self) -> Network {
         self.network
     }
+
+    /// How the chain charges for publishing its transactions on L1.
+    pub fn l1_fee_model(&self) -> L1FeeModel {
+        match self.network {
+            Network::Base | Network::BaseSepolia | Network::Optimism => L1FeeModel::OpStack,
+            Network::Arbitrum => L1FeeModel::Arbitrum,
+            _ => L1FeeModel::None,
+        }
+    }
+}
+
+/// How a rollup charges for publishing its transactions on L1, on top of L2 execution.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum L1FeeModel {
+    /// Not a rollup: `gasUsed * effectiveGasPrice` is the whole cost.
+    None,
+    /// OP Stack: an extra L1 data fee, reported as `l1Fee` in the receipt, is charged besides L2 gas.
+    OpStack,
+    /// Arbitrum: L1 costs are included in `gasUsed`; the receipt reports that share as `gasUsedForL1`.
+    Arbitrum,
 }
 
 impl TryFrom<Network> for EvmChain {
@@ -134,6 +203,15 @@ impl TryFrom<Network> for EvmChain {
             Network::Polygon => Ok(EvmChain::new(value, 137)),
             Network::Sei => Ok(EvmChain::new(value, 1329)),
             Network::SeiTestnet => Ok(EvmChain::new(value, 1328)),
+            Network::Arbitrum => Ok(EvmChain::new(value, 42161)),
+            Network::Optimism => Ok(EvmChain::new(value, 10)),
+            Network::Lightning => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::LightningTestnet => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Near => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::NearTestnet => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Tron => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::TronNile => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Custom(chain_id) => Ok(EvmChain::new(value, chain_id)),
         }
     }
 }
@@ -157,33 +235,85 @@ pub struct ExactEvmPayment {
     pub nonce: HexEncodedNonce,
     /// Raw signature bytes (EIP-1271 or EIP-6492-wrapped).
     pub signature: EvmSignature,
+    /// ERC-3009 function the authorization is signed for.
+    pub authorization: Erc3009Authorization,
 }
 
 /// EVM implementation of the x402 facilitator.
 ///
 /// Holds a composed Alloy ethereum provider [`InnerProvider`],
-/// an `eip1559` toggle for gas pricing strategy, and the `EvmChain` context.
+/// an `eip1559` toggle and a [`FeeStrategy`] for gas pricing, and the `EvmChain` context.
 #[derive(Debug)]
 pub struct EvmProvider {
     /// Composed Alloy provider with all fillers.
     inner: InnerProvider,
     /// Whether network supports EIP-1559 gas pricing.
     eip1559: bool,
+    /// Gas pricing of settlement transactions.
+    fee_strategy: FeeStrategy,
+    /// Source of EIP-1559 fee suggestions; the provider's estimator if `None`.
+    gas_oracle: Option<Arc<dyn GasOracle>>,
     /// Chain descriptor (network + chain ID).
     chain: EvmChain,
-    /// Available signer addresses for round-robin selection.
-    signer_addresses: Arc<Vec<Address>>,
-    /// Current position in round-robin signer rotation.
-    signer_cursor: Arc<AtomicUsize>,
+    /// Signer addresses by rotation generation, selected round-robin.
+    signers: Arc<SignerPool>,
+    /// Monthly request accounting for the RPC endpoint.
+    rpc_budget: RpcBudget,
+    /// Native gas token, used to report transaction costs.
+    native_token: NativeToken,
+    /// Whether settlement transactions carry a [`SettlementTag`].
+    settlement_tagging: bool,
+    /// Whether verification also simulates the settlement call, as it would be broadcast.
+    verify_simulation: bool,
+    /// Safe executing settlements on behalf of the signers, which must be enabled as its modules.
+    settlement_safe: Option<Address>,
+    /// Block confirmations awaited before a settlement is reported.
+    confirmations: u64,
+    /// Assets accepted in payment requirements; any asset if `None`.
+    accepted_assets: Option<Vec<Address>>,
+    /// Highest block seen on the network.
+    block_tracker: BlockTracker,
+    /// Block tag payment checks read state at.
+    block_tag: BlockTag,
+    /// Chain reads of recent verifications, reused by settlement; disabled if `None`.
+    warm_cache: Option<Arc<WarmCache>>,
+    /// ERC-4337 bundler UserOperation payments are submitted to; the `erc4337` scheme is disabled if `None`.
+    bundler: Option<Arc<Bundler>>,
+    /// Groups concurrent settlements into Multicall3 transactions; disabled if `None`.
+    batcher: Option<Arc<SettlementBatcher>>,
+    /// Relayer services sending settlements of some assets instead of the signers.
+    relayers: Option<Arc<Relayers>>,
+    /// Records broadcast settlements until mined, for recovery after a restart; disabled if `None`.
+    tx_journal: Option<Arc<TxJournal>>,
+    /// Handling of tokens deducting a fee on transfer, detected at `/verify`.
+    fee_on_transfer: FeeOnTransfer,
+    /// Replacement of settlements pending for too long; disabled if `None`.
+    speed_up: Option<SpeedUp>,
+    /// Settlements awaiting their receipt, tracked with speed-up enabled only.
+    pending_settlements: PendingSettlements,
+    /// Accepted tokens that failed their on-chain sanity checks, see [`crate::chain::token_probe`].
+    quarantine: Quarantine,
+    /// Nonces of the signers, shared with the nonce filler of `inner`.
+    nonce_manager: PendingNonceManager,
+    /// Settlements followed for reorganizations, see [`crate::chain::reorg`]; not followed if `None`.
+    reorg_watch: Option<ReorgWatch>,
+    /// Endpoints failed over between, with several RPC URLs only.
+    rpc_failover: Option<RpcFailover>,
+    /// New heads pacing the receipt checks of settlements, see [`crate::chain::heads`]; polled if `None`.
+    heads: Option<HeadSubscription>,
+    /// Native balance below which a signer degrades the network; never if `None`.
+    min_signer_balance: Option<U256>,
 }
 
 impl EvmProvider {
     /// Build an [`EvmProvider`] from a pre-composed Alloy ethereum provider [`InnerProvider`].
     pub async fn try_new(
         wallet: EthereumWallet,
-        rpc_url: &str,
+        rpc_urls: &[String],
         eip1559: bool,
         network: Network,
+        rpc_budget: RpcBudget,
+        native_token: NativeToken,
     ) -> Result<Self, Box<dyn std::error::Error>> {
         let chain = EvmChain::try_from(network)?;
         let signer_addresses: Vec<Address> =
@@ -191,39 +321,482 @@ impl EvmProvider {
         if signer_addresses.is_empty() {
             return Err("wallet must contain at least one signer".into());
         }
-        let signer_addresses = Arc::new(signer_addresses);
-        let signer_cursor = Arc::new(AtomicUsize::new(0));
+        // Retries are sent again through the budget, so that they count against the quota.
         let client = RpcClient::builder()
-            .connect(rpc_url)
-            .await
-            .map_err(|e| format!("Failed to connect to {network}: {e}"))?;
-        let filler = InnerFiller::default();
+            .layer(RpcRetryLayer::new(network, RetryPolicy::from_env()?))
+            .layer(RpcBudgetLayer::new(rpc_budget.clone()));
+        #[cfg(feature = "chaos")]
+        let client = client.layer(crate::chaos::ChaosLayer);
+        // Several endpoints are failed over between, see [`crate::chain::failover`].
+        let rpc_failover = match rpc_urls {
+            [_] => None,
+            _ => Some(RpcFailover::connect(network, rpc_urls).await?),
+        };
+        let client = match &rpc_failover {
+            Some(rpc_failover) => client.transport(rpc_failover.clone(), false),
+            None => client
+                .connect(&rpc_urls[0])
+                .await
+                .map_err(|e| format!("Failed to connect to {network}: {e}"))?,
+        };
+        let nonce_manager = PendingNonceManager::default();
+        let filler: InnerFiller = JoinFill::new(
+            GasFiller,
+            JoinFill::new(
+                BlobGasFiller,
+                JoinFill::new(
+                    NonceFiller::new(nonce_manager.clone()),
+                    ChainIdFiller::default(),
+                ),
+            ),
+        );
         let inner = ProviderBuilder::default()
             .filler(filler)
             .wallet(wallet)
             .connect_client(client);
+        // A misconfigured RPC URL must not settle on another chain. An unreachable RPC is left to health checks.
+        match inner.get_chain_id().await {
+            Ok(rpc_chain_id) if rpc_chain_id != chain.chain_id => {
+                return Err(format!(
+                    "{network}: RPC reports chain ID {rpc_chain_id}, expected {}",
+                    chain.chain_id
+                )
+                .into());
+            }
+            Ok(_) => {}
+            Err(e) => {
+                tracing::warn!(network=%network, error=%e, "Can not check the chain ID of the RPC");
+            }
+        }
 
-        tracing::info!(network=%network, rpc=rpc_url, signers=?signer_addresses, "Initialized provider");
+        tracing::info!(network=%network, rpc=rpc_urls.join(","), signers=?signer_addresses, native_token=%native_token.symbol, "Initialized provider");
 
         Ok(Self {
             inner,
             eip1559,
+            fee_strategy: FeeStrategy::default(),
+            gas_oracle: None,
             chain,
-            signer_addresses,
-            signer_cursor,
+            signers: Arc::new(SignerPool::new(signer_addresses)),
+            rpc_budget,
+            native_token,
+            settlement_tagging: false,
+            verify_simulation: false,
+            settlement_safe: None,
+            confirmations: 1,
+            accepted_assets: None,
+            block_tracker: BlockTracker::default(),
+            block_tag: BlockTag::Latest,
+            warm_cache: None,
+            bundler: None,
+            batcher: None,
+            relayers: None,
+            tx_journal: None,
+            fee_on_transfer: FeeOnTransfer::default(),
+            speed_up: None,
+            pending_settlements: PendingSettlements::default(),
+            quarantine: Quarantine::default(),
+            nonce_manager,
+            reorg_watch: None,
+            rpc_failover,
+            heads: None,
+            min_signer_balance: None,
         })
     }
 
-    /// Round-robin selection of next signer from wallet.
-    fn next_signer_address(&self) -> Address {
-        debug_assert!(!self.signer_addresses.is_empty());
-        if self.signer_addresses.len() == 1 {
-            self.signer_addresses[0]
-        } else {
-            let next =
-                self.signer_cursor.fetch_add(1, Ordering::Relaxed) % self.signer_addresses.len();
-            self.signer_addresses[next]
+    /// Routes settlements through the Safe at `safe` instead of sending them from the signer EOAs.
+    ///
+    /// Each signer must be enabled as a module of the Safe (directly or via an allowance/delegate setup),
+    /// so that the token transfer is executed with `execTransactionFromModule`.
+    /// Disabling the module on-chain revokes the facilitator's settlement authority.
+    pub async fn with_settlement_safe(
+        mut self,
+        safe: Address,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let safe_contract = ISafe::new(safe, &self.inner);
+        for signer in self.signers.all() {
+            let enabled = safe_contract.isModuleEnabled(signer).call().await?;
+            if !enabled {
+                return Err(
+                    format!("signer {signer} is not an enabled module of Safe {safe}").into(),
+                );
+            }
+        }
+        tracing::info!(network=%self.chain.network, safe=%safe, "Settlements routed through Safe");
+        self.settlement_safe = Some(safe);
+        Ok(self)
+    }
+
+    /// Keeps `next` signers out of rotation until [`SignerPool::rotate`] promotes them.
+    ///
+    /// The signers must already be registered in the wallet, so they can be funded and monitored
+    /// while the current signers keep settling.
+    pub fn with_next_signers(self, next: Vec<Address>) -> Self {
+        if !next.is_empty() {
+            self.signers.set_next(next);
+        }
+        self
+    }
+
+    /// Signers of this provider by rotation generation.
+    pub fn signers(&self) -> &SignerPool {
+        &self.signers
+    }
+
+    /// Enables or disables appending a [`SettlementTag`] to settlement calldata.
+    pub fn with_settlement_tagging(mut self, settlement_tagging: bool) -> Self {
+        self.settlement_tagging = settlement_tagging;
+        self
+    }
+
+    /// Enables or disables simulating the settlement call during verification.
+    pub fn with_verify_simulation(mut self, verify_simulation: bool) -> Self {
+        self.verify_simulation = verify_simulation;
+        self
+    }
+
+    /// Waits for `confirmations` blocks before reporting a settlement.
+    pub fn with_confirmations(mut self, confirmations: u64) -> Self {
+        self.confirmations = confirmations.max(1);
+        self
+    }
+
+    /// Reports the network degraded while a signer holds less than `min_signer_balance` of the native token.
+    pub fn with_min_signer_balance(mut self, min_signer_balance: Option<U256>) -> Self {
+        self.min_signer_balance = min_signer_balance;
+        self
+    }
+
+    /// Reads payment state at `block_tag`.
+    pub fn with_block_tag(mut self, block_tag: BlockTag) -> Self {
+        self.block_tag = block_tag;
+        self
+    }
+
+    /// Reuses chain reads of verifications in the settlements that follow them.
+    pub fn with_warm_cache(mut self, warm_cache: Option<WarmCache>) -> Self {
+        self.warm_cache = warm_cache.map(Arc::new);
+        self
+    }
+
+    /// Accepts ERC-4337 UserOperation payments, submitted to `bundler`.
+    pub fn with_bundler(mut self, bundler: Bundler) -> Self {
+        self.bundler = Some(Arc::new(bundler));
+        self
+    }
+
+    /// Settles concurrent ERC-3009 payments together with `batcher`.
+    pub fn with_batcher(mut self, batcher: Option<SettlementBatcher>) -> Self {
+        self.batcher = batcher.map(Arc::new);
+        self
+    }
+
+    /// Sends settlements of the assets routed to a relayer through it, instead of the signers.
+    pub fn with_relayers(mut self, relayers: Relayers) -> Self {
+        self.relayers = Some(Arc::new(relayers));
+        self
+    }
+
+    /// Journals broadcast settlements, so that [`Self::recover_in_flight`] can pick them up after a restart.
+    pub fn with_tx_journal(mut self, tx_journal: Option<TxJournal>) -> Self {
+        self.tx_journal = tx_journal.map(Arc::new);
+        self
+    }
+
+    /// Handles tokens deducting a fee on transfer according to `fee_on_transfer`.
+    pub fn with_fee_on_transfer(mut self, fee_on_transfer: FeeOnTransfer) -> Self {
+        self.fee_on_transfer = fee_on_transfer;
+        self
+    }
+
+    /// Prices settlement transactions according to `fee_strategy`, which may also switch them to legacy ones.
+    pub fn with_fee_strategy(mut self, fee_strategy: FeeStrategy) -> Self {
+        self.eip1559 = fee_strategy.eip1559(self.eip1559);
+        self.fee_strategy = fee_strategy;
+        self
+    }
+
+    /// Prices EIP-1559 settlement transactions from the suggestions of `gas_oracle`, see [`crate::chain::gas_oracle`].
+    pub fn with_gas_oracle(mut self, gas_oracle: Option<Arc<dyn GasOracle>>) -> Self {
+        self.gas_oracle = gas_oracle;
+        self
+    }
+
+    /// Replaces settlements pending for too long according to `speed_up`, see [`crate::chain::speed_up`].
+    pub fn with_speed_up(mut self, speed_up: Option<SpeedUp>) -> Self {
+        self.speed_up = speed_up;
+        self
+    }
+
+    /// Follows successful settlements with `reorg_watch`, see [`crate::chain::reorg`].
+    pub fn with_reorg_watch(mut self, reorg_watch: Option<ReorgWatch>) -> Self {
+        self.reorg_watch = reorg_watch;
+        self
+    }
+
+    /// Settlements followed for reorganizations, if any.
+    pub fn reorg_watch(&self) -> Option<&ReorgWatch> {
+        self.reorg_watch.as_ref()
+    }
+
+    /// Checks settlement receipts on the new heads announced by `heads` instead of polling.
+    pub fn with_heads(mut self, heads: Option<HeadSubscription>) -> Self {
+        self.heads = heads;
+        self
+    }
+
+    /// Endpoints failed over between, if several RPC URLs are configured.
+    pub fn rpc_failover(&self) -> Option<&RpcFailover> {
+        self.rpc_failover.as_ref()
+    }
+
+    /// Only accepts payments in `assets`.
+    pub fn with_accepted_assets(mut self, assets: Vec<Address>) -> Self {
+        self.accepted_assets = Some(assets);
+        self
+    }
+
+    /// Probes the accepted tokens, or the known USDC deployments, and updates the quarantine with the outcome.
+    pub async fn probe_tokens(&self) {
+        let network = self.chain.network();
+        let deployments = USDCDeployment::all_by_network(network);
+        let assets: Vec<Address> = match &self.accepted_assets {
+            Some(assets) => assets.clone(),
+            None => deployments
+                .iter()
+                .filter_map(|usdc| Address::try_from(usdc.address()).ok())
+                .collect(),
+        };
+        for asset in assets {
+            let decimals = deployments
+                .iter()
+                .find(|usdc| Address::try_from(usdc.address()).ok() == Some(asset))
+                .map(|usdc| usdc.decimals);
+            let probe = token_probe::probe(&self.inner, asset, decimals).await;
+            let changed = self.quarantine.record(asset, &probe);
+            match &probe {
+                Probe::Failed(reason) if changed => {
+                    tracing::error!(network=%network, asset=%asset, reason, "Quarantined token failing its sanity checks");
+                    tracing::info!(monotonic_counter.x402.token_quarantined = 1, network=%network, asset=%asset);
+                }
+                Probe::Passed(symbol) if changed => {
+                    tracing::info!(network=%network, asset=%asset, symbol, "Released token from quarantine");
+                }
+                Probe::Inconclusive(reason) => {
+                    tracing::warn!(network=%network, asset=%asset, reason, "Could not probe token");
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Checks the settlements followed for reorganizations, see [`crate::chain::reorg`].
+    pub async fn check_reorgs(&self) {
+        let Some(watch) = &self.reorg_watch else {
+            return;
+        };
+        let network = self.chain.network;
+        let head = match self.inner.get_block_number().await {
+            Ok(head) => head,
+            Err(e) => {
+                tracing::warn!(network = %network, error = %e, "Can not check settlements for reorganizations");
+                return;
+            }
+        };
+        for mut settlement in watch.settlements() {
+            let receipt = match self.settlement_receipt(&settlement).await {
+                Ok(receipt) => receipt,
+                Err(()) => continue,
+            };
+            if let Some(receipt) = receipt {
+                let (Some(block_hash), Some(block_number)) =
+                    (receipt.block_hash, receipt.block_number)
+                else {
+                    continue;
+                };
+                if settlement.block == Some((block_hash, block_number)) {
+                    if block_number + watch.depth() <= head {
+                        watch.forget(&settlement.reported);
+                    }
+                    continue;
+                }
+                // Mined again, in another block or after being rebroadcast.
+                settlement.block = Some((block_hash, block_number));
+                watch.update(settlement.clone());
+                watch.emit(
+                    settlement.reported,
+                    ReorgOutcome::Reincluded {
+                        transaction: settlement.reported,
+                        block_number,
+                        success: receipt.status(),
+                    },
+                );
+                continue;
+            }
+            match self
+                .inner
+                .get_transaction_by_hash(settlement.reported)
+                .await
+            {
+                // Back in the mempool: mined again sooner or later.
+                Ok(Some(_)) => {
+                    if settlement.block.take().is_some() {
+                        tracing::warn!(network = %network, tx = %settlement.reported, "Settlement reorganized out of the chain, back in the mempool");
+                        watch.update(settlement);
+                    }
+                }
+                Ok(None) => {
+                    // Nonces of the signer may have been reorganized out as well.
+                    self.nonce_manager.reset(settlement.from).await;
+                    let used_nonces = match self
+                        .inner
+                        .get_transaction_count(settlement.from)
+                        .block_id(BlockId::latest())
+                        .await
+                    {
+                        Ok(count) => count,
+                        Err(e) => {
+                            tracing::warn!(network = %network, tx = %settlement.reported, error = %e, "Can not check settlement for reorganizations");
+                            continue;
+                        }
+                    };
+                    if used_nonces > settlement.nonce {
+                        // The nonce is used: by the settlement itself if the node lags on its receipt, or else by
+                        // another transaction, and then the settlement can never be mined.
+                        if let Ok(None) = self.settlement_receipt(&settlement).await {
+                            watch.forget(&settlement.reported);
+                            watch.emit(
+                                settlement.reported,
+                                ReorgOutcome::Dropped {
+                                    error: format!(
+                                        "nonce {} of {} used by another transaction",
+                                        settlement.nonce, settlement.from
+                                    ),
+                                },
+                            );
+                        }
+                        continue;
+                    }
+                    // Sent again as signed: with its own nonce, it is mined at most once.
+                    match self.inner.send_raw_transaction(&settlement.raw).await {
+                        Ok(_) => {
+                            if settlement.block.take().is_some() {
+                                watch.update(settlement.clone());
+                                watch.emit(
+                                    settlement.reported,
+                                    ReorgOutcome::Rebroadcast {
+                                        transaction: settlement.reported,
+                                    },
+                                );
+                            }
+                        }
+                        Err(e) => {
+                            tracing::warn!(network = %network, tx = %settlement.reported, error = %e, "Can not rebroadcast settlement dropped by a reorganization");
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(network = %network, tx = %settlement.reported, error = %e, "Can not check settlement for reorganizations");
+                }
+            }
+        }
+    }
+
+    /// Receipt of the followed `settlement`, if mined; `Err` if the node can not tell.
+    async fn settlement_receipt(
+        &self,
+        settlement: &WatchedSettlement,
+    ) -> Result<Option<TransactionReceipt>, ()> {
+        self.inner
+            .get_transaction_receipt(settlement.reported)
+            .await
+            .map_err(|e| {
+                tracing::warn!(network = %self.chain.network, tx = %settlement.reported, error = %e, "Can not check settlement for reorganizations");
+            })
+    }
+
+    /// Native gas token of the network this provider is connected to.
+    pub fn native_token(&self) -> &NativeToken {
+        &self.native_token
+    }
+
+    /// Monthly request accounting for the RPC endpoint of this provider.
+    pub fn rpc_budget(&self) -> &RpcBudget {
+        &self.rpc_budget
+    }
+
+    /// Latest block number reported by the RPC node, used as a liveness probe.
+    pub async fn latest_block_number(&self) -> Result<u64, FacilitatorLocalError> {
+        self.inner
+            .get_block_number()
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+    }
+
+    /// Reads the native balance of every signer into the `x402.signer.balance` gauge.
+    ///
+    /// Fails, naming them, if some hold less than the configured minimum; balances that can not be read are skipped.
+    pub async fn check_signer_balances(&self) -> Result<(), String> {
+        let network = self.chain.network;
+        let mut low = Vec::new();
+        for signer in self.signers.all() {
+            let balance = match self.inner.get_balance(signer).await {
+                Ok(balance) => balance,
+                Err(e) => {
+                    tracing::warn!(network = %network, signer = %signer, error = %e, "Can not read signer balance");
+                    continue;
+                }
+            };
+            let units = format_units(balance, self.native_token.decimals)
+                .ok()
+                .and_then(|units| units.parse::<f64>().ok())
+                .unwrap_or_default();
+            tracing::info!(
+                gauge.x402.signer.balance = units,
+                network = %network,
+                signer = %signer,
+            );
+            if let Some(min) = self.min_signer_balance
+                && balance < min
+            {
+                tracing::warn!(
+                    monotonic_counter.x402.signer.low_balance = 1,
+                    network = %network,
+                    signer = %signer,
+                    balance = %self.native_token.format_amount(balance),
+                    min = %self.native_token.format_amount(min),
+                    "Signer balance low, settlements may soon fail for lack of gas"
+                );
+                low.push(format!(
+                    "{signer} holds {}",
+                    self.native_token.format_amount(balance)
+                ));
+            }
         }
+        match (low.is_empty(), self.min_signer_balance) {
+            (false, Some(min)) => Err(format!(
+                "Signer balance below {}: {}",
+                self.native_token.format_amount(min),
+                low.join(", ")
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Receipt of the transaction `hash`; `None` if it is not mined.
+    pub async fn transaction_receipt(
+        &self,
+        hash: B256,
+    ) -> Result<Option<TransactionReceipt>, FacilitatorLocalError> {
+        self.inner
+            .get_transaction_receipt(hash)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+    }
+
+    /// Round-robin selection of next active signer from wallet.
+    fn next_signer_address(&self) -> Address {
+        self.signers.next_signer()
     }
 }
 
@@ -239,6 +812,64 @@ pub trait MetaEvmProvider {
     /// Returns reference to chain descriptor.
     fn chain(&self) -> &EvmChain;
 
+    /// Addresses settlement calls are made from (`msg.sender`), active signers first.
+    fn settlement_addresses(&self) -> Vec<Address>;
+
+    /// Whether settlement calldata should carry a [`SettlementTag`]. Disabled by default.
+    fn settlement_tagging(&self) -> bool {
+        false
+    }
+
+    /// Whether verification also simulates the exact settlement call of ERC-3009 payments. Disabled by default.
+    fn verify_simulation(&self) -> bool {
+        false
+    }
+
+    /// Block confirmations awaited before a settlement is reported. One by default.
+    fn confirmations(&self) -> u64 {
+        1
+    }
+
+    /// Assets accepted in payment requirements; any asset if `None`, the default.
+    fn accepted_assets(&self) -> Option<&[Address]> {
+        None
+    }
+
+    /// Accepted tokens that failed their on-chain sanity checks; none if `None`, the default.
+    fn quarantine(&self) -> Option<&Quarantine> {
+        None
+    }
+
+    /// Highest block seen on the network, to pin payment checks to; `latest` is used if `None`, the default.
+    fn block_tracker(&self) -> Option<&BlockTracker> {
+        None
+    }
+
+    /// Block tag payment checks read state at. [`BlockTag::Latest`] by default.
+    fn block_tag(&self) -> BlockTag {
+        BlockTag::Latest
+    }
+
+    /// Chain reads of recent verifications, reused by settlement; none by default.
+    fn warm_cache(&self) -> Option<&WarmCache> {
+        None
+    }
+
+    /// Handling of tokens deducting a fee on transfer; rejected by default.
+    fn fee_on_transfer(&self) -> FeeOnTransfer {
+        FeeOnTransfer::default()
+    }
+
+    /// Bundler for ERC-4337 UserOperation payments; none by default, which disables them.
+    fn bundler(&self) -> Option<&Bundler> {
+        None
+    }
+
+    /// Batcher of concurrent settlements; none by default, each settlement sending its own transaction.
+    fn batcher(&self) -> Option<&SettlementBatcher> {
+        None
+    }
+
     /// Sends a meta-transaction to the network.
     fn send_transaction(
         &self,
@@ -246,6 +877,44 @@ pub trait MetaEvmProvider {
     ) -> impl Future<Output = Result<TransactionReceipt, Self::Error>> + Send;
 }
 
+/// Identifying tag appended to settlement calldata so on-chain analytics can attribute a transfer
+/// to an invoice without access to the facilitator database.
+///
+/// Encoded as the 4-byte magic `x402` followed by `keccak256(invoice_id)`. The invoice id is the
+/// `paymentId` of the payment requirements, else their `extra.invoiceId`, falling back to the `resource` URL.
+/// Token contracts ignore trailing calldata, so the tag does not change the transfer itself.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SettlementTag(pub B256);
+
+impl SettlementTag {
+    pub const MAGIC: [u8; 4] = *b"x402";
+
+    pub fn from_requirements(requirements: &PaymentRequirements) -> Self {
+        let invoice_id = requirements
+            .payment_id
+            .as_ref()
+            .map(|id| id.0.as_str())
+            .or_else(|| {
+                requirements
+                    .extra
+                    .as_ref()
+                    .and_then(|extra| extra.get("invoiceId"))
+                    .and_then(|id| id.as_str())
+            })
+            .unwrap_or(requirements.resource.as_str());
+        Self(keccak256(invoice_id.as_bytes()))
+    }
+
+    /// Returns `calldata` with the tag appended.
+    pub fn append_to(&self, calldata: &Bytes) -> Bytes {
+        let mut tagged = Vec::with_capacity(calldata.len() + Self::MAGIC.len() + 32);
+        tagged.extend_from_slice(calldata);
+        tagged.extend_from_slice(&Self::MAGIC);
+        tagged.extend_from_slice(self.0.as_slice());
+        tagged.into()
+    }
+}
+
 /// Meta-transaction parameters: target address, calldata, and required confirmations.
 pub struct MetaTransaction {
     /// Target contract address.
@@ -254,6 +923,9 @@ pub struct MetaTransaction {
     pub calldata: Bytes,
     /// Number of block confirmations to wait for.
     pub confirmations: u64,
+    /// Required `msg.sender` of the call to `to`, one of [`MetaEvmProvider::settlement_addresses`].
+    /// Any signer is used if `None`.
+    pub sender: Option<Address>,
 }
 
 impl MetaEvmProvider for EvmProvider {
@@ -268,6 +940,57 @@ impl MetaEvmProvider for EvmProvider {
         &self.chain
     }
 
+    fn settlement_addresses(&self) -> Vec<Address> {
+        match self.settlement_safe {
+            Some(safe) => vec![safe],
+            None => self.signers.all(),
+        }
+    }
+
+    fn settlement_tagging(&self) -> bool {
+        self.settlement_tagging
+    }
+
+    fn verify_simulation(&self) -> bool {
+        self.verify_simulation
+    }
+
+    fn confirmations(&self) -> u64 {
+        self.confirmations
+    }
+
+    fn accepted_assets(&self) -> Option<&[Address]> {
+        self.accepted_assets.as_deref()
+    }
+
+    fn quarantine(&self) -> Option<&Quarantine> {
+        Some(&self.quarantine)
+    }
+
+    fn block_tracker(&self) -> Option<&BlockTracker> {
+        Some(&self.block_tracker)
+    }
+
+    fn block_tag(&self) -> BlockTag {
+        self.block_tag
+    }
+
+    fn warm_cache(&self) -> Option<&WarmCache> {
+        self.warm_cache.as_deref()
+    }
+
+    fn fee_on_transfer(&self) -> FeeOnTransfer {
+        self.fee_on_transfer
+    }
+
+    fn bundler(&self) -> Option<&Bundler> {
+        self.bundler.as_deref()
+    }
+
+    fn batcher(&self) -> Option<&SettlementBatcher> {
+        self.batcher.as_deref()
+    }
+
     /// Send a meta-transaction with provided `to`, `calldata`, and automatically selected signer.
     ///
     /// This method constructs a transaction from the provided [`MetaTransaction`], automatically
@@ -297,29 +1020,466 @@ impl MetaEvmProvider for EvmProvider {
         &self,
         tx: MetaTransaction,
     ) -> Result<TransactionReceipt, Self::Error> {
-        let mut txr = TransactionRequest::default()
-            .with_to(tx.to)
-            .with_from(self.next_signer_address())
-            .with_input(tx.calldata);
-        if !self.eip1559 {
-            let provider = &self.inner;
-            let gas: u128 = provider
-                .get_gas_price()
-                .instrument(tracing::info_span!("get_gas_price"))
+        // Calls any sender may make to a token routed to a relayer are relayed, bypassing the signers and Safe.
+        if tx.sender.is_none()
+            && let Some(relayer) = self
+                .relayers
+                .as_ref()
+                .and_then(|relayers| relayers.for_asset(tx.to))
+        {
+            let tx_hash = relayer
+                .relay(tx.to, tx.calldata)
+                .instrument(tracing::info_span!(
+                    "relay",
+                    relayer = relayer.name(),
+                    otel.kind = "client"
+                ))
+                .await?;
+            let receipt = PendingTransactionBuilder::new(self.inner.root().clone(), tx_hash)
+                .with_required_confirmations(tx.confirmations)
+                .get_receipt()
                 .await
                 .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-            txr.set_gas_price(gas);
+            tracing::info!(
+                network = %self.chain.network,
+                tx = %receipt.transaction_hash,
+                relayer = relayer.name(),
+                "Transaction relayed"
+            );
+            return Ok(receipt);
         }
-        let pending_tx = self
-            .inner
-            .send_transaction(txr)
+        let (to, calldata) = match self.settlement_safe {
+            Some(safe) => {
+                let exec = ISafe::execTransactionFromModuleCall {
+                    to: tx.to,
+                    value: U256::ZERO,
+                    data: tx.calldata,
+                    operation: 0,
+                };
+                (safe, Bytes::from(exec.abi_encode()))
+            }
+            None => (tx.to, tx.calldata),
+        };
+        let from = match (self.settlement_safe, tx.sender) {
+            (Some(safe), Some(sender)) if sender != safe => {
+                return Err(FacilitatorLocalError::ContractCall(format!(
+                    "Can not call as {sender} through Safe {safe}"
+                )));
+            }
+            (None, Some(sender)) => sender,
+            _ => self.next_signer_address(),
+        };
+        let _in_flight = self.signers.begin(from);
+        let mut txr = TransactionRequest::default()
+            .with_to(to)
+            .with_from(from)
+            .with_input(calldata.clone());
+        let gas_price = self
+            .gas_price()
+            .instrument(tracing::info_span!("get_gas_price"))
             .await
             .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-        pending_tx
-            .with_required_confirmations(tx.confirmations)
-            .get_receipt()
-            .await
-            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+        match gas_price {
+            GasPrice::Eip1559 {
+                max_fee_per_gas,
+                max_priority_fee_per_gas,
+            } => {
+                txr.set_max_fee_per_gas(max_fee_per_gas);
+                txr.set_max_priority_fee_per_gas(max_priority_fee_per_gas);
+            }
+            GasPrice::Legacy(gas_price) => txr.set_gas_price(gas_price),
+        }
+        #[cfg(feature = "chaos")]
+        crate::chaos::Chaos::global().delay_broadcast().await;
+        let pending_tx = match self.inner.send_transaction(txr).await {
+            Ok(pending_tx) => pending_tx,
+            Err(e) => {
+                // The nonce allocated to the transaction may be unused, or the cache stale: refetch it next time.
+                self.nonce_manager.reset(from).await;
+                tracing::warn!(network = %self.chain.network, from = %from, error = %e, "Broadcast failed, nonce refreshed");
+                return Err(revert::rpc_error(None, e));
+            }
+        };
+        if let Some(journal) = &self.tx_journal {
+            journal.record(&JournalEntry {
+                network: self.chain.network,
+                from,
+                hash: *pending_tx.tx_hash(),
+                to,
+                calldata: calldata.clone(),
+            });
+        }
+        // Left in the journal if the receipt is not seen, so that a restart picks the transaction up.
+        let receipt = match (self.speed_up, &self.heads) {
+            (Some(_), _) => {
+                let hash = *pending_tx.tx_hash();
+                let _tracked = self.pending_settlements.track(hash, from);
+                let receipt = self.watch_pending(hash, tx.confirmations).await;
+                if let Some(journal) = &self.tx_journal {
+                    for hash in self.pending_settlements.hashes(hash) {
+                        journal.remove(hash);
+                    }
+                }
+                receipt
+            }
+            (None, Some(_)) => {
+                self.watch_pending(*pending_tx.tx_hash(), tx.confirmations)
+                    .await
+            }
+            (None, None) => pending_tx
+                .with_required_confirmations(tx.confirmations)
+                .get_receipt()
+                .await
+                .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?,
+        };
+        if let Some(journal) = &self.tx_journal {
+            journal.remove(receipt.transaction_hash);
+        }
+        if let Some(watch) = &self.reorg_watch
+            && receipt.status()
+            && let (Some(block_hash), Some(block_number)) =
+                (receipt.block_hash, receipt.block_number)
+        {
+            // Kept as signed, so that a transaction dropped by a reorganization is sent again unchanged.
+            match self
+                .inner
+                .get_transaction_by_hash(receipt.transaction_hash)
+                .await
+            {
+                Ok(Some(mined)) => watch.watch(
+                    receipt.transaction_hash,
+                    receipt.from,
+                    mined.nonce(),
+                    mined.inner.inner().encoded_2718().into(),
+                    (block_hash, block_number),
+                ),
+                Ok(None) => {
+                    tracing::warn!(network = %self.chain.network, tx = %receipt.transaction_hash, "Mined settlement not found, not followed for reorganizations");
+                }
+                Err(e) => {
+                    tracing::warn!(network = %self.chain.network, tx = %receipt.transaction_hash, error = %e, "Mined settlement not followed for reorganizations");
+                }
+            }
+        }
+        if let Some(safe) = self.settlement_safe {
+            let module_failed = receipt.inner.logs().iter().any(|log| {
+                log.address() == safe
+                    && log.topic0() == Some(&ISafe::ExecutionFromModuleFailure::SIGNATURE_HASH)
+            });
+            if module_failed {
+                return Err(FacilitatorLocalError::ContractCall(format!(
+                    "Safe {safe} failed to execute settlement in {}",
+                    receipt.transaction_hash
+                )));
+            }
+        }
+        let l2_cost = U256::from(receipt.gas_used) * U256::from(receipt.effective_gas_price);
+        let l1_fee = self.l1_fee(&receipt).await;
+        let gas_cost = match self.chain.l1_fee_model() {
+            L1FeeModel::OpStack => l2_cost + l1_fee,
+            L1FeeModel::None | L1FeeModel::Arbitrum => l2_cost,
+        };
+        tracing::info!(
+            network = %self.chain.network,
+            tx = %receipt.transaction_hash,
+            from = %receipt.from,
+            gas_used = receipt.gas_used,
+            gas_cost = %self.native_token.format_amount(gas_cost),
+            l1_fee = %self.native_token.format_amount(l1_fee),
+            "Transaction mined"
+        );
+        Ok(receipt)
+    }
+}
+
+impl EvmProvider {
+    /// Reattaches the settlements journaled before a restart, see [`crate::chain::recovery`].
+    pub async fn recover_in_flight(&self) {
+        let Some(journal) = &self.tx_journal else {
+            return;
+        };
+        let network = self.chain.network;
+        let entries = journal.entries(network);
+        for entry in &entries {
+            match self.inner.get_transaction_receipt(entry.hash).await {
+                Ok(Some(receipt)) => {
+                    tracing::info!(network = %network, tx = %entry.hash, success = receipt.status(), "In-flight transaction was mined");
+                    journal.remove(entry.hash);
+                    continue;
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    tracing::warn!(network = %network, tx = %entry.hash, error = %e, "Can not check in-flight transaction");
+                    continue;
+                }
+            }
+            let hash = match self.inner.get_transaction_by_hash(entry.hash).await {
+                Ok(Some(_)) => entry.hash,
+                Ok(None) => {
+                    let txr = TransactionRequest::default()
+                        .with_to(entry.to)
+                        .with_from(entry.from)
+                        .with_input(entry.calldata.clone());
+                    let sent = self.inner.send_transaction(txr).await;
+                    journal.remove(entry.hash);
+                    match sent {
+                        Ok(pending_tx) => {
+                            let hash = *pending_tx.tx_hash();
+                            tracing::info!(network = %network, dropped = %entry.hash, tx = %hash, "Rebroadcast dropped transaction");
+                            journal.record(&JournalEntry {
+                                hash,
+                                ..entry.clone()
+                            });
+                            hash
+                        }
+                        Err(e) => {
+                            tracing::warn!(network = %network, tx = %entry.hash, error = %e, "Dropped transaction can not be rebroadcast");
+                            continue;
+                        }
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(network = %network, tx = %entry.hash, error = %e, "Can not check in-flight transaction");
+                    continue;
+                }
+            };
+            let root = self.inner.root().clone();
+            let journal = journal.clone();
+            let confirmations = self.confirmations;
+            tokio::spawn(async move {
+                let receipt = PendingTransactionBuilder::new(root, hash)
+                    .with_required_confirmations(confirmations)
+                    .get_receipt()
+                    .await;
+                match receipt {
+                    Ok(receipt) => {
+                        tracing::info!(network = %network, tx = %hash, success = receipt.status(), "In-flight transaction was mined");
+                        journal.remove(hash);
+                    }
+                    Err(e) => {
+                        tracing::warn!(network = %network, tx = %hash, error = %e, "In-flight transaction still not mined");
+                    }
+                }
+            });
+        }
+        for signer in self.signers.all() {
+            let latest = self.inner.get_transaction_count(signer).latest().await;
+            let pending = self.inner.get_transaction_count(signer).pending().await;
+            let (Ok(latest), Ok(pending)) = (latest, pending) else {
+                continue;
+            };
+            let journaled = entries.iter().filter(|entry| entry.from == signer).count() as u64;
+            if pending > latest + journaled {
+                tracing::warn!(
+                    network = %network,
+                    signer = %signer,
+                    pending = pending - latest,
+                    journaled,
+                    "Signer has pending transactions missing from the journal"
+                );
+            }
+        }
+    }
+
+    /// Fees of a transaction sent now, according to the [`FeeStrategy`].
+    async fn gas_price(&self) -> TransportResult<GasPrice> {
+        if !self.eip1559 {
+            let gas_price = self.inner.get_gas_price().await?;
+            return Ok(GasPrice::Legacy(
+                self.fee_strategy.legacy_gas_price(gas_price),
+            ));
+        }
+        if let Some(gas_oracle) = &self.gas_oracle {
+            match gas_oracle.suggest(&self.inner.clone().erased()).await {
+                Ok(suggestion) => {
+                    let priority_fee = self
+                        .fee_strategy
+                        .max_priority_fee_per_gas
+                        .map_or(suggestion.max_priority_fee_per_gas, u128::from);
+                    return Ok(GasPrice::Eip1559 {
+                        max_fee_per_gas: self
+                            .fee_strategy
+                            .max_fee(suggestion.base_fee_per_gas, priority_fee),
+                        max_priority_fee_per_gas: priority_fee,
+                    });
+                }
+                Err(e) => {
+                    tracing::warn!(network = %self.chain.network, oracle = gas_oracle.name(), error = %e, "Gas oracle failed, using default pricing");
+                }
+            }
+        }
+        if !self.fee_strategy.overrides_eip1559() {
+            let estimate = self.inner.estimate_eip1559_fees().await?;
+            return Ok(GasPrice::Eip1559 {
+                max_fee_per_gas: estimate.max_fee_per_gas,
+                max_priority_fee_per_gas: estimate.max_priority_fee_per_gas,
+            });
+        }
+        let priority_fee = match self.fee_strategy.max_priority_fee_per_gas {
+            Some(priority_fee) => u128::from(priority_fee),
+            None => self.inner.get_max_priority_fee_per_gas().await?,
+        };
+        let base_fee = self
+            .inner
+            .get_block_by_number(BlockNumberOrTag::Latest)
+            .await?
+            .and_then(|block| block.header.base_fee_per_gas)
+            .unwrap_or_default();
+        Ok(GasPrice::Eip1559 {
+            max_fee_per_gas: self
+                .fee_strategy
+                .max_fee(u128::from(base_fee), priority_fee),
+            max_priority_fee_per_gas: priority_fee,
+        })
+    }
+
+    /// Waits for the receipt of the settlement first sent as `original`, or of one of its replacements,
+    /// and for `confirmations` blocks including it.
+    ///
+    /// Checks once per new head if subscribed to them, or else every [`PENDING_POLL_INTERVAL`].
+    async fn watch_pending(&self, original: B256, confirmations: u64) -> TransactionReceipt {
+        let mut ticker = BlockTicker::new(self.heads.as_ref(), PENDING_POLL_INTERVAL);
+        loop {
+            ticker.tick().await;
+            for hash in self.pending_settlements.hashes(original) {
+                // RPC failures are retried at the next tick, like the receipt watcher of alloy does.
+                let Ok(Some(receipt)) = self.inner.get_transaction_receipt(hash).await else {
+                    continue;
+                };
+                let mined_at = receipt.block_number.unwrap_or_default();
+                loop {
+                    let latest = match ticker.latest() {
+                        Some(latest) => Ok(latest),
+                        None => self.latest_block_number().await,
+                    };
+                    if latest.is_ok_and(|latest| latest + 1 >= mined_at + confirmations) {
+                        break;
+                    }
+                    ticker.tick().await;
+                }
+                if hash != original {
+                    tracing::info!(network = %self.chain.network, original = %original, tx = %hash, "Replacement transaction mined");
+                }
+                return receipt;
+            }
+        }
+    }
+
+    /// Rebroadcasts the settlements pending for longer than allowed with higher fees, see [`crate::chain::speed_up`].
+    pub async fn replace_stuck(&self) {
+        let Some(speed_up) = self.speed_up else {
+            return;
+        };
+        let network = self.chain.network;
+        for (original, pending) in self.pending_settlements.stale(speed_up.after) {
+            let Some(&latest) = pending.hashes.last() else {
+                continue;
+            };
+            if pending.hashes.len() > speed_up.max_bumps {
+                continue;
+            }
+            let stuck = match self.inner.get_transaction_by_hash(latest).await {
+                Ok(Some(stuck)) if stuck.block_number.is_none() => stuck,
+                // Mined, about to be seen by the settlement, or dropped, left to recovery after a restart.
+                Ok(_) => continue,
+                Err(e) => {
+                    tracing::warn!(network = %network, tx = %latest, error = %e, "Can not check pending transaction");
+                    continue;
+                }
+            };
+            let mut txr = TransactionRequest::default()
+                .with_from(pending.from)
+                .with_input(stuck.input().clone())
+                .with_nonce(stuck.nonce())
+                .with_gas_limit(stuck.gas_limit());
+            if let Some(to) = stuck.to() {
+                txr.set_to(to);
+            }
+            match self.gas_price().await {
+                Ok(GasPrice::Eip1559 {
+                    max_fee_per_gas,
+                    max_priority_fee_per_gas,
+                }) => {
+                    txr.set_max_fee_per_gas(
+                        speed_up.bump(stuck.max_fee_per_gas(), max_fee_per_gas),
+                    );
+                    txr.set_max_priority_fee_per_gas(speed_up.bump(
+                        stuck.max_priority_fee_per_gas().unwrap_or_default(),
+                        max_priority_fee_per_gas,
+                    ));
+                }
+                Ok(GasPrice::Legacy(gas_price)) => {
+                    txr.set_gas_price(
+                        speed_up.bump(stuck.gas_price().unwrap_or_default(), gas_price),
+                    );
+                }
+                Err(e) => {
+                    tracing::warn!(network = %network, tx = %latest, error = %e, "Can not price replacement transaction");
+                    continue;
+                }
+            }
+            let replacement = match self.inner.send_transaction(txr).await {
+                Ok(pending_tx) => *pending_tx.tx_hash(),
+                Err(e) => {
+                    tracing::warn!(network = %network, tx = %latest, error = %e, "Stuck transaction can not be replaced");
+                    continue;
+                }
+            };
+            self.pending_settlements.replaced(original, replacement);
+            if let Some(journal) = &self.tx_journal {
+                journal.record(&JournalEntry {
+                    network,
+                    from: pending.from,
+                    hash: replacement,
+                    to: stuck.to().unwrap_or_default(),
+                    calldata: stuck.input().clone(),
+                });
+                journal.remove(latest);
+            }
+            tracing::info!(
+                network = %network,
+                original = %original,
+                stuck = %latest,
+                tx = %replacement,
+                bumps = pending.hashes.len(),
+                "Replaced stuck transaction with higher fees"
+            );
+        }
+    }
+
+    /// L1 data fee paid by a mined transaction, in the native token; zero outside rollups.
+    ///
+    /// The Ethereum receipt type drops rollup-specific fields, so the receipt is fetched again as raw JSON.
+    /// Any failure is logged and counted as zero: it only affects cost accounting, not the settlement.
+    async fn l1_fee(&self, receipt: &TransactionReceipt) -> U256 {
+        let model = self.chain.l1_fee_model();
+        let field = match model {
+            L1FeeModel::None => return U256::ZERO,
+            L1FeeModel::OpStack => "l1Fee",
+            L1FeeModel::Arbitrum => "gasUsedForL1",
+        };
+        let raw_receipt: Result<serde_json::Value, _> = self
+            .inner
+            .raw_request(
+                "eth_getTransactionReceipt".into(),
+                (receipt.transaction_hash,),
+            )
+            .instrument(tracing::info_span!("fetch_l1_fee", tx = %receipt.transaction_hash, otel.kind = "client"))
+            .await;
+        let value = raw_receipt
+            .ok()
+            .and_then(|raw_receipt| raw_receipt.get(field)?.as_str().map(str::to_string))
+            .and_then(|value| U256::from_str_radix(value.trim_start_matches("0x"), 16).ok());
+        match (model, value) {
+            (L1FeeModel::Arbitrum, Some(l1_gas_used)) => {
+                l1_gas_used * U256::from(receipt.effective_gas_price)
+            }
+            (_, Some(l1_fee)) => l1_fee,
+            (_, None) => {
+                tracing::warn!(network = %self.chain.network, tx = %receipt.transaction_hash, field, "L1 fee not found in receipt");
+                U256::ZERO
+            }
+        }
     }
 }
 
@@ -345,7 +1505,54 @@ impl FromEnvByNetworkBuild for EvmProvider {
                 return Ok(None);
             }
         };
-        let wallet = from_env::SignerType::from_env()?.make_evm_wallet()?;
+        let mut config = NetworkConfig::from_rpc_url(rpc_url);
+        config.tokens = from_env::tokens_from_env(network)?;
+        Ok(Some(Self::from_config(network, &config).await?))
+    }
+}
+
+impl FromConfigByNetworkBuild for EvmProvider {
+    async fn from_config(
+        network: Network,
+        config: &NetworkConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let chain = EvmChain::try_from(network)?;
+        if let Some(chain_id) = config.chain_id
+            && chain_id != chain.chain_id
+        {
+            return Err(format!(
+                "{network}: configured chain ID {chain_id} does not match {}",
+                chain.chain_id
+            )
+            .into());
+        }
+        // Keys from the config file, local or in KMS, replace all signers from the environment.
+        let (mut wallet, next_signers) = match (&config.signer_keys, &config.signer_kms_keys) {
+            (Some(_), Some(_)) => {
+                return Err(
+                    format!("{network}: signer_keys and signer_kms_keys are exclusive").into(),
+                );
+            }
+            (Some(keys), None) => (
+                from_env::make_evm_wallet_from_keys(&keys.join(","))?,
+                Vec::new(),
+            ),
+            (None, Some(keys)) => (
+                kms::make_evm_wallet(network, keys, chain.chain_id).await?,
+                Vec::new(),
+            ),
+            (None, None) => {
+                let signer_type = from_env::SignerType::from_env()?;
+                (
+                    signer_type.make_evm_wallet()?,
+                    signer_type.make_evm_next_signers()?,
+                )
+            }
+        };
+        let next_addresses: Vec<Address> = next_signers.iter().map(|s| s.address()).collect();
+        for signer in next_signers {
+            wallet.register_signer(signer);
+        }
         let is_eip1559 = match network {
             Network::BaseSepolia => true,
             Network::Base => true,
@@ -358,9 +1565,162 @@ impl FromEnvByNetworkBuild for EvmProvider {
             Network::Polygon => true,
             Network::Sei => true,
             Network::SeiTestnet => true,
+            Network::Arbitrum => true,
+            Network::Optimism => true,
+            Network::Lightning => false,
+            Network::LightningTestnet => false,
+            Network::Near => false,
+            Network::NearTestnet => false,
+            Network::Tron => false,
+            Network::TronNile => false,
+            Network::Custom(chain_id) => {
+                CustomNetwork::by_chain_id(chain_id).is_none_or(|custom| custom.eip1559)
+            }
         };
-        let provider = EvmProvider::try_new(wallet, &rpc_url, is_eip1559, network).await?;
-        Ok(Some(provider))
+        let fee_strategy_env_var = from_env::fee_strategy_env_name_from_network(network);
+        let fee_strategy = match (
+            config.fee_strategy,
+            std::env::var(&fee_strategy_env_var).ok(),
+        ) {
+            (Some(fee_strategy), _) => {
+                fee_strategy
+                    .validate()
+                    .map_err(|e| format!("{network}: {e}"))?;
+                fee_strategy
+            }
+            (None, Some(fee_strategy)) => fee_strategy
+                .parse()
+                .map_err(|e| format!("{fee_strategy_env_var}: {e}"))?,
+            (None, None) => FeeStrategy::default(),
+        };
+        let gas_oracle_env_var = from_env::gas_oracle_env_name_from_network(network);
+        let gas_oracle = match (&config.gas_oracle, std::env::var(&gas_oracle_env_var).ok()) {
+            (Some(gas_oracle), _) => Some(gas_oracle.clone()),
+            (None, Some(gas_oracle)) => Some(
+                gas_oracle
+                    .parse::<GasOracleConfig>()
+                    .map_err(|e| format!("{gas_oracle_env_var}: {e}"))?,
+            ),
+            (None, None) => None,
+        };
+        let outbound = OutboundPolicy::from_env()?;
+        let gas_oracle = gas_oracle
+            .map(|gas_oracle| gas_oracle.build(network, chain.chain_id, &outbound))
+            .transpose()
+            .map_err(|e| format!("{network}: {e}"))?;
+        let rpc_budget = RpcBudget::from_env(network);
+        let native_token = NativeToken::from_env(network)?;
+        let min_signer_balance_env_var =
+            from_env::signer_min_balance_env_name_from_network(network);
+        let min_signer_balance = std::env::var(&min_signer_balance_env_var)
+            .ok()
+            .map(|amount| native_token.parse_amount(&amount))
+            .transpose()
+            .map_err(|e| format!("{min_signer_balance_env_var}: {e}"))?;
+        let mut provider = EvmProvider::try_new(
+            wallet,
+            &failover::rpc_urls(&config.rpc_url),
+            is_eip1559,
+            network,
+            rpc_budget,
+            native_token,
+        )
+        .await?
+        .with_next_signers(next_addresses)
+        .with_fee_strategy(fee_strategy)
+        .with_gas_oracle(gas_oracle)
+        .with_settlement_tagging(from_env::settlement_tagging_from_env())
+        .with_verify_simulation(
+            config
+                .verify_simulation
+                .unwrap_or_else(|| from_env::verify_simulation_from_env() || dry_run::enabled()),
+        )
+        .with_confirmations(config.confirmations.unwrap_or(1));
+        let block_tag_env_var = from_env::block_tag_env_name_from_network(network);
+        let block_tag = match (config.block_tag, std::env::var(&block_tag_env_var).ok()) {
+            (Some(block_tag), _) => block_tag,
+            (None, Some(block_tag)) => block_tag
+                .parse()
+                .map_err(|e| format!("{block_tag_env_var}: {e}"))?,
+            (None, None) => BlockTag::Latest,
+        };
+        provider = provider
+            .with_block_tag(block_tag)
+            .with_warm_cache(WarmCache::from_env()?)
+            .with_tx_journal(TxJournal::from_env()?)
+            .with_fee_on_transfer(FeeOnTransfer::from_env()?)
+            .with_speed_up(SpeedUp::from_env()?)
+            .with_reorg_watch(ReorgWatch::from_env(network)?)
+            .with_min_signer_balance(min_signer_balance)
+            .with_heads(
+                HeadSubscription::from_url_or_env(network, config.ws_url.clone())
+                    .map_err(|e| format!("{network}: {e}"))?,
+            )
+            .with_batcher(
+                SettlementBatcher::from_window_or_env(config.batch_window_ms)
+                    .map_err(|e| format!("{network}: {e}"))?,
+            );
+        if let Some(relayers) = &config.relayers {
+            let relayers = Relayers::from_config(relayers, provider.chain.chain_id, &outbound)
+                .map_err(|e| format!("{network}: {e}"))?;
+            provider = provider.with_relayers(relayers);
+        }
+        if let Some(tokens) = &config.tokens {
+            let tokens = tokens
+                .iter()
+                .map(|token| Address::try_from(token.clone()))
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("{network}: invalid token address: {e:?}"))?;
+            provider = provider.with_accepted_assets(tokens);
+        }
+        let bundler_url = config
+            .bundler_url
+            .clone()
+            .or_else(|| std::env::var(from_env::bundler_env_name_from_network(network)).ok());
+        let paymaster_url = config
+            .paymaster_url
+            .clone()
+            .or_else(|| std::env::var(from_env::paymaster_env_name_from_network(network)).ok());
+        let sponsored_pay_to = match &config.paymaster_pay_to {
+            Some(pay_to) => Some(pay_to.clone()),
+            None => from_env::paymaster_pay_to_from_env(network)?,
+        }
+        .unwrap_or_default()
+        .into_iter()
+        .map(Address::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("{network}: invalid paymaster payTo address: {e:?}"))?;
+        if paymaster_url.is_some() && sponsored_pay_to.is_empty() {
+            return Err(format!(
+                "{network}: a paymaster requires the recipients it sponsors, in paymaster_pay_to or {}",
+                from_env::paymaster_pay_to_env_name_from_network(network)
+            )
+            .into());
+        }
+        match (bundler_url, paymaster_url) {
+            (Some(bundler_url), paymaster_url) => {
+                let bundler =
+                    Bundler::connect(&bundler_url, paymaster_url.as_deref(), sponsored_pay_to)
+                        .await
+                        .map_err(|e| format!("{network}: failed to connect to bundler: {e}"))?;
+                provider = provider.with_bundler(bundler);
+            }
+            (None, Some(_)) => {
+                return Err(format!("{network}: a paymaster requires a bundler").into());
+            }
+            (None, None) => {}
+        }
+        let safe_env_var = from_env::safe_env_name_from_network(network);
+        let provider = match std::env::var(&safe_env_var).ok() {
+            Some(safe) => {
+                let safe = safe
+                    .parse::<Address>()
+                    .map_err(|e| format!("{safe_env_var}: {e}"))?;
+                provider.with_settlement_safe(safe).await?
+            }
+            None => provider,
+        };
+        Ok(provider)
     }
 }
 
@@ -378,21 +1738,112 @@ where
     /// then the token’s `transferWithAuthorization`. Both run within a single `eth_call`
     /// so the state is shared during simulation.
     ///
+    /// With [`MetaEvmProvider::verify_simulation`], the exact settlement call is simulated as well, tagged and
+    /// sent from the settling address, catching what the transfer simulation can not, e.g. a blacklisted facilitator.
+    ///
     /// # Errors
     /// - [`FacilitatorLocalError::NetworkMismatch`], [`FacilitatorLocalError::SchemeMismatch`], [`FacilitatorLocalError::ReceiverMismatch`] if inputs are inconsistent.
     /// - [`FacilitatorLocalError::InvalidTiming`] if outside `validAfter/validBefore`.
     /// - [`FacilitatorLocalError::InsufficientFunds`] / `FacilitatorLocalError::InsufficientValue` on balance/value checks.
-    /// - [`FacilitatorLocalError::ContractCall`] if on-chain calls revert.
+    /// - [`FacilitatorLocalError::Reverted`] if the transfer simulation reverts for a recognised reason.
+    /// - [`FacilitatorLocalError::ContractCall`] if other on-chain calls fail.
     async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
         let payload = &request.payment_payload;
         let requirements = &request.payment_requirements;
-        let (contract, payment, eip712_domain) =
-            assert_valid_payment(self.inner(), self.chain(), payload, requirements).await?;
+        assert_accepted_asset(self.accepted_assets(), self.quarantine(), requirements)?;
+        if let ExactPaymentPayload::Permit2(_) = payload.payload {
+            let payment = permit2::assert_valid_payment(
+                self.inner(),
+                self.chain(),
+                &self.settlement_addresses(),
+                payload,
+                requirements,
+                None,
+                request.allow_partial,
+            )
+            .await?;
+            payment.simulate(self.inner()).await?;
+            return Ok(VerifyResponse::valid(payment.owner.into()));
+        }
+        if let ExactPaymentPayload::Permit(_) = payload.payload {
+            let payment = permit::assert_valid_payment(
+                self.inner(),
+                self.chain(),
+                &self.settlement_addresses(),
+                payload,
+                requirements,
+            )
+            .await?;
+            payment.simulate(self.inner()).await?;
+            return Ok(VerifyResponse::valid(payment.owner.into()));
+        }
+        if let ExactPaymentPayload::Native(_) = payload.payload {
+            let payment =
+                native::assert_valid_payment(self.inner(), self.chain(), payload, requirements)
+                    .await?;
+            return Ok(VerifyResponse::valid(payment.from.into()));
+        }
+        if let ExactPaymentPayload::Stream(_) = payload.payload {
+            let payment =
+                stream::assert_valid_payment(self.inner(), self.chain(), payload, requirements)
+                    .await?;
+            return Ok(VerifyResponse::valid(payment.sender.into()));
+        }
+        if let ExactPaymentPayload::UserOperation(_) = payload.payload {
+            let bundler = self
+                .bundler()
+                .ok_or(FacilitatorLocalError::UnsupportedNetwork(None))?;
+            let payment =
+                aa::assert_valid_payment(self.inner(), self.chain(), payload, requirements).await?;
+            bundler.simulate(&payment.user_operation).await?;
+            return Ok(VerifyResponse::valid(payment.sender.into()));
+        }
+        let block = pinned_block(self).await?;
+        let (contract, payment, eip712_domain) = assert_valid_payment(
+            self.inner(),
+            self.chain(),
+            payload,
+            requirements,
+            ChainReads::Fetch(block),
+        )
+        .await?;
 
+        assert_receiver(&payment, &self.settlement_addresses())?;
         let signed_message = SignedMessage::extract(&payment, &eip712_domain)?;
         let payer = signed_message.address;
         let hash = signed_message.hash;
-        match signed_message.signature {
+        let receive = payment.authorization == Erc3009Authorization::ReceiveWithAuthorization;
+        let simulated_signature = self
+            .verify_simulation()
+            .then(|| signed_message.signature.clone());
+        // Increase of the recipient's balance by the simulated transfer, if measured.
+        let received = match signed_message.signature {
+            StructuredSignature::EIP6492 { .. } if receive => {
+                return Err(FacilitatorLocalError::InvalidSignature(
+                    payer.into(),
+                    "EIP-6492 signatures can not be settled with receiveWithAuthorization"
+                        .to_string(),
+                ));
+            }
+            StructuredSignature::EIP1271(signature) if receive => {
+                // Only the recipient may call receiveWithAuthorization, so simulate it as the recipient
+                receiveWithAuthorization_0(&contract, &payment, signature)
+                    .from(payment.to.0)
+                    .call()
+                    .block(block)
+                    .into_future()
+                    .instrument(tracing::info_span!("call_receiveWithAuthorization_0",
+                            from = %payment.from,
+                            to = %payment.to,
+                            value = %payment.value,
+                            nonce = %FixedBytes(payment.nonce.0),
+                            token_contract = %contract.address(),
+                            otel.kind = "client",
+                    ))
+                    .await
+                    .map_err(|e| revert::contract_error(Some(payer.into()), e))?;
+                None
+            }
             StructuredSignature::EIP6492 {
                 factory: _,
                 factory_calldata: _,
@@ -405,12 +1856,57 @@ where
                     validator6492.isValidSigWithSideEffects(payer, hash, original);
                 // Prepare the call to simulate transfer the funds
                 let transfer_call = transferWithAuthorization_0(&contract, &payment, inner).await?;
-                // Execute both calls in a single transaction simulation to accommodate for possible smart wallet creation
-                let (is_valid_signature_result, transfer_result) = self
+                // Execute all calls in a single transaction simulation to accommodate for possible smart wallet creation
+                let (is_valid_signature_result, balance_before, transfer_result, balance_after) =
+                    self.inner()
+                        .multicall()
+                        .add(is_valid_signature_call)
+                        .add(contract.balanceOf(payment.to.0))
+                        .add(transfer_call.tx)
+                        .add(contract.balanceOf(payment.to.0))
+                        .block(block)
+                        .aggregate3()
+                        .instrument(tracing::info_span!("call_transferWithAuthorization_0",
+                                from = %transfer_call.from,
+                                to = %transfer_call.to,
+                                value = %transfer_call.value,
+                                valid_after = %transfer_call.valid_after,
+                                valid_before = %transfer_call.valid_before,
+                                nonce = %transfer_call.nonce,
+                                signature = %transfer_call.signature,
+                                token_contract = %transfer_call.contract_address,
+                                otel.kind = "client",
+                        ))
+                        .await
+                        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+                let is_valid_signature_result = is_valid_signature_result
+                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+                if !is_valid_signature_result {
+                    return Err(FacilitatorLocalError::InvalidSignature(
+                        payer.into(),
+                        "Incorrect signature".to_string(),
+                    ));
+                }
+                transfer_result.map_err(|e| {
+                    revert::reverted(Some(payer.into()), Some(e.return_data.clone()))
+                        .unwrap_or_else(|| FacilitatorLocalError::ContractCall(format!("{e}")))
+                })?;
+                balance_after
+                    .ok()
+                    .zip(balance_before.ok())
+                    .map(|(after, before)| after.saturating_sub(before))
+            }
+            StructuredSignature::EIP1271(signature) if self.fee_on_transfer().is_enabled() => {
+                // EOA or EIP-1271 signature: simulate the transfer between two reads of the recipient's balance
+                let transfer_call =
+                    transferWithAuthorization_0(&contract, &payment, signature).await?;
+                let (balance_before, transfer_result, balance_after) = self
                     .inner()
                     .multicall()
-                    .add(is_valid_signature_call)
+                    .add(contract.balanceOf(payment.to.0))
                     .add(transfer_call.tx)
+                    .add(contract.balanceOf(payment.to.0))
+                    .block(block)
                     .aggregate3()
                     .instrument(tracing::info_span!("call_transferWithAuthorization_0",
                             from = %transfer_call.from,
@@ -425,15 +1921,14 @@ where
                     ))
                     .await
                     .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-                let is_valid_signature_result = is_valid_signature_result
-                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
-                if !is_valid_signature_result {
-                    return Err(FacilitatorLocalError::InvalidSignature(
-                        payer.into(),
-                        "Incorrect signature".to_string(),
-                    ));
-                }
-                transfer_result.map_err(|e| FacilitatorLocalError::ContractCall(format!("{e}")))?;
+                transfer_result.map_err(|e| {
+                    revert::reverted(Some(payer.into()), Some(e.return_data.clone()))
+                        .unwrap_or_else(|| FacilitatorLocalError::ContractCall(format!("{e}")))
+                })?;
+                balance_after
+                    .ok()
+                    .zip(balance_before.ok())
+                    .map(|(after, before)| after.saturating_sub(before))
             }
             StructuredSignature::EIP1271(signature) => {
                 // It is EOA or EIP-1271 signature, which we can pass to the transfer simulation
@@ -442,6 +1937,7 @@ where
                 transfer_call
                     .tx
                     .call()
+                    .block(block)
                     .into_future()
                     .instrument(tracing::info_span!("call_transferWithAuthorization_0",
                             from = %transfer_call.from,
@@ -455,11 +1951,84 @@ where
                             otel.kind = "client",
                     ))
                     .await
-                    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+                    .map_err(|e| revert::contract_error(Some(payer.into()), e))?;
+                None
+            }
+        };
+        // A payment to oneself does not change the balance.
+        let received_amount = match received {
+            Some(received) if self.fee_on_transfer().is_enabled() && payment.from != payment.to => {
+                self.fee_on_transfer()
+                    .check(payer.into(), payment.value.into(), received)?
+            }
+            _ => None,
+        };
+        // The transfer simulated above is not sent from the settling address, nor tagged: simulate the exact
+        // settlement call too, to catch e.g. a token blacklisting the facilitator.
+        if let Some(signature) = simulated_signature {
+            let settlement_tag = self
+                .settlement_tagging()
+                .then(|| SettlementTag::from_requirements(requirements));
+            let tag_calldata = |calldata: &Bytes| match &settlement_tag {
+                Some(tag) => tag.append_to(calldata),
+                None => calldata.clone(),
+            };
+            let sender = self.settlement_addresses().first().copied();
+            let (to, calldata, from) = match signature {
+                StructuredSignature::EIP1271(signature) if receive => {
+                    let receive_call = receiveWithAuthorization_0(&contract, &payment, signature);
+                    let calldata = tag_calldata(receive_call.calldata());
+                    (receive_call.target(), calldata, Some(payment.to.0))
+                }
+                StructuredSignature::EIP6492 {
+                    factory,
+                    factory_calldata,
+                    inner,
+                    original: _,
+                } => {
+                    let transfer_call =
+                        transferWithAuthorization_0(&contract, &payment, inner).await?;
+                    let calldata = tag_calldata(transfer_call.tx.calldata());
+                    if is_contract_deployed(self.inner(), &payer).await? {
+                        (transfer_call.tx.target(), calldata, sender)
+                    } else {
+                        let aggregate_call = IMulticall3::aggregate3Call {
+                            calls: vec![
+                                IMulticall3::Call3 {
+                                    allowFailure: true,
+                                    target: factory,
+                                    callData: factory_calldata,
+                                },
+                                IMulticall3::Call3 {
+                                    allowFailure: false,
+                                    target: transfer_call.tx.target(),
+                                    callData: calldata,
+                                },
+                            ],
+                        };
+                        (
+                            MULTICALL3_ADDRESS,
+                            aggregate_call.abi_encode().into(),
+                            sender,
+                        )
+                    }
+                }
+                StructuredSignature::EIP1271(signature) => {
+                    let transfer_call =
+                        transferWithAuthorization_0(&contract, &payment, signature).await?;
+                    let calldata = tag_calldata(transfer_call.tx.calldata());
+                    (transfer_call.tx.target(), calldata, sender)
+                }
+            };
+            if let Some(revert) = simulate_settlement(self.inner(), to, &calldata, from).await? {
+                return Err(revert.verify_error(payer.into()));
             }
         }
 
-        Ok(VerifyResponse::valid(payer.into()))
+        if let Some(warm_cache) = self.warm_cache() {
+            warm_cache.insert(request, eip712_domain);
+        }
+        Ok(VerifyResponse::valid(payer.into()).with_received_amount(received_amount))
     }
 
     /// Settle a verified payment on-chain.
@@ -483,12 +2052,302 @@ where
     async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
         let payload = &request.payment_payload;
         let requirements = &request.payment_requirements;
+        assert_accepted_asset(self.accepted_assets(), self.quarantine(), requirements)?;
+        let settlement_tag = self
+            .settlement_tagging()
+            .then(|| SettlementTag::from_requirements(requirements));
+        let tag_calldata = |calldata: &Bytes| match &settlement_tag {
+            Some(tag) => tag.append_to(calldata),
+            None => calldata.clone(),
+        };
+        if let ExactPaymentPayload::Permit2(_) = payload.payload {
+            let payment = permit2::assert_valid_payment(
+                self.inner(),
+                self.chain(),
+                &self.settlement_addresses(),
+                payload,
+                requirements,
+                request.settle_amount.map(|amount| amount.0),
+                request.allow_partial,
+            )
+            .await?;
+            let intended = request
+                .settle_amount
+                .map_or(requirements.max_amount_required.0, |amount| amount.0);
+            let settled = payment.call.transferDetails.requestedAmount;
+            let partial = (settled < intended).then(|| PartialSettlement {
+                settled_amount: TokenAmount(settled),
+                shortfall: TokenAmount(intended - settled),
+            });
+            if partial.is_some() {
+                tracing::info!(owner = %payment.owner, %settled, %intended, "Settling part of an upto payment");
+            }
+            let receipt = self
+                .send_transaction(MetaTransaction {
+                    to: permit2::PERMIT2_ADDRESS,
+                    calldata: tag_calldata(&payment.calldata()),
+                    confirmations: self.confirmations(),
+                    sender: Some(payment.spender),
+                })
+                .instrument(tracing::info_span!("call_permitWitnessTransferFrom",
+                    owner = %payment.owner,
+                    spender = %payment.spender,
+                    token = %payment.call.permit.permitted.token,
+                    to = %payment.call.transferDetails.to,
+                    amount = %payment.call.transferDetails.requestedAmount,
+                    otel.kind = "client",
+                ))
+                .await?;
+            let success = receipt.status();
+            if !success {
+                tracing::warn!(tx = %receipt.transaction_hash, "permitWitnessTransferFrom failed");
+            }
+            let error_reason = match success {
+                true => None,
+                false => Some(failure_reason(self.inner(), &receipt).await),
+            };
+            return Ok(SettleResponse {
+                success,
+                error_reason,
+                payer: Some(payment.owner.into()),
+                transaction: Some(TransactionHash::Evm(receipt.transaction_hash.0)),
+                network: payload.network,
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial,
+                revert_reason: None,
+                signer: Some(receipt.from.into()),
+                settlement_id: None,
+                simulated: false,
+            });
+        }
+        if let ExactPaymentPayload::Native(_) = payload.payload {
+            let payment =
+                native::assert_valid_payment(self.inner(), self.chain(), payload, requirements)
+                    .await?;
+            let receipt = payment
+                .broadcast(self.inner(), self.confirmations())
+                .await?;
+            let success = receipt.status();
+            if !success {
+                tracing::warn!(tx = %receipt.transaction_hash, "native transfer failed");
+            }
+            let error_reason = match success {
+                true => None,
+                false => Some(failure_reason(self.inner(), &receipt).await),
+            };
+            return Ok(SettleResponse {
+                success,
+                error_reason,
+                payer: Some(payment.from.into()),
+                transaction: Some(TransactionHash::Evm(receipt.transaction_hash.0)),
+                network: payload.network,
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: None,
+                settlement_id: None,
+                simulated: false,
+            });
+        }
+        if let ExactPaymentPayload::Stream(_) = payload.payload {
+            // The stream already pays; settling only confirms it is still flowing.
+            let payment =
+                stream::assert_valid_payment(self.inner(), self.chain(), payload, requirements)
+                    .await?;
+            tracing::info!(sender = %payment.sender, flow_rate = %payment.flow_rate, "Stream payment settled");
+            return Ok(SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: Some(payment.sender.into()),
+                transaction: None,
+                network: payload.network,
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: None,
+                settlement_id: None,
+                simulated: false,
+            });
+        }
+        if let ExactPaymentPayload::UserOperation(_) = payload.payload {
+            let bundler = self
+                .bundler()
+                .ok_or(FacilitatorLocalError::UnsupportedNetwork(None))?;
+            let payment =
+                aa::assert_valid_payment(self.inner(), self.chain(), payload, requirements).await?;
+            let receipt = bundler
+                .submit(&payment.user_operation)
+                .instrument(tracing::info_span!("submit_user_operation",
+                    sender = %payment.sender,
+                    token = %payment.token,
+                    amount = %payment.amount,
+                ))
+                .await?;
+            let success = receipt.success;
+            if !success {
+                tracing::warn!(tx = %receipt.receipt.transaction_hash, "UserOperation failed");
+            }
+            return Ok(SettleResponse {
+                success,
+                error_reason: (!success).then_some(FacilitatorErrorReason::InvalidScheme),
+                payer: Some(payment.sender.into()),
+                transaction: Some(TransactionHash::Evm(receipt.receipt.transaction_hash.0)),
+                network: payload.network,
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: None,
+                settlement_id: None,
+                simulated: false,
+            });
+        }
+        if let ExactPaymentPayload::Permit(_) = payload.payload {
+            let payment = permit::assert_valid_payment(
+                self.inner(),
+                self.chain(),
+                &self.settlement_addresses(),
+                payload,
+                requirements,
+            )
+            .await?;
+            let permit_receipt = self
+                .send_transaction(MetaTransaction {
+                    to: payment.token,
+                    calldata: payment.permit_calldata.clone(),
+                    confirmations: self.confirmations(),
+                    sender: Some(payment.spender),
+                })
+                .instrument(tracing::info_span!("call_permit",
+                    owner = %payment.owner,
+                    spender = %payment.spender,
+                    token = %payment.token,
+                    otel.kind = "client",
+                ))
+                .await?;
+            // A reverted permit is fine if it was already submitted by someone else.
+            if !permit_receipt.status() && payment.allowance(self.inner()).await? < payment.amount {
+                tracing::warn!(tx = %permit_receipt.transaction_hash, "permit failed");
+                return Ok(SettleResponse {
+                    success: false,
+                    error_reason: Some(FacilitatorErrorReason::InsufficientAllowance),
+                    payer: Some(payment.owner.into()),
+                    transaction: Some(TransactionHash::Evm(permit_receipt.transaction_hash.0)),
+                    network: payload.network,
+                    facilitator_version: None,
+                    batch: None,
+                    payment_id: None,
+                    partial: None,
+                    revert_reason: None,
+                    signer: Some(permit_receipt.from.into()),
+                    settlement_id: None,
+                    simulated: false,
+                });
+            }
+            if !payment.simulate_transfer(self.inner()).await? {
+                tracing::warn!(token = %payment.token, "transferFrom returned false");
+                return Ok(SettleResponse {
+                    success: false,
+                    error_reason: Some(FacilitatorErrorReason::UnexpectedSettleError),
+                    payer: Some(payment.owner.into()),
+                    transaction: Some(TransactionHash::Evm(permit_receipt.transaction_hash.0)),
+                    network: payload.network,
+                    facilitator_version: None,
+                    batch: None,
+                    payment_id: None,
+                    partial: None,
+                    revert_reason: None,
+                    signer: Some(permit_receipt.from.into()),
+                    settlement_id: None,
+                    simulated: false,
+                });
+            }
+            let receipt = self
+                .send_transaction(MetaTransaction {
+                    to: payment.token,
+                    calldata: tag_calldata(&payment.transfer_from_calldata()),
+                    confirmations: self.confirmations(),
+                    sender: Some(payment.spender),
+                })
+                .instrument(tracing::info_span!("call_transferFrom",
+                    from = %payment.owner,
+                    to = %payment.pay_to,
+                    value = %payment.amount,
+                    token_contract = %payment.token,
+                    otel.kind = "client",
+                ))
+                .await?;
+            let success = receipt.status();
+            if !success {
+                tracing::warn!(tx = %receipt.transaction_hash, "transferFrom failed");
+            }
+            let error_reason = match success {
+                true => None,
+                false => Some(failure_reason(self.inner(), &receipt).await),
+            };
+            return Ok(SettleResponse {
+                success,
+                error_reason,
+                payer: Some(payment.owner.into()),
+                transaction: Some(TransactionHash::Evm(receipt.transaction_hash.0)),
+                network: payload.network,
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: Some(receipt.from.into()),
+                settlement_id: None,
+                simulated: false,
+            });
+        }
+        // Settling a request just verified: its chain reads are still fresh.
+        let reads = match self.warm_cache().and_then(|cache| cache.take(request)) {
+            Some(domain) => ChainReads::Warm(domain),
+            None => ChainReads::Fetch(pinned_block(self).await?),
+        };
         let (contract, payment, eip712_domain) =
-            assert_valid_payment(self.inner(), self.chain(), payload, requirements).await?;
+            assert_valid_payment(self.inner(), self.chain(), payload, requirements, reads).await?;
 
+        assert_receiver(&payment, &self.settlement_addresses())?;
         let signed_message = SignedMessage::extract(&payment, &eip712_domain)?;
         let payer = signed_message.address;
-        let transaction_receipt_fut = match signed_message.signature {
+        let receive = payment.authorization == Erc3009Authorization::ReceiveWithAuthorization;
+        let (transaction, span) = match signed_message.signature {
+            StructuredSignature::EIP6492 { .. } if receive => {
+                return Err(FacilitatorLocalError::InvalidSignature(
+                    payer.into(),
+                    "EIP-6492 signatures can not be settled with receiveWithAuthorization"
+                        .to_string(),
+                ));
+            }
+            StructuredSignature::EIP1271(signature) if receive => {
+                // receiveWithAuthorization, sent by the recipient itself and never batched
+                let receive_call = receiveWithAuthorization_0(&contract, &payment, signature);
+                let transaction = MetaTransaction {
+                    to: receive_call.target(),
+                    calldata: tag_calldata(receive_call.calldata()),
+                    confirmations: self.confirmations(),
+                    sender: Some(payment.to.0),
+                };
+                let span = tracing::info_span!("call_receiveWithAuthorization_0",
+                    from = %payment.from,
+                    to = %payment.to,
+                    value = %payment.value,
+                    nonce = %FixedBytes(payment.nonce.0),
+                    token_contract = %contract.address(),
+                    sig_kind="EIP1271",
+                    otel.kind = "client",
+                );
+                (transaction, span)
+            }
             StructuredSignature::EIP6492 {
                 factory,
                 factory_calldata,
@@ -499,25 +2358,25 @@ where
                 let transfer_call = transferWithAuthorization_0(&contract, &payment, inner).await?;
                 if is_contract_deployed {
                     // transferWithAuthorization with inner signature
-                    self.send_transaction(MetaTransaction {
+                    let transaction = MetaTransaction {
                         to: transfer_call.tx.target(),
-                        calldata: transfer_call.tx.calldata().clone(),
-                        confirmations: 1,
-                    })
-                    .instrument(
-                        tracing::info_span!("call_transferWithAuthorization_0",
-                            from = %transfer_call.from,
-                            to = %transfer_call.to,
-                            value = %transfer_call.value,
-                            valid_after = %transfer_call.valid_after,
-                            valid_before = %transfer_call.valid_before,
-                            nonce = %transfer_call.nonce,
-                            signature = %transfer_call.signature,
-                            token_contract = %transfer_call.contract_address,
-                            sig_kind="EIP6492.deployed",
-                            otel.kind = "client",
-                        ),
-                    )
+                        calldata: tag_calldata(transfer_call.tx.calldata()),
+                        confirmations: self.confirmations(),
+                        sender: None,
+                    };
+                    let span = tracing::info_span!("call_transferWithAuthorization_0",
+                        from = %transfer_call.from,
+                        to = %transfer_call.to,
+                        value = %transfer_call.value,
+                        valid_after = %transfer_call.valid_after,
+                        valid_before = %transfer_call.valid_before,
+                        nonce = %transfer_call.nonce,
+                        signature = %transfer_call.signature,
+                        token_contract = %transfer_call.contract_address,
+                        sig_kind="EIP6492.deployed",
+                        otel.kind = "client",
+                    );
+                    (transaction, span)
                 } else {
                     // deploy the smart wallet, and transferWithAuthorization with inner signature
                     let deployment_call = IMulticall3::Call3 {
@@ -528,43 +2387,18 @@ where
                     let transfer_with_authorization_call = IMulticall3::Call3 {
                         allowFailure: false,
                         target: transfer_call.tx.target(),
-                        callData: transfer_call.tx.calldata().clone(),
+                        callData: tag_calldata(transfer_call.tx.calldata()),
                     };
                     let aggregate_call = IMulticall3::aggregate3Call {
                         calls: vec![deployment_call, transfer_with_authorization_call],
                     };
-                    self.send_transaction(MetaTransaction {
+                    let transaction = MetaTransaction {
                         to: MULTICALL3_ADDRESS,
                         calldata: aggregate_call.abi_encode().into(),
-                        confirmations: 1,
-                    })
-                    .instrument(
-                        tracing::info_span!("call_transferWithAuthorization_0",
-                            from = %transfer_call.from,
-                            to = %transfer_call.to,
-                            value = %transfer_call.value,
-                            valid_after = %transfer_call.valid_after,
-                            valid_before = %transfer_call.valid_before,
-                            nonce = %transfer_call.nonce,
-                            signature = %transfer_call.signature,
-                            token_contract = %transfer_call.contract_address,
-                            sig_kind="EIP6492.counterfactual",
-                            otel.kind = "client",
-                        ),
-                    )
-                }
-            }
-            StructuredSignature::EIP1271(eip1271_signature) => {
-                let transfer_call =
-                    transferWithAuthorization_0(&contract, &payment, eip1271_signature).await?;
-                // transferWithAuthorization with eip1271 signature
-                self.send_transaction(MetaTransaction {
-                    to: transfer_call.tx.target(),
-                    calldata: transfer_call.tx.calldata().clone(),
-                    confirmations: 1,
-                })
-                .instrument(
-                    tracing::info_span!("call_transferWithAuthorization_0",
+                        confirmations: self.confirmations(),
+                        sender: None,
+                    };
+                    let span = tracing::info_span!("call_transferWithAuthorization_0",
                         from = %transfer_call.from,
                         to = %transfer_call.to,
                         value = %transfer_call.value,
@@ -573,13 +2407,102 @@ where
                         nonce = %transfer_call.nonce,
                         signature = %transfer_call.signature,
                         token_contract = %transfer_call.contract_address,
-                        sig_kind="EIP1271",
+                        sig_kind="EIP6492.counterfactual",
                         otel.kind = "client",
-                    ),
-                )
+                    );
+                    (transaction, span)
+                }
+            }
+            StructuredSignature::EIP1271(eip1271_signature) => {
+                let transfer_call =
+                    transferWithAuthorization_0(&contract, &payment, eip1271_signature).await?;
+                if let Some(batcher) = self.batcher() {
+                    // transferWithAuthorization with eip1271 signature, in a Multicall3 batch
+                    let transfer = BatchedTransfer {
+                        token: transfer_call.contract_address,
+                        calldata: tag_calldata(transfer_call.tx.calldata()),
+                        authorizer: transfer_call.from,
+                        nonce: transfer_call.nonce,
+                    };
+                    // A reverting transfer would fail the whole batch: keep it out.
+                    if let Some(revert) =
+                        simulate_settlement(self.inner(), transfer.token, &transfer.calldata, None)
+                            .await?
+                    {
+                        return Ok(revert.settle_response(payment.from.into(), payload.network));
+                    }
+                    let outcome = batcher
+                        .settle(transfer, |calldata| async move {
+                            self.send_transaction(MetaTransaction {
+                                to: MULTICALL3_ADDRESS,
+                                calldata,
+                                confirmations: self.confirmations(),
+                                sender: None,
+                            })
+                            .await
+                            .map_err(FacilitatorLocalError::from)
+                        })
+                        .instrument(tracing::info_span!("call_transferWithAuthorization_0",
+                            from = %transfer_call.from,
+                            to = %transfer_call.to,
+                            value = %transfer_call.value,
+                            nonce = %transfer_call.nonce,
+                            token_contract = %transfer_call.contract_address,
+                            sig_kind="EIP1271.batched",
+                            otel.kind = "client",
+                        ))
+                        .await?;
+                    return Ok(SettleResponse {
+                        success: outcome.success,
+                        error_reason: (!outcome.success)
+                            .then_some(FacilitatorErrorReason::InvalidScheme),
+                        payer: Some(payment.from.into()),
+                        transaction: Some(TransactionHash::Evm(outcome.receipt.transaction_hash.0)),
+                        network: payload.network,
+                        facilitator_version: None,
+                        batch: Some(outcome.position),
+                        payment_id: None,
+                        partial: None,
+                        revert_reason: None,
+                        signer: Some(outcome.receipt.from.into()),
+                        settlement_id: None,
+                        simulated: false,
+                    });
+                }
+                // transferWithAuthorization with eip1271 signature
+                let transaction = MetaTransaction {
+                    to: transfer_call.tx.target(),
+                    calldata: tag_calldata(transfer_call.tx.calldata()),
+                    confirmations: self.confirmations(),
+                    sender: None,
+                };
+                let span = tracing::info_span!("call_transferWithAuthorization_0",
+                    from = %transfer_call.from,
+                    to = %transfer_call.to,
+                    value = %transfer_call.value,
+                    valid_after = %transfer_call.valid_after,
+                    valid_before = %transfer_call.valid_before,
+                    nonce = %transfer_call.nonce,
+                    signature = %transfer_call.signature,
+                    token_contract = %transfer_call.contract_address,
+                    sig_kind="EIP1271",
+                    otel.kind = "client",
+                );
+                (transaction, span)
             }
         };
-        let receipt = transaction_receipt_fut.await?;
+        // A settlement bound to revert would only burn gas: report why instead of broadcasting it.
+        if let Some(revert) = simulate_settlement(
+            self.inner(),
+            transaction.to,
+            &transaction.calldata,
+            transaction.sender,
+        )
+        .await?
+        {
+            return Ok(revert.settle_response(payment.from.into(), payload.network));
+        }
+        let receipt = self.send_transaction(transaction).instrument(span).await?;
         let success = receipt.status();
         if success {
             tracing::event!(Level::INFO,
@@ -590,9 +2513,17 @@ where
             Ok(SettleResponse {
                 success: true,
                 error_reason: None,
-                payer: payment.from.into(),
+                payer: Some(payment.from.into()),
                 transaction: Some(TransactionHash::Evm(receipt.transaction_hash.0)),
                 network: payload.network,
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: Some(receipt.from.into()),
+                settlement_id: None,
+                simulated: false,
             })
         } else {
             tracing::event!(
@@ -603,23 +2534,137 @@ where
             );
             Ok(SettleResponse {
                 success: false,
-                error_reason: Some(FacilitatorErrorReason::InvalidScheme),
-                payer: payment.from.into(),
+                error_reason: Some(failure_reason(self.inner(), &receipt).await),
+                payer: Some(payment.from.into()),
                 transaction: Some(TransactionHash::Evm(receipt.transaction_hash.0)),
                 network: payload.network,
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: Some(receipt.from.into()),
+                settlement_id: None,
+                simulated: false,
             })
         }
     }
 
     /// Report payment kinds supported by this provider on its current network.
     async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
-        let kinds = vec![SupportedPaymentKind {
-            network: self.chain().network().to_string(),
+        let network = self.chain().network();
+        // Known USDC deployments; tokens from the configuration on networks without any, e.g. custom ones.
+        let mut assets: Vec<MixedAddress> = USDCDeployment::all_by_network(network)
+            .into_iter()
+            .map(|usdc| usdc.address())
+            .collect();
+        if assets.is_empty() {
+            assets = self
+                .accepted_assets()
+                .unwrap_or_default()
+                .iter()
+                .map(|asset| (*asset).into())
+                .collect();
+        }
+        // Quarantined tokens are not advertised until they pass their sanity checks again.
+        if let Some(quarantine) = self.quarantine() {
+            assets.retain(|asset| match Address::try_from(asset.clone()) {
+                Ok(asset) => quarantine.reason(&asset).is_none(),
+                Err(_) => true,
+            });
+        }
+        // Settlements are spread over the signers, if there are several.
+        let signers: Vec<MixedAddress> = match self.settlement_addresses().as_slice() {
+            addresses @ [_, _, ..] => addresses.iter().map(|address| (*address).into()).collect(),
+            _ => Vec::new(),
+        };
+        let mut kinds = Vec::new();
+        match (assets.as_slice(), self.settlement_addresses().first()) {
+            // Several tokens (e.g. native and bridged USDC): one `exact` kind per token.
+            ([_, _, ..], Some(fee_payer)) => {
+                for asset in assets.iter() {
+                    kinds.push(SupportedPaymentKind {
+                        network: network.to_string(),
+                        x402_version: X402Version::V1,
+                        scheme: Scheme::Exact,
+                        extra: Some(SupportedPaymentKindExtra {
+                            fee_payer: (*fee_payer).into(),
+                            spender: None,
+                            asset: Some(asset.clone()),
+                            signers: signers.clone(),
+                        }),
+                    });
+                }
+            }
+            _ => kinds.push(SupportedPaymentKind {
+                network: network.to_string(),
+                x402_version: X402Version::V1,
+                scheme: Scheme::Exact,
+                extra: None,
+            }),
+        }
+        kinds.push(SupportedPaymentKind {
+            network: network.to_string(),
             x402_version: X402Version::V1,
-            scheme: Scheme::Exact,
+            scheme: Scheme::Native,
             extra: None,
-        }];
-        Ok(SupportedPaymentKindsResponse { kinds })
+        });
+        if stream::is_deployed(network) {
+            kinds.push(SupportedPaymentKind {
+                network: network.to_string(),
+                x402_version: X402Version::V1,
+                scheme: Scheme::Stream,
+                extra: None,
+            });
+        }
+        if self.bundler().is_some() {
+            kinds.push(SupportedPaymentKind {
+                network: network.to_string(),
+                x402_version: X402Version::V1,
+                scheme: Scheme::Erc4337,
+                extra: None,
+            });
+        }
+        if let Some(spender) = self.settlement_addresses().first() {
+            kinds.push(SupportedPaymentKind {
+                network: network.to_string(),
+                x402_version: X402Version::V1,
+                scheme: Scheme::Permit2,
+                extra: Some(SupportedPaymentKindExtra {
+                    fee_payer: (*spender).into(),
+                    spender: Some((*spender).into()),
+                    asset: None,
+                    signers: signers.clone(),
+                }),
+            });
+            kinds.push(SupportedPaymentKind {
+                network: network.to_string(),
+                x402_version: X402Version::V1,
+                scheme: Scheme::Upto,
+                extra: Some(SupportedPaymentKindExtra {
+                    fee_payer: (*spender).into(),
+                    spender: Some((*spender).into()),
+                    asset: None,
+                    signers: signers.clone(),
+                }),
+            });
+            kinds.push(SupportedPaymentKind {
+                network: network.to_string(),
+                x402_version: X402Version::V1,
+                scheme: Scheme::Permit,
+                extra: Some(SupportedPaymentKindExtra {
+                    fee_payer: (*spender).into(),
+                    spender: Some((*spender).into()),
+                    asset: None,
+                    signers: signers.clone(),
+                }),
+            });
+        }
+        Ok(SupportedPaymentKindsResponse {
+            kinds,
+            routes: Vec::new(),
+            maintenance: Vec::new(),
+        })
     }
 }
 
@@ -689,25 +2734,32 @@ fn assert_time(
 #[instrument(skip_all, err, fields(
     sender = %sender,
     max_required = %max_amount_required,
-    token_contract = %usdc_contract.address()
+    token_contract = %usdc_contract.address(),
+    block = ?block
 ))]
 async fn assert_enough_balance<P: Provider>(
     usdc_contract: &USDC::USDCInstance<P>,
     sender: &EvmAddress,
     max_amount_required: U256,
+    block: BlockId,
 ) -> Result<(), FacilitatorLocalError> {
-    let balance = usdc_contract
-        .balanceOf(sender.0)
-        .call()
-        .into_future()
-        .instrument(tracing::info_span!(
-            "fetch_token_balance",
-            token_contract = %usdc_contract.address(),
-            sender = %sender,
-            otel.kind = "client"
-        ))
-        .await
-        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    // A backend behind the pinned block fails the read; retry until one that has it answers.
+    let balance = with_lag_retries(|| async {
+        usdc_contract
+            .balanceOf(sender.0)
+            .call()
+            .block(block)
+            .into_future()
+            .instrument(tracing::info_span!(
+                "fetch_token_balance",
+                token_contract = %usdc_contract.address(),
+                sender = %sender,
+                otel.kind = "client"
+            ))
+            .await
+    })
+    .await
+    .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
 
     if balance < max_amount_required {
         Err(FacilitatorLocalError::InsufficientFunds((*sender).into()))
@@ -716,6 +2768,62 @@ async fn assert_enough_balance<P: Provider>(
     }
 }
 
+/// Checks the token would not refuse the transfer outright: it is not `paused()`, and neither the payer nor the
+/// recipient `isBlacklisted(…)`, as with USDC.
+///
+/// Tokens that do not expose these functions are assumed to have no such restriction.
+///
+/// # Errors
+/// Returns [`FacilitatorLocalError::TokenPaused`] or [`FacilitatorLocalError::AddressBlacklisted`] if the transfer would be refused.
+/// Returns [`FacilitatorLocalError::ContractCall`] if the RPC node can not be reached.
+#[instrument(skip_all, err, fields(token_contract = %token, payer = %payer, pay_to = %pay_to))]
+pub async fn assert_token_transferable<P: Provider>(
+    provider: &P,
+    token: Address,
+    payer: Address,
+    pay_to: Address,
+) -> Result<(), FacilitatorLocalError> {
+    let contract = USDC::new(token, provider);
+    let paused = contract
+        .paused()
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_token_paused", token_contract = %token, otel.kind = "client"))
+        .await;
+    if restriction_applies(paused)? {
+        return Err(FacilitatorLocalError::TokenPaused(payer.into()));
+    }
+    for address in [payer, pay_to] {
+        let blacklisted = contract
+            .isBlacklisted(address)
+            .call()
+            .into_future()
+            .instrument(tracing::info_span!("fetch_token_blacklisted", token_contract = %token, address = %address, otel.kind = "client"))
+            .await;
+        if restriction_applies(blacklisted)? {
+            return Err(FacilitatorLocalError::AddressBlacklisted(
+                payer.into(),
+                address.into(),
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Interprets a boolean restriction getter, treating a revert or an empty result as "not supported by the token".
+fn restriction_applies(
+    result: Result<bool, alloy::contract::Error>,
+) -> Result<bool, FacilitatorLocalError> {
+    match result {
+        Ok(applies) => Ok(applies),
+        Err(alloy::contract::Error::ZeroData(..) | alloy::contract::Error::AbiError(_)) => {
+            Ok(false)
+        }
+        Err(alloy::contract::Error::TransportError(e)) if e.as_error_resp().is_some() => Ok(false),
+        Err(e) => Err(FacilitatorLocalError::ContractCall(format!("{e:?}"))),
+    }
+}
+
 /// Verifies that the declared `value` in the payload is sufficient for the required amount.
 ///
 /// This is a static check (not on-chain) that compares two numbers.
@@ -738,6 +2846,90 @@ fn assert_enough_value(
     }
 }
 
+/// Revert of a settlement call, found by simulating it before broadcast.
+#[derive(Debug)]
+struct SimulatedRevert {
+    /// Recognised cause of the revert, if any.
+    revert: Option<Revert>,
+    /// Decoded revert message, or the raw revert data if it is not a message.
+    message: String,
+}
+
+impl SimulatedRevert {
+    fn reason(&self) -> FacilitatorErrorReason {
+        self.revert
+            .map_or(FacilitatorErrorReason::UnexpectedSettleError, |revert| {
+                revert.reason()
+            })
+    }
+
+    fn settle_response(self, payer: MixedAddress, network: Network) -> SettleResponse {
+        SettleResponse {
+            success: false,
+            error_reason: Some(self.reason()),
+            payer: Some(payer),
+            transaction: None,
+            network,
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: Some(self.message),
+            signer: None,
+            settlement_id: None,
+            simulated: false,
+        }
+    }
+
+    /// Verification error for the revert, as for a reverting transfer simulation.
+    fn verify_error(self, payer: MixedAddress) -> FacilitatorLocalError {
+        match self.revert {
+            Some(revert) => FacilitatorLocalError::Reverted(Some(payer), revert),
+            None => FacilitatorLocalError::ContractCall(format!(
+                "settlement simulation reverted: {}",
+                self.message
+            )),
+        }
+    }
+}
+
+/// Simulates a settlement call with `eth_call`, from `from` if set; its revert if it would fail.
+async fn simulate_settlement<P: Provider>(
+    provider: P,
+    to: Address,
+    calldata: &Bytes,
+    from: Option<Address>,
+) -> Result<Option<SimulatedRevert>, FacilitatorLocalError> {
+    let mut request = TransactionRequest::default()
+        .with_to(to)
+        .with_input(calldata.clone());
+    if let Some(from) = from {
+        request = request.with_from(from);
+    }
+    let Err(error) = provider
+        .call(request)
+        .into_future()
+        .instrument(tracing::info_span!("simulate_settlement", to = %to, otel.kind = "client"))
+        .await
+    else {
+        return Ok(None);
+    };
+    // Nodes answer reverts with an error response; anything else is a failure to simulate.
+    let Some(payload) = error.as_error_resp() else {
+        return Err(FacilitatorLocalError::ContractCall(format!("{error:?}")));
+    };
+    let data = payload.as_revert_data();
+    let revert = SimulatedRevert {
+        revert: data.as_ref().and_then(|data| Revert::decode(data)),
+        message: match &data {
+            Some(data) => decode_revert_reason(data).unwrap_or_else(|| data.to_string()),
+            None => payload.message.to_string(),
+        },
+    };
+    tracing::warn!(to = %to, reason = %revert.reason(), message = %revert.message, "Settlement simulation reverted");
+    Ok(Some(revert))
+}
+
 /// Check whether contract code is present at `address`.
 ///
 /// Uses `eth_getCode` against this provider. This is useful after a counterfactual
@@ -746,6 +2938,46 @@ fn assert_enough_value(
 ///
 /// # Errors
 /// Return [`FacilitatorLocalError::ContractCall`] if the RPC call fails.
+/// Why a mined settlement reverted, found by replaying it on the state before its block.
+///
+/// Receipts carry no revert data. Falls back to `invalid_scheme` if the replay succeeds or its revert is not recognised.
+async fn failure_reason<P: Provider>(
+    provider: P,
+    receipt: &TransactionReceipt,
+) -> FacilitatorErrorReason {
+    let replay = async {
+        let tx = provider
+            .get_transaction_by_hash(receipt.transaction_hash)
+            .await
+            .ok()??;
+        let block = receipt.block_number?.checked_sub(1)?;
+        let request = TransactionRequest::default()
+            .with_from(receipt.from)
+            .with_to(receipt.to?)
+            .with_value(tx.value())
+            .with_input(tx.input().clone());
+        let error = provider
+            .call(request)
+            .block(BlockId::number(block))
+            .into_future()
+            .instrument(tracing::info_span!("replay_reverted_settlement",
+                tx = %receipt.transaction_hash,
+                otel.kind = "client",
+            ))
+            .await
+            .err()?;
+        let data = error.as_error_resp()?.as_revert_data()?;
+        Revert::decode(&data)
+    };
+    match replay.await {
+        Some(revert) => {
+            tracing::info!(tx = %receipt.transaction_hash, %revert, "Settlement reverted");
+            revert.reason()
+        }
+        None => FacilitatorErrorReason::InvalidScheme,
+    }
+}
+
 async fn is_contract_deployed<P: Provider>(
     provider: P,
     address: &Address,
@@ -778,13 +3010,30 @@ async fn assert_domain<P: Provider>(
     asset_address: &Address,
     requirements: &PaymentRequirements,
 ) -> Result<Eip712Domain, FacilitatorLocalError> {
-    let usdc = USDCDeployment::by_network(payload.network);
+    // Known USDC deployment paid with, if any; native USDC otherwise, for its name.
+    let known = USDCDeployment::all_by_network(payload.network)
+        .into_iter()
+        .find(|usdc| usdc.address() == (*asset_address).into());
+    let usdc = known.or_else(|| USDCDeployment::try_by_network(payload.network));
     let name = requirements
         .extra
         .as_ref()
         .and_then(|e| e.get("name")?.as_str().map(str::to_string))
-        .or_else(|| usdc.eip712.clone().map(|e| e.name))
-        .ok_or(FacilitatorLocalError::UnsupportedNetwork(None))?;
+        .or_else(|| usdc.and_then(|usdc| usdc.eip712.clone()).map(|e| e.name));
+    // Tokens on custom networks are not known statically: ask the token contract.
+    let name = match name {
+        Some(name) => name,
+        None => token_contract
+            .name()
+            .call()
+            .into_future()
+            .instrument(tracing::info_span!(
+                "fetch_eip712_name",
+                otel.kind = "client"
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?,
+    };
     let chain_id = chain.chain_id;
     let version = requirements
         .extra
@@ -793,10 +3042,8 @@ async fn assert_domain<P: Provider>(
         .and_then(|version| version.as_str().map(|s| s.to_string()));
     let version = if let Some(extra_version) = version {
         Some(extra_version)
-    } else if usdc.address() == (*asset_address).into() {
-        usdc.eip712.clone().map(|e| e.version)
     } else {
-        None
+        known.and_then(|usdc| usdc.eip712.clone().map(|e| e.version))
     };
     let version = if let Some(version) = version {
         version
@@ -812,31 +3059,114 @@ async fn assert_domain<P: Provider>(
             .await
             .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?
     };
-    let domain = eip712_domain! {
-        name: name,
-        version: version,
-        chain_id: chain_id,
-        verifying_contract: *asset_address,
+    let chain_id_as_salt = known
+        .and_then(|usdc| usdc.eip712.as_ref())
+        .is_some_and(|eip712| eip712.chain_id_as_salt);
+    let domain = if chain_id_as_salt {
+        eip712_domain! {
+            name: name,
+            version: version,
+            verifying_contract: *asset_address,
+            salt: B256::from(U256::from(chain_id)),
+        }
+    } else {
+        eip712_domain! {
+            name: name,
+            version: version,
+            chain_id: chain_id,
+            verifying_contract: *asset_address,
+        }
     };
     Ok(domain)
 }
 
+/// Block to check a payment at, per the provider's [`BlockTag`].
+///
+/// For [`BlockTag::Latest`], that is the highest block seen on the network, or `latest` without a [`BlockTracker`].
+async fn pinned_block<P: MetaEvmProvider>(provider: &P) -> Result<BlockId, FacilitatorLocalError> {
+    let block_tag = provider.block_tag();
+    let tracker = match (block_tag, provider.block_tracker()) {
+        (BlockTag::Latest, Some(tracker)) => tracker,
+        _ => return Ok(block_tag.block_id(None)),
+    };
+    let latest = provider
+        .inner()
+        .get_block_number()
+        .into_future()
+        .instrument(tracing::info_span!(
+            "fetch_block_number",
+            otel.kind = "client"
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    Ok(block_tag.block_id(Some(tracker.observe(latest))))
+}
+
+/// Rejects requirements asking for an asset outside `accepted`, if an allowlist is configured.
+pub fn assert_accepted_asset(
+    accepted: Option<&[Address]>,
+    quarantine: Option<&Quarantine>,
+    requirements: &PaymentRequirements,
+) -> Result<(), FacilitatorLocalError> {
+    if accepted.is_none() && quarantine.is_none() {
+        return Ok(());
+    }
+    let asset: EvmAddress = requirements
+        .asset
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let accepted = accepted.is_none_or(|accepted| accepted.contains(&asset.0));
+    let quarantined = quarantine.and_then(|quarantine| quarantine.reason(&asset.0));
+    if let Some(reason) = &quarantined {
+        tracing::warn!(asset=%asset.0, reason, "Rejected payment in a quarantined token");
+    }
+    if accepted && quarantined.is_none() {
+        Ok(())
+    } else {
+        Err(FacilitatorLocalError::UnsupportedAsset(
+            requirements.asset.clone(),
+        ))
+    }
+}
+
+/// Where [`assert_valid_payment`] takes on-chain state from.
+#[derive(Debug)]
+enum ChainReads {
+    /// Read everything, balance at the given block.
+    Fetch(BlockId),
+    /// Reuse a verification of the same request from the [`WarmCache`], with its EIP-712 domain.
+    Warm(Eip712Domain),
+}
+
 /// Runs all preconditions needed for a successful payment:
 /// - Valid scheme, network, and receiver.
 /// - Valid time window (validAfter/validBefore).
+/// - Sufficient value in payload.
 /// - Correct EIP-712 domain construction.
 /// - Sufficient on-chain balance.
-/// - Sufficient value in payload.
-#[instrument(skip_all, err)]
+/// - Token not paused, payer and recipient not blacklisted.
+///
+/// The last three are skipped for [`ChainReads::Warm`].
+#[instrument(skip_all, err, fields(reads = ?reads))]
 async fn assert_valid_payment<P: Provider>(
     provider: P,
     chain: &EvmChain,
     payload: &PaymentPayload,
     requirements: &PaymentRequirements,
+    reads: ChainReads,
 ) -> Result<(USDC::USDCInstance<P>, ExactEvmPayment, Eip712Domain), FacilitatorLocalError> {
     let payment_payload = match &payload.payload {
         ExactPaymentPayload::Evm(payload) => payload,
-        ExactPaymentPayload::Solana(_) => {
+        ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
             return Err(FacilitatorLocalError::UnsupportedNetwork(None));
         }
     };
@@ -862,6 +3192,14 @@ async fn assert_valid_payment<P: Provider>(
             payload.scheme,
         ));
     }
+    if requirements.scheme == Scheme::Upto {
+        // An ERC-3009 authorization transfers its exact value; `upto` needs a Permit2 payload.
+        return Err(FacilitatorLocalError::SchemeMismatch(
+            Some(payer.into()),
+            Scheme::Permit2,
+            payload.scheme,
+        ));
+    }
     let payload_to: EvmAddress = payment_payload.authorization.to;
     let requirements_to: EvmAddress = requirements
         .pay_to
@@ -875,6 +3213,8 @@ async fn assert_valid_payment<P: Provider>(
             requirements_to.to_string(),
         ));
     }
+    let authorization = Erc3009Authorization::from_requirements(requirements)
+        .map_err(FacilitatorLocalError::DecodingError)?;
     let valid_after = payment_payload.authorization.valid_after;
     let valid_before = payment_payload.authorization.valid_before;
     assert_time(payer.into(), valid_after, valid_before)?;
@@ -884,19 +3224,33 @@ async fn assert_valid_payment<P: Provider>(
         .try_into()
         .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
     let contract = USDC::new(asset_address, provider);
-
-    let domain = assert_domain(chain, &contract, payload, &asset_address, requirements).await?;
-
     let amount_required = requirements.max_amount_required.0;
-    assert_enough_balance(
-        &contract,
-        &payment_payload.authorization.from,
-        amount_required,
-    )
-    .await?;
     let value: U256 = payment_payload.authorization.value.into();
     assert_enough_value(&payer, &value, &amount_required)?;
 
+    let domain = match reads {
+        ChainReads::Warm(domain) => domain,
+        ChainReads::Fetch(block) => {
+            let domain =
+                assert_domain(chain, &contract, payload, &asset_address, requirements).await?;
+            assert_enough_balance(
+                &contract,
+                &payment_payload.authorization.from,
+                amount_required,
+                block,
+            )
+            .await?;
+            assert_token_transferable(
+                contract.provider(),
+                asset_address,
+                payer.0,
+                requirements_to.0,
+            )
+            .await?;
+            domain
+        }
+    };
+
     let payment = ExactEvmPayment {
         chain: *chain,
         from: payment_payload.authorization.from,
@@ -906,6 +3260,7 @@ async fn assert_valid_payment<P: Provider>(
         valid_before: payment_payload.authorization.valid_before,
         nonce: payment_payload.authorization.nonce,
         signature: payment_payload.signature.clone(),
+        authorization,
     };
 
     Ok((contract, payment, domain))
@@ -952,6 +3307,42 @@ async fn transferWithAuthorization_0<'a, P: Provider>(
     })
 }
 
+/// Constructs a `receiveWithAuthorization` call for a verified payment payload, to be sent by its recipient.
+#[allow(non_snake_case)]
+fn receiveWithAuthorization_0<'a, P: Provider>(
+    contract: &'a USDC::USDCInstance<P>,
+    payment: &ExactEvmPayment,
+    signature: Bytes,
+) -> SolCallBuilder<&'a P, USDC::receiveWithAuthorization_0Call> {
+    contract.receiveWithAuthorization_0(
+        payment.from.into(),
+        payment.to.into(),
+        payment.value.into(),
+        payment.valid_after.into(),
+        payment.valid_before.into(),
+        FixedBytes(payment.nonce.0),
+        signature,
+    )
+}
+
+/// Checks that the facilitator may send a `receiveWithAuthorization` of `payment`, i.e. that it pays one of
+/// the `settlement_addresses`. Authorizations of `transferWithAuthorization` may pay anyone.
+fn assert_receiver(
+    payment: &ExactEvmPayment,
+    settlement_addresses: &[Address],
+) -> Result<(), FacilitatorLocalError> {
+    if payment.authorization == Erc3009Authorization::ReceiveWithAuthorization
+        && !settlement_addresses.contains(&payment.to.0)
+    {
+        return Err(FacilitatorLocalError::ReceiverMismatch(
+            payment.from.into(),
+            payment.to.to_string(),
+            "a facilitator settlement address".to_string(),
+        ));
+    }
+    Ok(())
+}
+
 /// A structured representation of an Ethereum signature.
 ///
 /// This enum normalizes two supported cases:
@@ -998,7 +3389,7 @@ impl SignedMessage {
     /// - And the raw signature bytes attached to the payment.
     ///
     /// Steps performed:
-    /// 1. Build an in-memory [`TransferWithAuthorization`] struct from the
+    /// 1. Build an in-memory [`TransferWithAuthorization`] (or [`ReceiveWithAuthorization`]) struct from the
     ///    `ExactEvmPayment` fields (`from`, `to`, `value`, validity window, `nonce`).
     /// 2. Compute the **EIP-712 struct hash** for that transfer under the given
     ///    `domain`. This becomes the `hash` field of the signed message.
@@ -1016,15 +3407,26 @@ impl SignedMessage {
         payment: &ExactEvmPayment,
         domain: &Eip712Domain,
     ) -> Result<Self, FacilitatorLocalError> {
-        let transfer_with_authorization = TransferWithAuthorization {
-            from: payment.from.0,
-            to: payment.to.0,
-            value: payment.value.into(),
-            validAfter: payment.valid_after.into(),
-            validBefore: payment.valid_before.into(),
-            nonce: FixedBytes(payment.nonce.0),
+        let eip712_hash = match payment.authorization {
+            Erc3009Authorization::TransferWithAuthorization => TransferWithAuthorization {
+                from: payment.from.0,
+                to: payment.to.0,
+                value: payment.value.into(),
+                validAfter: payment.valid_after.into(),
+                validBefore: payment.valid_before.into(),
+                nonce: FixedBytes(payment.nonce.0),
+            }
+            .eip712_signing_hash(domain),
+            Erc3009Authorization::ReceiveWithAuthorization => ReceiveWithAuthorization {
+                from: payment.from.0,
+                to: payment.to.0,
+                value: payment.value.into(),
+                validAfter: payment.valid_after.into(),
+                validBefore: payment.valid_before.into(),
+                nonce: FixedBytes(payment.nonce.0),
+            }
+            .eip712_signing_hash(domain),
         };
-        let eip712_hash = transfer_with_authorization.eip712_signing_hash(domain);
         let expected_address = payment.from;
         let structured_signature: StructuredSignature = payment.signature.clone().try_into()?;
         let signed_message = Self {
@@ -1110,6 +3512,8 @@ impl TryFrom<Vec<u8>> for StructuredSignature {
 /// - **Subsequent calls**: Increments the cached nonce locally without querying the RPC.
 /// - **Per-address tracking**: Each address has its own cached nonce, allowing concurrent
 ///   transaction submission from multiple addresses.
+/// - **Refresh on error**: [`PendingNonceManager::reset`] drops the cached nonce of an address after a failed
+///   broadcast, which may have left a gap or found the cache stale, so that the next call fetches it again.
 ///
 /// # Thread Safety
 ///
@@ -1123,6 +3527,22 @@ pub struct PendingNonceManager {
     nonces: Arc<DashMap<alloy::primitives::Address, Arc<Mutex<u64>>>>,
 }
 
+/// Sentinel of a nonce not fetched yet.
+const NONCE_UNKNOWN: u64 = u64::MAX;
+
+impl PendingNonceManager {
+    /// Forgets the cached nonce of `address`: the next one is fetched from the `pending` block.
+    pub async fn reset(&self, address: alloy::primitives::Address) {
+        let nonce = self
+            .nonces
+            .get(&address)
+            .map(|nonce| Arc::clone(nonce.value()));
+        if let Some(nonce) = nonce {
+            *nonce.lock().await = NONCE_UNKNOWN;
+        }
+    }
+}
+
 #[async_trait]
 impl NonceManager for PendingNonceManager {
     async fn get_next_nonce<P, N>(
@@ -1134,21 +3554,18 @@ impl NonceManager for PendingNonceManager {
         P: Provider<N>,
         N: alloy::network::Network,
     {
-        // Use `u64::MAX` as a sentinel value to indicate that the nonce has not been fetched yet.
-        const NONE: u64 = u64::MAX;
-
         // Locks dashmap internally for a short duration to clone the `Arc`.
         // We also don't want to hold the dashmap lock through the await point below.
         let nonce = {
             let rm = self
                 .nonces
                 .entry(address)
-                .or_insert_with(|| Arc::new(Mutex::new(NONE)));
+                .or_insert_with(|| Arc::new(Mutex::new(NONCE_UNKNOWN)));
             Arc::clone(rm.value())
         };
 
         let mut nonce = nonce.lock().await;
-        let new_nonce = if *nonce == NONE {
+        let new_nonce = if *nonce == NONCE_UNKNOWN {
             // Initialize the nonce if we haven't seen this account before.
             tracing::trace!(%address, "fetching nonce");
             provider.get_transaction_count(address).pending().await?
@@ -0,0 +1,406 @@
+//! Failover between several RPC endpoints of an EVM network.
+//!
+//! A single RPC endpoint is the main availability weakness of a facilitator. `RPC_URL_<NETWORK>` (or `rpc_url` in
+//! the config file) may list several endpoints, comma-separated, in order of preference. The provider of the network
+//! then sends its requests through an [`RpcFailover`], to the first usable endpoint:
+//! - a request failing at the transport level (connection error, timeout, HTTP error) or rate-limited is sent to the
+//!   next endpoint. Broadcasts are not, as they may have reached the failing endpoint; the settlement flow sends
+//!   them again;
+//! - an endpoint failing [`FAILURE_THRESHOLD`] requests in a row is down, and only tried once the others failed too;
+//! - every `RPC_FAILOVER_CHECK_INTERVAL_SECS`, every endpoint is asked for its latest block. An endpoint answering is
+//!   up again, unless it lags more than `RPC_MAX_LAG_BLOCKS` behind the most advanced one.
+//!
+//! Requests fail back to a preferred endpoint as soon as it is usable again. Switches between endpoints are logged
+//! and counted in the `x402.rpc.failovers` metric; `GET /admin/rpc` reports the state of every endpoint.
+//!
+//! Environment variables used:
+//! - `RPC_MAX_LAG_BLOCKS` — blocks an endpoint may lag behind the others before it is skipped (default: `5`),
+//! - `RPC_FAILOVER_CHECK_INTERVAL_SECS` — seconds between checks of the endpoints (default: `15`).
+
+use alloy::primitives::U64;
+use alloy::rpc::client::{BuiltInConnectionString, RpcClient};
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{BoxTransport, TransportError, TransportErrorKind, TransportFut};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tower::{Service, ServiceExt};
+use url::Url;
+
+use crate::chain::NetworkProvider;
+use crate::chain::rpc_retry;
+use crate::from_env;
+use crate::network::Network;
+use crate::provider_cache::{ProviderCache, ProviderMap};
+
+/// Requests an endpoint may fail in a row before it is down.
+pub const FAILURE_THRESHOLD: u32 = 3;
+/// Default lag, in blocks, after which an endpoint is skipped.
+const DEFAULT_MAX_LAG_BLOCKS: u64 = 5;
+/// Default interval between checks, in seconds.
+const DEFAULT_INTERVAL_SECS: u64 = 15;
+
+/// Splits a comma-separated list of RPC URLs, in order of preference.
+pub fn rpc_urls(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Scheme and host of `url`, without the path or credentials where API keys usually hide.
+fn redact(url: &str) -> String {
+    match Url::parse(url) {
+        Ok(url) => match url.port() {
+            Some(port) => format!(
+                "{}://{}:{port}",
+                url.scheme(),
+                url.host_str().unwrap_or_default()
+            ),
+            None => format!("{}://{}", url.scheme(), url.host_str().unwrap_or_default()),
+        },
+        Err(_) => "<invalid URL>".to_string(),
+    }
+}
+
+/// State of an RPC endpoint, as reported by `GET /admin/rpc`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EndpointHealth {
+    /// Scheme and host of the endpoint.
+    pub endpoint: String,
+    /// Whether requests are sent to this endpoint now.
+    pub active: bool,
+    pub up: bool,
+    pub lagging: bool,
+    pub consecutive_failures: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Default)]
+struct EndpointState {
+    consecutive_failures: u32,
+    down: bool,
+    lagging: bool,
+    block_number: Option<u64>,
+    last_error: Option<String>,
+}
+
+impl EndpointState {
+    fn usable(&self) -> bool {
+        !self.down && !self.lagging
+    }
+}
+
+struct Endpoint {
+    label: String,
+    transport: BoxTransport,
+    state: Mutex<EndpointState>,
+}
+
+struct RpcFailoverInner {
+    network: Network,
+    endpoints: Vec<Endpoint>,
+    max_lag_blocks: u64,
+    /// Endpoint the last request was answered by.
+    active: AtomicUsize,
+}
+
+/// Alloy transport failing over between the RPC endpoints of a network, see the [module docs](self).
+///
+/// Cheap to clone: all clones share the same endpoints and states.
+#[derive(Clone)]
+pub struct RpcFailover {
+    inner: Arc<RpcFailoverInner>,
+}
+
+impl std::fmt::Debug for RpcFailover {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RpcFailover")
+            .field("network", &self.inner.network)
+            .field("endpoints", &self.health())
+            .finish()
+    }
+}
+
+impl RpcFailover {
+    /// Connects to every endpoint of `urls`, in order of preference.
+    ///
+    /// The lag tolerance is read from `RPC_MAX_LAG_BLOCKS`.
+    pub async fn connect(
+        network: Network,
+        urls: &[String],
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let max_lag_blocks = match std::env::var(from_env::ENV_RPC_MAX_LAG_BLOCKS) {
+            Ok(value) => value
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_RPC_MAX_LAG_BLOCKS))?,
+            Err(_) => DEFAULT_MAX_LAG_BLOCKS,
+        };
+        let mut endpoints = Vec::with_capacity(urls.len());
+        for url in urls {
+            let transport = BuiltInConnectionString::from_str(url)
+                .map_err(|e| format!("{network}: {}: {e}", redact(url)))?
+                .connect_boxed()
+                .await
+                .map_err(|e| format!("Failed to connect to {network}: {}: {e}", redact(url)))?;
+            endpoints.push(Endpoint {
+                label: redact(url),
+                transport,
+                state: Mutex::new(EndpointState::default()),
+            });
+        }
+        if endpoints.is_empty() {
+            return Err(format!("{network}: no RPC URL configured").into());
+        }
+        Ok(Self {
+            inner: Arc::new(RpcFailoverInner {
+                network,
+                endpoints,
+                max_lag_blocks,
+                active: AtomicUsize::new(0),
+            }),
+        })
+    }
+
+    /// State of every endpoint, in order of preference.
+    pub fn health(&self) -> Vec<EndpointHealth> {
+        let active = self.inner.active.load(Ordering::Relaxed);
+        self.inner
+            .endpoints
+            .iter()
+            .enumerate()
+            .map(|(index, endpoint)| {
+                let state = endpoint.state.lock().expect("endpoint lock poisoned");
+                EndpointHealth {
+                    endpoint: endpoint.label.clone(),
+                    active: index == active,
+                    up: !state.down,
+                    lagging: state.lagging,
+                    consecutive_failures: state.consecutive_failures,
+                    block_number: state.block_number,
+                    last_error: state.last_error.clone(),
+                }
+            })
+            .collect()
+    }
+
+    /// Endpoints to try a request on: the usable ones in order of preference, then the others as a last resort.
+    fn candidates(&self) -> Vec<usize> {
+        let usable: Vec<bool> = self
+            .inner
+            .endpoints
+            .iter()
+            .map(|endpoint| {
+                endpoint
+                    .state
+                    .lock()
+                    .expect("endpoint lock poisoned")
+                    .usable()
+            })
+            .collect();
+        let (mut first, last): (Vec<usize>, Vec<usize>) =
+            (0..usable.len()).partition(|index| usable[*index]);
+        first.extend(last);
+        first
+    }
+
+    /// Records that endpoint `index` answered.
+    fn answered(&self, index: usize) {
+        let endpoint = &self.inner.endpoints[index];
+        endpoint
+            .state
+            .lock()
+            .expect("endpoint lock poisoned")
+            .consecutive_failures = 0;
+        let previous = self.inner.active.swap(index, Ordering::Relaxed);
+        if previous != index {
+            tracing::warn!(
+                monotonic_counter.x402.rpc.failovers = 1,
+                network = %self.inner.network,
+                from = self.inner.endpoints[previous].label,
+                to = endpoint.label,
+                "RPC endpoint switched"
+            );
+        }
+    }
+
+    /// Records that endpoint `index` failed with `error`.
+    fn failed(&self, index: usize, error: String) {
+        let endpoint = &self.inner.endpoints[index];
+        let mut state = endpoint.state.lock().expect("endpoint lock poisoned");
+        state.consecutive_failures += 1;
+        if !state.down && state.consecutive_failures >= FAILURE_THRESHOLD {
+            state.down = true;
+            tracing::warn!(network = %self.inner.network, endpoint = endpoint.label, error, "RPC endpoint down");
+        }
+        state.last_error = Some(error);
+    }
+
+    /// Asks every endpoint for its latest block, bringing back the ones answering and not lagging.
+    pub async fn check(&self) {
+        let mut block_numbers = Vec::with_capacity(self.inner.endpoints.len());
+        for endpoint in &self.inner.endpoints {
+            let client = RpcClient::new(endpoint.transport.clone(), false);
+            let block_number = client
+                .request_noparams::<U64>("eth_blockNumber")
+                .await
+                .map(|block_number| block_number.to::<u64>())
+                .map_err(|e| e.to_string());
+            block_numbers.push(block_number);
+        }
+        let best = block_numbers
+            .iter()
+            .filter_map(|block_number| block_number.as_ref().ok())
+            .max()
+            .copied();
+        for (endpoint, block_number) in self.inner.endpoints.iter().zip(block_numbers) {
+            let mut state = endpoint.state.lock().expect("endpoint lock poisoned");
+            match block_number {
+                Ok(block_number) => {
+                    let lagging = best.is_some_and(|best| {
+                        best.saturating_sub(block_number) > self.inner.max_lag_blocks
+                    });
+                    if state.down {
+                        tracing::info!(network = %self.inner.network, endpoint = endpoint.label, "RPC endpoint up again");
+                    }
+                    if lagging && !state.lagging {
+                        tracing::warn!(network = %self.inner.network, endpoint = endpoint.label, block_number, best, "RPC endpoint lagging");
+                    }
+                    state.down = false;
+                    state.consecutive_failures = 0;
+                    state.lagging = lagging;
+                    state.block_number = Some(block_number);
+                }
+                Err(error) => {
+                    if !state.down {
+                        tracing::warn!(network = %self.inner.network, endpoint = endpoint.label, error, "RPC endpoint down");
+                    }
+                    state.down = true;
+                    state.last_error = Some(error);
+                }
+            }
+        }
+    }
+}
+
+impl Service<RequestPacket> for RpcFailover {
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let failover = self.clone();
+        Box::pin(async move {
+            let broadcast = rpc_retry::is_broadcast(&request);
+            let mut last = None;
+            for index in failover.candidates() {
+                let transport = failover.inner.endpoints[index].transport.clone();
+                let result = transport.oneshot(request.clone()).await;
+                let failed = match &result {
+                    Err(error) => Some(error.to_string()),
+                    Ok(_) if rpc_retry::is_transient_result(&result) => {
+                        Some("rate limited".to_string())
+                    }
+                    Ok(_) => None,
+                };
+                match failed {
+                    None => {
+                        failover.answered(index);
+                        return result;
+                    }
+                    Some(error) => {
+                        failover.failed(index, error);
+                        if broadcast {
+                            return result;
+                        }
+                        last = Some(result);
+                    }
+                }
+            }
+            last.unwrap_or_else(|| Err(TransportErrorKind::custom_str("No RPC endpoint")))
+        })
+    }
+}
+
+/// Reads the interval between checks from `RPC_FAILOVER_CHECK_INTERVAL_SECS`.
+pub fn interval_from_env() -> Result<Duration, String> {
+    let secs = match std::env::var(from_env::ENV_RPC_FAILOVER_CHECK_INTERVAL_SECS) {
+        Ok(value) => value
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("{}: {e}", from_env::ENV_RPC_FAILOVER_CHECK_INTERVAL_SECS))?,
+        Err(_) => DEFAULT_INTERVAL_SECS,
+    };
+    if secs == 0 {
+        return Err(format!(
+            "{} must be positive",
+            from_env::ENV_RPC_FAILOVER_CHECK_INTERVAL_SECS
+        ));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Failover transports of the EVM networks with several endpoints.
+pub fn failovers(providers: &ProviderCache) -> Vec<RpcFailover> {
+    providers
+        .values()
+        .filter_map(|provider| match provider {
+            NetworkProvider::Evm(provider) => provider.rpc_failover().cloned(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks the endpoints of every network with several every `interval` until cancelled.
+pub fn spawn(
+    providers: Arc<ProviderCache>,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                _ = ticker.tick() => {
+                    for failover in failovers(&providers) {
+                        failover.check().await;
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_split_and_redacted() {
+        let urls = rpc_urls("https://base.example/v2/secret, https://backup.example:8545 ,");
+        assert_eq!(
+            urls,
+            vec![
+                "https://base.example/v2/secret".to_string(),
+                "https://backup.example:8545".to_string()
+            ]
+        );
+        assert_eq!(redact(&urls[0]), "https://base.example");
+    }
+}
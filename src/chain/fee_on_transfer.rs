@@ -0,0 +1,116 @@
+//! Detection of fee-on-transfer tokens.
+//!
+//! Some ERC-20 tokens deduct a fee from every transfer, so the recipient gets less than the signed value and a
+//! resource server paid in them is shortchanged. `/verify` of an ERC-3009 payment simulates the transfer between
+//! two reads of the recipient's balance, in one Multicall3 `eth_call`, and compares what arrived with the value.
+//! A shortfall is then handled as configured:
+//! - `reject` (default) — the payment is invalid, with reason `fee_on_transfer`;
+//! - `report` — the payment is valid, and `receivedAmount` of the verify response tells what `payTo` will get;
+//! - `ignore` — no check is made.
+//!
+//! `receiveWithAuthorization` payments are not checked, as only their recipient can simulate them.
+//!
+//! Environment variables used:
+//! - `FEE_ON_TRANSFER` — `reject`, `report` or `ignore`.
+
+use alloy::primitives::U256;
+use std::str::FromStr;
+
+use crate::chain::FacilitatorLocalError;
+use crate::from_env;
+use crate::types::{MixedAddress, TokenAmount};
+
+/// Handling of payments whose recipient would receive less than the signed value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeeOnTransfer {
+    #[default]
+    Reject,
+    Report,
+    Ignore,
+}
+
+impl FromStr for FeeOnTransfer {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "reject" => Ok(Self::Reject),
+            "report" => Ok(Self::Report),
+            "ignore" => Ok(Self::Ignore),
+            other => Err(format!(
+                "unknown policy {other:?}, expected reject, report or ignore"
+            )),
+        }
+    }
+}
+
+impl FeeOnTransfer {
+    /// Reads `FEE_ON_TRANSFER`, rejecting fee-on-transfer tokens if unset.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var(from_env::ENV_FEE_ON_TRANSFER) {
+            Ok(value) => value
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_FEE_ON_TRANSFER)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    /// Whether transfers are simulated between balance reads.
+    pub fn is_enabled(self) -> bool {
+        self != Self::Ignore
+    }
+
+    /// Applies the policy to a simulated transfer of `value` that raised the recipient's balance by `received`.
+    ///
+    /// Returns the amount to report as received, if short of `value`.
+    pub fn check(
+        self,
+        payer: MixedAddress,
+        value: U256,
+        received: U256,
+    ) -> Result<Option<TokenAmount>, FacilitatorLocalError> {
+        if received >= value {
+            return Ok(None);
+        }
+        match self {
+            Self::Reject => Err(FacilitatorLocalError::FeeOnTransfer(
+                payer,
+                format!("recipient would receive {received} of {value}"),
+            )),
+            Self::Report => Ok(Some(TokenAmount(received))),
+            Self::Ignore => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    #[test]
+    fn policies_handle_shortfall() {
+        let payer: MixedAddress = Address::repeat_byte(1).into();
+        let value = U256::from(1000);
+        for policy in [
+            FeeOnTransfer::Reject,
+            FeeOnTransfer::Report,
+            FeeOnTransfer::Ignore,
+        ] {
+            assert_eq!(policy.check(payer.clone(), value, value).unwrap(), None);
+        }
+        let short = U256::from(990);
+        assert!(matches!(
+            FeeOnTransfer::Reject.check(payer.clone(), value, short),
+            Err(FacilitatorLocalError::FeeOnTransfer(..))
+        ));
+        assert_eq!(
+            FeeOnTransfer::Report
+                .check(payer.clone(), value, short)
+                .unwrap(),
+            Some(TokenAmount(short))
+        );
+        assert_eq!(" Report ".parse(), Ok(FeeOnTransfer::Report));
+        assert!("skip".parse::<FeeOnTransfer>().is_err());
+    }
+}
@@ -0,0 +1,155 @@
+//! Gas pricing of settlement transactions, tunable per network.
+//!
+//! By default, EIP-1559 transactions are priced by the provider's estimator (twice the base fee plus the suggested
+//! priority fee), and legacy ones at the node's gas price. A [`FeeStrategy`] lets operators trade settlement cost
+//! for inclusion speed instead:
+//! - `mode` — `eip1559` or `legacy` transactions, overriding what the network is known to support;
+//! - `max_priority_fee_per_gas` — tip paid to validators, in wei, instead of the node's suggestion;
+//! - `max_fee_multiplier` — `maxFeePerGas` as a multiple of the latest base fee, plus the tip. On legacy
+//!   transactions, multiplies the node's gas price.
+//!
+//! Set as `fee_strategy` of a network in `CONFIG_FILE`, e.g.
+//! `{ max_priority_fee_per_gas = 1000000, max_fee_multiplier = 1.25 }`, or with `FEE_STRATEGY_<NETWORK>`, e.g.
+//! `eip1559,max_priority_fee_per_gas=1000000,max_fee_multiplier=1.25` or `legacy`.
+
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// Transaction type settlements are sent as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeMode {
+    Eip1559,
+    Legacy,
+}
+
+impl FromStr for FeeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "eip1559" => Ok(FeeMode::Eip1559),
+            "legacy" => Ok(FeeMode::Legacy),
+            other => Err(format!(
+                "unknown fee mode {other}, expected eip1559 or legacy"
+            )),
+        }
+    }
+}
+
+/// Gas pricing settings of a network, see the [module docs](self).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeStrategy {
+    #[serde(default)]
+    pub mode: Option<FeeMode>,
+    /// Priority fee per gas of EIP-1559 transactions, in wei; the node's suggestion if unset.
+    #[serde(default)]
+    pub max_priority_fee_per_gas: Option<u64>,
+    /// Multiple of the base fee, or of the legacy gas price, transactions pay at most.
+    #[serde(default)]
+    pub max_fee_multiplier: Option<f64>,
+}
+
+/// Fees set on a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GasPrice {
+    Eip1559 {
+        max_fee_per_gas: u128,
+        max_priority_fee_per_gas: u128,
+    },
+    Legacy(u128),
+}
+
+impl FeeStrategy {
+    /// Whether transactions are sent as EIP-1559 ones, `default` being what the network supports.
+    pub fn eip1559(&self, default: bool) -> bool {
+        self.mode.map_or(default, |mode| mode == FeeMode::Eip1559)
+    }
+
+    /// Whether EIP-1559 fees are computed here rather than by the provider's estimator.
+    pub fn overrides_eip1559(&self) -> bool {
+        self.max_priority_fee_per_gas.is_some() || self.max_fee_multiplier.is_some()
+    }
+
+    /// `maxFeePerGas` for a block of `base_fee` and a tip of `priority_fee`.
+    pub fn max_fee(&self, base_fee: u128, priority_fee: u128) -> u128 {
+        multiply(base_fee, self.max_fee_multiplier.unwrap_or(2.0)).saturating_add(priority_fee)
+    }
+
+    /// Legacy gas price to pay, given the node's `gas_price`.
+    pub fn legacy_gas_price(&self, gas_price: u128) -> u128 {
+        self.max_fee_multiplier
+            .map_or(gas_price, |multiplier| multiply(gas_price, multiplier))
+    }
+
+    /// Rejects multipliers that would price transactions below the base fee or gas price.
+    pub fn validate(&self) -> Result<(), String> {
+        match self.max_fee_multiplier {
+            Some(multiplier) if !multiplier.is_finite() || multiplier < 1.0 => Err(format!(
+                "max_fee_multiplier must be at least 1, got {multiplier}"
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+fn multiply(fee: u128, multiplier: f64) -> u128 {
+    (fee as f64 * multiplier).ceil() as u128
+}
+
+impl FromStr for FeeStrategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut strategy = FeeStrategy::default();
+        for setting in s.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+            match setting.split_once('=') {
+                None => strategy.mode = Some(setting.parse()?),
+                Some(("max_priority_fee_per_gas", value)) => {
+                    strategy.max_priority_fee_per_gas = Some(
+                        value
+                            .trim()
+                            .parse()
+                            .map_err(|e| format!("invalid {setting}: {e}"))?,
+                    );
+                }
+                Some(("max_fee_multiplier", value)) => {
+                    let multiplier: f64 = value
+                        .trim()
+                        .parse()
+                        .map_err(|e| format!("invalid {setting}: {e}"))?;
+                    strategy.max_fee_multiplier = Some(multiplier);
+                }
+                Some((key, _)) => return Err(format!("unknown fee strategy setting {key}")),
+            }
+        }
+        strategy.validate()?;
+        Ok(strategy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_and_prices() {
+        let strategy: FeeStrategy =
+            "eip1559, max_priority_fee_per_gas=1000, max_fee_multiplier=1.5"
+                .parse()
+                .unwrap();
+        assert!(strategy.eip1559(false));
+        assert!(strategy.overrides_eip1559());
+        assert_eq!(strategy.max_fee(10_000, 1000), 16_000);
+        assert_eq!(strategy.legacy_gas_price(3), 5);
+
+        let legacy: FeeStrategy = "legacy".parse().unwrap();
+        assert!(!legacy.eip1559(true));
+        assert!(!legacy.overrides_eip1559());
+        assert_eq!(legacy.legacy_gas_price(3), 3);
+
+        assert!("max_fee_multiplier=0.5".parse::<FeeStrategy>().is_err());
+        assert!("turbo".parse::<FeeStrategy>().is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Pluggable sources of EIP-1559 fee suggestions for settlement transactions.
+//!
+//! By default, settlements are priced by the provider's estimator or the [`FeeStrategy`] of the network. On
+//! congested chains, a [`GasOracle`] can suggest the base fee and priority fee instead:
+//! - `fee_history` — `eth_feeHistory` of the network's own RPC: the base fee of the next block, and the median of
+//!   a reward percentile over recent blocks as priority fee;
+//! - `api` — an external gas API answering `{"baseFeePerGas": "…", "maxPriorityFeePerGas": "…"}` in wei, with
+//!   `{network}` and `{chainId}` placeholders in its URL, subject to the outbound policy.
+//!
+//! The [`FeeStrategy`] still applies on top of the suggestion: its `max_priority_fee_per_gas` wins over the
+//! suggested one, and `maxFeePerGas` is the suggested base fee times `max_fee_multiplier`, plus the priority fee.
+//! When the oracle fails, settlements fall back to the default pricing. Legacy transactions are not priced by
+//! oracles.
+//!
+//! Set as `gas_oracle` of a network in `CONFIG_FILE`, e.g. `{ kind = "fee_history", blocks = 20,
+//! percentile = 60 }`, or with `GAS_ORACLE_<NETWORK>`, e.g. `fee_history`, `fee_history:20:60` or
+//! `https://gas.example/{chainId}`.
+//!
+//! [`FeeStrategy`]: crate::chain::fee_strategy::FeeStrategy
+
+use alloy::eips::BlockNumberOrTag;
+use alloy::providers::{DynProvider, Provider};
+use alloy::rpc::types::FeeHistory;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use url::Url;
+
+use crate::chain::FacilitatorLocalError;
+use crate::network::Network;
+use crate::outbound::OutboundPolicy;
+use crate::types::TokenAmount;
+
+/// Default number of blocks [`FeeHistoryOracle`] looks back.
+const DEFAULT_BLOCKS: u64 = 10;
+/// Default reward percentile [`FeeHistoryOracle`] suggests as priority fee.
+const DEFAULT_PERCENTILE: f64 = 50.0;
+
+/// Fees suggested for the next block, in wei.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FeeSuggestion {
+    pub base_fee_per_gas: u128,
+    pub max_priority_fee_per_gas: u128,
+}
+
+/// A source of fee suggestions.
+#[async_trait]
+pub trait GasOracle: Send + Sync + std::fmt::Debug {
+    /// Name of the oracle, for logs.
+    fn name(&self) -> &'static str;
+
+    /// Fees to pay in the next block, read through `provider` if the oracle is on-chain.
+    async fn suggest(&self, provider: &DynProvider)
+    -> Result<FeeSuggestion, FacilitatorLocalError>;
+}
+
+fn oracle_error(name: &str, message: impl std::fmt::Display) -> FacilitatorLocalError {
+    FacilitatorLocalError::ContractCall(format!("{name} gas oracle: {message}"))
+}
+
+/// Suggestions from `eth_feeHistory`, see the [module docs](self).
+#[derive(Debug)]
+pub struct FeeHistoryOracle {
+    blocks: u64,
+    percentile: f64,
+}
+
+impl FeeHistoryOracle {
+    pub fn new(blocks: u64, percentile: f64) -> Self {
+        Self { blocks, percentile }
+    }
+
+    /// Base fee of the next block, and the median reward of the blocks at the percentile.
+    fn suggestion(history: &FeeHistory) -> Option<FeeSuggestion> {
+        let base_fee_per_gas = *history.base_fee_per_gas.last()?;
+        let mut rewards: Vec<u128> = history
+            .reward
+            .as_ref()?
+            .iter()
+            .filter_map(|rewards| rewards.first().copied())
+            .collect();
+        rewards.sort_unstable();
+        let max_priority_fee_per_gas = *rewards.get(rewards.len() / 2)?;
+        Some(FeeSuggestion {
+            base_fee_per_gas,
+            max_priority_fee_per_gas,
+        })
+    }
+}
+
+#[async_trait]
+impl GasOracle for FeeHistoryOracle {
+    fn name(&self) -> &'static str {
+        "fee_history"
+    }
+
+    async fn suggest(
+        &self,
+        provider: &DynProvider,
+    ) -> Result<FeeSuggestion, FacilitatorLocalError> {
+        let history = provider
+            .get_fee_history(self.blocks, BlockNumberOrTag::Latest, &[self.percentile])
+            .await
+            .map_err(|e| oracle_error(self.name(), e))?;
+        Self::suggestion(&history).ok_or_else(|| oracle_error(self.name(), "empty fee history"))
+    }
+}
+
+/// Answer of a gas API, in wei.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiFees {
+    base_fee_per_gas: TokenAmount,
+    max_priority_fee_per_gas: TokenAmount,
+}
+
+/// Suggestions from an external gas API, see the [module docs](self).
+#[derive(Debug)]
+pub struct ApiGasOracle {
+    http: reqwest::Client,
+    url: String,
+}
+
+impl ApiGasOracle {
+    /// Oracle for `network` querying `url`, its placeholders replaced, with `http`.
+    pub fn new(url: &str, network: Network, chain_id: u64, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            url: url
+                .replace("{network}", &network.to_string())
+                .replace("{chainId}", &chain_id.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+impl GasOracle for ApiGasOracle {
+    fn name(&self) -> &'static str {
+        "api"
+    }
+
+    async fn suggest(
+        &self,
+        _provider: &DynProvider,
+    ) -> Result<FeeSuggestion, FacilitatorLocalError> {
+        let fees: ApiFees = self
+            .http
+            .get(&self.url)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| oracle_error(self.name(), e))?
+            .json()
+            .await
+            .map_err(|e| oracle_error(self.name(), e))?;
+        Ok(FeeSuggestion {
+            base_fee_per_gas: fees.base_fee_per_gas.0.saturating_to(),
+            max_priority_fee_per_gas: fees.max_priority_fee_per_gas.0.saturating_to(),
+        })
+    }
+}
+
+/// Settings of the gas oracle of a network.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case", deny_unknown_fields)]
+pub enum GasOracleConfig {
+    FeeHistory {
+        /// Blocks looked back; 10 if unset.
+        #[serde(default)]
+        blocks: Option<u64>,
+        /// Reward percentile suggested as priority fee, from 0 to 100; 50 if unset.
+        #[serde(default)]
+        percentile: Option<f64>,
+    },
+    Api {
+        url: String,
+    },
+}
+
+impl GasOracleConfig {
+    /// Builds the oracle for `network`; a gas API must be allowed by `outbound`.
+    pub fn build(
+        &self,
+        network: Network,
+        chain_id: u64,
+        outbound: &OutboundPolicy,
+    ) -> Result<Arc<dyn GasOracle>, String> {
+        let oracle: Arc<dyn GasOracle> = match self {
+            GasOracleConfig::FeeHistory { blocks, percentile } => {
+                let blocks = blocks.unwrap_or(DEFAULT_BLOCKS);
+                let percentile = percentile.unwrap_or(DEFAULT_PERCENTILE);
+                if blocks == 0 {
+                    return Err("fee_history gas oracle needs at least one block".to_string());
+                }
+                if !(0.0..=100.0).contains(&percentile) {
+                    return Err(format!(
+                        "percentile must be from 0 to 100, got {percentile}"
+                    ));
+                }
+                Arc::new(FeeHistoryOracle::new(blocks, percentile))
+            }
+            GasOracleConfig::Api { url } => {
+                let oracle = ApiGasOracle::new(url, network, chain_id, outbound.http_client());
+                Url::parse(&oracle.url)
+                    .map_err(|e| e.to_string())
+                    .and_then(|url| outbound.validate_url(&url).map_err(|e| e.to_string()))
+                    .map_err(|e| format!("invalid gas API url {}: {e}", oracle.url))?;
+                Arc::new(oracle)
+            }
+        };
+        Ok(oracle)
+    }
+}
+
+impl FromStr for GasOracleConfig {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if s.starts_with("http://") || s.starts_with("https://") {
+            return Ok(GasOracleConfig::Api { url: s.to_string() });
+        }
+        let mut parts = s.split(':').map(str::trim);
+        if parts.next() != Some("fee_history") {
+            return Err(format!(
+                "unknown gas oracle {s}, expected fee_history[:blocks[:percentile]] or a URL"
+            ));
+        }
+        let blocks = parts
+            .next()
+            .map(|blocks| {
+                blocks
+                    .parse()
+                    .map_err(|e| format!("invalid blocks {blocks}: {e}"))
+            })
+            .transpose()?;
+        let percentile = parts
+            .next()
+            .map(|percentile| {
+                percentile
+                    .parse()
+                    .map_err(|e| format!("invalid percentile {percentile}: {e}"))
+            })
+            .transpose()?;
+        if parts.next().is_some() {
+            return Err(format!("too many settings in gas oracle {s}"));
+        }
+        Ok(GasOracleConfig::FeeHistory { blocks, percentile })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_config() {
+        assert_eq!(
+            "fee_history".parse::<GasOracleConfig>().unwrap(),
+            GasOracleConfig::FeeHistory {
+                blocks: None,
+                percentile: None,
+            }
+        );
+        assert_eq!(
+            "fee_history:20:60".parse::<GasOracleConfig>().unwrap(),
+            GasOracleConfig::FeeHistory {
+                blocks: Some(20),
+                percentile: Some(60.0),
+            }
+        );
+        assert_eq!(
+            "https://gas.example/{chainId}"
+                .parse::<GasOracleConfig>()
+                .unwrap(),
+            GasOracleConfig::Api {
+                url: "https://gas.example/{chainId}".to_string(),
+            }
+        );
+        assert!("blocknative".parse::<GasOracleConfig>().is_err());
+        assert!(
+            "fee_history:20:150"
+                .parse::<GasOracleConfig>()
+                .unwrap()
+                .build(Network::Base, 8453, &OutboundPolicy::default())
+                .is_err()
+        );
+        assert!(
+            "http://127.0.0.1:8080/{chainId}"
+                .parse::<GasOracleConfig>()
+                .unwrap()
+                .build(Network::Base, 8453, &OutboundPolicy::default())
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn fee_history_suggests_next_base_fee_and_median_reward() {
+        let history = FeeHistory {
+            base_fee_per_gas: vec![100, 110, 120, 130],
+            reward: Some(vec![vec![5], vec![1], vec![9]]),
+            ..Default::default()
+        };
+        assert_eq!(
+            FeeHistoryOracle::suggestion(&history),
+            Some(FeeSuggestion {
+                base_fee_per_gas: 130,
+                max_priority_fee_per_gas: 5,
+            })
+        );
+        assert_eq!(FeeHistoryOracle::suggestion(&FeeHistory::default()), None);
+    }
+}
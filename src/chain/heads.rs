@@ -0,0 +1,133 @@
+//! New block announcements over WebSocket, driving settlement confirmations.
+//!
+//! Without a subscription, the receipt of a settlement is polled with `eth_getTransactionReceipt` every couple of
+//! seconds until it is mined, and confirmations with `eth_blockNumber`. With `WS_URL_<NETWORK>` set (or `ws_url` in
+//! the config file, with the `ws` feature), the provider of an EVM network keeps a [`HeadSubscription`] to
+//! `newHeads` on that endpoint instead: the receipt is read once per announced block, as soon as it is announced,
+//! and confirmations are counted on the announced heads. This lowers both the latency of `/settle` and the RPC
+//! request volume.
+//!
+//! The subscription reconnects by itself. Should it stay silent for longer than [`SILENCE_FALLBACK`], e.g. while
+//! reconnecting, the receipt is checked anyway.
+
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::Interval;
+
+use crate::from_env;
+use crate::network::Network;
+
+/// Announcements awaited at most before polling anyway.
+pub const SILENCE_FALLBACK: Duration = Duration::from_secs(15);
+/// Delay before reconnecting a failed subscription.
+#[cfg(feature = "ws")]
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Latest block number announced on a network, kept up to date by a background subscription.
+///
+/// Cheap to clone: all clones follow the same subscription.
+#[derive(Debug, Clone)]
+pub struct HeadSubscription {
+    heads: watch::Receiver<Option<u64>>,
+}
+
+impl HeadSubscription {
+    /// Subscribes to the new heads of `network` on the WebSocket endpoint `ws_url`.
+    #[cfg(feature = "ws")]
+    pub fn connect(network: Network, ws_url: String) -> Self {
+        use alloy::network::Ethereum;
+        use alloy::providers::{Provider, RootProvider};
+        use tokio::sync::broadcast::error::RecvError;
+
+        let (sender, heads) = watch::channel(None);
+        tokio::spawn(async move {
+            while !sender.is_closed() {
+                let subscription = match RootProvider::<Ethereum>::connect(&ws_url).await {
+                    Ok(provider) => provider.subscribe_blocks().await.map(|s| (provider, s)),
+                    Err(e) => Err(e),
+                };
+                match subscription {
+                    // The provider holds the connection the subscription is served on.
+                    Ok((_provider, mut subscription)) => {
+                        tracing::info!(network = %network, "Subscribed to new heads");
+                        loop {
+                            match subscription.recv().await {
+                                Ok(header) => {
+                                    sender.send_replace(Some(header.number));
+                                }
+                                Err(RecvError::Lagged(_)) => {}
+                                Err(RecvError::Closed) => break,
+                            }
+                        }
+                        tracing::warn!(network = %network, "New heads subscription ended");
+                    }
+                    Err(e) => {
+                        tracing::warn!(network = %network, error = %e, "Can not subscribe to new heads");
+                    }
+                }
+                tokio::time::sleep(RECONNECT_DELAY).await;
+            }
+        });
+        Self { heads }
+    }
+
+    /// Subscription of `network` to the endpoint `ws_url`, or else to `WS_URL_<NETWORK>`; `None` if neither is set.
+    pub fn from_url_or_env(
+        network: Network,
+        ws_url: Option<String>,
+    ) -> Result<Option<Self>, String> {
+        let env_var = from_env::ws_env_name_from_network(network);
+        let Some(ws_url) = ws_url.or_else(|| std::env::var(&env_var).ok()) else {
+            return Ok(None);
+        };
+        #[cfg(feature = "ws")]
+        {
+            Ok(Some(Self::connect(network, ws_url)))
+        }
+        #[cfg(not(feature = "ws"))]
+        {
+            let _ = ws_url;
+            Err(format!(
+                "{env_var}: WebSocket subscriptions require the `ws` feature"
+            ))
+        }
+    }
+
+    /// Latest block number announced, if any yet.
+    pub fn latest(&self) -> Option<u64> {
+        *self.heads.borrow()
+    }
+}
+
+/// Paces the checks of a pending transaction: once per block announced by a [`HeadSubscription`], or every
+/// `poll_interval` without one.
+pub struct BlockTicker {
+    heads: Option<watch::Receiver<Option<u64>>>,
+    ticker: Interval,
+}
+
+impl BlockTicker {
+    pub fn new(subscription: Option<&HeadSubscription>, poll_interval: Duration) -> Self {
+        Self {
+            heads: subscription.map(|subscription| subscription.heads.clone()),
+            ticker: tokio::time::interval(poll_interval),
+        }
+    }
+
+    /// Waits for the next check: the next announced block, or the next poll.
+    pub async fn tick(&mut self) {
+        let Some(heads) = &mut self.heads else {
+            self.ticker.tick().await;
+            return;
+        };
+        // A silent subscription is polled through; a closed one is given up for polling.
+        if let Ok(Err(_)) = tokio::time::timeout(SILENCE_FALLBACK, heads.changed()).await {
+            self.heads = None;
+        }
+    }
+
+    /// Latest block number announced, if subscribed and any was announced since.
+    pub fn latest(&self) -> Option<u64> {
+        self.heads.as_ref().and_then(|heads| *heads.borrow())
+    }
+}
@@ -0,0 +1,59 @@
+//! Settlement signers held in AWS KMS.
+//!
+//! With `signer_kms_keys` in the config file (and the `kms` feature), an EVM network signs its settlements with
+//! secp256k1 keys kept in AWS KMS rather than with private keys from the environment or the config file. Each network
+//! can name its own keys, so that mainnet funds sit behind keys that testnets, and the operators of testnets, have no
+//! access to. Keys are given by ID, ARN or alias; AWS credentials and region are read the usual way, from the
+//! environment, the shared config files or the instance role.
+
+use alloy::network::EthereumWallet;
+
+use crate::network::Network;
+
+/// Builds a wallet signing with the KMS keys `key_ids`, for the chain `chain_id` of `network`.
+#[cfg(feature = "kms")]
+pub async fn make_evm_wallet(
+    network: Network,
+    key_ids: &[String],
+    chain_id: u64,
+) -> Result<EthereumWallet, String> {
+    use alloy::signers::aws::AwsSigner;
+
+    let aws_config = aws_config::load_defaults(aws_config::BehaviorVersion::latest()).await;
+    let client = aws_sdk_kms::Client::new(&aws_config);
+    let mut signers = Vec::with_capacity(key_ids.len());
+    for key_id in key_ids {
+        let signer = AwsSigner::new(client.clone(), key_id.clone(), Some(chain_id))
+            .await
+            .map_err(|e| format!("{network}: KMS key {key_id}: {e}"))?;
+        tracing::info!(
+            network = %network,
+            key_id,
+            address = %alloy::signers::Signer::address(&signer),
+            "Using KMS signer"
+        );
+        signers.push(signer);
+    }
+    let mut iter = signers.into_iter();
+    let first_signer = iter
+        .next()
+        .ok_or_else(|| format!("{network}: no KMS keys provided"))?;
+    let mut wallet = EthereumWallet::from(first_signer);
+    for signer in iter {
+        wallet.register_signer(signer);
+    }
+    Ok(wallet)
+}
+
+/// Builds a wallet signing with the KMS keys `key_ids`, for the chain `chain_id` of `network`.
+#[cfg(not(feature = "kms"))]
+pub async fn make_evm_wallet(
+    network: Network,
+    key_ids: &[String],
+    chain_id: u64,
+) -> Result<EthereumWallet, String> {
+    let _ = (key_ids, chain_id);
+    Err(format!(
+        "{network}: signer_kms_keys require the `kms` feature"
+    ))
+}
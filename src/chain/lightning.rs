@@ -0,0 +1,698 @@
+//! x402 `exact` payments over the Lightning Network, with the `lightning` feature only.
+//!
+//! The resource server asks for a payment in millisatoshis, hands the client a BOLT11 invoice of the
+//! facilitator's Lightning node, and the client pays it with any wallet. Paying reveals the invoice
+//! preimage, which the client sends back as its payment payload together with the invoice.
+//!
+//! - **Verify**: decode the invoice and check that it is for this network and this node, not expired,
+//!   at least `maxAmountRequired` millisatoshis, that the preimage hashes to its payment hash, and that
+//!   it was not used for an earlier settlement.
+//! - **Settle**: confirm with the node that the invoice is settled for at least `maxAmountRequired`, and
+//!   mark it used. No transaction is sent: the payment already happened.
+//!
+//! Requirements name `BTC` as their asset. Lightning payers are anonymous, so the payer is reported
+//! as `ln-` followed by the first half of the payment hash. Used invoices are remembered until they
+//! expire in the replay store, under `REPLAY_STORE_DIR`, so that a restart does not accept them again.
+//!
+//! The node is reached through the gRPC APIs of LND (`lnrpc.Lightning`) and Core Lightning (the
+//! `cln-grpc` plugin), see `proto/lnd.proto` and `proto/cln.proto`.
+//!
+//! Environment variables used:
+//! - `RPC_URL_LIGHTNING`, `RPC_URL_LIGHTNING_TESTNET` — gRPC URL of the node, e.g. `https://lnd.internal:10009`,
+//!   subject to the outbound policy: a node on a private network must be in `OUTBOUND_ALLOWED_CIDRS`.
+//! - `MACAROON_<NETWORK>` — hex-encoded LND macaroon allowed to read invoices and node info.
+//! - `TLS_CLIENT_CERT_<NETWORK>`, `TLS_CLIENT_KEY_<NETWORK>` — paths to the PEM client certificate and key of a
+//!   Core Lightning node, instead of a macaroon.
+//! - `TLS_CERT_<NETWORK>` — path to the node's PEM certificate if it is self-signed, or to the CA of Core
+//!   Lightning's `cln-grpc` certificates (optional).
+
+use alloy::hex;
+use alloy::primitives::{U256, keccak256};
+use alloy::signers::k256::ecdsa::{RecoveryId, Signature, VerifyingKey};
+use bech32::{FromBase32, Variant, u5};
+use sha2::{Digest, Sha256};
+use std::fmt::{Debug, Formatter};
+use tonic::metadata::{Ascii, MetadataValue};
+use tonic::transport::{Certificate, Channel, ClientTlsConfig, Endpoint, Identity};
+use url::Url;
+
+use crate::chain::rpc_budget::RpcBudget;
+use crate::chain::{
+    FacilitatorLocalError, FromConfigByNetworkBuild, FromEnvByNetworkBuild, NetworkProviderOps,
+};
+use crate::config::NetworkConfig;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::outbound::OutboundPolicy;
+use crate::replay::{NonceRecord, NonceState, SeenNonces};
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ExactPaymentPayload, FacilitatorErrorReason, MixedAddress, PaymentPayload, PaymentRequirements,
+    Scheme, SettleRequest, SettleResponse, SupportedPaymentKind, SupportedPaymentKindsResponse,
+    VerifyRequest, VerifyResponse, X402Version,
+};
+
+/// Expiry of an invoice without an `x` field, in seconds.
+const DEFAULT_EXPIRY_SECS: u64 = 3600;
+/// Length of the recoverable signature ending every invoice, in 5-bit words.
+const SIGNATURE_WORDS: usize = 104;
+/// Length of the timestamp starting every invoice, in 5-bit words.
+const TIMESTAMP_WORDS: usize = 7;
+
+#[derive(Debug, thiserror::Error)]
+pub enum InvoiceError {
+    #[error("Invalid bech32 encoding: {0}")]
+    Bech32(#[from] bech32::Error),
+    #[error("Unknown invoice prefix {0}")]
+    Prefix(String),
+    #[error("Invalid invoice amount {0}")]
+    Amount(String),
+    #[error("Invoice is truncated")]
+    Truncated,
+    #[error("Invoice has no payment hash")]
+    MissingPaymentHash,
+    #[error("Invalid invoice signature")]
+    Signature,
+}
+
+/// The fields of a BOLT11 invoice checked by the facilitator.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invoice {
+    pub network: Network,
+    /// Amount requested, in millisatoshis; `None` for an invoice of any amount.
+    pub amount_msat: Option<u64>,
+    pub timestamp: UnixTimestamp,
+    pub expiry_secs: u64,
+    pub payment_hash: [u8; 32],
+    /// Compressed public key of the node the invoice pays.
+    pub payee: [u8; 33],
+}
+
+impl Invoice {
+    /// Decodes `invoice` and recovers its payee from the signature.
+    pub fn decode(invoice: &str) -> Result<Self, InvoiceError> {
+        let invoice = invoice.trim().to_ascii_lowercase();
+        let invoice = invoice.strip_prefix("lightning:").unwrap_or(&invoice);
+        let (hrp, data, variant) = bech32::decode(invoice)?;
+        if variant != Variant::Bech32 {
+            return Err(InvoiceError::Bech32(bech32::Error::InvalidChecksum));
+        }
+        let (network, amount_msat) = parse_hrp(&hrp)?;
+        if data.len() < TIMESTAMP_WORDS + SIGNATURE_WORDS {
+            return Err(InvoiceError::Truncated);
+        }
+        let (signed, signature) = data.split_at(data.len() - SIGNATURE_WORDS);
+        let timestamp = UnixTimestamp(words_to_u64(&signed[..TIMESTAMP_WORDS]));
+
+        let mut expiry_secs = DEFAULT_EXPIRY_SECS;
+        let mut payment_hash = None;
+        let mut explicit_payee: Option<[u8; 33]> = None;
+        let mut fields = &signed[TIMESTAMP_WORDS..];
+        while !fields.is_empty() {
+            if fields.len() < 3 {
+                return Err(InvoiceError::Truncated);
+            }
+            let len = words_to_u64(&fields[1..3]) as usize;
+            let value = fields.get(3..3 + len).ok_or(InvoiceError::Truncated)?;
+            // Readers skip fields they do not know, and known fields of an unexpected length.
+            match (fields[0].to_u8(), len) {
+                (1, 52) => payment_hash = Vec::<u8>::from_base32(value)?.try_into().ok(),
+                (6, _) => expiry_secs = words_to_u64(value),
+                (19, 53) => explicit_payee = Vec::<u8>::from_base32(value)?.try_into().ok(),
+                _ => {}
+            }
+            fields = &fields[3 + len..];
+        }
+        let payment_hash = payment_hash.ok_or(InvoiceError::MissingPaymentHash)?;
+
+        let signature = Vec::<u8>::from_base32(signature)?;
+        let mut message = hrp.into_bytes();
+        message.extend(bech32::convert_bits(signed, 5, 8, true)?);
+        let payee = recover_payee(&Sha256::digest(&message).into(), &signature)?;
+        if explicit_payee.is_some_and(|explicit| explicit != payee) {
+            return Err(InvoiceError::Signature);
+        }
+        Ok(Self {
+            network,
+            amount_msat,
+            timestamp,
+            expiry_secs,
+            payment_hash,
+            payee,
+        })
+    }
+
+    /// Time after which the invoice can no longer be paid.
+    pub fn expires_at(&self) -> UnixTimestamp {
+        self.timestamp + self.expiry_secs
+    }
+}
+
+/// Network and amount of the human-readable part, e.g. `lnbc2500u`.
+fn parse_hrp(hrp: &str) -> Result<(Network, Option<u64>), InvoiceError> {
+    let prefix_error = || InvoiceError::Prefix(hrp.to_string());
+    let rest = hrp.strip_prefix("ln").ok_or_else(prefix_error)?;
+    // `bcrt` and `tbs` before their prefixes `bc` and `tb`; amounts start with a digit.
+    let (network, amount) = [
+        ("bcrt", Network::LightningTestnet),
+        ("tbs", Network::LightningTestnet),
+        ("bc", Network::Lightning),
+        ("tb", Network::LightningTestnet),
+    ]
+    .into_iter()
+    .find_map(|(currency, network)| {
+        rest.strip_prefix(currency)
+            .filter(|amount| amount.chars().next().is_none_or(|c| c.is_ascii_digit()))
+            .map(|amount| (network, amount))
+    })
+    .ok_or_else(prefix_error)?;
+    if amount.is_empty() {
+        return Ok((network, None));
+    }
+    let amount_error = || InvoiceError::Amount(amount.to_string());
+    let (digits, multiplier) = match amount.char_indices().last() {
+        Some((i, c)) if c.is_ascii_alphabetic() => (&amount[..i], Some(c)),
+        _ => (amount, None),
+    };
+    let value: u64 = digits.parse().map_err(|_| amount_error())?;
+    // One bitcoin is 10^11 millisatoshis.
+    let amount_msat = match multiplier {
+        None => value.checked_mul(100_000_000_000),
+        Some('m') => value.checked_mul(100_000_000),
+        Some('u') => value.checked_mul(100_000),
+        Some('n') => value.checked_mul(100),
+        Some('p') if value.is_multiple_of(10) => Some(value / 10),
+        Some(_) => None,
+    }
+    .ok_or_else(amount_error)?;
+    Ok((network, Some(amount_msat)))
+}
+
+/// Big-endian integer of 5-bit words.
+fn words_to_u64(words: &[u5]) -> u64 {
+    words
+        .iter()
+        .fold(0u64, |acc, word| (acc << 5) | word.to_u8() as u64)
+}
+
+/// Compressed public key that made the 65-byte recoverable `signature` of `hash`.
+fn recover_payee(hash: &[u8; 32], signature: &[u8]) -> Result<[u8; 33], InvoiceError> {
+    let [compact @ .., recovery_id] = signature else {
+        return Err(InvoiceError::Signature);
+    };
+    let signature = Signature::from_slice(compact).map_err(|_| InvoiceError::Signature)?;
+    let recovery_id = RecoveryId::from_byte(*recovery_id).ok_or(InvoiceError::Signature)?;
+    let key = VerifyingKey::recover_from_prehash(hash, &signature, recovery_id)
+        .map_err(|_| InvoiceError::Signature)?;
+    key.to_encoded_point(true)
+        .as_bytes()
+        .try_into()
+        .map_err(|_| InvoiceError::Signature)
+}
+
+/// Short, anonymous identifier of a node or payment: `ln-` and the first 16 bytes of `bytes`, hex-encoded.
+fn short_id(bytes: &[u8]) -> String {
+    format!("ln-{}", hex::encode(&bytes[..16]))
+}
+
+/// An invoice checked to pay for the requested resource.
+#[derive(Debug, Clone)]
+pub struct LightningPayment {
+    pub invoice: Invoice,
+}
+
+impl LightningPayment {
+    pub fn payer(&self) -> MixedAddress {
+        MixedAddress::Offchain(short_id(&self.invoice.payment_hash))
+    }
+}
+
+/// Runs all preconditions of a Lightning payment:
+/// - Lightning payload, `exact` scheme, matching network and `BTC` asset.
+/// - Invoice of this network, paying `node_id`, not expired, for at least `maxAmountRequired` msat.
+/// - Preimage of the invoice's payment hash.
+pub fn assert_valid_payment(
+    network: Network,
+    node_id: &[u8; 33],
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<LightningPayment, FacilitatorLocalError> {
+    let lightning_payload = match &payload.payload {
+        ExactPaymentPayload::Lightning(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::UnsupportedNetwork(None));
+        }
+    };
+    for actual in [payload.network, requirements.network] {
+        if actual != network {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                None, network, actual,
+            ));
+        }
+    }
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != Scheme::Exact {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                None,
+                Scheme::Exact,
+                scheme,
+            ));
+        }
+    }
+    if requirements.asset != MixedAddress::Offchain("BTC".to_string()) {
+        return Err(FacilitatorLocalError::UnsupportedAsset(
+            requirements.asset.clone(),
+        ));
+    }
+    let invoice = Invoice::decode(&lightning_payload.invoice)
+        .map_err(|e| FacilitatorLocalError::DecodingError(e.to_string()))?;
+    let payment = LightningPayment { invoice };
+    let invoice = &payment.invoice;
+    let payer = payment.payer();
+    if invoice.network != network {
+        return Err(FacilitatorLocalError::NetworkMismatch(
+            Some(payer),
+            network,
+            invoice.network,
+        ));
+    }
+    if &invoice.payee != node_id {
+        return Err(FacilitatorLocalError::ReceiverMismatch(
+            payer,
+            hex::encode(invoice.payee),
+            hex::encode(node_id),
+        ));
+    }
+    let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+    if invoice.expires_at() < now {
+        return Err(FacilitatorLocalError::InvalidTiming(
+            payer,
+            format!("Expired: now {} > expiry {}", now, invoice.expires_at()),
+        ));
+    }
+    let amount = invoice.amount_msat.map(U256::from).unwrap_or_default();
+    if amount < requirements.max_amount_required.0 {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+    let preimage = hex::decode(lightning_payload.preimage.trim())
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("preimage: {e}")))?;
+    if Sha256::digest(&preimage).as_slice() != invoice.payment_hash {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            "Preimage does not match the invoice payment hash".to_string(),
+        ));
+    }
+    Ok(payment)
+}
+
+/// Client of LND's `lnrpc.Lightning` service, see `proto/lnd.proto`.
+mod lnrpc {
+    tonic::include_proto!("lnrpc");
+}
+
+/// Client of Core Lightning's `cln.Node` service, see `proto/cln.proto`.
+mod cln {
+    tonic::include_proto!("cln");
+}
+
+/// gRPC API of the Lightning node holding the invoices.
+#[derive(Debug, Clone)]
+enum NodeApi {
+    /// LND, authenticated by a macaroon sent with every call.
+    Lnd {
+        client: lnrpc::lightning_client::LightningClient<Channel>,
+        macaroon: MetadataValue<Ascii>,
+    },
+    /// Core Lightning, authenticated by the client certificate of the connection.
+    Cln {
+        client: cln::node_client::NodeClient<Channel>,
+    },
+}
+
+/// Client of the facilitator's Lightning node.
+#[derive(Clone)]
+struct LightningNode {
+    url: String,
+    api: NodeApi,
+    rpc_budget: RpcBudget,
+}
+
+impl LightningNode {
+    /// LND request of `message`, with the macaroon.
+    fn lnd_request<T>(message: T, macaroon: &MetadataValue<Ascii>) -> tonic::Request<T> {
+        let mut request = tonic::Request::new(message);
+        request.metadata_mut().insert("macaroon", macaroon.clone());
+        request
+    }
+
+    /// Public key and block height of the node.
+    async fn info(&self) -> Result<([u8; 33], u64), FacilitatorLocalError> {
+        self.rpc_budget.record(1);
+        let (id, block_height) = match &self.api {
+            NodeApi::Lnd { client, macaroon } => {
+                let info = client
+                    .clone()
+                    .get_info(Self::lnd_request(lnrpc::GetInfoRequest {}, macaroon))
+                    .await
+                    .map_err(node_error("GetInfo"))?
+                    .into_inner();
+                let id = hex::decode(&info.identity_pubkey).map_err(|_| {
+                    FacilitatorLocalError::InvalidAddress(format!(
+                        "node id {}",
+                        info.identity_pubkey
+                    ))
+                })?;
+                (id, info.block_height)
+            }
+            NodeApi::Cln { client } => {
+                let info = client
+                    .clone()
+                    .getinfo(cln::GetinfoRequest {})
+                    .await
+                    .map_err(node_error("Getinfo"))?
+                    .into_inner();
+                (info.id, info.blockheight)
+            }
+        };
+        let id = id.try_into().map_err(|id: Vec<u8>| {
+            FacilitatorLocalError::InvalidAddress(format!("node id {}", hex::encode(id)))
+        })?;
+        Ok((id, block_height.into()))
+    }
+
+    /// Millisatoshis received for the invoice of `payment_hash`, if it is settled.
+    async fn paid_msat(
+        &self,
+        payment_hash: &[u8; 32],
+    ) -> Result<Option<u64>, FacilitatorLocalError> {
+        self.rpc_budget.record(1);
+        match &self.api {
+            NodeApi::Lnd { client, macaroon } => {
+                let request = lnrpc::PaymentHash {
+                    r_hash: payment_hash.to_vec(),
+                };
+                let invoice = client
+                    .clone()
+                    .lookup_invoice(Self::lnd_request(request, macaroon))
+                    .await
+                    .map_err(node_error("LookupInvoice"))?
+                    .into_inner();
+                Ok((invoice.state() == lnrpc::invoice::InvoiceState::Settled)
+                    .then(|| u64::try_from(invoice.amt_paid_msat).ok())
+                    .flatten())
+            }
+            NodeApi::Cln { client } => {
+                let request = cln::ListinvoicesRequest {
+                    payment_hash: Some(payment_hash.to_vec()),
+                };
+                let invoices = client
+                    .clone()
+                    .list_invoices(request)
+                    .await
+                    .map_err(node_error("ListInvoices"))?
+                    .into_inner();
+                Ok(invoices
+                    .invoices
+                    .into_iter()
+                    .find(|invoice| {
+                        invoice.status()
+                            == cln::listinvoices_invoices::ListinvoicesInvoicesStatus::Paid
+                    })
+                    .and_then(|invoice| invoice.amount_received_msat)
+                    .map(|amount| amount.msat))
+            }
+        }
+    }
+}
+
+/// Error of the node answering the RPC `method`.
+fn node_error(method: &'static str) -> impl Fn(tonic::Status) -> FacilitatorLocalError {
+    move |status| FacilitatorLocalError::ContractCall(format!("Lightning node {method}: {status}"))
+}
+
+pub struct LightningProvider {
+    network: Network,
+    node: LightningNode,
+    node_id: [u8; 33],
+    /// Payment hashes of invoices claimed or settled, until they expire.
+    nonces: SeenNonces,
+}
+
+impl Debug for LightningProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LightningProvider")
+            .field("network", &self.network)
+            .field("node_id", &hex::encode(self.node_id))
+            .field("url", &self.node.url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LightningProvider {
+    /// Monthly request accounting for the node's REST endpoint.
+    pub fn rpc_budget(&self) -> &RpcBudget {
+        &self.node.rpc_budget
+    }
+
+    /// Block height of the node's chain backend, used as a liveness probe.
+    pub async fn latest_block_number(&self) -> Result<u64, FacilitatorLocalError> {
+        let (_, block_height) = self.node.info().await?;
+        Ok(block_height)
+    }
+
+    /// In-flight record of the invoice of `payment`, keyed by network and payment hash.
+    fn nonce_record(&self, payment: &LightningPayment) -> NonceRecord {
+        let invoice = &payment.invoice;
+        NonceRecord {
+            key: keccak256(format!(
+                "{}:{}",
+                self.network,
+                hex::encode(invoice.payment_hash)
+            )),
+            network: self.network,
+            payer: payment.payer(),
+            state: NonceState::InFlight,
+            expires_at: invoice.expires_at(),
+        }
+    }
+}
+
+impl FromEnvByNetworkBuild for LightningProvider {
+    async fn from_env(network: Network) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let env_var = from_env::rpc_env_name_from_network(network);
+        let rpc_url = match std::env::var(env_var).ok() {
+            Some(rpc_url) => rpc_url,
+            None => {
+                tracing::warn!(network=%network, "no RPC URL configured, skipping");
+                return Ok(None);
+            }
+        };
+        let config = NetworkConfig::from_rpc_url(rpc_url);
+        Ok(Some(Self::from_config(network, &config).await?))
+    }
+}
+
+impl FromConfigByNetworkBuild for LightningProvider {
+    async fn from_config(
+        network: Network,
+        config: &NetworkConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let macaroon_env_var = from_env::macaroon_env_name_from_network(network);
+        let client_cert_env_var = from_env::tls_client_cert_env_name_from_network(network);
+        let client_key_env_var = from_env::tls_client_key_env_name_from_network(network);
+        let read = |path: String| std::fs::read(&path).map_err(|e| format!("{path}: {e}"));
+        let mut tls = ClientTlsConfig::new().with_webpki_roots();
+        let ca = std::env::var(from_env::tls_cert_env_name_from_network(network)).ok();
+        if let Some(path) = ca.clone() {
+            tls = tls.ca_certificate(Certificate::from_pem(read(path)?));
+        }
+        let macaroon = match (
+            std::env::var(&macaroon_env_var),
+            std::env::var(&client_cert_env_var),
+        ) {
+            (Ok(macaroon), Err(_)) => Some(MetadataValue::try_from(macaroon.as_str())?),
+            (Err(_), Ok(client_cert)) => {
+                let client_key = std::env::var(&client_key_env_var).map_err(|_| {
+                    format!(
+                        "{network}: {client_key_env_var} is required with {client_cert_env_var}"
+                    )
+                })?;
+                tls = tls.identity(Identity::from_pem(read(client_cert)?, read(client_key)?));
+                // Certificates generated by `cln-grpc` are for the name `cln`, whatever the node's address.
+                if ca.is_some() {
+                    tls = tls.domain_name("cln");
+                }
+                None
+            }
+            _ => {
+                return Err(format!(
+                    "{network}: set exactly one of {macaroon_env_var} and {client_cert_env_var}"
+                )
+                .into());
+            }
+        };
+        // gRPC connections do not go through the guarded resolver of `OutboundPolicy::http_client`, so the node's
+        // addresses are checked once, as resolved now.
+        let outbound = OutboundPolicy::from_env()?;
+        let rpc_url =
+            Url::parse(&config.rpc_url).map_err(|e| format!("{network}: invalid RPC URL: {e}"))?;
+        outbound
+            .validate_url(&rpc_url)
+            .map_err(|e| format!("{network}: RPC URL: {e}"))?;
+        let host = rpc_url.host_str().unwrap_or_default();
+        let port = rpc_url.port_or_known_default().unwrap_or(443);
+        for address in tokio::net::lookup_host((host, port)).await? {
+            outbound
+                .check_ip(address.ip())
+                .map_err(|e| format!("{network}: RPC URL: {e}"))?;
+        }
+        let channel = Endpoint::from_shared(config.rpc_url.clone())?
+            .tls_config(tls)?
+            .connect()
+            .await
+            .map_err(|e| format!("{network}: {}: {e}", config.rpc_url))?;
+        let api = match macaroon {
+            Some(macaroon) => NodeApi::Lnd {
+                client: lnrpc::lightning_client::LightningClient::new(channel),
+                macaroon,
+            },
+            None => NodeApi::Cln {
+                client: cln::node_client::NodeClient::new(channel),
+            },
+        };
+        let node = LightningNode {
+            url: config.rpc_url.clone(),
+            api,
+            rpc_budget: RpcBudget::from_env(network),
+        };
+        let (node_id, _) = node.info().await?;
+        tracing::info!(network=%network, rpc=%node.url, node_id=%hex::encode(node_id), "Initialized provider");
+        Ok(Self {
+            network,
+            node,
+            node_id,
+            nonces: SeenNonces::persistent_from_env("lightning")
+                .map_err(|e| format!("{network}: {e}"))?,
+        })
+    }
+}
+
+impl NetworkProviderOps for LightningProvider {
+    fn signer_address(&self) -> MixedAddress {
+        MixedAddress::Offchain(short_id(&self.node_id))
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+}
+
+impl Facilitator for LightningProvider {
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let payment = assert_valid_payment(
+            self.network,
+            &self.node_id,
+            &request.payment_payload,
+            &request.payment_requirements,
+        )?;
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        self.nonces.check(&self.nonce_record(&payment), now)?;
+        Ok(VerifyResponse::valid(payment.payer()))
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let payment = assert_valid_payment(
+            self.network,
+            &self.node_id,
+            &request.payment_payload,
+            &request.payment_requirements,
+        )?;
+        let payment_hash = payment.invoice.payment_hash;
+        let record = self.nonce_record(&payment);
+        let key = record.key;
+        // Claimed and saved before asking the node, so concurrent settlements of one invoice can not both pass,
+        // nor a settlement after a restart.
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        self.nonces.claim_persisted(record, now)?;
+        let paid = self.node.paid_msat(&payment_hash).await;
+        let success = matches!(paid, Ok(Some(msat))
+            if U256::from(msat) >= request.payment_requirements.max_amount_required.0);
+        if success {
+            self.nonces.settled(&key);
+        } else {
+            self.nonces.release(&key);
+            paid?;
+        }
+        tracing::info!(payment_hash = %hex::encode(payment_hash), success, "Lightning payment settled");
+        Ok(SettleResponse {
+            success,
+            error_reason: (!success).then_some(FacilitatorErrorReason::InsufficientFunds),
+            payer: Some(payment.payer()),
+            transaction: None,
+            network: self.network,
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: false,
+        })
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        let kinds = vec![SupportedPaymentKind {
+            network: self.network.to_string(),
+            scheme: Scheme::Exact,
+            x402_version: X402Version::V1,
+            extra: None,
+        }];
+        Ok(SupportedPaymentKindsResponse {
+            kinds,
+            routes: Vec::new(),
+            maintenance: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// BOLT11 test vector: "Please send $3 for a cup of coffee to the same peer, within one minute".
+    const INVOICE: &str = "lnbc2500u1pvjluezsp5zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zyg3zygspp5qqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqqqsyqcyq5rqwzqfqypqdq5xysxxatsyp3k7enxv4jsxqzpu9qrsgquk0rl77nj30yxdy8j9vdx85fkpmdla2087ne0xh8nhedh8w27kyke0lp53ut353s06fv3qfegext0eh0ymjpf39tuven09sam30g4vgpfna3rh";
+
+    #[test]
+    fn decodes_bolt11_invoice() {
+        let invoice = Invoice::decode(INVOICE).unwrap();
+        assert_eq!(invoice.network, Network::Lightning);
+        assert_eq!(invoice.amount_msat, Some(250_000_000));
+        assert_eq!(invoice.timestamp, UnixTimestamp(1_496_314_658));
+        assert_eq!(invoice.expiry_secs, 60);
+        assert_eq!(
+            hex::encode(invoice.payment_hash),
+            "0001020304050607080900010203040506070809000102030405060708090102"
+        );
+        assert_eq!(
+            hex::encode(invoice.payee),
+            "03e7156ae33b0a208d0744199163177e909e80176e55d97a2f221ede0f934dd9ad"
+        );
+        assert!(Invoice::decode(&INVOICE.replace("lnbc", "lntb")).is_err());
+        assert_eq!(
+            parse_hrp("lnbcrt10p").unwrap(),
+            (Network::LightningTestnet, Some(1))
+        );
+    }
+}
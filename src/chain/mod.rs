@@ -1,7 +1,16 @@
-use std::time::SystemTimeError;
+use std::time::{Duration, SystemTimeError};
 
 use crate::chain::evm::EvmProvider;
+#[cfg(feature = "lightning")]
+use crate::chain::lightning::LightningProvider;
+use crate::chain::near::NearProvider;
+use crate::chain::revert::Revert;
+use crate::chain::rpc_budget::RpcBudget;
+use crate::chain::signers::{SignerGenerations, SignerRotationError};
 use crate::chain::solana::SolanaProvider;
+#[cfg(feature = "tron")]
+use crate::chain::tron::TronProvider;
+use crate::config::NetworkConfig;
 use crate::facilitator::Facilitator;
 use crate::network::{Network, NetworkFamily};
 use crate::types::{
@@ -9,12 +18,47 @@ use crate::types::{
     VerifyRequest, VerifyResponse,
 };
 
+pub mod aa;
+pub mod batch;
+pub mod block_tracker;
+pub mod cancel;
 pub mod evm;
+pub mod failover;
+pub mod fee_on_transfer;
+pub mod fee_strategy;
+pub mod gas_oracle;
+pub mod heads;
+pub mod kms;
+#[cfg(feature = "lightning")]
+pub mod lightning;
+pub mod native;
+pub mod near;
+pub mod permit;
+pub mod permit2;
+pub mod recovery;
+pub mod relayer;
+pub mod reorg;
+pub mod revert;
+pub mod rpc_budget;
+pub mod rpc_retry;
+pub mod signers;
 pub mod solana;
+pub mod speed_up;
+pub mod stream;
+pub mod token_probe;
+#[cfg(feature = "tron")]
+pub mod tron;
+pub mod warm_cache;
 
+#[allow(clippy::large_enum_variant)] // Built once per network and never moved afterwards
 pub enum NetworkProvider {
     Evm(EvmProvider),
     Solana(SolanaProvider),
+    #[cfg(feature = "lightning")]
+    Lightning(LightningProvider),
+    Near(NearProvider),
+    #[cfg(feature = "tron")]
+    Tron(TronProvider),
 }
 
 pub trait FromEnvByNetworkBuild: Sized {
@@ -35,11 +79,160 @@ impl FromEnvByNetworkBuild for NetworkProvider {
                 let provider = SolanaProvider::from_env(network).await?;
                 provider.map(NetworkProvider::Solana)
             }
+            #[cfg(feature = "lightning")]
+            NetworkFamily::Lightning => {
+                let provider = LightningProvider::from_env(network).await?;
+                provider.map(NetworkProvider::Lightning)
+            }
+            #[cfg(not(feature = "lightning"))]
+            NetworkFamily::Lightning => None,
+            NetworkFamily::Near => {
+                let provider = NearProvider::from_env(network).await?;
+                provider.map(NetworkProvider::Near)
+            }
+            #[cfg(feature = "tron")]
+            NetworkFamily::Tron => {
+                let provider = TronProvider::from_env(network).await?;
+                provider.map(NetworkProvider::Tron)
+            }
+            #[cfg(not(feature = "tron"))]
+            NetworkFamily::Tron => None,
         };
         Ok(provider)
     }
 }
 
+pub trait FromConfigByNetworkBuild: Sized {
+    fn from_config(
+        network: Network,
+        config: &NetworkConfig,
+    ) -> impl Future<Output = Result<Self, Box<dyn std::error::Error>>> + Send;
+}
+
+impl FromConfigByNetworkBuild for NetworkProvider {
+    async fn from_config(
+        network: Network,
+        config: &NetworkConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        config.validate(network)?;
+        let family: NetworkFamily = network.into();
+        let provider = match family {
+            NetworkFamily::Evm => {
+                NetworkProvider::Evm(EvmProvider::from_config(network, config).await?)
+            }
+            NetworkFamily::Solana => {
+                NetworkProvider::Solana(SolanaProvider::from_config(network, config).await?)
+            }
+            #[cfg(feature = "lightning")]
+            NetworkFamily::Lightning => {
+                NetworkProvider::Lightning(LightningProvider::from_config(network, config).await?)
+            }
+            #[cfg(not(feature = "lightning"))]
+            NetworkFamily::Lightning => {
+                return Err(format!("{network}: built without the lightning feature").into());
+            }
+            NetworkFamily::Near => {
+                NetworkProvider::Near(NearProvider::from_config(network, config).await?)
+            }
+            #[cfg(feature = "tron")]
+            NetworkFamily::Tron => {
+                NetworkProvider::Tron(TronProvider::from_config(network, config).await?)
+            }
+            #[cfg(not(feature = "tron"))]
+            NetworkFamily::Tron => {
+                return Err(format!("{network}: built without the tron feature").into());
+            }
+        };
+        Ok(provider)
+    }
+}
+
+impl NetworkProvider {
+    /// Latest block (EVM, Lightning, NEAR and Tron) or slot (Solana) reported by the underlying RPC node.
+    pub async fn latest_block_number(&self) -> Result<u64, FacilitatorLocalError> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.latest_block_number().await,
+            NetworkProvider::Solana(provider) => provider.latest_block_number().await,
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => provider.latest_block_number().await,
+            NetworkProvider::Near(provider) => provider.latest_block_number().await,
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => provider.latest_block_number().await,
+        }
+    }
+
+    /// Checks the native balance of the signers, on EVM networks only; see [`EvmProvider::check_signer_balances`].
+    pub async fn check_signer_balances(&self) -> Result<(), String> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.check_signer_balances().await,
+            _ => Ok(()),
+        }
+    }
+
+    /// Settlement signers of this provider by rotation generation.
+    pub fn signer_generations(&self) -> SignerGenerations {
+        match self {
+            NetworkProvider::Evm(provider) => provider.signers().generations(),
+            NetworkProvider::Solana(provider) => SignerGenerations {
+                active: vec![provider.signer_address()],
+                next: vec![],
+                draining: vec![],
+            },
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => SignerGenerations {
+                active: vec![provider.signer_address()],
+                next: vec![],
+                draining: vec![],
+            },
+            NetworkProvider::Near(provider) => SignerGenerations {
+                active: vec![provider.signer_address()],
+                next: vec![],
+                draining: vec![],
+            },
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => SignerGenerations {
+                active: vec![provider.signer_address()],
+                next: vec![],
+                draining: vec![],
+            },
+        }
+    }
+
+    /// Promotes the next signers to active; see [`signers::SignerPool::rotate`].
+    pub fn rotate_signers(&self) -> Result<SignerGenerations, SignerRotationError> {
+        match self {
+            NetworkProvider::Evm(provider) => provider.signers().rotate(),
+            NetworkProvider::Solana(provider) => {
+                Err(SignerRotationError::Unsupported(provider.network()))
+            }
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => {
+                Err(SignerRotationError::Unsupported(provider.network()))
+            }
+            NetworkProvider::Near(provider) => {
+                Err(SignerRotationError::Unsupported(provider.network()))
+            }
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => {
+                Err(SignerRotationError::Unsupported(provider.network()))
+            }
+        }
+    }
+
+    /// Monthly request accounting for the RPC endpoint of this provider.
+    pub fn rpc_budget(&self) -> &RpcBudget {
+        match self {
+            NetworkProvider::Evm(provider) => provider.rpc_budget(),
+            NetworkProvider::Solana(provider) => provider.rpc_budget(),
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => provider.rpc_budget(),
+            NetworkProvider::Near(provider) => provider.rpc_budget(),
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => provider.rpc_budget(),
+        }
+    }
+}
+
 pub trait NetworkProviderOps {
     fn signer_address(&self) -> MixedAddress;
     fn network(&self) -> Network;
@@ -50,6 +243,11 @@ impl NetworkProviderOps for NetworkProvider {
         match self {
             NetworkProvider::Evm(provider) => provider.signer_address(),
             NetworkProvider::Solana(provider) => provider.signer_address(),
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => provider.signer_address(),
+            NetworkProvider::Near(provider) => provider.signer_address(),
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => provider.signer_address(),
         }
     }
 
@@ -57,6 +255,11 @@ impl NetworkProviderOps for NetworkProvider {
         match self {
             NetworkProvider::Evm(provider) => provider.network(),
             NetworkProvider::Solana(provider) => provider.network(),
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => provider.network(),
+            NetworkProvider::Near(provider) => provider.network(),
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => provider.network(),
         }
     }
 }
@@ -68,6 +271,11 @@ impl Facilitator for NetworkProvider {
         match self {
             NetworkProvider::Evm(provider) => provider.verify(request).await,
             NetworkProvider::Solana(provider) => provider.verify(request).await,
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => provider.verify(request).await,
+            NetworkProvider::Near(provider) => provider.verify(request).await,
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => provider.verify(request).await,
         }
     }
 
@@ -75,6 +283,11 @@ impl Facilitator for NetworkProvider {
         match self {
             NetworkProvider::Evm(provider) => provider.settle(request).await,
             NetworkProvider::Solana(provider) => provider.settle(request).await,
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => provider.settle(request).await,
+            NetworkProvider::Near(provider) => provider.settle(request).await,
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => provider.settle(request).await,
         }
     }
 
@@ -82,10 +295,24 @@ impl Facilitator for NetworkProvider {
         match self {
             NetworkProvider::Evm(provider) => provider.supported().await,
             NetworkProvider::Solana(provider) => provider.supported().await,
+            #[cfg(feature = "lightning")]
+            NetworkProvider::Lightning(provider) => provider.supported().await,
+            NetworkProvider::Near(provider) => provider.supported().await,
+            #[cfg(feature = "tron")]
+            NetworkProvider::Tron(provider) => provider.supported().await,
         }
     }
 }
 
+/// Whether an ERC-20 `transfer` or `transferFrom` call that did not revert moved the tokens, from its return data.
+///
+/// Standard tokens return `true`. USDT and other early tokens return nothing, and some return a boolean word other
+/// than `0` or `1`, or trailing bytes; the strict `returns (bool)` decoding of `sol!` bindings fails on these and
+/// would report the transfer as failed. Only a zero return value is taken as a failure.
+pub fn erc20_transfer_succeeded(return_data: &[u8]) -> bool {
+    return_data.is_empty() || return_data.iter().take(32).any(|byte| *byte != 0)
+}
+
 #[derive(Debug, thiserror::Error)]
 pub enum FacilitatorLocalError {
     /// The network is not supported by this facilitator.
@@ -118,10 +345,100 @@ pub enum FacilitatorLocalError {
     /// The payer's on-chain balance is insufficient for the payment.
     #[error("Insufficient funds")]
     InsufficientFunds(MixedAddress),
+    /// The payer's token allowance to the Permit2 contract is insufficient for the payment.
+    #[error("Insufficient Permit2 allowance")]
+    InsufficientAllowance(MixedAddress),
     /// The payload's `value` is not enough to meet the requirements.
     #[error("Insufficient value")]
     InsufficientValue(MixedAddress),
+    /// The `settleAmount` of an `upto` settlement is out of bounds, or it or `allowPartial` is given for another scheme.
+    #[error("Invalid settle amount: {1}")]
+    InvalidSettleAmount(Option<MixedAddress>, String),
+    /// The `executeAfter` of a settlement is not before the expiry of its authorization, or can not be honoured.
+    #[error("Invalid execute after: {1}")]
+    InvalidExecuteAfter(Option<MixedAddress>, String),
+    /// The request breaks an operator-defined verification rule or plugin, named here.
+    #[error("Rejected by rule {0}")]
+    RuleViolation(String),
+    /// The payment reuses a reserved nonce that was already settled.
+    #[error("Nonce already used")]
+    NonceReused(MixedAddress),
+    /// The payment reuses the nonce of another one this facilitator is settling or has settled.
+    #[error("Nonce replayed")]
+    NonceReplayed(MixedAddress),
+    /// The payment exceeds what is left of the payer's daily budget.
+    #[error("Daily budget exceeded")]
+    BudgetExceeded(MixedAddress),
+    /// The token has paused all transfers.
+    #[error("Token paused")]
+    TokenPaused(MixedAddress),
+    /// The token blacklisted the payer or the recipient (the second address).
+    #[error("Address {1} is blacklisted by the token")]
+    AddressBlacklisted(MixedAddress, MixedAddress),
+    /// The token or Permit2 reverted the transfer for a recognised reason.
+    #[error("Transfer reverted: {1}")]
+    Reverted(Option<MixedAddress>, Revert),
+    /// The asset in the payment requirements is not accepted on this network.
+    #[error("Unsupported asset {0}")]
+    UnsupportedAsset(MixedAddress),
     /// The payload decoding failed.
     #[error("Decoding error: {0}")]
     DecodingError(String),
+    /// The transaction service for human-approved settlements could not be reached.
+    #[error("Approval service error: {0}")]
+    ApprovalService(String),
+    /// The bridge or swap adapter of a cross-network payment could not be reached or refused it.
+    #[error("Bridge adapter error: {0}")]
+    BridgeAdapter(String),
+    /// `maxAmountRequired` does not match `priceUsd` within the tolerance.
+    #[error("Price out of tolerance: {1}")]
+    PriceOutOfTolerance(Option<MixedAddress>, String),
+    /// No price could be obtained for a dollar-denominated payment.
+    #[error("Price oracle error: {0}")]
+    PriceOracle(String),
+    /// The token deducts a fee on transfer, so the recipient would receive less than the signed value.
+    #[error("Fee-on-transfer token: {1}")]
+    FeeOnTransfer(MixedAddress, String),
+    /// `facilitatorFee` of the requirements is below the fee due by the payer.
+    #[error("Insufficient facilitator fee: {1}")]
+    InsufficientFee(MixedAddress, String),
+    /// The circuit breaker of the network is open after repeated RPC failures, for the given time at most.
+    #[error("Network {0} temporarily unavailable")]
+    NetworkUnavailable(Network, Duration),
+}
+
+impl FacilitatorLocalError {
+    /// Whether the failure may go away on retry: RPC, clock and outside service errors, as opposed to an invalid
+    /// payment.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            FacilitatorLocalError::ContractCall(_)
+                | FacilitatorLocalError::ClockError(_)
+                | FacilitatorLocalError::ApprovalService(_)
+                | FacilitatorLocalError::BridgeAdapter(_)
+                | FacilitatorLocalError::PriceOracle(_)
+                | FacilitatorLocalError::NetworkUnavailable(..)
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::U256;
+
+    #[test]
+    fn transfer_return_data_is_decoded_tolerantly() {
+        assert!(erc20_transfer_succeeded(&[]));
+        assert!(erc20_transfer_succeeded(&U256::from(1).to_be_bytes::<32>()));
+        assert!(erc20_transfer_succeeded(&U256::from(2).to_be_bytes::<32>()));
+        assert!(erc20_transfer_succeeded(
+            &[[0; 31].as_slice(), &[1], &[0; 32]].concat()
+        ));
+        assert!(!erc20_transfer_succeeded(&[0; 32]));
+        assert!(!erc20_transfer_succeeded(
+            &[[0; 32].as_slice(), &[1]].concat()
+        ));
+    }
 }
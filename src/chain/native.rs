@@ -0,0 +1,206 @@
+//! x402 `native` scheme: payments in the chain's native coin (ETH, AVAX…).
+//!
+//! The payer signs a plain value transfer to `payTo` and hands the raw transaction to the facilitator,
+//! which only checks and broadcasts it. The payer also pays for gas; no facilitator signer is involved.
+//!
+//! - **Verify**: decode the EIP-2718 transaction, recover its sender, check chain ID, recipient,
+//!   value, empty calldata, that the nonce is the sender's next one, and that the balance covers
+//!   value plus maximum gas cost.
+//! - **Settle**: re-run the checks, broadcast the transaction and wait for its receipt.
+//!
+//! Payment requirements name the native coin with the [`NATIVE_ASSET`] placeholder address (EIP-7528).
+//! A signed transaction does not expire: it stays valid until its nonce is used.
+
+use alloy::consensus::transaction::SignerRecoverable;
+use alloy::consensus::{Transaction, TxEnvelope};
+use alloy::eips::eip2718::Decodable2718;
+use alloy::hex;
+use alloy::primitives::{Address, B256, Bytes, U256, address};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionReceipt;
+use tracing::{Instrument, instrument};
+
+use crate::chain::FacilitatorLocalError;
+use crate::chain::evm::EvmChain;
+use crate::types::{
+    EvmAddress, ExactPaymentPayload, MixedAddress, PaymentPayload, PaymentRequirements, Scheme,
+};
+
+/// Placeholder asset address standing for the native coin, per EIP-7528.
+pub const NATIVE_ASSET: Address = address!("0xEeeeeEeeeEeEeeEeEeEeeEEEeeeeEeeeeeeeEEeE");
+
+/// A validated native-coin payment, ready to be broadcast.
+#[derive(Debug, Clone)]
+pub struct NativePayment {
+    /// Sender recovered from the transaction signature.
+    pub from: Address,
+    /// Transaction hash.
+    pub hash: B256,
+    /// Transferred amount.
+    pub value: U256,
+    /// EIP-2718 encoded signed transaction.
+    pub raw: Bytes,
+}
+
+impl NativePayment {
+    /// Broadcasts the signed transaction and waits for `confirmations` blocks.
+    ///
+    /// # Errors
+    /// Returns [`FacilitatorLocalError::ContractCall`] if the node rejects the transaction or the receipt can not be fetched.
+    pub async fn broadcast<P: Provider>(
+        &self,
+        provider: &P,
+        confirmations: u64,
+    ) -> Result<TransactionReceipt, FacilitatorLocalError> {
+        provider
+            .send_raw_transaction(&self.raw)
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?
+            .with_required_confirmations(confirmations)
+            .get_receipt()
+            .instrument(tracing::info_span!("send_raw_transaction",
+                from = %self.from,
+                tx = %self.hash,
+                value = %self.value,
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+    }
+}
+
+/// Runs all preconditions of a native-coin payment:
+/// - Native payload, matching scheme and network, native asset.
+/// - Transaction signed for this chain, sending at least `maxAmountRequired` to `payTo` without calldata.
+/// - Nonce is the sender's next one.
+/// - Balance covers value and maximum gas cost.
+#[instrument(skip_all, err)]
+pub async fn assert_valid_payment<P: Provider>(
+    provider: &P,
+    chain: &EvmChain,
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<NativePayment, FacilitatorLocalError> {
+    let native_payload = match &payload.payload {
+        ExactPaymentPayload::Native(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                None,
+                Scheme::Native,
+                payload.scheme,
+            ));
+        }
+    };
+    let raw = hex::decode(&native_payload.signed_transaction)
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+    let tx = TxEnvelope::decode_2718(&mut raw.as_slice())
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+    let from = tx
+        .recover_signer()
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("{e}")))?;
+    let payer: MixedAddress = from.into();
+    for network in [payload.network, requirements.network] {
+        if network != chain.network() {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                Some(payer),
+                chain.network(),
+                network,
+            ));
+        }
+    }
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != Scheme::Native {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                Some(payer),
+                Scheme::Native,
+                scheme,
+            ));
+        }
+    }
+    let asset: EvmAddress = requirements
+        .asset
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    if asset.0 != NATIVE_ASSET {
+        return Err(FacilitatorLocalError::InvalidAddress(format!(
+            "Asset {asset} is not the native coin {NATIVE_ASSET}"
+        )));
+    }
+    if tx.chain_id() != Some(chain.chain_id) {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            format!(
+                "Transaction is signed for chain {:?}, expected {}",
+                tx.chain_id(),
+                chain.chain_id
+            ),
+        ));
+    }
+    let pay_to: EvmAddress = requirements
+        .pay_to
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    if tx.to() != Some(pay_to.0) {
+        return Err(FacilitatorLocalError::ReceiverMismatch(
+            payer,
+            tx.to()
+                .map_or_else(|| "contract creation".to_string(), |to| to.to_string()),
+            pay_to.to_string(),
+        ));
+    }
+    if !tx.input().is_empty() {
+        return Err(FacilitatorLocalError::DecodingError(
+            "Native payment transaction must not carry calldata".to_string(),
+        ));
+    }
+    let value = tx.value();
+    if value < requirements.max_amount_required.0 {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+
+    let nonce = provider
+        .get_transaction_count(from)
+        .pending()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_nonce", sender = %from, otel.kind = "client"))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if tx.nonce() != nonce {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            format!(
+                "Transaction nonce {} is not the next sender nonce {nonce}",
+                tx.nonce()
+            ),
+        ));
+    }
+    let balance = provider
+        .get_balance(from)
+        .into_future()
+        .instrument(
+            tracing::info_span!("fetch_native_balance", sender = %from, otel.kind = "client"),
+        )
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    let max_gas_cost = U256::from(tx.gas_limit()) * U256::from(tx.max_fee_per_gas());
+    if balance < value.saturating_add(max_gas_cost) {
+        return Err(FacilitatorLocalError::InsufficientFunds(payer));
+    }
+
+    Ok(NativePayment {
+        from,
+        hash: *tx.tx_hash(),
+        value,
+        raw: raw.into(),
+    })
+}
@@ -0,0 +1,893 @@
+//! x402 `exact` payments of NEP-141 fungible tokens on NEAR, relayed as NEP-366 meta-transactions.
+//!
+//! The payer signs a delegate action calling `ft_transfer` on the token contract, and sends it
+//! borsh-serialized and base64-encoded as `{"signedDelegateAction": "…"}`. The facilitator wraps it
+//! in a transaction of its own relayer account, which pays the gas.
+//!
+//! - **Verify**: decode the delegate action and check network and scheme, that it makes a single
+//!   `ft_transfer` call with one yoctoNEAR attached on the required asset, to `payTo`, of at least
+//!   `maxAmountRequired`, that it is signed by a full access key of the sender, that its nonce is
+//!   unused and that it has not expired, and that the sender holds enough tokens.
+//! - **Settle**: re-run the checks, then send the relayer transaction and wait for it to be final.
+//!
+//! The payer is reported by its account ID. Requirements name the token contract as `asset`,
+//! e.g. `17208628f84f5d6ad33f0da3bbbeb27ffcb398eac501a31bd6ad2011e36133a1` for USDC on mainnet.
+//!
+//! Environment variables used:
+//! - `RPC_URL_NEAR`, `RPC_URL_NEAR_TESTNET` — NEAR JSON-RPC endpoint, subject to the outbound policy.
+//! - `NEAR_PRIVATE_KEY` — relayer key as `ed25519:` and the base58 of its 64-byte secret key.
+//! - `NEAR_ACCOUNT_ID` — relayer account; the implicit account of the key if unset.
+//! - `TOKENS_<NETWORK>` — comma-separated token contracts accepted (optional).
+
+use alloy::hex;
+use alloy::primitives::U256;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as b64;
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use solana_sdk::bs58;
+use solana_sdk::signature::{Keypair, Signature};
+use solana_sdk::signer::Signer;
+use std::fmt::{Debug, Formatter};
+use std::sync::Mutex;
+use url::Url;
+
+use crate::chain::rpc_budget::RpcBudget;
+use crate::chain::{
+    FacilitatorLocalError, FromConfigByNetworkBuild, FromEnvByNetworkBuild, NetworkProviderOps,
+};
+use crate::config::NetworkConfig;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::outbound::OutboundPolicy;
+use crate::types::{
+    ExactPaymentPayload, FacilitatorErrorReason, MixedAddress, PaymentPayload, PaymentRequirements,
+    Scheme, SettleRequest, SettleResponse, SupportedPaymentKind, SupportedPaymentKindExtra,
+    SupportedPaymentKindsResponse, TransactionHash, VerifyRequest, VerifyResponse, X402Version,
+};
+
+/// Prefix of the borsh message signed for a delegate action: `2^30 + 366`, after NEP-461.
+const DELEGATE_ACTION_DISCRIMINANT: u32 = (1 << 30) + 366;
+/// Borsh tag of a `FunctionCall` action.
+const ACTION_FUNCTION_CALL: u8 = 2;
+/// Borsh tag of a `Delegate` action.
+const ACTION_DELEGATE: u8 = 8;
+/// Borsh tag of ED25519 public keys and signatures.
+const KEY_TYPE_ED25519: u8 = 0;
+/// Deposit `ft_transfer` requires, in yoctoNEAR.
+const FT_TRANSFER_DEPOSIT: u128 = 1;
+
+#[derive(Debug, thiserror::Error)]
+pub enum DelegateActionError {
+    #[error("Delegate action is truncated")]
+    Truncated,
+    #[error("Delegate action has trailing bytes")]
+    TrailingBytes,
+    #[error("Unsupported key type {0}")]
+    KeyType(u8),
+    #[error("Unsupported action {0}, only function calls are relayed")]
+    Action(u8),
+    #[error("Account ID or method name is not UTF-8")]
+    Utf8,
+}
+
+/// A `FunctionCall` action.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FunctionCall {
+    pub method_name: String,
+    pub args: Vec<u8>,
+    pub gas: u64,
+    /// Attached deposit, in yoctoNEAR.
+    pub deposit: u128,
+}
+
+/// NEP-366 `DelegateAction`: actions `sender_id` wants executed on `receiver_id`, paid for by a relayer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DelegateAction {
+    pub sender_id: String,
+    pub receiver_id: String,
+    pub actions: Vec<FunctionCall>,
+    pub nonce: u64,
+    /// Last block the action can be included in.
+    pub max_block_height: u64,
+    /// ED25519 access key of the sender that signed the action.
+    pub public_key: [u8; 32],
+}
+
+impl DelegateAction {
+    pub fn to_borsh(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        put_string(&mut out, &self.sender_id);
+        put_string(&mut out, &self.receiver_id);
+        out.extend((self.actions.len() as u32).to_le_bytes());
+        for action in &self.actions {
+            out.push(ACTION_FUNCTION_CALL);
+            put_string(&mut out, &action.method_name);
+            put_bytes(&mut out, &action.args);
+            out.extend(action.gas.to_le_bytes());
+            out.extend(action.deposit.to_le_bytes());
+        }
+        out.extend(self.nonce.to_le_bytes());
+        out.extend(self.max_block_height.to_le_bytes());
+        out.push(KEY_TYPE_ED25519);
+        out.extend(self.public_key);
+        out
+    }
+
+    /// Hash the sender signs: SHA-256 of the discriminant and the borsh-serialized action.
+    pub fn signing_hash(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(DELEGATE_ACTION_DISCRIMINANT.to_le_bytes());
+        hasher.update(self.to_borsh());
+        hasher.finalize().into()
+    }
+
+    fn decode(reader: &mut Reader) -> Result<Self, DelegateActionError> {
+        let sender_id = reader.string()?;
+        let receiver_id = reader.string()?;
+        let count = reader.u32()?;
+        let mut actions = Vec::new();
+        for _ in 0..count {
+            match reader.u8()? {
+                ACTION_FUNCTION_CALL => actions.push(FunctionCall {
+                    method_name: reader.string()?,
+                    args: reader.bytes()?.to_vec(),
+                    gas: reader.u64()?,
+                    deposit: reader.u128()?,
+                }),
+                other => return Err(DelegateActionError::Action(other)),
+            }
+        }
+        let nonce = reader.u64()?;
+        let max_block_height = reader.u64()?;
+        let public_key = match reader.u8()? {
+            KEY_TYPE_ED25519 => reader.array()?,
+            other => return Err(DelegateActionError::KeyType(other)),
+        };
+        Ok(Self {
+            sender_id,
+            receiver_id,
+            actions,
+            nonce,
+            max_block_height,
+            public_key,
+        })
+    }
+}
+
+/// NEP-366 `SignedDelegateAction`, as sent by the payer.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedDelegateAction {
+    pub delegate_action: DelegateAction,
+    pub signature: [u8; 64],
+}
+
+impl SignedDelegateAction {
+    pub fn decode(bytes: &[u8]) -> Result<Self, DelegateActionError> {
+        let mut reader = Reader(bytes);
+        let delegate_action = DelegateAction::decode(&mut reader)?;
+        let signature = match reader.u8()? {
+            KEY_TYPE_ED25519 => reader.array()?,
+            other => return Err(DelegateActionError::KeyType(other)),
+        };
+        if !reader.0.is_empty() {
+            return Err(DelegateActionError::TrailingBytes);
+        }
+        Ok(Self {
+            delegate_action,
+            signature,
+        })
+    }
+
+    pub fn to_borsh(&self) -> Vec<u8> {
+        let mut out = self.delegate_action.to_borsh();
+        out.push(KEY_TYPE_ED25519);
+        out.extend(self.signature);
+        out
+    }
+
+    /// Whether the signature is made by the action's public key.
+    pub fn verify_signature(&self) -> bool {
+        Signature::from(self.signature).verify(
+            &self.delegate_action.public_key,
+            &self.delegate_action.signing_hash(),
+        )
+    }
+}
+
+fn put_bytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    out.extend((bytes.len() as u32).to_le_bytes());
+    out.extend(bytes);
+}
+
+fn put_string(out: &mut Vec<u8>, s: &str) {
+    put_bytes(out, s.as_bytes());
+}
+
+/// Borsh reader over the remaining bytes.
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], DelegateActionError> {
+        if self.0.len() < len {
+            return Err(DelegateActionError::Truncated);
+        }
+        let (head, tail) = self.0.split_at(len);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], DelegateActionError> {
+        Ok(self.take(N)?.try_into().expect("length checked"))
+    }
+
+    fn u8(&mut self) -> Result<u8, DelegateActionError> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, DelegateActionError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Result<u64, DelegateActionError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn u128(&mut self) -> Result<u128, DelegateActionError> {
+        self.array().map(u128::from_le_bytes)
+    }
+
+    fn bytes(&mut self) -> Result<&'a [u8], DelegateActionError> {
+        let len = self.u32()? as usize;
+        self.take(len)
+    }
+
+    fn string(&mut self) -> Result<String, DelegateActionError> {
+        let bytes = self.bytes()?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DelegateActionError::Utf8)
+    }
+}
+
+/// Borsh-serialized relayer transaction executing `signed` on behalf of its sender.
+fn relayer_transaction(
+    signer_id: &str,
+    public_key: &[u8; 32],
+    nonce: u64,
+    block_hash: &[u8; 32],
+    signed: &SignedDelegateAction,
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    put_string(&mut out, signer_id);
+    out.push(KEY_TYPE_ED25519);
+    out.extend(public_key);
+    out.extend(nonce.to_le_bytes());
+    put_string(&mut out, &signed.delegate_action.sender_id);
+    out.extend(block_hash);
+    out.extend(1u32.to_le_bytes());
+    out.push(ACTION_DELEGATE);
+    out.extend(signed.to_borsh());
+    out
+}
+
+/// `"ed25519:<base58>"` form of a public key used by the RPC.
+fn public_key_string(public_key: &[u8; 32]) -> String {
+    format!("ed25519:{}", bs58::encode(public_key).into_string())
+}
+
+/// Arguments of an `ft_transfer` call.
+#[derive(Deserialize)]
+struct FtTransferArgs {
+    receiver_id: String,
+    /// u128 as a decimal string.
+    amount: String,
+}
+
+/// A delegate action checked to pay for the requested resource, before on-chain checks.
+#[derive(Debug, Clone)]
+pub struct NearPayment {
+    pub signed: SignedDelegateAction,
+    pub amount: u128,
+}
+
+impl NearPayment {
+    pub fn payer(&self) -> MixedAddress {
+        MixedAddress::Near(self.signed.delegate_action.sender_id.clone())
+    }
+}
+
+/// Runs the preconditions of a NEAR payment that need no RPC:
+/// - NEAR payload, `exact` scheme, matching network, accepted asset.
+/// - A single `ft_transfer` on the asset, with one yoctoNEAR, to `payTo`, of at least `maxAmountRequired`.
+/// - Signature by the action's public key.
+pub fn assert_valid_payment(
+    network: Network,
+    accepted_assets: Option<&[String]>,
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<NearPayment, FacilitatorLocalError> {
+    let near_payload = match &payload.payload {
+        ExactPaymentPayload::Near(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::UnsupportedNetwork(None));
+        }
+    };
+    for actual in [payload.network, requirements.network] {
+        if actual != network {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                None, network, actual,
+            ));
+        }
+    }
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != Scheme::Exact {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                None,
+                Scheme::Exact,
+                scheme,
+            ));
+        }
+    }
+    let MixedAddress::Near(asset) = &requirements.asset else {
+        return Err(FacilitatorLocalError::UnsupportedAsset(
+            requirements.asset.clone(),
+        ));
+    };
+    if accepted_assets.is_some_and(|accepted| !accepted.contains(asset)) {
+        return Err(FacilitatorLocalError::UnsupportedAsset(
+            requirements.asset.clone(),
+        ));
+    }
+    let bytes = b64
+        .decode(near_payload.signed_delegate_action.trim())
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("signedDelegateAction: {e}")))?;
+    let signed = SignedDelegateAction::decode(&bytes)
+        .map_err(|e| FacilitatorLocalError::DecodingError(e.to_string()))?;
+    let action = &signed.delegate_action;
+    let payer = MixedAddress::Near(action.sender_id.clone());
+    if &action.receiver_id != asset {
+        return Err(FacilitatorLocalError::UnsupportedAsset(MixedAddress::Near(
+            action.receiver_id.clone(),
+        )));
+    }
+    let [call] = action.actions.as_slice() else {
+        return Err(FacilitatorLocalError::DecodingError(
+            "expected a single ft_transfer call".to_string(),
+        ));
+    };
+    if call.method_name != "ft_transfer" || call.deposit != FT_TRANSFER_DEPOSIT {
+        return Err(FacilitatorLocalError::DecodingError(format!(
+            "expected ft_transfer with 1 yoctoNEAR, got {} with {}",
+            call.method_name, call.deposit
+        )));
+    }
+    let args: FtTransferArgs = serde_json::from_slice(&call.args)
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("ft_transfer args: {e}")))?;
+    if MixedAddress::Near(args.receiver_id.clone()) != requirements.pay_to {
+        return Err(FacilitatorLocalError::ReceiverMismatch(
+            payer,
+            args.receiver_id,
+            requirements.pay_to.to_string(),
+        ));
+    }
+    let amount: u128 = args
+        .amount
+        .parse()
+        .map_err(|e| FacilitatorLocalError::DecodingError(format!("ft_transfer amount: {e}")))?;
+    if U256::from(amount) < requirements.max_amount_required.0 {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+    if !signed.verify_signature() {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            "Delegate action is not signed by its public key".to_string(),
+        ));
+    }
+    Ok(NearPayment { signed, amount })
+}
+
+#[derive(Deserialize)]
+struct RpcResponse {
+    #[serde(default)]
+    result: Option<Value>,
+    #[serde(default)]
+    error: Option<Value>,
+}
+
+#[derive(Deserialize)]
+struct AccessKeyView {
+    nonce: u64,
+    /// `"FullAccess"`, or an object for function call keys.
+    permission: Value,
+}
+
+#[derive(Deserialize)]
+struct BlockView {
+    header: BlockHeaderView,
+}
+
+#[derive(Deserialize)]
+struct BlockHeaderView {
+    height: u64,
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct CallResult {
+    /// JSON returned by the view function, as bytes.
+    result: Vec<u8>,
+}
+
+#[derive(Deserialize)]
+struct ExecutionOutcome {
+    status: Value,
+    transaction: TransactionView,
+    receipts_outcome: Vec<ReceiptOutcome>,
+}
+
+#[derive(Deserialize)]
+struct TransactionView {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct ReceiptOutcome {
+    outcome: ReceiptOutcomeStatus,
+}
+
+#[derive(Deserialize)]
+struct ReceiptOutcomeStatus {
+    status: Value,
+}
+
+/// Client of a NEAR JSON-RPC endpoint.
+#[derive(Clone)]
+struct NearRpc {
+    url: String,
+    http: reqwest::Client,
+    rpc_budget: RpcBudget,
+}
+
+impl NearRpc {
+    /// Result of `method`, or the error object returned by the node.
+    async fn request(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<Result<Value, Value>, FacilitatorLocalError> {
+        let body = json!({ "jsonrpc": "2.0", "id": "x402", "method": method, "params": params });
+        self.rpc_budget.record(1);
+        let rpc_error =
+            |e: reqwest::Error| FacilitatorLocalError::ContractCall(format!("NEAR {method}: {e}"));
+        let response: RpcResponse = self
+            .http
+            .post(&self.url)
+            .json(&body)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(rpc_error)?
+            .json()
+            .await
+            .map_err(rpc_error)?;
+        match (response.result, response.error) {
+            (_, Some(error)) => Ok(Err(error)),
+            (Some(result), None) => Ok(Ok(result)),
+            (None, None) => Ok(Err(Value::Null)),
+        }
+    }
+
+    async fn call<T: DeserializeOwned>(
+        &self,
+        method: &str,
+        params: Value,
+    ) -> Result<T, FacilitatorLocalError> {
+        let result = self.request(method, params).await?.map_err(|error| {
+            FacilitatorLocalError::ContractCall(format!("NEAR {method}: {error}"))
+        })?;
+        serde_json::from_value(result)
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("NEAR {method}: {e}")))
+    }
+
+    /// Latest final block height and hash.
+    async fn final_block(&self) -> Result<(u64, [u8; 32]), FacilitatorLocalError> {
+        let block: BlockView = self.call("block", json!({ "finality": "final" })).await?;
+        let hash = bs58::decode(&block.header.hash)
+            .into_vec()
+            .ok()
+            .and_then(|hash| hash.try_into().ok())
+            .ok_or_else(|| {
+                FacilitatorLocalError::ContractCall(format!("block hash {}", block.header.hash))
+            })?;
+        Ok((block.header.height, hash))
+    }
+
+    /// Access key `public_key` of `account_id`, `None` if the account has no such key.
+    async fn access_key(
+        &self,
+        account_id: &str,
+        public_key: &[u8; 32],
+    ) -> Result<Option<AccessKeyView>, FacilitatorLocalError> {
+        let params = json!({
+            "request_type": "view_access_key",
+            "finality": "final",
+            "account_id": account_id,
+            "public_key": public_key_string(public_key),
+        });
+        match self.request("query", params).await? {
+            // Older nodes report a missing key as a result with an `error` field.
+            Ok(result) if result.get("error").is_some() => Ok(None),
+            Ok(result) => serde_json::from_value(result)
+                .map(Some)
+                .map_err(|e| FacilitatorLocalError::ContractCall(format!("NEAR query: {e}"))),
+            Err(error)
+                if error.to_string().contains("UNKNOWN_ACCESS_KEY")
+                    || error.to_string().contains("UNKNOWN_ACCOUNT") =>
+            {
+                Ok(None)
+            }
+            Err(error) => Err(FacilitatorLocalError::ContractCall(format!(
+                "NEAR query: {error}"
+            ))),
+        }
+    }
+
+    /// `ft_balance_of(account_id)` of the `token` contract.
+    async fn ft_balance_of(
+        &self,
+        token: &str,
+        account_id: &str,
+    ) -> Result<u128, FacilitatorLocalError> {
+        let args = json!({ "account_id": account_id }).to_string();
+        let params = json!({
+            "request_type": "call_function",
+            "finality": "final",
+            "account_id": token,
+            "method_name": "ft_balance_of",
+            "args_base64": b64.encode(args),
+        });
+        let result: CallResult = self.call("query", params).await?;
+        let balance: String = serde_json::from_slice(&result.result)
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("ft_balance_of: {e}")))?;
+        balance
+            .parse()
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("ft_balance_of: {e}")))
+    }
+
+    /// Sends a signed transaction and waits until it and all its receipts are final.
+    async fn send_tx(&self, signed_tx: &[u8]) -> Result<ExecutionOutcome, FacilitatorLocalError> {
+        let params = json!({ "signed_tx_base64": b64.encode(signed_tx), "wait_until": "FINAL" });
+        self.call("send_tx", params).await
+    }
+}
+
+/// Error reason of a failed relayer transaction, from its failure.
+fn failure_reason(failure: &Value) -> FacilitatorErrorReason {
+    let failure = failure.to_string();
+    if failure.contains("DelegateActionInvalidNonce") {
+        FacilitatorErrorReason::NonceReused
+    } else if failure.contains("DelegateActionExpired") {
+        FacilitatorErrorReason::InvalidValidBefore
+    } else if failure.contains("DelegateActionInvalidSignature") {
+        FacilitatorErrorReason::InvalidSignature
+    } else if failure.contains("enough balance") {
+        FacilitatorErrorReason::InsufficientFunds
+    } else {
+        FacilitatorErrorReason::UnexpectedSettleError
+    }
+}
+
+pub struct NearProvider {
+    network: Network,
+    rpc: NearRpc,
+    account_id: String,
+    keypair: Keypair,
+    /// Token contracts accepted as `asset`; any NEP-141 token if `None`.
+    accepted_assets: Option<Vec<String>>,
+    /// Last nonce used by the relayer key, so concurrent settlements do not reuse one.
+    last_nonce: Mutex<u64>,
+}
+
+impl Debug for NearProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NearProvider")
+            .field("network", &self.network)
+            .field("account_id", &self.account_id)
+            .field("url", &self.rpc.url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl NearProvider {
+    /// Monthly request accounting for the RPC endpoint.
+    pub fn rpc_budget(&self) -> &RpcBudget {
+        &self.rpc.rpc_budget
+    }
+
+    /// Height of the latest final block.
+    pub async fn latest_block_number(&self) -> Result<u64, FacilitatorLocalError> {
+        let (height, _) = self.rpc.final_block().await?;
+        Ok(height)
+    }
+
+    fn public_key(&self) -> [u8; 32] {
+        self.keypair.pubkey().to_bytes()
+    }
+
+    /// All preconditions of a payment: [`assert_valid_payment`], then
+    /// - the signing key is a full access key of the sender, and the nonce is above its nonce,
+    /// - the action can still be included in a block,
+    /// - the sender holds at least the transferred amount.
+    async fn verify_payment(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<NearPayment, FacilitatorLocalError> {
+        let payment = assert_valid_payment(
+            self.network,
+            self.accepted_assets.as_deref(),
+            payload,
+            requirements,
+        )?;
+        let action = &payment.signed.delegate_action;
+        let payer = payment.payer();
+        let access_key = self
+            .rpc
+            .access_key(&action.sender_id, &action.public_key)
+            .await?;
+        let Some(access_key) = access_key.filter(|key| key.permission == "FullAccess") else {
+            return Err(FacilitatorLocalError::InvalidSignature(
+                payer,
+                format!(
+                    "{} is not a full access key of {}",
+                    public_key_string(&action.public_key),
+                    action.sender_id
+                ),
+            ));
+        };
+        if action.nonce <= access_key.nonce {
+            return Err(FacilitatorLocalError::NonceReused(payer));
+        }
+        let (height, _) = self.rpc.final_block().await?;
+        if action.max_block_height <= height {
+            return Err(FacilitatorLocalError::InvalidTiming(
+                payer,
+                format!(
+                    "Expired: block {height} >= maxBlockHeight {}",
+                    action.max_block_height
+                ),
+            ));
+        }
+        let balance = self
+            .rpc
+            .ft_balance_of(&action.receiver_id, &action.sender_id)
+            .await?;
+        if balance < payment.amount {
+            return Err(FacilitatorLocalError::InsufficientFunds(payer));
+        }
+        Ok(payment)
+    }
+
+    /// Next nonce of the relayer key, above both the chain's and the last one used here.
+    async fn next_nonce(&self) -> Result<u64, FacilitatorLocalError> {
+        let access_key = self
+            .rpc
+            .access_key(&self.account_id, &self.public_key())
+            .await?
+            .ok_or_else(|| {
+                FacilitatorLocalError::InvalidAddress(format!(
+                    "{} has no access key {}",
+                    self.account_id,
+                    public_key_string(&self.public_key())
+                ))
+            })?;
+        let mut last_nonce = self.last_nonce.lock().expect("relayer nonce poisoned");
+        *last_nonce = (*last_nonce).max(access_key.nonce) + 1;
+        Ok(*last_nonce)
+    }
+}
+
+impl FromEnvByNetworkBuild for NearProvider {
+    async fn from_env(network: Network) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let env_var = from_env::rpc_env_name_from_network(network);
+        let rpc_url = match std::env::var(env_var).ok() {
+            Some(rpc_url) => rpc_url,
+            None => {
+                tracing::warn!(network=%network, "no RPC URL configured, skipping");
+                return Ok(None);
+            }
+        };
+        let mut config = NetworkConfig::from_rpc_url(rpc_url);
+        config.tokens = from_env::tokens_from_env(network)?;
+        Ok(Some(Self::from_config(network, &config).await?))
+    }
+}
+
+impl FromConfigByNetworkBuild for NearProvider {
+    async fn from_config(
+        network: Network,
+        config: &NetworkConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let private_key = match config.signer_keys.as_deref() {
+            Some([key]) => key.clone(),
+            Some(_) => return Err(format!("{network}: expected exactly one signer key").into()),
+            None => std::env::var(from_env::ENV_NEAR_PRIVATE_KEY)
+                .map_err(|_| format!("env {} not set", from_env::ENV_NEAR_PRIVATE_KEY))?,
+        };
+        let secret_key = bs58::decode(private_key.trim_start_matches("ed25519:"))
+            .into_vec()
+            .map_err(|e| format!("{network}: invalid NEAR private key: {e}"))?;
+        let keypair = Keypair::try_from(secret_key.as_slice())
+            .map_err(|e| format!("{network}: invalid NEAR private key: {e}"))?;
+        let account_id = std::env::var(from_env::ENV_NEAR_ACCOUNT_ID)
+            .unwrap_or_else(|_| hex::encode(keypair.pubkey().to_bytes()));
+        let accepted_assets = config
+            .tokens
+            .as_ref()
+            .map(|tokens| {
+                tokens
+                    .iter()
+                    .map(|token| match token {
+                        MixedAddress::Near(account_id) => Ok(account_id.clone()),
+                        other => Err(format!("{network}: invalid NEP-141 contract: {other}")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let outbound = OutboundPolicy::from_env()?;
+        let rpc_url =
+            Url::parse(&config.rpc_url).map_err(|e| format!("{network}: invalid RPC URL: {e}"))?;
+        outbound
+            .validate_url(&rpc_url)
+            .map_err(|e| format!("{network}: RPC URL: {e}"))?;
+        let rpc = NearRpc {
+            url: config.rpc_url.clone(),
+            http: outbound.http_client(),
+            rpc_budget: RpcBudget::from_env(network),
+        };
+        tracing::info!(network=%network, rpc=%rpc.url, account_id=%account_id, "Initialized provider");
+        Ok(Self {
+            network,
+            rpc,
+            account_id,
+            keypair,
+            accepted_assets,
+            last_nonce: Mutex::new(0),
+        })
+    }
+}
+
+impl NetworkProviderOps for NearProvider {
+    fn signer_address(&self) -> MixedAddress {
+        MixedAddress::Near(self.account_id.clone())
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+}
+
+impl Facilitator for NearProvider {
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let payment = self
+            .verify_payment(&request.payment_payload, &request.payment_requirements)
+            .await?;
+        Ok(VerifyResponse::valid(payment.payer()))
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let payment = self
+            .verify_payment(&request.payment_payload, &request.payment_requirements)
+            .await?;
+        let nonce = self.next_nonce().await?;
+        let (_, block_hash) = self.rpc.final_block().await?;
+        let transaction = relayer_transaction(
+            &self.account_id,
+            &self.public_key(),
+            nonce,
+            &block_hash,
+            &payment.signed,
+        );
+        let hash: [u8; 32] = Sha256::digest(&transaction).into();
+        let signature = self.keypair.sign_message(&hash);
+        let mut signed_tx = transaction;
+        signed_tx.push(KEY_TYPE_ED25519);
+        signed_tx.extend(signature.as_ref());
+
+        let outcome = self.rpc.send_tx(&signed_tx).await?;
+        let failure = std::iter::once(&outcome.status)
+            .chain(outcome.receipts_outcome.iter().map(|r| &r.outcome.status))
+            .find_map(|status| status.get("Failure"));
+        let success = failure.is_none();
+        tracing::info!(tx = %outcome.transaction.hash, success, "NEAR meta-transaction settled");
+        Ok(SettleResponse {
+            success,
+            error_reason: failure.map(failure_reason),
+            payer: Some(payment.payer()),
+            transaction: Some(TransactionHash::Near(hash)),
+            network: self.network,
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: false,
+        })
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        let kinds = vec![SupportedPaymentKind {
+            network: self.network.to_string(),
+            scheme: Scheme::Exact,
+            x402_version: X402Version::V1,
+            extra: Some(SupportedPaymentKindExtra {
+                fee_payer: self.signer_address(),
+                spender: None,
+                asset: None,
+                signers: Vec::new(),
+            }),
+        }];
+        Ok(SupportedPaymentKindsResponse {
+            kinds,
+            routes: Vec::new(),
+            maintenance: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signed_delegate_action_round_trips() {
+        let keypair = Keypair::new();
+        let delegate_action = DelegateAction {
+            sender_id: "alice.near".to_string(),
+            receiver_id: "usdc.near".to_string(),
+            actions: vec![FunctionCall {
+                method_name: "ft_transfer".to_string(),
+                args: br#"{"receiver_id":"shop.near","amount":"1000"}"#.to_vec(),
+                gas: 30_000_000_000_000,
+                deposit: 1,
+            }],
+            nonce: 7,
+            max_block_height: 100,
+            public_key: keypair.pubkey().to_bytes(),
+        };
+        let signature = keypair.sign_message(&delegate_action.signing_hash());
+        let signed = SignedDelegateAction {
+            delegate_action,
+            signature: signature.into(),
+        };
+        let decoded = SignedDelegateAction::decode(&signed.to_borsh()).unwrap();
+        assert_eq!(decoded, signed);
+        assert!(decoded.verify_signature());
+
+        let mut tampered = decoded.clone();
+        tampered.delegate_action.nonce += 1;
+        assert!(!tampered.verify_signature());
+        assert!(matches!(
+            SignedDelegateAction::decode(&signed.to_borsh()[..40]),
+            Err(DelegateActionError::Truncated)
+        ));
+        assert_eq!(
+            serde_json::from_str::<MixedAddress>("\"usdc.near\"").unwrap(),
+            MixedAddress::Near("usdc.near".to_string())
+        );
+    }
+}
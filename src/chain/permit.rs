@@ -0,0 +1,399 @@
+//! x402 `permit` scheme: ERC-20 payments through the token's own `permit` and `transferFrom`.
+//!
+//! Tokens implementing EIP-2612 (e.g. OpenZeppelin `ERC20Permit`) or the older DAI-style `permit`
+//! let a payer approve a spender by signature. The payer signs a `permit` naming the facilitator as
+//! `spender`; the facilitator then pulls the payment with `transferFrom`.
+//!
+//! - **Verify**: check network, scheme, spender, deadline, permitted value, token nonce, balance,
+//!   pause and blacklist, recover the signer, then simulate `permit` from the spender in an `eth_call`.
+//! - **Settle**: two transactions from the spender: `permit`, then `transferFrom(owner, payTo, maxAmountRequired)`.
+//!   If `permit` reverts because someone else already submitted it, settlement goes on as long as
+//!   the allowance covers the payment. `transferFrom` is simulated before being sent, as some tokens
+//!   return `false` instead of reverting, which the receipt would not show.
+//!
+//! The EIP-712 domain uses the token `name` and `version` from `extra` of the payment requirements,
+//! defaulting to the on-chain `name()` and version `"1"`. `permit` only accepts ECDSA signatures,
+//! so the payer must be an EOA.
+
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, B256, Bytes, Signature, U256};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol;
+use alloy::sol_types::{Eip712Domain, SolCall, SolStruct, eip712_domain};
+use tracing::{Instrument, instrument};
+
+use crate::chain::evm::{EvmChain, assert_token_transferable};
+use crate::chain::{FacilitatorLocalError, erc20_transfer_succeeded, revert};
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    EvmAddress, ExactPaymentPayload, MixedAddress, PaymentPayload, PaymentRequirements, Scheme,
+};
+
+sol! {
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IERC20Permit {
+        function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external;
+        function nonces(address owner) external view returns (uint256);
+        function name() external view returns (string);
+        function balanceOf(address owner) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function transferFrom(address from, address to, uint256 value) external returns (bool);
+    }
+}
+
+sol! {
+    #[allow(missing_docs, clippy::too_many_arguments)]
+    #[derive(Debug)]
+    interface IDaiPermit {
+        function permit(address holder, address spender, uint256 nonce, uint256 expiry, bool allowed, uint8 v, bytes32 r, bytes32 s) external;
+    }
+}
+
+/// EIP-712 `Permit` messages signed by the payer.
+mod typed_data {
+    pub mod eip2612 {
+        alloy::sol! {
+            struct Permit {
+                address owner;
+                address spender;
+                uint256 value;
+                uint256 nonce;
+                uint256 deadline;
+            }
+        }
+    }
+
+    pub mod dai {
+        alloy::sol! {
+            struct Permit {
+                address holder;
+                address spender;
+                uint256 nonce;
+                uint256 expiry;
+                bool allowed;
+            }
+        }
+    }
+}
+
+/// A validated `permit` payment, ready to be simulated or settled.
+#[derive(Debug, Clone)]
+pub struct PermitPayment {
+    /// Payer who signed the permit.
+    pub owner: Address,
+    /// Facilitator address the permit was issued to; must be `msg.sender` of `transferFrom`.
+    pub spender: Address,
+    /// Token contract.
+    pub token: Address,
+    /// Recipient of the payment.
+    pub pay_to: Address,
+    /// Amount pulled with `transferFrom`.
+    pub amount: U256,
+    /// ABI-encoded `permit` calldata, EIP-2612 or DAI-style.
+    pub permit_calldata: Bytes,
+}
+
+impl PermitPayment {
+    /// ABI-encoded `transferFrom` calldata, to be sent to the token by the spender.
+    pub fn transfer_from_calldata(&self) -> Bytes {
+        IERC20Permit::transferFromCall {
+            from: self.owner,
+            to: self.pay_to,
+            value: self.amount,
+        }
+        .abi_encode()
+        .into()
+    }
+
+    /// Simulates `permit` from the spender, surfacing signature and nonce failures.
+    ///
+    /// `transferFrom` can not be simulated before the permit is applied; the balance is checked instead.
+    ///
+    /// # Errors
+    /// Returns [`FacilitatorLocalError::ContractCall`] if the token reverts.
+    pub async fn simulate<P: Provider>(&self, provider: &P) -> Result<(), FacilitatorLocalError> {
+        let tx = TransactionRequest::default()
+            .with_from(self.spender)
+            .with_to(self.token)
+            .with_input(self.permit_calldata.clone());
+        provider
+            .call(tx)
+            .into_future()
+            .instrument(tracing::info_span!("call_permit",
+                owner = %self.owner,
+                spender = %self.spender,
+                token = %self.token,
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        Ok(())
+    }
+
+    /// Simulates `transferFrom` from the spender, once the permit is applied; whether the token reports the
+    /// transfer as done, read with [`erc20_transfer_succeeded`] so that tokens returning nothing pass.
+    ///
+    /// # Errors
+    /// Returns [`FacilitatorLocalError::Reverted`] or [`FacilitatorLocalError::ContractCall`] if the token reverts.
+    pub async fn simulate_transfer<P: Provider>(
+        &self,
+        provider: &P,
+    ) -> Result<bool, FacilitatorLocalError> {
+        let tx = TransactionRequest::default()
+            .with_from(self.spender)
+            .with_to(self.token)
+            .with_input(self.transfer_from_calldata());
+        let output = provider
+            .call(tx)
+            .into_future()
+            .instrument(tracing::info_span!("call_transferFrom",
+                from = %self.owner,
+                to = %self.pay_to,
+                value = %self.amount,
+                token_contract = %self.token,
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| revert::rpc_error(Some(self.owner.into()), e))?;
+        Ok(erc20_transfer_succeeded(&output))
+    }
+
+    /// Current allowance of the owner to the spender.
+    pub async fn allowance<P: Provider>(
+        &self,
+        provider: &P,
+    ) -> Result<U256, FacilitatorLocalError> {
+        IERC20Permit::new(self.token, provider)
+            .allowance(self.owner, self.spender)
+            .call()
+            .into_future()
+            .instrument(tracing::info_span!("fetch_allowance", token_contract = %self.token, sender = %self.owner, otel.kind = "client"))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+    }
+}
+
+/// Runs all preconditions of a `permit` payment:
+/// - Permit payload, matching scheme and network.
+/// - Spender is one of `spenders`.
+/// - Deadline not passed, permitted value covers `maxAmountRequired`.
+/// - Nonce matches the token's `nonces(owner)`.
+/// - Sufficient on-chain balance.
+/// - Token not paused, owner and recipient not blacklisted.
+/// - Signature recovers to the owner.
+#[instrument(skip_all, err)]
+pub async fn assert_valid_payment<P: Provider>(
+    provider: &P,
+    chain: &EvmChain,
+    spenders: &[Address],
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<PermitPayment, FacilitatorLocalError> {
+    let permit_payload = match &payload.payload {
+        ExactPaymentPayload::Permit(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                None,
+                Scheme::Permit,
+                payload.scheme,
+            ));
+        }
+    };
+    let permit = &permit_payload.permit;
+    let owner: Address = permit.owner.into();
+    let payer: MixedAddress = permit.owner.into();
+    for network in [payload.network, requirements.network] {
+        if network != chain.network() {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                Some(payer),
+                chain.network(),
+                network,
+            ));
+        }
+    }
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != Scheme::Permit {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                Some(payer),
+                Scheme::Permit,
+                scheme,
+            ));
+        }
+    }
+    let asset: EvmAddress = requirements
+        .asset
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let pay_to: EvmAddress = requirements
+        .pay_to
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let spender: Address = permit.spender.into();
+    if !spenders.contains(&spender) {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            format!("Permit spender {spender} is not this facilitator"),
+        ));
+    }
+    let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+    // DAI-style permits use a zero expiry for "never expires".
+    let never_expires = permit.value.is_none() && permit.deadline.0 == 0;
+    if !never_expires && permit.deadline < now + 6 {
+        return Err(FacilitatorLocalError::InvalidTiming(
+            payer,
+            format!("Expired: now {} > deadline {}", now + 6, permit.deadline),
+        ));
+    }
+    let amount = requirements.max_amount_required.0;
+    if let Some(value) = permit.value
+        && value.0 < amount
+    {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+
+    let token = IERC20Permit::new(asset.0, provider);
+    let nonce = token
+        .nonces(owner)
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_permit_nonce", token_contract = %asset, sender = %owner, otel.kind = "client"))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if nonce != permit.nonce.0 {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            format!(
+                "Permit nonce {} is not the token nonce {nonce}",
+                permit.nonce
+            ),
+        ));
+    }
+    let balance = token
+        .balanceOf(owner)
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_token_balance", token_contract = %asset, sender = %owner, otel.kind = "client"))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if balance < amount {
+        return Err(FacilitatorLocalError::InsufficientFunds(payer));
+    }
+    assert_token_transferable(provider, asset.0, owner, pay_to.0).await?;
+
+    let domain = permit_domain(&token, chain, asset.0, requirements).await?;
+    let deadline = U256::from(permit.deadline.0);
+    let hash = match permit.value {
+        Some(value) => typed_data::eip2612::Permit {
+            owner,
+            spender,
+            value: value.0,
+            nonce,
+            deadline,
+        }
+        .eip712_signing_hash(&domain),
+        None => typed_data::dai::Permit {
+            holder: owner,
+            spender,
+            nonce,
+            expiry: deadline,
+            allowed: true,
+        }
+        .eip712_signing_hash(&domain),
+    };
+    let signature = Signature::try_from(permit_payload.signature.0.as_slice())
+        .map_err(|e| FacilitatorLocalError::InvalidSignature(payer.clone(), format!("{e}")))?;
+    let recovered = signature
+        .recover_address_from_prehash(&hash)
+        .map_err(|e| FacilitatorLocalError::InvalidSignature(payer.clone(), format!("{e}")))?;
+    if recovered != owner {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            "Permit signature does not match owner".to_string(),
+        ));
+    }
+    let (v, r, s) = (
+        27 + u8::from(signature.v()),
+        B256::from(signature.r()),
+        B256::from(signature.s()),
+    );
+    let permit_calldata = match permit.value {
+        Some(value) => IERC20Permit::permitCall {
+            owner,
+            spender,
+            value: value.0,
+            deadline,
+            v,
+            r,
+            s,
+        }
+        .abi_encode(),
+        None => IDaiPermit::permitCall {
+            holder: owner,
+            spender,
+            nonce,
+            expiry: deadline,
+            allowed: true,
+            v,
+            r,
+            s,
+        }
+        .abi_encode(),
+    };
+
+    Ok(PermitPayment {
+        owner,
+        spender,
+        token: asset.0,
+        pay_to: pay_to.0,
+        amount,
+        permit_calldata: permit_calldata.into(),
+    })
+}
+
+/// EIP-712 domain of the token: `name` from `extra` or the token itself, `version` from `extra` or `"1"`.
+async fn permit_domain<P: Provider>(
+    token: &IERC20Permit::IERC20PermitInstance<&P>,
+    chain: &EvmChain,
+    asset: Address,
+    requirements: &PaymentRequirements,
+) -> Result<Eip712Domain, FacilitatorLocalError> {
+    let extra = |key: &str| {
+        requirements
+            .extra
+            .as_ref()
+            .and_then(|extra| extra.get(key)?.as_str().map(str::to_string))
+    };
+    let name = match extra("name") {
+        Some(name) => name,
+        None => token
+            .name()
+            .call()
+            .into_future()
+            .instrument(tracing::info_span!(
+                "fetch_eip712_name",
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?,
+    };
+    let version = extra("version").unwrap_or_else(|| "1".to_string());
+    Ok(eip712_domain! {
+        name: name,
+        version: version,
+        chain_id: chain.chain_id,
+        verifying_contract: asset,
+    })
+}
@@ -0,0 +1,386 @@
+//! x402 `permit2` scheme: ERC-20 payments through Uniswap's Permit2 signature transfers.
+//!
+//! Most ERC-20 tokens do not implement ERC-3009, so they can not be paid with the `exact` scheme.
+//! With `permit2`, the payer approves the canonical [`PERMIT2_ADDRESS`] contract for the token once,
+//! then signs an EIP-712 `PermitWitnessTransferFrom` message per payment, naming the facilitator as `spender`,
+//! with the witness `Witness(address to)` naming the recipient.
+//!
+//! - **Verify**: check network, scheme, token, spender, that the witness `to` is `payTo`, deadline, amount,
+//!   balance, Permit2 allowance, token pause and blacklist, then simulate `permitWitnessTransferFrom` from the
+//!   spender in an `eth_call`, which lets Permit2 itself validate the signature (EOA or EIP-1271), witness
+//!   included, and the nonce.
+//! - **Settle**: send `permitWitnessTransferFrom` from the spender, moving `maxAmountRequired` to `payTo`.
+//!
+//! Permit2 checks the signature over the witness, so a facilitator can not send the tokens anywhere but to the
+//! signed recipient. The requested amount is not part of the signed message; the facilitator takes it from the
+//! payment requirements.
+//!
+//! The `upto` scheme uses the same payload for metered usage: the signed amount is a maximum, and the
+//! resource server settles only what was consumed, as the `settleAmount` of the settle request. Permit2
+//! transfers any `requestedAmount` up to the permitted amount, and the unused remainder is never pulled.
+//! With `allowPartial` in the settle request, a payer whose balance or Permit2 allowance fell short in the
+//! meantime is charged what they cover, and the response reports the shortfall, so that a metered session
+//! can be closed out instead of failing entirely.
+
+use alloy::network::TransactionBuilder;
+use alloy::primitives::{Address, B256, Bytes, Signature, U256, address};
+use alloy::providers::Provider;
+use alloy::rpc::types::TransactionRequest;
+use alloy::sol;
+use alloy::sol_types::{SolCall, SolStruct, eip712_domain};
+use tracing::{Instrument, instrument};
+
+use crate::chain::FacilitatorLocalError;
+use crate::chain::evm::{EvmChain, assert_token_transferable};
+use crate::chain::revert;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    EvmAddress, ExactPaymentPayload, MixedAddress, PaymentPayload, PaymentRequirements, Scheme,
+};
+
+/// Canonical Permit2 deployment, at the same address on every supported EVM chain.
+pub const PERMIT2_ADDRESS: Address = address!("0x000000000022D473030F116dDEE9F6B43aC78BA3");
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IPermit2 {
+        struct TokenPermissions {
+            address token;
+            uint256 amount;
+        }
+        struct PermitTransferFrom {
+            TokenPermissions permitted;
+            uint256 nonce;
+            uint256 deadline;
+        }
+        struct SignatureTransferDetails {
+            address to;
+            uint256 requestedAmount;
+        }
+        function permitWitnessTransferFrom(PermitTransferFrom permit, SignatureTransferDetails transferDetails, address owner, bytes32 witness, string witnessTypeString, bytes signature) external;
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface IERC20 {
+        function balanceOf(address owner) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+    }
+}
+
+/// EIP-712 types signed by the payer; unlike the ABI struct, the message includes the `spender` and the witness.
+mod typed_data {
+    alloy::sol! {
+        struct TokenPermissions {
+            address token;
+            uint256 amount;
+        }
+        struct Witness {
+            address to;
+        }
+        struct PermitWitnessTransferFrom {
+            TokenPermissions permitted;
+            address spender;
+            uint256 nonce;
+            uint256 deadline;
+            Witness witness;
+        }
+    }
+}
+
+/// Type of the witness, as Permit2 appends it to `PermitWitnessTransferFrom(...,` to hash the signed message.
+pub const WITNESS_TYPE_STRING: &str =
+    "Witness witness)TokenPermissions(address token,uint256 amount)Witness(address to)";
+
+/// A validated Permit2 payment, ready to be simulated or settled.
+#[derive(Debug, Clone)]
+pub struct Permit2Payment {
+    /// Payer who signed the permit.
+    pub owner: Address,
+    /// Facilitator address the permit was issued to; must be `msg.sender` of the transfer.
+    pub spender: Address,
+    /// Fully populated `permitWitnessTransferFrom` call.
+    pub call: IPermit2::permitWitnessTransferFromCall,
+}
+
+impl Permit2Payment {
+    /// ABI-encoded `permitWitnessTransferFrom` calldata, to be sent to [`PERMIT2_ADDRESS`].
+    pub fn calldata(&self) -> Bytes {
+        self.call.abi_encode().into()
+    }
+
+    /// Simulates the transfer from the spender, surfacing signature, nonce and balance failures.
+    ///
+    /// # Errors
+    /// Returns [`FacilitatorLocalError::Reverted`] for a recognised revert of Permit2 or the token,
+    /// [`FacilitatorLocalError::ContractCall`] for any other failure.
+    pub async fn simulate<P: Provider>(&self, provider: &P) -> Result<(), FacilitatorLocalError> {
+        let tx = TransactionRequest::default()
+            .with_from(self.spender)
+            .with_to(PERMIT2_ADDRESS)
+            .with_input(self.calldata());
+        provider
+            .call(tx)
+            .into_future()
+            .instrument(tracing::info_span!("call_permitWitnessTransferFrom",
+                owner = %self.owner,
+                spender = %self.spender,
+                token = %self.call.permit.permitted.token,
+                to = %self.call.transferDetails.to,
+                amount = %self.call.transferDetails.requestedAmount,
+                otel.kind = "client",
+            ))
+            .await
+            .map_err(|e| revert::rpc_error(Some(self.owner.into()), e))?;
+        Ok(())
+    }
+}
+
+/// Runs all preconditions of a Permit2 payment, `permit2` or `upto`:
+/// - Permit2 payload, matching scheme and network.
+/// - Permitted token is the required asset, spender is one of `spenders`, witness `to` is `payTo`.
+/// - Deadline not passed, permitted amount covers `maxAmountRequired`.
+/// - Sufficient on-chain balance and allowance to the Permit2 contract.
+/// - Token not paused, owner and recipient not blacklisted.
+/// - For EOA payers, signature recovers to the owner.
+///
+/// The transfer moves `maxAmountRequired`, or `settle_amount` for `upto`, which must not exceed it. With
+/// `allow_partial`, it moves what the balance and allowance cover of that amount instead, if they fall short.
+#[instrument(skip_all, err)]
+pub async fn assert_valid_payment<P: Provider>(
+    provider: &P,
+    chain: &EvmChain,
+    spenders: &[Address],
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+    settle_amount: Option<U256>,
+    allow_partial: bool,
+) -> Result<Permit2Payment, FacilitatorLocalError> {
+    let permit2_payload = match &payload.payload {
+        ExactPaymentPayload::Permit2(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                None,
+                Scheme::Permit2,
+                payload.scheme,
+            ));
+        }
+    };
+    let owner: Address = permit2_payload.owner.into();
+    let payer: MixedAddress = permit2_payload.owner.into();
+    let permit = &permit2_payload.permit;
+    for network in [payload.network, requirements.network] {
+        if network != chain.network() {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                Some(payer),
+                chain.network(),
+                network,
+            ));
+        }
+    }
+    let expected_scheme = match requirements.scheme {
+        Scheme::Upto => Scheme::Upto,
+        _ => Scheme::Permit2,
+    };
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != expected_scheme {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                Some(payer),
+                expected_scheme,
+                scheme,
+            ));
+        }
+    }
+    let asset: EvmAddress = requirements
+        .asset
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    if permit.permitted.token != asset {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            format!(
+                "Permit is for token {}, requirements ask for {asset}",
+                permit.permitted.token
+            ),
+        ));
+    }
+    let pay_to: EvmAddress = requirements
+        .pay_to
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    if permit2_payload.witness.to != pay_to {
+        return Err(FacilitatorLocalError::ReceiverMismatch(
+            payer,
+            permit2_payload.witness.to.to_string(),
+            pay_to.to_string(),
+        ));
+    }
+    let spender: Address = permit.spender.into();
+    if !spenders.contains(&spender) {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            format!("Permit spender {spender} is not this facilitator"),
+        ));
+    }
+    let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+    if permit.deadline < now + 6 {
+        return Err(FacilitatorLocalError::InvalidTiming(
+            payer,
+            format!("Expired: now {} > deadline {}", now + 6, permit.deadline),
+        ));
+    }
+    let amount = requirements.max_amount_required.0;
+    if permit.permitted.amount.0 < amount {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+    let mut requested_amount = match settle_amount {
+        Some(settle_amount) if settle_amount > amount => {
+            return Err(FacilitatorLocalError::InvalidSettleAmount(
+                Some(payer),
+                format!("settleAmount {settle_amount} exceeds the maximum {amount}"),
+            ));
+        }
+        Some(settle_amount) => settle_amount,
+        None => amount,
+    };
+
+    let token = IERC20::new(asset.0, provider);
+    let balance = token
+        .balanceOf(owner)
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_token_balance", token_contract = %asset, sender = %owner, otel.kind = "client"))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if balance < amount && !allow_partial {
+        return Err(FacilitatorLocalError::InsufficientFunds(payer));
+    }
+    let allowance = token
+        .allowance(owner, PERMIT2_ADDRESS)
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_permit2_allowance", token_contract = %asset, sender = %owner, otel.kind = "client"))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if allowance < amount && !allow_partial {
+        return Err(FacilitatorLocalError::InsufficientAllowance(payer));
+    }
+    if allow_partial {
+        requested_amount = requested_amount.min(balance).min(allowance);
+        if requested_amount.is_zero() {
+            return Err(FacilitatorLocalError::InsufficientFunds(payer));
+        }
+    }
+    assert_token_transferable(provider, asset.0, owner, pay_to.0).await?;
+
+    let signature = Bytes::from(permit2_payload.signature.0.clone());
+    let witness = typed_data::Witness { to: pay_to.0 };
+    let witness_hash = witness.eip712_hash_struct();
+    let message = typed_data::PermitWitnessTransferFrom {
+        permitted: typed_data::TokenPermissions {
+            token: asset.0,
+            amount: permit.permitted.amount.0,
+        },
+        spender,
+        nonce: permit.nonce.0,
+        deadline: U256::from(permit.deadline.0),
+        witness,
+    };
+    let domain = eip712_domain! {
+        name: "Permit2",
+        chain_id: chain.chain_id,
+        verifying_contract: PERMIT2_ADDRESS,
+    };
+    assert_eoa_signature(
+        provider,
+        &payer,
+        owner,
+        &signature,
+        message.eip712_signing_hash(&domain),
+    )
+    .await?;
+
+    let call = IPermit2::permitWitnessTransferFromCall {
+        permit: IPermit2::PermitTransferFrom {
+            permitted: IPermit2::TokenPermissions {
+                token: asset.0,
+                amount: permit.permitted.amount.0,
+            },
+            nonce: permit.nonce.0,
+            deadline: U256::from(permit.deadline.0),
+        },
+        transferDetails: IPermit2::SignatureTransferDetails {
+            to: pay_to.0,
+            requestedAmount: requested_amount,
+        },
+        owner,
+        witness: witness_hash,
+        witnessTypeString: WITNESS_TYPE_STRING.to_string(),
+        signature,
+    };
+    Ok(Permit2Payment {
+        owner,
+        spender,
+        call,
+    })
+}
+
+/// Rejects a 65-byte signature that does not recover to `owner`, unless `owner` is a contract
+/// (then Permit2 validates it through EIP-1271 during simulation).
+async fn assert_eoa_signature<P: Provider>(
+    provider: &P,
+    payer: &MixedAddress,
+    owner: Address,
+    signature: &Bytes,
+    hash: B256,
+) -> Result<(), FacilitatorLocalError> {
+    let recovered = Signature::try_from(signature.as_ref())
+        .ok()
+        .and_then(|signature| signature.recover_address_from_prehash(&hash).ok());
+    if recovered == Some(owner) {
+        return Ok(());
+    }
+    let code = provider
+        .get_code_at(owner)
+        .into_future()
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    if code.is_empty() {
+        Err(FacilitatorLocalError::InvalidSignature(
+            payer.clone(),
+            "Permit2 signature does not match owner".to_string(),
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn witness_type_string_completes_the_signed_type() {
+        assert_eq!(
+            typed_data::PermitWitnessTransferFrom::eip712_encode_type(),
+            format!(
+                "PermitWitnessTransferFrom(TokenPermissions permitted,address spender,uint256 nonce,uint256 deadline,{WITNESS_TYPE_STRING}"
+            )
+        );
+    }
+}
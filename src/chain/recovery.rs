@@ -0,0 +1,150 @@
+//! Recovery of settlement transactions left in flight by a restart.
+//!
+//! A facilitator stopped between broadcasting a settlement and seeing it mined loses track of it: the client
+//! never got an answer and the payment is neither reported nor retried. With a [`TxJournal`], each EVM
+//! settlement is written to disk once broadcast and removed once its receipt is seen. On startup, [`recover`]
+//! goes through the entries left behind:
+//! - mined transactions are logged and dropped from the journal;
+//! - transactions still known to the node are watched until mined;
+//! - transactions the node dropped are broadcast again, from the same signer with the same calldata. A
+//!   rebroadcast failing, typically because the authorization was used in the meantime, drops the entry.
+//!
+//! Signers with pending transactions the journal knows nothing about are reported, as these were sent by
+//! something else or before the journal was enabled.
+//!
+//! Environment variables used:
+//! - `TX_JOURNAL_DIR` — directory of the journal. In-flight transactions are not recorded if unset.
+
+use alloy::primitives::{Address, B256, Bytes};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::chain::NetworkProvider;
+use crate::from_env;
+use crate::network::Network;
+use crate::provider_cache::{ProviderCache, ProviderMap};
+
+/// A broadcast settlement transaction whose receipt was not seen yet.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JournalEntry {
+    pub network: Network,
+    pub from: Address,
+    pub hash: B256,
+    pub to: Address,
+    pub calldata: Bytes,
+}
+
+/// In-flight settlement transactions, one JSON file per transaction.
+#[derive(Debug)]
+pub struct TxJournal {
+    dir: PathBuf,
+}
+
+impl TxJournal {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Opens the journal at `TX_JOURNAL_DIR`; `None` if unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(dir) = std::env::var(from_env::ENV_TX_JOURNAL_DIR) else {
+            return Ok(None);
+        };
+        Self::new(dir)
+            .map(Some)
+            .map_err(|e| format!("{}: {e}", from_env::ENV_TX_JOURNAL_DIR))
+    }
+
+    fn path(&self, hash: B256) -> PathBuf {
+        self.dir.join(format!("{hash}.json"))
+    }
+
+    /// Records a broadcast transaction. Failures are logged: they only affect recovery, not the settlement.
+    pub fn record(&self, entry: &JournalEntry) {
+        let path = self.path(entry.hash);
+        let tmp = path.with_extension("tmp");
+        let written = serde_json::to_vec(entry)
+            .map_err(std::io::Error::other)
+            .and_then(|bytes| std::fs::write(&tmp, bytes))
+            .and_then(|_| std::fs::rename(&tmp, &path));
+        if let Err(e) = written {
+            tracing::warn!(tx = %entry.hash, error = %e, "Failed to journal transaction");
+        }
+    }
+
+    /// Forgets a transaction once its receipt was seen.
+    pub fn remove(&self, hash: B256) {
+        match std::fs::remove_file(self.path(hash)) {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                tracing::warn!(tx = %hash, error = %e, "Failed to remove journaled transaction")
+            }
+        }
+    }
+
+    /// Journaled transactions sent on `network`. Unreadable entries are logged and skipped.
+    pub fn entries(&self, network: Network) -> Vec<JournalEntry> {
+        let read_dir = match std::fs::read_dir(&self.dir) {
+            Ok(read_dir) => read_dir,
+            Err(e) => {
+                tracing::warn!(dir = %self.dir.display(), error = %e, "Failed to read transaction journal");
+                return Vec::new();
+            }
+        };
+        read_dir
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().and_then(|e| e.to_str()) == Some("json"))
+            .filter_map(|path| match Self::read(&path) {
+                Ok(entry) => Some(entry),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping unreadable journal entry");
+                    None
+                }
+            })
+            .filter(|entry| entry.network == network)
+            .collect()
+    }
+
+    fn read(path: &Path) -> Result<JournalEntry, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_slice(&std::fs::read(path)?)?)
+    }
+}
+
+/// Reattaches the journaled transactions of every EVM provider, see the [module docs](self).
+pub async fn recover(providers: Arc<ProviderCache>) {
+    for provider in providers.values() {
+        if let NetworkProvider::Evm(provider) = provider {
+            provider.recover_in_flight().await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn journal_round_trip() {
+        let dir = std::env::temp_dir().join(format!("x402-tx-journal-{}", std::process::id()));
+        let journal = TxJournal::new(&dir).unwrap();
+        let entry = JournalEntry {
+            network: Network::BaseSepolia,
+            from: Address::repeat_byte(1),
+            hash: B256::repeat_byte(2),
+            to: Address::repeat_byte(3),
+            calldata: Bytes::from(vec![0xde, 0xad]),
+        };
+        journal.record(&entry);
+        assert_eq!(journal.entries(Network::BaseSepolia), vec![entry.clone()]);
+        assert!(journal.entries(Network::Base).is_empty());
+        journal.remove(entry.hash);
+        journal.remove(entry.hash);
+        assert!(journal.entries(Network::BaseSepolia).is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,422 @@
+//! Settlement through managed relayer services instead of the facilitator's own signers.
+//!
+//! Operators who would rather not hold funded keys for every network, or for every asset, can have some
+//! settlements sent by a relayer service. A [`SettlementBackend`] submits a call and reports the hash of
+//! the transaction that carried it once mined; the facilitator then reads the receipt from its own RPC
+//! endpoint, as for any other settlement. Two services are supported:
+//!
+//! - [Gelato Relay](https://docs.gelato.network), with a sponsored call paid from the operator's 1Balance.
+//! - [OpenZeppelin Relayer](https://docs.openzeppelin.com/relayer), the successor of Defender Relayers.
+//!
+//! Relayers are configured per network and may be restricted to some assets; settlements of other assets
+//! keep using the local signers. Only calls that any `msg.sender` may make are relayed: ERC-3009
+//! `transferWithAuthorization` settlements. The `permit2` and `permit` schemes, which name the facilitator's
+//! signer as spender, and batched settlements always use the local signers.
+
+use alloy::primitives::{Address, B256, Bytes};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+use crate::chain::FacilitatorLocalError;
+use crate::outbound::OutboundPolicy;
+use crate::types::MixedAddress;
+
+/// Time between two status requests to a relayer.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+/// Time after which a relayed call that is not mined is given up on.
+const RELAY_TIMEOUT: Duration = Duration::from_secs(180);
+
+/// A service sending settlement calls on behalf of the facilitator.
+#[async_trait]
+pub trait SettlementBackend: Send + Sync {
+    /// Name of the service, for logs.
+    fn name(&self) -> &'static str;
+
+    /// Has `calldata` sent to `to` and waits for it to be mined; returns the transaction hash.
+    ///
+    /// A mined but reverted transaction is not an error: its receipt tells the settlement failed.
+    async fn relay(&self, to: Address, calldata: Bytes) -> Result<B256, FacilitatorLocalError>;
+}
+
+/// Kind of relayer service.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RelayerKind {
+    Gelato,
+    Openzeppelin,
+}
+
+/// Settings of a relayer, in the `relayers` of a network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RelayerConfig {
+    pub kind: RelayerKind,
+    /// API key: the Gelato sponsor API key, or the OpenZeppelin Relayer API key.
+    pub api_key: String,
+    /// Base URL of the service; required for OpenZeppelin Relayer, Gelato's public API by default.
+    #[serde(default)]
+    pub url: Option<String>,
+    /// Relayer ID, for OpenZeppelin Relayer.
+    #[serde(default)]
+    pub relayer_id: Option<String>,
+    /// Assets settled through this relayer; all assets if unset.
+    #[serde(default)]
+    pub assets: Option<Vec<MixedAddress>>,
+}
+
+fn relay_error(name: &str, message: impl std::fmt::Display) -> FacilitatorLocalError {
+    FacilitatorLocalError::ContractCall(format!("{name} relayer: {message}"))
+}
+
+/// Polls `status` until it returns a transaction hash, or fails after [`RELAY_TIMEOUT`].
+async fn poll<F, Fut>(name: &'static str, mut status: F) -> Result<B256, FacilitatorLocalError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<Option<B256>, FacilitatorLocalError>>,
+{
+    let deadline = tokio::time::Instant::now() + RELAY_TIMEOUT;
+    loop {
+        if let Some(hash) = status().await? {
+            return Ok(hash);
+        }
+        if tokio::time::Instant::now() >= deadline {
+            return Err(relay_error(name, "timed out waiting for the transaction"));
+        }
+        tokio::time::sleep(POLL_INTERVAL).await;
+    }
+}
+
+/// [Gelato Relay](https://docs.gelato.network) sponsored calls.
+pub struct GelatoRelay {
+    http: reqwest::Client,
+    url: String,
+    api_key: String,
+    chain_id: u64,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GelatoTask {
+    task_id: String,
+}
+
+#[derive(Deserialize)]
+struct GelatoStatus {
+    task: GelatoTaskStatus,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct GelatoTaskStatus {
+    task_state: String,
+    #[serde(default)]
+    transaction_hash: Option<B256>,
+    #[serde(default)]
+    last_check_message: Option<String>,
+}
+
+impl GelatoRelay {
+    pub const DEFAULT_URL: &str = "https://api.gelato.digital";
+
+    pub fn new(url: String, api_key: String, chain_id: u64, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            url: url.trim_end_matches('/').to_string(),
+            api_key,
+            chain_id,
+        }
+    }
+
+    async fn status(&self, task_id: &str) -> Result<Option<B256>, FacilitatorLocalError> {
+        let status: GelatoStatus = self
+            .http
+            .get(format!("{}/tasks/status/{task_id}", self.url))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| relay_error(self.name(), e))?
+            .json()
+            .await
+            .map_err(|e| relay_error(self.name(), e))?;
+        let task = status.task;
+        match task.task_state.as_str() {
+            "ExecSuccess" | "ExecReverted" => task
+                .transaction_hash
+                .map(Some)
+                .ok_or_else(|| relay_error(self.name(), "no transaction hash for executed task")),
+            "Cancelled" | "NotFound" | "Blacklisted" => Err(relay_error(
+                self.name(),
+                format!(
+                    "task {task_id} {}: {}",
+                    task.task_state,
+                    task.last_check_message.unwrap_or_default()
+                ),
+            )),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl SettlementBackend for GelatoRelay {
+    fn name(&self) -> &'static str {
+        "gelato"
+    }
+
+    async fn relay(&self, to: Address, calldata: Bytes) -> Result<B256, FacilitatorLocalError> {
+        let task: GelatoTask = self
+            .http
+            .post(format!("{}/relays/v2/sponsored-call", self.url))
+            .json(&serde_json::json!({
+                "chainId": self.chain_id.to_string(),
+                "target": to,
+                "data": calldata,
+                "sponsorApiKey": self.api_key,
+            }))
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| relay_error(self.name(), e))?
+            .json()
+            .await
+            .map_err(|e| relay_error(self.name(), e))?;
+        tracing::info!(task_id = %task.task_id, "Relayed through Gelato");
+        poll(self.name(), || self.status(&task.task_id)).await
+    }
+}
+
+/// [OpenZeppelin Relayer](https://docs.openzeppelin.com/relayer) transactions.
+pub struct OpenZeppelinRelayer {
+    http: reqwest::Client,
+    url: String,
+    relayer_id: String,
+    api_key: String,
+}
+
+#[derive(Deserialize)]
+struct OpenZeppelinResponse {
+    data: OpenZeppelinTransaction,
+}
+
+#[derive(Deserialize)]
+struct OpenZeppelinTransaction {
+    id: String,
+    status: String,
+    #[serde(default)]
+    hash: Option<B256>,
+    #[serde(default)]
+    status_reason: Option<String>,
+}
+
+impl OpenZeppelinRelayer {
+    pub fn new(url: String, relayer_id: String, api_key: String, http: reqwest::Client) -> Self {
+        Self {
+            http,
+            url: url.trim_end_matches('/').to_string(),
+            relayer_id,
+            api_key,
+        }
+    }
+
+    fn transactions_url(&self) -> String {
+        format!(
+            "{}/api/v1/relayers/{}/transactions",
+            self.url, self.relayer_id
+        )
+    }
+
+    async fn send(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<OpenZeppelinTransaction, FacilitatorLocalError> {
+        let response: OpenZeppelinResponse = request
+            .bearer_auth(&self.api_key)
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(|e| relay_error(self.name(), e))?
+            .json()
+            .await
+            .map_err(|e| relay_error(self.name(), e))?;
+        Ok(response.data)
+    }
+
+    async fn status(&self, id: &str) -> Result<Option<B256>, FacilitatorLocalError> {
+        let url = format!("{}/{id}", self.transactions_url());
+        let transaction = self.send(self.http.get(url)).await?;
+        match transaction.status.as_str() {
+            "mined" | "confirmed" => transaction
+                .hash
+                .map(Some)
+                .ok_or_else(|| relay_error(self.name(), "no hash for mined transaction")),
+            "failed" | "expired" | "canceled" => Err(relay_error(
+                self.name(),
+                format!(
+                    "transaction {id} {}: {}",
+                    transaction.status,
+                    transaction.status_reason.unwrap_or_default()
+                ),
+            )),
+            _ => Ok(None),
+        }
+    }
+}
+
+#[async_trait]
+impl SettlementBackend for OpenZeppelinRelayer {
+    fn name(&self) -> &'static str {
+        "openzeppelin"
+    }
+
+    async fn relay(&self, to: Address, calldata: Bytes) -> Result<B256, FacilitatorLocalError> {
+        let request = self
+            .http
+            .post(self.transactions_url())
+            .json(&serde_json::json!({
+                "to": to,
+                "value": 0,
+                "data": calldata,
+                "speed": "fast",
+            }));
+        let transaction = self.send(request).await?;
+        tracing::info!(transaction_id = %transaction.id, "Relayed through OpenZeppelin Relayer");
+        poll(self.name(), || self.status(&transaction.id)).await
+    }
+}
+
+/// A relayer and the assets it settles, all of them if `None`.
+type Route = (Option<Vec<Address>>, Arc<dyn SettlementBackend>);
+
+/// Relayers of a network, each for all assets or some of them.
+#[derive(Default)]
+pub struct Relayers {
+    routes: Vec<Route>,
+}
+
+impl std::fmt::Debug for Relayers {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(
+                self.routes
+                    .iter()
+                    .map(|(assets, backend)| (backend.name(), assets)),
+            )
+            .finish()
+    }
+}
+
+impl Relayers {
+    /// Relayers from the `relayers` of a network with the given chain ID, reached within `outbound`.
+    pub fn from_config(
+        configs: &[RelayerConfig],
+        chain_id: u64,
+        outbound: &OutboundPolicy,
+    ) -> Result<Self, String> {
+        let http = outbound.http_client();
+        let routes = configs
+            .iter()
+            .map(|config| {
+                let assets = config
+                    .assets
+                    .as_ref()
+                    .map(|assets| {
+                        assets
+                            .iter()
+                            .map(|asset| Address::try_from(asset.clone()))
+                            .collect::<Result<Vec<_>, _>>()
+                    })
+                    .transpose()
+                    .map_err(|e| format!("invalid relayer asset: {e:?}"))?;
+                let url = match config.kind {
+                    RelayerKind::Gelato => config
+                        .url
+                        .clone()
+                        .unwrap_or_else(|| GelatoRelay::DEFAULT_URL.to_string()),
+                    RelayerKind::Openzeppelin => config
+                        .url
+                        .clone()
+                        .ok_or("openzeppelin relayer requires url and relayer_id")?,
+                };
+                Url::parse(&url)
+                    .map_err(|e| e.to_string())
+                    .and_then(|parsed| outbound.validate_url(&parsed).map_err(|e| e.to_string()))
+                    .map_err(|e| format!("invalid relayer url {url}: {e}"))?;
+                let backend: Arc<dyn SettlementBackend> = match config.kind {
+                    RelayerKind::Gelato => Arc::new(GelatoRelay::new(
+                        url,
+                        config.api_key.clone(),
+                        chain_id,
+                        http.clone(),
+                    )),
+                    RelayerKind::Openzeppelin => {
+                        let Some(relayer_id) = &config.relayer_id else {
+                            return Err("openzeppelin relayer requires url and relayer_id".into());
+                        };
+                        Arc::new(OpenZeppelinRelayer::new(
+                            url,
+                            relayer_id.clone(),
+                            config.api_key.clone(),
+                            http.clone(),
+                        ))
+                    }
+                };
+                Ok((assets, backend))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Self { routes })
+    }
+
+    /// Relayer of settlements of `asset`: the first configured for it, or for all assets.
+    pub fn for_asset(&self, asset: Address) -> Option<&dyn SettlementBackend> {
+        self.routes
+            .iter()
+            .find(|(assets, _)| assets.as_ref().is_none_or(|assets| assets.contains(&asset)))
+            .map(|(_, backend)| backend.as_ref())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_assets_to_their_relayer() {
+        let usdc = Address::repeat_byte(1);
+        let configs: Vec<RelayerConfig> = toml::from_str::<toml::Value>(
+            r#"
+            relayers = [
+                { kind = "openzeppelin", url = "http://relayer", relayer_id = "base", api_key = "k", assets = ["0x0101010101010101010101010101010101010101"] },
+                { kind = "gelato", api_key = "k" },
+            ]
+            "#,
+        )
+        .unwrap()["relayers"]
+            .clone()
+            .try_into()
+            .unwrap();
+        let outbound =
+            OutboundPolicy::new(vec!["http".to_string(), "https".to_string()], Vec::new());
+        let relayers = Relayers::from_config(&configs, 8453, &outbound).unwrap();
+        assert_eq!(relayers.for_asset(usdc).unwrap().name(), "openzeppelin");
+        assert_eq!(
+            relayers.for_asset(Address::repeat_byte(2)).unwrap().name(),
+            "gelato"
+        );
+        assert!(
+            Relayers::from_config(&configs[1..], 8453, &outbound)
+                .unwrap()
+                .for_asset(usdc)
+                .is_some()
+        );
+        assert!(Relayers::default().for_asset(usdc).is_none());
+        let mut missing_id = configs[0].clone();
+        missing_id.relayer_id = None;
+        assert!(Relayers::from_config(&[missing_id], 8453, &outbound).is_err());
+        let mut internal = configs[0].clone();
+        internal.url = Some("http://10.0.0.1".to_string());
+        assert!(Relayers::from_config(&[internal], 8453, &outbound).is_err());
+    }
+}
@@ -0,0 +1,261 @@
+//! Detection of settlements undone by chain reorganizations.
+//!
+//! A settlement is reported once its transaction is mined with the configured confirmations, but a reorganization
+//! deeper than that can still take its block out of the chain. With a [`ReorgWatch`], every successful EVM
+//! settlement is followed for `REORG_WATCH_BLOCKS` blocks after it was mined. On each check, a transaction:
+//! - still in the block it was mined in is left alone, and forgotten once deep enough;
+//! - mined again in another block is reported as re-included, with its new status;
+//! - back in the mempool is waited for;
+//! - dropped by the node is broadcast again exactly as signed, with its original nonce, so that it can only ever be
+//!   mined once. It is reported as dropped only once the signer's nonce has moved past it, i.e. another transaction
+//!   took its place and it can not be mined anymore.
+//!
+//! Every outcome is logged, counted in the `x402.settlement_reorged` metric, and published as a [`ReorgEvent`] to
+//! [`ReorgWatch::subscribe`]rs; the settlement queue corrects the state served by `GET /queue/{id}` with them.
+//!
+//! Environment variables used:
+//! - `REORG_WATCH_BLOCKS` — blocks a mined settlement is followed for. Settlements are not followed if unset or `0`.
+//! - `REORG_CHECK_INTERVAL_SECS` — seconds between checks (default: `15`).
+
+use alloy::primitives::{Address, B256, Bytes};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+
+use crate::chain::NetworkProvider;
+use crate::from_env;
+use crate::network::Network;
+use crate::provider_cache::{ProviderCache, ProviderMap};
+
+/// Default interval between checks, in seconds.
+const DEFAULT_INTERVAL_SECS: u64 = 15;
+/// Events kept for slow subscribers.
+const EVENT_CAPACITY: usize = 256;
+
+/// A successful settlement transaction, followed until it is deep enough.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WatchedSettlement {
+    /// Transaction reported to the client.
+    pub reported: B256,
+    pub from: Address,
+    /// Nonce of the transaction for `from`.
+    pub nonce: u64,
+    /// The transaction as signed, EIP-2718 encoded, to broadcast again unchanged.
+    pub raw: Bytes,
+    /// Hash and number of the block the transaction is in; `None` while it waits to be mined again.
+    pub block: Option<(B256, u64)>,
+}
+
+/// What became of a settlement whose block left the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "outcome", rename_all = "snake_case")]
+pub enum ReorgOutcome {
+    /// The settlement is mined again, in `transaction`, successfully or not.
+    #[serde(rename_all = "camelCase")]
+    Reincluded {
+        transaction: B256,
+        block_number: u64,
+        success: bool,
+    },
+    /// The node dropped the transaction: it was broadcast again, unchanged, as `transaction`.
+    Rebroadcast { transaction: B256 },
+    /// The node dropped the transaction, and the signer's nonce has since been used by another one.
+    Dropped { error: String },
+}
+
+impl ReorgOutcome {
+    /// Transaction now carrying the settlement, if any.
+    pub fn transaction(&self) -> Option<B256> {
+        match self {
+            ReorgOutcome::Reincluded { transaction, .. }
+            | ReorgOutcome::Rebroadcast { transaction } => Some(*transaction),
+            ReorgOutcome::Dropped { .. } => None,
+        }
+    }
+}
+
+/// A settlement affected by a reorganization.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReorgEvent {
+    pub network: Network,
+    /// Transaction the settlement was reported with; apart from the `transaction` of the outcome.
+    #[serde(rename = "reportedTransaction")]
+    pub transaction: B256,
+    #[serde(flatten)]
+    pub outcome: ReorgOutcome,
+}
+
+/// Settlements of a network followed for reorganizations, see the [module docs](self).
+///
+/// Cheap to clone: all clones share the same settlements and subscribers.
+#[derive(Debug, Clone)]
+pub struct ReorgWatch {
+    network: Network,
+    /// Blocks a settlement is followed for after it was mined.
+    depth: u64,
+    settlements: Arc<DashMap<B256, WatchedSettlement>>,
+    events: broadcast::Sender<ReorgEvent>,
+}
+
+impl ReorgWatch {
+    pub fn new(network: Network, depth: u64) -> Self {
+        Self {
+            network,
+            depth,
+            settlements: Arc::new(DashMap::new()),
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        }
+    }
+
+    /// Watch of `network` following settlements for `REORG_WATCH_BLOCKS`; `None` if unset or `0`.
+    pub fn from_env(network: Network) -> Result<Option<Self>, String> {
+        let depth = match std::env::var(from_env::ENV_REORG_WATCH_BLOCKS) {
+            Ok(value) => value
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_REORG_WATCH_BLOCKS))?,
+            Err(_) => 0,
+        };
+        Ok((depth > 0).then(|| Self::new(network, depth)))
+    }
+
+    pub fn depth(&self) -> u64 {
+        self.depth
+    }
+
+    /// Follows the settlement `hash` of `from` with `nonce`, signed as `raw` and mined in `block`.
+    pub fn watch(&self, hash: B256, from: Address, nonce: u64, raw: Bytes, block: (B256, u64)) {
+        self.settlements.insert(
+            hash,
+            WatchedSettlement {
+                reported: hash,
+                from,
+                nonce,
+                raw,
+                block: Some(block),
+            },
+        );
+    }
+
+    /// Settlements followed now.
+    pub fn settlements(&self) -> Vec<WatchedSettlement> {
+        self.settlements.iter().map(|entry| entry.clone()).collect()
+    }
+
+    /// Replaces the followed settlement reported as `settlement.reported`.
+    pub fn update(&self, settlement: WatchedSettlement) {
+        self.settlements.insert(settlement.reported, settlement);
+    }
+
+    /// Stops following the settlement reported as `reported`.
+    pub fn forget(&self, reported: &B256) {
+        self.settlements.remove(reported);
+    }
+
+    /// Events of the settlements affected by reorganizations from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<ReorgEvent> {
+        self.events.subscribe()
+    }
+
+    /// Logs and publishes what became of the settlement reported as `reported`.
+    pub fn emit(&self, reported: B256, outcome: ReorgOutcome) {
+        tracing::warn!(
+            monotonic_counter.x402.settlement_reorged = 1,
+            network = %self.network,
+            tx = %reported,
+            outcome = ?outcome,
+            "Settlement affected by a reorganization"
+        );
+        // No subscriber is fine: the outcome is logged.
+        let _ = self.events.send(ReorgEvent {
+            network: self.network,
+            transaction: reported,
+            outcome,
+        });
+    }
+}
+
+/// Reads the interval between checks from `REORG_CHECK_INTERVAL_SECS`.
+pub fn interval_from_env() -> Result<Duration, String> {
+    let secs = match std::env::var(from_env::ENV_REORG_CHECK_INTERVAL_SECS) {
+        Ok(value) => value
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("{}: {e}", from_env::ENV_REORG_CHECK_INTERVAL_SECS))?,
+        Err(_) => DEFAULT_INTERVAL_SECS,
+    };
+    if secs == 0 {
+        return Err(format!(
+            "{} must be positive",
+            from_env::ENV_REORG_CHECK_INTERVAL_SECS
+        ));
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+/// Watches of the EVM networks following settlements.
+pub fn watches(providers: &ProviderCache) -> Vec<ReorgWatch> {
+    providers
+        .values()
+        .filter_map(|provider| match provider {
+            NetworkProvider::Evm(provider) => provider.reorg_watch().cloned(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Checks the followed settlements of every EVM network every `interval` until cancelled.
+pub fn spawn(
+    providers: Arc<ProviderCache>,
+    interval: Duration,
+    cancellation_token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                _ = ticker.tick() => {
+                    for provider in providers.values() {
+                        if let NetworkProvider::Evm(provider) = provider {
+                            let budget = provider.rpc_budget();
+                            if budget.should_throttle() {
+                                tracing::debug!(network = %budget.network(), "RPC quota nearly used, skipping reorg check");
+                                continue;
+                            }
+                            provider.check_reorgs().await;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_serialize_with_their_outcome() {
+        let event = ReorgEvent {
+            network: Network::Base,
+            transaction: B256::repeat_byte(1),
+            outcome: ReorgOutcome::Reincluded {
+                transaction: B256::repeat_byte(2),
+                block_number: 7,
+                success: true,
+            },
+        };
+        let json = serde_json::to_value(&event).unwrap();
+        assert_eq!(json["network"], "base");
+        assert_eq!(json["outcome"], "reincluded");
+        assert_eq!(json["transaction"], B256::repeat_byte(2).to_string());
+        assert_eq!(json["blockNumber"], 7);
+        assert_eq!(serde_json::from_value::<ReorgEvent>(json).unwrap(), event);
+    }
+}
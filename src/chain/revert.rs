@@ -0,0 +1,172 @@
+//! Known reasons a token or Permit2 reverts a settlement, decoded from revert data.
+//!
+//! Failed simulations and transactions carry revert data: either an `Error(string)` message, such as FiatToken's
+//! `FiatTokenV2: authorization is used or canceled`, or an ABI-encoded custom error, such as OpenZeppelin's
+//! `ERC20InsufficientBalance`. [`Revert::decode`] recognises the common ones so that clients get a specific
+//! [`FacilitatorErrorReason`] instead of a generic contract call failure.
+
+use alloy::primitives::Bytes;
+use alloy::sol;
+use alloy::sol_types::{SolError, decode_revert_reason};
+use alloy::transports::TransportError;
+
+use crate::chain::FacilitatorLocalError;
+use crate::types::{FacilitatorErrorReason, MixedAddress};
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    interface TokenErrors {
+        error ERC20InsufficientBalance(address sender, uint256 balance, uint256 needed);
+        error ERC20InsufficientAllowance(address spender, uint256 allowance, uint256 needed);
+        error EnforcedPause();
+        // Permit2
+        error InvalidNonce();
+        error SignatureExpired(uint256 signatureDeadline);
+        error InvalidSignature();
+        error InvalidSigner();
+        error InsufficientAllowance(uint256 amount);
+        error AllowanceExpired(uint256 deadline);
+    }
+}
+
+/// Recognised cause of a reverted settlement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub enum Revert {
+    #[error("authorization already used")]
+    AuthorizationUsed,
+    #[error("authorization not yet valid")]
+    AuthorizationNotYetValid,
+    #[error("authorization expired")]
+    AuthorizationExpired,
+    #[error("invalid signature")]
+    InvalidSignature,
+    #[error("insufficient balance")]
+    InsufficientBalance,
+    #[error("insufficient allowance")]
+    InsufficientAllowance,
+    #[error("account blacklisted")]
+    Blacklisted,
+    #[error("token paused")]
+    Paused,
+}
+
+impl Revert {
+    /// Recognises a custom error or revert message in `data`; `None` for anything else.
+    pub fn decode(data: &[u8]) -> Option<Self> {
+        let selector: [u8; 4] = data.get(..4)?.try_into().ok()?;
+        let revert = match selector {
+            TokenErrors::ERC20InsufficientBalance::SELECTOR => Self::InsufficientBalance,
+            TokenErrors::ERC20InsufficientAllowance::SELECTOR
+            | TokenErrors::InsufficientAllowance::SELECTOR
+            | TokenErrors::AllowanceExpired::SELECTOR => Self::InsufficientAllowance,
+            TokenErrors::EnforcedPause::SELECTOR => Self::Paused,
+            TokenErrors::InvalidNonce::SELECTOR => Self::AuthorizationUsed,
+            TokenErrors::SignatureExpired::SELECTOR => Self::AuthorizationExpired,
+            TokenErrors::InvalidSignature::SELECTOR | TokenErrors::InvalidSigner::SELECTOR => {
+                Self::InvalidSignature
+            }
+            _ => return Self::from_message(&decode_revert_reason(data)?),
+        };
+        Some(revert)
+    }
+
+    /// Recognises a revert message of FiatToken (USDC, EURC), OpenZeppelin or Solmate tokens.
+    fn from_message(message: &str) -> Option<Self> {
+        let message = message.to_lowercase();
+        let revert = if message.contains("authorization is used")
+            || message.contains("authorization already used")
+        {
+            Self::AuthorizationUsed
+        } else if message.contains("not yet valid") {
+            Self::AuthorizationNotYetValid
+        } else if message.contains("expired") {
+            Self::AuthorizationExpired
+        } else if message.contains("invalid signature") {
+            Self::InvalidSignature
+        } else if message.contains("exceeds balance") || message.contains("insufficient balance") {
+            Self::InsufficientBalance
+        } else if message.contains("exceeds allowance")
+            || message.contains("insufficient allowance")
+        {
+            Self::InsufficientAllowance
+        } else if message.contains("blacklisted") || message.contains("blocklisted") {
+            Self::Blacklisted
+        } else if message.contains("paused") {
+            Self::Paused
+        } else {
+            return None;
+        };
+        Some(revert)
+    }
+
+    /// Error reason reported to clients.
+    pub fn reason(&self) -> FacilitatorErrorReason {
+        match self {
+            Revert::AuthorizationUsed => FacilitatorErrorReason::NonceReused,
+            Revert::AuthorizationNotYetValid => FacilitatorErrorReason::InvalidValidAfter,
+            Revert::AuthorizationExpired => FacilitatorErrorReason::InvalidValidBefore,
+            Revert::InvalidSignature => FacilitatorErrorReason::InvalidSignature,
+            Revert::InsufficientBalance => FacilitatorErrorReason::InsufficientFunds,
+            Revert::InsufficientAllowance => FacilitatorErrorReason::InsufficientAllowance,
+            Revert::Blacklisted => FacilitatorErrorReason::AddressBlacklisted,
+            Revert::Paused => FacilitatorErrorReason::TokenPaused,
+        }
+    }
+}
+
+/// Maps a failed contract call to [`FacilitatorLocalError::Reverted`] if its revert is recognised.
+pub fn contract_error(
+    payer: Option<MixedAddress>,
+    e: alloy::contract::Error,
+) -> FacilitatorLocalError {
+    reverted(payer, e.as_revert_data())
+        .unwrap_or_else(|| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+}
+
+/// Maps a failed RPC request, e.g. gas estimation of a settlement, like [`contract_error`].
+pub fn rpc_error(payer: Option<MixedAddress>, e: TransportError) -> FacilitatorLocalError {
+    let data = e
+        .as_error_resp()
+        .and_then(|payload| payload.as_revert_data());
+    reverted(payer, data).unwrap_or_else(|| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+}
+
+/// [`FacilitatorLocalError::Reverted`] for recognised revert `data`.
+pub fn reverted(payer: Option<MixedAddress>, data: Option<Bytes>) -> Option<FacilitatorLocalError> {
+    let revert = Revert::decode(&data?)?;
+    Some(FacilitatorLocalError::Reverted(payer, revert))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::{Address, U256};
+    use alloy::sol_types::Revert as ErrorString;
+
+    #[test]
+    fn decodes_known_reverts() {
+        let message = |reason: &str| ErrorString::from(reason).abi_encode();
+        assert_eq!(
+            Revert::decode(&message("FiatTokenV2: authorization is used or canceled")),
+            Some(Revert::AuthorizationUsed)
+        );
+        assert_eq!(
+            Revert::decode(&message("Blacklistable: account is blacklisted")),
+            Some(Revert::Blacklisted)
+        );
+        assert_eq!(
+            Revert::decode(&message("FiatTokenV2: authorization is not yet valid")),
+            Some(Revert::AuthorizationNotYetValid)
+        );
+        let custom = TokenErrors::ERC20InsufficientBalance {
+            sender: Address::ZERO,
+            balance: U256::ZERO,
+            needed: U256::from(1),
+        }
+        .abi_encode();
+        assert_eq!(Revert::decode(&custom), Some(Revert::InsufficientBalance));
+        assert_eq!(Revert::decode(&message("something else")), None);
+        assert_eq!(Revert::decode(&[0xde, 0xad]), None);
+    }
+}
@@ -0,0 +1,261 @@
+//! RPC request accounting against monthly provider quotas.
+//!
+//! Hosted RPC providers usually bill or cap by request count per calendar month.
+//! An [`RpcBudget`] counts every JSON-RPC request sent to a network's endpoint (a batch counts as
+//! one request per call), resets at the start of each UTC month, and reports when usage gets
+//! close to the configured quota so non-critical reads can back off. The background readers do:
+//! health probes with the signer balance checks, reorg checks of settled transactions, and token probes.
+//! Verification and settlement are never throttled.
+//!
+//! Counting is wired into the transports themselves: [`RpcBudgetLayer`] wraps the Alloy transport
+//! used by EVM providers, and [`RpcBudgetSender`] wraps the Solana HTTP sender.
+//!
+//! Usage is exported as the `x402.rpc.requests` counter and the `x402.rpc.monthly_usage` gauge,
+//! both tagged by network.
+//!
+//! Environment variables used:
+//! - `RPC_MONTHLY_QUOTA_<NETWORK>` — monthly request quota of the endpoint configured
+//!   in `RPC_URL_<NETWORK>`, e.g. `RPC_MONTHLY_QUOTA_BASE=3000000`. Unlimited if unset.
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportFut};
+use async_trait::async_trait;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_sender::{RpcSender, RpcTransportStats};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::task::{Context, Poll};
+use std::time::SystemTime;
+use tower::{Layer, Service};
+
+use crate::from_env;
+use crate::network::Network;
+
+/// Share of the monthly quota after which non-critical reads should be skipped.
+const THROTTLE_RATIO: f64 = 0.9;
+
+#[derive(Debug)]
+struct RpcBudgetInner {
+    network: Network,
+    monthly_quota: Option<u64>,
+    /// Month the `used` counter belongs to, as months since January 1970.
+    month: AtomicU32,
+    used: AtomicU64,
+}
+
+/// Monthly RPC request counter for a single network endpoint.
+///
+/// Cheap to clone: all clones share the same counters.
+#[derive(Debug, Clone)]
+pub struct RpcBudget {
+    inner: Arc<RpcBudgetInner>,
+}
+
+impl RpcBudget {
+    pub fn new(network: Network, monthly_quota: Option<u64>) -> Self {
+        Self {
+            inner: Arc::new(RpcBudgetInner {
+                network,
+                monthly_quota,
+                month: AtomicU32::new(current_month()),
+                used: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Reads the quota for `network` from `RPC_MONTHLY_QUOTA_<NETWORK>`.
+    pub fn from_env(network: Network) -> Self {
+        let env_var = from_env::rpc_quota_env_name_from_network(network);
+        let monthly_quota = std::env::var(&env_var)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok());
+        Self::new(network, monthly_quota)
+    }
+
+    pub fn network(&self) -> Network {
+        self.inner.network
+    }
+
+    pub fn monthly_quota(&self) -> Option<u64> {
+        self.inner.monthly_quota
+    }
+
+    /// Requests sent during the current UTC month.
+    pub fn used(&self) -> u64 {
+        self.roll_over();
+        self.inner.used.load(Ordering::Relaxed)
+    }
+
+    /// Accounts for `requests` JSON-RPC calls sent to the endpoint.
+    pub fn record(&self, requests: u64) {
+        self.roll_over();
+        let used = self.inner.used.fetch_add(requests, Ordering::Relaxed) + requests;
+        let network = self.inner.network;
+        tracing::info!(
+            monotonic_counter.x402.rpc.requests = requests,
+            gauge.x402.rpc.monthly_usage = used,
+            network = %network,
+        );
+        if let Some(quota) = self.inner.monthly_quota
+            && used.saturating_sub(requests) < quota
+            && used >= quota
+        {
+            tracing::warn!(network = %network, quota, "Monthly RPC quota exhausted");
+        }
+    }
+
+    /// Whether usage is close enough to the quota that non-critical reads should be skipped.
+    ///
+    /// Always `false` when no quota is configured.
+    pub fn should_throttle(&self) -> bool {
+        match self.inner.monthly_quota {
+            Some(quota) => self.used() as f64 >= quota as f64 * THROTTLE_RATIO,
+            None => false,
+        }
+    }
+
+    /// Resets the counter when a new month has started since the last request.
+    fn roll_over(&self) {
+        let month = current_month();
+        let previous = self.inner.month.load(Ordering::Relaxed);
+        if previous != month
+            && self
+                .inner
+                .month
+                .compare_exchange(previous, month, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.inner.used.store(0, Ordering::Relaxed);
+        }
+    }
+}
+
+/// Months elapsed since January 1970 (UTC) at the current system time.
+fn current_month() -> u32 {
+    let secs = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    month_index(secs / 86_400)
+}
+
+/// Converts days since the Unix epoch into months since January 1970.
+///
+/// Uses the civil-from-days algorithm for the proleptic Gregorian calendar.
+fn month_index(days: u64) -> u32 {
+    let z = days + 719_468;
+    let era = z / 146_097;
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + u64::from(month <= 2);
+    ((year - 1970) * 12 + (month - 1)) as u32
+}
+
+/// Tower layer counting requests of an Alloy transport against an [`RpcBudget`].
+#[derive(Debug, Clone)]
+pub struct RpcBudgetLayer {
+    budget: RpcBudget,
+}
+
+impl RpcBudgetLayer {
+    pub fn new(budget: RpcBudget) -> Self {
+        Self { budget }
+    }
+}
+
+impl<S> Layer<S> for RpcBudgetLayer {
+    type Service = RpcBudgetService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcBudgetService {
+            inner,
+            budget: self.budget.clone(),
+        }
+    }
+}
+
+/// Transport service produced by [`RpcBudgetLayer`].
+#[derive(Debug, Clone)]
+pub struct RpcBudgetService<S> {
+    inner: S,
+    budget: RpcBudget,
+}
+
+impl<S> Service<RequestPacket> for RpcBudgetService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>,
+    S::Future: Send + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        self.budget.record(request.len() as u64);
+        Box::pin(self.inner.call(request))
+    }
+}
+
+/// Solana [`RpcSender`] counting requests of the wrapped sender against an [`RpcBudget`].
+pub struct RpcBudgetSender<S> {
+    inner: S,
+    budget: RpcBudget,
+}
+
+impl<S> RpcBudgetSender<S> {
+    pub fn new(inner: S, budget: RpcBudget) -> Self {
+        Self { inner, budget }
+    }
+}
+
+#[async_trait]
+impl<S: RpcSender + Send + Sync> RpcSender for RpcBudgetSender<S> {
+    async fn send(
+        &self,
+        request: RpcRequest,
+        params: serde_json::Value,
+    ) -> ClientResult<serde_json::Value> {
+        self.budget.record(1);
+        self.inner.send(request, params).await
+    }
+
+    fn get_transport_stats(&self) -> RpcTransportStats {
+        self.inner.get_transport_stats()
+    }
+
+    fn url(&self) -> String {
+        self.inner.url()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn month_index_follows_the_calendar() {
+        assert_eq!(month_index(0), 0); // 1970-01-01
+        assert_eq!(month_index(30), 0); // 1970-01-31
+        assert_eq!(month_index(31), 1); // 1970-02-01
+        assert_eq!(month_index(19_782), 649); // 2024-02-29
+        assert_eq!(month_index(19_783), 650); // 2024-03-01
+    }
+
+    #[test]
+    fn throttles_near_quota() {
+        let budget = RpcBudget::new(Network::Base, Some(100));
+        budget.record(89);
+        assert!(!budget.should_throttle());
+        budget.record(1);
+        assert!(budget.should_throttle());
+        assert!(!RpcBudget::new(Network::Base, None).should_throttle());
+    }
+}
@@ -0,0 +1,224 @@
+//! Retries of transient RPC failures.
+//!
+//! Hosted RPC endpoints rate-limit, time out and restart their backends now and then. Without retries, such a
+//! hiccup fails the `/verify` or `/settle` that happened to hit it. [`RpcRetryLayer`] wraps the Alloy transport of
+//! EVM providers and sends a request again when it fails transiently:
+//! - rate limits (HTTP `429`, JSON-RPC `-32005` and the like) and temporarily unavailable backends,
+//! - timeouts and connection failures.
+//!
+//! Retries wait with exponential backoff and full jitter: a random delay up to `RPC_RETRY_INITIAL_BACKOFF_MS`,
+//! doubling on every retry up to `RPC_RETRY_MAX_BACKOFF_MS`, so that requests failing together do not retry in
+//! lockstep. Broadcasts (`eth_sendRawTransaction`) are never retried here: a broadcast that timed out may have gone
+//! through, and sending it again is left to the settlement flow, which knows its nonce. Solana providers are not
+//! wrapped, as their HTTP sender already retries rate limits.
+//!
+//! Retries are counted in the `x402.rpc.retries` metric, tagged by network and method.
+//!
+//! Environment variables used:
+//! - `RPC_MAX_RETRIES` — retries of a failing request, `0` to disable (default: `3`),
+//! - `RPC_RETRY_INITIAL_BACKOFF_MS` — upper bound of the first delay (default: `200`),
+//! - `RPC_RETRY_MAX_BACKOFF_MS` — upper bound of any delay (default: `5000`).
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::layers::{RateLimitRetryPolicy, RetryPolicy as _};
+use alloy::transports::{RpcError, TransportError, TransportErrorKind, TransportFut};
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+
+use crate::from_env;
+use crate::network::Network;
+
+/// Methods sent at most once: they are not idempotent from the facilitator's point of view.
+const NEVER_RETRIED: &[&str] = &["eth_sendRawTransaction", "eth_sendTransaction"];
+
+/// How failing RPC requests are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Retries of a failing request, the first attempt excluded.
+    pub max_retries: u32,
+    /// Upper bound of the delay before the first retry.
+    pub initial_backoff: Duration,
+    /// Upper bound of the delay before any retry.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(200),
+            max_backoff: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Reads the policy from `RPC_MAX_RETRIES`, `RPC_RETRY_INITIAL_BACKOFF_MS` and `RPC_RETRY_MAX_BACKOFF_MS`.
+    pub fn from_env() -> Result<Self, String> {
+        fn read(name: &str) -> Result<Option<u64>, String> {
+            match std::env::var(name) {
+                Ok(value) => value
+                    .trim()
+                    .parse::<u64>()
+                    .map(Some)
+                    .map_err(|e| format!("{name}: {e}")),
+                Err(_) => Ok(None),
+            }
+        }
+        let mut policy = Self::default();
+        if let Some(max_retries) = read(from_env::ENV_RPC_MAX_RETRIES)? {
+            policy.max_retries = u32::try_from(max_retries)
+                .map_err(|e| format!("{}: {e}", from_env::ENV_RPC_MAX_RETRIES))?;
+        }
+        if let Some(ms) = read(from_env::ENV_RPC_RETRY_INITIAL_BACKOFF_MS)? {
+            policy.initial_backoff = Duration::from_millis(ms);
+        }
+        if let Some(ms) = read(from_env::ENV_RPC_RETRY_MAX_BACKOFF_MS)? {
+            policy.max_backoff = Duration::from_millis(ms);
+        }
+        Ok(policy)
+    }
+
+    /// Upper bound of the delay before retry number `retry`, counted from `0`.
+    pub fn backoff_cap(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+
+    /// Random delay before retry number `retry`, between zero and [`RetryPolicy::backoff_cap`].
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let cap = self.backoff_cap(retry).as_millis() as u64;
+        let random = RandomState::new().build_hasher().finish();
+        Duration::from_millis(random % (cap + 1))
+    }
+}
+
+/// Whether `request` broadcasts a transaction, see [`NEVER_RETRIED`].
+pub fn is_broadcast(request: &RequestPacket) -> bool {
+    request
+        .method_names()
+        .any(|method| NEVER_RETRIED.contains(&method))
+}
+
+/// Whether `error` may go away if the same request is sent again.
+pub fn is_transient(error: &TransportError) -> bool {
+    if RateLimitRetryPolicy::default().should_retry(error) {
+        return true;
+    }
+    match error {
+        RpcError::Transport(TransportErrorKind::Custom(error)) => error
+            .downcast_ref::<reqwest::Error>()
+            .is_some_and(|error| error.is_timeout() || error.is_connect()),
+        _ => false,
+    }
+}
+
+/// Whether the outcome of a request is a transient failure, see [`is_transient`].
+///
+/// Rate limits often come as JSON-RPC errors in an otherwise successful response.
+pub fn is_transient_result(result: &Result<ResponsePacket, TransportError>) -> bool {
+    match result {
+        Ok(response) => response
+            .as_error()
+            .is_some_and(|payload| is_transient(&RpcError::ErrorResp(payload.clone()))),
+        Err(error) => is_transient(error),
+    }
+}
+
+/// Tower layer retrying transient failures of an Alloy transport, see the [module docs](self).
+#[derive(Debug, Clone)]
+pub struct RpcRetryLayer {
+    network: Network,
+    policy: RetryPolicy,
+}
+
+impl RpcRetryLayer {
+    pub fn new(network: Network, policy: RetryPolicy) -> Self {
+        Self { network, policy }
+    }
+}
+
+impl<S> Layer<S> for RpcRetryLayer {
+    type Service = RpcRetryService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RpcRetryService {
+            inner,
+            network: self.network,
+            policy: self.policy,
+        }
+    }
+}
+
+/// Transport service produced by [`RpcRetryLayer`].
+#[derive(Debug, Clone)]
+pub struct RpcRetryService<S> {
+    inner: S,
+    network: Network,
+    policy: RetryPolicy,
+}
+
+impl<S> Service<RequestPacket> for RpcRetryService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>
+        + Clone
+        + Send
+        + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        // The service polled ready is the one to call first; retries use a clone.
+        let mut inner = self.inner.clone();
+        std::mem::swap(&mut inner, &mut self.inner);
+        let network = self.network;
+        let policy = self.policy;
+        let retriable = !is_broadcast(&request);
+        Box::pin(async move {
+            let mut retry = 0;
+            loop {
+                let result = inner.call(request.clone()).await;
+                if !retriable || retry >= policy.max_retries || !is_transient_result(&result) {
+                    return result;
+                }
+                let method = request.method_names().next().unwrap_or_default();
+                tracing::info!(
+                    monotonic_counter.x402.rpc.retries = 1,
+                    network = %network,
+                    method,
+                );
+                tracing::debug!(network = %network, method, retry, "Retrying RPC request");
+                tokio::time::sleep(policy.backoff(retry)).await;
+                retry += 1;
+                std::future::poll_fn(|cx| inner.poll_ready(cx)).await?;
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_maximum() {
+        let policy = RetryPolicy::default();
+        assert_eq!(policy.backoff_cap(0), Duration::from_millis(200));
+        assert_eq!(policy.backoff_cap(2), Duration::from_millis(800));
+        assert_eq!(policy.backoff_cap(10), Duration::from_secs(5));
+        assert_eq!(policy.backoff_cap(u32::MAX), Duration::from_secs(5));
+        for retry in 0..8 {
+            assert!(policy.backoff(retry) <= policy.backoff_cap(retry));
+        }
+    }
+}
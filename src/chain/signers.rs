@@ -0,0 +1,198 @@
+//! Settlement signer pool with zero-downtime rotation.
+//!
+//! A [`SignerPool`] holds three generations of signer addresses:
+//! - _active_ signers, selected round-robin for new transactions,
+//! - _next_ signers, already loaded in the wallet (so they can be funded and monitored) but not used yet,
+//! - _draining_ signers, formerly active ones that only finish their in-flight transactions.
+//!
+//! [`SignerPool::rotate`] promotes the next generation to active and moves the active one to draining.
+//! Draining signers are retired once they have no transaction in flight.
+
+use alloy::primitives::Address;
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+
+use crate::network::Network;
+use crate::types::MixedAddress;
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignerRotationError {
+    #[error("No next signer configured")]
+    NoNextSigner,
+    #[error("Signer rotation is not supported on {0}")]
+    Unsupported(Network),
+}
+
+#[derive(Debug, Default)]
+struct Generations {
+    active: Vec<Address>,
+    next: Vec<Address>,
+    draining: Vec<Address>,
+}
+
+/// Pool of signer addresses a provider sends transactions from.
+#[derive(Debug)]
+pub struct SignerPool {
+    generations: RwLock<Generations>,
+    cursor: AtomicUsize,
+    in_flight: Arc<DashMap<Address, usize>>,
+}
+
+/// Signers by generation, as reported by the admin API.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SignerGenerations {
+    pub active: Vec<MixedAddress>,
+    pub next: Vec<MixedAddress>,
+    pub draining: Vec<MixedAddress>,
+}
+
+impl SignerPool {
+    /// Creates a pool where all `active` signers are used right away.
+    pub fn new(active: Vec<Address>) -> Self {
+        Self {
+            generations: RwLock::new(Generations {
+                active,
+                ..Default::default()
+            }),
+            cursor: AtomicUsize::new(0),
+            in_flight: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Moves `next` out of the active generation into the next one.
+    ///
+    /// Ignored if that would leave no active signer.
+    pub fn set_next(&self, next: Vec<Address>) {
+        let mut generations = self.generations.write().expect("signer pool lock poisoned");
+        let active: Vec<Address> = generations
+            .active
+            .iter()
+            .copied()
+            .filter(|address| !next.contains(address))
+            .collect();
+        if active.is_empty() {
+            tracing::warn!("next signers would leave no active signer, ignoring");
+            return;
+        }
+        generations.active = active;
+        generations.next = next;
+    }
+
+    /// Round-robin selection of the next active signer.
+    pub fn next_signer(&self) -> Address {
+        let generations = self.generations.read().expect("signer pool lock poisoned");
+        debug_assert!(!generations.active.is_empty());
+        if generations.active.len() == 1 {
+            generations.active[0]
+        } else {
+            let next = self.cursor.fetch_add(1, Ordering::Relaxed) % generations.active.len();
+            generations.active[next]
+        }
+    }
+
+    /// Every signer known to the pool, regardless of generation.
+    pub fn all(&self) -> Vec<Address> {
+        let generations = self.generations.read().expect("signer pool lock poisoned");
+        generations
+            .active
+            .iter()
+            .chain(generations.next.iter())
+            .chain(generations.draining.iter())
+            .copied()
+            .collect()
+    }
+
+    /// Marks a transaction from `address` as in flight until the returned guard is dropped.
+    pub fn begin(&self, address: Address) -> InFlight {
+        *self.in_flight.entry(address).or_insert(0) += 1;
+        InFlight {
+            address,
+            in_flight: self.in_flight.clone(),
+        }
+    }
+
+    /// Promotes the next generation to active; the current active signers start draining.
+    pub fn rotate(&self) -> Result<SignerGenerations, SignerRotationError> {
+        let mut generations = self.generations.write().expect("signer pool lock poisoned");
+        if generations.next.is_empty() {
+            return Err(SignerRotationError::NoNextSigner);
+        }
+        let next = std::mem::take(&mut generations.next);
+        let previous = std::mem::replace(&mut generations.active, next);
+        generations.draining.extend(previous);
+        self.retire_drained(&mut generations);
+        tracing::info!(active = ?generations.active, draining = ?generations.draining, "Signers rotated");
+        Ok(Self::report(&generations))
+    }
+
+    /// Current generations, retiring draining signers without in-flight transactions.
+    pub fn generations(&self) -> SignerGenerations {
+        let mut generations = self.generations.write().expect("signer pool lock poisoned");
+        self.retire_drained(&mut generations);
+        Self::report(&generations)
+    }
+
+    fn retire_drained(&self, generations: &mut Generations) {
+        generations.draining.retain(|address| {
+            self.in_flight
+                .get(address)
+                .is_some_and(|in_flight| *in_flight > 0)
+        });
+    }
+
+    fn report(generations: &Generations) -> SignerGenerations {
+        let mixed = |addresses: &[Address]| addresses.iter().map(|a| (*a).into()).collect();
+        SignerGenerations {
+            active: mixed(&generations.active),
+            next: mixed(&generations.next),
+            draining: mixed(&generations.draining),
+        }
+    }
+}
+
+/// Guard tracking an in-flight transaction of a signer; see [`SignerPool::begin`].
+pub struct InFlight {
+    address: Address,
+    in_flight: Arc<DashMap<Address, usize>>,
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if let Some(mut count) = self.in_flight.get_mut(&self.address) {
+            *count = count.saturating_sub(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotation_drains_previous_signers() {
+        let (a, b, c) = (
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            Address::repeat_byte(3),
+        );
+        let pool = SignerPool::new(vec![a, b, c]);
+        pool.set_next(vec![c]);
+        assert_eq!(pool.generations().active, vec![a.into(), b.into()]);
+
+        let in_flight = pool.begin(a);
+        let rotated = pool.rotate().expect("next signer configured");
+        assert_eq!(rotated.active, vec![c.into()]);
+        assert_eq!(rotated.draining, vec![a.into()]);
+        assert_eq!(pool.next_signer(), c);
+
+        drop(in_flight);
+        assert!(pool.generations().draining.is_empty());
+        assert!(matches!(
+            pool.rotate(),
+            Err(SignerRotationError::NoNextSigner)
+        ));
+    }
+}
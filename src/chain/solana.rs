@@ -1,4 +1,8 @@
-use crate::chain::{FacilitatorLocalError, FromEnvByNetworkBuild, NetworkProviderOps};
+use crate::chain::rpc_budget::{RpcBudget, RpcBudgetSender};
+use crate::chain::{
+    FacilitatorLocalError, FromConfigByNetworkBuild, FromEnvByNetworkBuild, NetworkProviderOps,
+};
+use crate::config::NetworkConfig;
 use crate::facilitator::Facilitator;
 use crate::from_env;
 use crate::network::Network;
@@ -9,8 +13,10 @@ use crate::types::{
 };
 use crate::types::{Scheme, X402Version};
 use solana_client::nonblocking::rpc_client::RpcClient;
+use solana_client::rpc_client::RpcClientConfig;
 use solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig};
 use solana_commitment_config::CommitmentConfig;
+use solana_rpc_client::http_sender::HttpSender;
 use solana_sdk::instruction::CompiledInstruction;
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::{Keypair, Signature};
@@ -43,6 +49,15 @@ impl TryFrom<Network> for SolanaChain {
             Network::Polygon => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
             Network::Sei => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
             Network::SeiTestnet => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Arbitrum => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Optimism => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Lightning => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::LightningTestnet => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Near => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::NearTestnet => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Tron => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::TronNile => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
+            Network::Custom(_) => Err(FacilitatorLocalError::UnsupportedNetwork(None)),
         }
     }
 }
@@ -75,6 +90,9 @@ impl TryFrom<MixedAddress> for SolanaAddress {
             MixedAddress::Offchain(_) => Err(FacilitatorLocalError::InvalidAddress(
                 "expected Solana address".to_string(),
             )),
+            MixedAddress::Near(_) | MixedAddress::Tron(_) => Err(
+                FacilitatorLocalError::InvalidAddress("expected Solana address".to_string()),
+            ),
             MixedAddress::Solana(pubkey) => Ok(Self { pubkey }),
         }
     }
@@ -91,6 +109,9 @@ pub struct SolanaProvider {
     keypair: Arc<Keypair>,
     chain: SolanaChain,
     rpc_client: Arc<RpcClient>,
+    rpc_budget: RpcBudget,
+    /// SPL mints accepted as payment assets; any mint if `None`.
+    accepted_assets: Option<Vec<Pubkey>>,
 }
 
 impl Debug for SolanaProvider {
@@ -108,20 +129,46 @@ impl SolanaProvider {
         keypair: Keypair,
         rpc_url: String,
         network: Network,
+        rpc_budget: RpcBudget,
     ) -> Result<Self, FacilitatorLocalError> {
         let chain = SolanaChain::try_from(network)?;
         {
             let signer_addresses = vec![keypair.pubkey()];
             tracing::info!(network=%network, rpc=rpc_url, signers=?signer_addresses, "Initialized provider");
         }
-        let rpc_client = RpcClient::new(rpc_url);
+        let sender = RpcBudgetSender::new(HttpSender::new(rpc_url), rpc_budget.clone());
+        let rpc_client = RpcClient::new_sender(
+            sender,
+            RpcClientConfig::with_commitment(CommitmentConfig::default()),
+        );
         Ok(Self {
             keypair: Arc::new(keypair),
             chain,
             rpc_client: Arc::new(rpc_client),
+            rpc_budget,
+            accepted_assets: None,
         })
     }
 
+    /// Restricts payments to the given SPL mints.
+    pub fn with_accepted_assets(mut self, assets: Vec<Pubkey>) -> Self {
+        self.accepted_assets = Some(assets);
+        self
+    }
+
+    /// Monthly request accounting for the RPC endpoint of this provider.
+    pub fn rpc_budget(&self) -> &RpcBudget {
+        &self.rpc_budget
+    }
+
+    /// Latest slot reported by the RPC node, used as a liveness probe.
+    pub async fn latest_block_number(&self) -> Result<u64, FacilitatorLocalError> {
+        self.rpc_client
+            .get_slot()
+            .await
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))
+    }
+
     pub fn verify_compute_limit_instruction(
         &self,
         transaction: &VersionedTransaction,
@@ -232,85 +279,7 @@ impl SolanaProvider {
         let tx = TransactionInt::new(transaction.clone());
         let instruction = tx.instruction(instruction_index)?;
         instruction.assert_not_empty()?;
-        let program_id = instruction.program_id();
-        let transfer_checked_instruction = if spl_token::ID.eq(&program_id) {
-            let token_instruction =
-                spl_token::instruction::TokenInstruction::unpack(instruction.data_slice())
-                    .map_err(|_| {
-                        FacilitatorLocalError::DecodingError(
-                            "invalid_exact_svm_payload_transaction_instructions".to_string(),
-                        )
-                    })?;
-            let (amount, decimals) = match token_instruction {
-                spl_token::instruction::TokenInstruction::TransferChecked { amount, decimals } => {
-                    (amount, decimals)
-                }
-                _ => {
-                    return Err(FacilitatorLocalError::DecodingError(
-                        "invalid_exact_svm_payload_transaction_instructions".to_string(),
-                    ));
-                }
-            };
-            // Source = 0
-            let source = instruction.account(0)?;
-            // Mint = 1
-            let mint = instruction.account(1)?;
-            // Destination = 2
-            let destination = instruction.account(2)?;
-            // Authority = 3
-            let authority = instruction.account(3)?;
-            TransferCheckedInstruction {
-                amount,
-                decimals,
-                source,
-                mint,
-                destination,
-                authority,
-                token_program: spl_token::ID,
-                data: instruction.data(),
-            }
-        } else if spl_token_2022::ID.eq(&program_id) {
-            let token_instruction =
-                spl_token_2022::instruction::TokenInstruction::unpack(instruction.data_slice())
-                    .map_err(|_| {
-                        FacilitatorLocalError::DecodingError(
-                            "invalid_exact_svm_payload_transaction_instructions".to_string(),
-                        )
-                    })?;
-            let (amount, decimals) = match token_instruction {
-                spl_token_2022::instruction::TokenInstruction::TransferChecked {
-                    amount,
-                    decimals,
-                } => (amount, decimals),
-                _ => {
-                    return Err(FacilitatorLocalError::DecodingError(
-                        "invalid_exact_svm_payload_transaction_instructions".to_string(),
-                    ));
-                }
-            };
-            // Source = 0
-            let source = instruction.account(0)?;
-            // Mint = 1
-            let mint = instruction.account(1)?;
-            // Destination = 2
-            let destination = instruction.account(2)?;
-            // Authority = 3
-            let authority = instruction.account(3)?;
-            TransferCheckedInstruction {
-                amount,
-                decimals,
-                source,
-                mint,
-                destination,
-                authority,
-                token_program: spl_token_2022::ID,
-                data: instruction.data(),
-            }
-        } else {
-            return Err(FacilitatorLocalError::DecodingError(
-                "invalid_exact_svm_payload_transaction_not_a_transfer_instruction".to_string(),
-            ));
-        };
+        let transfer_checked_instruction = instruction.transfer_checked()?;
         let asset_address: SolanaAddress = requirements.asset.clone().try_into()?;
         let pay_to_address: SolanaAddress = requirements.pay_to.clone().try_into()?;
         let token_program = transfer_checked_instruction.token_program;
@@ -366,7 +335,15 @@ impl SolanaProvider {
 
         // Assert valid payment START
         let payment_payload = match &payload.payload {
-            ExactPaymentPayload::Evm(..) => {
+            ExactPaymentPayload::Evm(..)
+            | ExactPaymentPayload::Permit2(..)
+            | ExactPaymentPayload::Permit(..)
+            | ExactPaymentPayload::Native(..)
+            | ExactPaymentPayload::UserOperation(..)
+            | ExactPaymentPayload::Stream(..)
+            | ExactPaymentPayload::Lightning(..)
+            | ExactPaymentPayload::Near(..)
+            | ExactPaymentPayload::Tron(..) => {
                 return Err(FacilitatorLocalError::UnsupportedNetwork(None));
             }
             ExactPaymentPayload::Solana(payload) => payload,
@@ -385,13 +362,21 @@ impl SolanaProvider {
                 requirements.network,
             ));
         }
-        if payload.scheme != requirements.scheme {
+        if payload.scheme != requirements.scheme || requirements.scheme != Scheme::Exact {
             return Err(FacilitatorLocalError::SchemeMismatch(
                 None,
-                requirements.scheme,
+                Scheme::Exact,
                 payload.scheme,
             ));
         }
+        if let Some(accepted) = &self.accepted_assets {
+            let asset: SolanaAddress = requirements.asset.clone().try_into()?;
+            if !accepted.contains(&asset.pubkey) {
+                return Err(FacilitatorLocalError::UnsupportedAsset(
+                    requirements.asset.clone(),
+                ));
+            }
+        }
         let transaction_b64_string = payment_payload.transaction.clone();
         let bytes = Base64Bytes::from(transaction_b64_string.as_bytes())
             .decode()
@@ -456,9 +441,36 @@ impl FromEnvByNetworkBuild for SolanaProvider {
                 return Ok(None);
             }
         };
-        let keypair = from_env::SignerType::from_env()?.make_solana_wallet()?;
-        let provider = SolanaProvider::try_new(keypair, rpc_url, network)?;
-        Ok(Some(provider))
+        let mut config = NetworkConfig::from_rpc_url(rpc_url);
+        config.tokens = from_env::tokens_from_env(network)?;
+        Ok(Some(Self::from_config(network, &config).await?))
+    }
+}
+
+impl FromConfigByNetworkBuild for SolanaProvider {
+    async fn from_config(
+        network: Network,
+        config: &NetworkConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let keypair = match config.signer_keys.as_deref() {
+            Some([key]) => Keypair::from_base58_string(key),
+            Some(_) => return Err(format!("{network}: expected exactly one signer key").into()),
+            None => from_env::SignerType::from_env()?.make_solana_wallet()?,
+        };
+        let rpc_budget = RpcBudget::from_env(network);
+        let mut provider =
+            SolanaProvider::try_new(keypair, config.rpc_url.clone(), network, rpc_budget)?;
+        if let Some(tokens) = &config.tokens {
+            let tokens = tokens
+                .iter()
+                .map(|token| match token {
+                    MixedAddress::Solana(pubkey) => Ok(*pubkey),
+                    other => Err(format!("{network}: invalid SPL mint: {other}")),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            provider = provider.with_accepted_assets(tokens);
+        }
+        Ok(provider)
     }
 }
 
@@ -506,9 +518,17 @@ impl Facilitator for SolanaProvider {
             return Ok(SettleResponse {
                 success: false,
                 error_reason: Some(FacilitatorErrorReason::UnexpectedSettleError),
-                payer: verification.payer.into(),
+                payer: Some(verification.payer.into()),
                 transaction: None,
                 network: self.network(),
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: None,
+                settlement_id: None,
+                simulated: false,
             });
         }
         let tx_sig = tx
@@ -517,23 +537,46 @@ impl Facilitator for SolanaProvider {
         let settle_response = SettleResponse {
             success: true,
             error_reason: None,
-            payer: verification.payer.into(),
+            payer: Some(verification.payer.into()),
             transaction: Some(TransactionHash::Solana(*tx_sig.as_array())),
             network: self.network(),
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: false,
         };
         Ok(settle_response)
     }
 
     async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
-        let kinds = vec![SupportedPaymentKind {
+        let kind = |asset: Option<MixedAddress>| SupportedPaymentKind {
             network: self.network().to_string(),
             scheme: Scheme::Exact,
             x402_version: X402Version::V1,
             extra: Some(SupportedPaymentKindExtra {
                 fee_payer: self.signer_address(),
+                spender: None,
+                asset,
+                signers: Vec::new(),
             }),
-        }];
-        Ok(SupportedPaymentKindsResponse { kinds })
+        };
+        // Configured mints: one `exact` kind per mint, so clients can tell which ones are accepted.
+        let kinds = match self.accepted_assets.as_deref() {
+            Some(mints) if !mints.is_empty() => mints
+                .iter()
+                .map(|mint| kind(Some(MixedAddress::Solana(*mint))))
+                .collect(),
+            _ => vec![kind(None)],
+        };
+        Ok(SupportedPaymentKindsResponse {
+            kinds,
+            routes: Vec::new(),
+            maintenance: Vec::new(),
+        })
     }
 }
 
@@ -587,6 +630,92 @@ impl InstructionInt {
             ))?;
         Ok(pubkey)
     }
+
+    /// Decodes a `TransferChecked` instruction of the SPL Token or Token-2022 program.
+    pub fn transfer_checked(&self) -> Result<TransferCheckedInstruction, FacilitatorLocalError> {
+        let program_id = self.program_id();
+        let transfer_checked_instruction = if spl_token::ID.eq(&program_id) {
+            let token_instruction = spl_token::instruction::TokenInstruction::unpack(
+                self.data_slice(),
+            )
+            .map_err(|_| {
+                FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_instructions".to_string(),
+                )
+            })?;
+            let (amount, decimals) = match token_instruction {
+                spl_token::instruction::TokenInstruction::TransferChecked { amount, decimals } => {
+                    (amount, decimals)
+                }
+                _ => {
+                    return Err(FacilitatorLocalError::DecodingError(
+                        "invalid_exact_svm_payload_transaction_instructions".to_string(),
+                    ));
+                }
+            };
+            // Source = 0
+            let source = self.account(0)?;
+            // Mint = 1
+            let mint = self.account(1)?;
+            // Destination = 2
+            let destination = self.account(2)?;
+            // Authority = 3
+            let authority = self.account(3)?;
+            TransferCheckedInstruction {
+                amount,
+                decimals,
+                source,
+                mint,
+                destination,
+                authority,
+                token_program: spl_token::ID,
+                data: self.data(),
+            }
+        } else if spl_token_2022::ID.eq(&program_id) {
+            let token_instruction = spl_token_2022::instruction::TokenInstruction::unpack(
+                self.data_slice(),
+            )
+            .map_err(|_| {
+                FacilitatorLocalError::DecodingError(
+                    "invalid_exact_svm_payload_transaction_instructions".to_string(),
+                )
+            })?;
+            let (amount, decimals) = match token_instruction {
+                spl_token_2022::instruction::TokenInstruction::TransferChecked {
+                    amount,
+                    decimals,
+                } => (amount, decimals),
+                _ => {
+                    return Err(FacilitatorLocalError::DecodingError(
+                        "invalid_exact_svm_payload_transaction_instructions".to_string(),
+                    ));
+                }
+            };
+            // Source = 0
+            let source = self.account(0)?;
+            // Mint = 1
+            let mint = self.account(1)?;
+            // Destination = 2
+            let destination = self.account(2)?;
+            // Authority = 3
+            let authority = self.account(3)?;
+            TransferCheckedInstruction {
+                amount,
+                decimals,
+                source,
+                mint,
+                destination,
+                authority,
+                token_program: spl_token_2022::ID,
+                data: self.data(),
+            }
+        } else {
+            return Err(FacilitatorLocalError::DecodingError(
+                "invalid_exact_svm_payload_transaction_not_a_transfer_instruction".to_string(),
+            ));
+        };
+        Ok(transfer_checked_instruction)
+    }
 }
 
 pub struct TransactionInt {
@@ -695,3 +824,120 @@ impl TransactionInt {
         Ok(string)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::compute_budget::ComputeBudgetInstruction;
+    use solana_sdk::hash::Hash;
+    use solana_sdk::message::{VersionedMessage, v0};
+
+    /// An `exact` SVM payload laid out as the TypeScript client builds it: a v0 message paid by the facilitator,
+    /// with compute unit limit, compute unit price and `TransferChecked` instructions, signed by the payer only.
+    /// Keys are derived from fixed seeds, so the bytes are reproducible.
+    const FIXTURE: &str = "AgAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAACE2WXSdju8/I4pswy7pfhJxUXQ1we0kV5IBWht1R/clPISX9UmoxSf8oDBmU2+DlRxOhSaZEv0afaPm3h9SoPgAIBAweKiOPddAnxlf1S2y08ul1yymcJvx2UEhvzdIgBtA9vXIE5dw6ofRdfVqNUZsNMfszLjYqRtO43ol32D1uPybOUu1D+1r3nZR9wUFUKD1nHds7A+0/8jeFbikVKj9nIKO/YZzX+SBU157cpkGjfMb0J+sTOsxxRqc84CuX3b/3dJAMGRm/lIRcy/+ytunLDm+e8jOW7xfcSayxDmzpAAAAABAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQEBAQG3fbh12Whk9nL4UbO63msHLSF7V9bN5E6jPWFfv8AqQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFAwQABQJYGwAABAAJAwEAAAAAAAAABgQDBQIBCgwQJwAAAAAAAAYA";
+
+    fn keypair(seed: u8) -> Keypair {
+        Keypair::new_from_array([seed; 32])
+    }
+
+    fn ata(owner: &Pubkey, mint: &Pubkey) -> Pubkey {
+        let program_id = Pubkey::from_str("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL").unwrap();
+        Pubkey::find_program_address(
+            &[owner.as_ref(), spl_token::ID.as_ref(), mint.as_ref()],
+            &program_id,
+        )
+        .0
+    }
+
+    fn fixture_transaction() -> VersionedTransaction {
+        let (fee_payer, payer, pay_to) = (keypair(1), keypair(2), keypair(3).pubkey());
+        let mint = Pubkey::new_from_array([4; 32]);
+        let instructions = [
+            ComputeBudgetInstruction::set_compute_unit_limit(7_000),
+            ComputeBudgetInstruction::set_compute_unit_price(1),
+            spl_token::instruction::transfer_checked(
+                &spl_token::ID,
+                &ata(&payer.pubkey(), &mint),
+                &mint,
+                &ata(&pay_to, &mint),
+                &payer.pubkey(),
+                &[],
+                10_000,
+                6,
+            )
+            .unwrap(),
+        ];
+        let message = v0::Message::try_compile(
+            &fee_payer.pubkey(),
+            &instructions,
+            &[],
+            Hash::new_from_array([5; 32]),
+        )
+        .unwrap();
+        let message = VersionedMessage::V0(message);
+        let signature = payer.sign_message(&message.serialize());
+        VersionedTransaction {
+            signatures: vec![Signature::default(), signature],
+            message,
+        }
+    }
+
+    fn provider() -> SolanaProvider {
+        let network = Network::SolanaDevnet;
+        SolanaProvider::try_new(
+            keypair(1),
+            "http://127.0.0.1:1".to_string(),
+            network,
+            RpcBudget::new(network, None),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn exact_svm_fixture_is_byte_compatible() {
+        let bytes = Base64Bytes::from(FIXTURE.as_bytes()).decode().unwrap();
+        let transaction = bincode::deserialize::<VersionedTransaction>(&bytes).unwrap();
+        assert_eq!(transaction, fixture_transaction());
+        let tx = TransactionInt::new(transaction.clone());
+        assert_eq!(tx.as_base64().unwrap(), FIXTURE);
+
+        let provider = provider();
+        provider
+            .verify_compute_limit_instruction(&transaction, 0)
+            .unwrap();
+        provider
+            .verify_compute_price_instruction(&transaction, 1)
+            .unwrap();
+        let transfer = tx.instruction(2).unwrap().transfer_checked().unwrap();
+        assert_eq!(transfer.authority, keypair(2).pubkey());
+        assert_eq!((transfer.amount, transfer.decimals), (10_000, 6));
+
+        // The payer signs the serialized message; the fee payer slot is left for the facilitator.
+        let message = transaction.message.serialize();
+        assert!(transaction.signatures[1].verify(keypair(2).pubkey().as_ref(), &message));
+        assert!(!tx.is_fully_signed());
+        let signed = tx.sign(&keypair(1)).unwrap();
+        assert!(signed.is_fully_signed());
+        assert!(signed.inner.verify_with_results().iter().all(|ok| *ok));
+    }
+
+    #[tokio::test]
+    async fn supported_lists_fee_payer_per_mint() {
+        let mints = vec![
+            Pubkey::new_from_array([4; 32]),
+            Pubkey::new_from_array([6; 32]),
+        ];
+        let supported = provider()
+            .with_accepted_assets(mints.clone())
+            .supported()
+            .await
+            .unwrap();
+        assert_eq!(supported.kinds.len(), 2);
+        for (kind, mint) in supported.kinds.iter().zip(mints) {
+            let extra = kind.extra.as_ref().unwrap();
+            assert_eq!(extra.fee_payer, MixedAddress::Solana(keypair(1).pubkey()));
+            assert_eq!(extra.asset, Some(MixedAddress::Solana(mint)));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+//! Replacement of settlement transactions stuck in the mempool.
+//!
+//! A settlement sent with a fee the network then outbids can stay pending for a long time, holding both the
+//! client's request and the signer's next nonce. With `SPEED_UP_AFTER_SECS` set, a background monitor goes
+//! through the settlements in flight on every EVM network, and rebroadcasts those pending for longer with the
+//! same nonce, gas limit and calldata, and fees raised by `SPEED_UP_BUMP_PERCENT` (or to the current estimate
+//! of the node, if higher). Nodes only accept a replacement paying at least 10% more than the transaction it
+//! replaces.
+//!
+//! The settlement waits for whichever of the original transaction and its replacements is mined, and reports
+//! its hash. The transaction journal (see [`crate::chain::recovery`]) follows the latest replacement.
+//!
+//! Environment variables used:
+//! - `SPEED_UP_AFTER_SECS` — seconds a settlement may stay pending before it is replaced. Disabled if unset.
+//! - `SPEED_UP_BUMP_PERCENT` — fee increase of each replacement, in percent (default: `12`).
+//! - `SPEED_UP_MAX_BUMPS` — replacements sent for one settlement at most (default: `5`).
+
+use alloy::primitives::{Address, B256};
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::chain::NetworkProvider;
+use crate::from_env;
+use crate::provider_cache::{ProviderCache, ProviderMap};
+
+/// Default fee increase of a replacement, in percent.
+const DEFAULT_BUMP_PERCENT: u64 = 12;
+/// Default number of replacements of one settlement.
+const DEFAULT_MAX_BUMPS: usize = 5;
+
+/// When and how much to raise the fees of a pending settlement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedUp {
+    /// Time a transaction may stay pending before it is replaced.
+    pub after: Duration,
+    /// Fee increase of each replacement, in percent.
+    pub bump_percent: u64,
+    /// Replacements sent for one settlement at most.
+    pub max_bumps: usize,
+}
+
+impl SpeedUp {
+    /// Reads `SPEED_UP_*`; `None` if `SPEED_UP_AFTER_SECS` is unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        fn parse<T: std::str::FromStr>(name: &str) -> Result<Option<T>, String>
+        where
+            T::Err: std::fmt::Display,
+        {
+            std::env::var(name)
+                .ok()
+                .map(|value| value.trim().parse().map_err(|e| format!("{name}: {e}")))
+                .transpose()
+        }
+        let Some(after_secs) = parse::<u64>(from_env::ENV_SPEED_UP_AFTER_SECS)? else {
+            return Ok(None);
+        };
+        let bump_percent =
+            parse(from_env::ENV_SPEED_UP_BUMP_PERCENT)?.unwrap_or(DEFAULT_BUMP_PERCENT);
+        if bump_percent < 10 {
+            return Err(format!(
+                "{}: nodes reject replacements raising fees by less than 10%",
+                from_env::ENV_SPEED_UP_BUMP_PERCENT
+            ));
+        }
+        Ok(Some(Self {
+            after: Duration::from_secs(after_secs.max(1)),
+            bump_percent,
+            max_bumps: parse(from_env::ENV_SPEED_UP_MAX_BUMPS)?.unwrap_or(DEFAULT_MAX_BUMPS),
+        }))
+    }
+
+    /// Fee of a replacement for a transaction paying `fee`, at least `estimate`.
+    pub fn bump(&self, fee: u128, estimate: u128) -> u128 {
+        let bumped =
+            fee.saturating_add(fee.saturating_mul(self.bump_percent as u128).div_ceil(100));
+        bumped.max(fee + 1).max(estimate)
+    }
+}
+
+/// A broadcast settlement, with the replacements sent for it.
+#[derive(Debug, Clone)]
+pub struct PendingSettlement {
+    /// Sender of the transaction.
+    pub from: Address,
+    /// Hashes of the original transaction and its replacements, oldest first.
+    pub hashes: Vec<B256>,
+    /// When the latest of them was broadcast.
+    pub sent_at: Instant,
+}
+
+/// Settlements of a network awaiting their receipt, by hash of the original transaction.
+#[derive(Debug, Clone, Default)]
+pub struct PendingSettlements(Arc<DashMap<B256, PendingSettlement>>);
+
+impl PendingSettlements {
+    /// Tracks the transaction `hash` sent by `from` until the returned guard is dropped.
+    pub fn track(&self, hash: B256, from: Address) -> Tracked {
+        self.0.insert(
+            hash,
+            PendingSettlement {
+                from,
+                hashes: vec![hash],
+                sent_at: Instant::now(),
+            },
+        );
+        Tracked {
+            hash,
+            pending: self.clone(),
+        }
+    }
+
+    /// Hashes of the settlement first sent as `original`, oldest first.
+    pub fn hashes(&self, original: B256) -> Vec<B256> {
+        self.0
+            .get(&original)
+            .map(|pending| pending.hashes.clone())
+            .unwrap_or_else(|| vec![original])
+    }
+
+    /// Settlements whose latest transaction was broadcast more than `after` ago.
+    pub fn stale(&self, after: Duration) -> Vec<(B256, PendingSettlement)> {
+        self.0
+            .iter()
+            .filter(|entry| entry.sent_at.elapsed() >= after)
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect()
+    }
+
+    /// Records `replacement` as the latest transaction of the settlement first sent as `original`.
+    pub fn replaced(&self, original: B256, replacement: B256) {
+        if let Some(mut pending) = self.0.get_mut(&original) {
+            pending.hashes.push(replacement);
+            pending.sent_at = Instant::now();
+        }
+    }
+}
+
+/// Guard of a settlement tracked by [`PendingSettlements::track`].
+#[derive(Debug)]
+pub struct Tracked {
+    hash: B256,
+    pending: PendingSettlements,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        self.pending.0.remove(&self.hash);
+    }
+}
+
+/// Replaces stuck settlements on every EVM network until cancelled, see the [module docs](self).
+pub fn spawn(
+    providers: Arc<ProviderCache>,
+    speed_up: SpeedUp,
+    cancellation_token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval((speed_up.after / 4).max(Duration::from_secs(1)));
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                _ = ticker.tick() => {
+                    for provider in providers.values() {
+                        if let NetworkProvider::Evm(provider) = provider {
+                            provider.replace_stuck().await;
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bump_raises_fee_by_percent_or_to_estimate() {
+        let speed_up = SpeedUp {
+            after: Duration::from_secs(30),
+            bump_percent: 12,
+            max_bumps: 5,
+        };
+        assert_eq!(speed_up.bump(1_000, 0), 1_120);
+        assert_eq!(speed_up.bump(1_000, 2_000), 2_000);
+        assert_eq!(speed_up.bump(1, 0), 2);
+        assert_eq!(speed_up.bump(0, 0), 1);
+    }
+
+    #[test]
+    fn pending_settlements_follow_replacements() {
+        let pending = PendingSettlements::default();
+        let original = B256::repeat_byte(1);
+        let replacement = B256::repeat_byte(2);
+        let tracked = pending.track(original, Address::repeat_byte(3));
+        assert!(pending.stale(Duration::from_secs(60)).is_empty());
+        assert_eq!(pending.stale(Duration::ZERO).len(), 1);
+        pending.replaced(original, replacement);
+        assert_eq!(pending.hashes(original), vec![original, replacement]);
+        drop(tracked);
+        assert!(pending.stale(Duration::ZERO).is_empty());
+        assert_eq!(pending.hashes(original), vec![original]);
+    }
+}
@@ -0,0 +1,240 @@
+//! x402 `stream` scheme: pay-per-second access through a Superfluid money stream.
+//!
+//! The payer opens a constant flow of a Super Token to `payTo` once, with Superfluid's constant flow
+//! agreement. Each request then carries a short-lived claim of that stream signed by the payer, and the
+//! facilitator checks the stream instead of moving funds: the money is already flowing.
+//!
+//! - **Verify**: check network, scheme, that the Super Token is the required asset, that the claim is
+//!   signed by the stream sender and not expired, that the flow rate from the sender to `payTo` is at
+//!   least `maxAmountRequired` per second, and that the sender's available balance keeps the stream
+//!   solvent for `maxTimeoutSeconds`.
+//! - **Settle**: re-run the checks; no transaction is sent.
+//!
+//! The claim is an EIP-191 message (see [`claim_message`]) binding the stream to the paid resource.
+//! Only EOA senders can sign it.
+
+use alloy::primitives::{Address, I256, Signature, U256, address};
+use alloy::providers::Provider;
+use alloy::sol;
+use tracing::{Instrument, instrument};
+
+use crate::chain::FacilitatorLocalError;
+use crate::chain::evm::EvmChain;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    EvmAddress, ExactPaymentPayload, MixedAddress, PaymentPayload, PaymentRequirements, Scheme,
+};
+
+/// Superfluid `CFAv1Forwarder`, at the same address on every network Superfluid is deployed to.
+pub const CFA_FORWARDER_ADDRESS: Address = address!("0xcfA132E353cB4E398080B9700609bb008eceB125");
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface ICFAv1Forwarder {
+        function getFlowrate(address token, address sender, address receiver) external view returns (int96 flowrate);
+    }
+}
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface ISuperToken {
+        function realtimeBalanceOfNow(address account) external view returns (int256 availableBalance, uint256 deposit, uint256 owedDeposit, uint256 timestamp);
+    }
+}
+
+/// Whether Superfluid, with the [`CFA_FORWARDER_ADDRESS`], is deployed on `network`.
+pub fn is_deployed(network: Network) -> bool {
+    matches!(
+        network,
+        Network::Base
+            | Network::BaseSepolia
+            | Network::Avalanche
+            | Network::AvalancheFuji
+            | Network::Polygon
+            | Network::Arbitrum
+            | Network::Optimism
+    )
+}
+
+/// A stream checked to pay for the requested resource.
+#[derive(Debug, Clone)]
+pub struct StreamPayment {
+    /// Payer sending the stream.
+    pub sender: Address,
+    /// Current flow rate to `payTo`, in the token's smallest unit per second.
+    pub flow_rate: U256,
+}
+
+/// Text the stream sender signs with EIP-191 `personal_sign` to claim the stream for a request.
+pub fn claim_message(
+    network: Network,
+    token: Address,
+    receiver: Address,
+    resource: &str,
+    valid_before: UnixTimestamp,
+) -> String {
+    format!(
+        "x402 stream\nnetwork: {network}\ntoken: {token}\nreceiver: {receiver}\nresource: {resource}\nvalidBefore: {valid_before}"
+    )
+}
+
+/// Runs all preconditions of a stream payment:
+/// - Stream payload, matching scheme and network.
+/// - Claim signed by the sender for this token, receiver and resource, not expired.
+/// - Flow rate from the sender to `payTo` covers `maxAmountRequired` per second.
+/// - Sender's available balance sustains the flow for `maxTimeoutSeconds`.
+#[instrument(skip_all, err)]
+pub async fn assert_valid_payment<P: Provider>(
+    provider: &P,
+    chain: &EvmChain,
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<StreamPayment, FacilitatorLocalError> {
+    let stream_payload = match &payload.payload {
+        ExactPaymentPayload::Stream(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                None,
+                Scheme::Stream,
+                payload.scheme,
+            ));
+        }
+    };
+    let sender: Address = stream_payload.sender.into();
+    let payer: MixedAddress = stream_payload.sender.into();
+    for network in [payload.network, requirements.network] {
+        if network != chain.network() {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                Some(payer),
+                chain.network(),
+                network,
+            ));
+        }
+    }
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != Scheme::Stream {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                Some(payer),
+                Scheme::Stream,
+                scheme,
+            ));
+        }
+    }
+    if !is_deployed(chain.network()) {
+        return Err(FacilitatorLocalError::UnsupportedNetwork(Some(payer)));
+    }
+    let token: EvmAddress = requirements
+        .asset
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let pay_to: EvmAddress = requirements
+        .pay_to
+        .clone()
+        .try_into()
+        .map_err(|e| FacilitatorLocalError::InvalidAddress(format!("{e:?}")))?;
+    let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+    if stream_payload.valid_before < now + 6 {
+        return Err(FacilitatorLocalError::InvalidTiming(
+            payer,
+            format!(
+                "Expired: now {} > validBefore {}",
+                now + 6,
+                stream_payload.valid_before
+            ),
+        ));
+    }
+    let message = claim_message(
+        chain.network(),
+        token.0,
+        pay_to.0,
+        requirements.resource.as_str(),
+        stream_payload.valid_before,
+    );
+    let recovered = Signature::try_from(stream_payload.signature.0.as_slice())
+        .ok()
+        .and_then(|signature| signature.recover_address_from_msg(&message).ok());
+    if recovered != Some(sender) {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            "Stream claim is not signed by the sender".to_string(),
+        ));
+    }
+
+    let flow_rate = ICFAv1Forwarder::new(CFA_FORWARDER_ADDRESS, provider)
+        .getFlowrate(token.0, sender, pay_to.0)
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_flow_rate",
+            token_contract = %token,
+            sender = %sender,
+            receiver = %pay_to,
+            otel.kind = "client",
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    let flow_rate = i128::try_from(flow_rate)
+        .ok()
+        .and_then(|rate| u128::try_from(rate).ok())
+        .map(U256::from)
+        .unwrap_or_default();
+    if flow_rate < requirements.max_amount_required.0 || flow_rate.is_zero() {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+    let balance = ISuperToken::new(token.0, provider)
+        .realtimeBalanceOfNow(sender)
+        .call()
+        .into_future()
+        .instrument(tracing::info_span!("fetch_realtime_balance",
+            token_contract = %token,
+            sender = %sender,
+            otel.kind = "client",
+        ))
+        .await
+        .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+    let runway = flow_rate.saturating_mul(U256::from(requirements.max_timeout_seconds));
+    let available = balance.availableBalance;
+    if available <= I256::ZERO || available.into_raw() < runway {
+        return Err(FacilitatorLocalError::InsufficientFunds(payer));
+    }
+
+    Ok(StreamPayment { sender, flow_rate })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::signers::SignerSync;
+    use alloy::signers::local::PrivateKeySigner;
+
+    #[test]
+    fn claim_recovers_to_its_signer() {
+        let signer = PrivateKeySigner::random();
+        let message = claim_message(
+            Network::BaseSepolia,
+            Address::repeat_byte(1),
+            Address::repeat_byte(2),
+            "https://example.com/live",
+            UnixTimestamp(1_800_000_000),
+        );
+        assert!(message.contains("resource: https://example.com/live"));
+        let signature = signer.sign_message_sync(message.as_bytes()).unwrap();
+        assert_eq!(
+            signature.recover_address_from_msg(&message).ok(),
+            Some(signer.address())
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! On-chain sanity checks of the tokens accepted on EVM networks.
+//!
+//! A token address copied from the wrong network, or a token that does not implement ERC-3009, makes every
+//! payment in it fail at `/verify` with an opaque contract call error. At startup, and then periodically, every
+//! EVM network probes the tokens it accepts (its `tokens` if configured, the known USDC deployments otherwise)
+//! with `eth_call`s:
+//! - a contract is deployed at the address;
+//! - `symbol()` and `decimals()` answer, and `decimals()` matches the known deployment, if any;
+//! - `authorizationState(address,bytes32)` of ERC-3009 answers.
+//!
+//! A token failing any of them is quarantined: it is no longer advertised by `/supported`, payments in it are
+//! rejected as unsupported, and the failure is logged. It is released once it passes the probes again, e.g. after
+//! an upgrade of the token. Probes failing for network reasons leave the token as it was.
+//!
+//! Environment variables used:
+//! - `TOKEN_PROBE_INTERVAL_SECS` — seconds between probes after the one at startup (default: `3600`, `0` probes
+//!   at startup only).
+
+use alloy::primitives::{Address, B256};
+use alloy::providers::Provider;
+use alloy::sol;
+use dashmap::DashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+
+use crate::chain::NetworkProvider;
+use crate::from_env;
+use crate::provider_cache::{ProviderCache, ProviderMap};
+
+/// Default interval between probes, in seconds.
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface ITokenProbe {
+        function symbol() external view returns (string);
+        function decimals() external view returns (uint8);
+        function authorizationState(address authorizer, bytes32 nonce) external view returns (bool);
+    }
+}
+
+/// Outcome of probing a token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Probe {
+    /// The token answered all probes; its symbol.
+    Passed(String),
+    /// The token is misconfigured, with the reason.
+    Failed(String),
+    /// The probes could not be made, e.g. the RPC is unreachable.
+    Inconclusive(String),
+}
+
+/// Reads the interval from `TOKEN_PROBE_INTERVAL_SECS`; `None` to probe at startup only.
+pub fn interval_from_env() -> Result<Option<Duration>, String> {
+    let secs = match std::env::var(from_env::ENV_TOKEN_PROBE_INTERVAL_SECS) {
+        Ok(value) => value
+            .trim()
+            .parse::<u64>()
+            .map_err(|e| format!("{}: {e}", from_env::ENV_TOKEN_PROBE_INTERVAL_SECS))?,
+        Err(_) => DEFAULT_INTERVAL_SECS,
+    };
+    Ok((secs > 0).then(|| Duration::from_secs(secs)))
+}
+
+/// Probes the token at `asset`, expected to have `decimals` if known.
+pub async fn probe<P: Provider>(provider: &P, asset: Address, decimals: Option<u8>) -> Probe {
+    match provider.get_code_at(asset).await {
+        Ok(code) if code.is_empty() => return Probe::Failed("no contract deployed".to_string()),
+        Ok(_) => {}
+        Err(e) => return Probe::Inconclusive(format!("eth_getCode: {e}")),
+    }
+    let token = ITokenProbe::new(asset, provider);
+    let symbol = match token.symbol().call().await {
+        Ok(symbol) => symbol,
+        Err(e) => return call_failed("symbol()", e),
+    };
+    match token.decimals().call().await {
+        Ok(actual) => {
+            if let Some(expected) = decimals.filter(|expected| *expected != actual) {
+                return Probe::Failed(format!("decimals() is {actual}, expected {expected}"));
+            }
+        }
+        Err(e) => return call_failed("decimals()", e),
+    }
+    if let Err(e) = token
+        .authorizationState(Address::ZERO, B256::ZERO)
+        .call()
+        .await
+    {
+        return call_failed("authorizationState(address,bytes32)", e);
+    }
+    Probe::Passed(symbol)
+}
+
+/// A call reverting or returning garbage fails the probe; a transport error is inconclusive.
+fn call_failed(call: &str, e: alloy::contract::Error) -> Probe {
+    match &e {
+        alloy::contract::Error::TransportError(error) if error.as_error_resp().is_none() => {
+            Probe::Inconclusive(format!("{call}: {e}"))
+        }
+        _ => Probe::Failed(format!("{call} failed: {e}")),
+    }
+}
+
+/// Tokens of a network that failed their probes, with the reason.
+#[derive(Debug, Clone, Default)]
+pub struct Quarantine(Arc<DashMap<Address, String>>);
+
+impl Quarantine {
+    /// Why `asset` is quarantined; `None` if it is not.
+    pub fn reason(&self, asset: &Address) -> Option<String> {
+        self.0.get(asset).map(|reason| reason.clone())
+    }
+
+    /// Records the outcome of a probe of `asset`; `true` if it changed whether `asset` is quarantined.
+    pub fn record(&self, asset: Address, probe: &Probe) -> bool {
+        match probe {
+            Probe::Passed(_) => self.0.remove(&asset).is_some(),
+            Probe::Failed(reason) => self.0.insert(asset, reason.clone()).is_none(),
+            Probe::Inconclusive(_) => false,
+        }
+    }
+}
+
+/// Probes the tokens of every EVM network now, then every `interval` until cancelled.
+pub fn spawn(
+    providers: Arc<ProviderCache>,
+    interval: Option<Duration>,
+    cancellation_token: CancellationToken,
+) -> tokio::task::JoinHandle<()> {
+    tokio::spawn(async move {
+        let probe_all = async || {
+            for provider in providers.values() {
+                if let NetworkProvider::Evm(provider) = provider {
+                    let budget = provider.rpc_budget();
+                    if budget.should_throttle() {
+                        tracing::debug!(network = %budget.network(), "RPC quota nearly used, skipping token probes");
+                        continue;
+                    }
+                    provider.probe_tokens().await;
+                }
+            }
+        };
+        let Some(interval) = interval else {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => {}
+                _ = probe_all() => {}
+            }
+            return;
+        };
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            tokio::select! {
+                _ = cancellation_token.cancelled() => break,
+                _ = ticker.tick() => probe_all().await,
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quarantine_follows_conclusive_probes() {
+        let quarantine = Quarantine::default();
+        let asset = Address::repeat_byte(1);
+        assert!(quarantine.record(asset, &Probe::Failed("no contract deployed".into())));
+        assert!(!quarantine.record(asset, &Probe::Failed("no contract deployed".into())));
+        assert!(!quarantine.record(asset, &Probe::Inconclusive("timeout".into())));
+        assert_eq!(
+            quarantine.reason(&asset).as_deref(),
+            Some("no contract deployed")
+        );
+        assert!(quarantine.record(asset, &Probe::Passed("USDC".into())));
+        assert_eq!(quarantine.reason(&asset), None);
+    }
+}
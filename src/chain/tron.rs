@@ -0,0 +1,725 @@
+//! x402 `exact` payments of TRC-20 tokens on Tron, such as USDT, with the `tron` feature only.
+//!
+//! TRC-20 USDT has no `transferWithAuthorization`. The payer instead approves the facilitator's Tron
+//! account (the `spender` advertised by `/supported`) for the token once, then signs a TIP-712
+//! `TransferWithAuthorization` per payment, with the fields of ERC-3009's, under the domain
+//! `{name: "x402 Tron", version: "1", chainId, verifyingContract: <token>}`.
+//!
+//! - **Verify**: check network, scheme and asset, that `to` is `payTo` and `value` at least
+//!   `maxAmountRequired`, the validity window, that the signature recovers to `from`, that the nonce
+//!   was not settled before, and that the payer's balance and allowance to the facilitator cover `value`.
+//! - **Settle**: re-run the checks, call `transferFrom(from, to, value)` through the TronGrid HTTP API
+//!   and wait for the transaction receipt.
+//!
+//! The token does not record the nonces, so claimed and settled ones are remembered until `validBefore` in the
+//! `tron` directory of `REPLAY_STORE_DIR`, which survives restarts; Tron networks can not start without it.
+//!
+//! Environment variables used:
+//! - `RPC_URL_TRON`, `RPC_URL_TRON_NILE` — TronGrid or full node HTTP API, e.g. `https://api.trongrid.io`, subject
+//!   to the outbound policy.
+//! - `TRON_PRIVATE_KEY` — hex-encoded secp256k1 key of the facilitator account, paying the energy.
+//! - `TRON_API_KEY` — TronGrid API key (optional).
+//! - `TOKENS_<NETWORK>` — comma-separated TRC-20 contracts accepted (optional).
+//! - `REPLAY_STORE_DIR` — directory of the persistent nonce store (required).
+
+use alloy::hex;
+use alloy::primitives::{Address, B256, FixedBytes, Signature, U256, keccak256};
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use alloy::sol;
+use alloy::sol_types::{SolCall, SolStruct, eip712_domain};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use serde_json::{Value, json};
+use sha2::{Digest, Sha256};
+use std::fmt::{Debug, Formatter};
+use std::str::FromStr;
+use std::time::Duration;
+use url::Url;
+
+use crate::chain::revert::Revert;
+use crate::chain::rpc_budget::RpcBudget;
+use crate::chain::{
+    FacilitatorLocalError, FromConfigByNetworkBuild, FromEnvByNetworkBuild, NetworkProviderOps,
+};
+use crate::config::NetworkConfig;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::outbound::OutboundPolicy;
+use crate::replay::{NonceRecord, NonceState, SeenNonces};
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ExactPaymentPayload, FacilitatorErrorReason, MixedAddress, PaymentPayload, PaymentRequirements,
+    Scheme, SettleRequest, SettleResponse, SupportedPaymentKind, SupportedPaymentKindExtra,
+    SupportedPaymentKindsResponse, TransactionHash, TransferWithAuthorization, TronAddress,
+    TronAuthorization, VerifyRequest, VerifyResponse, X402Version,
+};
+
+/// Most TRX, in sun, a settlement may burn for energy.
+const FEE_LIMIT_SUN: u64 = 100_000_000;
+/// How long to wait for a settlement receipt.
+const RECEIPT_TIMEOUT: Duration = Duration::from_secs(60);
+/// Tron block time, between receipt polls.
+const RECEIPT_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    interface ITRC20 {
+        function balanceOf(address account) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+        function transferFrom(address from, address to, uint256 value) external returns (bool);
+    }
+}
+
+/// TIP-712 chain ID: the last four bytes of the network's genesis block hash.
+pub fn chain_id(network: Network) -> Option<u64> {
+    match network {
+        Network::Tron => Some(0x2b6653dc),
+        Network::TronNile => Some(0xcd8690dc),
+        _ => None,
+    }
+}
+
+/// Hash the payer signs for `authorization` of `token` on `network`.
+pub fn signing_hash(chain_id: u64, token: Address, authorization: &TronAuthorization) -> B256 {
+    let domain = eip712_domain! {
+        name: "x402 Tron",
+        version: "1",
+        chain_id: chain_id,
+        verifying_contract: token,
+    };
+    TransferWithAuthorization {
+        from: authorization.from.0,
+        to: authorization.to.0,
+        value: authorization.value.0,
+        validAfter: U256::from(authorization.valid_after.0),
+        validBefore: U256::from(authorization.valid_before.0),
+        nonce: FixedBytes(authorization.nonce.0),
+    }
+    .eip712_signing_hash(&domain)
+}
+
+/// An authorization checked to pay for the requested resource, before on-chain checks.
+#[derive(Debug, Clone)]
+pub struct TronPayment {
+    pub token: TronAddress,
+    pub authorization: TronAuthorization,
+}
+
+impl TronPayment {
+    pub fn payer(&self) -> MixedAddress {
+        MixedAddress::Tron(self.authorization.from)
+    }
+}
+
+/// Runs the preconditions of a Tron payment that need no RPC:
+/// - Tron payload, `exact` scheme, matching network, accepted asset.
+/// - `to` is `payTo`, `value` covers `maxAmountRequired`, within the validity window.
+/// - Signature by `from`.
+pub fn assert_valid_payment(
+    network: Network,
+    accepted_assets: Option<&[TronAddress]>,
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Result<TronPayment, FacilitatorLocalError> {
+    let tron_payload = match &payload.payload {
+        ExactPaymentPayload::Tron(payload) => payload,
+        ExactPaymentPayload::Evm(_)
+        | ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => {
+            return Err(FacilitatorLocalError::UnsupportedNetwork(None));
+        }
+    };
+    let authorization = tron_payload.authorization;
+    let payer = MixedAddress::Tron(authorization.from);
+    for actual in [payload.network, requirements.network] {
+        if actual != network {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                Some(payer),
+                network,
+                actual,
+            ));
+        }
+    }
+    for scheme in [payload.scheme, requirements.scheme] {
+        if scheme != Scheme::Exact {
+            return Err(FacilitatorLocalError::SchemeMismatch(
+                Some(payer),
+                Scheme::Exact,
+                scheme,
+            ));
+        }
+    }
+    let MixedAddress::Tron(token) = requirements.asset else {
+        return Err(FacilitatorLocalError::UnsupportedAsset(
+            requirements.asset.clone(),
+        ));
+    };
+    if accepted_assets.is_some_and(|accepted| !accepted.contains(&token)) {
+        return Err(FacilitatorLocalError::UnsupportedAsset(
+            requirements.asset.clone(),
+        ));
+    }
+    if MixedAddress::Tron(authorization.to) != requirements.pay_to {
+        return Err(FacilitatorLocalError::ReceiverMismatch(
+            payer,
+            authorization.to.to_string(),
+            requirements.pay_to.to_string(),
+        ));
+    }
+    if authorization.value < requirements.max_amount_required {
+        return Err(FacilitatorLocalError::InsufficientValue(payer));
+    }
+    let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+    if authorization.valid_before < now + 6 {
+        return Err(FacilitatorLocalError::InvalidTiming(
+            payer,
+            format!(
+                "Expired: now {} > valid_before {}",
+                now + 6,
+                authorization.valid_before
+            ),
+        ));
+    }
+    if authorization.valid_after > now {
+        return Err(FacilitatorLocalError::InvalidTiming(
+            payer,
+            format!(
+                "Not active yet: valid_after {} > now {}",
+                authorization.valid_after, now
+            ),
+        ));
+    }
+    let chain_id = chain_id(network).ok_or(FacilitatorLocalError::UnsupportedNetwork(None))?;
+    let hash = signing_hash(chain_id, token.0, &authorization);
+    let recovered = Signature::try_from(tron_payload.signature.0.as_slice())
+        .ok()
+        .and_then(|signature| signature.recover_address_from_prehash(&hash).ok());
+    if recovered != Some(authorization.from.0) {
+        return Err(FacilitatorLocalError::InvalidSignature(
+            payer,
+            "Authorization is not signed by from".to_string(),
+        ));
+    }
+    Ok(TronPayment {
+        token,
+        authorization,
+    })
+}
+
+#[derive(Deserialize)]
+struct ConstantResult {
+    #[serde(default)]
+    constant_result: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TriggerResult {
+    transaction: Value,
+}
+
+#[derive(Deserialize)]
+struct BroadcastResult {
+    #[serde(default)]
+    result: bool,
+    #[serde(default)]
+    code: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TransactionInfo {
+    #[serde(default)]
+    receipt: Option<Receipt>,
+    #[serde(default, rename = "contractResult")]
+    contract_result: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Receipt {
+    #[serde(default)]
+    result: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct NowBlock {
+    block_header: BlockHeader,
+}
+
+#[derive(Deserialize)]
+struct BlockHeader {
+    raw_data: BlockRawData,
+}
+
+#[derive(Deserialize)]
+struct BlockRawData {
+    number: u64,
+}
+
+/// Client of the TronGrid HTTP API.
+#[derive(Clone)]
+struct TronGrid {
+    url: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+    rpc_budget: RpcBudget,
+}
+
+impl TronGrid {
+    async fn post<T: DeserializeOwned>(
+        &self,
+        path: &str,
+        body: Value,
+    ) -> Result<T, FacilitatorLocalError> {
+        let url = format!("{}{path}", self.url.trim_end_matches('/'));
+        let mut request = self.http.post(url).json(&body);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("TRON-PRO-API-KEY", api_key);
+        }
+        self.rpc_budget.record(1);
+        let api_error =
+            |e: reqwest::Error| FacilitatorLocalError::ContractCall(format!("Tron {path}: {e}"));
+        request
+            .send()
+            .await
+            .and_then(reqwest::Response::error_for_status)
+            .map_err(api_error)?
+            .json()
+            .await
+            .map_err(api_error)
+    }
+
+    /// Result of a read-only call of `contract`, ABI-decoded.
+    async fn call<C: SolCall>(
+        &self,
+        owner: TronAddress,
+        contract: TronAddress,
+        call: C,
+    ) -> Result<C::Return, FacilitatorLocalError> {
+        let body = json!({
+            "owner_address": owner.to_hex(),
+            "contract_address": contract.to_hex(),
+            "function_selector": C::SIGNATURE,
+            "parameter": hex::encode(&call.abi_encode()[4..]),
+        });
+        let result: ConstantResult = self.post("/wallet/triggerconstantcontract", body).await?;
+        let output = result
+            .constant_result
+            .first()
+            .and_then(|output| hex::decode(output).ok())
+            .ok_or_else(|| {
+                FacilitatorLocalError::ContractCall(format!("Tron {}: no result", C::SIGNATURE))
+            })?;
+        C::abi_decode_returns(&output)
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("Tron {}: {e}", C::SIGNATURE)))
+    }
+
+    async fn latest_block_number(&self) -> Result<u64, FacilitatorLocalError> {
+        let block: NowBlock = self.post("/wallet/getnowblock", json!({})).await?;
+        Ok(block.block_header.raw_data.number)
+    }
+}
+
+pub struct TronProvider {
+    network: Network,
+    chain_id: u64,
+    api: TronGrid,
+    signer: PrivateKeySigner,
+    /// Token contracts accepted as `asset`; any TRC-20 token if `None`.
+    accepted_assets: Option<Vec<TronAddress>>,
+    /// Nonces claimed and settled, persisted until their `validBefore`.
+    nonces: SeenNonces,
+}
+
+impl Debug for TronProvider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TronProvider")
+            .field("network", &self.network)
+            .field("address", &self.address().to_string())
+            .field("url", &self.api.url)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TronProvider {
+    /// Monthly request accounting for the HTTP API.
+    pub fn rpc_budget(&self) -> &RpcBudget {
+        &self.api.rpc_budget
+    }
+
+    /// Number of the latest block.
+    pub async fn latest_block_number(&self) -> Result<u64, FacilitatorLocalError> {
+        self.api.latest_block_number().await
+    }
+
+    fn address(&self) -> TronAddress {
+        TronAddress(self.signer.address())
+    }
+
+    /// All preconditions of a payment: [`assert_valid_payment`], then an unused nonce, and the payer's
+    /// balance and allowance to the facilitator covering `value`.
+    async fn verify_payment(
+        &self,
+        payload: &PaymentPayload,
+        requirements: &PaymentRequirements,
+    ) -> Result<TronPayment, FacilitatorLocalError> {
+        let payment = assert_valid_payment(
+            self.network,
+            self.accepted_assets.as_deref(),
+            payload,
+            requirements,
+        )?;
+        let authorization = &payment.authorization;
+        let payer = payment.payer();
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        self.nonces.check(&self.nonce_record(&payment), now)?;
+        let balance = self
+            .api
+            .call(
+                self.address(),
+                payment.token,
+                ITRC20::balanceOfCall {
+                    account: authorization.from.0,
+                },
+            )
+            .await?;
+        if balance < authorization.value.0 {
+            return Err(FacilitatorLocalError::InsufficientFunds(payer));
+        }
+        let allowance = self
+            .api
+            .call(
+                self.address(),
+                payment.token,
+                ITRC20::allowanceCall {
+                    owner: authorization.from.0,
+                    spender: self.signer.address(),
+                },
+            )
+            .await?;
+        if allowance < authorization.value.0 {
+            return Err(FacilitatorLocalError::InsufficientAllowance(payer));
+        }
+        Ok(payment)
+    }
+
+    /// In-flight record of the nonce of `payment`, keyed by network, token, payer and nonce.
+    fn nonce_record(&self, payment: &TronPayment) -> NonceRecord {
+        let authorization = &payment.authorization;
+        let key = keccak256(format!(
+            "{}:{}:{}:{}",
+            self.network,
+            payment.token.to_hex(),
+            hex::encode(authorization.from.0),
+            hex::encode(authorization.nonce.0),
+        ));
+        NonceRecord {
+            key,
+            network: self.network,
+            payer: payment.payer(),
+            state: NonceState::InFlight,
+            expires_at: authorization.valid_before,
+        }
+    }
+
+    /// Signs and broadcasts `transferFrom` for the payment; the transaction ID.
+    async fn send_transfer(
+        &self,
+        payment: &TronPayment,
+    ) -> Result<[u8; 32], FacilitatorLocalError> {
+        let authorization = &payment.authorization;
+        let call = ITRC20::transferFromCall {
+            from: authorization.from.0,
+            to: authorization.to.0,
+            value: authorization.value.0,
+        };
+        let body = json!({
+            "owner_address": self.address().to_hex(),
+            "contract_address": payment.token.to_hex(),
+            "function_selector": ITRC20::transferFromCall::SIGNATURE,
+            "parameter": hex::encode(&call.abi_encode()[4..]),
+            "fee_limit": FEE_LIMIT_SUN,
+            "call_value": 0,
+        });
+        let trigger: TriggerResult = self.api.post("/wallet/triggersmartcontract", body).await?;
+        let mut transaction = trigger.transaction;
+        // The ID signed is the hash of the raw transaction; check it rather than trusting the node.
+        let raw_data = transaction["raw_data_hex"]
+            .as_str()
+            .and_then(|raw| hex::decode(raw).ok())
+            .ok_or_else(|| FacilitatorLocalError::ContractCall("Tron: no raw_data_hex".into()))?;
+        let tx_id: [u8; 32] = Sha256::digest(&raw_data).into();
+        if transaction["txID"].as_str() != Some(hex::encode(tx_id).as_str()) {
+            return Err(FacilitatorLocalError::ContractCall(
+                "Tron: transaction ID does not match its raw data".to_string(),
+            ));
+        }
+        let signature = self
+            .signer
+            .sign_hash_sync(&B256::from(tx_id))
+            .map_err(|e| FacilitatorLocalError::ContractCall(format!("{e:?}")))?;
+        transaction["signature"] = json!([hex::encode(signature.as_bytes())]);
+        let broadcast: BroadcastResult = self
+            .api
+            .post("/wallet/broadcasttransaction", transaction)
+            .await?;
+        if !broadcast.result {
+            return Err(FacilitatorLocalError::ContractCall(format!(
+                "Tron broadcast failed: {}",
+                broadcast.code.unwrap_or_default()
+            )));
+        }
+        Ok(tx_id)
+    }
+
+    /// Waits for the receipt of `tx_id`; the error reason if the transaction failed.
+    async fn wait_for_receipt(
+        &self,
+        tx_id: [u8; 32],
+    ) -> Result<Option<FacilitatorErrorReason>, FacilitatorLocalError> {
+        let deadline = tokio::time::Instant::now() + RECEIPT_TIMEOUT;
+        loop {
+            tokio::time::sleep(RECEIPT_POLL_INTERVAL).await;
+            let info: TransactionInfo = self
+                .api
+                .post(
+                    "/wallet/gettransactioninfobyid",
+                    json!({ "value": hex::encode(tx_id) }),
+                )
+                .await?;
+            if let Some(receipt) = info.receipt {
+                if receipt.result.as_deref() == Some("SUCCESS") {
+                    return Ok(None);
+                }
+                let reason = info
+                    .contract_result
+                    .first()
+                    .and_then(|data| hex::decode(data).ok())
+                    .and_then(|data| Revert::decode(&data))
+                    .map(|revert| revert.reason())
+                    .unwrap_or(FacilitatorErrorReason::UnexpectedSettleError);
+                return Ok(Some(reason));
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return Err(FacilitatorLocalError::ContractCall(format!(
+                    "Tron transaction {} not confirmed in time",
+                    hex::encode(tx_id)
+                )));
+            }
+        }
+    }
+}
+
+impl FromEnvByNetworkBuild for TronProvider {
+    async fn from_env(network: Network) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let env_var = from_env::rpc_env_name_from_network(network);
+        let rpc_url = match std::env::var(env_var).ok() {
+            Some(rpc_url) => rpc_url,
+            None => {
+                tracing::warn!(network=%network, "no RPC URL configured, skipping");
+                return Ok(None);
+            }
+        };
+        let mut config = NetworkConfig::from_rpc_url(rpc_url);
+        config.tokens = from_env::tokens_from_env(network)?;
+        Ok(Some(Self::from_config(network, &config).await?))
+    }
+}
+
+impl FromConfigByNetworkBuild for TronProvider {
+    async fn from_config(
+        network: Network,
+        config: &NetworkConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let chain_id = chain_id(network).ok_or_else(|| format!("{network}: not a Tron network"))?;
+        let private_key = match config.signer_keys.as_deref() {
+            Some([key]) => key.clone(),
+            Some(_) => return Err(format!("{network}: expected exactly one signer key").into()),
+            None => std::env::var(from_env::ENV_TRON_PRIVATE_KEY)
+                .map_err(|_| format!("env {} not set", from_env::ENV_TRON_PRIVATE_KEY))?,
+        };
+        let signer = PrivateKeySigner::from_str(&private_key)
+            .map_err(|e| format!("{network}: invalid Tron private key: {e}"))?;
+        let accepted_assets = config
+            .tokens
+            .as_ref()
+            .map(|tokens| {
+                tokens
+                    .iter()
+                    .map(|token| match token {
+                        MixedAddress::Tron(address) => Ok(*address),
+                        other => Err(format!("{network}: invalid TRC-20 contract: {other}")),
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .transpose()?;
+        let outbound = OutboundPolicy::from_env()?;
+        let rpc_url =
+            Url::parse(&config.rpc_url).map_err(|e| format!("{network}: invalid RPC URL: {e}"))?;
+        outbound
+            .validate_url(&rpc_url)
+            .map_err(|e| format!("{network}: RPC URL: {e}"))?;
+        let api = TronGrid {
+            url: config.rpc_url.clone(),
+            api_key: std::env::var(from_env::ENV_TRON_API_KEY).ok(),
+            http: outbound.http_client(),
+            rpc_budget: RpcBudget::from_env(network),
+        };
+        let provider = Self {
+            network,
+            chain_id,
+            api,
+            signer,
+            accepted_assets,
+            nonces: SeenNonces::persistent_from_env("tron")
+                .map_err(|e| format!("{network}: {e}"))?,
+        };
+        tracing::info!(network=%network, rpc=%provider.api.url, address=%provider.address(), "Initialized provider");
+        Ok(provider)
+    }
+}
+
+impl NetworkProviderOps for TronProvider {
+    fn signer_address(&self) -> MixedAddress {
+        MixedAddress::Tron(self.address())
+    }
+
+    fn network(&self) -> Network {
+        self.network
+    }
+}
+
+impl Facilitator for TronProvider {
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let payment = self
+            .verify_payment(&request.payment_payload, &request.payment_requirements)
+            .await?;
+        Ok(VerifyResponse::valid(payment.payer()))
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let payment = self
+            .verify_payment(&request.payment_payload, &request.payment_requirements)
+            .await?;
+        let record = self.nonce_record(&payment);
+        let key = record.key;
+        // Claimed and saved before sending, so concurrent settlements of one authorization can not both pass,
+        // nor a settlement after a restart.
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        self.nonces.claim_persisted(record, now)?;
+        let release = || self.nonces.release(&key);
+        let tx_id = self
+            .send_transfer(&payment)
+            .await
+            .inspect_err(|_| release())?;
+        // Kept claimed if the receipt is not known, as the transaction may still be mined.
+        let error_reason = self.wait_for_receipt(tx_id).await?;
+        let success = error_reason.is_none();
+        if success {
+            self.nonces.settled(&key);
+        } else {
+            release();
+        }
+        tracing::info!(tx = %hex::encode(tx_id), success, chain_id = self.chain_id, "Tron transfer settled");
+        Ok(SettleResponse {
+            success,
+            error_reason,
+            payer: Some(payment.payer()),
+            transaction: Some(TransactionHash::Evm(tx_id)),
+            network: self.network,
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: false,
+        })
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        let kinds = vec![SupportedPaymentKind {
+            network: self.network.to_string(),
+            scheme: Scheme::Exact,
+            x402_version: X402Version::V1,
+            extra: Some(SupportedPaymentKindExtra {
+                fee_payer: self.signer_address(),
+                spender: Some(self.signer_address()),
+                asset: None,
+                signers: Vec::new(),
+            }),
+        }];
+        Ok(SupportedPaymentKindsResponse {
+            kinds,
+            routes: Vec::new(),
+            maintenance: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{EvmSignature, HexEncodedNonce, TokenAmount, TronPayload};
+
+    #[test]
+    fn authorization_recovers_to_its_signer() {
+        let token = TronAddress::from_str("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t").unwrap();
+        assert_eq!(token.to_string(), "TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t");
+        assert_eq!(token.to_hex(), "41a614f803b6fd780986a42c78ec9c7f77e6ded13c");
+        assert!(TronAddress::from_str("TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6u").is_err());
+
+        let signer = PrivateKeySigner::random();
+        let pay_to = TronAddress(Address::repeat_byte(2));
+        let now = UnixTimestamp::try_now().unwrap();
+        let authorization = TronAuthorization {
+            from: TronAddress(signer.address()),
+            to: pay_to,
+            value: TokenAmount::from(1_000_000u64),
+            valid_after: UnixTimestamp(0),
+            valid_before: now + 300,
+            nonce: HexEncodedNonce([7; 32]),
+        };
+        let hash = signing_hash(0x2b6653dc, token.0, &authorization);
+        let signature = signer.sign_hash_sync(&hash).unwrap();
+        let payload: PaymentPayload = serde_json::from_value(json!({
+            "x402Version": 1,
+            "scheme": "exact",
+            "network": "tron",
+            "payload": TronPayload {
+                signature: EvmSignature(signature.as_bytes().to_vec()),
+                authorization,
+            },
+        }))
+        .unwrap();
+        let requirements: PaymentRequirements = serde_json::from_value(json!({
+            "scheme": "exact",
+            "network": "tron",
+            "maxAmountRequired": "1000000",
+            "resource": "https://example.com/paid",
+            "description": "",
+            "mimeType": "application/json",
+            "payTo": pay_to,
+            "maxTimeoutSeconds": 60,
+            "asset": token,
+            "extra": null,
+        }))
+        .unwrap();
+        let payment = assert_valid_payment(Network::Tron, None, &payload, &requirements).unwrap();
+        assert_eq!(payment.payer(), MixedAddress::Tron(authorization.from));
+        assert!(matches!(
+            assert_valid_payment(Network::TronNile, None, &payload, &requirements),
+            Err(FacilitatorLocalError::NetworkMismatch(..))
+        ));
+    }
+}
@@ -0,0 +1,97 @@
+//! Reuse of chain reads made by `/verify` in the following `/settle`.
+//!
+//! Clients almost always settle a payment right after verifying it. Verifying an ERC-3009 payment reads the
+//! token's EIP-712 domain, the payer's balance and the token restrictions; settling re-checks all of them
+//! before sending the transaction. A [`WarmCache`] remembers, for a short while, that a request was verified,
+//! keyed by its hash, so that settling the very same request skips these reads and only sends the transaction.
+//!
+//! Purely local checks (networks, receiver, amounts, validity window) are always repeated. A payer spending
+//! their balance in between makes the settlement revert, as it would if they did so right after the checks.
+//!
+//! Environment variables used:
+//! - `WARM_CACHE_TTL_SECS` — how long a verification is reused for (default: `30`, `0` disables the cache).
+
+use alloy::primitives::B256;
+use alloy::sol_types::Eip712Domain;
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::time::{Duration, Instant};
+
+use crate::from_env;
+use crate::types::VerifyRequest;
+
+/// Default lifetime of a cached verification, in seconds.
+const DEFAULT_TTL_SECS: u64 = 30;
+
+#[derive(Debug, Clone)]
+struct WarmEntry {
+    domain: Eip712Domain,
+    expires_at: Instant,
+}
+
+/// Chain state of recently verified requests, by request hash.
+#[derive(Debug)]
+pub struct WarmCache {
+    ttl: Duration,
+    entries: DashMap<B256, WarmEntry>,
+}
+
+impl WarmCache {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: DashMap::new(),
+        }
+    }
+
+    /// Reads the TTL from `WARM_CACHE_TTL_SECS`; `None` if the cache is disabled.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let ttl_secs = match std::env::var(from_env::ENV_WARM_CACHE_TTL_SECS) {
+            Ok(value) => value
+                .parse::<u64>()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_WARM_CACHE_TTL_SECS))?,
+            Err(_) => DEFAULT_TTL_SECS,
+        };
+        Ok((ttl_secs > 0).then(|| Self::new(Duration::from_secs(ttl_secs))))
+    }
+
+    /// Hash of the request, covering both the payload and the requirements.
+    fn key(request: &VerifyRequest) -> Option<B256> {
+        let bytes = serde_json::to_vec(request).ok()?;
+        Some(B256::from_slice(&Sha256::digest(bytes)))
+    }
+
+    /// Records a successful verification of `request` against the token's `domain`.
+    pub fn insert(&self, request: &VerifyRequest, domain: Eip712Domain) {
+        let Some(key) = Self::key(request) else {
+            return;
+        };
+        let now = Instant::now();
+        self.entries.retain(|_, entry| entry.expires_at > now);
+        self.entries.insert(
+            key,
+            WarmEntry {
+                domain,
+                expires_at: now + self.ttl,
+            },
+        );
+    }
+
+    /// Takes the verification of `request` out of the cache, if still fresh.
+    pub fn take(&self, request: &VerifyRequest) -> Option<Eip712Domain> {
+        let entry = Self::key(request)
+            .and_then(|key| self.entries.remove(&key))
+            .map(|(_, entry)| entry)
+            .filter(|entry| entry.expires_at > Instant::now());
+        match entry {
+            Some(entry) => {
+                tracing::info!(monotonic_counter.x402.warm_cache.hits = 1);
+                Some(entry.domain)
+            }
+            None => {
+                tracing::info!(monotonic_counter.x402.warm_cache.misses = 1);
+                None
+            }
+        }
+    }
+}
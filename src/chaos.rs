@@ -0,0 +1,182 @@
+//! Fault injection for rehearsing incidents, behind the `chaos` Cargo feature.
+//!
+//! Production builds do not compile this module. In builds with `--features chaos`, operators can
+//! make the facilitator misbehave on purpose, to rehearse incident response and to check how
+//! clients retry against realistic failures:
+//! - drop a share of EVM RPC responses, after the request has reached the node,
+//! - delay every EVM settlement broadcast,
+//! - fail a share of `/settle` requests before they reach the chain.
+//!
+//! Faults are all disabled at startup and configured at runtime through the admin API:
+//! `GET /admin/chaos` (viewer) and `PUT /admin/chaos/config` (admin). Every injected fault is counted
+//! in the `x402.chaos.faults` counter, tagged by kind.
+
+use alloy::rpc::json_rpc::{RequestPacket, ResponsePacket};
+use alloy::transports::{TransportError, TransportErrorKind, TransportFut};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, put};
+use axum::{Extension, Json, Router};
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::hash::{BuildHasher, Hasher, RandomState};
+use std::sync::{Arc, RwLock};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tower::{Layer, Service};
+use tracing::instrument;
+
+use crate::admin::{CallerRole, error_response};
+
+/// Faults currently injected. All disabled by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChaosConfig {
+    /// Share of EVM RPC responses dropped, in percent.
+    #[serde(default)]
+    pub rpc_drop_percent: u8,
+    /// Delay before each EVM settlement transaction is broadcast, in milliseconds.
+    #[serde(default)]
+    pub broadcast_delay_ms: u64,
+    /// Share of `/settle` requests failed, in percent.
+    #[serde(default)]
+    pub settle_failure_percent: u8,
+}
+
+/// Runtime-configurable fault injector.
+///
+/// Cheap to clone: all clones share the same configuration.
+#[derive(Debug, Clone, Default)]
+pub struct Chaos {
+    config: Arc<RwLock<ChaosConfig>>,
+}
+
+static GLOBAL: Lazy<Chaos> = Lazy::new(Chaos::default);
+
+impl Chaos {
+    /// Process-wide injector, shared by the transports, the settlement path and the admin API.
+    pub fn global() -> &'static Chaos {
+        &GLOBAL
+    }
+
+    pub fn config(&self) -> ChaosConfig {
+        *self.config.read().expect("chaos lock poisoned")
+    }
+
+    pub fn set_config(&self, config: ChaosConfig) {
+        *self.config.write().expect("chaos lock poisoned") = config;
+        tracing::warn!(?config, "Chaos faults reconfigured");
+    }
+
+    /// Whether to drop the current RPC response.
+    pub fn drop_rpc_response(&self) -> bool {
+        self.inject("rpc_drop", self.config().rpc_drop_percent)
+    }
+
+    /// Whether to fail the current settlement.
+    pub fn fail_settle(&self) -> bool {
+        self.inject("settle_failure", self.config().settle_failure_percent)
+    }
+
+    /// Waits for the configured broadcast delay, if any.
+    pub async fn delay_broadcast(&self) {
+        let delay_ms = self.config().broadcast_delay_ms;
+        if delay_ms > 0 {
+            tracing::info!(
+                monotonic_counter.x402.chaos.faults = 1,
+                kind = "broadcast_delay",
+            );
+            tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+        }
+    }
+
+    fn inject(&self, kind: &'static str, percent: u8) -> bool {
+        let hit = percent > 0 && random_percent() < u64::from(percent);
+        if hit {
+            tracing::info!(monotonic_counter.x402.chaos.faults = 1, kind);
+        }
+        hit
+    }
+}
+
+/// Uniform value in `0..100`, from the randomly keyed std hasher.
+fn random_percent() -> u64 {
+    RandomState::new().build_hasher().finish() % 100
+}
+
+/// Tower layer dropping a share of Alloy transport responses, as configured in [`Chaos::global`].
+#[derive(Debug, Clone, Default)]
+pub struct ChaosLayer;
+
+impl<S> Layer<S> for ChaosLayer {
+    type Service = ChaosService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        ChaosService { inner }
+    }
+}
+
+/// Transport service produced by [`ChaosLayer`].
+#[derive(Debug, Clone)]
+pub struct ChaosService<S> {
+    inner: S,
+}
+
+impl<S> Service<RequestPacket> for ChaosService<S>
+where
+    S: Service<RequestPacket, Response = ResponsePacket, Error = TransportError>,
+    S::Future: Send + 'static,
+{
+    type Response = ResponsePacket;
+    type Error = TransportError;
+    type Future = TransportFut<'static>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: RequestPacket) -> Self::Future {
+        let response = self.inner.call(request);
+        Box::pin(async move {
+            let response = response.await?;
+            if Chaos::global().drop_rpc_response() {
+                return Err(TransportErrorKind::custom_str(
+                    "chaos: RPC response dropped",
+                ));
+            }
+            Ok(response)
+        })
+    }
+}
+
+/// Admin routes reporting the injected faults.
+pub fn routes() -> Router<Chaos> {
+    Router::new().route("/chaos", get(get_chaos))
+}
+
+/// Operator routes changing the injected faults.
+pub fn config_routes() -> Router<Chaos> {
+    Router::new().route("/chaos/config", put(put_chaos_config))
+}
+
+/// `GET /admin/chaos`: Currently injected faults.
+#[instrument(skip_all)]
+pub async fn get_chaos(State(chaos): State<Chaos>) -> impl IntoResponse {
+    (StatusCode::OK, Json(chaos.config()))
+}
+
+/// `PUT /admin/chaos/config`: Replaces the injected faults.
+#[instrument(skip_all)]
+pub async fn put_chaos_config(
+    State(chaos): State<Chaos>,
+    Extension(CallerRole(role)): Extension<CallerRole>,
+    Json(config): Json<ChaosConfig>,
+) -> Response {
+    if config.rpc_drop_percent > 100 || config.settle_failure_percent > 100 {
+        return error_response(StatusCode::BAD_REQUEST, "Percentages must be at most 100");
+    }
+    tracing::warn!(role = %role, "Chaos faults changed through admin API");
+    chaos.set_config(config);
+    (StatusCode::OK, Json(config)).into_response()
+}
@@ -0,0 +1,237 @@
+//! Circuit breakers around the chain providers.
+//!
+//! When the RPC endpoints of a network fail, every `/verify` and `/settle` for it waits for its RPC calls to time out,
+//! which ties up connections and workers, and answers late with an error anyway. [`CircuitGate`] keeps a breaker per
+//! network instead: after `CIRCUIT_BREAKER_THRESHOLD` consecutive RPC failures, the breaker opens, and requests for
+//! that network fail right away with `503 Service Unavailable`, a "network temporarily unavailable" error and a
+//! `Retry-After` header. After `CIRCUIT_BREAKER_COOLDOWN_SECS`, a single request is let through to probe the network:
+//! the breaker closes if it succeeds, and opens again otherwise.
+//!
+//! Only RPC failures count: payments the chain refuses, e.g. for insufficient funds, are answers of a working network.
+//! Breakers opening are logged and counted in the `x402.circuit.opened` metric, tagged by network.
+//!
+//! Environment variables used:
+//! - `CIRCUIT_BREAKER_THRESHOLD` — consecutive failures opening a breaker, `0` to disable breakers (default: `5`),
+//! - `CIRCUIT_BREAKER_COOLDOWN_SECS` — how long a breaker stays open before probing the network (default: `30`).
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::types::{
+    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Default consecutive failures opening a breaker.
+const DEFAULT_THRESHOLD: u32 = 5;
+/// Default time a breaker stays open.
+const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// State of the breaker of a network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Breaker {
+    /// Requests pass, counting consecutive failures.
+    Closed { failures: u32 },
+    /// Requests fail fast until `until`.
+    Open { until: Instant },
+    /// A probing request is in flight since `since`; others fail fast.
+    HalfOpen { since: Instant },
+}
+
+/// Breakers of all networks, see the [module docs](self).
+///
+/// Cheap to clone: all clones share the same breakers.
+#[derive(Debug, Clone)]
+pub struct CircuitBreakers {
+    threshold: u32,
+    cooldown: Duration,
+    breakers: Arc<Mutex<HashMap<Network, Breaker>>>,
+}
+
+impl CircuitBreakers {
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold,
+            cooldown,
+            breakers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Reads `CIRCUIT_BREAKER_THRESHOLD` and `CIRCUIT_BREAKER_COOLDOWN_SECS`; `None` if breakers are disabled.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let threshold = match std::env::var(from_env::ENV_CIRCUIT_BREAKER_THRESHOLD) {
+            Ok(value) => value
+                .trim()
+                .parse::<u32>()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_CIRCUIT_BREAKER_THRESHOLD))?,
+            Err(_) => DEFAULT_THRESHOLD,
+        };
+        if threshold == 0 {
+            return Ok(None);
+        }
+        let cooldown = match std::env::var(from_env::ENV_CIRCUIT_BREAKER_COOLDOWN_SECS) {
+            Ok(value) => value
+                .trim()
+                .parse::<u64>()
+                .map(Duration::from_secs)
+                .map_err(|e| format!("{}: {e}", from_env::ENV_CIRCUIT_BREAKER_COOLDOWN_SECS))?,
+            Err(_) => DEFAULT_COOLDOWN,
+        };
+        Ok(Some(Self::new(threshold, cooldown)))
+    }
+
+    /// Whether a request for `network` may go through at `now`; if not, how long until it may.
+    fn admit(&self, network: Network, now: Instant) -> Result<(), Duration> {
+        let mut breakers = self.breakers.lock().expect("circuit breakers poisoned");
+        let breaker = breakers
+            .entry(network)
+            .or_insert(Breaker::Closed { failures: 0 });
+        match *breaker {
+            Breaker::Closed { .. } => Ok(()),
+            Breaker::Open { until } if until > now => Err(until - now),
+            // A probe that never reported back, e.g. cancelled with its request, is given up after a cooldown.
+            Breaker::HalfOpen { since } if since + self.cooldown > now => {
+                Err(since + self.cooldown - now)
+            }
+            Breaker::Open { .. } | Breaker::HalfOpen { .. } => {
+                tracing::info!(network = %network, "Probing network behind open circuit breaker");
+                *breaker = Breaker::HalfOpen { since: now };
+                Ok(())
+            }
+        }
+    }
+
+    /// Records the outcome of a request for `network` admitted at `now`.
+    fn record(&self, network: Network, failed: bool, now: Instant) {
+        let mut breakers = self.breakers.lock().expect("circuit breakers poisoned");
+        let breaker = breakers
+            .entry(network)
+            .or_insert(Breaker::Closed { failures: 0 });
+        let next = match (*breaker, failed) {
+            (Breaker::Closed { .. }, false) => Breaker::Closed { failures: 0 },
+            (_, false) => {
+                tracing::info!(network = %network, "Circuit breaker closed");
+                Breaker::Closed { failures: 0 }
+            }
+            (Breaker::Closed { failures }, true) if failures + 1 < self.threshold => {
+                Breaker::Closed {
+                    failures: failures + 1,
+                }
+            }
+            // Requests admitted before the breaker opened may still be failing.
+            (Breaker::Open { until }, true) => Breaker::Open { until },
+            (Breaker::Closed { .. } | Breaker::HalfOpen { .. }, true) => {
+                tracing::warn!(
+                    monotonic_counter.x402.circuit.opened = 1,
+                    network = %network,
+                    cooldown = ?self.cooldown,
+                    "Circuit breaker opened, failing requests fast"
+                );
+                Breaker::Open {
+                    until: now + self.cooldown,
+                }
+            }
+        };
+        *breaker = next;
+    }
+}
+
+/// Whether `error` tells the network failed, rather than refused the payment.
+fn is_network_failure(error: &FacilitatorLocalError) -> bool {
+    matches!(error, FacilitatorLocalError::ContractCall(_))
+}
+
+/// [`Facilitator`] decorator failing fast on networks whose [`CircuitBreakers`] are open.
+///
+/// Passes every call through when breakers are disabled.
+pub struct CircuitGate<F> {
+    facilitator: F,
+    breakers: Option<CircuitBreakers>,
+}
+
+impl<F> CircuitGate<F> {
+    pub fn new(facilitator: F, breakers: Option<CircuitBreakers>) -> Self {
+        Self {
+            facilitator,
+            breakers,
+        }
+    }
+
+    /// Runs `call` for `network` through its breaker.
+    async fn guard<T>(
+        &self,
+        network: Network,
+        call: impl Future<Output = Result<T, FacilitatorLocalError>>,
+    ) -> Result<T, FacilitatorLocalError> {
+        let Some(breakers) = &self.breakers else {
+            return call.await;
+        };
+        breakers
+            .admit(network, Instant::now())
+            .map_err(|retry_after| {
+                FacilitatorLocalError::NetworkUnavailable(network, retry_after)
+            })?;
+        let result = call.await;
+        let failed = result.as_ref().is_err_and(is_network_failure);
+        breakers.record(network, failed, Instant::now());
+        result
+    }
+}
+
+impl<F> Facilitator for CircuitGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.guard(request.network(), self.facilitator.verify(request))
+            .await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        self.guard(request.network(), self.facilitator.settle(request))
+            .await
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_consecutive_failures_and_probes_after_cooldown() {
+        let breakers = CircuitBreakers::new(2, Duration::from_secs(30));
+        let start = Instant::now();
+        assert!(breakers.admit(Network::Base, start).is_ok());
+        breakers.record(Network::Base, true, start);
+        breakers.record(Network::Base, false, start);
+        breakers.record(Network::Base, true, start);
+        assert!(breakers.admit(Network::Base, start).is_ok());
+        breakers.record(Network::Base, true, start);
+        assert_eq!(
+            breakers.admit(Network::Base, start + Duration::from_secs(10)),
+            Err(Duration::from_secs(20))
+        );
+        assert!(breakers.admit(Network::Avalanche, start).is_ok());
+
+        let later = start + Duration::from_secs(30);
+        assert!(breakers.admit(Network::Base, later).is_ok());
+        assert!(breakers.admit(Network::Base, later).is_err());
+        breakers.record(Network::Base, true, later);
+        assert!(breakers.admit(Network::Base, later).is_err());
+
+        let much_later = later + Duration::from_secs(30);
+        assert!(breakers.admit(Network::Base, much_later).is_ok());
+        breakers.record(Network::Base, false, much_later);
+        assert!(breakers.admit(Network::Base, much_later).is_ok());
+    }
+}
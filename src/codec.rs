@@ -0,0 +1,189 @@
+//! Binary serialization formats for the protocol endpoints: CBOR and MessagePack.
+//!
+//! Bandwidth-sensitive clients, like embedded agents, can send `/verify` and `/settle` bodies as
+//! `application/cbor` or `application/msgpack` instead of JSON. The payloads are the same typed structures:
+//! [`negotiate`] converts a binary body to JSON before the handlers see it, and converts the JSON response
+//! back to the format the client asked for with `Accept`, or to the format of the request otherwise.
+
+use axum::body::{Body, Bytes, to_bytes};
+use axum::extract::Request;
+use axum::http::{HeaderMap, HeaderValue, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+
+use crate::types::ErrorResponse;
+
+/// Largest request or response body converted, as for axum's `Json` extractor.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Serialization format of a body.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Cbor,
+    MsgPack,
+}
+
+impl Format {
+    /// Format named by a media type, parameters ignored.
+    pub fn from_media_type(media_type: &str) -> Option<Self> {
+        let essence = media_type.split(';').next()?.trim().to_ascii_lowercase();
+        match essence.as_str() {
+            "application/json" => Some(Format::Json),
+            "application/cbor" => Some(Format::Cbor),
+            "application/msgpack" | "application/x-msgpack" | "application/vnd.msgpack" => {
+                Some(Format::MsgPack)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Format::Json => "application/json",
+            Format::Cbor => "application/cbor",
+            Format::MsgPack => "application/msgpack",
+        }
+    }
+
+    /// First supported format listed in `Accept`, if any.
+    fn from_accept(headers: &HeaderMap) -> Option<Self> {
+        headers
+            .get_all(header::ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .find_map(Format::from_media_type)
+    }
+
+    fn from_content_type(headers: &HeaderMap) -> Option<Self> {
+        let value = headers.get(header::CONTENT_TYPE)?.to_str().ok()?;
+        Format::from_media_type(value)
+    }
+
+    /// Decodes a body in this format into a JSON value.
+    pub fn decode(&self, bytes: &[u8]) -> Result<serde_json::Value, String> {
+        match self {
+            Format::Json => serde_json::from_slice(bytes).map_err(|e| e.to_string()),
+            Format::Cbor => ciborium::de::from_reader(bytes).map_err(|e| e.to_string()),
+            Format::MsgPack => rmp_serde::from_slice(bytes).map_err(|e| e.to_string()),
+        }
+    }
+
+    /// Encodes a JSON value in this format.
+    pub fn encode(&self, value: &serde_json::Value) -> Result<Vec<u8>, String> {
+        match self {
+            Format::Json => serde_json::to_vec(value).map_err(|e| e.to_string()),
+            Format::Cbor => {
+                let mut bytes = Vec::new();
+                ciborium::ser::into_writer(value, &mut bytes).map_err(|e| e.to_string())?;
+                Ok(bytes)
+            }
+            Format::MsgPack => rmp_serde::to_vec_named(value).map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Converts `bytes` from one format to another, through JSON.
+fn transcode(bytes: &[u8], from: Format, to: Format) -> Result<Vec<u8>, String> {
+    to.encode(&from.decode(bytes)?)
+}
+
+fn error_response(status: StatusCode, error: String, format: Format) -> Response {
+    let body = serde_json::to_value(ErrorResponse { error }).unwrap_or_default();
+    match format.encode(&body) {
+        Ok(bytes) => (
+            status,
+            [(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(format.content_type()),
+            )],
+            bytes,
+        )
+            .into_response(),
+        Err(_) => (status, axum::Json(body)).into_response(),
+    }
+}
+
+/// Middleware accepting CBOR and MessagePack bodies and answering in the negotiated format.
+pub async fn negotiate(request: Request, next: Next) -> Response {
+    let request_format = Format::from_content_type(request.headers());
+    let response_format = Format::from_accept(request.headers())
+        .or(request_format)
+        .unwrap_or(Format::Json);
+    let request = match request_format {
+        Some(format @ (Format::Cbor | Format::MsgPack)) => {
+            let (mut parts, body) = request.into_parts();
+            let bytes = match to_bytes(body, BODY_LIMIT).await {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    return error_response(StatusCode::BAD_REQUEST, e.to_string(), response_format);
+                }
+            };
+            let json = match transcode(&bytes, format, Format::Json) {
+                Ok(json) => json,
+                Err(e) => {
+                    let error = format!("Invalid {} body: {e}", format.content_type());
+                    return error_response(StatusCode::BAD_REQUEST, error, response_format);
+                }
+            };
+            tracing::info!(
+                monotonic_counter.x402.http.binary_requests = 1u64,
+                content_type = format.content_type(),
+            );
+            parts.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(Format::Json.content_type()),
+            );
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Request::from_parts(parts, Body::from(json))
+        }
+        Some(Format::Json) | None => request,
+    };
+    let response = next.run(request).await;
+    let is_json = Format::from_content_type(response.headers()) == Some(Format::Json);
+    if response_format == Format::Json || !is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let bytes: Bytes = match to_bytes(body, BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            return error_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                e.to_string(),
+                response_format,
+            );
+        }
+    };
+    match transcode(&bytes, Format::Json, response_format) {
+        Ok(encoded) => {
+            parts.headers.insert(
+                header::CONTENT_TYPE,
+                HeaderValue::from_static(response_format.content_type()),
+            );
+            parts.headers.remove(header::CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(encoded))
+        }
+        Err(e) => error_response(StatusCode::INTERNAL_SERVER_ERROR, e, response_format),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transcodes_through_json() {
+        let json = br#"{"x402Version":1,"paymentPayload":{"scheme":"exact","network":"base"}}"#;
+        for format in [Format::Cbor, Format::MsgPack] {
+            let encoded = transcode(json, Format::Json, format).unwrap();
+            let decoded = format.decode(&encoded).unwrap();
+            assert_eq!(decoded, Format::Json.decode(json).unwrap());
+        }
+        assert_eq!(
+            Format::from_media_type("application/vnd.msgpack; q=0.9"),
+            Some(Format::MsgPack)
+        );
+    }
+}
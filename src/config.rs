@@ -0,0 +1,379 @@
+//! Multi-network configuration from a TOML file.
+//!
+//! By default, networks are configured with `RPC_URL_<NETWORK>` and friends. With `CONFIG_FILE` set,
+//! the facilitator instead builds a provider for every network declared in that file, and only those:
+//!
+//! ```toml
+//! [networks.base]
+//! rpc_url = "https://mainnet.base.org" # EVM only: several comma-separated URLs are failed over between
+//! chain_id = 8453                    # optional, checked against the network
+//! signer_keys = ["0x…", "0x…"]       # optional, defaults to EVM_PRIVATE_KEY / SOLANA_PRIVATE_KEY
+//! signer_kms_keys = ["arn:aws:kms:…"] # optional, EVM only with the `kms` feature, instead of signer_keys
+//! confirmations = 2                  # optional, EVM only, defaults to 1
+//! tokens = ["0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"] # optional, accepted assets (SPL mints on Solana, NEP-141 contracts on NEAR, TRC-20 on Tron)
+//! block_tag = "safe"                 # optional, EVM only: latest (default), safe or finalized
+//! bundler_url = "https://…"          # optional, EVM only, enables ERC-4337 UserOperation payments
+//! paymaster_url = "https://…"        # optional, EVM only, ERC-7677 paymaster sponsoring them
+//! paymaster_pay_to = ["0x…"]         # required with paymaster_url, recipients whose payments are sponsored
+//! batch_window_ms = 200              # optional, EVM only, batches settlements through Multicall3
+//! fee_strategy = { max_priority_fee_per_gas = 1000000, max_fee_multiplier = 1.25 } # optional, EVM only
+//! gas_oracle = { kind = "fee_history", blocks = 20, percentile = 60 } # optional, EVM only, or { kind = "api", url = "…" }
+//! verify_simulation = true           # optional, EVM only, simulates the settlement call in /verify
+//! ws_url = "wss://…"                 # optional, EVM only with the `ws` feature, new heads drive receipt checks
+//!
+//! # Optional relayer services settling some assets instead of the signers, EVM only.
+//! [[networks.base.relayers]]
+//! kind = "gelato"                    # gelato or openzeppelin
+//! api_key = "…"
+//! assets = ["0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913"] # optional, all assets if unset
+//!
+//! [networks.solana]
+//! rpc_url = "https://api.mainnet-beta.solana.com"
+//!
+//! # Any other EVM chain, registered as a custom network: `chain_id` is then required.
+//! [networks.my-rollup]
+//! rpc_url = "https://rpc.my-rollup.example"
+//! chain_id = 123456
+//! tokens = ["0x…"]
+//! eip1559 = false                    # optional, custom networks only, defaults to true
+//! native_token = { symbol = "GAS", decimals = 18 } # optional, custom networks only, defaults to ETH
+//!
+//! # Optional verification rules, see the `rules` module.
+//! [[rules]]
+//! name = "small-payments"
+//! expr = 'amount <= 10_000000'
+//! ```
+//!
+//! A network only accepts the settings its family supports, see [`allowed_keys`]; any other setting fails startup
+//! rather than being ignored.
+//!
+//! Settings not covered by the file (signer type, next signers, Safe, RPC quotas, native token overrides,
+//! signer balance minimums) are still read from the environment.
+//!
+//! Environment variables used:
+//! - `CONFIG_FILE` — path of the TOML configuration file. Networks are configured from the environment if unset.
+
+use serde::de::{Error as _, IntoDeserializer};
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::chain::block_tracker::BlockTag;
+use crate::chain::fee_strategy::FeeStrategy;
+use crate::chain::gas_oracle::GasOracleConfig;
+use crate::chain::relayer::RelayerConfig;
+use crate::from_env;
+use crate::network::{CustomNetwork, NativeToken, Network, NetworkFamily};
+use crate::rules::RuleConfig;
+use crate::types::MixedAddress;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Can not read config file {path}: {source}")]
+    Io {
+        path: String,
+        #[source]
+        source: std::io::Error,
+    },
+    #[error("Invalid config file {path}: {source}")]
+    Format {
+        path: String,
+        #[source]
+        source: toml::de::Error,
+    },
+}
+
+/// Settings of a single network.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct NetworkConfig {
+    pub rpc_url: String,
+    /// Expected chain ID; startup fails if it does not match the network.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Private keys signing settlements on this network, instead of the keys from the environment.
+    #[serde(default)]
+    pub signer_keys: Option<Vec<String>>,
+    /// AWS KMS keys signing settlements on this network instead, with the `kms` feature. EVM only.
+    #[serde(default)]
+    pub signer_kms_keys: Option<Vec<String>>,
+    /// Block confirmations awaited before a settlement is reported.
+    #[serde(default)]
+    pub confirmations: Option<u64>,
+    /// Token contracts, SPL mints or NEP-141 contracts accepted in payment requirements; any asset if unset.
+    /// Falls back to `TOKENS_<NETWORK>` when networks come from the environment.
+    #[serde(default)]
+    pub tokens: Option<Vec<MixedAddress>>,
+    /// Block tag balances and authorizations are read at: `latest` (default), `safe` or `finalized`.
+    #[serde(default)]
+    pub block_tag: Option<BlockTag>,
+    /// ERC-4337 bundler endpoint, enabling the `erc4337` scheme.
+    #[serde(default)]
+    pub bundler_url: Option<String>,
+    /// ERC-7677 paymaster service sponsoring UserOperations; requires `bundler_url`.
+    #[serde(default)]
+    pub paymaster_url: Option<String>,
+    /// Recipients whose payments the paymaster sponsors; falls back to `PAYMASTER_PAY_TO_<NETWORK>`. Required with
+    /// `paymaster_url`.
+    #[serde(default)]
+    pub paymaster_pay_to: Option<Vec<MixedAddress>>,
+    /// Whether a custom network supports EIP-1559 transactions; defaults to `true`.
+    #[serde(default)]
+    pub eip1559: Option<bool>,
+    /// Native gas token of a custom network; defaults to ETH.
+    #[serde(default)]
+    pub native_token: Option<NativeToken>,
+    /// Window during which concurrent settlements are batched into one Multicall3 transaction, in milliseconds.
+    #[serde(default)]
+    pub batch_window_ms: Option<u64>,
+    /// Relayer services settling some or all assets instead of the local signers.
+    #[serde(default)]
+    pub relayers: Option<Vec<RelayerConfig>>,
+    /// Gas pricing of settlement transactions; falls back to `FEE_STRATEGY_<NETWORK>`.
+    #[serde(default)]
+    pub fee_strategy: Option<FeeStrategy>,
+    /// Source of fee suggestions for settlement transactions; falls back to `GAS_ORACLE_<NETWORK>`.
+    #[serde(default)]
+    pub gas_oracle: Option<GasOracleConfig>,
+    /// Whether `/verify` also simulates the exact settlement call; falls back to `VERIFY_SIMULATION`.
+    #[serde(default)]
+    pub verify_simulation: Option<bool>,
+    /// WebSocket endpoint announcing new heads to check settlement receipts on; falls back to `WS_URL_<NETWORK>`.
+    #[serde(default)]
+    pub ws_url: Option<String>,
+}
+
+impl NetworkConfig {
+    /// Configuration with only an RPC URL, everything else from defaults and the environment.
+    pub fn from_rpc_url(rpc_url: String) -> Self {
+        Self {
+            rpc_url,
+            ..Default::default()
+        }
+    }
+
+    /// Checks that only settings of the family of `network` are set, see [`allowed_keys`].
+    pub fn validate(&self, network: Network) -> Result<(), String> {
+        let family = NetworkFamily::from(network);
+        let allowed = allowed_keys(family);
+        let unsupported: Vec<&str> = self
+            .set_keys()
+            .into_iter()
+            .filter(|key| !allowed.contains(key))
+            .collect();
+        if unsupported.is_empty() {
+            return Ok(());
+        }
+        Err(format!(
+            "{network}: {} not supported on {family:?} networks",
+            unsupported.join(", ")
+        ))
+    }
+
+    /// Keys of the settings set, besides `rpc_url`, as named in the configuration file.
+    fn set_keys(&self) -> Vec<&'static str> {
+        [
+            ("chain_id", self.chain_id.is_some()),
+            ("signer_keys", self.signer_keys.is_some()),
+            ("signer_kms_keys", self.signer_kms_keys.is_some()),
+            ("confirmations", self.confirmations.is_some()),
+            ("tokens", self.tokens.is_some()),
+            ("block_tag", self.block_tag.is_some()),
+            ("bundler_url", self.bundler_url.is_some()),
+            ("paymaster_url", self.paymaster_url.is_some()),
+            ("paymaster_pay_to", self.paymaster_pay_to.is_some()),
+            ("eip1559", self.eip1559.is_some()),
+            ("native_token", self.native_token.is_some()),
+            ("batch_window_ms", self.batch_window_ms.is_some()),
+            ("relayers", self.relayers.is_some()),
+            ("fee_strategy", self.fee_strategy.is_some()),
+            ("gas_oracle", self.gas_oracle.is_some()),
+            ("verify_simulation", self.verify_simulation.is_some()),
+            ("ws_url", self.ws_url.is_some()),
+        ]
+        .into_iter()
+        .filter_map(|(key, set)| set.then_some(key))
+        .collect()
+    }
+
+    /// Definition of the custom network `name` configured by these settings.
+    pub fn custom_network(&self, name: String, chain_id: u64) -> CustomNetwork {
+        CustomNetwork {
+            eip1559: self.eip1559.unwrap_or(true),
+            native_token: self.native_token.clone(),
+            ..CustomNetwork::new(name, chain_id)
+        }
+    }
+}
+
+/// Settings of [`NetworkConfig`] the providers of `family` read, besides `rpc_url`.
+pub fn allowed_keys(family: NetworkFamily) -> &'static [&'static str] {
+    match family {
+        NetworkFamily::Evm => &[
+            "chain_id",
+            "signer_keys",
+            "signer_kms_keys",
+            "confirmations",
+            "tokens",
+            "block_tag",
+            "bundler_url",
+            "paymaster_url",
+            "paymaster_pay_to",
+            "eip1559",
+            "native_token",
+            "batch_window_ms",
+            "relayers",
+            "fee_strategy",
+            "gas_oracle",
+            "verify_simulation",
+            "ws_url",
+        ],
+        NetworkFamily::Solana | NetworkFamily::Near | NetworkFamily::Tron => {
+            &["signer_keys", "tokens"]
+        }
+        NetworkFamily::Lightning => &[],
+    }
+}
+
+/// Contents of the configuration file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FacilitatorConfig {
+    #[serde(default, deserialize_with = "deserialize_networks")]
+    pub networks: HashMap<Network, NetworkConfig>,
+    /// Verification rules every request must satisfy, see [`crate::rules`].
+    #[serde(default)]
+    pub rules: Vec<RuleConfig>,
+}
+
+/// Network tables keyed by network name; `toml` can not deserialize enum map keys by itself.
+///
+/// Unknown names declaring a `chain_id` are registered as [`CustomNetwork`]s.
+fn deserialize_networks<'de, D>(
+    deserializer: D,
+) -> Result<HashMap<Network, NetworkConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    HashMap::<String, NetworkConfig>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(name, config)| {
+            let known = Network::deserialize(name.as_str().into_deserializer());
+            let network = match (known, config.chain_id) {
+                (Ok(network @ Network::Custom(_)), _) => network,
+                (Ok(network), _) if config.eip1559.is_some() || config.native_token.is_some() => {
+                    return Err(D::Error::custom(format!(
+                        "{network}: eip1559 and native_token are only allowed for custom networks"
+                    )));
+                }
+                (Ok(network), _) => network,
+                (Err(_), Some(chain_id)) => config
+                    .custom_network(name, chain_id)
+                    .register()
+                    .map_err(D::Error::custom)?,
+                (Err(e), None) => return Err(e),
+            };
+            Ok((network, config))
+        })
+        .collect()
+}
+
+impl FacilitatorConfig {
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let display = path.display().to_string();
+        let contents = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: display.clone(),
+            source,
+        })?;
+        toml::from_str(&contents).map_err(|source| ConfigError::Format {
+            path: display,
+            source,
+        })
+    }
+
+    /// Loads the file at `CONFIG_FILE`; `None` if unset.
+    pub fn from_env() -> Result<Option<Self>, ConfigError> {
+        match std::env::var(from_env::ENV_CONFIG_FILE) {
+            Ok(path) => Self::from_file(Path::new(&path)).map(Some),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_networks_by_name() {
+        let config: FacilitatorConfig = toml::from_str(
+            r#"
+            [networks.base-sepolia]
+            rpc_url = "https://sepolia.base.org"
+            chain_id = 84532
+            confirmations = 3
+            tokens = ["0x036CbD53842c5426634e7929541eC2318f3dCF7e"]
+
+            [networks.solana-devnet]
+            rpc_url = "https://api.devnet.solana.com"
+            "#,
+        )
+        .unwrap();
+        let base_sepolia = &config.networks[&Network::BaseSepolia];
+        assert_eq!(base_sepolia.chain_id, Some(84532));
+        assert_eq!(base_sepolia.confirmations, Some(3));
+        assert_eq!(base_sepolia.tokens.as_ref().map(Vec::len), Some(1));
+        assert!(config.networks.contains_key(&Network::SolanaDevnet));
+        assert!(toml::from_str::<FacilitatorConfig>("[networks.base]\nrpc = \"x\"").is_err());
+    }
+
+    #[test]
+    fn registers_custom_networks() {
+        let config: FacilitatorConfig = toml::from_str(
+            r#"
+            [networks.config-test-rollup]
+            rpc_url = "https://rpc.example"
+            chain_id = 990001
+            eip1559 = false
+            "#,
+        )
+        .unwrap();
+        let network = Network::Custom(990001);
+        assert!(config.networks.contains_key(&network));
+        assert_eq!(network.to_string(), "config-test-rollup");
+        assert_eq!("config-test-rollup".parse::<Network>(), Ok(network));
+        assert!(
+            toml::from_str::<FacilitatorConfig>("[networks.nowhere]\nrpc_url = \"x\"").is_err()
+        );
+        assert!(
+            toml::from_str::<FacilitatorConfig>(
+                "[networks.base]\nrpc_url = \"x\"\neip1559 = false"
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn rejects_settings_of_other_network_families() {
+        let config = NetworkConfig {
+            signer_keys: Some(vec!["key".to_string()]),
+            tokens: Some(Vec::new()),
+            ..NetworkConfig::from_rpc_url("https://rpc.example".to_string())
+        };
+        assert!(config.validate(Network::Base).is_ok());
+        assert!(config.validate(Network::Solana).is_ok());
+        assert_eq!(
+            config.validate(Network::Lightning),
+            Err("lightning: signer_keys, tokens not supported on Lightning networks".to_string())
+        );
+        let config = NetworkConfig {
+            signer_kms_keys: Some(vec!["arn:aws:kms:…".to_string()]),
+            ws_url: Some("wss://rpc.example".to_string()),
+            ..NetworkConfig::from_rpc_url("https://rpc.example".to_string())
+        };
+        assert_eq!(
+            config.validate(Network::Near),
+            Err("near: signer_kms_keys, ws_url not supported on Near networks".to_string())
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! `GET /docs`: usage documentation of the running facilitator, as a single HTML page.
+//!
+//! Integrators often meet a facilitator by its URL alone. The page describes the public endpoints with
+//! the doc comments of their handlers, embedded at build time, and lists what this very deployment
+//! supports (schemes, networks, assets and fee payers) from the live [`Facilitator::supported`] data.
+//! Admin endpoints are not documented there.
+
+use axum::extract::State;
+use axum::response::{Html, IntoResponse};
+use std::fmt::Write as _;
+use tracing::instrument;
+
+use crate::build_info::BuildInfo;
+use crate::facilitator::Facilitator;
+use crate::types::SupportedPaymentKindsResponse;
+
+/// Sources of the modules serving public endpoints, whose handler doc comments make up the page.
+const SOURCES: [&str; 7] = [
+    include_str!("handlers.rs"),
+    include_str!("openapi.rs"),
+    include_str!("health.rs"),
+    include_str!("identity.rs"),
+    include_str!("nonces.rs"),
+    include_str!("approval.rs"),
+    include_str!("chain/aa.rs"),
+];
+
+/// Documentation of an endpoint, from a handler doc comment starting with `` `METHOD /path`: ``.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EndpointDoc {
+    /// Method and path, e.g. `POST /verify`.
+    route: String,
+    /// Paragraphs of the doc comment, the summary after the route first.
+    paragraphs: Vec<String>,
+}
+
+/// Endpoint docs found in `source`, in order.
+fn endpoint_docs(source: &str) -> Vec<EndpointDoc> {
+    let mut docs = Vec::new();
+    let mut block: Vec<&str> = Vec::new();
+    for line in source.lines().chain(std::iter::once("")) {
+        match line.strip_prefix("///") {
+            Some(doc) => block.push(doc.strip_prefix(' ').unwrap_or(doc)),
+            None if block.is_empty() => {}
+            None => {
+                docs.extend(endpoint_doc(&block));
+                block.clear();
+            }
+        }
+    }
+    docs
+}
+
+fn endpoint_doc(block: &[&str]) -> Option<EndpointDoc> {
+    let (route, summary) = block.first()?.strip_prefix('`')?.split_once("`:")?;
+    let (method, _) = route.split_once(' ')?;
+    if !["GET", "POST", "PUT", "DELETE"].contains(&method) || route.contains("/admin") {
+        return None;
+    }
+    let mut paragraphs = vec![summary.trim().to_string()];
+    let mut in_code = false;
+    for line in &block[1..] {
+        if line.starts_with("```") {
+            in_code = !in_code;
+        } else if in_code {
+            continue;
+        } else if line.trim().is_empty() {
+            paragraphs.push(String::new());
+        } else {
+            let paragraph = paragraphs.last_mut().expect("summary paragraph");
+            if !paragraph.is_empty() {
+                paragraph.push(' ');
+            }
+            paragraph.push_str(line.trim());
+        }
+    }
+    paragraphs.retain(|paragraph| !paragraph.is_empty());
+    Some(EndpointDoc {
+        route: route.to_string(),
+        paragraphs,
+    })
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// HTML of a doc comment paragraph: intra-doc links lose their brackets, backticks become `<code>`.
+fn render_paragraph(paragraph: &str) -> String {
+    let text = escape(&paragraph.replace("[`", "`").replace("`]", "`"));
+    let mut html = String::new();
+    for (i, part) in text.split('`').enumerate() {
+        if i % 2 == 1 {
+            let _ = write!(html, "<code>{part}</code>");
+        } else {
+            html.push_str(part);
+        }
+    }
+    html
+}
+
+fn render(supported: Option<&SupportedPaymentKindsResponse>) -> String {
+    let build = BuildInfo::current();
+    let mut html = String::new();
+    let _ = write!(
+        html,
+        "<!DOCTYPE html><html><head><meta charset=\"utf-8\"><title>x402 facilitator</title></head><body>\
+         <h1>x402 facilitator</h1><p>{} {}</p>\
+         <p>Settles <a href=\"https://x402.org\">x402</a> payments. Resource servers send the client's payment \
+         payload and their payment requirements to <code>POST /verify</code>, then <code>POST /settle</code>.</p>",
+        escape(env!("CARGO_PKG_NAME")),
+        escape(&build.version_tag()),
+    );
+    html.push_str("<h2>Supported payments</h2>");
+    match supported {
+        Some(supported) if !supported.kinds.is_empty() => {
+            html.push_str(
+                "<table><tr><th>x402 version</th><th>Scheme</th><th>Network</th><th>Asset</th><th>Fee payer</th></tr>",
+            );
+            for kind in &supported.kinds {
+                let extra = kind.extra.as_ref();
+                let _ = write!(
+                    html,
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    escape(&kind.x402_version.to_string()),
+                    escape(&kind.scheme.to_string()),
+                    escape(&kind.network),
+                    extra
+                        .and_then(|extra| extra.asset.as_ref())
+                        .map_or("USDC".to_string(), |asset| escape(&asset.to_string())),
+                    extra.map_or(String::new(), |extra| escape(&extra.fee_payer.to_string())),
+                );
+            }
+            html.push_str("</table>");
+        }
+        Some(_) => html.push_str("<p>No payment kinds are currently supported.</p>"),
+        None => html.push_str("<p>Supported payment kinds are currently unavailable.</p>"),
+    }
+    html.push_str("<h2>Endpoints</h2>");
+    for doc in SOURCES.iter().flat_map(|source| endpoint_docs(source)) {
+        let _ = write!(html, "<h3><code>{}</code></h3>", escape(&doc.route));
+        for paragraph in &doc.paragraphs {
+            let _ = write!(html, "<p>{}</p>", render_paragraph(paragraph));
+        }
+    }
+    html.push_str("</body></html>");
+    html
+}
+
+/// `GET /docs`: Usage documentation of this facilitator, with the payment kinds it currently supports.
+#[instrument(skip_all)]
+pub async fn get_docs<A>(State(facilitator): State<A>) -> impl IntoResponse
+where
+    A: Facilitator,
+{
+    let supported = facilitator.supported().await.ok();
+    Html(render(supported.as_ref()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracts_public_endpoint_docs() {
+        let source = "/// `POST /verify`: Verifies a payment.\n///\n/// Uses [`VerifyRequest`].\n\
+                      /// ```json\n/// {}\n/// ```\nfn a() {}\n\
+                      /// `GET /admin/signers`: Signers.\nfn b() {}\n/// Not an endpoint.\nfn c() {}\n";
+        let docs = endpoint_docs(source);
+        assert_eq!(
+            docs,
+            vec![EndpointDoc {
+                route: "POST /verify".to_string(),
+                paragraphs: vec![
+                    "Verifies a payment.".to_string(),
+                    "Uses [`VerifyRequest`].".to_string()
+                ],
+            }]
+        );
+        assert_eq!(
+            render_paragraph(&docs[0].paragraphs[1]),
+            "Uses <code>VerifyRequest</code>."
+        );
+        assert!(endpoint_docs(include_str!("handlers.rs")).len() >= 5);
+    }
+}
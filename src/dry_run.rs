@@ -0,0 +1,171 @@
+//! Dry-run mode: settlements are validated and simulated, never broadcast.
+//!
+//! A staging facilitator pointed at mainnet RPC endpoints exercises the whole `/settle` flow against real balances,
+//! nonces and contracts, but must not move funds. With `--dry-run` on the command line or `DRY_RUN=true`,
+//! [`DryRunGate`] answers every `settle` with the verification of the request by the whole facilitator stack instead:
+//! rules, budgets, fees, replay and nonce checks, and the on-chain simulation of the transfer. The exact settlement
+//! call of EVM payments is simulated as well, as with `VERIFY_SIMULATION=true`, unless a network disables it.
+//!
+//! The response is synthetic: `success` tells whether the payment would have been settled, no `transaction` is
+//! given, and `simulated` is `true`. As nothing is settled, nothing is recorded either: budgets are not spent,
+//! nonces are not marked as used, and the settlement queue, log and webhooks never see dry-run settlements.
+//!
+//! Environment variables used:
+//! - `DRY_RUN` — `true` or `1` to only simulate settlements, as `--dry-run` does.
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::types::{
+    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Command-line flag enabling dry-run mode.
+pub const DRY_RUN_FLAG: &str = "--dry-run";
+
+/// Whether dry-run mode is enabled, by [`DRY_RUN_FLAG`] or `DRY_RUN`.
+pub fn enabled() -> bool {
+    std::env::args().skip(1).any(|arg| arg == DRY_RUN_FLAG)
+        || std::env::var(from_env::ENV_DRY_RUN)
+            .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "true" | "1"))
+            .unwrap_or(false)
+}
+
+/// [`Facilitator`] decorator answering `settle` with a simulation, see the [module docs](self).
+///
+/// Passes every call through when dry-run mode is disabled.
+pub struct DryRunGate<F> {
+    facilitator: F,
+    enabled: bool,
+}
+
+impl<F> DryRunGate<F> {
+    pub fn new(facilitator: F, enabled: bool) -> Self {
+        Self {
+            facilitator,
+            enabled,
+        }
+    }
+}
+
+impl<F> Facilitator for DryRunGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        if !self.enabled {
+            return self.facilitator.settle(request).await;
+        }
+        let (success, error_reason, payer) = match self.facilitator.verify(request).await? {
+            VerifyResponse::Valid { payer, .. } => (true, None, Some(payer)),
+            VerifyResponse::Invalid { reason, payer } => (false, Some(reason), payer),
+        };
+        tracing::info!(network = %request.network(), success, "Settlement simulated, not broadcast");
+        Ok(SettleResponse {
+            success,
+            error_reason,
+            payer,
+            transaction: None,
+            network: request.network(),
+            facilitator_version: None,
+            batch: None,
+            payment_id: request.payment_id().cloned(),
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: true,
+        })
+    }
+
+    async fn submit(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        if self.enabled {
+            return self.settle(request).await;
+        }
+        self.facilitator.submit(request).await
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{FacilitatorErrorReason, MixedAddress, TokenAmount};
+
+    /// Facilitator refusing payments of more than one unit, and never settling.
+    struct Strict;
+
+    impl Facilitator for Strict {
+        type Error = FacilitatorLocalError;
+
+        async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            let payer = MixedAddress::Offchain("payer".to_string());
+            if request.payment_requirements.max_amount_required > TokenAmount::from(1u64) {
+                return Ok(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::InsufficientFunds,
+                ));
+            }
+            Ok(VerifyResponse::valid(payer))
+        }
+
+        async fn settle(&self, _request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            unimplemented!("dry-run settlements must not reach the facilitator")
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            unimplemented!()
+        }
+    }
+
+    fn request(amount: &str) -> SettleRequest {
+        serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": amount,
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x0000000000000000000000000000000000000002"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn settlements_are_simulated_by_verification() {
+        let gate = DryRunGate::new(Strict, true);
+
+        let response = gate.settle(&request("1")).await.unwrap();
+        assert!(response.success);
+        assert!(response.simulated);
+        assert!(response.transaction.is_none());
+
+        let response = gate.settle(&request("2")).await.unwrap();
+        assert!(!response.success);
+        assert!(response.simulated);
+        assert!(matches!(
+            response.error_reason,
+            Some(FacilitatorErrorReason::InsufficientFunds)
+        ));
+    }
+}
@@ -52,6 +52,32 @@ pub trait Facilitator {
         request: &SettleRequest,
     ) -> impl Future<Output = Result<SettleResponse, Self::Error>> + Send;
 
+    /// Accepts a [`SettleRequest`] to be settled in the background, answering as soon as it is accepted.
+    ///
+    /// The answer of a settlement left in progress has the [`FacilitatorErrorReason::SettlementPending`] reason and
+    /// the `settlement_id` to follow it by. Facilitators without a settlement queue settle before answering, as
+    /// [`Facilitator::settle`] does, which is the default.
+    ///
+    /// [`FacilitatorErrorReason::SettlementPending`]: crate::types::FacilitatorErrorReason::SettlementPending
+    fn submit(
+        &self,
+        request: &SettleRequest,
+    ) -> impl Future<Output = Result<SettleResponse, Self::Error>> + Send {
+        self.settle(request)
+    }
+
+    /// Settles a [`SettleRequest`] an outer layer already verified, given the outcome of that verification.
+    ///
+    /// Layers needing the payer take it from `verification` instead of verifying the payment again, and pass it
+    /// on. Facilitators not needing it settle as [`Facilitator::settle`] does, which is the default.
+    fn settle_verified(
+        &self,
+        request: &SettleRequest,
+        _verification: &VerifyResponse,
+    ) -> impl Future<Output = Result<SettleResponse, Self::Error>> + Send {
+        self.settle(request)
+    }
+
     #[allow(dead_code)] // For some reason clippy believes it is not used.
     fn supported(
         &self,
@@ -75,6 +101,21 @@ impl<T: Facilitator> Facilitator for Arc<T> {
         self.as_ref().settle(request)
     }
 
+    fn submit(
+        &self,
+        request: &SettleRequest,
+    ) -> impl Future<Output = Result<SettleResponse, Self::Error>> + Send {
+        self.as_ref().submit(request)
+    }
+
+    fn settle_verified(
+        &self,
+        request: &SettleRequest,
+        verification: &VerifyResponse,
+    ) -> impl Future<Output = Result<SettleResponse, Self::Error>> + Send {
+        self.as_ref().settle_verified(request, verification)
+    }
+
     fn supported(
         &self,
     ) -> impl Future<Output = Result<SupportedPaymentKindsResponse, Self::Error>> + Send {
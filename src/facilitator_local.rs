@@ -14,8 +14,10 @@ use tracing::instrument;
 use crate::chain::FacilitatorLocalError;
 use crate::facilitator::Facilitator;
 use crate::provider_cache::ProviderMap;
+use crate::timestamp::UnixTimestamp;
 use crate::types::{
-    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+    Scheme, SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest,
+    VerifyResponse,
 };
 
 /// A concrete [`Facilitator`] implementation that verifies and settles x402 payments
@@ -85,6 +87,34 @@ where
     /// in the response on success or failure.
     #[instrument(skip_all, err, fields(network = %request.payment_payload.network))]
     async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        if request.settle_amount.is_some() && request.payment_requirements.scheme != Scheme::Upto {
+            return Err(FacilitatorLocalError::InvalidSettleAmount(
+                None,
+                format!(
+                    "settleAmount is only allowed with the {} scheme",
+                    Scheme::Upto
+                ),
+            ));
+        }
+        if request.allow_partial && request.payment_requirements.scheme != Scheme::Upto {
+            return Err(FacilitatorLocalError::InvalidSettleAmount(
+                None,
+                format!(
+                    "allowPartial is only allowed with the {} scheme",
+                    Scheme::Upto
+                ),
+            ));
+        }
+        // Held back by the settlement queue until due; settling it right away would break the payer's terms.
+        if let Some(execute_after) = request.execute_after {
+            let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+            if execute_after > now {
+                return Err(FacilitatorLocalError::InvalidExecuteAfter(
+                    None,
+                    format!("settlement is due at {execute_after}, not before"),
+                ));
+            }
+        }
         let network = request.network();
         let provider = self
             .provider_map
@@ -101,6 +131,10 @@ where
             let mut supported_kinds = supported.map(|k| k.kinds).unwrap_or_default();
             kinds.append(&mut supported_kinds);
         }
-        Ok(SupportedPaymentKindsResponse { kinds })
+        Ok(SupportedPaymentKindsResponse {
+            kinds,
+            routes: Vec::new(),
+            maintenance: Vec::new(),
+        })
     }
 }
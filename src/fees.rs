@@ -0,0 +1,680 @@
+//! Facilitator fees, reduced or waived for frequent payers.
+//!
+//! A facilitator charging for its service takes a fee in basis points of each payment. Resource servers include it
+//! in `maxAmountRequired` and state it as `facilitatorFee` of the payment requirements, and the operator collects
+//! it from them. [`FeeGate`] refuses payments whose `facilitatorFee` is below the fee due with the
+//! `insufficient_fee` error reason, at `/verify` and again at `/settle`.
+//!
+//! With `FEE_MODE=surcharge`, the fee is charged on top of the price instead: payers sign for `maxAmountRequired`
+//! plus the fee due, [`FeeGate`] verifies that the signed amount covers both, and the full signed amount is settled to
+//! `payTo`, from which the operator collects the fee. Resource servers then price their requirements as they would
+//! without a facilitator fee. Fees collected this way are counted in the `x402.fees.collected` metric and totalled per
+//! network and asset at `GET /admin/fees/collected`.
+//!
+//! The fee due depends on how many payments the payer settled in the current period: volume tiers lower it, down
+//! to zero. Periods are fixed windows of `FEE_PERIOD_SECS` since the epoch; settled payments are counted in memory.
+//! `GET /fees/quote?amount=…&payer=…` tells what is due for a payment of `amount`, so that requirements can be
+//! priced for a known payer. Without `payer`, the quote is the fee of a payer without settled payments.
+//!
+//! Environment variables used:
+//! - `FEE_BPS` — fee in basis points of `maxAmountRequired`. No fee is charged if unset.
+//! - `FEE_TIERS` — comma-separated `payments:bps` tiers, e.g. `100:5,1000:0`: payers with at least `payments`
+//!   settled in the period pay `bps` instead.
+//! - `FEE_PERIOD_SECS` — length of the period payments are counted over (default: `2592000`, 30 days).
+//! - `FEE_MODE` — `included` (default) for fees included in `maxAmountRequired`, `surcharge` for fees on top of it.
+
+use alloy::primitives::U256;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tracing::instrument;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    MixedAddress, SettleRequest, SettleResponse, SupportedPaymentKindsResponse, TokenAmount,
+    VerifyRequest, VerifyResponse,
+};
+
+const DEFAULT_PERIOD_SECS: u64 = 30 * 86_400;
+
+/// Fee of payers with at least `min_payments` settled in the period.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeTier {
+    pub min_payments: u64,
+    pub fee_bps: u32,
+}
+
+/// How payers pay the fee.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FeeMode {
+    /// Included in `maxAmountRequired` and stated as `facilitatorFee` by resource servers.
+    #[default]
+    Included,
+    /// Signed for on top of `maxAmountRequired`, and settled with it.
+    Surcharge,
+}
+
+impl std::str::FromStr for FeeMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "included" => Ok(Self::Included),
+            "surcharge" => Ok(Self::Surcharge),
+            other => Err(format!(
+                "unknown fee mode {other}, expected included or surcharge"
+            )),
+        }
+    }
+}
+
+/// Base fee and volume tiers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FeeSchedule {
+    pub fee_bps: u32,
+    /// Tiers by ascending `min_payments`.
+    pub tiers: Vec<FeeTier>,
+    pub period_secs: u64,
+    pub mode: FeeMode,
+}
+
+impl FeeSchedule {
+    /// Reads `FEE_*`; `None` if `FEE_BPS` is unset.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(fee_bps) = std::env::var(from_env::ENV_FEE_BPS) else {
+            return Ok(None);
+        };
+        let fee_bps = fee_bps
+            .trim()
+            .parse()
+            .map_err(|e| format!("{}: {e}", from_env::ENV_FEE_BPS))?;
+        let tiers = match std::env::var(from_env::ENV_FEE_TIERS) {
+            Ok(tiers) => {
+                parse_tiers(&tiers).map_err(|e| format!("{}: {e}", from_env::ENV_FEE_TIERS))?
+            }
+            Err(_) => Vec::new(),
+        };
+        let period_secs = match std::env::var(from_env::ENV_FEE_PERIOD_SECS) {
+            Ok(secs) => secs
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_FEE_PERIOD_SECS))?
+                .max(1),
+            Err(_) => DEFAULT_PERIOD_SECS,
+        };
+        let mode = match std::env::var(from_env::ENV_FEE_MODE) {
+            Ok(mode) => mode
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_FEE_MODE))?,
+            Err(_) => FeeMode::default(),
+        };
+        Ok(Some(Self {
+            fee_bps,
+            tiers,
+            period_secs,
+            mode,
+        }))
+    }
+
+    /// Fee in basis points of a payer with `payments` settled in the period.
+    pub fn fee_bps(&self, payments: u64) -> u32 {
+        self.tiers
+            .iter()
+            .rev()
+            .find(|tier| payments >= tier.min_payments)
+            .map_or(self.fee_bps, |tier| tier.fee_bps)
+    }
+
+    /// Tier a payer with `payments` settled in the period reaches next, if any.
+    pub fn next_tier(&self, payments: u64) -> Option<FeeTier> {
+        self.tiers
+            .iter()
+            .find(|tier| tier.min_payments > payments)
+            .copied()
+    }
+}
+
+fn parse_tiers(tiers: &str) -> Result<Vec<FeeTier>, String> {
+    let mut tiers = tiers
+        .split(',')
+        .map(str::trim)
+        .filter(|tier| !tier.is_empty())
+        .map(|tier| {
+            let (payments, bps) = tier
+                .split_once(':')
+                .ok_or_else(|| format!("expected payments:bps, got {tier}"))?;
+            Ok(FeeTier {
+                min_payments: payments
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid payments in {tier}: {e}"))?,
+                fee_bps: bps
+                    .trim()
+                    .parse()
+                    .map_err(|e| format!("invalid bps in {tier}: {e}"))?,
+            })
+        })
+        .collect::<Result<Vec<_>, String>>()?;
+    tiers.sort_by_key(|tier| tier.min_payments);
+    Ok(tiers)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PayerCount {
+    /// Period, in periods since the epoch, `payments` are counted for.
+    period: u64,
+    payments: u64,
+}
+
+/// Fee due for a payment, as served by `GET /fees/quote`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FeeQuote {
+    pub fee_bps: u32,
+    /// Fee for the quoted amount, to set as `facilitatorFee`, or to sign for on top of it with surcharged fees.
+    pub fee: TokenAmount,
+    /// Payments of the payer settled in the current period.
+    pub payments: u64,
+    /// When the current period ends and payments are counted from zero again.
+    pub period_ends_at: UnixTimestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_tier: Option<FeeTier>,
+}
+
+/// Surcharged fees collected in an asset on a network since startup, as served by `GET /admin/fees/collected`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CollectedFees {
+    pub network: Network,
+    pub asset: MixedAddress,
+    pub amount: TokenAmount,
+}
+
+/// Fee schedule, the payments settled per payer in the current period, and the surcharged fees collected.
+#[derive(Debug, Clone)]
+pub struct Fees {
+    schedule: Arc<FeeSchedule>,
+    payers: Arc<DashMap<MixedAddress, PayerCount>>,
+    collected: Arc<DashMap<(Network, MixedAddress), U256>>,
+}
+
+impl Fees {
+    pub fn new(schedule: FeeSchedule) -> Self {
+        Self {
+            schedule: Arc::new(schedule),
+            payers: Arc::new(DashMap::new()),
+            collected: Arc::new(DashMap::new()),
+        }
+    }
+
+    /// Reads the fee schedule from the environment; `None` if no fee is charged.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        Ok(FeeSchedule::from_env()?.map(Self::new))
+    }
+
+    fn period(&self, now: UnixTimestamp) -> u64 {
+        now.seconds_since_epoch() / self.schedule.period_secs
+    }
+
+    /// Payments of `payer` settled in `period`.
+    fn payments(&self, payer: &MixedAddress, period: u64) -> u64 {
+        self.payers
+            .get(payer)
+            .filter(|count| count.period == period)
+            .map_or(0, |count| count.payments)
+    }
+
+    /// Fee due by `payer`, or by a payer without settled payments, for a payment of `amount`.
+    pub fn quote(
+        &self,
+        payer: Option<&MixedAddress>,
+        amount: TokenAmount,
+    ) -> Result<FeeQuote, FacilitatorLocalError> {
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        let period = self.period(now);
+        let payments = payer.map_or(0, |payer| self.payments(payer, period));
+        let fee_bps = self.schedule.fee_bps(payments);
+        let fee = amount
+            .0
+            .saturating_mul(U256::from(fee_bps))
+            .saturating_add(U256::from(9_999))
+            / U256::from(10_000);
+        Ok(FeeQuote {
+            fee_bps,
+            fee: fee.into(),
+            payments,
+            period_ends_at: UnixTimestamp((period + 1) * self.schedule.period_secs),
+            next_tier: self.schedule.next_tier(payments),
+        })
+    }
+
+    /// Checks that the requirements of `request` state at least the fee due by `payer`.
+    fn check(
+        &self,
+        request: &VerifyRequest,
+        payer: &MixedAddress,
+    ) -> Result<(), FacilitatorLocalError> {
+        let requirements = &request.payment_requirements;
+        let quote = self.quote(Some(payer), requirements.max_amount_required)?;
+        let stated = requirements
+            .facilitator_fee
+            .unwrap_or(TokenAmount(U256::ZERO));
+        if stated < quote.fee {
+            tracing::info!(
+                monotonic_counter.x402.fees.rejections = 1,
+                payer = %payer,
+                "Payment states less than the facilitator fee"
+            );
+            return Err(FacilitatorLocalError::InsufficientFee(
+                payer.clone(),
+                format!(
+                    "facilitator fee is {}, requirements state {stated}",
+                    quote.fee
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    /// `request` with the fee due by `payer` added to the amounts it moves, and stated as `facilitatorFee`.
+    ///
+    /// `None` if no fee is due.
+    fn surcharge(
+        &self,
+        request: &VerifyRequest,
+        payer: &MixedAddress,
+    ) -> Result<Option<(VerifyRequest, TokenAmount)>, FacilitatorLocalError> {
+        let requirements = &request.payment_requirements;
+        let amount = request
+            .settle_amount
+            .unwrap_or(requirements.max_amount_required);
+        let fee = self.quote(Some(payer), amount)?.fee;
+        if fee.0.is_zero() {
+            return Ok(None);
+        }
+        let mut surcharged = request.clone();
+        let requirements = &mut surcharged.payment_requirements;
+        requirements.max_amount_required =
+            TokenAmount(requirements.max_amount_required.0.saturating_add(fee.0));
+        requirements.facilitator_fee = Some(fee);
+        surcharged.settle_amount = request
+            .settle_amount
+            .map(|amount| TokenAmount(amount.0.saturating_add(fee.0)));
+        Ok(Some((surcharged, fee)))
+    }
+
+    /// Records `fee` collected in `asset` on `network`.
+    fn collect(&self, network: Network, asset: &MixedAddress, fee: TokenAmount) {
+        tracing::info!(
+            monotonic_counter.x402.fees.collected = 1,
+            network = %network,
+            asset = %asset,
+            fee = %fee,
+            "Facilitator fee collected"
+        );
+        let mut total = self
+            .collected
+            .entry((network, asset.clone()))
+            .or_insert(U256::ZERO);
+        *total = total.saturating_add(fee.0);
+    }
+
+    /// Surcharged fees collected since startup, in no particular order.
+    pub fn collected(&self) -> Vec<CollectedFees> {
+        self.collected
+            .iter()
+            .map(|entry| {
+                let (network, asset) = entry.key();
+                CollectedFees {
+                    network: *network,
+                    asset: asset.clone(),
+                    amount: TokenAmount(*entry.value()),
+                }
+            })
+            .collect()
+    }
+
+    /// Counts a payment settled by `payer` in the current period.
+    fn count(&self, payer: &MixedAddress) {
+        let Ok(now) = UnixTimestamp::try_now() else {
+            return;
+        };
+        let period = self.period(now);
+        let mut count = self.payers.entry(payer.clone()).or_insert(PayerCount {
+            period,
+            payments: 0,
+        });
+        if count.period != period {
+            *count = PayerCount {
+                period,
+                payments: 0,
+            };
+        }
+        count.payments += 1;
+    }
+}
+
+/// [`Facilitator`] wrapper charging the fees of [`Fees`].
+pub struct FeeGate<F> {
+    facilitator: F,
+    fees: Option<Fees>,
+}
+
+impl<F> FeeGate<F> {
+    pub fn new(facilitator: F, fees: Option<Fees>) -> Self {
+        Self { facilitator, fees }
+    }
+}
+
+impl<F> Facilitator for FeeGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let response = self.facilitator.verify(request).await?;
+        let (Some(fees), VerifyResponse::Valid { payer, .. }) = (&self.fees, &response) else {
+            return Ok(response);
+        };
+        match fees.schedule.mode {
+            FeeMode::Included => {
+                fees.check(request, payer)?;
+                Ok(response)
+            }
+            FeeMode::Surcharge => match fees.surcharge(request, payer)? {
+                Some((surcharged, _)) => self.facilitator.verify(&surcharged).await,
+                None => Ok(response),
+            },
+        }
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        if self.fees.is_none() {
+            return self.facilitator.settle(request).await;
+        }
+        // The fee due depends on the payer, only known for sure once the payment is verified.
+        let verification = self.facilitator.verify(request).await?;
+        self.settle_verified(request, &verification).await
+    }
+
+    async fn settle_verified(
+        &self,
+        request: &SettleRequest,
+        verification: &VerifyResponse,
+    ) -> Result<SettleResponse, Self::Error> {
+        let Some(fees) = &self.fees else {
+            return self
+                .facilitator
+                .settle_verified(request, verification)
+                .await;
+        };
+        let surcharge = match (verification, fees.schedule.mode) {
+            (VerifyResponse::Valid { payer, .. }, FeeMode::Included) => {
+                fees.check(request, payer)?;
+                None
+            }
+            (VerifyResponse::Valid { payer, .. }, FeeMode::Surcharge) => {
+                fees.surcharge(request, payer)?
+            }
+            (VerifyResponse::Invalid { .. }, _) => None,
+        };
+        let response = match &surcharge {
+            Some((surcharged, _)) => {
+                self.facilitator
+                    .settle_verified(surcharged, verification)
+                    .await?
+            }
+            None => {
+                self.facilitator
+                    .settle_verified(request, verification)
+                    .await?
+            }
+        };
+        if response.success {
+            if let Some(payer) = &response.payer {
+                fees.count(payer);
+            }
+            if let Some((_, fee)) = &surcharge {
+                // A partial settlement collects what it moved beyond the price, if anything.
+                let fee = match &response.partial {
+                    Some(partial) => {
+                        let price = request
+                            .settle_amount
+                            .unwrap_or(request.payment_requirements.max_amount_required);
+                        TokenAmount(partial.settled_amount.0.saturating_sub(price.0))
+                    }
+                    None => *fee,
+                };
+                fees.collect(request.network(), &request.payment_requirements.asset, fee);
+            }
+        }
+        Ok(response)
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+/// Query of `GET /fees/quote`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FeeQuoteQuery {
+    pub amount: TokenAmount,
+    pub payer: Option<MixedAddress>,
+}
+
+/// Routes quoting facilitator fees.
+pub fn routes() -> Router<Fees> {
+    Router::new().route("/fees/quote", get(get_quote))
+}
+
+/// Admin routes listing the surcharged fees collected.
+pub fn admin_routes() -> Router<Fees> {
+    Router::new().route("/fees/collected", get(get_collected))
+}
+
+/// `GET /admin/fees/collected`: Surcharged fees collected per network and asset since startup.
+#[instrument(skip_all)]
+pub async fn get_collected(State(fees): State<Fees>) -> Json<Vec<CollectedFees>> {
+    Json(fees.collected())
+}
+
+/// `GET /fees/quote`: Fee due for a payment of `amount` by `payer`.
+#[instrument(skip_all)]
+pub async fn get_quote(State(fees): State<Fees>, Query(query): Query<FeeQuoteQuery>) -> Response {
+    match fees.quote(query.payer.as_ref(), query.amount) {
+        Ok(quote) => (StatusCode::OK, Json(quote)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schedule() -> FeeSchedule {
+        FeeSchedule {
+            fee_bps: 30,
+            tiers: parse_tiers("1000:0, 2:10").unwrap(),
+            period_secs: DEFAULT_PERIOD_SECS,
+            mode: FeeMode::Included,
+        }
+    }
+
+    #[test]
+    fn tiers_lower_the_fee_of_frequent_payers() {
+        let schedule = schedule();
+        assert_eq!(schedule.fee_bps(0), 30);
+        assert_eq!(schedule.fee_bps(2), 10);
+        assert_eq!(schedule.fee_bps(999), 10);
+        assert_eq!(schedule.fee_bps(1000), 0);
+        assert_eq!(
+            schedule.next_tier(2).map(|tier| tier.min_payments),
+            Some(1000)
+        );
+        assert_eq!(schedule.next_tier(1000), None);
+        assert!(parse_tiers("100").is_err());
+    }
+
+    #[test]
+    fn quotes_follow_settled_payments() {
+        let fees = Fees::new(schedule());
+        let payer = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(1).into());
+        let amount = TokenAmount::from(1_000_001u64);
+        let quote = fees.quote(Some(&payer), amount).unwrap();
+        // 30 bps of 1000001, rounded up.
+        assert_eq!(quote.fee, TokenAmount::from(3_001u64));
+        fees.count(&payer);
+        fees.count(&payer);
+        let quote = fees.quote(Some(&payer), amount).unwrap();
+        assert_eq!((quote.payments, quote.fee_bps), (2, 10));
+        assert_eq!(fees.quote(None, amount).unwrap().fee_bps, 30);
+    }
+
+    #[test]
+    fn surcharges_add_the_fee_to_the_amounts_moved() {
+        let fees = Fees::new(schedule());
+        let payer = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(1).into());
+        let mut request: VerifyRequest = serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": "1000000",
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x0000000000000000000000000000000000000002"
+            }
+        }))
+        .unwrap();
+        let (surcharged, fee) = fees.surcharge(&request, &payer).unwrap().unwrap();
+        assert_eq!(fee, TokenAmount::from(3_000u64));
+        let requirements = &surcharged.payment_requirements;
+        assert_eq!(
+            requirements.max_amount_required,
+            TokenAmount::from(1_003_000u64)
+        );
+        assert_eq!(requirements.facilitator_fee, Some(fee));
+        assert_eq!(surcharged.settle_amount, None);
+
+        request.settle_amount = Some(TokenAmount::from(500_000u64));
+        let (surcharged, fee) = fees.surcharge(&request, &payer).unwrap().unwrap();
+        assert_eq!(
+            surcharged.settle_amount,
+            Some(TokenAmount::from(501_500u64))
+        );
+        fees.collect(Network::Base, &request.payment_requirements.asset, fee);
+        fees.collect(Network::Base, &request.payment_requirements.asset, fee);
+        assert_eq!(fees.collected()[0].amount, TokenAmount::from(3_000u64));
+    }
+
+    /// Facilitator counting its verifications, all valid, and settling every payment.
+    #[derive(Default)]
+    struct Counting {
+        verifications: std::sync::atomic::AtomicU32,
+    }
+
+    impl Facilitator for Counting {
+        type Error = FacilitatorLocalError;
+
+        async fn verify(&self, _request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            self.verifications
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(VerifyResponse::valid(MixedAddress::Evm(
+                alloy::primitives::Address::repeat_byte(1).into(),
+            )))
+        }
+
+        async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            Ok(SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: None,
+                transaction: None,
+                network: request.network(),
+                facilitator_version: None,
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: None,
+                settlement_id: None,
+                simulated: false,
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            unimplemented!()
+        }
+    }
+
+    #[tokio::test]
+    async fn settlements_are_verified_once() {
+        let budgets = crate::budgets::Budgets::default();
+        let payer = MixedAddress::Evm(alloy::primitives::Address::repeat_byte(1).into());
+        let asset = MixedAddress::Evm(alloy::primitives::Address::with_last_byte(2).into());
+        budgets.set(Network::Base, payer, asset, TokenAmount::from(1_003_000u64));
+        let schedule = FeeSchedule {
+            mode: FeeMode::Surcharge,
+            ..schedule()
+        };
+        let counting = Arc::new(Counting::default());
+        let gate = FeeGate::new(
+            crate::budgets::BudgetGate::new(counting.clone(), budgets.clone()),
+            Some(Fees::new(schedule)),
+        );
+        let request: SettleRequest = serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": "1000000",
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x0000000000000000000000000000000000000002"
+            }
+        }))
+        .unwrap();
+        assert!(gate.settle(&request).await.unwrap().success);
+        assert_eq!(
+            counting
+                .verifications
+                .load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+        // The budget counts the surcharged amount, settled with the verification of the fee gate.
+        assert_eq!(
+            budgets.list().unwrap()[0].spent_today,
+            TokenAmount::from(1_003_000u64)
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::network::Network;
+use crate::types::MixedAddress;
 use alloy::network::EthereumWallet;
 use alloy::signers::local::PrivateKeySigner;
 use serde::Deserialize;
@@ -9,7 +10,14 @@ use std::str::FromStr;
 
 pub const ENV_SIGNER_TYPE: &str = "SIGNER_TYPE";
 pub const ENV_EVM_PRIVATE_KEY: &str = "EVM_PRIVATE_KEY";
+pub const ENV_EVM_NEXT_PRIVATE_KEY: &str = "EVM_NEXT_PRIVATE_KEY";
 pub const ENV_SOLANA_PRIVATE_KEY: &str = "SOLANA_PRIVATE_KEY";
+pub const ENV_NEAR_ACCOUNT_ID: &str = "NEAR_ACCOUNT_ID";
+pub const ENV_NEAR_PRIVATE_KEY: &str = "NEAR_PRIVATE_KEY";
+#[cfg(feature = "tron")]
+pub const ENV_TRON_PRIVATE_KEY: &str = "TRON_PRIVATE_KEY";
+#[cfg(feature = "tron")]
+pub const ENV_TRON_API_KEY: &str = "TRON_API_KEY";
 
 pub const ENV_RPC_BASE: &str = "RPC_URL_BASE";
 pub const ENV_RPC_BASE_SEPOLIA: &str = "RPC_URL_BASE_SEPOLIA";
@@ -22,9 +30,258 @@ pub const ENV_RPC_POLYGON_AMOY: &str = "RPC_URL_POLYGON_AMOY";
 pub const ENV_RPC_POLYGON: &str = "RPC_URL_POLYGON";
 pub const ENV_RPC_SEI: &str = "RPC_URL_SEI";
 pub const ENV_RPC_SEI_TESTNET: &str = "RPC_URL_SEI_TESTNET";
+pub const ENV_RPC_ARBITRUM: &str = "RPC_URL_ARBITRUM";
+pub const ENV_RPC_OPTIMISM: &str = "RPC_URL_OPTIMISM";
+pub const ENV_RPC_LIGHTNING: &str = "RPC_URL_LIGHTNING";
+pub const ENV_RPC_LIGHTNING_TESTNET: &str = "RPC_URL_LIGHTNING_TESTNET";
+pub const ENV_RPC_NEAR: &str = "RPC_URL_NEAR";
+pub const ENV_RPC_NEAR_TESTNET: &str = "RPC_URL_NEAR_TESTNET";
+pub const ENV_RPC_TRON: &str = "RPC_URL_TRON";
+pub const ENV_RPC_TRON_NILE: &str = "RPC_URL_TRON_NILE";
 
-pub fn rpc_env_name_from_network(network: Network) -> &'static str {
-    match network {
+pub fn rpc_quota_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "RPC_MONTHLY_QUOTA_", 1)
+}
+
+pub fn native_token_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "NATIVE_TOKEN_", 1)
+}
+
+pub fn safe_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "SAFE_ADDRESS_", 1)
+}
+
+/// `MACAROON_<NETWORK>`, the hex-encoded LND macaroon of a Lightning network's node.
+#[cfg(feature = "lightning")]
+pub fn macaroon_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "MACAROON_", 1)
+}
+
+/// `TLS_CERT_<NETWORK>`, path to the PEM certificate of a Lightning node with a self-signed one, or of its CA.
+#[cfg(feature = "lightning")]
+pub fn tls_cert_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "TLS_CERT_", 1)
+}
+
+/// `TLS_CLIENT_CERT_<NETWORK>`, path to the PEM client certificate of a Lightning network's Core Lightning node.
+#[cfg(feature = "lightning")]
+pub fn tls_client_cert_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "TLS_CLIENT_CERT_", 1)
+}
+
+/// `TLS_CLIENT_KEY_<NETWORK>`, path to the PEM key of the client certificate of a Core Lightning node.
+#[cfg(feature = "lightning")]
+pub fn tls_client_key_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "TLS_CLIENT_KEY_", 1)
+}
+
+pub fn block_tag_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "BLOCK_TAG_", 1)
+}
+
+/// `FEE_STRATEGY_<NETWORK>`, the gas pricing of settlements on an EVM network, see [`crate::chain::fee_strategy`].
+pub fn fee_strategy_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "FEE_STRATEGY_", 1)
+}
+
+/// `GAS_ORACLE_<NETWORK>`, the source of fee suggestions on an EVM network, see [`crate::chain::gas_oracle`].
+pub fn gas_oracle_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "GAS_ORACLE_", 1)
+}
+
+/// `SIGNER_MIN_BALANCE_<NETWORK>`, the native balance below which a signer degrades its network, see
+/// [`crate::health`].
+pub fn signer_min_balance_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "SIGNER_MIN_BALANCE_", 1)
+}
+
+/// `WS_URL_<NETWORK>`, the WebSocket endpoint announcing new heads, see [`crate::chain::heads`].
+pub fn ws_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "WS_URL_", 1)
+}
+
+pub fn bundler_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "BUNDLER_URL_", 1)
+}
+
+pub fn paymaster_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "PAYMASTER_URL_", 1)
+}
+
+pub fn paymaster_pay_to_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "PAYMASTER_PAY_TO_", 1)
+}
+
+pub fn tokens_env_name_from_network(network: Network) -> String {
+    rpc_env_name_from_network(network).replacen("RPC_URL_", "TOKENS_", 1)
+}
+
+/// Comma-separated token contracts (or SPL mints) accepted on `network`, from `TOKENS_<NETWORK>`; any if unset.
+pub fn tokens_from_env(network: Network) -> Result<Option<Vec<MixedAddress>>, String> {
+    addresses_from_env(&tokens_env_name_from_network(network))
+}
+
+/// Comma-separated recipients whose payments the paymaster of `network` sponsors, from `PAYMASTER_PAY_TO_<NETWORK>`.
+pub fn paymaster_pay_to_from_env(network: Network) -> Result<Option<Vec<MixedAddress>>, String> {
+    addresses_from_env(&paymaster_pay_to_env_name_from_network(network))
+}
+
+fn addresses_from_env(env_var: &str) -> Result<Option<Vec<MixedAddress>>, String> {
+    let Ok(value) = env::var(env_var) else {
+        return Ok(None);
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| {
+            serde_json::from_value(serde_json::Value::String(address.to_string()))
+                .map_err(|e| format!("{env_var}: invalid address {address}: {e}"))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+}
+
+pub const ENV_SETTLEMENT_TAGGING: &str = "SETTLEMENT_TAGGING";
+
+/// Whether `SETTLEMENT_TAGGING` is set to `true` or `1`.
+pub fn settlement_tagging_from_env() -> bool {
+    env::var(ENV_SETTLEMENT_TAGGING)
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "true" | "1"))
+        .unwrap_or(false)
+}
+
+pub const ENV_VERIFY_SIMULATION: &str = "VERIFY_SIMULATION";
+
+/// Whether `VERIFY_SIMULATION` is set to `true` or `1`.
+pub fn verify_simulation_from_env() -> bool {
+    env::var(ENV_VERIFY_SIMULATION)
+        .map(|v| matches!(v.trim().to_ascii_lowercase().as_str(), "true" | "1"))
+        .unwrap_or(false)
+}
+
+pub const ENV_APPROVAL_THRESHOLD: &str = "APPROVAL_THRESHOLD";
+pub const ENV_APPROVAL_SERVICE_URL: &str = "APPROVAL_SERVICE_URL";
+pub const ENV_APPROVAL_CALLBACK_TOKEN: &str = "APPROVAL_CALLBACK_TOKEN";
+pub const ENV_APPROVAL_STORE_DIR: &str = "APPROVAL_STORE_DIR";
+pub const ENV_BUDGET_STORE_DIR: &str = "BUDGET_STORE_DIR";
+
+pub const ENV_BRIDGE_ADAPTER_URL: &str = "BRIDGE_ADAPTER_URL";
+pub const ENV_BRIDGE_ROUTES: &str = "BRIDGE_ROUTES";
+
+pub const ENV_ADMIN_API_KEYS: &str = "ADMIN_API_KEYS";
+
+pub const ENV_IDENTITY_KEY_PATH: &str = "IDENTITY_KEY_PATH";
+
+pub const ENV_OUTBOUND_ALLOWED_SCHEMES: &str = "OUTBOUND_ALLOWED_SCHEMES";
+pub const ENV_OUTBOUND_ALLOWED_CIDRS: &str = "OUTBOUND_ALLOWED_CIDRS";
+
+pub const ENV_VERIFY_TIMEOUT_SECS: &str = "VERIFY_TIMEOUT_SECS";
+pub const ENV_SETTLE_TIMEOUT_SECS: &str = "SETTLE_TIMEOUT_SECS";
+pub const ENV_SETTLE_MODE: &str = "SETTLE_MODE";
+
+pub const ENV_VERIFY_DEDUP_WINDOW_MS: &str = "VERIFY_DEDUP_WINDOW_MS";
+
+pub const ENV_PAYLOAD_STORE_DIR: &str = "PAYLOAD_STORE_DIR";
+pub const ENV_PAYLOAD_STORE_KEY: &str = "PAYLOAD_STORE_KEY";
+pub const ENV_PAYLOAD_STORE_RETENTION_SECS: &str = "PAYLOAD_STORE_RETENTION_SECS";
+pub const ENV_PAYLOAD_STORE_MAX_BYTES: &str = "PAYLOAD_STORE_MAX_BYTES";
+
+pub const ENV_NONCE_RESERVATION_TTL_SECS: &str = "NONCE_RESERVATION_TTL_SECS";
+pub const ENV_NONCE_RESERVATION_MAX: &str = "NONCE_RESERVATION_MAX";
+pub const ENV_NONCE_RESERVATION_MAX_PER_PAYER: &str = "NONCE_RESERVATION_MAX_PER_PAYER";
+pub const ENV_NONCE_RESERVATION_RATE: &str = "NONCE_RESERVATION_RATE";
+pub const ENV_PAYMASTER_SPONSOR_RATE: &str = "PAYMASTER_SPONSOR_RATE";
+pub const ENV_REPLAY_STORE_DIR: &str = "REPLAY_STORE_DIR";
+pub const ENV_REPLAY_REPLICATION_PEERS: &str = "REPLAY_REPLICATION_PEERS";
+pub const ENV_REPLAY_REPLICATION_TOKEN: &str = "REPLAY_REPLICATION_TOKEN";
+
+pub const ENV_WARM_CACHE_TTL_SECS: &str = "WARM_CACHE_TTL_SECS";
+
+pub const ENV_TX_JOURNAL_DIR: &str = "TX_JOURNAL_DIR";
+
+pub const ENV_SPEED_UP_AFTER_SECS: &str = "SPEED_UP_AFTER_SECS";
+pub const ENV_SPEED_UP_BUMP_PERCENT: &str = "SPEED_UP_BUMP_PERCENT";
+pub const ENV_SPEED_UP_MAX_BUMPS: &str = "SPEED_UP_MAX_BUMPS";
+
+pub const ENV_TOKEN_PROBE_INTERVAL_SECS: &str = "TOKEN_PROBE_INTERVAL_SECS";
+
+pub const ENV_REORG_WATCH_BLOCKS: &str = "REORG_WATCH_BLOCKS";
+pub const ENV_REORG_CHECK_INTERVAL_SECS: &str = "REORG_CHECK_INTERVAL_SECS";
+
+pub const ENV_SETTLEMENT_QUEUE_DIR: &str = "SETTLEMENT_QUEUE_DIR";
+pub const ENV_SETTLEMENT_MAX_ATTEMPTS: &str = "SETTLEMENT_MAX_ATTEMPTS";
+pub const ENV_SETTLEMENT_QUEUE_RETENTION_SECS: &str = "SETTLEMENT_QUEUE_RETENTION_SECS";
+pub const ENV_SETTLEMENT_WEBHOOK_URLS: &str = "SETTLEMENT_WEBHOOK_URLS";
+
+pub const ENV_FEE_ON_TRANSFER: &str = "FEE_ON_TRANSFER";
+
+pub const ENV_FEE_BPS: &str = "FEE_BPS";
+pub const ENV_FEE_TIERS: &str = "FEE_TIERS";
+pub const ENV_FEE_PERIOD_SECS: &str = "FEE_PERIOD_SECS";
+pub const ENV_FEE_MODE: &str = "FEE_MODE";
+
+#[cfg(feature = "acme")]
+pub const ENV_ACME_DOMAIN: &str = "ACME_DOMAIN";
+#[cfg(feature = "acme")]
+pub const ENV_ACME_EMAIL: &str = "ACME_EMAIL";
+#[cfg(feature = "acme")]
+pub const ENV_ACME_DIRECTORY_URL: &str = "ACME_DIRECTORY_URL";
+#[cfg(feature = "acme")]
+pub const ENV_ACME_CACHE_DIR: &str = "ACME_CACHE_DIR";
+#[cfg(feature = "acme")]
+pub const ENV_ACME_HTTP_PORT: &str = "ACME_HTTP_PORT";
+
+#[cfg(feature = "plugins")]
+pub const ENV_PLUGIN_PATHS: &str = "PLUGIN_PATHS";
+
+pub const ENV_SETTLEMENT_BATCH_WINDOW_MS: &str = "SETTLEMENT_BATCH_WINDOW_MS";
+
+pub const ENV_CONFIG_FILE: &str = "CONFIG_FILE";
+
+pub const ENV_FACILITATOR_MODE: &str = "FACILITATOR_MODE";
+pub const ENV_ACTIVE_SETTLER_URL: &str = "ACTIVE_SETTLER_URL";
+pub const ENV_DRY_RUN: &str = "DRY_RUN";
+
+pub const ENV_PAYMENT_CONCURRENCY_LIMIT: &str = "PAYMENT_CONCURRENCY_LIMIT";
+pub const ENV_OPS_PORT: &str = "OPS_PORT";
+
+pub const ENV_PRICE_FEED_RPC_URL: &str = "PRICE_FEED_RPC_URL";
+pub const ENV_PRICE_FEEDS: &str = "PRICE_FEEDS";
+pub const ENV_PRICE_API_URL: &str = "PRICE_API_URL";
+pub const ENV_PRICE_TOLERANCE_BPS: &str = "PRICE_TOLERANCE_BPS";
+pub const ENV_PRICE_CACHE_SECS: &str = "PRICE_CACHE_SECS";
+
+pub const ENV_LOCALE_DIR: &str = "LOCALE_DIR";
+
+pub const ENV_MAINTENANCE_WINDOWS: &str = "MAINTENANCE_WINDOWS";
+
+pub const ENV_MIRROR_PORT: &str = "MIRROR_PORT";
+pub const ENV_MIRROR_API_KEYS: &str = "MIRROR_API_KEYS";
+pub const ENV_MIRROR_RETENTION: &str = "MIRROR_RETENTION";
+
+#[cfg(feature = "grpc")]
+pub const ENV_GRPC_PORT: &str = "GRPC_PORT";
+
+pub const ENV_RPC_MAX_RETRIES: &str = "RPC_MAX_RETRIES";
+pub const ENV_RPC_RETRY_INITIAL_BACKOFF_MS: &str = "RPC_RETRY_INITIAL_BACKOFF_MS";
+pub const ENV_RPC_RETRY_MAX_BACKOFF_MS: &str = "RPC_RETRY_MAX_BACKOFF_MS";
+pub const ENV_RPC_MAX_LAG_BLOCKS: &str = "RPC_MAX_LAG_BLOCKS";
+pub const ENV_RPC_FAILOVER_CHECK_INTERVAL_SECS: &str = "RPC_FAILOVER_CHECK_INTERVAL_SECS";
+
+pub const ENV_HEALTH_CHECK_INTERVAL_SECS: &str = "HEALTH_CHECK_INTERVAL_SECS";
+pub const ENV_HEALTH_HISTORY_WINDOW_SECS: &str = "HEALTH_HISTORY_WINDOW_SECS";
+
+pub const ENV_SLO_TARGETS: &str = "SLO_TARGETS";
+pub const ENV_SLO_BURN_RATE_THRESHOLD: &str = "SLO_BURN_RATE_THRESHOLD";
+pub const ENV_SLO_ALERT_WEBHOOK_URL: &str = "SLO_ALERT_WEBHOOK_URL";
+
+pub const ENV_CIRCUIT_BREAKER_THRESHOLD: &str = "CIRCUIT_BREAKER_THRESHOLD";
+pub const ENV_CIRCUIT_BREAKER_COOLDOWN_SECS: &str = "CIRCUIT_BREAKER_COOLDOWN_SECS";
+
+/// `RPC_URL_<NETWORK>`; for a custom network, its name in upper snake case, e.g. `RPC_URL_MY_ROLLUP`.
+pub fn rpc_env_name_from_network(network: Network) -> String {
+    let env_var = match network {
         Network::BaseSepolia => ENV_RPC_BASE_SEPOLIA,
         Network::Base => ENV_RPC_BASE,
         Network::XdcMainnet => ENV_RPC_XDC,
@@ -36,7 +293,23 @@ pub fn rpc_env_name_from_network(network: Network) -> &'static str {
         Network::Polygon => ENV_RPC_POLYGON,
         Network::Sei => ENV_RPC_SEI,
         Network::SeiTestnet => ENV_RPC_SEI_TESTNET,
-    }
+        Network::Arbitrum => ENV_RPC_ARBITRUM,
+        Network::Optimism => ENV_RPC_OPTIMISM,
+        Network::Lightning => ENV_RPC_LIGHTNING,
+        Network::LightningTestnet => ENV_RPC_LIGHTNING_TESTNET,
+        Network::Near => ENV_RPC_NEAR,
+        Network::NearTestnet => ENV_RPC_NEAR_TESTNET,
+        Network::Tron => ENV_RPC_TRON,
+        Network::TronNile => ENV_RPC_TRON_NILE,
+        Network::Custom(_) => {
+            let name = network
+                .to_string()
+                .to_ascii_uppercase()
+                .replace(['-', ':'], "_");
+            return format!("RPC_URL_{name}");
+        }
+    };
+    env_var.to_string()
 }
 
 /// Supported methods for constructing an Ethereum wallet from environment variables.
@@ -68,32 +341,24 @@ impl SignerType {
             SignerType::PrivateKey => {
                 let raw_keys = env::var(ENV_EVM_PRIVATE_KEY)
                     .map_err(|_| format!("env {ENV_EVM_PRIVATE_KEY} not set"))?;
-                let signers = raw_keys
-                    .split(',')
-                    .map(str::trim)
-                    .filter(|entry| !entry.is_empty())
-                    .map(PrivateKeySigner::from_str)
-                    .collect::<Result<Vec<_>, _>>()
-                    .map_err(|err| -> Box<dyn std::error::Error> { Box::new(err) })?;
-                if signers.is_empty() {
-                    return Err("env EVM_PRIVATE_KEY did not contain any private keys".into());
-                }
-
-                let mut iter = signers.into_iter();
-                let first_signer = iter
-                    .next()
-                    .expect("iterator contains at least one element by construction");
-                let mut wallet = EthereumWallet::from(first_signer);
-
-                for signer in iter {
-                    wallet.register_signer(signer);
-                }
-
-                Ok(wallet)
+                make_evm_wallet_from_keys(&raw_keys)
+                    .map_err(|e| format!("env {ENV_EVM_PRIVATE_KEY}: {e}").into())
             }
         }
     }
 
+    /// Signers staged for the next rotation, from `EVM_NEXT_PRIVATE_KEY`. Empty if unset.
+    pub fn make_evm_next_signers(
+        &self,
+    ) -> Result<Vec<PrivateKeySigner>, Box<dyn std::error::Error>> {
+        match self {
+            SignerType::PrivateKey => match env::var(ENV_EVM_NEXT_PRIVATE_KEY) {
+                Ok(raw_keys) => parse_evm_private_keys(&raw_keys),
+                Err(_) => Ok(Vec::new()),
+            },
+        }
+    }
+
     pub fn make_solana_wallet(&self) -> Result<Keypair, Box<dyn std::error::Error>> {
         match self {
             SignerType::PrivateKey => {
@@ -106,6 +371,32 @@ impl SignerType {
     }
 }
 
+/// Constructs an [`EthereumWallet`] from a comma-separated list of private keys.
+pub fn make_evm_wallet_from_keys(
+    raw_keys: &str,
+) -> Result<EthereumWallet, Box<dyn std::error::Error>> {
+    let signers = parse_evm_private_keys(raw_keys)?;
+    let mut iter = signers.into_iter();
+    let first_signer = iter.next().ok_or("no private keys provided")?;
+    let mut wallet = EthereumWallet::from(first_signer);
+    for signer in iter {
+        wallet.register_signer(signer);
+    }
+    Ok(wallet)
+}
+
+fn parse_evm_private_keys(
+    raw_keys: &str,
+) -> Result<Vec<PrivateKeySigner>, Box<dyn std::error::Error>> {
+    raw_keys
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(PrivateKeySigner::from_str)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|err| -> Box<dyn std::error::Error> { Box::new(err) })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,593 @@
+//! gRPC interface of the facilitator, with the `grpc` feature only.
+//!
+//! Infrastructure that standardizes on gRPC internally can reach the facilitator without a JSON client: when
+//! `GRPC_PORT` is set, a separate listener serves the `x402.facilitator.v1.Facilitator` service of
+//! `proto/facilitator.proto`, with `Verify`, `Settle` and `Supported` RPCs. Its messages mirror the version 1 JSON
+//! bodies of `POST /verify`, `POST /settle` and `GET /supported`, with the scheme payload as a typed `oneof` and
+//! free-form objects such as `extra` as `google.protobuf.Struct`s. Requests are converted to the JSON types on
+//! arrival, and go through the same [`Facilitator`] as HTTP requests, with every gate, fee and queue in front of it.
+//!
+//! Rejected payments are answered as the HTTP API answers them: an invalid `VerifyResponse`, or an unsuccessful
+//! `SettleResponse` with its `error_reason`. Failures answered with an HTTP error are answered with the matching gRPC
+//! status: `INVALID_ARGUMENT` for `400`, `UNAVAILABLE` for `502` and `503`, `DEADLINE_EXCEEDED` for `504`.
+//!
+//! The listener is plaintext and unauthenticated, for use within a private network or behind a mesh.
+//!
+//! Environment variables used:
+//! - `GRPC_PORT` — port of the gRPC listener, bound on `HOST`. Disabled if unset.
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use prost_types::value::Kind;
+use serde::Serialize;
+use serde_json::json;
+use std::net::{IpAddr, SocketAddr};
+use tokio_stream::wrappers::TcpListenerStream;
+use tokio_util::sync::CancellationToken;
+use tonic::{Code, Request, Response, Status};
+
+use crate::build_info::BuildInfo;
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::types::{
+    ErrorResponse, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+    X402Version,
+};
+
+/// Messages and service generated from `proto/facilitator.proto`.
+pub mod proto {
+    tonic::include_proto!("x402.facilitator.v1");
+}
+
+use proto::facilitator_server::{Facilitator as FacilitatorRpc, FacilitatorServer};
+use proto::payment_payload::Payload;
+
+/// Encoding of `value` in the JSON API, unquoted if it is a string.
+fn json_string<T: Serialize>(value: &T) -> String {
+    match serde_json::to_value(value) {
+        Ok(serde_json::Value::String(string)) => string,
+        Ok(other) => other.to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// `field` of a request, which protobuf leaves optional.
+fn required<T>(field: &str, value: Option<T>) -> Result<T, Status> {
+    value.ok_or_else(|| Status::invalid_argument(format!("{field} is required")))
+}
+
+/// JSON body of `payload` in the JSON API, as the `payload` field of a payment payload.
+fn payload_json(payload: Payload) -> Result<serde_json::Value, Status> {
+    let json = match payload {
+        Payload::ExactEvm(payload) => {
+            let authorization = required(
+                "payment_payload.exact_evm.authorization",
+                payload.authorization,
+            )?;
+            json!({
+                "signature": payload.signature,
+                "authorization": {
+                    "from": authorization.from,
+                    "to": authorization.to,
+                    "value": authorization.value,
+                    "validAfter": authorization.valid_after.to_string(),
+                    "validBefore": authorization.valid_before.to_string(),
+                    "nonce": authorization.nonce,
+                },
+            })
+        }
+        Payload::Tron(payload) => {
+            let authorization =
+                required("payment_payload.tron.authorization", payload.authorization)?;
+            json!({
+                "signature": payload.signature,
+                "authorization": {
+                    "from": authorization.from,
+                    "to": authorization.to,
+                    "value": authorization.value,
+                    "validAfter": authorization.valid_after.to_string(),
+                    "validBefore": authorization.valid_before.to_string(),
+                    "nonce": authorization.nonce,
+                },
+            })
+        }
+        Payload::Permit2(payload) => {
+            let permit = required("payment_payload.permit2.permit", payload.permit)?;
+            let permitted = required("payment_payload.permit2.permit.permitted", permit.permitted)?;
+            let witness = required("payment_payload.permit2.witness", payload.witness)?;
+            json!({
+                "signature": payload.signature,
+                "owner": payload.owner,
+                "permit": {
+                    "permitted": {
+                        "token": permitted.token,
+                        "amount": permitted.amount,
+                    },
+                    "spender": permit.spender,
+                    "nonce": permit.nonce,
+                    "deadline": permit.deadline.to_string(),
+                },
+                "witness": {
+                    "to": witness.to,
+                },
+            })
+        }
+        Payload::Permit(payload) => {
+            let permit = required("payment_payload.permit.permit", payload.permit)?;
+            json!({
+                "signature": payload.signature,
+                "permit": {
+                    "owner": permit.owner,
+                    "spender": permit.spender,
+                    "value": permit.value,
+                    "nonce": permit.nonce,
+                    "deadline": permit.deadline.to_string(),
+                },
+            })
+        }
+        Payload::Native(payload) => json!({
+            "signedTransaction": payload.signed_transaction,
+        }),
+        Payload::UserOperation(payload) => {
+            let operation = required(
+                "payment_payload.user_operation.user_operation",
+                payload.user_operation,
+            )?;
+            json!({
+                "userOperation": {
+                    "sender": operation.sender,
+                    "nonce": operation.nonce,
+                    "factory": operation.factory,
+                    "factoryData": operation.factory_data,
+                    "callData": operation.call_data,
+                    "callGasLimit": operation.call_gas_limit,
+                    "verificationGasLimit": operation.verification_gas_limit,
+                    "preVerificationGas": operation.pre_verification_gas,
+                    "maxFeePerGas": operation.max_fee_per_gas,
+                    "maxPriorityFeePerGas": operation.max_priority_fee_per_gas,
+                    "paymaster": operation.paymaster,
+                    "paymasterVerificationGasLimit": operation.paymaster_verification_gas_limit,
+                    "paymasterPostOpGasLimit": operation.paymaster_post_op_gas_limit,
+                    "paymasterData": operation.paymaster_data,
+                    "signature": operation.signature,
+                },
+                "entryPoint": payload.entry_point,
+            })
+        }
+        Payload::Stream(payload) => json!({
+            "sender": payload.sender,
+            "signature": payload.signature,
+            "validBefore": payload.valid_before.to_string(),
+        }),
+        Payload::Lightning(payload) => json!({
+            "invoice": payload.invoice,
+            "preimage": payload.preimage,
+        }),
+        Payload::Near(payload) => json!({
+            "signedDelegateAction": payload.signed_delegate_action,
+        }),
+        Payload::Solana(payload) => json!({
+            "transaction": payload.transaction,
+        }),
+    };
+    Ok(json)
+}
+
+/// `value` as a protobuf `Struct`, if it is a JSON object.
+fn to_struct(value: serde_json::Value) -> Option<prost_types::Struct> {
+    match to_proto_value(value).kind {
+        Some(Kind::StructValue(fields)) => Some(fields),
+        _ => None,
+    }
+}
+
+fn to_proto_value(value: serde_json::Value) -> prost_types::Value {
+    let kind = match value {
+        serde_json::Value::Null => Kind::NullValue(prost_types::NullValue::NullValue as i32),
+        serde_json::Value::Bool(value) => Kind::BoolValue(value),
+        serde_json::Value::Number(value) => Kind::NumberValue(value.as_f64().unwrap_or_default()),
+        serde_json::Value::String(value) => Kind::StringValue(value),
+        serde_json::Value::Array(values) => Kind::ListValue(prost_types::ListValue {
+            values: values.into_iter().map(to_proto_value).collect(),
+        }),
+        serde_json::Value::Object(fields) => Kind::StructValue(prost_types::Struct {
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| (name, to_proto_value(value)))
+                .collect(),
+        }),
+    };
+    prost_types::Value { kind: Some(kind) }
+}
+
+/// JSON object of a protobuf `Struct`.
+fn from_struct(fields: prost_types::Struct) -> serde_json::Value {
+    serde_json::Value::Object(
+        fields
+            .fields
+            .into_iter()
+            .map(|(name, value)| (name, from_proto_value(value)))
+            .collect(),
+    )
+}
+
+/// JSON of a protobuf `Value`. Protobuf numbers are all doubles: integral ones are converted back to JSON integers,
+/// for fields such as `extra.decimals`.
+fn from_proto_value(value: prost_types::Value) -> serde_json::Value {
+    match value.kind {
+        None | Some(Kind::NullValue(_)) => serde_json::Value::Null,
+        Some(Kind::BoolValue(value)) => serde_json::Value::Bool(value),
+        Some(Kind::NumberValue(value)) if value.fract() == 0.0 && value.abs() < 2f64.powi(53) => {
+            serde_json::Value::from(value as i64)
+        }
+        Some(Kind::NumberValue(value)) => serde_json::Number::from_f64(value)
+            .map(serde_json::Value::Number)
+            .unwrap_or_default(),
+        Some(Kind::StringValue(value)) => serde_json::Value::String(value),
+        Some(Kind::ListValue(list)) => {
+            serde_json::Value::Array(list.values.into_iter().map(from_proto_value).collect())
+        }
+        Some(Kind::StructValue(fields)) => from_struct(fields),
+    }
+}
+
+fn version_number(version: X402Version) -> u32 {
+    match version {
+        X402Version::V1 => 1,
+        X402Version::V2 => 2,
+    }
+}
+
+impl TryFrom<proto::VerifyRequest> for VerifyRequest {
+    type Error = Status;
+
+    /// Converts through the JSON body of `POST /verify`, so that the request is checked as an HTTP request is.
+    fn try_from(request: proto::VerifyRequest) -> Result<Self, Self::Error> {
+        let payload = required("payment_payload", request.payment_payload)?;
+        let requirements = required("payment_requirements", request.payment_requirements)?;
+        let body = json!({
+            "x402Version": request.x402_version,
+            "paymentPayload": {
+                "x402Version": payload.x402_version,
+                "scheme": payload.scheme,
+                "network": payload.network,
+                "payload": payload_json(required("payment_payload.payload", payload.payload)?)?,
+                "paymentId": payload.payment_id,
+            },
+            "paymentRequirements": {
+                "scheme": requirements.scheme,
+                "network": requirements.network,
+                "maxAmountRequired": requirements.max_amount_required,
+                "resource": requirements.resource,
+                "description": requirements.description,
+                "mimeType": requirements.mime_type,
+                "outputSchema": requirements.output_schema.map(from_struct),
+                "payTo": requirements.pay_to,
+                "maxTimeoutSeconds": requirements.max_timeout_seconds,
+                "asset": requirements.asset,
+                "extra": requirements.extra.map(from_struct),
+                "paymentId": requirements.payment_id,
+                "priceUsd": requirements.price_usd,
+                "facilitatorFee": requirements.facilitator_fee,
+            },
+            "settleAmount": request.settle_amount,
+            "allowPartial": request.allow_partial,
+            "executeAfter": request.execute_after.map(|seconds| seconds.to_string()),
+        });
+        serde_json::from_value(body).map_err(|e| Status::invalid_argument(e.to_string()))
+    }
+}
+
+impl From<VerifyResponse> for proto::VerifyResponse {
+    fn from(response: VerifyResponse) -> Self {
+        match response {
+            VerifyResponse::Valid {
+                payer,
+                received_amount,
+            } => Self {
+                is_valid: true,
+                payer: Some(json_string(&payer)),
+                invalid_reason: None,
+                received_amount: received_amount.as_ref().map(json_string),
+            },
+            VerifyResponse::Invalid { reason, payer } => Self {
+                is_valid: false,
+                payer: payer.as_ref().map(json_string),
+                invalid_reason: Some(json_string(&reason)),
+                received_amount: None,
+            },
+        }
+    }
+}
+
+impl From<SettleResponse> for proto::SettleResponse {
+    fn from(response: SettleResponse) -> Self {
+        Self {
+            success: response.success,
+            error_reason: response.error_reason.as_ref().map(json_string),
+            payer: response.payer.as_ref().map(json_string),
+            transaction: response.transaction.as_ref().map(json_string),
+            network: json_string(&response.network),
+            facilitator_version: response.facilitator_version,
+            batch: response.batch.map(|batch| proto::BatchPosition {
+                index: batch.index,
+                size: batch.size,
+            }),
+            payment_id: response.payment_id.as_ref().map(json_string),
+            partial: response.partial.map(|partial| proto::PartialSettlement {
+                settled_amount: json_string(&partial.settled_amount),
+                shortfall: json_string(&partial.shortfall),
+            }),
+            revert_reason: response.revert_reason,
+            signer: response.signer.as_ref().map(json_string),
+            settlement_id: response.settlement_id,
+            simulated: response.simulated,
+        }
+    }
+}
+
+impl From<SupportedPaymentKindsResponse> for proto::SupportedResponse {
+    fn from(response: SupportedPaymentKindsResponse) -> Self {
+        Self {
+            kinds: response
+                .kinds
+                .into_iter()
+                .map(|kind| proto::SupportedPaymentKind {
+                    x402_version: version_number(kind.x402_version),
+                    scheme: json_string(&kind.scheme),
+                    network: kind.network,
+                    extra: kind
+                        .extra
+                        .and_then(|extra| serde_json::to_value(extra).ok())
+                        .and_then(to_struct),
+                })
+                .collect(),
+            routes: response
+                .routes
+                .into_iter()
+                .map(|route| proto::NetworkRoute {
+                    source: json_string(&route.source),
+                    destination: json_string(&route.destination),
+                })
+                .collect(),
+            maintenance: response
+                .maintenance
+                .into_iter()
+                .map(|window| proto::MaintenanceWindow {
+                    start: window.start.seconds_since_epoch(),
+                    end: window.end.seconds_since_epoch(),
+                    network: window.network.as_ref().map(json_string),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Answer of the HTTP API to `error`: the invalid [`VerifyResponse`] it is answered with, or the [`Status`]
+/// matching its HTTP error.
+async fn rejection(error: FacilitatorLocalError) -> Result<VerifyResponse, Status> {
+    let response = error.into_response();
+    let status = response.status();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?;
+    if status == StatusCode::OK {
+        return serde_json::from_slice(&body).map_err(|e| Status::internal(e.to_string()));
+    }
+    let message = serde_json::from_slice::<ErrorResponse>(&body)
+        .map(|response| response.error)
+        .unwrap_or_else(|_| status.to_string());
+    let code = match status {
+        StatusCode::BAD_REQUEST => Code::InvalidArgument,
+        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE => Code::Unavailable,
+        StatusCode::GATEWAY_TIMEOUT => Code::DeadlineExceeded,
+        _ => Code::Internal,
+    };
+    Err(Status::new(code, message))
+}
+
+/// The `Facilitator` gRPC service over a [`Facilitator`].
+pub struct FacilitatorService<A> {
+    facilitator: A,
+}
+
+impl<A> FacilitatorService<A> {
+    pub fn new(facilitator: A) -> Self {
+        Self { facilitator }
+    }
+}
+
+#[tonic::async_trait]
+impl<A> FacilitatorRpc for FacilitatorService<A>
+where
+    A: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    async fn verify(
+        &self,
+        request: Request<proto::VerifyRequest>,
+    ) -> Result<Response<proto::VerifyResponse>, Status> {
+        let request = VerifyRequest::try_from(request.into_inner())?;
+        let response = match self.facilitator.verify(&request).await {
+            Ok(response) => response,
+            Err(error) => {
+                tracing::warn!(error = ?error, "Verification over gRPC failed");
+                rejection(error).await?
+            }
+        };
+        Ok(Response::new(response.into()))
+    }
+
+    async fn settle(
+        &self,
+        request: Request<proto::VerifyRequest>,
+    ) -> Result<Response<proto::SettleResponse>, Status> {
+        let request = VerifyRequest::try_from(request.into_inner())?;
+        let response = match self.facilitator.settle(&request).await {
+            Ok(mut response) => {
+                response.facilitator_version = Some(BuildInfo::current().version_tag());
+                response.payment_id = request.payment_id().cloned();
+                response.into()
+            }
+            Err(error) => {
+                tracing::warn!(error = ?error, "Settlement over gRPC failed");
+                let (reason, payer) = match rejection(error).await? {
+                    VerifyResponse::Invalid { reason, payer } => (reason, payer),
+                    VerifyResponse::Valid { .. } => {
+                        return Err(Status::internal("settlement failed"));
+                    }
+                };
+                proto::SettleResponse {
+                    success: false,
+                    error_reason: Some(json_string(&reason)),
+                    payer: payer.as_ref().map(json_string),
+                    network: json_string(&request.network()),
+                    facilitator_version: Some(BuildInfo::current().version_tag()),
+                    payment_id: request.payment_id().map(json_string),
+                    ..Default::default()
+                }
+            }
+        };
+        Ok(Response::new(response))
+    }
+
+    async fn supported(
+        &self,
+        _request: Request<proto::SupportedRequest>,
+    ) -> Result<Response<proto::SupportedResponse>, Status> {
+        match self.facilitator.supported().await {
+            Ok(response) => Ok(Response::new(response.into())),
+            Err(error) => Err(rejection(error)
+                .await
+                .err()
+                .unwrap_or_else(|| Status::internal("failed to list supported payment kinds"))),
+        }
+    }
+}
+
+/// Listener serving the [`FacilitatorService`].
+pub struct GrpcServer {
+    addr: SocketAddr,
+}
+
+impl GrpcServer {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
+
+    /// Listener on `GRPC_PORT` of `host`; `Ok(None)` if `GRPC_PORT` is unset.
+    pub fn from_env(host: IpAddr) -> Result<Option<Self>, String> {
+        let Ok(port) = std::env::var(from_env::ENV_GRPC_PORT) else {
+            return Ok(None);
+        };
+        let port = port
+            .parse::<u16>()
+            .map_err(|e| format!("{}: {e}", from_env::ENV_GRPC_PORT))?;
+        Ok(Some(Self::new(SocketAddr::new(host, port))))
+    }
+
+    /// Binds the listener and serves `facilitator` until `cancellation_token` is cancelled.
+    pub async fn spawn<A>(
+        self,
+        facilitator: A,
+        cancellation_token: CancellationToken,
+    ) -> std::io::Result<()>
+    where
+        A: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+    {
+        let listener = tokio::net::TcpListener::bind(self.addr).await?;
+        tracing::info!("Starting gRPC server at {}", self.addr);
+        let service = FacilitatorServer::new(FacilitatorService::new(facilitator));
+        tokio::spawn(async move {
+            let shutdown = async move { cancellation_token.cancelled().await };
+            if let Err(e) = tonic::transport::Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(TcpListenerStream::new(listener), shutdown)
+                .await
+            {
+                tracing::error!("gRPC server failed: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_requests_through_the_json_types() {
+        let request = proto::VerifyRequest {
+            x402_version: 1,
+            payment_payload: Some(proto::PaymentPayload {
+                x402_version: 1,
+                scheme: "exact".to_string(),
+                network: "base".to_string(),
+                payment_id: None,
+                payload: Some(Payload::Solana(proto::ExactSolanaPayload {
+                    transaction: "AA==".to_string(),
+                })),
+            }),
+            payment_requirements: Some(proto::PaymentRequirements {
+                scheme: "exact".to_string(),
+                network: "base".to_string(),
+                max_amount_required: "1000".to_string(),
+                resource: "https://example.com/weather".to_string(),
+                mime_type: "application/json".to_string(),
+                pay_to: "0x2222222222222222222222222222222222222222".to_string(),
+                max_timeout_seconds: 60,
+                asset: "0x0000000000000000000000000000000000000001".to_string(),
+                ..Default::default()
+            }),
+            settle_amount: None,
+            allow_partial: false,
+            execute_after: Some(1_900_000_000),
+        };
+        let converted = VerifyRequest::try_from(request.clone()).unwrap();
+        assert_eq!(
+            converted.execute_after.map(|t| t.seconds_since_epoch()),
+            Some(1_900_000_000)
+        );
+        assert_eq!(
+            json_string(&converted.payment_requirements.max_amount_required),
+            "1000"
+        );
+
+        let invalid = proto::VerifyRequest {
+            payment_payload: None,
+            ..request
+        };
+        let status = VerifyRequest::try_from(invalid).unwrap_err();
+        assert_eq!(status.code(), Code::InvalidArgument);
+
+        let response = proto::VerifyResponse::from(VerifyResponse::invalid(
+            None,
+            crate::types::FacilitatorErrorReason::InsufficientFunds,
+        ));
+        assert!(!response.is_valid);
+        assert_eq!(
+            response.invalid_reason.as_deref(),
+            Some("insufficient_funds")
+        );
+    }
+
+    #[test]
+    fn converts_objects_to_and_from_structs() {
+        let extra =
+            json!({"name": "USD Coin", "decimals": 6, "rate": 0.5, "tags": ["stable", null]});
+        let converted = to_struct(extra.clone()).unwrap();
+        assert_eq!(from_struct(converted), extra);
+        assert!(to_struct(json!("not an object")).is_none());
+
+        let status = payload_json(Payload::ExactEvm(proto::ExactEvmPayload {
+            signature: "0x00".to_string(),
+            authorization: None,
+        }))
+        .unwrap_err();
+        assert_eq!(
+            status.message(),
+            "payment_payload.exact_evm.authorization is required"
+        );
+    }
+}
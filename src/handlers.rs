@@ -7,70 +7,387 @@
 //! with the TypeScript and Go client SDKs.
 //!
 //! Each endpoint consumes or produces structured JSON payloads defined in `x402-rs`,
-//! and is compatible with official x402 client SDKs.
+//! and is compatible with official x402 client SDKs. The protocol endpoints also accept and produce
+//! CBOR and MessagePack (see [`codec`]).
+//!
+//! The protocol endpoints are served under `/v1`, e.g. `POST /v1/verify`, and under their unversioned legacy paths
+//! as aliases (see [`ApiVersion`]).
 
-use axum::extract::State;
-use axum::http::StatusCode;
+use alloy::hex;
+use axum::extract::{FromRequestParts, Request, State};
+use axum::http::request::Parts;
+use axum::http::{HeaderMap, HeaderValue, Method, StatusCode, header};
+use axum::middleware::{self, Next};
 use axum::response::Response;
 use axum::routing::{get, post};
-use axum::{Json, Router, response::IntoResponse};
+use axum::{Extension, Json, Router, response::IntoResponse};
 use serde_json::json;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::Semaphore;
 use tracing::instrument;
+use url::Url;
 
+use crate::build_info::BuildInfo;
 use crate::chain::FacilitatorLocalError;
+use crate::codec;
+use crate::docs;
 use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::identity::{FacilitatorIdentity, IdentityError};
+use crate::openapi;
 use crate::types::{
-    ErrorResponse, FacilitatorErrorReason, MixedAddress, SettleRequest, VerifyRequest,
-    VerifyResponse,
+    ErrorResponse, FacilitatorErrorReason, MixedAddress, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse, X402Version,
 };
+use crate::webhooks::{IDENTITY_HEADER, SIGNATURE_HEADER};
 
-/// `GET /verify`: Returns a machine-readable description of the `/verify` endpoint.
+/// `GET /verify`: Returns the OpenAPI description of the `/verify` endpoint.
 ///
-/// This is served by the facilitator to help clients understand how to construct
+/// This is the `/verify` entry of `GET /openapi.json`, to help clients understand how to construct
 /// a valid [`VerifyRequest`] for payment verification.
-///
-/// This is optional metadata and primarily useful for discoverability and debugging tools.
 #[instrument(skip_all)]
 pub async fn get_verify_info() -> impl IntoResponse {
-    Json(json!({
-        "endpoint": "/verify",
-        "description": "POST to verify x402 payments",
-        "body": {
-            "paymentPayload": "PaymentPayload",
-            "paymentRequirements": "PaymentRequirements",
-        }
-    }))
+    Json(openapi::path_item("/verify"))
 }
 
-/// `GET /settle`: Returns a machine-readable description of the `/settle` endpoint.
+/// `GET /settle`: Returns the OpenAPI description of the `/settle` endpoint.
 ///
-/// This is served by the facilitator to describe the structure of a valid
+/// This is the `/settle` entry of `GET /openapi.json`, describing the structure of a valid
 /// [`SettleRequest`] used to initiate on-chain payment settlement.
 #[instrument(skip_all)]
 pub async fn get_settle_info() -> impl IntoResponse {
-    Json(json!({
-        "endpoint": "/settle",
-        "description": "POST to settle x402 payments",
-        "body": {
-            "paymentPayload": "PaymentPayload",
-            "paymentRequirements": "PaymentRequirements",
+    Json(openapi::path_item("/settle"))
+}
+
+/// Time limits of the protocol-critical endpoints.
+///
+/// A request exceeding its limit is answered with `504 Gateway Timeout`. The handler future is dropped
+/// at that point, as it is when the client disconnects, which cancels any RPC call still in flight.
+/// A settlement transaction that was already broadcast is not reverted by this.
+#[derive(Debug, Clone, Copy)]
+pub struct RequestTimeouts {
+    pub verify: Duration,
+    pub settle: Duration,
+}
+
+impl Default for RequestTimeouts {
+    fn default() -> Self {
+        Self {
+            verify: Duration::from_secs(10),
+            settle: Duration::from_secs(90),
+        }
+    }
+}
+
+impl RequestTimeouts {
+    /// Reads `VERIFY_TIMEOUT_SECS` and `SETTLE_TIMEOUT_SECS`, falling back to 10 and 90 seconds.
+    pub fn from_env() -> Self {
+        let secs = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs)
+        };
+        let defaults = Self::default();
+        Self {
+            verify: secs(from_env::ENV_VERIFY_TIMEOUT_SECS).unwrap_or(defaults.verify),
+            settle: secs(from_env::ENV_SETTLE_TIMEOUT_SECS).unwrap_or(defaults.settle),
+        }
+    }
+}
+
+async fn with_timeout(State(timeout): State<Duration>, request: Request, next: Next) -> Response {
+    let path = request.uri().path().to_string();
+    match tokio::time::timeout(timeout, next.run(request)).await {
+        Ok(response) => response,
+        Err(_) => {
+            tracing::warn!(
+                monotonic_counter.x402.http.timeouts = 1u64,
+                path = %path,
+                timeout = ?timeout,
+                "Request timed out"
+            );
+            (
+                StatusCode::GATEWAY_TIMEOUT,
+                Json(ErrorResponse {
+                    error: "Request timed out".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+/// How `POST /settle` answers.
+///
+/// In `Sync` mode, the default, a settlement is answered once its transaction is mined. In `Async` mode, or when the
+/// request carries `Prefer: respond-async`, it is answered with `202 Accepted` as soon as the settlement queue takes
+/// it, with the `settlementId` to follow it at `GET /settlements/{id}`, also given in the `Location` header.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SettleMode {
+    #[default]
+    Sync,
+    Async,
+}
+
+impl SettleMode {
+    /// Reads `SETTLE_MODE` (`sync` or `async`, default `sync`).
+    pub fn from_env() -> Result<Self, String> {
+        let mode = std::env::var(from_env::ENV_SETTLE_MODE).unwrap_or_default();
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "" | "sync" => Ok(SettleMode::Sync),
+            "async" => Ok(SettleMode::Async),
+            other => Err(format!(
+                "Invalid {}: {other}, expected sync or async",
+                from_env::ENV_SETTLE_MODE
+            )),
+        }
+    }
+
+    /// The mode of a request with `headers`: `Async` if the request prefers so.
+    fn of_request(self, headers: &HeaderMap) -> Self {
+        let respond_async = headers
+            .get_all("prefer")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .any(|preference| preference.trim().eq_ignore_ascii_case("respond-async"));
+        match respond_async {
+            true => SettleMode::Async,
+            false => self,
+        }
+    }
+}
+
+/// Version of the API a request was routed by, from the prefix of its path.
+///
+/// [`routes`] serves the protocol endpoints under `/v1`, and at the root for clients predating versioned paths. Both
+/// answer alike today; a breaking change of the payloads would be served under a new prefix, with handlers telling
+/// the versions apart by this marker. Requests routed elsewhere are [`ApiVersion::Unversioned`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// Legacy paths without a version prefix, answered as `/v1`.
+    #[default]
+    Unversioned,
+    /// Paths under `/v1`.
+    V1,
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for ApiVersion {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        Ok(parts
+            .extensions
+            .get::<ApiVersion>()
+            .copied()
+            .unwrap_or_default())
+    }
+}
+
+/// Whether this node settles payments.
+///
+/// A `Standby` node is a warm replica of an active/passive pair: it serves `/verify`, `/supported` and
+/// read APIs, but never broadcasts. Settlement requests are answered with `307 Temporary Redirect` to the
+/// active settler if its URL is known, or `503 Service Unavailable` otherwise.
+#[derive(Debug, Clone, Default)]
+pub enum RunMode {
+    #[default]
+    Active,
+    Standby {
+        active_settler: Option<Url>,
+    },
+}
+
+impl RunMode {
+    /// Reads `FACILITATOR_MODE` (`active` or `standby`, default `active`) and `ACTIVE_SETTLER_URL`.
+    pub fn from_env() -> Result<Self, String> {
+        let mode = std::env::var(from_env::ENV_FACILITATOR_MODE).unwrap_or_default();
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "" | "active" => Ok(RunMode::Active),
+            "standby" => {
+                let active_settler = match std::env::var(from_env::ENV_ACTIVE_SETTLER_URL) {
+                    Ok(url) => Some(Url::parse(&url).map_err(|e| {
+                        format!("Invalid {}: {e}", from_env::ENV_ACTIVE_SETTLER_URL)
+                    })?),
+                    Err(_) => None,
+                };
+                Ok(RunMode::Standby { active_settler })
+            }
+            other => Err(format!(
+                "Invalid {}: {other}, expected active or standby",
+                from_env::ENV_FACILITATOR_MODE
+            )),
+        }
+    }
+}
+
+/// Refuses settling requests on a standby node, pointing to the active settler.
+///
+/// Layered on every route that may broadcast a transaction; other methods than `POST` pass through.
+pub async fn standby_guard(State(mode): State<RunMode>, request: Request, next: Next) -> Response {
+    let RunMode::Standby { active_settler } = mode else {
+        return next.run(request).await;
+    };
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    tracing::info!(
+        monotonic_counter.x402.http.standby_rejections = 1u64,
+        path = %request.uri().path(),
+        "Settlement refused on standby node"
+    );
+    let target = active_settler.and_then(|base| {
+        let path_and_query = request
+            .uri()
+            .path_and_query()
+            .map_or(request.uri().path(), |pq| pq.as_str());
+        base.join(path_and_query.trim_start_matches('/')).ok()
+    });
+    match target {
+        Some(target) => (
+            StatusCode::TEMPORARY_REDIRECT,
+            [(header::LOCATION, target.to_string())],
+            Json(ErrorResponse {
+                error: format!("Standby node does not settle, use {target}"),
+            }),
+        )
+            .into_response(),
+        None => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Standby node does not settle".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Bound on the number of payment requests (`POST /verify`, `POST /settle`) processed at once.
+///
+/// Requests over the bound are rejected right away with `503 Service Unavailable` rather than queued,
+/// so a traffic spike can not starve health checks and in-flight settlements of the same runtime.
+#[derive(Debug, Clone, Default)]
+pub struct LoadShedder {
+    permits: Option<Arc<Semaphore>>,
+}
+
+impl LoadShedder {
+    /// Sheds load above `limit` concurrent requests; unbounded if `None`.
+    pub fn new(limit: Option<usize>) -> Self {
+        Self {
+            permits: limit.map(|limit| Arc::new(Semaphore::new(limit))),
         }
-    }))
+    }
+
+    /// Reads the limit from `PAYMENT_CONCURRENCY_LIMIT`; unbounded if unset.
+    pub fn from_env() -> Self {
+        let limit = std::env::var(from_env::ENV_PAYMENT_CONCURRENCY_LIMIT)
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .filter(|limit| *limit > 0);
+        Self::new(limit)
+    }
+}
+
+async fn shed_load(State(shedder): State<LoadShedder>, request: Request, next: Next) -> Response {
+    let Some(permits) = shedder.permits else {
+        return next.run(request).await;
+    };
+    match permits.try_acquire_owned() {
+        Ok(_permit) => next.run(request).await,
+        Err(_) => {
+            tracing::warn!(
+                monotonic_counter.x402.http.shed = 1u64,
+                path = %request.uri().path(),
+                "Payment request shed"
+            );
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(header::RETRY_AFTER, "1")],
+                Json(ErrorResponse {
+                    error: "Facilitator overloaded".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
 }
 
-pub fn routes<A>() -> Router<A>
+pub fn routes<A>(
+    timeouts: RequestTimeouts,
+    shedder: LoadShedder,
+    mode: RunMode,
+    settle_mode: SettleMode,
+    identity: FacilitatorIdentity,
+) -> Router<A>
 where
     A: Facilitator + Clone + Send + Sync + 'static,
     A::Error: IntoResponse,
 {
-    Router::new()
+    let protocol = Router::new()
         .route("/", get(get_root))
         .route("/verify", get(get_verify_info))
-        .route("/verify", post(post_verify::<A>))
+        .route(
+            "/verify",
+            post(post_verify::<A>)
+                .layer(middleware::from_fn_with_state(
+                    timeouts.verify,
+                    with_timeout,
+                ))
+                .layer(middleware::from_fn_with_state(shedder.clone(), shed_load)),
+        )
         .route("/settle", get(get_settle_info))
-        .route("/settle", post(post_settle::<A>))
-        .route("/health", get(get_health::<A>))
+        .route(
+            "/settle",
+            post(post_settle::<A>)
+                .layer(Extension(settle_mode))
+                .layer(middleware::from_fn_with_state(
+                    timeouts.settle,
+                    with_timeout,
+                ))
+                .layer(middleware::from_fn_with_state(shedder, shed_load))
+                .layer(middleware::from_fn_with_state(mode, standby_guard)),
+        )
         .route("/supported", get(get_supported::<A>))
+        .route("/docs", get(docs::get_docs::<A>))
+        .route("/openapi.json", get(openapi::get_openapi))
+        .merge(swagger_ui_routes())
+        .layer(middleware::from_fn(codec::negotiate));
+    Router::new()
+        .merge(protocol.clone().layer(Extension(ApiVersion::Unversioned)))
+        .nest("/v1", protocol.layer(Extension(ApiVersion::V1)))
+        .merge(ops_routes(identity))
+}
+
+/// Swagger UI route, with the `swagger-ui` feature.
+fn swagger_ui_routes<A>() -> Router<A>
+where
+    A: Clone + Send + Sync + 'static,
+{
+    #[cfg(feature = "swagger-ui")]
+    {
+        Router::new().route("/swagger-ui", get(openapi::get_swagger_ui))
+    }
+    #[cfg(not(feature = "swagger-ui"))]
+    {
+        Router::new()
+    }
+}
+
+/// Liveness and version routes, also served by the [`crate::ops::OpsServer`] listener; `/version` is signed by
+/// `identity`.
+pub fn ops_routes<A>(identity: FacilitatorIdentity) -> Router<A>
+where
+    A: Facilitator + Clone + Send + Sync + 'static,
+    A::Error: IntoResponse,
+{
+    Router::new()
+        .route("/health", get(get_health::<A>))
+        .route("/version", get(get_version).layer(Extension(identity)))
 }
 
 /// `GET /`: Returns a simple greeting message from the facilitator.
@@ -80,10 +397,58 @@ pub async fn get_root() -> impl IntoResponse {
     (StatusCode::OK, format!("Hello from {pkg_name}!"))
 }
 
+/// `GET /version`: Returns the facilitator build metadata as a [`BuildInfo`].
+///
+/// Includes crate version, git commit, build timestamp, enabled features and supported x402 protocol versions,
+/// which helps to diagnose incompatibilities between facilitator and client versions. The body is signed by the
+/// identity key like webhooks are, so that clients can tell which facilitator answered.
+#[instrument(skip_all)]
+pub async fn get_version(Extension(identity): Extension<FacilitatorIdentity>) -> Response {
+    let signed = serde_json::to_vec(&BuildInfo::current())
+        .map_err(IdentityError::from)
+        .and_then(|body| Ok((identity.sign(&body)?, body)));
+    match signed {
+        Ok((signature, body)) => (
+            StatusCode::OK,
+            [
+                (
+                    header::CONTENT_TYPE.as_str(),
+                    "application/json".to_string(),
+                ),
+                (
+                    SIGNATURE_HEADER,
+                    format!("0x{}", hex::encode(&signature.signature.0)),
+                ),
+                (IDENTITY_HEADER, signature.address.to_string()),
+            ],
+            body,
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to sign build metadata");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to sign build metadata".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
 /// `GET /supported`: Lists the x402 payment schemes and networks supported by this facilitator.
 ///
 /// Facilitators may expose this to help clients dynamically configure their payment requests
 /// based on available network and scheme support.
+#[utoipa::path(
+    get,
+    path = "/supported",
+    responses(
+        (status = 200, description = "Supported kinds", body = SupportedPaymentKindsResponse),
+        (status = 502, description = "An RPC node could not be reached", body = ErrorResponse),
+    )
+)]
 #[instrument(skip_all)]
 pub async fn get_supported<A>(State(facilitator): State<A>) -> impl IntoResponse
 where
@@ -91,7 +456,7 @@ where
     A::Error: IntoResponse,
 {
     match facilitator.supported().await {
-        Ok(supported) => (StatusCode::OK, Json(json!(supported))).into_response(),
+        Ok(supported) => (StatusCode::OK, Json(json!(supported.with_v2_kinds()))).into_response(),
         Err(error) => error.into_response(),
     }
 }
@@ -111,15 +476,31 @@ where
 /// [`PaymentRequirements`], including signature validity, scheme match, and fund sufficiency.
 ///
 /// Responds with a [`VerifyResponse`] indicating whether the payment can be accepted.
-#[instrument(skip_all)]
+#[utoipa::path(
+    post,
+    path = "/verify",
+    request_body = VerifyRequest,
+    responses(
+        (status = 200, description = "Whether the payment is valid", body = VerifyResponse),
+        (status = 502, description = "An RPC node or outside service failed", body = ErrorResponse),
+        (status = 503, description = "Overloaded, or network unavailable", body = ErrorResponse),
+        (status = 504, description = "Verification timed out", body = ErrorResponse),
+    )
+)]
+#[instrument(skip_all, fields(payment_id, api_version))]
 pub async fn post_verify<A>(
     State(facilitator): State<A>,
+    api_version: ApiVersion,
     Json(body): Json<VerifyRequest>,
 ) -> impl IntoResponse
 where
     A: Facilitator,
     A::Error: IntoResponse,
 {
+    tracing::Span::current().record("api_version", tracing::field::debug(api_version));
+    if let Some(payment_id) = body.payment_id() {
+        tracing::Span::current().record("payment_id", tracing::field::display(payment_id));
+    }
     match facilitator.verify(&body).await {
         Ok(valid_response) => (StatusCode::OK, Json(valid_response)).into_response(),
         Err(error) => {
@@ -138,18 +519,73 @@ where
 /// Given a valid [`SettleRequest`], this endpoint attempts to execute the payment
 /// via ERC-3009 `transferWithAuthorization`, and returns a [`SettleResponse`] with transaction details.
 ///
-/// This endpoint is typically called after a successful `/verify` step.
-#[instrument(skip_all)]
+/// This endpoint is typically called after a successful `/verify` step. See [`SettleMode`] for answers given before
+/// the settlement is through.
+#[utoipa::path(
+    post,
+    path = "/settle",
+    request_body = SettleRequest,
+    responses(
+        (status = 200, description = "Outcome of the settlement", body = SettleResponse),
+        (status = 202, description = "Settlement pending, see `Location`", body = SettleResponse),
+        (status = 307, description = "Standby facilitator, settle with the active one"),
+        (status = 502, description = "An RPC node or outside service failed", body = ErrorResponse),
+        (status = 503, description = "Overloaded, or network unavailable", body = ErrorResponse),
+        (status = 504, description = "Settlement timed out, and carries on", body = ErrorResponse),
+    )
+)]
+#[instrument(skip_all, fields(payment_id, api_version))]
 pub async fn post_settle<A>(
     State(facilitator): State<A>,
+    Extension(settle_mode): Extension<SettleMode>,
+    api_version: ApiVersion,
+    headers: HeaderMap,
     Json(body): Json<SettleRequest>,
 ) -> impl IntoResponse
 where
     A: Facilitator,
     A::Error: IntoResponse,
 {
-    match facilitator.settle(&body).await {
-        Ok(valid_response) => (StatusCode::OK, Json(valid_response)).into_response(),
+    tracing::Span::current().record("api_version", tracing::field::debug(api_version));
+    if let Some(payment_id) = body.payment_id() {
+        tracing::Span::current().record("payment_id", tracing::field::display(payment_id));
+    }
+    #[cfg(feature = "chaos")]
+    if crate::chaos::Chaos::global().fail_settle() {
+        tracing::warn!("Settlement failed by chaos fault injection");
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: "Injected settlement failure".to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let result = match settle_mode.of_request(&headers) {
+        SettleMode::Sync => facilitator.settle(&body).await,
+        SettleMode::Async => facilitator.submit(&body).await,
+    };
+    match result {
+        Ok(mut valid_response) => {
+            valid_response.facilitator_version = Some(BuildInfo::current().version_tag());
+            valid_response.payment_id = body.payment_id().cloned();
+            let location = match valid_response.error_reason {
+                Some(FacilitatorErrorReason::SettlementPending) => valid_response
+                    .settlement_id
+                    .as_ref()
+                    .map(|id| format!("/settlements/{id}")),
+                _ => None,
+            };
+            let mut response = match body.x402_version {
+                X402Version::V1 => Json(valid_response).into_response(),
+                X402Version::V2 => Json(valid_response.into_v2()).into_response(),
+            };
+            if let Some(location) = location.and_then(|l| HeaderValue::from_str(&l).ok()) {
+                *response.status_mut() = StatusCode::ACCEPTED;
+                response.headers_mut().insert(header::LOCATION, location);
+            }
+            response
+        }
         Err(error) => {
             tracing::warn!(
                 error = ?error,
@@ -207,6 +643,147 @@ impl IntoResponse for FacilitatorLocalError {
                 )),
             )
                 .into_response(),
+            FacilitatorLocalError::ApprovalService(..) => (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse {
+                    error: "Approval service unavailable".to_string(),
+                }),
+            )
+                .into_response(),
+            FacilitatorLocalError::BridgeAdapter(..) => (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse {
+                    error: "Bridge adapter unavailable".to_string(),
+                }),
+            )
+                .into_response(),
+            FacilitatorLocalError::PriceOracle(..) => (
+                StatusCode::BAD_GATEWAY,
+                Json(ErrorResponse {
+                    error: "Price oracle unavailable".to_string(),
+                }),
+            )
+                .into_response(),
+            FacilitatorLocalError::NetworkUnavailable(_, retry_after) => (
+                StatusCode::SERVICE_UNAVAILABLE,
+                [(
+                    header::RETRY_AFTER,
+                    retry_after.as_secs_f64().ceil().max(1.0).to_string(),
+                )],
+                Json(ErrorResponse {
+                    error: "Network temporarily unavailable".to_string(),
+                }),
+            )
+                .into_response(),
+            FacilitatorLocalError::FeeOnTransfer(payer, ..) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::FeeOnTransfer,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::InsufficientFee(payer, ..) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::InsufficientFee,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::PriceOutOfTolerance(payer, ..) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    payer,
+                    FacilitatorErrorReason::PriceOutOfTolerance,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::InsufficientAllowance(payer) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::InsufficientAllowance,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::InvalidSettleAmount(payer, _) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    payer,
+                    FacilitatorErrorReason::InvalidSettleAmount,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::InvalidExecuteAfter(payer, _) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    payer,
+                    FacilitatorErrorReason::InvalidExecuteAfter,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::RuleViolation(_) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    None,
+                    FacilitatorErrorReason::RuleViolation,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::NonceReused(payer) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::NonceReused,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::NonceReplayed(payer) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::NonceReplayed,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::BudgetExceeded(payer) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::BudgetExceeded,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::TokenPaused(payer) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::TokenPaused,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::AddressBlacklisted(payer, _) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    Some(payer),
+                    FacilitatorErrorReason::AddressBlacklisted,
+                )),
+            )
+                .into_response(),
+            FacilitatorLocalError::Reverted(payer, revert) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(payer, revert.reason())),
+            )
+                .into_response(),
+            FacilitatorLocalError::UnsupportedAsset(_) => (
+                StatusCode::OK,
+                Json(VerifyResponse::invalid(
+                    None,
+                    FacilitatorErrorReason::UnsupportedAsset,
+                )),
+            )
+                .into_response(),
             FacilitatorLocalError::InsufficientFunds(payer) => (
                 StatusCode::OK,
                 Json(VerifyResponse::invalid(
@@ -0,0 +1,351 @@
+//! Per-network health tracking for the facilitator.
+//!
+//! A [`HealthMonitor`] periodically probes every configured network provider (latest block or slot)
+//! and records the outcome into a [`HealthHistory`]. The history keeps a rolling window of
+//! incidents — intervals during which a network was unreachable — and derives an uptime ratio
+//! from them, so operators can see e.g. "Avalanche RPC degraded from 12:00 to 12:20" straight from
+//! the facilitator via `GET /health/history`.
+//!
+//! On EVM networks, every probe also reads the native balance of each signer. A network with
+//! `SIGNER_MIN_BALANCE_<NETWORK>` set is degraded while a signer holds less than that, so operators top it up
+//! before settlements start failing for lack of gas.
+//!
+//! Every probe also emits OpenTelemetry metrics through the tracing metrics layer:
+//! - `x402.network.up` gauge (`1` when healthy, `0` when degraded), per network,
+//! - `x402.network.health_transitions` counter, per network and new status,
+//! - `x402.signer.balance` gauge, in native token units, per network and signer,
+//! - `x402.signer.low_balance` counter, per network and signer below its minimum.
+//!
+//! Environment variables used:
+//! - `HEALTH_CHECK_INTERVAL_SECS` — seconds between probes (default: `30`),
+//! - `HEALTH_HISTORY_WINDOW_SECS` — length of the rolling window (default: `86400`),
+//! - `SIGNER_MIN_BALANCE_<NETWORK>` — minimum native balance of each signer, e.g. `SIGNER_MIN_BALANCE_BASE=0.005`.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
+
+use crate::chain::NetworkProvider;
+use crate::chain::NetworkProviderOps;
+use crate::from_env;
+use crate::network::Network;
+use crate::provider_cache::ProviderMap;
+use crate::timestamp::UnixTimestamp;
+
+/// Health of a single network as last observed by the [`HealthMonitor`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    /// The network RPC answered the last probe.
+    Healthy,
+    /// The network RPC failed the last probe, or a signer's balance is below its minimum.
+    Degraded,
+}
+
+/// A contiguous period during which a network was [`HealthStatus::Degraded`].
+///
+/// `ended_at` is `None` while the incident is still ongoing.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthIncident {
+    pub started_at: UnixTimestamp,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ended_at: Option<UnixTimestamp>,
+    /// Error reported by the first failing probe of the incident.
+    pub reason: String,
+}
+
+/// Health summary of a single network over the rolling window, as served by `GET /health/history`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NetworkHealth {
+    pub network: Network,
+    pub status: HealthStatus,
+    /// When the network entered its current status.
+    pub since: UnixTimestamp,
+    pub last_checked: UnixTimestamp,
+    /// Fraction of the observed part of the window the network was healthy, in `[0, 1]`.
+    pub uptime: f64,
+    pub incidents: Vec<HealthIncident>,
+}
+
+/// Response body of `GET /health/history`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HealthHistoryResponse {
+    pub window_secs: u64,
+    pub networks: Vec<NetworkHealth>,
+}
+
+#[derive(Debug)]
+struct NetworkRecord {
+    status: HealthStatus,
+    first_seen: UnixTimestamp,
+    since: UnixTimestamp,
+    last_checked: UnixTimestamp,
+    incidents: VecDeque<HealthIncident>,
+}
+
+/// Rolling, in-memory record of per-network health transitions.
+///
+/// Cheap to clone: all clones share the same underlying state.
+#[derive(Debug, Clone)]
+pub struct HealthHistory {
+    window_secs: u64,
+    records: Arc<Mutex<HashMap<Network, NetworkRecord>>>,
+}
+
+impl HealthHistory {
+    pub const DEFAULT_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+    /// Creates an empty history retaining incidents for `window_secs` after they end.
+    pub fn new(window_secs: u64) -> Self {
+        Self {
+            window_secs,
+            records: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Creates an empty history using `HEALTH_HISTORY_WINDOW_SECS`, falling back to 24 hours.
+    pub fn from_env() -> Self {
+        let window_secs = std::env::var(from_env::ENV_HEALTH_HISTORY_WINDOW_SECS)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .unwrap_or(Self::DEFAULT_WINDOW_SECS);
+        Self::new(window_secs)
+    }
+
+    /// Records the outcome of a single probe of `network` taken at `now`.
+    ///
+    /// Opens an incident on a healthy → degraded transition and closes it on the way back.
+    pub fn record(&self, network: Network, now: UnixTimestamp, probe: Result<(), String>) {
+        let status = match probe {
+            Ok(()) => HealthStatus::Healthy,
+            Err(_) => HealthStatus::Degraded,
+        };
+        tracing::info!(
+            gauge.x402.network.up = if status == HealthStatus::Healthy { 1u64 } else { 0u64 },
+            network = %network,
+        );
+
+        let mut records = self.records.lock().expect("health history lock poisoned");
+        let record = records.entry(network).or_insert_with(|| NetworkRecord {
+            status: HealthStatus::Healthy,
+            first_seen: now,
+            since: now,
+            last_checked: now,
+            incidents: VecDeque::new(),
+        });
+        record.last_checked = now;
+
+        if record.status != status {
+            record.status = status;
+            record.since = now;
+            tracing::info!(
+                monotonic_counter.x402.network.health_transitions = 1u64,
+                network = %network,
+                status = ?status,
+            );
+            match probe {
+                Ok(()) => {
+                    tracing::info!(network = %network, "Network recovered");
+                    if let Some(incident) = record.incidents.back_mut() {
+                        incident.ended_at = Some(now);
+                    }
+                }
+                Err(reason) => {
+                    tracing::warn!(network = %network, reason = %reason, "Network degraded");
+                    record.incidents.push_back(HealthIncident {
+                        started_at: now,
+                        ended_at: None,
+                        reason,
+                    });
+                }
+            }
+        }
+
+        let window_start = UnixTimestamp(now.0.saturating_sub(self.window_secs));
+        while record
+            .incidents
+            .front()
+            .is_some_and(|i| i.ended_at.is_some_and(|ended_at| ended_at < window_start))
+        {
+            record.incidents.pop_front();
+        }
+    }
+
+    /// Summarizes the history of every tracked network as of `now`.
+    pub fn snapshot(&self, now: UnixTimestamp) -> HealthHistoryResponse {
+        let window_start = now.0.saturating_sub(self.window_secs);
+        let records = self.records.lock().expect("health history lock poisoned");
+        let mut networks: Vec<NetworkHealth> = records
+            .iter()
+            .map(|(network, record)| {
+                let observed_from = record.first_seen.0.max(window_start);
+                let observed = now.0.saturating_sub(observed_from);
+                let downtime: u64 = record
+                    .incidents
+                    .iter()
+                    .map(|incident| {
+                        let start = incident.started_at.0.max(observed_from);
+                        let end = incident.ended_at.map_or(now.0, |e| e.0).min(now.0);
+                        end.saturating_sub(start)
+                    })
+                    .sum();
+                let uptime = if observed == 0 {
+                    match record.status {
+                        HealthStatus::Healthy => 1.0,
+                        HealthStatus::Degraded => 0.0,
+                    }
+                } else {
+                    1.0 - (downtime.min(observed) as f64 / observed as f64)
+                };
+                NetworkHealth {
+                    network: *network,
+                    status: record.status,
+                    since: record.since,
+                    last_checked: record.last_checked,
+                    uptime,
+                    incidents: record.incidents.iter().cloned().collect(),
+                }
+            })
+            .collect();
+        networks.sort_by_key(|n| n.network.to_string());
+        HealthHistoryResponse {
+            window_secs: self.window_secs,
+            networks,
+        }
+    }
+}
+
+/// Background task probing every configured network provider on a fixed interval.
+pub struct HealthMonitor<P> {
+    providers: P,
+    history: HealthHistory,
+    interval: Duration,
+}
+
+impl<P> HealthMonitor<P>
+where
+    P: ProviderMap<Value = NetworkProvider> + Send + Sync + 'static,
+{
+    pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+
+    /// Creates a monitor probing `providers` every `HEALTH_CHECK_INTERVAL_SECS` (default 30s).
+    pub fn from_env(providers: P, history: HealthHistory) -> Self {
+        let interval = std::env::var(from_env::ENV_HEALTH_CHECK_INTERVAL_SECS)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok())
+            .filter(|secs| *secs > 0)
+            .map(Duration::from_secs)
+            .unwrap_or(Self::DEFAULT_INTERVAL);
+        Self {
+            providers,
+            history,
+            interval,
+        }
+    }
+
+    /// Spawns the probing loop, stopping when `cancellation_token` is cancelled.
+    pub fn spawn(self, cancellation_token: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(self.interval);
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    _ = ticker.tick() => self.probe_all().await,
+                }
+            }
+        })
+    }
+
+    /// Probes every network concurrently, each for at most the probe interval, so that one stuck RPC neither
+    /// delays the others nor piles up probes.
+    async fn probe_all(&self) {
+        let probes = self.providers.values().map(|provider| async move {
+            if provider.rpc_budget().should_throttle() {
+                tracing::debug!(network = %provider.network(), "RPC quota nearly used, skipping probe");
+                return;
+            }
+            let probe = async {
+                provider
+                    .latest_block_number()
+                    .await
+                    .map_err(|e| e.to_string())?;
+                provider.check_signer_balances().await
+            };
+            let probe = tokio::time::timeout(self.interval, probe)
+                .await
+                .unwrap_or_else(|_| Err(format!("probe timed out after {:?}", self.interval)));
+            match UnixTimestamp::try_now() {
+                Ok(now) => self.history.record(provider.network(), now, probe),
+                Err(e) => tracing::error!(error = %e, "Can not get system clock"),
+            }
+        });
+        futures::future::join_all(probes).await;
+    }
+}
+
+/// Routes exposing [`HealthHistory`]; merged next to [`crate::handlers::routes`].
+pub fn routes() -> Router<HealthHistory> {
+    Router::new().route("/health/history", get(get_health_history))
+}
+
+/// `GET /health/history`: Per-network health transitions and uptime over the rolling window.
+#[instrument(skip_all)]
+pub async fn get_health_history(State(history): State<HealthHistory>) -> impl IntoResponse {
+    match UnixTimestamp::try_now() {
+        Ok(now) => (StatusCode::OK, Json(history.snapshot(now))).into_response(),
+        Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incidents_open_close_and_affect_uptime() {
+        let history = HealthHistory::new(1000);
+        let network = Network::Avalanche;
+        history.record(network, UnixTimestamp(0), Ok(()));
+        history.record(network, UnixTimestamp(100), Err("timeout".to_string()));
+        history.record(network, UnixTimestamp(150), Err("timeout".to_string()));
+        history.record(network, UnixTimestamp(200), Ok(()));
+
+        let snapshot = history.snapshot(UnixTimestamp(400));
+        let avalanche = &snapshot.networks[0];
+        assert_eq!(avalanche.status, HealthStatus::Healthy);
+        assert_eq!(avalanche.since, UnixTimestamp(200));
+        assert_eq!(
+            avalanche.incidents,
+            vec![HealthIncident {
+                started_at: UnixTimestamp(100),
+                ended_at: Some(UnixTimestamp(200)),
+                reason: "timeout".to_string(),
+            }]
+        );
+        assert!((avalanche.uptime - 0.75).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn old_incidents_fall_out_of_the_window() {
+        let history = HealthHistory::new(100);
+        let network = Network::Base;
+        history.record(network, UnixTimestamp(0), Err("down".to_string()));
+        history.record(network, UnixTimestamp(10), Ok(()));
+        history.record(network, UnixTimestamp(500), Ok(()));
+
+        let snapshot = history.snapshot(UnixTimestamp(500));
+        assert!(snapshot.networks[0].incidents.is_empty());
+        assert!((snapshot.networks[0].uptime - 1.0).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,270 @@
+//! Persistent facilitator identity key with rotation history.
+//!
+//! The identity key is separate from the settlement signers: it never holds funds nor sends
+//! transactions, and only signs what the facilitator asserts off-chain — receipts, webhook payloads
+//! and its well-known metadata. It is a secp256k1 key signing EIP-191 personal messages, so anyone can
+//! check a signature with standard Ethereum tooling against the published identity address.
+//!
+//! The key is generated on first start and persisted to `IDENTITY_KEY_PATH`. Rotating it
+//! (`POST /admin/identity/rotate`) replaces the current key but keeps the address of every retired key
+//! published at `GET /.well-known/x402-facilitator`, so receipts signed before a rotation stay verifiable.
+//!
+//! Environment variables used:
+//! - `IDENTITY_KEY_PATH` — identity key file (default: `facilitator-identity.json`), created with mode `0600`.
+
+use alloy::primitives::B256;
+use alloy::signers::SignerSync;
+use alloy::signers::local::PrivateKeySigner;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+use std::time::SystemTimeError;
+use tracing::instrument;
+
+use crate::build_info::BuildInfo;
+use crate::from_env;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{ErrorResponse, EvmAddress, EvmSignature};
+
+#[derive(Debug, thiserror::Error)]
+pub enum IdentityError {
+    #[error("Identity key file error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid identity key file: {0}")]
+    Format(#[from] serde_json::Error),
+    #[error("Invalid identity key: {0}")]
+    Key(String),
+    #[error("Can not get system clock")]
+    Clock(#[source] SystemTimeError),
+}
+
+/// Public information about an identity key, current or retired.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityKeyInfo {
+    pub address: EvmAddress,
+    pub created_at: UnixTimestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retired_at: Option<UnixTimestamp>,
+}
+
+/// Current identity key and all retired ones, newest first.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentityKeys {
+    pub current: IdentityKeyInfo,
+    pub retired: Vec<IdentityKeyInfo>,
+}
+
+/// EIP-191 signature by the facilitator identity key.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdentitySignature {
+    /// Identity address that produced the signature.
+    pub address: EvmAddress,
+    pub signature: EvmSignature,
+}
+
+/// On-disk format of the identity key file.
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IdentityFile {
+    private_key: B256,
+    created_at: UnixTimestamp,
+    #[serde(default)]
+    retired: Vec<IdentityKeyInfo>,
+}
+
+struct IdentityState {
+    signer: PrivateKeySigner,
+    created_at: UnixTimestamp,
+    retired: Vec<IdentityKeyInfo>,
+}
+
+impl IdentityState {
+    fn generate(retired: Vec<IdentityKeyInfo>) -> Result<Self, IdentityError> {
+        Ok(Self {
+            signer: PrivateKeySigner::random(),
+            created_at: UnixTimestamp::try_now().map_err(IdentityError::Clock)?,
+            retired,
+        })
+    }
+
+    fn keys(&self) -> IdentityKeys {
+        IdentityKeys {
+            current: IdentityKeyInfo {
+                address: self.signer.address().into(),
+                created_at: self.created_at,
+                retired_at: None,
+            },
+            retired: self.retired.clone(),
+        }
+    }
+
+    fn save(&self, path: &Path) -> Result<(), IdentityError> {
+        let file = IdentityFile {
+            private_key: self.signer.to_bytes(),
+            created_at: self.created_at,
+            retired: self.retired.clone(),
+        };
+        let json = serde_json::to_vec_pretty(&file)?;
+        // Write next to the target and rename, so a crash never leaves a truncated key file.
+        let tmp_path = path.with_extension("tmp");
+        let mut tmp = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(&tmp_path)?;
+        tmp.write_all(&json)?;
+        tmp.sync_all()?;
+        std::fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+}
+
+/// Facilitator identity key, persisted to disk.
+///
+/// Cheap to clone: all clones share the same key and rotation history.
+#[derive(Clone)]
+pub struct FacilitatorIdentity {
+    path: PathBuf,
+    state: Arc<RwLock<IdentityState>>,
+}
+
+impl FacilitatorIdentity {
+    pub const DEFAULT_PATH: &str = "facilitator-identity.json";
+
+    /// Loads the identity key from `path`, generating and persisting a new one if the file does not exist.
+    pub fn load_or_create(path: impl Into<PathBuf>) -> Result<Self, IdentityError> {
+        let path = path.into();
+        let state = match std::fs::read(&path) {
+            Ok(bytes) => {
+                let file: IdentityFile = serde_json::from_slice(&bytes)?;
+                let signer = PrivateKeySigner::from_bytes(&file.private_key)
+                    .map_err(|e| IdentityError::Key(e.to_string()))?;
+                IdentityState {
+                    signer,
+                    created_at: file.created_at,
+                    retired: file.retired,
+                }
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let state = IdentityState::generate(Vec::new())?;
+                state.save(&path)?;
+                tracing::info!(address = %state.signer.address(), path = %path.display(), "Generated facilitator identity key");
+                state
+            }
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self {
+            path,
+            state: Arc::new(RwLock::new(state)),
+        })
+    }
+
+    /// Loads or creates the identity key at `IDENTITY_KEY_PATH`.
+    pub fn from_env() -> Result<Self, IdentityError> {
+        let path = std::env::var(from_env::ENV_IDENTITY_KEY_PATH)
+            .unwrap_or_else(|_| Self::DEFAULT_PATH.to_string());
+        Self::load_or_create(path)
+    }
+
+    /// Current and retired identity keys.
+    pub fn keys(&self) -> IdentityKeys {
+        self.state.read().expect("identity lock poisoned").keys()
+    }
+
+    /// Signs `message` as an EIP-191 personal message with the current identity key.
+    pub fn sign(&self, message: &[u8]) -> Result<IdentitySignature, IdentityError> {
+        let state = self.state.read().expect("identity lock poisoned");
+        let signature = state
+            .signer
+            .sign_message_sync(message)
+            .map_err(|e| IdentityError::Key(e.to_string()))?;
+        Ok(IdentitySignature {
+            address: state.signer.address().into(),
+            signature: EvmSignature(signature.as_bytes().to_vec()),
+        })
+    }
+
+    /// Replaces the current key with a new one, keeping the previous address in the published history.
+    ///
+    /// The new key is persisted before it is used; on failure the current key stays in place.
+    pub fn rotate(&self) -> Result<IdentityKeys, IdentityError> {
+        let mut state = self.state.write().expect("identity lock poisoned");
+        let now = UnixTimestamp::try_now().map_err(IdentityError::Clock)?;
+        let mut retired = vec![IdentityKeyInfo {
+            address: state.signer.address().into(),
+            created_at: state.created_at,
+            retired_at: Some(now),
+        }];
+        retired.extend(state.retired.iter().cloned());
+        let next = IdentityState::generate(retired)?;
+        next.save(&self.path)?;
+        tracing::info!(
+            previous = %state.signer.address(),
+            current = %next.signer.address(),
+            "Facilitator identity key rotated"
+        );
+        *state = next;
+        Ok(state.keys())
+    }
+}
+
+/// Response body of `GET /.well-known/x402-facilitator`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FacilitatorMetadata {
+    pub name: String,
+    pub version: String,
+    pub identity: IdentityKeys,
+    /// Signature of the JSON-encoded `identity` field by the current identity key.
+    pub signature: IdentitySignature,
+}
+
+/// Public routes publishing the facilitator identity.
+pub fn routes() -> Router<FacilitatorIdentity> {
+    Router::new().route(
+        "/.well-known/x402-facilitator",
+        get(get_facilitator_metadata),
+    )
+}
+
+/// `GET /.well-known/x402-facilitator`: Identity keys, current and retired, for verifying signed receipts.
+#[instrument(skip_all)]
+pub async fn get_facilitator_metadata(State(identity): State<FacilitatorIdentity>) -> Response {
+    let keys = identity.keys();
+    let signature = serde_json::to_vec(&keys)
+        .map_err(IdentityError::from)
+        .and_then(|bytes| identity.sign(&bytes));
+    match signature {
+        Ok(signature) => {
+            let build_info = BuildInfo::current();
+            let metadata = FacilitatorMetadata {
+                name: build_info.name,
+                version: build_info.version,
+                identity: keys,
+                signature,
+            };
+            (StatusCode::OK, Json(metadata)).into_response()
+        }
+        Err(e) => {
+            tracing::error!(error = %e, "Failed to sign facilitator metadata");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to sign facilitator metadata".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
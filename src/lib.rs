@@ -9,24 +9,98 @@
 //! - _Buyer_: a client that constructs and submits x402-compliant payments
 //!
 //! Modules:
+//! - [`acme`] — HTTPS with certificates issued and renewed over ACME, with the `acme` feature only.
+//! - [`admin`] — `/admin` API with role-based access control for operators.
+//! - [`approval`] — routes high-value settlements through human approval.
+//! - [`chaos`] — runtime fault injection for incident drills, with the `chaos` feature only.
+//! - [`budgets`] — daily spend caps per payer, managed through the admin API.
+//! - [`builder`] — [`builder::FacilitatorBuilder`] embedding the facilitator in another axum application.
+//! - [`build_info`] — version, git commit and protocol metadata of the running build.
+//! - [`circuit`] — per-network circuit breakers failing requests fast while a network's RPC is down.
+//! - [`codec`] — CBOR and MessagePack bodies on the protocol endpoints.
+//! - [`config`] — multi-network configuration from a TOML file.
+//! - [`docs`] — `/docs` page documenting the endpoints and payment kinds of the running instance.
+//! - [`dry_run`] — dry-run mode validating and simulating settlements without broadcasting them.
 //! - [`facilitator`] — defines the [`facilitator::Facilitator`] trait used to validate and settle x402 payments.
 //! - [`facilitator_local`] — a concrete implementation of [`facilitator::Facilitator`].
+//! - [`fees`] — facilitator fees per payment, reduced or waived for frequent payers.
+//! - [`grpc`] — gRPC service mirroring the protocol endpoints, with the `grpc` feature only.
+//! - [`health`] — per-network health probing with a rolling incident history.
+//! - [`identity`] — persistent facilitator identity key for signed receipts and metadata, with rotation.
+//! - [`locale`] — `Accept-Language` localization of human-readable error messages.
+//! - [`maintenance`] — announcement of planned maintenance windows in `/supported` and a response header.
+//! - [`mirror`] — read-only listener serving settlements, stats and discovery data to analytics consumers.
 //! - [`network`] — enumerates supported Ethereum-compatible networks and known token deployments.
+//! - [`nonces`] — random ERC-3009 nonce reservations with early replay detection.
+//! - [`openapi`] — OpenAPI 3 document of the protocol endpoints, generated from their types.
+//! - [`ops`] — isolated listener for health, version and admin traffic.
+//! - [`oracle`] — price oracle resolving dollar-denominated payment requirements.
+//! - [`outbound`] — SSRF protection for outbound calls to operator-supplied URLs.
+//! - [`payload_store`] — content-addressable, optionally encrypted storage of raw request bodies for forensics.
+//! - [`plugins`] — WebAssembly verification hooks and pricing logic, with the `plugins` feature only.
 //! - [`provider_cache`] — dynamic initialization and caching of Ethereum JSON-RPC providers.
+//! - [`replay`] — replay protection for ERC-3009 nonces in flight or settled.
+//! - [`replication`] — replication of the replay store between the facilitators of an active/passive pair.
+//! - [`routing`] — cross-network settlement through a bridge or swap adapter.
+//! - [`rules`] — operator-defined verification rules in a small expression language.
+//! - [`settle_queue`] — durable queue settling payments in a worker task, with retries.
+//! - [`settlement_stream`] — `GET /ws` WebSocket streaming the lifecycle events of subscribed settlements.
+//! - [`slo`] — latency SLO tracking per endpoint and network with burn-rate alerts.
 //! - [`telemetry`] — OpenTelemetry instrumentation setup for tracing and observability.
 //! - [`types`] — all shared x402 protocol structures and payload formats.
+//! - [`verify_dedup`] — answers repeated identical `/verify` requests from a short-lived cache.
+//! - [`webhooks`] — signed webhook notifications of settlement lifecycle events.
 
+#[cfg(feature = "acme")]
+pub mod acme;
+pub mod admin;
+pub mod approval;
+pub mod budgets;
+pub mod build_info;
+pub mod builder;
 pub mod chain;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod circuit;
+pub mod codec;
+pub mod config;
+pub mod docs;
+pub mod dry_run;
 pub mod facilitator;
 pub mod facilitator_local;
+pub mod fees;
 pub mod from_env;
+#[cfg(feature = "grpc")]
+pub mod grpc;
 pub mod handlers;
+pub mod health;
+pub mod identity;
+pub mod locale;
+pub mod maintenance;
+pub mod mirror;
 pub mod network;
+pub mod nonces;
+pub mod openapi;
+pub mod ops;
+pub mod oracle;
+pub mod outbound;
+pub mod payload_store;
+#[cfg(feature = "plugins")]
+pub mod plugins;
 pub mod provider_cache;
+pub mod replay;
+pub mod replication;
+pub mod routing;
+pub mod rules;
+pub mod settle_queue;
+pub mod settlement_stream;
 pub mod sig_down;
+pub mod slo;
 pub mod telemetry;
 pub mod timestamp;
 pub mod types;
+pub mod verify_dedup;
+pub mod webhooks;
 
 // Hidden re-exports just for macro expansion.
 #[doc(hidden)]
@@ -0,0 +1,191 @@
+//! Localization of the human-readable error messages of responses.
+//!
+//! Error bodies carry a human-readable `error` message next to stable machine codes such as `invalidReason`.
+//! Merchants serving non-English customers can provide message catalogs: [`localize`] picks the catalog matching
+//! the request's `Accept-Language` and translates the `error` message of JSON bodies, setting `Content-Language`.
+//! Machine codes and every other field are left untouched.
+//!
+//! A catalog is a JSON object mapping English messages to their translation, stored as `<language tag>.json`,
+//! e.g. `de.json` or `pt-BR.json`. Messages with details after a colon, like `Invalid request: missing field`,
+//! are matched on the part before the colon.
+//!
+//! Environment variables used:
+//! - `LOCALE_DIR` — directory of the catalogs. Responses are not localized if unset.
+
+use axum::body::{Body, Bytes, to_bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, HeaderValue, header};
+use axum::middleware::Next;
+use axum::response::Response;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::from_env;
+
+/// Largest response body localized.
+const BODY_LIMIT: usize = 2 * 1024 * 1024;
+
+/// Message catalogs by lowercase language tag.
+#[derive(Debug, Clone, Default)]
+pub struct Catalogs {
+    catalogs: Arc<HashMap<String, Arc<HashMap<String, String>>>>,
+}
+
+impl Catalogs {
+    pub fn new(catalogs: HashMap<String, HashMap<String, String>>) -> Self {
+        let catalogs = catalogs
+            .into_iter()
+            .map(|(tag, messages)| (tag.to_ascii_lowercase(), Arc::new(messages)))
+            .collect();
+        Self {
+            catalogs: Arc::new(catalogs),
+        }
+    }
+
+    /// Loads every `<tag>.json` of `LOCALE_DIR`; no catalogs if unset.
+    pub fn from_env() -> Result<Self, String> {
+        let Ok(dir) = std::env::var(from_env::ENV_LOCALE_DIR) else {
+            return Ok(Self::default());
+        };
+        Self::load(Path::new(&dir)).map_err(|e| format!("{}: {e}", from_env::ENV_LOCALE_DIR))
+    }
+
+    fn load(dir: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut catalogs = HashMap::new();
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(tag) = path.file_stem().and_then(|s| s.to_str()) else {
+                continue;
+            };
+            let messages: HashMap<String, String> = serde_json::from_slice(&std::fs::read(&path)?)
+                .map_err(|e| format!("{}: {e}", path.display()))?;
+            catalogs.insert(tag.to_string(), messages);
+        }
+        tracing::info!(languages = ?catalogs.keys().collect::<Vec<_>>(), "Loaded message catalogs");
+        Ok(Self::new(catalogs))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.catalogs.is_empty()
+    }
+
+    /// Tag and catalog of the preferred language of `Accept-Language` that has one.
+    fn negotiate(&self, headers: &HeaderMap) -> Option<(&str, &Arc<HashMap<String, String>>)> {
+        let mut ranges: Vec<(&str, f32)> = headers
+            .get_all(header::ACCEPT_LANGUAGE)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|range| {
+                let mut parts = range.split(';').map(str::trim);
+                let tag = parts.next().filter(|tag| !tag.is_empty())?;
+                let quality = parts
+                    .find_map(|param| param.strip_prefix("q="))
+                    .map_or(Some(1.0), |q| q.parse().ok())?;
+                Some((tag, quality))
+            })
+            .filter(|(_, quality)| *quality > 0.0)
+            .collect();
+        // Stable, so equally preferred languages keep the client's order.
+        ranges.sort_by(|a, b| b.1.total_cmp(&a.1));
+        ranges.into_iter().find_map(|(tag, _)| {
+            let tag = tag.to_ascii_lowercase();
+            let primary = tag.split('-').next().unwrap_or_default().to_string();
+            [tag, primary]
+                .into_iter()
+                .find_map(|tag| self.catalogs.get_key_value(&tag))
+                .map(|(tag, catalog)| (tag.as_str(), catalog))
+        })
+    }
+}
+
+/// Translation of `message`, whole or before its first colon.
+fn translate(catalog: &HashMap<String, String>, message: &str) -> Option<String> {
+    if let Some(translation) = catalog.get(message) {
+        return Some(translation.clone());
+    }
+    let (head, details) = message.split_once(':')?;
+    catalog
+        .get(head)
+        .map(|translation| format!("{translation}:{details}"))
+}
+
+/// Middleware translating the `error` message of JSON responses to the client's language.
+pub async fn localize(State(catalogs): State<Catalogs>, request: Request, next: Next) -> Response {
+    let language = catalogs
+        .negotiate(request.headers())
+        .map(|(tag, catalog)| (tag.to_string(), catalog.clone()));
+    let response = next.run(request).await;
+    let Some((tag, catalog)) = language else {
+        return response;
+    };
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !is_json {
+        return response;
+    }
+    let (mut parts, body) = response.into_parts();
+    let bytes: Bytes = match to_bytes(body, BODY_LIMIT).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to read response body for localization");
+            return Response::from_parts(parts, Body::empty());
+        }
+    };
+    let Ok(mut json) = serde_json::from_slice::<serde_json::Value>(&bytes) else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    let translation = json
+        .get("error")
+        .and_then(|error| error.as_str())
+        .and_then(|message| translate(&catalog, message));
+    let Some(translation) = translation else {
+        return Response::from_parts(parts, Body::from(bytes));
+    };
+    json["error"] = serde_json::Value::String(translation);
+    parts.headers.remove(header::CONTENT_LENGTH);
+    if let Ok(tag) = HeaderValue::from_str(&tag) {
+        parts.headers.insert(header::CONTENT_LANGUAGE, tag);
+    }
+    Response::from_parts(parts, Body::from(json.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_preferred_catalog_and_translates() {
+        let de = HashMap::from([(
+            "Invalid request".to_string(),
+            "Ungültige Anfrage".to_string(),
+        )]);
+        let catalogs = Catalogs::new(HashMap::from([("de".to_string(), de)]));
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::ACCEPT_LANGUAGE,
+            HeaderValue::from_static("fr;q=0.9, de-CH;q=0.8, en;q=0.5"),
+        );
+        let (tag, catalog) = catalogs.negotiate(&headers).unwrap();
+        assert_eq!(tag, "de");
+        assert_eq!(
+            translate(catalog, "Invalid request").as_deref(),
+            Some("Ungültige Anfrage")
+        );
+        assert_eq!(
+            translate(catalog, "Invalid request: missing field").as_deref(),
+            Some("Ungültige Anfrage: missing field")
+        );
+        assert_eq!(translate(catalog, "Unauthorized"), None);
+
+        headers.insert(header::ACCEPT_LANGUAGE, HeaderValue::from_static("de;q=0"));
+        assert!(catalogs.negotiate(&headers).is_none());
+    }
+}
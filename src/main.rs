@@ -4,11 +4,33 @@
 //! for payment verification and settlement via Ethereum-compatible networks.
 //!
 //! Endpoints:
-//! - `GET /verify` – Supported verification schema
+//! - `GET /verify` – OpenAPI description of `POST /verify`
 //! - `POST /verify` – Verify a payment payload against requirements
-//! - `GET /settle` – Supported settlement schema
+//! - `GET /settle` – OpenAPI description of `POST /settle`
 //! - `POST /settle` – Settle an accepted payment payload on-chain
 //! - `GET /supported` – List supported payment kinds (version/scheme/network)
+//! - `GET /openapi.json` – OpenAPI 3 document of the protocol endpoints
+//! - `GET /swagger-ui` – Swagger UI on the OpenAPI document, with the `swagger-ui` feature
+//! - `GET /ws` – WebSocket streaming the lifecycle events of subscribed settlements
+//! - `GET /approvals/{id}` – State of a settlement awaiting human approval
+//! - `POST /approvals/{id}` – Approval decision callback from the transaction service
+//! - `POST /userop/sponsor` – Paymaster sponsorship of an ERC-4337 UserOperation
+//! - `POST /routes/quote` – Source-network requirements of a cross-network payment, with routing configured
+//! - `GET /fees/quote` – Facilitator fee due by a payer, with fees configured
+//! - `GET|POST /replication/nonces` – Replay store replication between peers, with `REPLAY_REPLICATION_PEERS` set
+//! - `/admin/*` – Operator API, authenticated with role-bound API keys (`ADMIN_API_KEYS`)
+//! - `GET /version` – Build metadata and supported x402 protocol versions, signed by the identity key
+//! - `GET /health/history` – Per-network health transitions and rolling uptime
+//! - `GET /.well-known/x402-facilitator` – Current and retired identity keys, signed
+//!
+//! The protocol endpoints, from `GET /verify` to `GET /swagger-ui`, are also served under `/v1`.
+//!
+//! With `OPS_PORT` set, `/health`, `/health/history`, `/version` and `/admin/*` are also served
+//! on a separate listener and runtime (see [`ops`]), and `/admin/*` is served there only.
+//! With `MIRROR_PORT` set, settlements, stats and discovery data are served read-only to analytics
+//! consumers on another listener (see [`mirror`]).
+//! With the `grpc` feature and `GRPC_PORT` set, verification and settlement are also served over
+//! gRPC on another listener (see [`grpc`]).
 //!
 //! This server includes:
 //! - OpenTelemetry tracing via `TraceLayer`
@@ -17,7 +39,8 @@
 //!
 //! Environment:
 //! - `.env` values loaded at startup
-//! - `HOST`, `PORT` control binding address
+//! - `HOST`, `PORT` control binding address, `OPS_PORT` the optional operational listener,
+//!   `MIRROR_PORT` the optional read-only mirror, `GRPC_PORT` the optional gRPC listener
 //! - `OTEL_*` variables enable tracing to systems like Honeycomb
 
 use axum::Router;
@@ -27,22 +50,88 @@ use std::net::SocketAddr;
 use std::sync::Arc;
 use tower_http::cors;
 
+use crate::admin::{AdminAuth, AdminRouter, Role};
+use crate::approval::ApprovalGate;
+use crate::budgets::{BudgetGate, Budgets};
+use crate::chain::aa::{SponsorLimiter, Sponsorship};
+use crate::chain::speed_up::SpeedUp;
+use crate::circuit::{CircuitBreakers, CircuitGate};
+use crate::config::FacilitatorConfig;
+use crate::dry_run::DryRunGate;
 use crate::facilitator_local::FacilitatorLocal;
+use crate::fees::{FeeGate, Fees};
+use crate::handlers::{LoadShedder, RequestTimeouts, RunMode, SettleMode};
+use crate::health::{HealthHistory, HealthMonitor};
+use crate::identity::FacilitatorIdentity;
+use crate::locale::Catalogs;
+use crate::maintenance::{MaintenanceGate, MaintenanceSchedule};
+use crate::mirror::{MirrorServer, SettlementLog, SettlementRecorder};
+use crate::nonces::{NonceGuard, NonceReservations};
+use crate::ops::OpsServer;
+use crate::oracle::{PriceGate, PriceOracle};
+use crate::payload_store::PayloadStore;
 use crate::provider_cache::ProviderCache;
+use crate::replay::{ReplayGuard, SeenNonces};
+use crate::replication::ReplayReplication;
+use crate::routing::{BridgeAdapter, RouteGate};
+use crate::rules::{RuleGate, Rules};
+use crate::settle_queue::{SettlementQueue, Settlements};
+use crate::settlement_stream::SettlementStream;
 use crate::sig_down::SigDown;
+use crate::slo::{SloGate, SloTracker};
 use crate::telemetry::Telemetry;
+use crate::verify_dedup::VerifyDedup;
+use crate::webhooks::Webhooks;
 
+#[cfg(feature = "acme")]
+mod acme;
+mod admin;
+mod approval;
+mod budgets;
+mod build_info;
 mod chain;
+#[cfg(feature = "chaos")]
+mod chaos;
+mod circuit;
+mod codec;
+mod config;
+mod docs;
+mod dry_run;
 mod facilitator;
 mod facilitator_local;
+mod fees;
 mod from_env;
+#[cfg(feature = "grpc")]
+mod grpc;
 mod handlers;
+mod health;
+mod identity;
+mod locale;
+mod maintenance;
+mod mirror;
 mod network;
+mod nonces;
+mod openapi;
+mod ops;
+mod oracle;
+mod outbound;
+mod payload_store;
+#[cfg(feature = "plugins")]
+mod plugins;
 mod provider_cache;
+mod replay;
+mod replication;
+mod routing;
+mod rules;
+mod settle_queue;
+mod settlement_stream;
 mod sig_down;
+mod slo;
 mod telemetry;
 mod timestamp;
 mod types;
+mod verify_dedup;
+mod webhooks;
 
 /// Initializes the x402 facilitator server.
 ///
@@ -62,7 +151,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_version(env!("CARGO_PKG_VERSION"))
         .register();
 
-    let provider_cache = ProviderCache::from_env().await;
+    let config = match FacilitatorConfig::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            tracing::error!("Failed to load config file: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let provider_cache = match &config {
+        Some(config) => ProviderCache::from_config(config).await,
+        None => ProviderCache::from_env().await,
+    };
     // Abort if we can't initialise Ethereum providers early
     let provider_cache = match provider_cache {
         Ok(provider_cache) => provider_cache,
@@ -71,26 +170,515 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         }
     };
-    let facilitator = FacilitatorLocal::new(provider_cache);
+    let provider_cache = Arc::new(provider_cache);
+    tokio::spawn(chain::recovery::recover(provider_cache.clone()));
+    let facilitator = FacilitatorLocal::new(provider_cache.clone());
+    let facilitator = match CircuitBreakers::from_env() {
+        Ok(breakers) => CircuitGate::new(facilitator, breakers),
+        Err(e) => {
+            tracing::error!("Failed to configure circuit breakers: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let bridge_adapter = match BridgeAdapter::from_env() {
+        Ok(bridge_adapter) => bridge_adapter,
+        Err(e) => {
+            tracing::error!("Failed to configure cross-network routing: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = RouteGate::new(facilitator, bridge_adapter.clone());
+    let maintenance = match MaintenanceSchedule::from_env() {
+        Ok(maintenance) => maintenance,
+        Err(e) => {
+            tracing::error!("Failed to configure maintenance windows: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = MaintenanceGate::new(facilitator, maintenance.clone());
+    let facilitator = match PriceOracle::from_env() {
+        Ok(oracle) => PriceGate::new(facilitator, oracle),
+        Err(e) => {
+            tracing::error!("Failed to configure price oracle: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let nonce_reservations = match NonceReservations::from_env() {
+        Ok(nonce_reservations) => nonce_reservations,
+        Err(e) => {
+            tracing::error!("Failed to configure nonce reservations: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let sponsor_limiter = match SponsorLimiter::from_env() {
+        Ok(sponsor_limiter) => sponsor_limiter,
+        Err(e) => {
+            tracing::error!("Failed to configure UserOperation sponsorship: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let rules = config
+        .as_ref()
+        .map_or(Ok(Rules::default()), |config| Rules::compile(&config.rules));
+    let rules = match rules {
+        Ok(rules) => rules,
+        Err(e) => {
+            tracing::error!("Failed to compile verification rules: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = RuleGate::new(facilitator, rules);
+    #[cfg(feature = "plugins")]
+    let facilitator = match plugins::Plugins::from_env() {
+        Ok(plugins) => plugins::PluginGate::new(facilitator, plugins),
+        Err(e) => {
+            tracing::error!("Failed to load plugins: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = NonceGuard::new(facilitator, nonce_reservations.clone());
+    let seen_nonces = match SeenNonces::from_env() {
+        Ok(nonces) => nonces,
+        Err(e) => {
+            tracing::error!("Failed to configure nonce replay store: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = ReplayGuard::new(facilitator, seen_nonces.clone());
+    let replay_replication = match ReplayReplication::from_env() {
+        Ok(replay_replication) => replay_replication,
+        Err(e) => {
+            tracing::error!("Failed to configure replay store replication: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let budgets = match Budgets::from_env() {
+        Ok(budgets) => budgets,
+        Err(e) => {
+            tracing::error!("Failed to load payer budgets: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = BudgetGate::new(facilitator, budgets.clone());
+    let fees = match Fees::from_env() {
+        Ok(fees) => fees,
+        Err(e) => {
+            tracing::error!("Failed to configure facilitator fees: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = FeeGate::new(facilitator, fees.clone());
+    let facilitator = match ApprovalGate::from_env(facilitator, provider_cache.clone()) {
+        Ok(facilitator) => facilitator,
+        Err(e) => {
+            tracing::error!("Failed to configure settlement approval: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let approvals = facilitator.approvals();
+    let settlement_log = match SettlementLog::from_env() {
+        Ok(settlement_log) => settlement_log,
+        Err(e) => {
+            tracing::error!("Failed to configure settlement log: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = SettlementRecorder::new(facilitator, settlement_log.clone());
+    let facilitator = match SettlementQueue::from_env(facilitator) {
+        Ok(facilitator) => facilitator,
+        Err(e) => {
+            tracing::error!("Failed to configure settlement queue: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let queue_records = facilitator.records();
+    let dead_letters = facilitator.dead_letters();
+    let settlement_events = facilitator.subscribe();
+    let settlement_stream_events = facilitator.events();
+    for watch in chain::reorg::watches(&provider_cache) {
+        facilitator.follow_reorgs(watch.subscribe());
+    }
+    let slo_tracker = match SloTracker::from_env() {
+        Ok(slo_tracker) => slo_tracker,
+        Err(e) => {
+            tracing::error!("Failed to configure latency SLOs: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let facilitator = SloGate::new(facilitator, slo_tracker.clone());
+    let dry_run = dry_run::enabled();
+    if dry_run {
+        tracing::warn!("Running in dry-run mode, settlements are simulated and never broadcast");
+    }
+    let facilitator = DryRunGate::new(facilitator, dry_run);
     let axum_state = Arc::new(facilitator);
 
-    let http_endpoints = Router::new()
-        .merge(handlers::routes().with_state(axum_state))
-        .layer(telemetry.http_tracing())
-        .layer(
-            cors::CorsLayer::new()
-                .allow_origin(cors::Any)
-                .allow_methods([Method::GET, Method::POST])
-                .allow_headers(cors::Any),
+    let run_mode = match RunMode::from_env() {
+        Ok(run_mode) => run_mode,
+        Err(e) => {
+            tracing::error!("Failed to configure run mode: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if let RunMode::Standby { active_settler } = &run_mode {
+        tracing::info!(active_settler = ?active_settler.as_ref().map(|url| url.as_str()), "Running as standby, settlements are refused");
+    }
+    let settle_mode = match SettleMode::from_env() {
+        Ok(settle_mode) => settle_mode,
+        Err(e) => {
+            tracing::error!("Failed to configure settle mode: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let identity = match FacilitatorIdentity::from_env() {
+        Ok(identity) => identity,
+        Err(e) => {
+            tracing::error!("Failed to load facilitator identity key: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let webhooks = match Webhooks::from_env(identity.clone()) {
+        Ok(webhooks) => webhooks,
+        Err(e) => {
+            tracing::error!("Failed to configure settlement webhooks: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let payload_store = match PayloadStore::from_env() {
+        Ok(payload_store) => payload_store,
+        Err(e) => {
+            tracing::error!("Failed to configure payload store: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let admin_auth = match AdminAuth::from_env() {
+        Ok(admin_auth) => admin_auth,
+        Err(e) => {
+            tracing::error!("Failed to configure admin API keys: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let admin_router = AdminRouter::new(admin_auth)
+        .with_routes(Role::Viewer, admin::approval_routes(), approvals.clone())
+        .with_routes(Role::Viewer, budgets::routes(), budgets.clone())
+        .with_routes(Role::Admin, budgets::config_routes(), budgets.clone())
+        .with_routes(Role::Viewer, admin::signer_routes(), provider_cache.clone())
+        .with_routes(Role::Viewer, admin::rpc_routes(), provider_cache.clone())
+        .with_routes(
+            Role::Admin,
+            admin::signer_rotation_routes(),
+            provider_cache.clone(),
+        )
+        .with_routes(Role::Admin, admin::network_routes(), provider_cache.clone())
+        .with_routes(Role::Admin, admin::identity_routes(), identity.clone())
+        .with_routes(
+            Role::Viewer,
+            settle_queue::dead_letter_routes(),
+            dead_letters.clone(),
+        )
+        .with_routes(
+            Role::Operator,
+            settle_queue::dead_letter_config_routes(),
+            dead_letters,
+        );
+    let admin_router = match &fees {
+        Some(fees) => admin_router.with_routes(Role::Viewer, fees::admin_routes(), fees.clone()),
+        None => admin_router,
+    };
+    let admin_router = match &payload_store {
+        Some(store) => {
+            admin_router.with_routes(Role::Admin, payload_store::admin_routes(), store.clone())
+        }
+        None => admin_router,
+    };
+    #[cfg(feature = "chaos")]
+    let admin_router = admin_router
+        .with_routes(
+            Role::Viewer,
+            chaos::routes(),
+            chaos::Chaos::global().clone(),
+        )
+        .with_routes(
+            Role::Operator,
+            chaos::config_routes(),
+            chaos::Chaos::global().clone(),
         );
+    let admin_endpoints = admin_router.into_router();
+
+    let sig_down = SigDown::try_new()?;
+
+    let health_history = HealthHistory::from_env();
+    HealthMonitor::from_env(provider_cache.clone(), health_history.clone())
+        .spawn(sig_down.cancellation_token());
+    if let Some(store) = &payload_store {
+        store.spawn_pruning(sig_down.cancellation_token());
+    }
+    match SpeedUp::from_env() {
+        Ok(Some(speed_up)) => {
+            chain::speed_up::spawn(
+                provider_cache.clone(),
+                speed_up,
+                sig_down.cancellation_token(),
+            );
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to configure stuck transaction replacement: {}", e);
+            std::process::exit(1);
+        }
+    }
+    match chain::token_probe::interval_from_env() {
+        Ok(interval) => {
+            chain::token_probe::spawn(
+                provider_cache.clone(),
+                interval,
+                sig_down.cancellation_token(),
+            );
+        }
+        Err(e) => {
+            tracing::error!("Failed to configure token sanity checks: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if !chain::reorg::watches(&provider_cache).is_empty() {
+        match chain::reorg::interval_from_env() {
+            Ok(interval) => {
+                chain::reorg::spawn(
+                    provider_cache.clone(),
+                    interval,
+                    sig_down.cancellation_token(),
+                );
+            }
+            Err(e) => {
+                tracing::error!("Failed to configure reorganization checks: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if !chain::failover::failovers(&provider_cache).is_empty() {
+        match chain::failover::interval_from_env() {
+            Ok(interval) => {
+                chain::failover::spawn(
+                    provider_cache.clone(),
+                    interval,
+                    sig_down.cancellation_token(),
+                );
+            }
+            Err(e) => {
+                tracing::error!("Failed to configure RPC endpoint checks: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(slo_tracker) = &slo_tracker {
+        slo_tracker.clone().spawn(sig_down.cancellation_token());
+    }
+    if let Some(webhooks) = webhooks {
+        webhooks.spawn(settlement_events, sig_down.cancellation_token());
+    }
+    if let Some(replay_replication) = &replay_replication {
+        replay_replication
+            .clone()
+            .spawn(seen_nonces.clone(), sig_down.cancellation_token());
+    }
 
     let host = std::env::var("HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
+    let host = host.parse().expect("HOST must be a valid IP address");
     let port = std::env::var("PORT")
         .ok()
         .and_then(|s| s.parse::<u16>().ok())
         .unwrap_or(8080);
 
-    let addr = SocketAddr::new(host.parse().expect("HOST must be a valid IP address"), port);
+    let payment_endpoints = handlers::routes(
+        RequestTimeouts::from_env(),
+        LoadShedder::from_env(),
+        run_mode.clone(),
+        settle_mode,
+        identity.clone(),
+    )
+    .with_state(axum_state.clone());
+    let payment_endpoints = match payload_store {
+        Some(store) => payment_endpoints.route_layer(axum::middleware::from_fn_with_state(
+            store,
+            payload_store::record_payload,
+        )),
+        None => payment_endpoints,
+    };
+    let payment_endpoints = match VerifyDedup::from_env() {
+        Ok(Some(dedup)) => payment_endpoints.route_layer(axum::middleware::from_fn_with_state(
+            dedup,
+            verify_dedup::dedup_verify,
+        )),
+        Ok(None) => payment_endpoints,
+        Err(e) => {
+            tracing::error!("Failed to configure verify deduplication: {}", e);
+            std::process::exit(1);
+        }
+    };
+    let mut http_endpoints = Router::new()
+        .merge(payment_endpoints)
+        .merge(health::routes().with_state(health_history.clone()))
+        .merge(
+            approval::routes()
+                .route_layer(axum::middleware::from_fn_with_state(
+                    run_mode.clone(),
+                    handlers::standby_guard,
+                ))
+                .with_state(approvals),
+        )
+        .merge(settle_queue::routes().with_state(queue_records.clone()))
+        .merge(settle_queue::settlement_routes().with_state(Settlements {
+            records: queue_records.clone(),
+            providers: provider_cache.clone(),
+        }))
+        .merge(settlement_stream::routes().with_state(SettlementStream {
+            records: queue_records,
+            events: settlement_stream_events,
+        }))
+        .merge(match fees {
+            Some(fees) => fees::routes().with_state(fees),
+            None => Router::new(),
+        })
+        .merge(match slo_tracker {
+            Some(slo_tracker) => slo::routes().with_state(slo_tracker),
+            None => Router::new(),
+        })
+        .merge(identity::routes().with_state(identity.clone()))
+        .merge(
+            chain::aa::routes()
+                .with_state(Sponsorship::new(provider_cache.clone(), sponsor_limiter)),
+        )
+        .merge(
+            chain::cancel::routes()
+                .route_layer(axum::middleware::from_fn_with_state(
+                    run_mode.clone(),
+                    handlers::standby_guard,
+                ))
+                .with_state(provider_cache.clone()),
+        )
+        .merge(match bridge_adapter {
+            Some(bridge_adapter) => routing::routes().with_state(bridge_adapter),
+            None => Router::new(),
+        })
+        // Served on standby nodes too, which follow the nonces of the active one.
+        .merge(match &replay_replication {
+            Some(replay_replication) => {
+                replication::routes().with_state(replay_replication.endpoint(seen_nonces))
+            }
+            None => Router::new(),
+        })
+        // Settlement happens on the active node, which must know the reservations.
+        .merge(
+            nonces::routes()
+                .route_layer(axum::middleware::from_fn_with_state(
+                    run_mode,
+                    handlers::standby_guard,
+                ))
+                .with_state(nonce_reservations),
+        );
+
+    match MirrorServer::from_env(host) {
+        Ok(Some(mirror_server)) => {
+            mirror_server
+                .spawn(
+                    settlement_log,
+                    axum_state.clone(),
+                    identity.clone(),
+                    sig_down.cancellation_token(),
+                )
+                .await?
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to configure mirror listener: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    match grpc::GrpcServer::from_env(host) {
+        Ok(Some(grpc_server)) => {
+            grpc_server
+                .spawn(axum_state.clone(), sig_down.cancellation_token())
+                .await?
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to configure gRPC listener: {}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // With a dedicated ops listener, the admin API is served there only.
+    match OpsServer::from_env(host) {
+        Some(ops_server) => {
+            let ops_endpoints = Router::new()
+                .merge(handlers::ops_routes(identity.clone()).with_state(axum_state))
+                .merge(health::routes().with_state(health_history))
+                .merge(admin_endpoints)
+                .layer(telemetry.http_tracing());
+            ops_server.spawn(ops_endpoints, sig_down.cancellation_token())?;
+        }
+        None => http_endpoints = http_endpoints.merge(admin_endpoints),
+    }
+
+    let catalogs = match Catalogs::from_env() {
+        Ok(catalogs) => catalogs,
+        Err(e) => {
+            tracing::error!("Failed to load message catalogs: {}", e);
+            std::process::exit(1);
+        }
+    };
+    if !catalogs.is_empty() {
+        http_endpoints = http_endpoints.layer(axum::middleware::from_fn_with_state(
+            catalogs,
+            locale::localize,
+        ));
+    }
+    if !maintenance.is_empty() {
+        http_endpoints = http_endpoints.layer(axum::middleware::from_fn_with_state(
+            maintenance,
+            maintenance::announce_maintenance,
+        ));
+    }
+
+    let http_endpoints = http_endpoints.layer(telemetry.http_tracing()).layer(
+        cors::CorsLayer::new()
+            .allow_origin(cors::Any)
+            .allow_methods([Method::GET, Method::POST])
+            .allow_headers(cors::Any),
+    );
+
+    let addr = SocketAddr::new(host, port);
+    let axum_cancellation_token = sig_down.cancellation_token();
+    let axum_graceful_shutdown = async move { axum_cancellation_token.cancelled().await };
+
+    #[cfg(feature = "acme")]
+    match acme::AcmeServer::from_env(host) {
+        Ok(Some(acme_server)) => {
+            tracing::info!("Starting server at https://{}", addr);
+            let listener = acme_server
+                .listen(addr, sig_down.cancellation_token())
+                .await
+                .unwrap_or_else(|e| {
+                    tracing::error!("Failed to start HTTPS listener on {}: {}", addr, e);
+                    std::process::exit(1);
+                });
+            axum::serve(listener, http_endpoints)
+                .with_graceful_shutdown(axum_graceful_shutdown)
+                .await?;
+            return Ok(());
+        }
+        Ok(None) => {}
+        Err(e) => {
+            tracing::error!("Failed to configure ACME: {}", e);
+            std::process::exit(1);
+        }
+    }
+
     tracing::info!("Starting server at http://{}", addr);
 
     let listener = tokio::net::TcpListener::bind(addr)
@@ -100,9 +688,6 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             std::process::exit(1);
         });
 
-    let sig_down = SigDown::try_new()?;
-    let axum_cancellation_token = sig_down.cancellation_token();
-    let axum_graceful_shutdown = async move { axum_cancellation_token.cancelled().await };
     axum::serve(listener, http_endpoints)
         .with_graceful_shutdown(axum_graceful_shutdown)
         .await?;
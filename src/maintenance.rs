@@ -0,0 +1,213 @@
+//! Announcement of planned maintenance windows.
+//!
+//! Operators declare upcoming downtime of the facilitator, or of one of its networks, so that client SDKs can fail
+//! over to another facilitator before it starts rather than after requests begin to fail. Windows that have not
+//! ended yet are announced in two places:
+//! - under `maintenance` in `GET /supported`, by [`MaintenanceGate`], for networks the facilitator supports;
+//! - in the `X-Maintenance` header of every HTTP response, by [`announce_maintenance`], so resource servers can pass
+//!   the hint on with their `402 Payment Required` answers.
+//!
+//! A window is written `<start>/<end>`, in Unix seconds, followed by `@<network>` if only that network is affected,
+//! e.g. `1767225600/1767232800@base`. The header lists windows in the same syntax, separated by commas.
+//!
+//! Environment variables used:
+//! - `MAINTENANCE_WINDOWS` — comma-separated maintenance windows. Nothing is announced if unset.
+
+use axum::extract::{Request, State};
+use axum::http::HeaderValue;
+use axum::middleware::Next;
+use axum::response::Response;
+use std::str::FromStr;
+use std::sync::Arc;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    MaintenanceWindow, SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest,
+    VerifyResponse,
+};
+
+/// Response header listing the announced maintenance windows.
+pub const MAINTENANCE_HEADER: &str = "x-maintenance";
+
+/// Declared maintenance windows.
+///
+/// Cheap to clone: all clones share the same windows.
+#[derive(Debug, Clone, Default)]
+pub struct MaintenanceSchedule {
+    windows: Arc<Vec<MaintenanceWindow>>,
+}
+
+impl MaintenanceSchedule {
+    pub fn new(windows: Vec<MaintenanceWindow>) -> Self {
+        Self {
+            windows: Arc::new(windows),
+        }
+    }
+
+    /// Reads the windows from `MAINTENANCE_WINDOWS`; an empty schedule if unset.
+    pub fn from_env() -> Result<Self, String> {
+        match std::env::var(from_env::ENV_MAINTENANCE_WINDOWS) {
+            Ok(value) => parse_windows(&value)
+                .map(Self::new)
+                .map_err(|e| format!("{}: {e}", from_env::ENV_MAINTENANCE_WINDOWS)),
+            Err(_) => Ok(Self::default()),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.windows.is_empty()
+    }
+
+    /// Windows not ended at `now`, ongoing ones included.
+    pub fn upcoming(&self, now: UnixTimestamp) -> impl Iterator<Item = &MaintenanceWindow> {
+        self.windows.iter().filter(move |window| window.end > now)
+    }
+
+    /// Value of the [`MAINTENANCE_HEADER`] at `now`; `None` if nothing is announced.
+    fn header_value(&self, now: UnixTimestamp) -> Option<HeaderValue> {
+        let windows = self
+            .upcoming(now)
+            .map(format_window)
+            .collect::<Vec<_>>()
+            .join(",");
+        if windows.is_empty() {
+            return None;
+        }
+        HeaderValue::from_str(&windows).ok()
+    }
+}
+
+fn parse_windows(windows: &str) -> Result<Vec<MaintenanceWindow>, String> {
+    windows
+        .split(',')
+        .map(str::trim)
+        .filter(|window| !window.is_empty())
+        .map(|window| {
+            let (period, network) = match window.split_once('@') {
+                Some((period, network)) => {
+                    let network = Network::from_str(network.trim())
+                        .map_err(|_| format!("unknown network {network}"))?;
+                    (period, Some(network))
+                }
+                None => (window, None),
+            };
+            let (start, end) = period
+                .split_once('/')
+                .ok_or_else(|| format!("expected start/end, got {window}"))?;
+            let timestamp = |value: &str| {
+                value
+                    .trim()
+                    .parse::<u64>()
+                    .map(UnixTimestamp)
+                    .map_err(|_| format!("invalid timestamp {value} in {window}"))
+            };
+            let (start, end) = (timestamp(start)?, timestamp(end)?);
+            if start >= end {
+                return Err(format!("window {window} ends before it starts"));
+            }
+            Ok(MaintenanceWindow {
+                start,
+                end,
+                network,
+            })
+        })
+        .collect()
+}
+
+fn format_window(window: &MaintenanceWindow) -> String {
+    match window.network {
+        Some(network) => format!("{}/{}@{network}", window.start, window.end),
+        None => format!("{}/{}", window.start, window.end),
+    }
+}
+
+/// Sets the [`MAINTENANCE_HEADER`] on responses while maintenance windows are announced.
+pub async fn announce_maintenance(
+    State(schedule): State<MaintenanceSchedule>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    let now = UnixTimestamp::try_now().unwrap_or(UnixTimestamp(0));
+    if let Some(value) = schedule.header_value(now) {
+        response.headers_mut().insert(MAINTENANCE_HEADER, value);
+    }
+    response
+}
+
+/// [`Facilitator`] decorator listing the upcoming windows of its [`MaintenanceSchedule`] in `supported`.
+///
+/// Windows of networks the inner facilitator does not support are left out.
+pub struct MaintenanceGate<F> {
+    facilitator: F,
+    schedule: MaintenanceSchedule,
+}
+
+impl<F> MaintenanceGate<F> {
+    pub fn new(facilitator: F, schedule: MaintenanceSchedule) -> Self {
+        Self {
+            facilitator,
+            schedule,
+        }
+    }
+}
+
+impl<F> Facilitator for MaintenanceGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        self.facilitator.settle(request).await
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        let mut supported = self.facilitator.supported().await?;
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        supported.maintenance = self
+            .schedule
+            .upcoming(now)
+            .filter(|window| match window.network {
+                Some(network) => {
+                    let network = network.to_string();
+                    supported.kinds.iter().any(|kind| kind.network == network)
+                }
+                None => true,
+            })
+            .cloned()
+            .collect();
+        Ok(supported)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_are_announced_until_they_end() {
+        let schedule =
+            MaintenanceSchedule::new(parse_windows("100/200, 300/400@base-sepolia").unwrap());
+        assert_eq!(
+            schedule.header_value(UnixTimestamp(150)).unwrap(),
+            "100/200,300/400@base-sepolia"
+        );
+        assert_eq!(
+            schedule.header_value(UnixTimestamp(200)).unwrap(),
+            "300/400@base-sepolia"
+        );
+        assert!(schedule.header_value(UnixTimestamp(400)).is_none());
+        assert!(parse_windows("200/100").is_err());
+        assert!(parse_windows("100-200").is_err());
+        assert!(parse_windows("100/200@nowhere").is_err());
+    }
+}
@@ -0,0 +1,410 @@
+//! Read-only mirror API for analytics consumers.
+//!
+//! Analytics and BI tools need settlement data, but must not reach the protocol or admin endpoints. When
+//! `MIRROR_PORT` is set, a separate listener serves only:
+//! - `GET /settlements` — recent settlements, oldest first, paginated with `?cursor=...&limit=...`,
+//! - `GET /stats` — settlement counts and volumes per network and asset since startup,
+//! - `GET /supported` — supported payment kinds,
+//! - `GET /version` — build metadata, signed by the identity key.
+//!
+//! Every request must present one of `MIRROR_API_KEYS` as `Authorization: Bearer <key>`; these keys grant nothing
+//! else, and admin keys are not accepted. Settlements are recorded by [`SettlementRecorder`] and kept in memory.
+//!
+//! Environment variables used:
+//! - `MIRROR_PORT` — port of the mirror listener, bound on `HOST`. Disabled if unset.
+//! - `MIRROR_API_KEYS` — comma-separated keys accepted by the mirror. Required with `MIRROR_PORT`.
+//! - `MIRROR_RETENTION` — number of most recent settlements kept (default: `10000`).
+
+use alloy::primitives::U256;
+use axum::extract::{Query, Request, State};
+use axum::http::{StatusCode, header};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
+
+use crate::admin::error_response;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::handlers;
+use crate::identity::FacilitatorIdentity;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    FacilitatorErrorReason, MixedAddress, Page, PageRequest, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, TokenAmount, TransactionHash, VerifyRequest, VerifyResponse,
+};
+
+const DEFAULT_RETENTION: usize = 10_000;
+
+/// A settlement as served by `GET /settlements`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementRecord {
+    /// Sequence number of the settlement since startup.
+    pub id: u64,
+    pub network: Network,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub payer: Option<MixedAddress>,
+    pub pay_to: MixedAddress,
+    pub asset: MixedAddress,
+    pub amount: TokenAmount,
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<FacilitatorErrorReason>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionHash>,
+    pub settled_at: UnixTimestamp,
+}
+
+/// Settlement totals of one network and asset, as served by `GET /stats`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementStats {
+    pub network: Network,
+    pub asset: MixedAddress,
+    pub settled: u64,
+    pub failed: u64,
+    /// Sum of the amounts of successful settlements.
+    pub volume: TokenAmount,
+}
+
+#[derive(Default)]
+struct LogState {
+    next_id: u64,
+    records: VecDeque<SettlementRecord>,
+    /// Totals by network and asset, kept beyond the retained records.
+    stats: BTreeMap<(String, String), SettlementStats>,
+}
+
+/// Recent settlements and totals since startup.
+#[derive(Clone)]
+pub struct SettlementLog {
+    retention: usize,
+    state: Arc<Mutex<LogState>>,
+}
+
+impl Default for SettlementLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_RETENTION)
+    }
+}
+
+impl SettlementLog {
+    pub fn new(retention: usize) -> Self {
+        Self {
+            retention,
+            state: Arc::new(Mutex::new(LogState::default())),
+        }
+    }
+
+    /// Log keeping `MIRROR_RETENTION` settlements.
+    pub fn from_env() -> Result<Self, String> {
+        let retention = match std::env::var(from_env::ENV_MIRROR_RETENTION) {
+            Ok(value) => value
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_MIRROR_RETENTION))?,
+            Err(_) => DEFAULT_RETENTION,
+        };
+        Ok(Self::new(retention))
+    }
+
+    fn record(&self, request: &SettleRequest, response: &SettleResponse, now: UnixTimestamp) {
+        let requirements = &request.payment_requirements;
+        let amount = request
+            .settle_amount
+            .unwrap_or(requirements.max_amount_required);
+        let mut state = self.state.lock().expect("settlement log poisoned");
+        let id = state.next_id;
+        state.next_id += 1;
+        let stats = state
+            .stats
+            .entry((response.network.to_string(), requirements.asset.to_string()))
+            .or_insert_with(|| SettlementStats {
+                network: response.network,
+                asset: requirements.asset.clone(),
+                settled: 0,
+                failed: 0,
+                volume: U256::ZERO.into(),
+            });
+        if response.success {
+            stats.settled += 1;
+            stats.volume = stats.volume.0.saturating_add(amount.0).into();
+        } else {
+            stats.failed += 1;
+        }
+        state.records.push_back(SettlementRecord {
+            id,
+            network: response.network,
+            payer: response.payer.clone(),
+            pay_to: requirements.pay_to.clone(),
+            asset: requirements.asset.clone(),
+            amount,
+            success: response.success,
+            error_reason: response.error_reason.clone(),
+            transaction: response.transaction.clone(),
+            settled_at: now,
+        });
+        while state.records.len() > self.retention {
+            state.records.pop_front();
+        }
+    }
+
+    /// Retained settlements, oldest first.
+    pub fn records(&self) -> Vec<SettlementRecord> {
+        let state = self.state.lock().expect("settlement log poisoned");
+        state.records.iter().cloned().collect()
+    }
+
+    /// Totals per network and asset, sorted by network then asset.
+    pub fn stats(&self) -> Vec<SettlementStats> {
+        let state = self.state.lock().expect("settlement log poisoned");
+        state.stats.values().cloned().collect()
+    }
+}
+
+/// [`Facilitator`] decorator recording settlement outcomes in a [`SettlementLog`].
+///
+/// Only settlements answered with a receipt are recorded, successful or not; rejected requests are not.
+pub struct SettlementRecorder<F> {
+    facilitator: F,
+    log: SettlementLog,
+}
+
+impl<F> SettlementRecorder<F> {
+    pub fn new(facilitator: F, log: SettlementLog) -> Self {
+        Self { facilitator, log }
+    }
+}
+
+impl<F> Facilitator for SettlementRecorder<F>
+where
+    F: Facilitator + Sync + Send,
+{
+    type Error = F::Error;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let response = self.facilitator.settle(request).await?;
+        if let Ok(now) = UnixTimestamp::try_now() {
+            self.log.record(request, &response, now);
+        }
+        Ok(response)
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+/// Keys accepted by the mirror.
+#[derive(Debug, Clone)]
+pub struct MirrorAuth {
+    keys: Arc<HashSet<String>>,
+}
+
+impl MirrorAuth {
+    pub fn new(keys: HashSet<String>) -> Self {
+        Self {
+            keys: Arc::new(keys),
+        }
+    }
+
+    fn allows(&self, request: &Request) -> bool {
+        request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .is_some_and(|key| self.keys.contains(key))
+    }
+}
+
+async fn authorize(State(auth): State<MirrorAuth>, request: Request, next: Next) -> Response {
+    if auth.allows(&request) {
+        next.run(request).await
+    } else {
+        error_response(StatusCode::UNAUTHORIZED, "Unauthorized")
+    }
+}
+
+/// Mirror listener with its keys.
+#[derive(Debug, Clone)]
+pub struct MirrorServer {
+    addr: SocketAddr,
+    auth: MirrorAuth,
+}
+
+impl MirrorServer {
+    pub fn new(addr: SocketAddr, auth: MirrorAuth) -> Self {
+        Self { addr, auth }
+    }
+
+    /// Listener on `MIRROR_PORT` of `host` with `MIRROR_API_KEYS`; `Ok(None)` if `MIRROR_PORT` is unset.
+    pub fn from_env(host: std::net::IpAddr) -> Result<Option<Self>, String> {
+        let Ok(port) = std::env::var(from_env::ENV_MIRROR_PORT) else {
+            return Ok(None);
+        };
+        let port = port
+            .parse::<u16>()
+            .map_err(|e| format!("{}: {e}", from_env::ENV_MIRROR_PORT))?;
+        let keys: HashSet<String> = std::env::var(from_env::ENV_MIRROR_API_KEYS)
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|key| !key.is_empty())
+            .map(str::to_string)
+            .collect();
+        if keys.is_empty() {
+            return Err(format!(
+                "{} is required with {}",
+                from_env::ENV_MIRROR_API_KEYS,
+                from_env::ENV_MIRROR_PORT
+            ));
+        }
+        Ok(Some(Self::new(
+            SocketAddr::new(host, port),
+            MirrorAuth::new(keys),
+        )))
+    }
+
+    /// Binds the listener and serves the mirror of `log` and `facilitator`, with `/version` signed by `identity`,
+    /// until `cancellation_token` is cancelled.
+    pub async fn spawn<A>(
+        self,
+        log: SettlementLog,
+        facilitator: A,
+        identity: FacilitatorIdentity,
+        cancellation_token: CancellationToken,
+    ) -> std::io::Result<()>
+    where
+        A: Facilitator + Clone + Send + Sync + 'static,
+        A::Error: IntoResponse,
+    {
+        let router = Router::new()
+            .merge(routes().with_state(log))
+            .merge(
+                Router::new()
+                    .route("/supported", get(handlers::get_supported::<A>))
+                    .route(
+                        "/version",
+                        get(handlers::get_version).layer(Extension(identity)),
+                    )
+                    .with_state(facilitator),
+            )
+            .layer(middleware::from_fn_with_state(self.auth, authorize));
+        let listener = tokio::net::TcpListener::bind(self.addr).await?;
+        tracing::info!("Starting mirror server at http://{}", self.addr);
+        tokio::spawn(async move {
+            let shutdown = async move { cancellation_token.cancelled().await };
+            if let Err(e) = axum::serve(listener, router)
+                .with_graceful_shutdown(shutdown)
+                .await
+            {
+                tracing::error!("Mirror server failed: {}", e);
+            }
+        });
+        Ok(())
+    }
+}
+
+/// Routes serving the settlement log.
+pub fn routes() -> Router<SettlementLog> {
+    Router::new()
+        .route("/settlements", get(get_settlements))
+        .route("/stats", get(get_stats))
+}
+
+/// `GET /settlements`: Recent settlements, oldest first.
+#[instrument(skip_all)]
+pub async fn get_settlements(
+    State(log): State<SettlementLog>,
+    Query(page_request): Query<PageRequest>,
+) -> Response {
+    let page: Result<Page<SettlementRecord>, _> =
+        page_request.paginate(log.records(), |record| record.id);
+    match page {
+        Ok(page) => (StatusCode::OK, Json(page)).into_response(),
+        Err(e) => error_response(StatusCode::BAD_REQUEST, &e.to_string()),
+    }
+}
+
+/// `GET /stats`: Settlement totals per network and asset since startup.
+#[instrument(skip_all)]
+pub async fn get_stats(State(log): State<SettlementLog>) -> impl IntoResponse {
+    Json(log.stats())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_recent_settlements_and_totals() {
+        let log = SettlementLog::new(2);
+        let request: SettleRequest = serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {
+                    "signature": "0x00",
+                    "authorization": {
+                        "from": "0x1111111111111111111111111111111111111111",
+                        "to": "0x2222222222222222222222222222222222222222",
+                        "value": "1000",
+                        "validAfter": "0",
+                        "validBefore": "99999999999",
+                        "nonce": "0x0000000000000000000000000000000000000000000000000000000000000000",
+                    },
+                },
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": "1000",
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x2222222222222222222222222222222222222222",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+                "extra": null,
+            },
+        }))
+        .unwrap();
+        let response = |success| SettleResponse {
+            success,
+            error_reason: None,
+            payer: None,
+            transaction: None,
+            network: Network::Base,
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: false,
+        };
+        for success in [true, false, true] {
+            log.record(&request, &response(success), UnixTimestamp(0));
+        }
+        let records = log.records();
+        assert_eq!(records.iter().map(|r| r.id).collect::<Vec<_>>(), [1, 2]);
+        let stats = log.stats();
+        assert_eq!((stats[0].settled, stats[0].failed), (2, 1));
+        assert_eq!(stats[0].volume, TokenAmount::from(2000u64));
+    }
+}
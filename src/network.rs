@@ -2,55 +2,70 @@
 //!
 //! This module defines supported networks and their chain IDs,
 //! and provides statically known USDC deployments per network.
+//!
+//! Besides the built-in networks, operators can register further EVM chains at runtime
+//! (see [`CustomNetwork`]), from the config file or the admin API, without recompiling.
 
+use crate::from_env;
 use crate::types::{MixedAddress, TokenAsset, TokenDeployment, TokenDeploymentEip712};
-use alloy::primitives::address;
+use alloy::primitives::utils::{format_units, parse_units};
+use alloy::primitives::{U256, address};
 use once_cell::sync::Lazy;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use solana_sdk::pubkey::Pubkey;
 use std::borrow::Borrow;
+use std::collections::HashMap;
 use std::fmt::{Display, Formatter};
 use std::ops::Deref;
 use std::str::FromStr;
+use std::sync::RwLock;
 
 /// Supported Ethereum-compatible networks.
 ///
 /// Used to differentiate between testnet and mainnet environments for the x402 protocol.
-#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Serialized by name, e.g. `base-sepolia`.
+#[derive(Debug, Hash, Clone, Copy, PartialEq, Eq)]
 pub enum Network {
     /// Base Sepolia testnet (chain ID 84532).
-    #[serde(rename = "base-sepolia")]
     BaseSepolia,
     /// Base mainnet (chain ID 8453).
-    #[serde(rename = "base")]
     Base,
     /// XDC mainnet (chain ID 50).
-    #[serde(rename = "xdc")]
     XdcMainnet,
     /// Avalanche Fuji testnet (chain ID 43113)
-    #[serde(rename = "avalanche-fuji")]
     AvalancheFuji,
     /// Avalanche Mainnet (chain ID 43114)
-    #[serde(rename = "avalanche")]
     Avalanche,
     /// Solana Mainnet - Live production environment for deployed applications
-    #[serde(rename = "solana")]
     Solana,
     /// Solana Devnet - Testing with public accessibility for developers experimenting with their applications
-    #[serde(rename = "solana-devnet")]
     SolanaDevnet,
     /// Polygon Amoy testnet (chain ID 80002).
-    #[serde(rename = "polygon-amoy")]
     PolygonAmoy,
     /// Polygon mainnet (chain ID 137).
-    #[serde(rename = "polygon")]
     Polygon,
     /// Sei mainnet (chain ID 1329).
-    #[serde(rename = "sei")]
     Sei,
     /// Sei testnet (chain ID 1328).
-    #[serde(rename = "sei-testnet")]
     SeiTestnet,
+    /// Arbitrum One (chain ID 42161).
+    Arbitrum,
+    /// OP Mainnet (chain ID 10).
+    Optimism,
+    /// Bitcoin Lightning Network, BOLT11 invoices prefixed `lnbc`.
+    Lightning,
+    /// Lightning Network on Bitcoin testnet, signet or regtest (`lntb`, `lntbs` and `lnbcrt` invoices).
+    LightningTestnet,
+    /// NEAR Protocol mainnet.
+    Near,
+    /// NEAR Protocol testnet.
+    NearTestnet,
+    /// Tron mainnet.
+    Tron,
+    /// Tron Nile testnet.
+    TronNile,
+    /// EVM chain registered at runtime, by chain ID (see [`CustomNetwork`]).
+    Custom(u64),
 }
 
 impl Display for Network {
@@ -67,14 +82,56 @@ impl Display for Network {
             Network::Polygon => write!(f, "polygon"),
             Network::Sei => write!(f, "sei"),
             Network::SeiTestnet => write!(f, "sei-testnet"),
+            Network::Arbitrum => write!(f, "arbitrum"),
+            Network::Optimism => write!(f, "optimism"),
+            Network::Lightning => write!(f, "lightning"),
+            Network::LightningTestnet => write!(f, "lightning-testnet"),
+            Network::Near => write!(f, "near"),
+            Network::NearTestnet => write!(f, "near-testnet"),
+            Network::Tron => write!(f, "tron"),
+            Network::TronNile => write!(f, "tron-nile"),
+            Network::Custom(chain_id) => match CustomNetwork::by_chain_id(*chain_id) {
+                Some(custom) => write!(f, "{}", custom.name),
+                None => write!(f, "eip155:{chain_id}"),
+            },
         }
     }
 }
 
+impl FromStr for Network {
+    type Err = String;
+
+    /// Parses a built-in network name, or the name of a registered [`CustomNetwork`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(network) = Network::variants().iter().find(|n| n.to_string() == s) {
+            return Ok(*network);
+        }
+        CustomNetwork::by_name(s)
+            .map(|custom| Network::Custom(custom.chain_id))
+            .ok_or_else(|| format!("Unknown network {s}"))
+    }
+}
+
+impl Serialize for Network {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Network {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let name = String::deserialize(deserializer)?;
+        Network::from_str(&name).map_err(serde::de::Error::custom)
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub enum NetworkFamily {
     Evm,
     Solana,
+    Lightning,
+    Near,
+    Tron,
 }
 
 impl From<Network> for NetworkFamily {
@@ -91,12 +148,21 @@ impl From<Network> for NetworkFamily {
             Network::Polygon => NetworkFamily::Evm,
             Network::Sei => NetworkFamily::Evm,
             Network::SeiTestnet => NetworkFamily::Evm,
+            Network::Arbitrum => NetworkFamily::Evm,
+            Network::Optimism => NetworkFamily::Evm,
+            Network::Lightning => NetworkFamily::Lightning,
+            Network::LightningTestnet => NetworkFamily::Lightning,
+            Network::Near => NetworkFamily::Near,
+            Network::NearTestnet => NetworkFamily::Near,
+            Network::Tron => NetworkFamily::Tron,
+            Network::TronNile => NetworkFamily::Tron,
+            Network::Custom(_) => NetworkFamily::Evm,
         }
     }
 }
 
 impl Network {
-    /// Return all known [`Network`] variants.
+    /// Return all built-in [`Network`] variants; registered custom networks are not included.
     pub fn variants() -> &'static [Network] {
         &[
             Network::BaseSepolia,
@@ -110,8 +176,242 @@ impl Network {
             Network::Polygon,
             Network::Sei,
             Network::SeiTestnet,
+            Network::Arbitrum,
+            Network::Optimism,
+            Network::Lightning,
+            Network::LightningTestnet,
+            Network::Near,
+            Network::NearTestnet,
+            Network::Tron,
+            Network::TronNile,
         ]
     }
+
+    /// Default native gas token of the network.
+    ///
+    /// Can be overridden per network with `NATIVE_TOKEN_<NETWORK>`, e.g. for an Avalanche subnet
+    /// whose gas token is not AVAX (see [`NativeToken::from_env`]).
+    pub fn native_token(&self) -> NativeToken {
+        let (symbol, decimals) = match self {
+            Network::BaseSepolia | Network::Base => ("ETH", 18),
+            Network::Arbitrum | Network::Optimism => ("ETH", 18),
+            Network::XdcMainnet => ("XDC", 18),
+            Network::AvalancheFuji | Network::Avalanche => ("AVAX", 18),
+            Network::Solana | Network::SolanaDevnet => ("SOL", 9),
+            Network::PolygonAmoy | Network::Polygon => ("POL", 18),
+            Network::Sei | Network::SeiTestnet => ("SEI", 18),
+            Network::Lightning | Network::LightningTestnet => ("BTC", 11),
+            Network::Near | Network::NearTestnet => ("NEAR", 24),
+            Network::Tron | Network::TronNile => ("TRX", 6),
+            Network::Custom(chain_id) => {
+                if let Some(native_token) =
+                    CustomNetwork::by_chain_id(*chain_id).and_then(|c| c.native_token)
+                {
+                    return native_token;
+                }
+                ("ETH", 18)
+            }
+        };
+        NativeToken {
+            symbol: symbol.to_string(),
+            decimals,
+        }
+    }
+}
+
+impl Network {
+    /// CAIP-2 chain ID of the network, as used by x402 version 2, e.g. `eip155:8453`.
+    ///
+    /// Lightning networks have none and keep their name.
+    pub fn caip2(&self) -> String {
+        let chain_id = match self {
+            Network::BaseSepolia => 84532,
+            Network::Base => 8453,
+            Network::XdcMainnet => 50,
+            Network::AvalancheFuji => 43113,
+            Network::Avalanche => 43114,
+            Network::PolygonAmoy => 80002,
+            Network::Polygon => 137,
+            Network::Sei => 1329,
+            Network::SeiTestnet => 1328,
+            Network::Arbitrum => 42161,
+            Network::Optimism => 10,
+            Network::Custom(chain_id) => *chain_id,
+            Network::Solana => return "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp".to_string(),
+            Network::SolanaDevnet => return "solana:EtWTRABZaYq6iMfeYKouRu166VU2xqa1".to_string(),
+            Network::Near => return "near:mainnet".to_string(),
+            Network::NearTestnet => return "near:testnet".to_string(),
+            Network::Tron => return "tron:0x2b6653dc".to_string(),
+            Network::TronNile => return "tron:0xcd8690dc".to_string(),
+            Network::Lightning | Network::LightningTestnet => return self.to_string(),
+        };
+        format!("eip155:{chain_id}")
+    }
+
+    /// Network of a CAIP-2 chain ID, or of a network name as in version 1.
+    pub fn from_caip2(s: &str) -> Result<Network, String> {
+        if let Some(network) = Network::variants().iter().find(|n| n.caip2() == s) {
+            return Ok(*network);
+        }
+        if let Some(chain_id) = s.strip_prefix("eip155:").and_then(|id| id.parse().ok())
+            && CustomNetwork::by_chain_id(chain_id).is_some()
+        {
+            return Ok(Network::Custom(chain_id));
+        }
+        Network::from_str(s)
+    }
+}
+
+/// Serde of a [`Network`] as its CAIP-2 chain ID, for x402 version 2 types; network names are accepted too.
+pub mod caip2 {
+    use super::Network;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(network: &Network, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&network.caip2())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Network, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        Network::from_caip2(&s).map_err(serde::de::Error::custom)
+    }
+}
+
+/// Custom networks registered so far, by chain ID.
+static CUSTOM_NETWORKS: Lazy<RwLock<HashMap<u64, CustomNetwork>>> =
+    Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// An EVM chain not built into the facilitator, registered at runtime.
+///
+/// Once registered, it is known as [`Network::Custom`] and accepted by name wherever a network is,
+/// e.g. in payment payloads. Registrations last until the process exits.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomNetwork {
+    /// Network name used in x402 messages, e.g. `my-rollup`.
+    pub name: String,
+    pub chain_id: u64,
+    /// Whether the chain supports EIP-1559 transactions.
+    #[serde(default = "default_eip1559")]
+    pub eip1559: bool,
+    /// Native gas token; ETH with 18 decimals if unset.
+    #[serde(default)]
+    pub native_token: Option<NativeToken>,
+}
+
+fn default_eip1559() -> bool {
+    true
+}
+
+impl CustomNetwork {
+    pub fn new(name: String, chain_id: u64) -> Self {
+        Self {
+            name,
+            chain_id,
+            eip1559: default_eip1559(),
+            native_token: None,
+        }
+    }
+
+    /// Registers the network, making its name resolvable.
+    ///
+    /// Registering the exact same network again is a no-op. Fails if the name is taken by a built-in
+    /// network, or if the name or chain ID is already registered otherwise.
+    pub fn register(self) -> Result<Network, String> {
+        let is_valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+        if !is_valid_name {
+            return Err(format!(
+                "Invalid network name {:?}: use lowercase letters, digits and dashes",
+                self.name
+            ));
+        }
+        if Network::variants()
+            .iter()
+            .any(|n| n.to_string() == self.name)
+        {
+            return Err(format!("{} is a built-in network", self.name));
+        }
+        let mut networks = CUSTOM_NETWORKS.write().unwrap_or_else(|e| e.into_inner());
+        if let Some(existing) = networks.get(&self.chain_id) {
+            return if *existing == self {
+                Ok(Network::Custom(self.chain_id))
+            } else {
+                Err(format!(
+                    "Chain ID {} is already registered as {}",
+                    self.chain_id, existing.name
+                ))
+            };
+        }
+        if networks.values().any(|existing| existing.name == self.name) {
+            return Err(format!("Network {} is already registered", self.name));
+        }
+        let network = Network::Custom(self.chain_id);
+        networks.insert(self.chain_id, self);
+        Ok(network)
+    }
+
+    pub fn by_chain_id(chain_id: u64) -> Option<CustomNetwork> {
+        let networks = CUSTOM_NETWORKS.read().unwrap_or_else(|e| e.into_inner());
+        networks.get(&chain_id).cloned()
+    }
+
+    pub fn by_name(name: &str) -> Option<CustomNetwork> {
+        let networks = CUSTOM_NETWORKS.read().unwrap_or_else(|e| e.into_inner());
+        networks.values().find(|c| c.name == name).cloned()
+    }
+}
+
+/// Native gas token of a network: the currency used to pay transaction fees.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NativeToken {
+    pub symbol: String,
+    pub decimals: u8,
+}
+
+impl NativeToken {
+    /// Native token of `network`, honouring the `NATIVE_TOKEN_<NETWORK>` override.
+    ///
+    /// The override has the form `SYMBOL:DECIMALS`, e.g. `NATIVE_TOKEN_AVALANCHE=DFK:18`.
+    pub fn from_env(network: Network) -> Result<Self, String> {
+        let env_var = from_env::native_token_env_name_from_network(network);
+        let Ok(value) = std::env::var(&env_var) else {
+            return Ok(network.native_token());
+        };
+        let (symbol, decimals) = value
+            .split_once(':')
+            .ok_or_else(|| format!("{env_var} must be in the form SYMBOL:DECIMALS"))?;
+        let decimals = decimals
+            .trim()
+            .parse::<u8>()
+            .map_err(|e| format!("{env_var} has invalid decimals: {e}"))?;
+        Ok(NativeToken {
+            symbol: symbol.trim().to_string(),
+            decimals,
+        })
+    }
+
+    /// Formats a raw amount in the smallest unit as a human-readable value, e.g. `0.0021 AVAX`.
+    pub fn format_amount(&self, amount: U256) -> String {
+        match format_units(amount, self.decimals) {
+            Ok(units) if units.contains('.') => {
+                let units = units.trim_end_matches('0').trim_end_matches('.');
+                format!("{units} {}", self.symbol)
+            }
+            Ok(units) => format!("{units} {}", self.symbol),
+            Err(_) => format!("{amount} (10^-{}) {}", self.decimals, self.symbol),
+        }
+    }
+
+    /// Parses a decimal amount of this token, e.g. `0.01`, into its smallest unit.
+    pub fn parse_amount(&self, amount: &str) -> Result<U256, String> {
+        parse_units(amount.trim(), self.decimals)
+            .map(|units| units.get_absolute())
+            .map_err(|e| e.to_string())
+    }
 }
 
 /// Lazily initialized known USDC deployment on Base Sepolia as [`USDCDeployment`].
@@ -125,6 +425,7 @@ static USDC_BASE_SEPOLIA: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USDC".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -140,6 +441,7 @@ static USDC_BASE: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USD Coin".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -155,6 +457,7 @@ static USDC_XDC: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "Bridged USDC(XDC)".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -170,6 +473,7 @@ static USDC_AVALANCHE_FUJI: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USD Coin".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -185,6 +489,7 @@ static USDC_AVALANCHE: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USD Coin".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -228,6 +533,7 @@ static USDC_POLYGON_AMOY: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USDC".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -243,6 +549,25 @@ static USDC_POLYGON: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USDC".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
+        }),
+    })
+});
+
+/// Lazily initialized bridged USDC (USDC.e) deployment on Polygon mainnet as [`USDCDeployment`].
+///
+/// Predates native USDC on Polygon and is still widely held. Its EIP-712 domain puts the chain ID in `salt`.
+static USDC_E_POLYGON: Lazy<USDCDeployment> = Lazy::new(|| {
+    USDCDeployment(TokenDeployment {
+        asset: TokenAsset {
+            address: address!("0x2791Bca1f2de4661ED88A30C99A7a9449Aa84174").into(),
+            network: Network::Polygon,
+        },
+        decimals: 6,
+        eip712: Some(TokenDeploymentEip712 {
+            name: "USD Coin (PoS)".into(),
+            version: "1".into(),
+            chain_id_as_salt: true,
         }),
     })
 });
@@ -257,6 +582,7 @@ static USDC_SEI: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USDC".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -271,6 +597,39 @@ static USDC_SEI_TESTNET: Lazy<USDCDeployment> = Lazy::new(|| {
         eip712: Some(TokenDeploymentEip712 {
             name: "USDC".into(),
             version: "2".into(),
+            chain_id_as_salt: false,
+        }),
+    })
+});
+
+/// Lazily initialized known USDC deployment on Arbitrum One as [`USDCDeployment`].
+static USDC_ARBITRUM: Lazy<USDCDeployment> = Lazy::new(|| {
+    USDCDeployment(TokenDeployment {
+        asset: TokenAsset {
+            address: address!("0xaf88d065e77c8cC2239327C5EDb3A432268e5831").into(),
+            network: Network::Arbitrum,
+        },
+        decimals: 6,
+        eip712: Some(TokenDeploymentEip712 {
+            name: "USD Coin".into(),
+            version: "2".into(),
+            chain_id_as_salt: false,
+        }),
+    })
+});
+
+/// Lazily initialized known USDC deployment on OP Mainnet as [`USDCDeployment`].
+static USDC_OPTIMISM: Lazy<USDCDeployment> = Lazy::new(|| {
+    USDCDeployment(TokenDeployment {
+        asset: TokenAsset {
+            address: address!("0x0b2C639c533813f4Aa9D7837CAf62653d097Ff85").into(),
+            network: Network::Optimism,
+        },
+        decimals: 6,
+        eip712: Some(TokenDeploymentEip712 {
+            name: "USD Coin".into(),
+            version: "2".into(),
+            chain_id_as_salt: false,
         }),
     })
 });
@@ -308,9 +667,16 @@ impl From<&USDCDeployment> for Vec<TokenAsset> {
 impl USDCDeployment {
     /// Return the known USDC deployment for the given network.
     ///
-    /// Panic if the network is unsupported (not expected in practice).
+    /// Panic if the network has none, i.e. for a [`Network::Custom`]; see [`USDCDeployment::try_by_network`].
     pub fn by_network<N: Borrow<Network>>(network: N) -> &'static USDCDeployment {
-        match network.borrow() {
+        let network = *network.borrow();
+        Self::try_by_network(network)
+            .unwrap_or_else(|| panic!("No known USDC deployment on {network}"))
+    }
+
+    /// Return the known USDC deployment for the given network, if any.
+    pub fn try_by_network<N: Borrow<Network>>(network: N) -> Option<&'static USDCDeployment> {
+        let usdc: &'static USDCDeployment = match network.borrow() {
             Network::BaseSepolia => &USDC_BASE_SEPOLIA,
             Network::Base => &USDC_BASE,
             Network::XdcMainnet => &USDC_XDC,
@@ -322,6 +688,33 @@ impl USDCDeployment {
             Network::Polygon => &USDC_POLYGON,
             Network::Sei => &USDC_SEI,
             Network::SeiTestnet => &USDC_SEI_TESTNET,
+            Network::Arbitrum => &USDC_ARBITRUM,
+            Network::Optimism => &USDC_OPTIMISM,
+            Network::Lightning
+            | Network::LightningTestnet
+            | Network::Near
+            | Network::NearTestnet
+            | Network::Tron
+            | Network::TronNile
+            | Network::Custom(_) => return None,
+        };
+        Some(usdc)
+    }
+
+    /// Return the known bridged USDC deployment for the given network, if any, e.g. USDC.e on Polygon.
+    pub fn bridged_by_network<N: Borrow<Network>>(network: N) -> Option<&'static USDCDeployment> {
+        match network.borrow() {
+            Network::Polygon => Some(&USDC_E_POLYGON),
+            _ => None,
         }
     }
+
+    /// Return all known USDC deployments for the given network: native first, then bridged.
+    pub fn all_by_network<N: Borrow<Network>>(network: N) -> Vec<&'static USDCDeployment> {
+        let network = *network.borrow();
+        Self::try_by_network(network)
+            .into_iter()
+            .chain(Self::bridged_by_network(network))
+            .collect()
+    }
 }
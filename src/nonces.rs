@@ -0,0 +1,448 @@
+//! Nonce reservation for ERC-3009 payments.
+//!
+//! Payers choose the 32-byte `nonce` of a `transferWithAuthorization` themselves. Clients running
+//! concurrent sessions for the same payer can instead ask the facilitator for a fresh random nonce
+//! with `POST /nonces`. The facilitator records the reservation, which lets [`NonceGuard`] reject:
+//! - a payment using a reserved nonce for another payer or network,
+//! - a payment reusing a reserved nonce that was already settled,
+//!
+//! at `/verify` already, instead of failing with a revert at settlement. Nonces that were not
+//! reserved are passed through unchanged.
+//!
+//! Reservations expire after a TTL; settled nonces are remembered until then too.
+//!
+//! `POST /nonces` is unauthenticated, so reservations are bounded: at most `NONCE_RESERVATION_MAX` outstanding,
+//! at most `NONCE_RESERVATION_MAX_PER_PAYER` for one payer (reservations without a payer sharing one allowance),
+//! and at most `NONCE_RESERVATION_RATE` handed out per second. Requests over a bound are answered with
+//! `429 Too Many Requests`.
+//!
+//! Environment variables used:
+//! - `NONCE_RESERVATION_TTL_SECS` — lifetime of a reservation (default: `3600`).
+//! - `NONCE_RESERVATION_MAX` — outstanding reservations (default: `100000`).
+//! - `NONCE_RESERVATION_MAX_PER_PAYER` — outstanding reservations of one payer (default: `100`).
+//! - `NONCE_RESERVATION_RATE` — reservations handed out per second (default: `50`).
+
+use alloy::primitives::B256;
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::post;
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex};
+use std::time::SystemTimeError;
+use tracing::instrument;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ErrorResponse, ExactPaymentPayload, HexEncodedNonce, MixedAddress, PaymentPayload,
+    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Default lifetime of a reservation, in seconds.
+const DEFAULT_TTL_SECS: u64 = 3600;
+/// Default bound on outstanding reservations.
+const DEFAULT_MAX_RESERVATIONS: usize = 100_000;
+/// Default bound on outstanding reservations of one payer.
+const DEFAULT_MAX_PER_PAYER: usize = 100;
+/// Default bound on reservations handed out per second.
+const DEFAULT_RATE: u32 = 50;
+
+/// Bounds on the reservations handed out by `POST /nonces`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReservationLimits {
+    pub max_reservations: usize,
+    pub max_per_payer: usize,
+    /// Reservations handed out per second.
+    pub rate: u32,
+}
+
+impl Default for ReservationLimits {
+    fn default() -> Self {
+        Self {
+            max_reservations: DEFAULT_MAX_RESERVATIONS,
+            max_per_payer: DEFAULT_MAX_PER_PAYER,
+            rate: DEFAULT_RATE,
+        }
+    }
+}
+
+/// Why a nonce was not reserved.
+#[derive(Debug, thiserror::Error)]
+pub enum ReservationError {
+    #[error("Too many nonce reservations per second")]
+    RateLimited,
+    #[error("Too many outstanding nonce reservations")]
+    TooManyReservations,
+    #[error("Too many outstanding nonce reservations for this payer")]
+    TooManyForPayer,
+    #[error("Can not get system clock")]
+    Clock(#[source] SystemTimeError),
+}
+
+/// A nonce handed out by the facilitator.
+#[derive(Debug, Clone)]
+struct Reservation {
+    network: Network,
+    payer: Option<MixedAddress>,
+    expires_at: UnixTimestamp,
+    settled: bool,
+}
+
+/// Body of `POST /nonces`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceReservationRequest {
+    pub network: Network,
+    /// Payer the nonce is reserved for; any payer may use it if unset.
+    #[serde(default)]
+    pub payer: Option<MixedAddress>,
+}
+
+/// Answer of `POST /nonces`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceReservationResponse {
+    pub nonce: HexEncodedNonce,
+    pub network: Network,
+    pub expires_at: UnixTimestamp,
+}
+
+/// Reserved nonces, shared between [`NonceGuard`] and the [`routes`].
+///
+/// Cheap to clone: all clones share the same reservations.
+#[derive(Debug, Clone)]
+pub struct NonceReservations {
+    ttl_secs: u64,
+    limits: ReservationLimits,
+    reservations: Arc<DashMap<B256, Reservation>>,
+    /// Second of the current rate window, and reservations handed out in it.
+    window: Arc<Mutex<(UnixTimestamp, u32)>>,
+}
+
+impl NonceReservations {
+    pub fn new(ttl_secs: u64, limits: ReservationLimits) -> Self {
+        Self {
+            ttl_secs,
+            limits,
+            reservations: Arc::new(DashMap::new()),
+            window: Arc::new(Mutex::new((UnixTimestamp(0), 0))),
+        }
+    }
+
+    /// Reads the reservation TTL and limits from `NONCE_RESERVATION_*` variables.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let ttl_secs = env_or(from_env::ENV_NONCE_RESERVATION_TTL_SECS, DEFAULT_TTL_SECS)?;
+        let limits = ReservationLimits {
+            max_reservations: env_or(
+                from_env::ENV_NONCE_RESERVATION_MAX,
+                DEFAULT_MAX_RESERVATIONS,
+            )?,
+            max_per_payer: env_or(
+                from_env::ENV_NONCE_RESERVATION_MAX_PER_PAYER,
+                DEFAULT_MAX_PER_PAYER,
+            )?,
+            rate: env_or(from_env::ENV_NONCE_RESERVATION_RATE, DEFAULT_RATE)?,
+        };
+        Ok(Self::new(ttl_secs, limits))
+    }
+
+    /// Hands out a fresh random nonce within the [`ReservationLimits`], dropping expired reservations on the way.
+    pub fn reserve(
+        &self,
+        request: NonceReservationRequest,
+    ) -> Result<NonceReservationResponse, ReservationError> {
+        let now = UnixTimestamp::try_now().map_err(ReservationError::Clock)?;
+        self.reserve_at(request, now)
+    }
+
+    fn reserve_at(
+        &self,
+        request: NonceReservationRequest,
+        now: UnixTimestamp,
+    ) -> Result<NonceReservationResponse, ReservationError> {
+        {
+            let mut window = self.window.lock().expect("reservation window poisoned");
+            if window.0 != now {
+                *window = (now, 0);
+            }
+            if window.1 >= self.limits.rate {
+                return Err(ReservationError::RateLimited);
+            }
+            window.1 += 1;
+        }
+        self.reservations
+            .retain(|_, reservation| reservation.expires_at > now);
+        if self.reservations.len() >= self.limits.max_reservations {
+            return Err(ReservationError::TooManyReservations);
+        }
+        let of_payer = self
+            .reservations
+            .iter()
+            .filter(|reservation| reservation.payer == request.payer)
+            .count();
+        if of_payer >= self.limits.max_per_payer {
+            return Err(ReservationError::TooManyForPayer);
+        }
+        let expires_at = now + self.ttl_secs;
+        let nonce = B256::random();
+        self.reservations.insert(
+            nonce,
+            Reservation {
+                network: request.network,
+                payer: request.payer,
+                expires_at,
+                settled: false,
+            },
+        );
+        tracing::info!(
+            monotonic_counter.x402.nonces.reserved = 1,
+            network = %request.network,
+        );
+        Ok(NonceReservationResponse {
+            nonce: HexEncodedNonce(nonce.0),
+            network: request.network,
+            expires_at,
+        })
+    }
+
+    /// Rejects a payment whose nonce is reserved for someone else, or was already settled.
+    fn check(&self, payload: &PaymentPayload) -> Result<(), FacilitatorLocalError> {
+        let Some((nonce, payer)) = reserved_nonce(payload) else {
+            return Ok(());
+        };
+        let Some(reservation) = self.reservations.get(&nonce) else {
+            return Ok(());
+        };
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        if reservation.expires_at <= now {
+            return Ok(());
+        }
+        if reservation.settled {
+            return Err(FacilitatorLocalError::NonceReused(payer));
+        }
+        let payer_matches = reservation.payer.as_ref().is_none_or(|p| *p == payer);
+        if reservation.network != payload.network || !payer_matches {
+            return Err(FacilitatorLocalError::InvalidSignature(
+                payer,
+                format!("Nonce {nonce} is reserved for another payer or network"),
+            ));
+        }
+        Ok(())
+    }
+
+    fn mark_settled(&self, payload: &PaymentPayload) {
+        if let Some((nonce, _)) = reserved_nonce(payload)
+            && let Some(mut reservation) = self.reservations.get_mut(&nonce)
+        {
+            reservation.settled = true;
+        }
+    }
+}
+
+/// Value of the variable `name`, or `default` if unset.
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    match std::env::var(name) {
+        Ok(value) => value.parse::<T>().map_err(|e| format!("{name}: {e}")),
+        Err(_) => Ok(default),
+    }
+}
+
+/// Nonce and payer of an ERC-3009 payload; other schemes do not use reservable nonces.
+fn reserved_nonce(payload: &PaymentPayload) -> Option<(B256, MixedAddress)> {
+    match &payload.payload {
+        ExactPaymentPayload::Evm(payload) => Some((
+            B256::from(payload.authorization.nonce.0),
+            payload.authorization.from.into(),
+        )),
+        ExactPaymentPayload::Permit2(_)
+        | ExactPaymentPayload::Permit(_)
+        | ExactPaymentPayload::Native(_)
+        | ExactPaymentPayload::UserOperation(_)
+        | ExactPaymentPayload::Stream(_)
+        | ExactPaymentPayload::Tron(_)
+        | ExactPaymentPayload::Lightning(_)
+        | ExactPaymentPayload::Near(_)
+        | ExactPaymentPayload::Solana(_) => None,
+    }
+}
+
+/// [`Facilitator`] wrapper enforcing [`NonceReservations`].
+pub struct NonceGuard<F> {
+    facilitator: F,
+    reservations: NonceReservations,
+}
+
+impl<F> NonceGuard<F> {
+    pub fn new(facilitator: F, reservations: NonceReservations) -> Self {
+        Self {
+            facilitator,
+            reservations,
+        }
+    }
+}
+
+impl<F> Facilitator for NonceGuard<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.reservations.check(&request.payment_payload)?;
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        self.reservations.check(&request.payment_payload)?;
+        let response = self.facilitator.settle(request).await?;
+        if response.success {
+            self.reservations.mark_settled(&request.payment_payload);
+        }
+        Ok(response)
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+/// Routes handing out nonce reservations.
+pub fn routes() -> Router<NonceReservations> {
+    Router::new().route("/nonces", post(post_nonces))
+}
+
+/// `POST /nonces`: Reserves a random ERC-3009 nonce.
+#[instrument(skip_all)]
+pub async fn post_nonces(
+    State(reservations): State<NonceReservations>,
+    Json(request): Json<NonceReservationRequest>,
+) -> impl IntoResponse {
+    match reservations.reserve(request) {
+        Ok(response) => (StatusCode::OK, Json(response)).into_response(),
+        Err(e) => {
+            let status = match e {
+                ReservationError::Clock(_) => StatusCode::INTERNAL_SERVER_ERROR,
+                ReservationError::RateLimited
+                | ReservationError::TooManyReservations
+                | ReservationError::TooManyForPayer => {
+                    tracing::warn!(
+                        monotonic_counter.x402.nonces.reservations_refused = 1,
+                        error = %e,
+                    );
+                    StatusCode::TOO_MANY_REQUESTS
+                }
+            };
+            (
+                status,
+                Json(ErrorResponse {
+                    error: e.to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{
+        EvmAddress, EvmSignature, ExactEvmPayload, ExactEvmPayloadAuthorization, Scheme,
+        TokenAmount, X402Version,
+    };
+    use alloy::primitives::Address;
+
+    fn payload(nonce: HexEncodedNonce, from: Address) -> PaymentPayload {
+        PaymentPayload {
+            x402_version: X402Version::V1,
+            scheme: Scheme::Exact,
+            network: Network::BaseSepolia,
+            payload: ExactPaymentPayload::Evm(ExactEvmPayload {
+                signature: EvmSignature(vec![0; 65]),
+                authorization: ExactEvmPayloadAuthorization {
+                    from: EvmAddress(from),
+                    to: EvmAddress(Address::ZERO),
+                    value: TokenAmount::from(1u64),
+                    valid_after: UnixTimestamp(0),
+                    valid_before: UnixTimestamp(u64::MAX),
+                    nonce,
+                },
+            }),
+            payment_id: None,
+        }
+    }
+
+    #[test]
+    fn rejects_other_payer_and_settled_nonces() {
+        let reservations = NonceReservations::new(60, ReservationLimits::default());
+        let payer = Address::repeat_byte(1);
+        let reserved = reservations
+            .reserve(NonceReservationRequest {
+                network: Network::BaseSepolia,
+                payer: Some(EvmAddress(payer).into()),
+            })
+            .unwrap();
+        let own = payload(reserved.nonce, payer);
+        assert!(reservations.check(&own).is_ok());
+        let other = payload(reserved.nonce, Address::repeat_byte(2));
+        assert!(reservations.check(&other).is_err());
+        reservations.mark_settled(&own);
+        assert!(matches!(
+            reservations.check(&own),
+            Err(FacilitatorLocalError::NonceReused(_))
+        ));
+        let unreserved = payload(HexEncodedNonce([7; 32]), payer);
+        assert!(reservations.check(&unreserved).is_ok());
+    }
+
+    #[test]
+    fn reservations_are_bounded() {
+        let reservations = NonceReservations::new(
+            60,
+            ReservationLimits {
+                max_reservations: 3,
+                max_per_payer: 2,
+                rate: 100,
+            },
+        );
+        let request = |byte| NonceReservationRequest {
+            network: Network::BaseSepolia,
+            payer: Some(EvmAddress(Address::repeat_byte(byte)).into()),
+        };
+        reservations.reserve(request(1)).unwrap();
+        reservations.reserve(request(1)).unwrap();
+        assert!(matches!(
+            reservations.reserve(request(1)),
+            Err(ReservationError::TooManyForPayer)
+        ));
+        reservations.reserve(request(2)).unwrap();
+        assert!(matches!(
+            reservations.reserve(request(3)),
+            Err(ReservationError::TooManyReservations)
+        ));
+
+        let limited = NonceReservations::new(
+            60,
+            ReservationLimits {
+                rate: 1,
+                ..ReservationLimits::default()
+            },
+        );
+        let now = UnixTimestamp(1_000);
+        limited.reserve_at(request(1), now).unwrap();
+        assert!(matches!(
+            limited.reserve_at(request(1), now),
+            Err(ReservationError::RateLimited)
+        ));
+        limited.reserve_at(request(1), now + 1).unwrap();
+    }
+}
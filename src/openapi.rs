@@ -0,0 +1,169 @@
+//! OpenAPI 3 description of the protocol endpoints, generated from their request and response types.
+//!
+//! `GET /openapi.json` serves the document, built with [`utoipa`] from the `#[utoipa::path]` attributes of the
+//! handlers and the [`ToSchema`] implementations of the types they exchange, so that it follows the code. Types with
+//! a hand-written serde format, such as addresses, amounts and timestamps, describe their format here.
+//! `GET /verify` and `GET /settle` answer the entry of their endpoint in the document.
+//!
+//! With the `swagger-ui` feature, `GET /swagger-ui` serves Swagger UI on the document. Its assets are loaded from
+//! the unpkg CDN by the browser, and are not part of the build.
+
+use axum::Json;
+use axum::response::IntoResponse;
+use once_cell::sync::Lazy;
+use tracing::instrument;
+use utoipa::openapi::RefOr;
+use utoipa::openapi::path::PathItem;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
+use utoipa::{OpenApi, PartialSchema, ToSchema};
+
+use crate::handlers;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    EvmAddress, EvmSignature, FacilitatorErrorReason, HexEncodedNonce, MixedAddress, MoneyAmount,
+    PaymentId, TokenAmount, TransactionHash, TronAddress, VerifyResponse, X402Version,
+};
+
+/// Protocol endpoints described by `GET /openapi.json`.
+#[derive(OpenApi)]
+#[openapi(
+    info(description = "Verifies and settles x402 payments."),
+    paths(handlers::post_verify, handlers::post_settle, handlers::get_supported)
+)]
+pub struct ApiDoc;
+
+static DOCUMENT: Lazy<utoipa::openapi::OpenApi> = Lazy::new(ApiDoc::openapi);
+
+/// The OpenAPI document of the protocol endpoints.
+pub fn document() -> &'static utoipa::openapi::OpenApi {
+    &DOCUMENT
+}
+
+/// Entry of `path` in the [`document`], if it describes it.
+pub fn path_item(path: &str) -> Option<PathItem> {
+    DOCUMENT.paths.paths.get(path).cloned()
+}
+
+/// Implements [`ToSchema`] for types serialized as a string, with a description of their format.
+macro_rules! string_schema {
+    ($($ty:ty => $description:expr),* $(,)?) => {
+        $(
+            impl PartialSchema for $ty {
+                fn schema() -> RefOr<Schema> {
+                    ObjectBuilder::new()
+                        .schema_type(Type::String)
+                        .description(Some($description))
+                        .into()
+                }
+            }
+
+            impl ToSchema for $ty {}
+        )*
+    };
+}
+
+string_schema! {
+    EvmAddress => "EVM address, 0x-prefixed hex.",
+    TronAddress => "Tron address, base58check starting with `T`.",
+    EvmSignature => "EVM signature, 0x-prefixed hex: EOA, EIP-1271 or EIP-6492.",
+    HexEncodedNonce => "32-byte nonce, 0x-prefixed hex.",
+    TokenAmount => "Amount in the smallest unit of the token, as a decimal string.",
+    MoneyAmount => "Price in US dollars, e.g. `0.01`.",
+    MixedAddress => "Address on the network of the payment: EVM (0x-prefixed hex), Solana (base58), NEAR account \
+                     ID, Tron (base58check), or off-chain identifier.",
+    TransactionHash => "Transaction hash: 0x-prefixed hex on EVM networks, base58 on Solana and NEAR.",
+    PaymentId => "Correlation ID of the payment, e.g. `pay_3f9c…`.",
+    FacilitatorErrorReason => "Machine-readable reason, e.g. `insufficient_funds` or `invalid_scheme`.",
+    UnixTimestamp => "Seconds since the Unix epoch, as a decimal string.",
+    Network => "Network name, e.g. `base` or `solana-devnet`, or the name of a custom network.",
+}
+
+impl PartialSchema for X402Version {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::Integer)
+            .enum_values(Some([1, 2]))
+            .description(Some("x402 protocol version."))
+            .into()
+    }
+}
+
+impl ToSchema for X402Version {}
+
+impl PartialSchema for VerifyResponse {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .description(Some(
+                "Outcome of a verification: valid with its `payer`, or invalid with an `invalidReason`.",
+            ))
+            .property("isValid", ObjectBuilder::new().schema_type(Type::Boolean))
+            .required("isValid")
+            .property("payer", MixedAddress::schema())
+            .property("invalidReason", FacilitatorErrorReason::schema())
+            .property(
+                "receivedAmount",
+                ObjectBuilder::new()
+                    .schema_type(Type::String)
+                    .description(Some(
+                        "What `payTo` receives, if the token deducts a fee on transfer.",
+                    )),
+            )
+            .into()
+    }
+}
+
+impl ToSchema for VerifyResponse {}
+
+/// `GET /openapi.json`: OpenAPI 3 description of `POST /verify`, `POST /settle` and `GET /supported`.
+///
+/// Generated from the request and response types of the facilitator, so that clients can be generated from it
+/// and requests validated against it.
+#[instrument(skip_all)]
+pub async fn get_openapi() -> impl IntoResponse {
+    Json(document())
+}
+
+/// `GET /swagger-ui`: Swagger UI on `GET /openapi.json`, with the `swagger-ui` feature.
+#[cfg(feature = "swagger-ui")]
+#[instrument(skip_all)]
+pub async fn get_swagger_ui() -> impl IntoResponse {
+    axum::response::Html(SWAGGER_UI)
+}
+
+#[cfg(feature = "swagger-ui")]
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>x402 facilitator API</title>
+<link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css">
+</head>
+<body>
+<div id="swagger-ui"></div>
+<script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js"></script>
+<script>
+window.ui = SwaggerUIBundle({ url: "/openapi.json", dom_id: "#swagger-ui" });
+</script>
+</body>
+</html>
+"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn documents_the_protocol_endpoints_and_their_types() {
+        let document = serde_json::to_value(document()).unwrap();
+        assert!(document["paths"]["/verify"]["post"].is_object());
+        assert!(document["paths"]["/settle"]["post"].is_object());
+        assert!(document["paths"]["/supported"]["get"].is_object());
+        let schemas = &document["components"]["schemas"];
+        let requirements = &schemas["PaymentRequirements"]["properties"];
+        assert!(requirements["maxAmountRequired"].is_object());
+        assert!(schemas["SettleResponse"]["properties"]["settlementId"].is_object());
+        assert_eq!(schemas["VerifyResponse"]["required"][0], "isValid");
+        assert!(path_item("/verify").is_some());
+    }
+}
@@ -0,0 +1,69 @@
+//! Isolated listener for operational traffic.
+//!
+//! Liveness probes, version checks and the admin API must keep answering while payment traffic
+//! saturates the main server, otherwise an orchestrator could restart the facilitator in the middle
+//! of a settlement. When `OPS_PORT` is set, an [`OpsServer`] serves these routes on a separate port,
+//! from a dedicated OS thread running its own single-threaded Tokio runtime, so they never queue
+//! behind `/verify` or `/settle` requests.
+//!
+//! Environment variables used:
+//! - `OPS_PORT` — port of the operational listener, bound on `HOST`. Disabled if unset.
+
+use axum::Router;
+use std::net::SocketAddr;
+use tokio_util::sync::CancellationToken;
+
+use crate::from_env;
+
+/// Operational HTTP listener running on its own runtime.
+#[derive(Debug, Clone, Copy)]
+pub struct OpsServer {
+    addr: SocketAddr,
+}
+
+impl OpsServer {
+    pub fn new(addr: SocketAddr) -> Self {
+        Self { addr }
+    }
+
+    /// Listener on `OPS_PORT` of `host`; `None` if `OPS_PORT` is unset or invalid.
+    pub fn from_env(host: std::net::IpAddr) -> Option<Self> {
+        let port = std::env::var(from_env::ENV_OPS_PORT)
+            .ok()
+            .and_then(|s| s.parse::<u16>().ok())?;
+        Some(Self::new(SocketAddr::new(host, port)))
+    }
+
+    /// Serves `router` on a dedicated thread until `cancellation_token` is cancelled.
+    pub fn spawn(
+        self,
+        router: Router,
+        cancellation_token: CancellationToken,
+    ) -> std::io::Result<std::thread::JoinHandle<()>> {
+        let addr = self.addr;
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        std::thread::Builder::new()
+            .name("x402-ops".to_string())
+            .spawn(move || {
+                runtime.block_on(async move {
+                    let listener = match tokio::net::TcpListener::bind(addr).await {
+                        Ok(listener) => listener,
+                        Err(e) => {
+                            tracing::error!("Failed to bind ops listener to {}: {}", addr, e);
+                            return;
+                        }
+                    };
+                    tracing::info!("Starting ops server at http://{}", addr);
+                    let shutdown = async move { cancellation_token.cancelled().await };
+                    if let Err(e) = axum::serve(listener, router)
+                        .with_graceful_shutdown(shutdown)
+                        .await
+                    {
+                        tracing::error!("Ops server failed: {}", e);
+                    }
+                })
+            })
+    }
+}
@@ -0,0 +1,403 @@
+//! Dollar-denominated payment requirements, resolved with a price oracle.
+//!
+//! Resource servers pricing in dollars set `priceUsd` in the payment requirements, next to the
+//! `maxAmountRequired` the client signs for. [`PriceGate`] resolves `priceUsd` to token units at the current price
+//! of the asset and refuses payments whose `maxAmountRequired` is off by more than the tolerance, in either
+//! direction, with the `price_out_of_tolerance` error reason.
+//!
+//! Prices come from a Chainlink USD feed when one is configured for the asset, or else from a price API. Token
+//! decimals are those of the known USDC deployments, or `extra.decimals` of the requirements.
+//!
+//! Environment variables used:
+//! - `PRICE_FEED_RPC_URL` — JSON-RPC endpoint of the chain the Chainlink feeds live on,
+//! - `PRICE_FEEDS` — comma-separated `network:asset=feed` entries, e.g. `base:0x8335…2913=0x7e86…8165`,
+//! - `PRICE_API_URL` — price API answering `{"usd": "0.9998"}`, with `{network}` and `{asset}` placeholders, subject
+//!   to the outbound policy,
+//! - `PRICE_TOLERANCE_BPS` — accepted deviation in basis points (default: `100`),
+//! - `PRICE_CACHE_SECS` — how long a price is reused (default: `30`).
+//!
+//! Requirements with `priceUsd` are refused if no oracle is configured.
+
+use alloy::primitives::{Address, U256};
+use alloy::providers::{DynProvider, Provider, ProviderBuilder};
+use alloy::sol;
+use dashmap::DashMap;
+use rust_decimal::Decimal;
+use rust_decimal::prelude::FromPrimitive;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use url::Url;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::{Network, USDCDeployment};
+use crate::outbound::OutboundPolicy;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    MixedAddress, MoneyAmount, PaymentRequirements, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+const DEFAULT_TOLERANCE_BPS: u32 = 100;
+const DEFAULT_CACHE: Duration = Duration::from_secs(30);
+/// Chainlink answers older than this are stale; USD feeds update at least daily.
+const MAX_FEED_AGE_SECS: u64 = 25 * 3600;
+
+sol! {
+    #[allow(missing_docs)]
+    #[derive(Debug)]
+    #[sol(rpc)]
+    interface AggregatorV3Interface {
+        function decimals() external view returns (uint8);
+        function latestRoundData() external view returns (uint80 roundId, int256 answer, uint256 startedAt, uint256 updatedAt, uint80 answeredInRound);
+    }
+}
+
+#[derive(Deserialize)]
+struct ApiPrice {
+    usd: Decimal,
+}
+
+/// Where USD prices of assets come from.
+pub struct PriceOracle {
+    feeds: HashMap<(Network, MixedAddress), Address>,
+    feed_provider: Option<DynProvider>,
+    api_url: Option<String>,
+    http: reqwest::Client,
+    tolerance_bps: u32,
+    cache_for: Duration,
+    cache: DashMap<(Network, MixedAddress), (Instant, Decimal)>,
+}
+
+impl PriceOracle {
+    /// Reads the `PRICE_*` variables; `Ok(None)` if neither feeds nor a price API are configured.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let feeds = match std::env::var(from_env::ENV_PRICE_FEEDS) {
+            Ok(feeds) => {
+                parse_feeds(&feeds).map_err(|e| format!("{}: {e}", from_env::ENV_PRICE_FEEDS))?
+            }
+            Err(_) => HashMap::new(),
+        };
+        let feed_provider = match std::env::var(from_env::ENV_PRICE_FEED_RPC_URL) {
+            Ok(url) => {
+                let url = url
+                    .parse()
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_PRICE_FEED_RPC_URL))?;
+                Some(ProviderBuilder::new().connect_http(url).erased())
+            }
+            Err(_) if !feeds.is_empty() => {
+                return Err(format!(
+                    "{} is required with {}",
+                    from_env::ENV_PRICE_FEED_RPC_URL,
+                    from_env::ENV_PRICE_FEEDS
+                )
+                .into());
+            }
+            Err(_) => None,
+        };
+        let api_url = std::env::var(from_env::ENV_PRICE_API_URL).ok();
+        if feeds.is_empty() && api_url.is_none() {
+            return Ok(None);
+        }
+        let outbound = OutboundPolicy::from_env()?;
+        if let Some(api_url) = &api_url {
+            // Placeholders only ever stand for a network name or an address, not for the scheme or an IP.
+            let url = Url::parse(
+                &api_url
+                    .replace("{network}", "network")
+                    .replace("{asset}", "asset"),
+            )
+            .map_err(|e| format!("{}: {e}", from_env::ENV_PRICE_API_URL))?;
+            outbound
+                .validate_url(&url)
+                .map_err(|e| format!("{}: {e}", from_env::ENV_PRICE_API_URL))?;
+        }
+        let tolerance_bps = match std::env::var(from_env::ENV_PRICE_TOLERANCE_BPS) {
+            Ok(bps) => bps
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_PRICE_TOLERANCE_BPS))?,
+            Err(_) => DEFAULT_TOLERANCE_BPS,
+        };
+        let cache_for = match std::env::var(from_env::ENV_PRICE_CACHE_SECS) {
+            Ok(secs) => Duration::from_secs(
+                secs.parse()
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_PRICE_CACHE_SECS))?,
+            ),
+            Err(_) => DEFAULT_CACHE,
+        };
+        Ok(Some(Self {
+            feeds,
+            feed_provider,
+            api_url,
+            http: outbound.http_client(),
+            tolerance_bps,
+            cache_for,
+            cache: DashMap::new(),
+        }))
+    }
+
+    /// Current USD price of one whole unit of `asset` on `network`.
+    pub async fn usd_price(
+        &self,
+        network: Network,
+        asset: &MixedAddress,
+    ) -> Result<Decimal, FacilitatorLocalError> {
+        let key = (network, asset.clone());
+        if let Some(entry) = self.cache.get(&key) {
+            let (fetched_at, price) = *entry;
+            if fetched_at.elapsed() < self.cache_for {
+                return Ok(price);
+            }
+        }
+        let price = match (self.feeds.get(&key), &self.feed_provider) {
+            (Some(feed), Some(provider)) => self.feed_price(provider, *feed).await?,
+            _ => self.api_price(network, asset).await?,
+        };
+        if price <= Decimal::ZERO {
+            return Err(FacilitatorLocalError::PriceOracle(format!(
+                "non-positive price {price} for {asset} on {network}"
+            )));
+        }
+        self.cache.insert(key, (Instant::now(), price));
+        Ok(price)
+    }
+
+    async fn feed_price(
+        &self,
+        provider: &DynProvider,
+        feed: Address,
+    ) -> Result<Decimal, FacilitatorLocalError> {
+        let aggregator = AggregatorV3Interface::new(feed, provider);
+        let oracle_error = |e: alloy::contract::Error| {
+            FacilitatorLocalError::PriceOracle(format!("Chainlink feed {feed}: {e}"))
+        };
+        let decimals = aggregator.decimals().call().await.map_err(oracle_error)?;
+        let round = aggregator
+            .latestRoundData()
+            .call()
+            .await
+            .map_err(oracle_error)?;
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        let oldest = now.seconds_since_epoch().saturating_sub(MAX_FEED_AGE_SECS);
+        if U256::from(oldest) > round.updatedAt {
+            return Err(FacilitatorLocalError::PriceOracle(format!(
+                "Chainlink feed {feed} is stale"
+            )));
+        }
+        let answer = i128::try_from(round.answer)
+            .ok()
+            .and_then(|answer| {
+                Decimal::try_from_i128_with_scale(answer, 0)
+                    .ok()?
+                    .checked_div(pow10(decimals)?)
+            })
+            .ok_or_else(|| {
+                FacilitatorLocalError::PriceOracle(format!(
+                    "Chainlink feed {feed}: answer out of range"
+                ))
+            })?;
+        Ok(answer)
+    }
+
+    async fn api_price(
+        &self,
+        network: Network,
+        asset: &MixedAddress,
+    ) -> Result<Decimal, FacilitatorLocalError> {
+        let Some(api_url) = &self.api_url else {
+            return Err(FacilitatorLocalError::PriceOracle(format!(
+                "no price source for {asset} on {network}"
+            )));
+        };
+        let url = api_url
+            .replace("{network}", &network.to_string())
+            .replace("{asset}", &asset.to_string());
+        let price: ApiPrice = self
+            .http
+            .get(url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| FacilitatorLocalError::PriceOracle(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| FacilitatorLocalError::PriceOracle(e.to_string()))?;
+        Ok(price.usd)
+    }
+
+    /// Checks that `maxAmountRequired` matches `priceUsd` within the tolerance, if the requirements set a price.
+    pub async fn check(
+        &self,
+        requirements: &PaymentRequirements,
+    ) -> Result<(), FacilitatorLocalError> {
+        let Some(price_usd) = &requirements.price_usd else {
+            return Ok(());
+        };
+        let decimals = token_decimals(requirements).ok_or_else(|| {
+            FacilitatorLocalError::PriceOutOfTolerance(
+                None,
+                format!("unknown decimals of {}", requirements.asset),
+            )
+        })?;
+        let price = self
+            .usd_price(requirements.network, &requirements.asset)
+            .await?;
+        check_amount(
+            price_usd,
+            price,
+            decimals,
+            requirements.max_amount_required.0,
+            self.tolerance_bps,
+        )
+    }
+}
+
+fn pow10(decimals: u8) -> Option<Decimal> {
+    Decimal::from_u128(10u128.checked_pow(u32::from(decimals))?)
+}
+
+/// Decimals of the known USDC deployment matching the asset, or else `extra.decimals`.
+fn token_decimals(requirements: &PaymentRequirements) -> Option<u8> {
+    let usdc = [
+        USDCDeployment::try_by_network(requirements.network),
+        USDCDeployment::bridged_by_network(requirements.network),
+    ];
+    usdc.into_iter()
+        .flatten()
+        .find(|usdc| usdc.address() == requirements.asset)
+        .map(|usdc| usdc.decimals)
+        .or_else(|| {
+            let decimals = requirements.extra.as_ref()?.get("decimals")?.as_u64()?;
+            u8::try_from(decimals).ok()
+        })
+}
+
+/// Checks `amount`, in token units of `decimals`, against `price_usd` at `price` dollars per token.
+fn check_amount(
+    price_usd: &MoneyAmount,
+    price: Decimal,
+    decimals: u8,
+    amount: U256,
+    tolerance_bps: u32,
+) -> Result<(), FacilitatorLocalError> {
+    let out_of_range =
+        || FacilitatorLocalError::PriceOutOfTolerance(None, "amount out of range".to_string());
+    let expected = price_usd
+        .0
+        .checked_div(price)
+        .and_then(|tokens| tokens.checked_mul(pow10(decimals)?))
+        .ok_or_else(out_of_range)?;
+    let amount = u128::try_from(amount)
+        .ok()
+        .and_then(Decimal::from_u128)
+        .ok_or_else(out_of_range)?;
+    let tolerance = expected * Decimal::from(tolerance_bps) / Decimal::from(10_000);
+    if (amount - expected).abs() > tolerance {
+        return Err(FacilitatorLocalError::PriceOutOfTolerance(
+            None,
+            format!(
+                "${price_usd} at ${price} is {} token units, requirements ask {amount}",
+                expected.round()
+            ),
+        ));
+    }
+    Ok(())
+}
+
+fn parse_feeds(feeds: &str) -> Result<HashMap<(Network, MixedAddress), Address>, String> {
+    feeds
+        .split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (asset, feed) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("expected network:asset=feed, got {entry}"))?;
+            let (network, asset) = asset
+                .split_once(':')
+                .ok_or_else(|| format!("expected network:asset=feed, got {entry}"))?;
+            let network = Network::from_str(network.trim())
+                .map_err(|_| format!("unknown network {network}"))?;
+            let asset: MixedAddress = serde_json::from_value(asset.trim().into())
+                .map_err(|e| format!("invalid asset {asset}: {e}"))?;
+            let feed =
+                Address::from_str(feed.trim()).map_err(|e| format!("invalid feed {feed}: {e}"))?;
+            Ok(((network, asset), feed))
+        })
+        .collect()
+}
+
+/// [`Facilitator`] decorator checking dollar-denominated requirements against a [`PriceOracle`].
+pub struct PriceGate<F> {
+    facilitator: F,
+    oracle: Option<PriceOracle>,
+}
+
+impl<F> PriceGate<F> {
+    pub fn new(facilitator: F, oracle: Option<PriceOracle>) -> Self {
+        Self {
+            facilitator,
+            oracle,
+        }
+    }
+
+    async fn check(&self, requirements: &PaymentRequirements) -> Result<(), FacilitatorLocalError> {
+        match &self.oracle {
+            Some(oracle) => oracle.check(requirements).await,
+            None if requirements.price_usd.is_some() => Err(FacilitatorLocalError::PriceOracle(
+                "no price oracle configured".to_string(),
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<F> Facilitator for PriceGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.check(&request.payment_requirements).await?;
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        self.check(&request.payment_requirements).await?;
+        self.facilitator.settle(request).await
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checks_amounts_within_tolerance() {
+        let price_usd = MoneyAmount::from_str("1.50").unwrap();
+        let price = Decimal::from_str("0.9990").unwrap();
+        // $1.50 at $0.999 is 1_501_501 units of a 6-decimals token.
+        assert!(check_amount(&price_usd, price, 6, U256::from(1_501_501), 100).is_ok());
+        assert!(check_amount(&price_usd, price, 6, U256::from(1_500_000), 100).is_ok());
+        assert!(matches!(
+            check_amount(&price_usd, price, 6, U256::from(1_400_000), 100),
+            Err(FacilitatorLocalError::PriceOutOfTolerance(..))
+        ));
+        assert!(check_amount(&price_usd, price, 6, U256::from(1_600_000), 100).is_err());
+
+        let feeds = parse_feeds(
+            "base:0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913=0x7e860098F58bBFC8648a4311b374B1D669a2bc6B",
+        )
+        .unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert!(parse_feeds("base=0x00").is_err());
+    }
+}
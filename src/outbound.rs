@@ -0,0 +1,224 @@
+//! Restrictions on outbound HTTP calls to operator-supplied URLs.
+//!
+//! The facilitator calls external services (e.g. the approval service or webhooks) from inside
+//! the deployment network, so a mistyped or malicious URL could reach internal hosts or cloud
+//! metadata endpoints. An [`OutboundPolicy`] guards those calls:
+//! - only allowlisted URL schemes are accepted (`https` by default),
+//! - loopback, private, link-local, CGNAT, multicast and unspecified addresses are refused,
+//!   unless listed in `OUTBOUND_ALLOWED_CIDRS`; link-local (cloud metadata) addresses are always refused,
+//! - host names are checked against the same rules every time they are resolved, on the connection
+//!   path itself, so DNS rebinding can not swap in an internal address after validation,
+//! - redirects and system proxies are not followed.
+//!
+//! Environment variables used:
+//! - `OUTBOUND_ALLOWED_SCHEMES` — comma-separated URL schemes (default: `https`),
+//! - `OUTBOUND_ALLOWED_CIDRS` — comma-separated networks allowed despite being internal, e.g. `10.1.0.0/16`.
+
+use ipnet::IpNet;
+use reqwest::dns::{Addrs, Name, Resolve, Resolving};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use url::{Host, Url};
+
+use crate::from_env;
+
+#[derive(Debug, thiserror::Error)]
+pub enum OutboundError {
+    #[error("URL scheme {0} is not allowed for outbound calls")]
+    SchemeNotAllowed(String),
+    #[error("URL has no host")]
+    MissingHost,
+    #[error("Outbound calls to {0} are not allowed")]
+    AddressNotAllowed(IpAddr),
+    #[error("{0} does not resolve to any allowed address")]
+    NoAllowedAddress(String),
+    #[error("Invalid {name}: {reason}")]
+    Config { name: &'static str, reason: String },
+}
+
+/// Which URLs and addresses the facilitator may call out to.
+#[derive(Debug, Clone)]
+pub struct OutboundPolicy {
+    allowed_schemes: Vec<String>,
+    allowed_networks: Vec<IpNet>,
+}
+
+impl Default for OutboundPolicy {
+    fn default() -> Self {
+        Self {
+            allowed_schemes: vec!["https".to_string()],
+            allowed_networks: Vec::new(),
+        }
+    }
+}
+
+impl OutboundPolicy {
+    pub fn new(allowed_schemes: Vec<String>, allowed_networks: Vec<IpNet>) -> Self {
+        Self {
+            allowed_schemes,
+            allowed_networks,
+        }
+    }
+
+    /// Reads `OUTBOUND_ALLOWED_SCHEMES` and `OUTBOUND_ALLOWED_CIDRS`.
+    pub fn from_env() -> Result<Self, OutboundError> {
+        let defaults = Self::default();
+        let allowed_schemes = match std::env::var(from_env::ENV_OUTBOUND_ALLOWED_SCHEMES) {
+            Ok(schemes) => schemes
+                .split(',')
+                .map(|s| s.trim().to_ascii_lowercase())
+                .filter(|s| !s.is_empty())
+                .collect(),
+            Err(_) => defaults.allowed_schemes,
+        };
+        let allowed_networks = match std::env::var(from_env::ENV_OUTBOUND_ALLOWED_CIDRS) {
+            Ok(cidrs) => cidrs
+                .split(',')
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(|s| {
+                    s.parse::<IpNet>().map_err(|e| OutboundError::Config {
+                        name: from_env::ENV_OUTBOUND_ALLOWED_CIDRS,
+                        reason: format!("{s}: {e}"),
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            Err(_) => defaults.allowed_networks,
+        };
+        Ok(Self::new(allowed_schemes, allowed_networks))
+    }
+
+    /// Checks the scheme of `url`, and its host if given as an IP address.
+    ///
+    /// Host names are checked on resolution by clients built with [`OutboundPolicy::http_client`].
+    pub fn validate_url(&self, url: &Url) -> Result<(), OutboundError> {
+        if !self.allowed_schemes.iter().any(|s| s == url.scheme()) {
+            return Err(OutboundError::SchemeNotAllowed(url.scheme().to_string()));
+        }
+        match url.host() {
+            None => Err(OutboundError::MissingHost),
+            Some(Host::Domain(_)) => Ok(()),
+            Some(Host::Ipv4(ip)) => self.check_ip(IpAddr::V4(ip)),
+            Some(Host::Ipv6(ip)) => self.check_ip(IpAddr::V6(ip)),
+        }
+    }
+
+    /// Whether the facilitator may connect to `ip`.
+    pub fn check_ip(&self, ip: IpAddr) -> Result<(), OutboundError> {
+        let ip = match ip {
+            IpAddr::V6(v6) => v6.to_ipv4_mapped().map(IpAddr::V4).unwrap_or(ip),
+            IpAddr::V4(_) => ip,
+        };
+        let allowed = if is_link_local(ip) {
+            false
+        } else if is_internal(ip) {
+            self.allowed_networks.iter().any(|net| net.contains(&ip))
+        } else {
+            true
+        };
+        if allowed {
+            Ok(())
+        } else {
+            Err(OutboundError::AddressNotAllowed(ip))
+        }
+    }
+
+    /// HTTP client enforcing this policy on every resolved address, without redirects or proxies.
+    pub fn http_client(&self) -> reqwest::Client {
+        reqwest::Client::builder()
+            .dns_resolver(Arc::new(GuardedResolver {
+                policy: self.clone(),
+            }))
+            .redirect(reqwest::redirect::Policy::none())
+            .no_proxy()
+            .build()
+            .expect("outbound HTTP client configuration is valid")
+    }
+}
+
+fn is_link_local(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => v4.is_link_local(),
+        IpAddr::V6(v6) => v6.is_unicast_link_local(),
+    }
+}
+
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            v4.is_loopback()
+                || v4.is_private()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_multicast()
+                || v4.is_documentation()
+                // Carrier-grade NAT, 100.64.0.0/10
+                || (v4.octets()[0] == 100 && (v4.octets()[1] & 0xc0) == 64)
+        }
+        IpAddr::V6(v6) => {
+            v6.is_loopback() || v6.is_unspecified() || v6.is_multicast() || v6.is_unique_local()
+        }
+    }
+}
+
+/// DNS resolver dropping addresses refused by the [`OutboundPolicy`].
+struct GuardedResolver {
+    policy: OutboundPolicy,
+}
+
+impl Resolve for GuardedResolver {
+    fn resolve(&self, name: Name) -> Resolving {
+        let policy = self.policy.clone();
+        Box::pin(async move {
+            let host = name.as_str().to_string();
+            let addresses: Vec<SocketAddr> = tokio::net::lookup_host((host.as_str(), 0))
+                .await?
+                .filter(|address| match policy.check_ip(address.ip()) {
+                    Ok(()) => true,
+                    Err(e) => {
+                        tracing::warn!(host, error = %e, "Refusing resolved outbound address");
+                        false
+                    }
+                })
+                .collect();
+            if addresses.is_empty() {
+                return Err(OutboundError::NoAllowedAddress(host).into());
+            }
+            let addrs: Addrs = Box::new(addresses.into_iter());
+            Ok(addrs)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refuses_internal_targets_unless_allowlisted() {
+        let policy = OutboundPolicy::new(
+            vec!["https".to_string()],
+            vec!["10.1.0.0/16".parse().unwrap()],
+        );
+        let check = |url: &str| policy.validate_url(&Url::parse(url).unwrap());
+        assert!(check("https://example.com/hook").is_ok());
+        assert!(check("https://10.1.2.3/hook").is_ok());
+        assert!(matches!(
+            check("http://example.com/hook"),
+            Err(OutboundError::SchemeNotAllowed(_))
+        ));
+        for url in [
+            "https://127.0.0.1/",
+            "https://10.2.0.1/",
+            "https://169.254.169.254/latest/meta-data",
+            "https://[::1]/",
+            "https://[::ffff:192.168.0.1]/",
+            "https://100.64.0.1/",
+        ] {
+            assert!(
+                matches!(check(url), Err(OutboundError::AddressNotAllowed(_))),
+                "{url}"
+            );
+        }
+    }
+}
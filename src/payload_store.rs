@@ -0,0 +1,343 @@
+//! Content-addressable storage of raw `/verify` and `/settle` request bodies, for forensics.
+//!
+//! When a payment is disputed, or a client sends a payload the facilitator can not parse, the logs
+//! only show what the facilitator made of it. A [`PayloadStore`] keeps the request bodies exactly as
+//! received, one file per distinct body, named after the SHA-256 of its bytes. The hash is returned
+//! to the client in the `X-Payload-Hash` response header and logged with the request, so a payload
+//! can be looked up later with `GET /admin/payloads/{hash}`.
+//!
+//! Stored payloads contain payment signatures. With `PAYLOAD_STORE_KEY` set, they are encrypted at
+//! rest with AES-256-GCM-SIV under a random nonce. Files older than the retention period are deleted,
+//! then the oldest ones if the store grows over its size limit.
+//!
+//! Environment variables used:
+//! - `PAYLOAD_STORE_DIR` — directory of the store. Storage is disabled if unset.
+//! - `PAYLOAD_STORE_KEY` — 32-byte encryption key, hex-encoded. Payloads are stored in clear if unset.
+//! - `PAYLOAD_STORE_RETENTION_SECS` — how long payloads are kept (default: `604800`, 7 days).
+//! - `PAYLOAD_STORE_MAX_BYTES` — total size above which the oldest payloads are deleted (default: unlimited).
+
+use aes_gcm_siv::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm_siv::{Aes256GcmSiv, Nonce};
+use alloy::hex;
+use axum::body::{Body, Bytes};
+use axum::extract::{Path, Request, State};
+use axum::http::{HeaderValue, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
+
+use crate::from_env;
+use crate::types::ErrorResponse;
+
+/// Largest request body recorded; bigger requests are rejected with `413 Payload Too Large`.
+const MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+/// Interval between retention sweeps.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(600);
+/// Length of the AES-GCM-SIV nonce prepended to encrypted payloads.
+const NONCE_LEN: usize = 12;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PayloadStoreError {
+    #[error("Payload store I/O error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Invalid {name}: {reason}")]
+    Config { name: &'static str, reason: String },
+    #[error("Can not decrypt stored payload")]
+    Decryption,
+}
+
+/// Hex-encoded SHA-256 of a request body.
+pub fn payload_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+struct PayloadStoreInner {
+    dir: PathBuf,
+    cipher: Option<Aes256GcmSiv>,
+    retention: Duration,
+    max_bytes: Option<u64>,
+}
+
+/// Directory of raw request bodies keyed by their SHA-256.
+///
+/// Cheap to clone: all clones share the same directory and key.
+#[derive(Clone)]
+pub struct PayloadStore {
+    inner: Arc<PayloadStoreInner>,
+}
+
+impl PayloadStore {
+    /// Creates the store in `dir`, encrypting payloads if `key` is given.
+    pub fn new(
+        dir: PathBuf,
+        key: Option<[u8; 32]>,
+        retention: Duration,
+        max_bytes: Option<u64>,
+    ) -> Result<Self, PayloadStoreError> {
+        std::fs::create_dir_all(&dir)?;
+        let cipher = key.map(|key| Aes256GcmSiv::new(&key.into()));
+        Ok(Self {
+            inner: Arc::new(PayloadStoreInner {
+                dir,
+                cipher,
+                retention,
+                max_bytes,
+            }),
+        })
+    }
+
+    /// Reads the `PAYLOAD_STORE_*` variables; `None` if `PAYLOAD_STORE_DIR` is unset.
+    pub fn from_env() -> Result<Option<Self>, PayloadStoreError> {
+        let Ok(dir) = std::env::var(from_env::ENV_PAYLOAD_STORE_DIR) else {
+            return Ok(None);
+        };
+        let key = match std::env::var(from_env::ENV_PAYLOAD_STORE_KEY) {
+            Ok(key) => {
+                let config_error = |reason: String| PayloadStoreError::Config {
+                    name: from_env::ENV_PAYLOAD_STORE_KEY,
+                    reason,
+                };
+                let bytes = hex::decode(key.trim()).map_err(|e| config_error(e.to_string()))?;
+                let key: [u8; 32] = bytes
+                    .try_into()
+                    .map_err(|_| config_error("expected 32 bytes".to_string()))?;
+                Some(key)
+            }
+            Err(_) => None,
+        };
+        let number = |name: &str| {
+            std::env::var(name)
+                .ok()
+                .and_then(|s| s.parse::<u64>().ok())
+                .filter(|n| *n > 0)
+        };
+        let retention = number(from_env::ENV_PAYLOAD_STORE_RETENTION_SECS)
+            .map(Duration::from_secs)
+            .unwrap_or(Duration::from_secs(7 * 24 * 3600));
+        let max_bytes = number(from_env::ENV_PAYLOAD_STORE_MAX_BYTES);
+        Self::new(PathBuf::from(dir), key, retention, max_bytes).map(Some)
+    }
+
+    /// Stores `bytes` under their hash, which is returned. Storing the same bytes twice is a no-op.
+    pub async fn put(&self, bytes: &[u8]) -> Result<String, PayloadStoreError> {
+        let hash = payload_hash(bytes);
+        let path = self.inner.dir.join(&hash);
+        if tokio::fs::try_exists(&path).await? {
+            return Ok(hash);
+        }
+        let contents = match &self.inner.cipher {
+            Some(cipher) => {
+                let nonce = Aes256GcmSiv::generate_nonce(&mut OsRng);
+                let ciphertext = cipher
+                    .encrypt(&nonce, bytes)
+                    .expect("AES-GCM-SIV encryption of an in-memory buffer");
+                [nonce.as_slice(), &ciphertext].concat()
+            }
+            None => bytes.to_vec(),
+        };
+        let tmp_path = path.with_extension("tmp");
+        tokio::fs::write(&tmp_path, contents).await?;
+        tokio::fs::rename(&tmp_path, &path).await?;
+        Ok(hash)
+    }
+
+    /// Raw bytes stored under `hash`, if still retained.
+    pub async fn get(&self, hash: &str) -> Result<Option<Vec<u8>>, PayloadStoreError> {
+        // Only accept hashes, so the lookup can not escape the store directory.
+        if hash.len() != 64 || !hash.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Ok(None);
+        }
+        let contents = match tokio::fs::read(self.inner.dir.join(hash.to_ascii_lowercase())).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match &self.inner.cipher {
+            Some(cipher) => {
+                if contents.len() < NONCE_LEN {
+                    return Err(PayloadStoreError::Decryption);
+                }
+                let (nonce, ciphertext) = contents.split_at(NONCE_LEN);
+                cipher
+                    .decrypt(Nonce::from_slice(nonce), ciphertext)
+                    .map(Some)
+                    .map_err(|_| PayloadStoreError::Decryption)
+            }
+            None => Ok(Some(contents)),
+        }
+    }
+
+    /// Hashes of the stored payloads, oldest first.
+    #[allow(dead_code)] // Used by the x402-replay binary.
+    pub async fn hashes(&self) -> Result<Vec<String>, PayloadStoreError> {
+        let mut files = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.inner.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            // Skips temporary files of interrupted writes.
+            if metadata.is_file() && name.len() == 64 {
+                files.push((metadata.modified()?, name));
+            }
+        }
+        files.sort();
+        Ok(files.into_iter().map(|(_, hash)| hash).collect())
+    }
+
+    /// Deletes payloads past retention, then the oldest ones until the store fits its size limit.
+    pub async fn prune(&self) -> Result<(), PayloadStoreError> {
+        let now = SystemTime::now();
+        let mut files = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.inner.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let metadata = entry.metadata().await?;
+            if !metadata.is_file() {
+                continue;
+            }
+            let modified = metadata.modified()?;
+            let age = now.duration_since(modified).unwrap_or_default();
+            if age > self.inner.retention {
+                tokio::fs::remove_file(entry.path()).await?;
+            } else {
+                files.push((modified, metadata.len(), entry.path()));
+            }
+        }
+        if let Some(max_bytes) = self.inner.max_bytes {
+            files.sort_by_key(|(modified, _, _)| *modified);
+            let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+            for (_, len, path) in files {
+                if total <= max_bytes {
+                    break;
+                }
+                tokio::fs::remove_file(&path).await?;
+                total -= len;
+            }
+        }
+        Ok(())
+    }
+
+    /// Spawns the retention sweep, stopping when `cancellation_token` is cancelled.
+    pub fn spawn_pruning(
+        &self,
+        cancellation_token: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        let store = self.clone();
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(PRUNE_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    _ = ticker.tick() => {
+                        if let Err(e) = store.prune().await {
+                            tracing::warn!(error = %e, "Payload store pruning failed");
+                        }
+                    }
+                }
+            }
+        })
+    }
+}
+
+/// Records the body of `POST` requests in the [`PayloadStore`] before passing them on.
+///
+/// A failure to store is logged and does not fail the request.
+pub async fn record_payload(
+    State(store): State<PayloadStore>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if request.method() != Method::POST {
+        return next.run(request).await;
+    }
+    let (parts, body) = request.into_parts();
+    let bytes: Bytes = match axum::body::to_bytes(body, MAX_PAYLOAD_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse {
+                    error: "Request body too large".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+    let hash = match store.put(&bytes).await {
+        Ok(hash) => {
+            tracing::info!(path = %parts.uri.path(), payload_hash = %hash, "Request payload stored");
+            Some(hash)
+        }
+        Err(e) => {
+            tracing::warn!(path = %parts.uri.path(), error = %e, "Failed to store request payload");
+            None
+        }
+    };
+    let mut response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    if let Some(value) = hash.and_then(|hash| HeaderValue::from_str(&hash).ok()) {
+        response.headers_mut().insert("x-payload-hash", value);
+    }
+    response
+}
+
+/// Admin routes reading stored payloads.
+pub fn admin_routes() -> Router<PayloadStore> {
+    Router::new().route("/payloads/{hash}", get(get_payload))
+}
+
+/// `GET /admin/payloads/{hash}`: Raw request body stored under `hash`.
+#[instrument(skip_all)]
+pub async fn get_payload(State(store): State<PayloadStore>, Path(hash): Path<String>) -> Response {
+    match store.get(&hash).await {
+        Ok(Some(bytes)) => (
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "application/octet-stream")],
+            bytes,
+        )
+            .into_response(),
+        Ok(None) => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Payload not found".to_string(),
+            }),
+        )
+            .into_response(),
+        Err(e) => {
+            tracing::error!(error = %e, hash, "Failed to read stored payload");
+            (
+                StatusCode::INTERNAL_SERVER_ERROR,
+                Json(ErrorResponse {
+                    error: "Failed to read stored payload".to_string(),
+                }),
+            )
+                .into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn encrypted_payloads_round_trip() {
+        let dir = std::env::temp_dir().join(format!("x402-payloads-{}", std::process::id()));
+        let store = PayloadStore::new(dir.clone(), Some([7; 32]), Duration::from_secs(60), None)
+            .expect("store directory");
+        let body = br#"{"paymentPayload":{}}"#;
+        let hash = store.put(body).await.unwrap();
+        assert_eq!(hash, payload_hash(body));
+        assert_ne!(std::fs::read(dir.join(&hash)).unwrap(), body);
+        assert_eq!(store.get(&hash).await.unwrap().as_deref(), Some(&body[..]));
+        assert_eq!(store.get("../secret").await.unwrap(), None);
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+//! WebAssembly plugins supplying verification hooks and pricing logic, with the `plugins` feature only.
+//!
+//! Operators who can not recompile the facilitator can load WASM modules at startup instead. A plugin is a
+//! core WebAssembly module (binary or text) exporting its `memory`, an allocator and one or both hooks:
+//!
+//! - `x402_alloc(len: i32) -> i32` — returns a pointer to `len` writable bytes.
+//! - `x402_verify(ptr: i32, len: i32) -> i32` — `0` accepts the request, anything else rejects it.
+//! - `x402_min_amount(ptr: i32, len: i32) -> i64` — smallest `maxAmountRequired` accepted for the request,
+//!   in the token's smallest unit; negative for no minimum.
+//!
+//! Hooks receive `{"phase": "verify" | "settle", "request": <VerifyRequest>}` as JSON, on both `/verify`
+//! and `/settle`. Plugins are sandboxed: a module may not import anything, so it has no WASI, clock, network
+//! or filesystem access. Each call runs in a fresh instance with bounded memory and fuel; a plugin that traps,
+//! runs out of either, or lacks the expected exports rejects the request.
+//!
+//! Environment variables used:
+//! - `PLUGIN_PATHS` — comma-separated paths of the `.wasm` or `.wat` modules to load, in order.
+
+use std::path::Path;
+use wasmtime::{Config, Engine, Instance, Module, Store, StoreLimits, StoreLimitsBuilder};
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::types::{
+    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Instructions a single hook call may execute, in wasmtime fuel units.
+const FUEL_PER_CALL: u64 = 10_000_000;
+/// Linear memory a plugin instance may grow to.
+const MEMORY_LIMIT_BYTES: usize = 16 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum PluginError {
+    #[error("Can not load plugin {path}: {source}")]
+    Load {
+        path: String,
+        #[source]
+        source: wasmtime::Error,
+    },
+    #[error("Plugin {0} imports host functions, which are not allowed")]
+    Imports(String),
+    #[error("Can not start the plugin engine: {0}")]
+    Engine(#[source] wasmtime::Error),
+}
+
+struct Plugin {
+    name: String,
+    module: Module,
+}
+
+/// Verification phase a hook is called in.
+#[derive(Debug, Clone, Copy)]
+enum Phase {
+    Verify,
+    Settle,
+}
+
+impl Phase {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Verify => "verify",
+            Phase::Settle => "settle",
+        }
+    }
+}
+
+/// Loaded plugin modules and the engine running them.
+pub struct Plugins {
+    engine: Engine,
+    plugins: Vec<Plugin>,
+}
+
+impl Plugins {
+    /// Loads the modules at `paths`, refusing any that imports host functions.
+    pub fn load<P: AsRef<Path>>(paths: &[P]) -> Result<Self, PluginError> {
+        let mut config = Config::new();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config).map_err(PluginError::Engine)?;
+        let plugins = paths
+            .iter()
+            .map(|path| {
+                let path = path.as_ref();
+                let display = path.display().to_string();
+                let module =
+                    Module::from_file(&engine, path).map_err(|source| PluginError::Load {
+                        path: display.clone(),
+                        source,
+                    })?;
+                if module.imports().len() > 0 {
+                    return Err(PluginError::Imports(display));
+                }
+                let name = path
+                    .file_stem()
+                    .map_or(display, |stem| stem.to_string_lossy().into_owned());
+                tracing::info!(plugin = %name, "Loaded plugin");
+                Ok(Plugin { name, module })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { engine, plugins })
+    }
+
+    /// Loads the modules listed in `PLUGIN_PATHS`; no plugins if unset.
+    pub fn from_env() -> Result<Self, PluginError> {
+        let paths: Vec<String> = std::env::var(from_env::ENV_PLUGIN_PATHS)
+            .map(|value| {
+                value
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|path| !path.is_empty())
+                    .map(str::to_string)
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self::load(&paths)
+    }
+
+    /// Runs the hooks of every plugin on `request`; the first plugin rejecting it wins.
+    fn check(&self, phase: Phase, request: &VerifyRequest) -> Result<(), FacilitatorLocalError> {
+        if self.plugins.is_empty() {
+            return Ok(());
+        }
+        let input = serde_json::to_vec(&serde_json::json!({
+            "phase": phase.as_str(),
+            "request": request,
+        }))
+        .map_err(|e| FacilitatorLocalError::DecodingError(e.to_string()))?;
+        for plugin in &self.plugins {
+            let accepted = self.run(plugin, &input, request);
+            match accepted {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::info!(
+                        monotonic_counter.x402.plugins.rejections = 1,
+                        plugin = %plugin.name,
+                        phase = phase.as_str(),
+                    );
+                    return Err(FacilitatorLocalError::RuleViolation(format!(
+                        "plugin {}",
+                        plugin.name
+                    )));
+                }
+                Err(e) => {
+                    tracing::warn!(plugin = %plugin.name, error = %e, "Plugin call failed");
+                    return Err(FacilitatorLocalError::RuleViolation(format!(
+                        "plugin {}",
+                        plugin.name
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the hooks `plugin` exports in a fresh, limited instance; `Ok(false)` if one rejects `request`.
+    fn run(
+        &self,
+        plugin: &Plugin,
+        input: &[u8],
+        request: &VerifyRequest,
+    ) -> Result<bool, wasmtime::Error> {
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MEMORY_LIMIT_BYTES)
+            .instances(1)
+            .build();
+        let mut store: Store<StoreLimits> = Store::new(&self.engine, limits);
+        store.limiter(|limits| limits);
+        store.set_fuel(FUEL_PER_CALL)?;
+        let instance = Instance::new(&mut store, &plugin.module, &[])?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .ok_or_else(|| wasmtime::Error::msg("missing memory export"))?;
+        let alloc = instance.get_typed_func::<i32, i32>(&mut store, "x402_alloc")?;
+        let len = i32::try_from(input.len())?;
+        let ptr = alloc.call(&mut store, len)?;
+        memory.write(&mut store, usize::try_from(ptr)?, input)?;
+
+        if let Ok(verify) = instance.get_typed_func::<(i32, i32), i32>(&mut store, "x402_verify")
+            && verify.call(&mut store, (ptr, len))? != 0
+        {
+            return Ok(false);
+        }
+        if let Ok(min_amount) =
+            instance.get_typed_func::<(i32, i32), i64>(&mut store, "x402_min_amount")
+            && let Ok(min_amount) = u64::try_from(min_amount.call(&mut store, (ptr, len))?)
+            && request.payment_requirements.max_amount_required.0
+                < alloy::primitives::U256::from(min_amount)
+        {
+            return Ok(false);
+        }
+        Ok(true)
+    }
+}
+
+/// A [`Facilitator`] consulting the operator's WASM [`Plugins`] before verifying or settling a request.
+pub struct PluginGate<F> {
+    facilitator: F,
+    plugins: Plugins,
+}
+
+impl<F> PluginGate<F> {
+    pub fn new(facilitator: F, plugins: Plugins) -> Self {
+        Self {
+            facilitator,
+            plugins,
+        }
+    }
+}
+
+impl<F> Facilitator for PluginGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.plugins.check(Phase::Verify, request)?;
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        self.plugins.check(Phase::Settle, request)?;
+        self.facilitator.settle(request).await
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepts any request, with a minimum amount of 1000.
+    const MIN_AMOUNT_PLUGIN: &str = r#"
+        (module
+            (memory (export "memory") 1)
+            (func (export "x402_alloc") (param i32) (result i32) (i32.const 0))
+            (func (export "x402_verify") (param i32 i32) (result i32) (i32.const 0))
+            (func (export "x402_min_amount") (param i32 i32) (result i64) (i64.const 1000)))
+    "#;
+
+    fn request(amount: u64) -> VerifyRequest {
+        serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": amount.to_string(),
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x0000000000000000000000000000000000000002"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn enforces_plugin_hooks() {
+        let dir = std::env::temp_dir().join(format!("x402-plugins-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let min_amount = dir.join("min-amount.wat");
+        std::fs::write(&min_amount, MIN_AMOUNT_PLUGIN).unwrap();
+        let spinning = dir.join("spinning.wat");
+        std::fs::write(
+            &spinning,
+            r#"(module (memory (export "memory") 1)
+                (func (export "x402_alloc") (param i32) (result i32) (i32.const 0))
+                (func (export "x402_verify") (param i32 i32) (result i32) (loop (br 0)) (i32.const 0)))"#,
+        )
+        .unwrap();
+        let importing = dir.join("importing.wat");
+        std::fs::write(&importing, r#"(module (import "env" "f" (func)))"#).unwrap();
+
+        let plugins = Plugins::load(&[&min_amount]).unwrap();
+        assert!(plugins.check(Phase::Verify, &request(1000)).is_ok());
+        assert!(plugins.check(Phase::Settle, &request(999)).is_err());
+        let plugins = Plugins::load(&[&spinning]).unwrap();
+        assert!(plugins.check(Phase::Verify, &request(1000)).is_err());
+        assert!(matches!(
+            Plugins::load(&[&importing]),
+            Err(PluginError::Imports(_))
+        ));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
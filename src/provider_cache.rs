@@ -18,12 +18,15 @@
 //! let provider = provider_cache.by_network(Network::Base)?;
 //! ```
 
+use dashmap::DashMap;
 use std::borrow::Borrow;
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use crate::chain::FromEnvByNetworkBuild;
-use crate::chain::NetworkProvider;
-use crate::network::Network;
+use crate::chain::{FromConfigByNetworkBuild, NetworkProvider};
+use crate::config::{FacilitatorConfig, NetworkConfig};
+use crate::network::{CustomNetwork, Network};
 
 /// A cache of pre-initialized [`EthereumProvider`] instances keyed by network.
 ///
@@ -31,8 +34,12 @@ use crate::network::Network;
 /// and wrapping them with appropriate signing and filler middleware.
 ///
 /// Use [`ProviderCache::from_env`] to load credentials and connect using environment variables.
+/// Custom networks can be added afterwards with [`ProviderCache::register`].
 pub struct ProviderCache {
     providers: HashMap<Network, NetworkProvider>,
+    /// Providers of networks registered at runtime. They are leaked so that [`ProviderMap::by_network`]
+    /// can hand out plain references; networks can not be unregistered, so this is bounded.
+    registered: DashMap<Network, &'static NetworkProvider>,
 }
 
 /// A generic cache of pre-initialized Ethereum provider instances [`ProviderMap::Value`] keyed by network.
@@ -49,6 +56,7 @@ pub trait ProviderMap {
     fn values(&self) -> impl Iterator<Item = &Self::Value> + Send;
 }
 
+/// Iterates over the providers configured at startup, without those registered at runtime.
 impl<'a> IntoIterator for &'a ProviderCache {
     type Item = (&'a Network, &'a NetworkProvider);
     type IntoIter = std::collections::hash_map::Iter<'a, Network, NetworkProvider>;
@@ -59,6 +67,13 @@ impl<'a> IntoIterator for &'a ProviderCache {
 }
 
 impl ProviderCache {
+    fn new(providers: HashMap<Network, NetworkProvider>) -> Self {
+        Self {
+            providers,
+            registered: DashMap::new(),
+        }
+    }
+
     /// Constructs a new [`ProviderCache`] from environment variables.
     ///
     /// Expects the following to be set:
@@ -75,7 +90,56 @@ impl ProviderCache {
                 providers.insert(*network, network_provider);
             }
         }
-        Ok(Self { providers })
+        Ok(Self::new(providers))
+    }
+
+    /// Constructs a new [`ProviderCache`] with exactly the networks declared in a [`FacilitatorConfig`].
+    ///
+    /// Fails if a network is misconfigured or if a provider cannot connect.
+    pub async fn from_config(
+        config: &FacilitatorConfig,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_networks(&config.networks).await
+    }
+
+    /// Constructs a new [`ProviderCache`] with exactly the given networks.
+    ///
+    /// Fails if a network is misconfigured or if a provider cannot connect.
+    pub async fn from_networks<'a>(
+        networks: impl IntoIterator<Item = (&'a Network, &'a NetworkConfig)>,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut providers = HashMap::new();
+        for (network, network_config) in networks {
+            let network_provider = NetworkProvider::from_config(*network, network_config).await?;
+            providers.insert(*network, network_provider);
+        }
+        Ok(Self::new(providers))
+    }
+
+    /// Registers a custom EVM network and connects to it, making it available to `/verify`, `/settle`
+    /// and `/supported` right away.
+    ///
+    /// Fails if the network is already served, conflicts with a known one, or if the provider cannot connect.
+    pub async fn register(
+        &self,
+        custom: CustomNetwork,
+        config: &NetworkConfig,
+    ) -> Result<Network, Box<dyn std::error::Error>> {
+        let network = custom.register()?;
+        if self.by_network(network).is_some() {
+            return Err(format!("Network {network} is already configured").into());
+        }
+        let provider = NetworkProvider::from_config(network, config).await?;
+        match self.registered.entry(network) {
+            dashmap::Entry::Occupied(_) => {
+                Err(format!("Network {network} is already configured").into())
+            }
+            dashmap::Entry::Vacant(entry) => {
+                entry.insert(Box::leak(Box::new(provider)));
+                tracing::info!(network = %network, "Custom network registered");
+                Ok(network)
+            }
+        }
     }
 }
 
@@ -83,10 +147,27 @@ impl ProviderMap for ProviderCache {
     type Value = NetworkProvider;
 
     fn by_network<N: Borrow<Network>>(&self, network: N) -> Option<&NetworkProvider> {
-        self.providers.get(network.borrow())
+        let network = network.borrow();
+        self.providers
+            .get(network)
+            .or_else(|| self.registered.get(network).map(|provider| *provider))
     }
 
     fn values(&self) -> impl Iterator<Item = &Self::Value> {
-        self.providers.values()
+        let registered: Vec<&NetworkProvider> =
+            self.registered.iter().map(|provider| *provider).collect();
+        self.providers.values().chain(registered)
+    }
+}
+
+impl<T: ProviderMap> ProviderMap for Arc<T> {
+    type Value = T::Value;
+
+    fn by_network<N: Borrow<Network>>(&self, network: N) -> Option<&Self::Value> {
+        self.as_ref().by_network(network)
+    }
+
+    fn values(&self) -> impl Iterator<Item = &Self::Value> + Send {
+        self.as_ref().values()
     }
 }
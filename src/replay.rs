@@ -0,0 +1,488 @@
+//! Replay protection for ERC-3009 nonces.
+//!
+//! An ERC-3009 authorization can be settled once: the token marks its nonce used. Two clients racing to settle
+//! the same authorization both pass `/verify`, and the second settlement reverts after having cost gas. The
+//! [`ReplayGuard`] remembers the nonces of the authorizations this facilitator is settling or has settled, per
+//! network, token and payer, and rejects payments reusing them with `nonce_replayed`, at `/verify` already,
+//! before any RPC call.
+//!
+//! A nonce is claimed when its settlement starts. It is released if the settlement fails, so the payment can be
+//! retried, and kept until the authorization's `validBefore` if it succeeds; the token rejects the authorization
+//! by itself after that.
+//!
+//! Stores of settled nonces:
+//! - [`MemoryStore`] (default) — nothing survives a restart,
+//! - [`DirStore`] — one JSON file per nonce, in `REPLAY_STORE_DIR`.
+//!
+//! Every change of the nonces is also published as a [`NonceChange`], which [`crate::replication`] sends to the
+//! other facilitators of an active/passive pair, so that a failover does not open a replay window.
+//!
+//! Backends whose chain does not record nonces, such as Tron, keep their own [`SeenNonces`] in a subdirectory of
+//! `REPLAY_STORE_DIR`, and save nonces as soon as they are claimed: their settlements are not protected by the
+//! chain, so they can not start without a persistent store.
+//!
+//! Environment variables used:
+//! - `REPLAY_STORE_DIR` — directory of the persistent store; settled nonces are kept in memory only if unset.
+
+use alloy::primitives::{B256, keccak256};
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ExactPaymentPayload, MixedAddress, PaymentPayload, PaymentRequirements, SettleRequest,
+    SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Where a nonce stands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NonceState {
+    /// A settlement with this nonce is under way.
+    InFlight,
+    /// An authorization with this nonce was settled.
+    Settled,
+}
+
+/// A nonce seen by the facilitator.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NonceRecord {
+    /// Hash of the network, token, payer and nonce.
+    pub key: B256,
+    pub network: Network,
+    pub payer: MixedAddress,
+    pub state: NonceState,
+    /// `validBefore` of the authorization; the record can be dropped after it.
+    pub expires_at: UnixTimestamp,
+}
+
+/// Change of the nonces of a [`SeenNonces`], as replicated to other facilitators.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum NonceChange {
+    /// The nonce was claimed or settled.
+    Upsert(NonceRecord),
+    /// The nonce in flight was released, its settlement having failed.
+    Remove(B256),
+}
+
+/// Changes kept for subscribers lagging behind, such as a slow replication peer.
+const CHANGE_CAPACITY: usize = 1024;
+
+/// Persistence of settled nonces.
+pub trait ReplayStore: Send + Sync {
+    fn save(&self, record: &NonceRecord) -> std::io::Result<()>;
+    fn remove(&self, key: &B256) -> std::io::Result<()>;
+    /// Records saved before a restart.
+    fn load(&self) -> std::io::Result<Vec<NonceRecord>>;
+}
+
+/// Store keeping nothing: nonces live in the guard only.
+#[derive(Debug, Default)]
+pub struct MemoryStore;
+
+impl ReplayStore for MemoryStore {
+    fn save(&self, _record: &NonceRecord) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&self, _key: &B256) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn load(&self) -> std::io::Result<Vec<NonceRecord>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Store writing one JSON file per settled nonce into a directory.
+#[derive(Debug)]
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, key: &B256) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+impl ReplayStore for DirStore {
+    fn save(&self, record: &NonceRecord) -> std::io::Result<()> {
+        let path = self.path(&record.key);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(record)?)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    fn remove(&self, key: &B256) -> std::io::Result<()> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn load(&self) -> std::io::Result<Vec<NonceRecord>> {
+        let mut records = Vec::new();
+        for file in std::fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match serde_json::from_slice(&std::fs::read(&path)?) {
+                Ok(record) => records.push(record),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping unreadable nonce record")
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+/// Nonces in flight or settled, by key.
+///
+/// Cheap to clone: all clones share the same nonces.
+#[derive(Clone)]
+pub struct SeenNonces {
+    nonces: Arc<DashMap<B256, NonceRecord>>,
+    store: Arc<dyn ReplayStore>,
+    changes: broadcast::Sender<NonceChange>,
+}
+
+impl SeenNonces {
+    /// Nonces of `store`, dropping the expired ones.
+    pub fn new(store: Box<dyn ReplayStore>) -> std::io::Result<Self> {
+        let now = UnixTimestamp::try_now().map_err(std::io::Error::other)?;
+        let nonces = DashMap::new();
+        for record in store.load()? {
+            if record.expires_at > now {
+                nonces.insert(record.key, record);
+            } else {
+                store.remove(&record.key)?;
+            }
+        }
+        Ok(Self {
+            nonces: Arc::new(nonces),
+            store: Arc::from(store),
+            changes: broadcast::channel(CHANGE_CAPACITY).0,
+        })
+    }
+
+    /// Nonces stored in `REPLAY_STORE_DIR`, or in memory only if unset.
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let store: Box<dyn ReplayStore> = match std::env::var(from_env::ENV_REPLAY_STORE_DIR) {
+            Ok(dir) => Box::new(DirStore::new(dir)?),
+            Err(_) => Box::new(MemoryStore),
+        };
+        Ok(Self::new(store)?)
+    }
+
+    /// Nonces stored in the `subdir` directory of `REPLAY_STORE_DIR`, which must be set.
+    #[allow(dead_code)] // Used by the Tron and Lightning backends.
+    pub fn persistent_from_env(subdir: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let dir = std::env::var(from_env::ENV_REPLAY_STORE_DIR).map_err(|_| {
+            format!(
+                "env {} not set, required to remember settled nonces",
+                from_env::ENV_REPLAY_STORE_DIR
+            )
+        })?;
+        Ok(Self::new(Box::new(DirStore::new(
+            PathBuf::from(dir).join(subdir),
+        )?))?)
+    }
+
+    /// Changes of the nonces from now on, except those applied with [`Self::apply`].
+    pub fn subscribe(&self) -> broadcast::Receiver<NonceChange> {
+        self.changes.subscribe()
+    }
+
+    /// Nonces in flight or settled, in no particular order.
+    pub fn records(&self) -> Vec<NonceRecord> {
+        self.nonces
+            .iter()
+            .map(|entry| entry.value().clone())
+            .collect()
+    }
+
+    /// Applies a change made by another facilitator. A settled nonce is never downgraded to in flight, nor
+    /// removed.
+    pub fn apply(&self, change: NonceChange) {
+        match change {
+            NonceChange::Upsert(record) => {
+                let key = record.key;
+                let settled = record.state == NonceState::Settled;
+                match self.nonces.entry(key) {
+                    Entry::Occupied(seen)
+                        if seen.get().state == NonceState::Settled && !settled => {}
+                    Entry::Occupied(mut seen) => {
+                        seen.insert(record);
+                    }
+                    Entry::Vacant(entry) => {
+                        entry.insert(record);
+                    }
+                }
+                if settled
+                    && let Some(record) = self.nonces.get(&key)
+                    && let Err(e) = self.store.save(&record)
+                {
+                    tracing::error!(key = %key, error = %e, "Failed to save replicated nonce");
+                }
+            }
+            NonceChange::Remove(key) => {
+                self.nonces
+                    .remove_if(&key, |_, record| record.state == NonceState::InFlight);
+            }
+        }
+    }
+
+    fn publish(&self, change: NonceChange) {
+        // No subscriber without replication.
+        let _ = self.changes.send(change);
+    }
+
+    /// Rejects `record` if its nonce is in flight or settled.
+    pub fn check(
+        &self,
+        record: &NonceRecord,
+        now: UnixTimestamp,
+    ) -> Result<(), FacilitatorLocalError> {
+        match self.nonces.get(&record.key) {
+            Some(seen) if seen.expires_at > now => Err(replayed(seen.value())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Marks the nonce of `record` in flight, unless it already is or was settled.
+    pub fn claim(
+        &self,
+        record: NonceRecord,
+        now: UnixTimestamp,
+    ) -> Result<(), FacilitatorLocalError> {
+        self.prune(now);
+        match self.nonces.entry(record.key) {
+            Entry::Occupied(seen) if seen.get().expires_at > now => {
+                return Err(replayed(seen.get()));
+            }
+            Entry::Occupied(mut seen) => {
+                seen.insert(record.clone());
+            }
+            Entry::Vacant(entry) => {
+                entry.insert(record.clone());
+            }
+        }
+        self.publish(NonceChange::Upsert(record));
+        Ok(())
+    }
+
+    /// Marks the nonce of `record` in flight as [`Self::claim`], and saves it right away, so that a restart during
+    /// the settlement does not forget it.
+    #[allow(dead_code)] // Used by the Tron and Lightning backends.
+    pub fn claim_persisted(
+        &self,
+        record: NonceRecord,
+        now: UnixTimestamp,
+    ) -> Result<(), FacilitatorLocalError> {
+        let key = record.key;
+        self.claim(record.clone(), now)?;
+        self.store.save(&record).map_err(|e| {
+            self.release(&key);
+            FacilitatorLocalError::ContractCall(format!("Failed to save claimed nonce: {e}"))
+        })
+    }
+
+    /// Marks the nonce `key` settled, until its authorization expires.
+    pub fn settled(&self, key: &B256) {
+        let Some(mut record) = self.nonces.get_mut(key) else {
+            return;
+        };
+        record.state = NonceState::Settled;
+        if let Err(e) = self.store.save(&record) {
+            tracing::error!(key = %key, error = %e, "Failed to save settled nonce");
+        }
+        let change = NonceChange::Upsert(record.clone());
+        drop(record);
+        self.publish(change);
+    }
+
+    /// Forgets the nonce `key` if still in flight, so that the payment can be retried.
+    pub fn release(&self, key: &B256) {
+        let released = self
+            .nonces
+            .remove_if(key, |_, record| record.state == NonceState::InFlight);
+        if released.is_none() {
+            return;
+        }
+        if let Err(e) = self.store.remove(key) {
+            tracing::warn!(key = %key, error = %e, "Failed to remove released nonce");
+        }
+        self.publish(NonceChange::Remove(*key));
+    }
+
+    /// Drops the nonces whose authorization expired.
+    fn prune(&self, now: UnixTimestamp) {
+        self.nonces.retain(|key, record| {
+            let keep = record.expires_at > now;
+            if !keep && let Err(e) = self.store.remove(key) {
+                tracing::warn!(key = %key, error = %e, "Failed to remove expired nonce");
+            }
+            keep
+        });
+    }
+}
+
+fn replayed(record: &NonceRecord) -> FacilitatorLocalError {
+    tracing::info!(
+        monotonic_counter.x402.nonces.replayed = 1,
+        network = %record.network,
+    );
+    FacilitatorLocalError::NonceReplayed(record.payer.clone())
+}
+
+/// In-flight record of the ERC-3009 nonce of a payment; other schemes have none.
+fn nonce_record(
+    payload: &PaymentPayload,
+    requirements: &PaymentRequirements,
+) -> Option<NonceRecord> {
+    let ExactPaymentPayload::Evm(evm) = &payload.payload else {
+        return None;
+    };
+    let authorization = &evm.authorization;
+    let key = keccak256(
+        format!(
+            "{}:{}:{}:{}",
+            payload.network,
+            requirements.asset,
+            authorization.from,
+            B256::from(authorization.nonce.0),
+        )
+        .to_lowercase(),
+    );
+    Some(NonceRecord {
+        key,
+        network: payload.network,
+        payer: authorization.from.into(),
+        state: NonceState::InFlight,
+        expires_at: authorization.valid_before,
+    })
+}
+
+/// [`Facilitator`] decorator rejecting payments that reuse the nonce of another one in flight or settled.
+pub struct ReplayGuard<F> {
+    facilitator: F,
+    nonces: SeenNonces,
+}
+
+impl<F> ReplayGuard<F> {
+    pub fn new(facilitator: F, nonces: SeenNonces) -> Self {
+        Self {
+            facilitator,
+            nonces,
+        }
+    }
+}
+
+impl<F> Facilitator for ReplayGuard<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        if let Some(record) = nonce_record(&request.payment_payload, &request.payment_requirements)
+        {
+            let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+            self.nonces.check(&record, now)?;
+        }
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let Some(record) = nonce_record(&request.payment_payload, &request.payment_requirements)
+        else {
+            return self.facilitator.settle(request).await;
+        };
+        let key = record.key;
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        self.nonces.claim(record, now)?;
+        let response = self.facilitator.settle(request).await;
+        match &response {
+            Ok(response) if response.success => self.nonces.settled(&key),
+            _ => self.nonces.release(&key),
+        }
+        response
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+
+    fn record(nonce: u8, expires_at: u64) -> NonceRecord {
+        NonceRecord {
+            key: B256::repeat_byte(nonce),
+            network: Network::BaseSepolia,
+            payer: Address::repeat_byte(1).into(),
+            state: NonceState::InFlight,
+            expires_at: UnixTimestamp(expires_at),
+        }
+    }
+
+    #[test]
+    fn nonces_are_claimed_once_until_released_or_expired() {
+        let nonces = SeenNonces::new(Box::new(MemoryStore)).unwrap();
+        let now = UnixTimestamp(100);
+        nonces.claim(record(1, 200), now).unwrap();
+        assert!(matches!(
+            nonces.check(&record(1, 200), now),
+            Err(FacilitatorLocalError::NonceReplayed(_))
+        ));
+        assert!(nonces.claim(record(1, 200), now).is_err());
+        nonces.release(&B256::repeat_byte(1));
+        nonces.claim(record(1, 200), now).unwrap();
+        nonces.settled(&B256::repeat_byte(1));
+        nonces.release(&B256::repeat_byte(1));
+        assert!(nonces.check(&record(1, 200), now).is_err());
+        assert!(nonces.check(&record(1, 200), UnixTimestamp(200)).is_ok());
+        nonces.claim(record(1, 300), UnixTimestamp(200)).unwrap();
+        nonces.check(&record(2, 200), now).unwrap();
+    }
+
+    #[test]
+    fn persisted_claims_survive_restarts() {
+        let dir = std::env::temp_dir().join(format!("x402-replay-{}", std::process::id()));
+        let now = UnixTimestamp(100);
+        let nonces = SeenNonces::new(Box::new(DirStore::new(&dir).unwrap())).unwrap();
+        nonces
+            .claim_persisted(record(1, 4_000_000_000), now)
+            .unwrap();
+        nonces
+            .claim_persisted(record(2, 4_000_000_000), now)
+            .unwrap();
+        nonces.release(&B256::repeat_byte(2));
+        let restarted = SeenNonces::new(Box::new(DirStore::new(&dir).unwrap())).unwrap();
+        assert!(restarted.check(&record(1, 4_000_000_000), now).is_err());
+        restarted.check(&record(2, 4_000_000_000), now).unwrap();
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
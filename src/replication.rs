@@ -0,0 +1,277 @@
+//! Replication of the replay store between the facilitators of an active/passive pair.
+//!
+//! The [`SeenNonces`] of a facilitator live in its memory and its own `REPLAY_STORE_DIR`. When a standby node
+//! takes over after a failover, it would not know the nonces the active node claimed or settled, and would
+//! accept their payments again until the token rejects them on chain, after having cost gas. With
+//! `REPLAY_REPLICATION_PEERS` set, every [`NonceChange`] is posted asynchronously to each peer, which applies it to
+//! its own nonces; a node starting up also fetches the nonces of its peers.
+//!
+//! Peers expose:
+//! - `POST /replication/nonces` — applies a [`NonceChange`] of a peer,
+//! - `GET /replication/nonces` — all nonces in flight or settled, for a peer starting up.
+//!
+//! Both require `Authorization: Bearer <REPLAY_REPLICATION_TOKEN>`, the same on every node. Deliveries failing are
+//! attempted up to [`crate::webhooks::MAX_ATTEMPTS`] times; changes are not persisted, so changes still pending
+//! at shutdown are only caught up by the peer at its next start. Replication is asynchronous: a failover in the
+//! instants between a claim and its delivery can still let a payment through twice.
+//!
+//! Environment variables used:
+//! - `REPLAY_REPLICATION_PEERS` — comma-separated base URLs of the other facilitators, subject to the outbound
+//!   policy,
+//! - `REPLAY_REPLICATION_TOKEN` — bearer token shared by the peers; required with peers.
+
+use alloy::primitives::{B256, keccak256};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode, header};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
+use url::Url;
+
+use crate::from_env;
+use crate::outbound::OutboundPolicy;
+use crate::replay::{NonceChange, NonceRecord, SeenNonces};
+use crate::types::ErrorResponse;
+use crate::webhooks;
+
+/// Path of the replication endpoints, on every peer.
+pub const REPLICATION_PATH: &str = "/replication/nonces";
+
+/// Peers the nonces are replicated to, see the [module docs](self).
+#[derive(Clone)]
+pub struct ReplayReplication {
+    peers: Vec<Url>,
+    token: String,
+    http: reqwest::Client,
+}
+
+impl ReplayReplication {
+    /// Replication to `peers`, authenticated with `token`.
+    pub fn new(peers: Vec<Url>, token: String, http: reqwest::Client) -> Self {
+        Self { peers, token, http }
+    }
+
+    /// Reads the peers from `REPLAY_REPLICATION_PEERS`; `None` if unset or empty.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let Ok(value) = std::env::var(from_env::ENV_REPLAY_REPLICATION_PEERS) else {
+            return Ok(None);
+        };
+        let outbound = OutboundPolicy::from_env()?;
+        let peers = value
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| {
+                let url = Url::parse(url)
+                    .and_then(|base| base.join(REPLICATION_PATH))
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_REPLAY_REPLICATION_PEERS))?;
+                outbound
+                    .validate_url(&url)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_REPLAY_REPLICATION_PEERS))?;
+                Ok(url)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if peers.is_empty() {
+            return Ok(None);
+        }
+        let token = std::env::var(from_env::ENV_REPLAY_REPLICATION_TOKEN)
+            .ok()
+            .filter(|token| !token.is_empty())
+            .ok_or_else(|| {
+                format!(
+                    "env {} not set, required with {}",
+                    from_env::ENV_REPLAY_REPLICATION_TOKEN,
+                    from_env::ENV_REPLAY_REPLICATION_PEERS
+                )
+            })?;
+        Ok(Some(Self::new(peers, token, outbound.http_client())))
+    }
+
+    /// State of [`routes`], accepting the changes of the peers into `nonces`.
+    pub fn endpoint(&self, nonces: SeenNonces) -> ReplicationEndpoint {
+        ReplicationEndpoint {
+            nonces,
+            token: self.token.clone(),
+        }
+    }
+
+    /// Fetches the nonces of the peers, then posts every change of `nonces` to them until cancelled.
+    pub fn spawn(
+        self,
+        nonces: SeenNonces,
+        cancellation_token: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        let mut changes = nonces.subscribe();
+        tokio::spawn(async move {
+            self.catch_up(&nonces).await;
+            loop {
+                let change = tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    change = changes.recv() => change,
+                };
+                match change {
+                    Ok(change) => self.replicate(&change),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!(missed, "Missed nonce changes, not replicated");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Applies the nonces of every peer reachable, e.g. of the active node to a standby node starting up.
+    async fn catch_up(&self, nonces: &SeenNonces) {
+        for peer in &self.peers {
+            let records = self
+                .http
+                .get(peer.clone())
+                .bearer_auth(&self.token)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            let records = match records {
+                Ok(response) => response.json::<Vec<NonceRecord>>().await,
+                Err(e) => Err(e),
+            };
+            match records {
+                Ok(records) => {
+                    tracing::info!(peer = %peer, nonces = records.len(), "Caught up with the nonces of a peer");
+                    for record in records {
+                        nonces.apply(NonceChange::Upsert(record));
+                    }
+                }
+                Err(e) => {
+                    tracing::warn!(peer = %peer, error = %e, "Failed to fetch the nonces of a peer");
+                }
+            }
+        }
+    }
+
+    /// Spawns the delivery of `change` to every peer.
+    fn replicate(&self, change: &NonceChange) {
+        for peer in &self.peers {
+            let request = self
+                .http
+                .post(peer.clone())
+                .bearer_auth(&self.token)
+                .json(change);
+            let peer = peer.clone();
+            tokio::spawn(async move {
+                let outcome = match webhooks::deliver(request).await {
+                    Ok(()) => "delivered",
+                    Err(e) => {
+                        tracing::warn!(peer = %peer, error = %e, "Failed to replicate nonce change");
+                        "failed"
+                    }
+                };
+                tracing::info!(monotonic_counter.x402.replication.deliveries = 1, outcome);
+            });
+        }
+    }
+}
+
+/// State of [`routes`]: the nonces changed by the peers, and the token they present.
+#[derive(Clone)]
+pub struct ReplicationEndpoint {
+    nonces: SeenNonces,
+    token: String,
+}
+
+impl ReplicationEndpoint {
+    /// Whether `headers` carry the replication token, compared in constant time.
+    fn authorizes(&self, headers: &HeaderMap) -> bool {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        constant_time_eq(keccak256(presented), keccak256(&self.token))
+    }
+}
+
+/// Routes receiving the nonce changes of the peers.
+pub fn routes() -> Router<ReplicationEndpoint> {
+    Router::new().route(REPLICATION_PATH, get(get_nonces).post(post_nonce_change))
+}
+
+/// `GET /replication/nonces`: All nonces in flight or settled, for a peer starting up.
+#[instrument(skip_all)]
+pub async fn get_nonces(
+    State(endpoint): State<ReplicationEndpoint>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if !endpoint.authorizes(&headers) {
+        return unauthorized();
+    }
+    (StatusCode::OK, Json(endpoint.nonces.records())).into_response()
+}
+
+/// `POST /replication/nonces`: Applies a nonce change of a peer.
+#[instrument(skip_all)]
+pub async fn post_nonce_change(
+    State(endpoint): State<ReplicationEndpoint>,
+    headers: HeaderMap,
+    Json(change): Json<NonceChange>,
+) -> impl IntoResponse {
+    if !endpoint.authorizes(&headers) {
+        return unauthorized();
+    }
+    endpoint.nonces.apply(change);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+/// Equality of two hashes, in a time independent of where they differ.
+fn constant_time_eq(a: B256, b: B256) -> bool {
+    a.iter()
+        .zip(b.iter())
+        .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+        == 0
+}
+
+fn unauthorized() -> axum::response::Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            error: "Unauthorized".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+    use crate::replay::{MemoryStore, NonceState};
+    use crate::timestamp::UnixTimestamp;
+    use alloy::primitives::Address;
+
+    fn record(state: NonceState) -> NonceRecord {
+        NonceRecord {
+            key: B256::repeat_byte(1),
+            network: Network::BaseSepolia,
+            payer: Address::repeat_byte(1).into(),
+            state,
+            expires_at: UnixTimestamp(4_000_000_000),
+        }
+    }
+
+    #[test]
+    fn replicated_changes_never_forget_settled_nonces() {
+        let nonces = SeenNonces::new(Box::new(MemoryStore)).unwrap();
+        let mut changes = nonces.subscribe();
+        nonces.apply(NonceChange::Upsert(record(NonceState::InFlight)));
+        nonces.apply(NonceChange::Remove(B256::repeat_byte(1)));
+        assert!(nonces.records().is_empty());
+        nonces.apply(NonceChange::Upsert(record(NonceState::Settled)));
+        nonces.apply(NonceChange::Upsert(record(NonceState::InFlight)));
+        nonces.apply(NonceChange::Remove(B256::repeat_byte(1)));
+        assert_eq!(nonces.records()[0].state, NonceState::Settled);
+        // Changes of peers are not sent back to them.
+        assert!(changes.try_recv().is_err());
+    }
+}
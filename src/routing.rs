@@ -0,0 +1,362 @@
+//! Cross-network settlement through a bridge or swap adapter.
+//!
+//! A payment is settled on the network of its requirements, and a payload signed for another network is refused
+//! with `invalid_network`. With routes configured, a payer holding funds on a _source_ network can pay requirements
+//! of a _destination_ network instead:
+//! 1. the client asks `POST /routes/quote` for the requirements to sign on the source network. The adapter answers
+//!    with its deposit address as `payTo`, the source asset and the amount due, fees included;
+//! 2. the client signs a payload for those requirements and sends it to `/verify` and `/settle` along with the
+//!    original, destination requirements;
+//! 3. the facilitator quotes again, verifies and settles the payload on the source network, then hands the source
+//!    transaction to the adapter, which delivers the destination amount to the original `payTo`.
+//!
+//! The settlement receipt is the one of the source network. Configured routes are listed under `routes` in
+//! `GET /supported`.
+//!
+//! The adapter is any HTTP service answering:
+//! - `POST {BRIDGE_ADAPTER_URL}/quote` with a [`RouteQuoteRequest`], returning a [`RouteQuote`],
+//! - `POST {BRIDGE_ADAPTER_URL}/forward` with a [`RouteForward`] once the source payment is settled.
+//!
+//! Environment variables used:
+//! - `BRIDGE_ADAPTER_URL` — base URL of the adapter,
+//! - `BRIDGE_ROUTES` — comma-separated `source>destination` network pairs, e.g. `base>solana,polygon>base`.
+//!
+//! Routing is disabled unless both are set.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::post;
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::instrument;
+use url::Url;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::outbound::OutboundPolicy;
+use crate::types::{
+    MixedAddress, NetworkRoute, PaymentRequirements, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, TokenAmount, TransactionHash, VerifyRequest, VerifyResponse,
+};
+
+/// Body of `POST /routes/quote`, also sent to the adapter's `/quote`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteQuoteRequest {
+    /// Network the payer signs on.
+    pub source: Network,
+    /// Requirements of the resource, on the destination network.
+    pub payment_requirements: PaymentRequirements,
+}
+
+/// What the payer pays on the source network for a [`RouteQuoteRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteQuote {
+    /// Deposit address of the adapter.
+    pub pay_to: MixedAddress,
+    pub asset: MixedAddress,
+    /// Amount due on the source network, including bridge and swap fees.
+    pub max_amount_required: TokenAmount,
+    /// Scheme-specific details of the source asset, e.g. its EIP-712 `name` and `version`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<serde_json::Value>,
+}
+
+/// Notice sent to the adapter's `/forward` once the source payment is settled.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RouteForward {
+    pub source: Network,
+    pub transaction: Option<TransactionHash>,
+    pub payer: Option<MixedAddress>,
+    /// Requirements of the resource, on the destination network.
+    pub payment_requirements: PaymentRequirements,
+}
+
+/// Adapter and the routes it serves.
+#[derive(Debug, Clone)]
+pub struct BridgeAdapter {
+    url: Url,
+    routes: Arc<Vec<NetworkRoute>>,
+    http: reqwest::Client,
+}
+
+impl BridgeAdapter {
+    pub fn new(url: Url, routes: Vec<NetworkRoute>, outbound: &OutboundPolicy) -> Self {
+        Self {
+            url,
+            routes: Arc::new(routes),
+            http: outbound.http_client(),
+        }
+    }
+
+    /// Reads `BRIDGE_ADAPTER_URL` and `BRIDGE_ROUTES`; `Ok(None)` if routing is not configured.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let url = std::env::var(from_env::ENV_BRIDGE_ADAPTER_URL).ok();
+        let routes = std::env::var(from_env::ENV_BRIDGE_ROUTES).ok();
+        let (Some(url), Some(routes)) = (url, routes) else {
+            return Ok(None);
+        };
+        let url =
+            Url::parse(&url).map_err(|e| format!("{}: {e}", from_env::ENV_BRIDGE_ADAPTER_URL))?;
+        let outbound = OutboundPolicy::from_env()?;
+        outbound
+            .validate_url(&url)
+            .map_err(|e| format!("{}: {e}", from_env::ENV_BRIDGE_ADAPTER_URL))?;
+        let routes =
+            parse_routes(&routes).map_err(|e| format!("{}: {e}", from_env::ENV_BRIDGE_ROUTES))?;
+        Ok(Some(Self::new(url, routes, &outbound)))
+    }
+
+    /// Whether payments signed on `source` may pay requirements on `destination`.
+    pub fn routes(&self, source: Network, destination: Network) -> bool {
+        self.routes
+            .iter()
+            .any(|route| route.source == source && route.destination == destination)
+    }
+
+    async fn post<B: Serialize, R: for<'de> Deserialize<'de>>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> Result<R, FacilitatorLocalError> {
+        let url = self
+            .url
+            .join(path)
+            .map_err(|e| FacilitatorLocalError::BridgeAdapter(e.to_string()))?;
+        self.http
+            .post(url)
+            .json(body)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| FacilitatorLocalError::BridgeAdapter(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| FacilitatorLocalError::BridgeAdapter(e.to_string()))
+    }
+
+    /// Quotes the source-network requirements of `request`, which must follow a configured route.
+    pub async fn quote(
+        &self,
+        request: &RouteQuoteRequest,
+    ) -> Result<PaymentRequirements, FacilitatorLocalError> {
+        let destination = request.payment_requirements.network;
+        if !self.routes(request.source, destination) {
+            return Err(FacilitatorLocalError::NetworkMismatch(
+                None,
+                destination,
+                request.source,
+            ));
+        }
+        let quote: RouteQuote = self.post("quote", request).await?;
+        Ok(source_requirements(
+            request.source,
+            &request.payment_requirements,
+            quote,
+        ))
+    }
+}
+
+/// Destination requirements rewritten to be paid on `source` as quoted.
+fn source_requirements(
+    source: Network,
+    requirements: &PaymentRequirements,
+    quote: RouteQuote,
+) -> PaymentRequirements {
+    PaymentRequirements {
+        network: source,
+        pay_to: quote.pay_to,
+        asset: quote.asset,
+        max_amount_required: quote.max_amount_required,
+        extra: quote.extra,
+        // Quoted in source tokens already.
+        price_usd: None,
+        ..requirements.clone()
+    }
+}
+
+fn parse_routes(routes: &str) -> Result<Vec<NetworkRoute>, String> {
+    routes
+        .split(',')
+        .map(str::trim)
+        .filter(|route| !route.is_empty())
+        .map(|route| {
+            let (source, destination) = route
+                .split_once('>')
+                .ok_or_else(|| format!("expected source>destination, got {route}"))?;
+            let network = |name: &str| {
+                Network::from_str(name.trim()).map_err(|_| format!("unknown network {name}"))
+            };
+            Ok(NetworkRoute {
+                source: network(source)?,
+                destination: network(destination)?,
+            })
+        })
+        .collect()
+}
+
+/// [`Facilitator`] decorator settling payloads signed on another network than their requirements through a
+/// [`BridgeAdapter`].
+///
+/// Passes every call through to the inner facilitator when no adapter is configured.
+pub struct RouteGate<F> {
+    facilitator: F,
+    adapter: Option<BridgeAdapter>,
+}
+
+impl<F> RouteGate<F> {
+    pub fn new(facilitator: F, adapter: Option<BridgeAdapter>) -> Self {
+        Self {
+            facilitator,
+            adapter,
+        }
+    }
+
+    /// The adapter and the request rewritten for the source network, if `request` is to be routed.
+    async fn routed(
+        &self,
+        request: &VerifyRequest,
+    ) -> Result<Option<(&BridgeAdapter, VerifyRequest)>, FacilitatorLocalError> {
+        let source = request.payment_payload.network;
+        let destination = request.payment_requirements.network;
+        let Some(adapter) = self
+            .adapter
+            .as_ref()
+            .filter(|adapter| source != destination && adapter.routes(source, destination))
+        else {
+            return Ok(None);
+        };
+        let quote_request = RouteQuoteRequest {
+            source,
+            payment_requirements: request.payment_requirements.clone(),
+        };
+        let requirements = adapter.quote(&quote_request).await?;
+        let routed = VerifyRequest {
+            payment_requirements: requirements,
+            ..request.clone()
+        };
+        Ok(Some((adapter, routed)))
+    }
+}
+
+impl<F> Facilitator for RouteGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        match self.routed(request).await? {
+            Some((_, routed)) => self.facilitator.verify(&routed).await,
+            None => self.facilitator.verify(request).await,
+        }
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let Some((adapter, routed)) = self.routed(request).await? else {
+            return self.facilitator.settle(request).await;
+        };
+        let response = self.facilitator.settle(&routed).await?;
+        if response.success {
+            let forward = RouteForward {
+                source: routed.network(),
+                transaction: response.transaction.clone(),
+                payer: response.payer.clone(),
+                payment_requirements: request.payment_requirements.clone(),
+            };
+            // The source payment went through; the operator reconciles it with the adapter if forwarding fails.
+            adapter
+                .post::<_, serde_json::Value>("forward", &forward)
+                .await
+                .inspect_err(|e| {
+                    tracing::error!(source = %forward.source, destination = %request.payment_requirements.network, transaction = ?forward.transaction, error = %e, "Failed to forward routed payment");
+                })?;
+            tracing::info!(source = %forward.source, destination = %request.payment_requirements.network, "Routed payment forwarded");
+        }
+        Ok(response)
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        let mut supported = self.facilitator.supported().await?;
+        if let Some(adapter) = &self.adapter {
+            supported.routes = adapter
+                .routes
+                .iter()
+                .filter(|route| {
+                    let source = route.source.to_string();
+                    supported.kinds.iter().any(|kind| kind.network == source)
+                })
+                .cloned()
+                .collect();
+        }
+        Ok(supported)
+    }
+}
+
+/// Routes serving quotes of routed payments.
+pub fn routes() -> Router<BridgeAdapter> {
+    Router::new().route("/routes/quote", post(post_quote))
+}
+
+/// `POST /routes/quote`: Requirements to sign on the source network to pay destination requirements.
+#[instrument(skip_all, fields(source = %request.source, destination = %request.payment_requirements.network))]
+pub async fn post_quote(
+    State(adapter): State<BridgeAdapter>,
+    Json(request): Json<RouteQuoteRequest>,
+) -> Response {
+    match adapter.quote(&request).await {
+        Ok(requirements) => (StatusCode::OK, Json(requirements)).into_response(),
+        Err(error) => error.into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_routes_and_rewrites_requirements() {
+        let routes = parse_routes("base>solana, polygon>base").unwrap();
+        assert_eq!(
+            routes[0],
+            NetworkRoute {
+                source: Network::Base,
+                destination: Network::Solana,
+            }
+        );
+        assert!(parse_routes("base").is_err());
+        assert!(parse_routes("base>mars").is_err());
+
+        let requirements: PaymentRequirements = serde_json::from_value(serde_json::json!({
+            "scheme": "exact",
+            "network": "solana",
+            "maxAmountRequired": "1000000",
+            "resource": "https://example.com/paid",
+            "description": "",
+            "mimeType": "application/json",
+            "payTo": "EGBQqKn968sVv5cQh5Cr72pSTHfxsuzq7o7asqYB5uEV",
+            "maxTimeoutSeconds": 60,
+            "asset": "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+            "extra": null,
+        }))
+        .unwrap();
+        let quote: RouteQuote = serde_json::from_value(serde_json::json!({
+            "payTo": "0x1111111111111111111111111111111111111111",
+            "asset": "0x833589fCD6eDb6E08f4c7C32D4f71b54bdA02913",
+            "maxAmountRequired": "1003000",
+            "extra": {"name": "USD Coin", "version": "2"},
+        }))
+        .unwrap();
+        let source = source_requirements(Network::Base, &requirements, quote);
+        assert_eq!(source.network, Network::Base);
+        assert_eq!(source.max_amount_required, TokenAmount::from(1_003_000u64));
+        assert_eq!(source.resource, requirements.resource);
+    }
+}
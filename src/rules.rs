@@ -0,0 +1,480 @@
+//! Operator-defined verification rules, written in a small expression language.
+//!
+//! Simple policies (payment caps, allowed networks or recipients) do not need a Rust hook: each rule of the
+//! configuration file is a boolean expression over the payment requirements, and a request is only verified
+//! or settled if every rule holds.
+//!
+//! ```toml
+//! [[rules]]
+//! name = "small-avalanche-payments"
+//! expr = 'amount <= 10_000000 && network in ["avalanche", "avalanche-fuji"]'
+//! ```
+//!
+//! Expressions are sandboxed: they can only read the variables below, and have no loops, calls or side effects.
+//! - `amount` — `maxAmountRequired`, in the token's smallest unit.
+//! - `settle_amount` — `settleAmount` of an `upto` settlement, `amount` otherwise.
+//! - `network`, `scheme` — as in the payment requirements, e.g. `"base"`, `"exact"`.
+//! - `asset`, `pay_to` — token and recipient addresses, lowercase.
+//! - `resource` — URL of the paid resource.
+//!
+//! Literals are integers (`_` separators allowed), double-quoted strings, `true`, `false` and lists `[…]`.
+//! Operators, loosest first: `||`, `&&`, `!`, then `==`, `!=`, `<`, `<=`, `>`, `>=`, `in`; parentheses group.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::types::{
+    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// A rule of the configuration file.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RuleConfig {
+    /// Name reported when the rule rejects a request.
+    pub name: String,
+    /// Boolean expression a request must satisfy.
+    pub expr: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid rule {name}: {reason}")]
+pub struct RuleError {
+    name: String,
+    reason: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Int(u128),
+    Str(String),
+    Bool(bool),
+    List(Vec<Value>),
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Int(n) => write!(f, "{n}"),
+            Value::Str(s) => write!(f, "{s:?}"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::List(items) => write!(f, "list of {}", items.len()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Var {
+    Amount,
+    SettleAmount,
+    Network,
+    Scheme,
+    Asset,
+    PayTo,
+    Resource,
+}
+
+impl Var {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "amount" => Some(Var::Amount),
+            "settle_amount" => Some(Var::SettleAmount),
+            "network" => Some(Var::Network),
+            "scheme" => Some(Var::Scheme),
+            "asset" => Some(Var::Asset),
+            "pay_to" => Some(Var::PayTo),
+            "resource" => Some(Var::Resource),
+            _ => None,
+        }
+    }
+
+    fn read(&self, request: &VerifyRequest) -> Result<Value, String> {
+        let requirements = &request.payment_requirements;
+        let int = |amount: alloy::primitives::U256| {
+            u128::try_from(amount)
+                .map(Value::Int)
+                .map_err(|_| format!("amount {amount} is out of range"))
+        };
+        Ok(match self {
+            Var::Amount => int(requirements.max_amount_required.0)?,
+            Var::SettleAmount => int(request
+                .settle_amount
+                .unwrap_or(requirements.max_amount_required)
+                .0)?,
+            Var::Network => Value::Str(requirements.network.to_string()),
+            Var::Scheme => Value::Str(requirements.scheme.to_string()),
+            Var::Asset => Value::Str(requirements.asset.to_string().to_lowercase()),
+            Var::PayTo => Value::Str(requirements.pay_to.to_string().to_lowercase()),
+            Var::Resource => Value::Str(requirements.resource.to_string()),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    In,
+}
+
+#[derive(Debug, Clone)]
+enum Expr {
+    Literal(Value),
+    Var(Var),
+    List(Vec<Expr>),
+    Not(Box<Expr>),
+    And(Box<Expr>, Box<Expr>),
+    Or(Box<Expr>, Box<Expr>),
+    Cmp(CmpOp, Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    fn eval(&self, request: &VerifyRequest) -> Result<Value, String> {
+        match self {
+            Expr::Literal(value) => Ok(value.clone()),
+            Expr::Var(var) => var.read(request),
+            Expr::List(items) => items
+                .iter()
+                .map(|item| item.eval(request))
+                .collect::<Result<_, _>>()
+                .map(Value::List),
+            Expr::Not(inner) => Ok(Value::Bool(!inner.eval(request)?.as_bool()?)),
+            Expr::And(left, right) => Ok(Value::Bool(
+                left.eval(request)?.as_bool()? && right.eval(request)?.as_bool()?,
+            )),
+            Expr::Or(left, right) => Ok(Value::Bool(
+                left.eval(request)?.as_bool()? || right.eval(request)?.as_bool()?,
+            )),
+            Expr::Cmp(op, left, right) => {
+                let (left, right) = (left.eval(request)?, right.eval(request)?);
+                let result = match (op, &left, &right) {
+                    (CmpOp::Eq, _, _) => left == right,
+                    (CmpOp::Ne, _, _) => left != right,
+                    (CmpOp::In, _, Value::List(items)) => items.contains(&left),
+                    (CmpOp::Lt, Value::Int(a), Value::Int(b)) => a < b,
+                    (CmpOp::Le, Value::Int(a), Value::Int(b)) => a <= b,
+                    (CmpOp::Gt, Value::Int(a), Value::Int(b)) => a > b,
+                    (CmpOp::Ge, Value::Int(a), Value::Int(b)) => a >= b,
+                    _ => return Err(format!("can not compare {left} with {right}")),
+                };
+                Ok(Value::Bool(result))
+            }
+        }
+    }
+}
+
+impl Value {
+    fn as_bool(&self) -> Result<bool, String> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            other => Err(format!("{other} is not a boolean")),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Int(u128),
+    Str(String),
+    Ident(String),
+    Op(&'static str),
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    const OPS: [&str; 14] = [
+        "||", "&&", "==", "!=", "<=", ">=", "<", ">", "!", "(", ")", "[", "]", ",",
+    ];
+    let mut tokens = Vec::new();
+    let mut rest = source.trim_start();
+    while !rest.is_empty() {
+        if let Some(op) = OPS.iter().find(|op| rest.starts_with(*op)) {
+            tokens.push(Token::Op(op));
+            rest = &rest[op.len()..];
+        } else if let Some(string) = rest.strip_prefix('"') {
+            let end = string.find('"').ok_or("unterminated string")?;
+            tokens.push(Token::Str(string[..end].to_string()));
+            rest = &string[end + 1..];
+        } else if rest.starts_with(|c: char| c.is_ascii_digit()) {
+            let end = rest
+                .find(|c: char| !c.is_ascii_digit() && c != '_')
+                .unwrap_or(rest.len());
+            let digits = rest[..end].replace('_', "");
+            let n = digits
+                .parse()
+                .map_err(|_| format!("invalid number {}", &rest[..end]))?;
+            tokens.push(Token::Int(n));
+            rest = &rest[end..];
+        } else if rest.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+            let end = rest
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            tokens.push(Token::Ident(rest[..end].to_string()));
+            rest = &rest[end..];
+        } else {
+            return Err(format!("unexpected character {:?}", rest.chars().next()));
+        }
+        rest = rest.trim_start();
+    }
+    Ok(tokens)
+}
+
+/// Recursive descent parser over the tokens of an expression.
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn eat(&mut self, op: &str) -> bool {
+        let matches = match self.peek() {
+            Some(Token::Op(o)) => *o == op,
+            Some(Token::Ident(ident)) => ident == op,
+            _ => false,
+        };
+        if matches {
+            self.position += 1;
+        }
+        matches
+    }
+
+    fn expect(&mut self, op: &str) -> Result<(), String> {
+        if self.eat(op) {
+            Ok(())
+        } else {
+            Err(format!("expected `{op}`"))
+        }
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut expr = self.and()?;
+        while self.eat("||") {
+            expr = Expr::Or(Box::new(expr), Box::new(self.and()?));
+        }
+        Ok(expr)
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut expr = self.not()?;
+        while self.eat("&&") {
+            expr = Expr::And(Box::new(expr), Box::new(self.not()?));
+        }
+        Ok(expr)
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expr, String> {
+        let left = self.primary()?;
+        let ops = [
+            ("==", CmpOp::Eq),
+            ("!=", CmpOp::Ne),
+            ("<=", CmpOp::Le),
+            (">=", CmpOp::Ge),
+            ("<", CmpOp::Lt),
+            (">", CmpOp::Gt),
+            ("in", CmpOp::In),
+        ];
+        for (token, op) in ops {
+            if self.eat(token) {
+                return Ok(Expr::Cmp(op, Box::new(left), Box::new(self.primary()?)));
+            }
+        }
+        Ok(left)
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+        let token = self.peek().cloned().ok_or("unexpected end of expression")?;
+        self.position += 1;
+        match token {
+            Token::Int(n) => Ok(Expr::Literal(Value::Int(n))),
+            Token::Str(s) => Ok(Expr::Literal(Value::Str(s))),
+            Token::Ident(ident) => match ident.as_str() {
+                "true" => Ok(Expr::Literal(Value::Bool(true))),
+                "false" => Ok(Expr::Literal(Value::Bool(false))),
+                name => Var::from_name(name)
+                    .map(Expr::Var)
+                    .ok_or_else(|| format!("unknown variable {name}")),
+            },
+            Token::Op("(") => {
+                let expr = self.or()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            Token::Op("[") => {
+                let mut items = Vec::new();
+                while !self.eat("]") {
+                    if !items.is_empty() {
+                        self.expect(",")?;
+                    }
+                    items.push(self.primary()?);
+                }
+                Ok(Expr::List(items))
+            }
+            Token::Op(op) => Err(format!("unexpected `{op}`")),
+        }
+    }
+}
+
+fn parse(source: &str) -> Result<Expr, String> {
+    let mut parser = Parser {
+        tokens: tokenize(source)?,
+        position: 0,
+    };
+    let expr = parser.or()?;
+    match parser.peek() {
+        None => Ok(expr),
+        Some(token) => Err(format!("unexpected {token:?}")),
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rule {
+    name: String,
+    expr: Expr,
+}
+
+/// Compiled rules, all of which a request must satisfy.
+#[derive(Debug, Clone, Default)]
+pub struct Rules {
+    rules: Vec<Rule>,
+}
+
+impl Rules {
+    /// Parses the configured rules; fails on the first invalid one.
+    pub fn compile(configs: &[RuleConfig]) -> Result<Self, RuleError> {
+        let rules = configs
+            .iter()
+            .map(|config| {
+                parse(&config.expr)
+                    .map(|expr| Rule {
+                        name: config.name.clone(),
+                        expr,
+                    })
+                    .map_err(|reason| RuleError {
+                        name: config.name.clone(),
+                        reason,
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(Self { rules })
+    }
+
+    /// Rejects `request` unless every rule holds; a rule failing to evaluate rejects it too.
+    pub fn check(&self, request: &VerifyRequest) -> Result<(), FacilitatorLocalError> {
+        for rule in &self.rules {
+            let holds = rule.expr.eval(request).and_then(|value| value.as_bool());
+            match holds {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::info!(
+                        monotonic_counter.x402.rules.rejections = 1,
+                        rule = %rule.name,
+                    );
+                    return Err(FacilitatorLocalError::RuleViolation(rule.name.clone()));
+                }
+                Err(e) => {
+                    tracing::warn!(rule = %rule.name, error = %e, "Rule evaluation failed");
+                    return Err(FacilitatorLocalError::RuleViolation(rule.name.clone()));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// A [`Facilitator`] that only verifies and settles requests satisfying the operator's [`Rules`].
+pub struct RuleGate<F> {
+    facilitator: F,
+    rules: Rules,
+}
+
+impl<F> RuleGate<F> {
+    pub fn new(facilitator: F, rules: Rules) -> Self {
+        Self { facilitator, rules }
+    }
+}
+
+impl<F> Facilitator for RuleGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.rules.check(request)?;
+        self.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        self.rules.check(request)?;
+        self.facilitator.settle(request).await
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(amount: u64, network: &str) -> VerifyRequest {
+        serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": network,
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": network,
+                "maxAmountRequired": amount.to_string(),
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x0000000000000000000000000000000000000002"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn evaluates_rules() {
+        let rules = Rules::compile(&[RuleConfig {
+            name: "small-avalanche".to_string(),
+            expr: r#"amount <= 10_000000 && network in ["avalanche"] && !(scheme == "upto")"#
+                .to_string(),
+        }])
+        .unwrap();
+        assert!(rules.check(&request(5_000000, "avalanche")).is_ok());
+        assert!(rules.check(&request(50_000000, "avalanche")).is_err());
+        assert!(rules.check(&request(5_000000, "base")).is_err());
+        for invalid in ["amount <=", "payer == \"x\"", "(amount > 1", "amount > 1 1"] {
+            let config = RuleConfig {
+                name: "invalid".to_string(),
+                expr: invalid.to_string(),
+            };
+            assert!(Rules::compile(&[config]).is_err(), "{invalid}");
+        }
+    }
+}
@@ -0,0 +1,1339 @@
+//! Durable queue of settlements.
+//!
+//! Without a queue, `/settle` signs and broadcasts inline: a crash between accepting a settlement and seeing it
+//! through loses it. [`SettlementQueue`] enqueues each settlement in a [`QueueStore`] instead, and a worker task
+//! settles it, retrying transient failures (RPC errors, unreachable outside services) with exponential backoff.
+//! `/settle` still waits for the outcome and answers it as before. Should the request time out or the client go
+//! away, the settlement carries on, and its state can be polled at `GET /queue/{id}`, where `id` is the queue ID
+//! logged on enqueue, or the `paymentId` of the request.
+//!
+//! Settling the same request again while it is queued waits for the queued settlement; once it succeeded, its
+//! outcome is answered without settling again.
+//!
+//! [`Facilitator::submit`] does not wait: it answers as soon as the settlement is queued, with the `settlement_pending`
+//! reason and the `settlementId` to follow it by. This is how `/settle` answers `202 Accepted` in async mode, see
+//! [`crate::handlers::SettleMode`].
+//!
+//! `GET /settlements/{id}` serves the fate of a settlement by the `settlementId` of its response, its queue ID, or
+//! its `paymentId`: `pending` until its transaction is mined, then `confirmed` or `failed`, with the block number and
+//! gas used of EVM transactions, read from their receipt. Settlements are known for the retention of the queue.
+//!
+//! Settlements later affected by a chain reorganization are corrected from the [`ReorgEvent`]s of the networks, see
+//! [`crate::chain::reorg`]: they take the hash of the transaction that carries them in the end, and fail if it
+//! reverted or could not be broadcast again.
+//!
+//! Every settlement is published as a [`SettlementEvent`] to [`SettlementQueue::subscribe`]rs when it is queued
+//! (`settlement.submitted`), and when it is settled (`settlement.confirmed`) or fails (`settlement.failed`), also after
+//! a reorganization; [`crate::webhooks`] posts them to the operator's endpoints.
+//!
+//! A settlement with an `executeAfter` time in the future is scheduled: it is verified, queued, answered as pending
+//! with its `settlementId` right away, also in sync mode, and held until due before settling. `executeAfter` must be
+//! before its authorization expires; payloads without an expiry, e.g. Solana transactions, can not be scheduled.
+//! Scheduled settlements survive restarts with a persistent store only.
+//!
+//! Settlements whose last attempt fails with an error are kept as [`DeadLetters`] until an operator re-drives or
+//! discards them at `/admin/dead-letters`.
+//!
+//! Entries left queued or in progress by a restart are settled again on startup, with a persistent store only. A
+//! settlement whose transaction had already been broadcast then fails verification with `nonce_reused`; the
+//! transaction journal (`TX_JOURNAL_DIR`) is what follows such transactions until they are mined.
+//!
+//! Stores:
+//! - [`MemoryStore`] (default) — nothing survives a restart,
+//! - [`DirStore`] — one JSON file per entry, in `SETTLEMENT_QUEUE_DIR`.
+//!
+//! Environment variables used:
+//! - `SETTLEMENT_QUEUE_DIR` — directory of the persistent store; entries are kept in memory only if unset,
+//! - `SETTLEMENT_MAX_ATTEMPTS` — attempts of a settlement failing transiently (default: `3`),
+//! - `SETTLEMENT_QUEUE_RETENTION_SECS` — how long finished entries can be polled (default: `86400`).
+
+use alloy::primitives::{B256, keccak256};
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::routing::{delete, get, post};
+use axum::{Json, Router};
+use dashmap::DashMap;
+use dashmap::mapref::entry::Entry;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, broadcast, mpsc, oneshot};
+use tracing::instrument;
+
+use crate::chain::reorg::{ReorgEvent, ReorgOutcome};
+use crate::chain::{FacilitatorLocalError, NetworkProvider};
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::provider_cache::ProviderMap;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    ErrorResponse, FacilitatorErrorReason, MixedAddress, PaymentId, SettleRequest, SettleResponse,
+    SupportedPaymentKindsResponse, TransactionHash, VerifyRequest, VerifyResponse,
+};
+
+/// Events kept for slow subscribers.
+const EVENT_CAPACITY: usize = 256;
+
+/// Retry and retention settings of a [`SettlementQueue`].
+#[derive(Debug, Clone, Copy)]
+pub struct QueueSettings {
+    /// Attempts of a settlement failing transiently, the first included.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled on every further one.
+    pub retry_delay: Duration,
+    /// How long finished entries are kept.
+    pub retention_secs: u64,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            retry_delay: Duration::from_secs(2),
+            retention_secs: 86400,
+        }
+    }
+}
+
+/// Lifecycle of a queued settlement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStatus {
+    /// Waiting for the worker, initially or between attempts.
+    Queued,
+    Processing,
+    Settled,
+    Failed,
+}
+
+impl QueueStatus {
+    pub fn is_finished(self) -> bool {
+        matches!(self, QueueStatus::Settled | QueueStatus::Failed)
+    }
+}
+
+/// State of a queued settlement, as served by `GET /queue/{id}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueRecord {
+    pub id: String,
+    pub status: QueueStatus,
+    pub network: Network,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<PaymentId>,
+    /// Settlement attempts made so far.
+    pub attempts: u32,
+    pub enqueued_at: UnixTimestamp,
+    pub updated_at: UnixTimestamp,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<MixedAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionHash>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<FacilitatorErrorReason>,
+    /// Last error of the facilitator, if an attempt failed without a settlement response.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether the settlement failed with an error on its last attempt, and awaits being re-driven or discarded.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub dead_lettered: bool,
+    /// Time the settlement is held until, if scheduled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_after: Option<UnixTimestamp>,
+    /// Response of the facilitator to the last attempt, answered again to requests for the same payment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response: Option<SettleResponse>,
+}
+
+/// A queued settlement with its request, as kept in a [`QueueStore`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct QueueEntry {
+    pub record: QueueRecord,
+    pub request: SettleRequest,
+}
+
+/// Persistence of queue entries. Every change of an entry is saved before the worker acts on it.
+pub trait QueueStore: Send + Sync {
+    fn save(&self, entry: &QueueEntry) -> std::io::Result<()>;
+    fn remove(&self, id: &str) -> std::io::Result<()>;
+    /// Entries saved before a restart.
+    fn load(&self) -> std::io::Result<Vec<QueueEntry>>;
+}
+
+/// Store keeping nothing: entries live in the queue only.
+#[derive(Debug, Default)]
+pub struct MemoryStore;
+
+impl QueueStore for MemoryStore {
+    fn save(&self, _entry: &QueueEntry) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn remove(&self, _id: &str) -> std::io::Result<()> {
+        Ok(())
+    }
+
+    fn load(&self) -> std::io::Result<Vec<QueueEntry>> {
+        Ok(Vec::new())
+    }
+}
+
+/// Store writing one JSON file per entry into a directory.
+#[derive(Debug)]
+pub struct DirStore {
+    dir: PathBuf,
+}
+
+impl DirStore {
+    pub fn new(dir: impl Into<PathBuf>) -> std::io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{id}.json"))
+    }
+}
+
+impl QueueStore for DirStore {
+    fn save(&self, entry: &QueueEntry) -> std::io::Result<()> {
+        let path = self.path(&entry.record.id);
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(entry)?)?;
+        std::fs::rename(&tmp, &path)
+    }
+
+    fn remove(&self, id: &str) -> std::io::Result<()> {
+        match std::fs::remove_file(self.path(id)) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn load(&self) -> std::io::Result<Vec<QueueEntry>> {
+        let mut entries = Vec::new();
+        for file in std::fs::read_dir(&self.dir)? {
+            let path = file?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match serde_json::from_slice(&std::fs::read(&path)?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    tracing::warn!(path = %path.display(), error = %e, "Skipping unreadable queue entry")
+                }
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// Queued settlements, shared between the [`SettlementQueue`] and the [`routes`].
+///
+/// Cheap to clone: all clones share the same entries.
+#[derive(Clone)]
+pub struct QueueRecords {
+    entries: Arc<DashMap<String, QueueEntry>>,
+}
+
+impl QueueRecords {
+    /// Record of the entry with queue ID `id`, or else of the latest one for payment ID `id`.
+    pub fn get(&self, id: &str) -> Option<QueueRecord> {
+        if let Some(entry) = self.entries.get(id) {
+            return Some(entry.record.clone());
+        }
+        self.entries
+            .iter()
+            .filter(|entry| entry.record.payment_id.as_ref().is_some_and(|p| p.0 == id))
+            .map(|entry| entry.record.clone())
+            .max_by_key(|record| record.enqueued_at)
+    }
+}
+
+type SettleResult = Result<SettleResponse, FacilitatorLocalError>;
+
+/// Outcome of [`QueueInner::enqueue`].
+enum Enqueued {
+    /// Settled already by an earlier identical request.
+    Settled(Box<SettleResponse>),
+    /// Queued or being settled for an earlier identical request, with the ID of its entry.
+    InProgress(String),
+    /// Queued now, with the ID of the entry and the receiver of its outcome.
+    New(String, oneshot::Receiver<SettleResult>),
+}
+
+struct QueueInner<F> {
+    facilitator: F,
+    store: Box<dyn QueueStore>,
+    records: QueueRecords,
+    /// Requests waiting for the outcome of the settlement they enqueued.
+    waiters: DashMap<String, oneshot::Sender<SettleResult>>,
+    /// Woken whenever a settlement finishes, for requests waiting on one they did not enqueue.
+    finished: Notify,
+    jobs: mpsc::UnboundedSender<String>,
+    settings: QueueSettings,
+    events: broadcast::Sender<SettlementEvent>,
+}
+
+/// [`Facilitator`] wrapper settling through a durable queue, see the [module docs](self).
+pub struct SettlementQueue<F> {
+    inner: Arc<QueueInner<F>>,
+}
+
+impl<F> SettlementQueue<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    /// Creates the queue and spawns its worker, which first resumes the unfinished entries of `store`.
+    pub fn new(
+        facilitator: F,
+        store: Box<dyn QueueStore>,
+        settings: QueueSettings,
+    ) -> std::io::Result<Self> {
+        let (jobs, mut pending) = mpsc::unbounded_channel();
+        let inner = Arc::new(QueueInner {
+            facilitator,
+            store,
+            records: QueueRecords {
+                entries: Arc::new(DashMap::new()),
+            },
+            waiters: DashMap::new(),
+            finished: Notify::new(),
+            jobs,
+            settings,
+            events: broadcast::channel(EVENT_CAPACITY).0,
+        });
+        let mut resumed = 0;
+        for mut entry in inner.store.load()? {
+            let id = entry.record.id.clone();
+            if !entry.record.status.is_finished() {
+                entry.record.status = QueueStatus::Queued;
+                let _ = inner.jobs.send(id.clone());
+                resumed += 1;
+            }
+            inner.records.entries.insert(id, entry);
+        }
+        if resumed > 0 {
+            tracing::info!(resumed, "Resuming queued settlements");
+        }
+        let worker = inner.clone();
+        tokio::spawn(async move {
+            while let Some(id) = pending.recv().await {
+                tokio::spawn(worker.clone().process(id));
+            }
+        });
+        Ok(Self { inner })
+    }
+
+    /// Reads the store and retry settings from the environment.
+    pub fn from_env(facilitator: F) -> Result<Self, Box<dyn std::error::Error>> {
+        let store: Box<dyn QueueStore> = match std::env::var(from_env::ENV_SETTLEMENT_QUEUE_DIR) {
+            Ok(dir) => Box::new(
+                DirStore::new(dir)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_SETTLEMENT_QUEUE_DIR))?,
+            ),
+            Err(_) => Box::new(MemoryStore),
+        };
+        let mut settings = QueueSettings::default();
+        if let Ok(value) = std::env::var(from_env::ENV_SETTLEMENT_MAX_ATTEMPTS) {
+            settings.max_attempts = value
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_SETTLEMENT_MAX_ATTEMPTS))?;
+        }
+        if let Ok(value) = std::env::var(from_env::ENV_SETTLEMENT_QUEUE_RETENTION_SECS) {
+            settings.retention_secs = value
+                .parse()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_SETTLEMENT_QUEUE_RETENTION_SECS))?;
+        }
+        Ok(Self::new(facilitator, store, settings)?)
+    }
+
+    /// Corrects the entries of settlements affected by reorganizations, as published by `events`.
+    pub fn follow_reorgs(&self, mut events: broadcast::Receiver<ReorgEvent>) {
+        let inner = self.inner.clone();
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => inner.apply_reorg(&event),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!(missed, "Missed reorganization events");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => return,
+                }
+            }
+        });
+    }
+}
+
+impl<F> SettlementQueue<F> {
+    /// Handle to the queue entries, for serving [`routes`].
+    pub fn records(&self) -> QueueRecords {
+        self.inner.records.clone()
+    }
+
+    /// Lifecycle events of the settlements from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<SettlementEvent> {
+        self.inner.events.subscribe()
+    }
+
+    /// Handle subscribing to the lifecycle events of the settlements, for serving [`crate::settlement_stream`].
+    pub fn events(&self) -> SettlementEvents {
+        SettlementEvents {
+            sender: self.inner.events.clone(),
+        }
+    }
+
+    /// Handle to the dead-lettered entries, for serving [`dead_letter_routes`].
+    pub fn dead_letters(&self) -> DeadLetters<F> {
+        DeadLetters {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Settlements that failed for good, kept until an operator re-drives or discards them.
+///
+/// An entry is dead-lettered when its last attempt failed with an error rather than a settlement response: a
+/// transient failure outlasting `SETTLEMENT_MAX_ATTEMPTS`, or a failure not worth retrying. Rejected payments,
+/// answered with a reason, are not. Dead-lettered entries outlive the queue retention, and keep their request.
+pub struct DeadLetters<F> {
+    inner: Arc<QueueInner<F>>,
+}
+
+impl<F> Clone for DeadLetters<F> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<F> DeadLetters<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    /// Dead-lettered entries, the latest failure first.
+    pub fn list(&self) -> Vec<QueueEntry> {
+        let mut entries: Vec<QueueEntry> = self
+            .inner
+            .records
+            .entries
+            .iter()
+            .filter(|entry| entry.record.dead_lettered)
+            .map(|entry| entry.clone())
+            .collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.record.updated_at));
+        entries
+    }
+
+    /// Queues the dead-lettered entry `id` again, with a fresh count of attempts; `None` if there is none.
+    pub fn redrive(&self, id: &str) -> Option<QueueRecord> {
+        let inner = &self.inner;
+        let mut entry = inner
+            .records
+            .entries
+            .get_mut(id)
+            .filter(|entry| entry.record.dead_lettered)?;
+        entry.record.status = QueueStatus::Queued;
+        entry.record.attempts = 0;
+        entry.record.error = None;
+        entry.record.dead_lettered = false;
+        if let Ok(now) = UnixTimestamp::try_now() {
+            entry.record.updated_at = now;
+        }
+        inner.save(&entry);
+        let record = entry.record.clone();
+        drop(entry);
+        tracing::info!(id, "Dead-lettered settlement re-driven");
+        inner.publish(SettlementEventKind::Submitted, record.clone());
+        if inner.jobs.send(id.to_string()).is_err() {
+            tracing::warn!(
+                id,
+                "Settlement queue stopped, re-driven settlement left queued"
+            );
+        }
+        Some(record)
+    }
+
+    /// Drops the dead-lettered entry `id`; `None` if there is none.
+    pub fn discard(&self, id: &str) -> Option<QueueEntry> {
+        let (_, entry) = self
+            .inner
+            .records
+            .entries
+            .remove_if(id, |_, entry| entry.record.dead_lettered)?;
+        if let Err(e) = self.inner.store.remove(id) {
+            tracing::warn!(id, error = %e, "Failed to remove dead-lettered settlement");
+        }
+        tracing::info!(id, "Dead-lettered settlement discarded");
+        Some(entry)
+    }
+}
+
+impl<F> QueueInner<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    fn save(&self, entry: &QueueEntry) {
+        if let Err(e) = self.store.save(entry) {
+            tracing::warn!(id = entry.record.id, error = %e, "Failed to save queued settlement");
+        }
+    }
+
+    /// Publishes the lifecycle event `kind` of the settlement in `record`.
+    fn publish(&self, kind: SettlementEventKind, record: QueueRecord) {
+        let Ok(at) = UnixTimestamp::try_now() else {
+            return;
+        };
+        // No subscriber is fine: nobody asked for events.
+        let _ = self.events.send(SettlementEvent {
+            kind,
+            at,
+            settlement: record.into(),
+        });
+    }
+
+    /// Applies `update` to the entry `id` and saves it; the request, or `None` if the entry is gone.
+    fn update(&self, id: &str, update: impl FnOnce(&mut QueueRecord)) -> Option<SettleRequest> {
+        let mut entry = self.records.entries.get_mut(id)?;
+        update(&mut entry.record);
+        if let Ok(now) = UnixTimestamp::try_now() {
+            entry.record.updated_at = now;
+        }
+        self.save(&entry);
+        Some(entry.request.clone())
+    }
+
+    /// Settles the entry `id`, retrying transient failures.
+    async fn process(self: Arc<Self>, id: String) {
+        let execute_after = self
+            .records
+            .entries
+            .get(&id)
+            .and_then(|entry| entry.record.execute_after);
+        if let Some(execute_after) = execute_after
+            && let Ok(now) = UnixTimestamp::try_now()
+            && execute_after > now
+        {
+            tracing::info!(id, %execute_after, "Holding scheduled settlement until due");
+            tokio::time::sleep(Duration::from_secs(execute_after.0 - now.0)).await;
+        }
+        loop {
+            let Some(request) = self.update(&id, |record| {
+                record.status = QueueStatus::Processing;
+                record.attempts += 1;
+            }) else {
+                return;
+            };
+            let result = self.facilitator.settle(&request).await;
+            let mut retry_in = None;
+            let mut dead_lettered = false;
+            self.update(&id, |record| match &result {
+                Ok(response) => {
+                    record.status = match response.success {
+                        true => QueueStatus::Settled,
+                        false => QueueStatus::Failed,
+                    };
+                    record.payer = response.payer.clone();
+                    record.transaction = response.transaction.clone();
+                    record.error_reason = response.error_reason.clone();
+                    record.error = None;
+                    record.response = Some(response.clone());
+                }
+                Err(e) => {
+                    record.error = Some(e.to_string());
+                    record.response = None;
+                    if e.is_transient() && record.attempts < self.settings.max_attempts {
+                        record.status = QueueStatus::Queued;
+                        retry_in = Some(self.settings.retry_delay * 2u32.pow(record.attempts - 1));
+                    } else {
+                        record.status = QueueStatus::Failed;
+                        record.dead_lettered = true;
+                        dead_lettered = true;
+                    }
+                }
+            });
+            if let Some(delay) = retry_in {
+                tracing::warn!(id, ?delay, "Queued settlement failed, retrying");
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            if dead_lettered {
+                tracing::warn!(
+                    monotonic_counter.x402.settlement.dead_lettered = 1,
+                    id,
+                    network = %request.network(),
+                    "Queued settlement failed for good, dead-lettered"
+                );
+            }
+            if let Some(record) = self.records.get(&id) {
+                let kind = match record.status {
+                    QueueStatus::Settled => SettlementEventKind::Confirmed,
+                    _ => SettlementEventKind::Failed,
+                };
+                self.publish(kind, record);
+            }
+            if let Some((_, waiter)) = self.waiters.remove(&id) {
+                let result = result.map(|response| SettleResponse {
+                    settlement_id: Some(id.clone()),
+                    ..response
+                });
+                let _ = waiter.send(result);
+            }
+            self.finished.notify_waiters();
+            return;
+        }
+    }
+
+    /// Corrects the entries settled in the transaction reorganized in `event`, or in its rebroadcast.
+    fn apply_reorg(&self, event: &ReorgEvent) {
+        let hashes: Vec<TransactionHash> = std::iter::once(event.transaction)
+            .chain(event.outcome.transaction())
+            .map(|hash| TransactionHash::Evm(hash.0))
+            .collect();
+        let ids: Vec<String> = self
+            .records
+            .entries
+            .iter()
+            .filter(|entry| {
+                entry.record.network == event.network
+                    && entry
+                        .record
+                        .transaction
+                        .as_ref()
+                        .is_some_and(|transaction| hashes.contains(transaction))
+            })
+            .map(|entry| entry.key().clone())
+            .collect();
+        for id in ids {
+            let mut failed = false;
+            self.update(&id, |record| match &event.outcome {
+                ReorgOutcome::Reincluded {
+                    transaction,
+                    success,
+                    ..
+                } => {
+                    record.transaction = Some(TransactionHash::Evm(transaction.0));
+                    if !success {
+                        failed = record.status != QueueStatus::Failed;
+                        record.status = QueueStatus::Failed;
+                        record.error = Some(format!(
+                            "Settlement reverted once mined again in {transaction}"
+                        ));
+                    }
+                }
+                ReorgOutcome::Rebroadcast { transaction } => {
+                    record.transaction = Some(TransactionHash::Evm(transaction.0));
+                }
+                ReorgOutcome::Dropped { error } => {
+                    failed = record.status != QueueStatus::Failed;
+                    record.status = QueueStatus::Failed;
+                    record.error =
+                        Some(format!("Settlement reorganized out of the chain: {error}"));
+                }
+            });
+            tracing::warn!(id, outcome = ?event.outcome, "Queued settlement corrected after a reorganization");
+            if failed && let Some(record) = self.records.get(&id) {
+                self.publish(SettlementEventKind::Failed, record);
+            }
+        }
+    }
+
+    /// Drops finished entries older than the retention.
+    fn prune(&self, now: UnixTimestamp) {
+        self.records.entries.retain(|id, entry| {
+            let expired = entry.record.status.is_finished()
+                && !entry.record.dead_lettered
+                && entry.record.updated_at + self.settings.retention_secs < now;
+            if expired && let Err(e) = self.store.remove(id) {
+                tracing::warn!(id, error = %e, "Failed to remove queued settlement");
+            }
+            !expired
+        });
+    }
+
+    /// Enqueues `request`, unless it is queued or settled already.
+    fn enqueue(&self, request: &SettleRequest) -> Result<Enqueued, FacilitatorLocalError> {
+        let id = serde_json::to_vec(request)
+            .map(|bytes| keccak256(bytes).to_string())
+            .map_err(|e| FacilitatorLocalError::DecodingError(e.to_string()))?;
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        self.prune(now);
+        let entry = QueueEntry {
+            record: QueueRecord {
+                id: id.clone(),
+                status: QueueStatus::Queued,
+                network: request.network(),
+                payment_id: request.payment_id().cloned(),
+                attempts: 0,
+                enqueued_at: now,
+                updated_at: now,
+                payer: None,
+                transaction: None,
+                error_reason: None,
+                error: None,
+                dead_lettered: false,
+                execute_after: request.execute_after,
+                response: None,
+            },
+            request: request.clone(),
+        };
+        let record = entry.record.clone();
+        let (sender, receiver) = oneshot::channel();
+        match self.records.entries.entry(id.clone()) {
+            Entry::Occupied(existing) if existing.get().record.status == QueueStatus::Settled => {
+                return Ok(Enqueued::Settled(Box::new(response_of(existing.get()))));
+            }
+            Entry::Occupied(existing) if !existing.get().record.status.is_finished() => {
+                return Ok(Enqueued::InProgress(id));
+            }
+            // New, or failed before: settled (again) from scratch.
+            Entry::Occupied(mut existing) => {
+                self.save(&entry);
+                existing.insert(entry);
+            }
+            Entry::Vacant(vacant) => {
+                self.save(&entry);
+                vacant.insert(entry);
+            }
+        }
+        self.waiters.insert(id.clone(), sender);
+        tracing::info!(id, network = %request.network(), "Settlement queued");
+        self.publish(SettlementEventKind::Submitted, record);
+        self.jobs
+            .send(id.clone())
+            .map_err(|_| FacilitatorLocalError::ContractCall("Settlement queue stopped".into()))?;
+        Ok(Enqueued::New(id, receiver))
+    }
+
+    /// Waits for the entry `id`, enqueued by another request, to finish; its outcome as a settlement response.
+    async fn wait(&self, id: &str) -> SettleResult {
+        loop {
+            let finished = self.finished.notified();
+            tokio::pin!(finished);
+            finished.as_mut().enable();
+            let Some(entry) = self.records.entries.get(id).map(|entry| entry.clone()) else {
+                return Err(FacilitatorLocalError::ContractCall(format!(
+                    "Queued settlement {id} is gone"
+                )));
+            };
+            if entry.record.status.is_finished() {
+                return Ok(response_of(&entry));
+            }
+            finished.await;
+        }
+    }
+}
+
+/// Whether `request` is scheduled for later, checking that its `executeAfter` is before its authorization expires.
+fn is_scheduled(
+    request: &SettleRequest,
+    now: UnixTimestamp,
+) -> Result<bool, FacilitatorLocalError> {
+    let Some(execute_after) = request.execute_after.filter(|at| *at > now) else {
+        return Ok(false);
+    };
+    match request.payment_payload.payload.valid_before() {
+        Some(valid_before) if execute_after < valid_before => Ok(true),
+        Some(valid_before) => Err(FacilitatorLocalError::InvalidExecuteAfter(
+            None,
+            format!(
+                "executeAfter {execute_after} is not before the authorization expires at {valid_before}"
+            ),
+        )),
+        None => Err(FacilitatorLocalError::InvalidExecuteAfter(
+            None,
+            "the payload has no expiry to schedule its settlement within".to_string(),
+        )),
+    }
+}
+
+/// Response to `request` settling nothing yet, or nothing at all, with `error_reason`.
+fn unsettled(
+    request: &SettleRequest,
+    error_reason: FacilitatorErrorReason,
+    payer: Option<MixedAddress>,
+    settlement_id: Option<String>,
+) -> SettleResponse {
+    SettleResponse {
+        success: false,
+        error_reason: Some(error_reason),
+        payer,
+        transaction: None,
+        network: request.network(),
+        facilitator_version: None,
+        batch: None,
+        payment_id: request.payment_id().cloned(),
+        partial: None,
+        revert_reason: None,
+        signer: None,
+        settlement_id,
+        simulated: false,
+    }
+}
+
+/// Settlement response of a finished entry: the response of the facilitator, corrected by reorganizations since, or
+/// the failure of its last attempt.
+fn response_of(entry: &QueueEntry) -> SettleResponse {
+    let record = &entry.record;
+    let success = record.status == QueueStatus::Settled;
+    let error_reason = match success {
+        true => None,
+        false => Some(
+            record
+                .error_reason
+                .clone()
+                .unwrap_or(FacilitatorErrorReason::UnexpectedSettleError),
+        ),
+    };
+    match &record.response {
+        Some(response) => SettleResponse {
+            success,
+            error_reason,
+            transaction: record.transaction.clone(),
+            settlement_id: Some(record.id.clone()),
+            ..response.clone()
+        },
+        None => SettleResponse {
+            success,
+            error_reason,
+            payer: record.payer.clone(),
+            transaction: record.transaction.clone(),
+            network: record.network,
+            facilitator_version: None,
+            batch: None,
+            payment_id: record.payment_id.clone(),
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: Some(record.id.clone()),
+            simulated: false,
+        },
+    }
+}
+
+impl<F> Facilitator for SettlementQueue<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        self.inner.facilitator.verify(request).await
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        // Nobody waits until a scheduled settlement is due: it is answered once held, as in async mode.
+        if is_scheduled(request, now)? {
+            return self.submit(request).await;
+        }
+        match self.inner.enqueue(request)? {
+            Enqueued::Settled(response) => Ok(*response),
+            Enqueued::InProgress(id) => self.inner.wait(&id).await,
+            Enqueued::New(id, receiver) => receiver.await.unwrap_or_else(|_| {
+                Err(FacilitatorLocalError::ContractCall(format!(
+                    "Queued settlement {id} was dropped"
+                )))
+            }),
+        }
+    }
+
+    async fn submit(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let now = UnixTimestamp::try_now().map_err(FacilitatorLocalError::ClockError)?;
+        // Invalid payments are refused now rather than once due.
+        if is_scheduled(request, now)?
+            && let VerifyResponse::Invalid { reason, payer } =
+                self.inner.facilitator.verify(request).await?
+        {
+            return Ok(unsettled(request, reason, payer, None));
+        }
+        match self.inner.enqueue(request)? {
+            Enqueued::Settled(response) => Ok(*response),
+            Enqueued::InProgress(id) | Enqueued::New(id, _) => Ok(unsettled(
+                request,
+                FacilitatorErrorReason::SettlementPending,
+                None,
+                Some(id),
+            )),
+        }
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.inner.facilitator.supported().await
+    }
+}
+
+/// Route polling queued settlements.
+pub fn routes() -> Router<QueueRecords> {
+    Router::new().route("/queue/{id}", get(get_queued))
+}
+
+/// `GET /queue/{id}`: State of a queued settlement, by queue ID or payment ID.
+#[instrument(skip_all)]
+pub async fn get_queued(State(records): State<QueueRecords>, Path(id): Path<String>) -> Response {
+    match records.get(&id) {
+        Some(record) => (StatusCode::OK, Json(record)).into_response(),
+        None => (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Queued settlement not found".to_string(),
+            }),
+        )
+            .into_response(),
+    }
+}
+
+/// Route listing dead-lettered settlements, under `/admin`.
+pub fn dead_letter_routes<F>() -> Router<DeadLetters<F>>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    Router::new().route("/dead-letters", get(get_dead_letters::<F>))
+}
+
+/// Operator routes re-driving and discarding dead-lettered settlements, under `/admin`.
+pub fn dead_letter_config_routes<F>() -> Router<DeadLetters<F>>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    Router::new()
+        .route("/dead-letters/{id}", delete(delete_dead_letter::<F>))
+        .route("/dead-letters/{id}/redrive", post(post_redrive::<F>))
+}
+
+/// `GET /admin/dead-letters`: Dead-lettered settlements with their failure and original request.
+#[instrument(skip_all)]
+pub async fn get_dead_letters<F>(State(dead_letters): State<DeadLetters<F>>) -> Response
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    (StatusCode::OK, Json(dead_letters.list())).into_response()
+}
+
+/// `POST /admin/dead-letters/{id}/redrive`: Queues a dead-lettered settlement again.
+#[instrument(skip_all, fields(id = %id))]
+pub async fn post_redrive<F>(
+    State(dead_letters): State<DeadLetters<F>>,
+    Path(id): Path<String>,
+) -> Response
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    match dead_letters.redrive(&id) {
+        Some(record) => (StatusCode::ACCEPTED, Json(record)).into_response(),
+        None => dead_letter_not_found(),
+    }
+}
+
+/// `DELETE /admin/dead-letters/{id}`: Discards a dead-lettered settlement for good.
+#[instrument(skip_all, fields(id = %id))]
+pub async fn delete_dead_letter<F>(
+    State(dead_letters): State<DeadLetters<F>>,
+    Path(id): Path<String>,
+) -> Response
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Send + Sync + 'static,
+{
+    match dead_letters.discard(&id) {
+        Some(_) => StatusCode::NO_CONTENT.into_response(),
+        None => dead_letter_not_found(),
+    }
+}
+
+fn dead_letter_not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: "Dead-lettered settlement not found".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// Fate of a settlement, as served by `GET /settlements/{id}`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SettlementStatus {
+    /// Queued, being settled, or broadcast and not mined yet.
+    Pending,
+    Confirmed,
+    Failed,
+}
+
+/// State of a settlement, as served by `GET /settlements/{id}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementState {
+    pub id: String,
+    pub status: SettlementStatus,
+    pub network: Network,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<PaymentId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<MixedAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionHash>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_used: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<FacilitatorErrorReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Time the settlement is held until, if scheduled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_after: Option<UnixTimestamp>,
+}
+
+impl From<QueueRecord> for SettlementState {
+    fn from(record: QueueRecord) -> Self {
+        Self {
+            status: match record.status {
+                QueueStatus::Queued | QueueStatus::Processing => SettlementStatus::Pending,
+                QueueStatus::Settled => SettlementStatus::Confirmed,
+                QueueStatus::Failed => SettlementStatus::Failed,
+            },
+            id: record.id,
+            network: record.network,
+            payment_id: record.payment_id,
+            payer: record.payer,
+            transaction: record.transaction,
+            block_number: None,
+            gas_used: None,
+            error_reason: record.error_reason,
+            error: record.error,
+            execute_after: record.execute_after,
+        }
+    }
+}
+
+/// Step of the lifecycle of a settlement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SettlementEventKind {
+    /// Queued, before its transaction is sent.
+    #[serde(rename = "settlement.submitted")]
+    Submitted,
+    #[serde(rename = "settlement.confirmed")]
+    Confirmed,
+    #[serde(rename = "settlement.failed")]
+    Failed,
+}
+
+/// A settlement reaching a step of its lifecycle, as published to [`SettlementQueue::subscribe`]rs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettlementEvent {
+    #[serde(rename = "event")]
+    pub kind: SettlementEventKind,
+    pub at: UnixTimestamp,
+    pub settlement: SettlementState,
+}
+
+/// Subscriptions to the [`SettlementEvent`]s of a [`SettlementQueue`].
+#[derive(Clone)]
+pub struct SettlementEvents {
+    sender: broadcast::Sender<SettlementEvent>,
+}
+
+impl SettlementEvents {
+    /// Lifecycle events of the settlements from now on.
+    pub fn subscribe(&self) -> broadcast::Receiver<SettlementEvent> {
+        self.sender.subscribe()
+    }
+}
+
+/// State of [`settlement_routes`]: the queue entries, and the providers reading transaction receipts.
+#[derive(Clone)]
+pub struct Settlements<P> {
+    pub records: QueueRecords,
+    pub providers: P,
+}
+
+/// Route serving the fate of settlements.
+pub fn settlement_routes<P>() -> Router<Settlements<P>>
+where
+    P: ProviderMap<Value = NetworkProvider> + Clone + Send + Sync + 'static,
+{
+    Router::new().route("/settlements/{id}", get(get_settlement::<P>))
+}
+
+/// `GET /settlements/{id}`: Fate of a settlement, by settlement ID or payment ID.
+#[instrument(skip_all)]
+pub async fn get_settlement<P>(
+    State(settlements): State<Settlements<P>>,
+    Path(id): Path<String>,
+) -> Response
+where
+    P: ProviderMap<Value = NetworkProvider>,
+{
+    let Some(record) = settlements.records.get(&id) else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: "Settlement not found".to_string(),
+            }),
+        )
+            .into_response();
+    };
+    let settled = record.status == QueueStatus::Settled;
+    let mut state = SettlementState::from(record);
+    // EVM transactions are read back from the chain, where they may since have been reorganized out.
+    if let Some(TransactionHash::Evm(hash)) = &state.transaction
+        && let Some(NetworkProvider::Evm(provider)) =
+            settlements.providers.by_network(state.network)
+    {
+        match provider.transaction_receipt(B256::from(*hash)).await {
+            Ok(Some(receipt)) => {
+                state.block_number = receipt.block_number;
+                state.gas_used = Some(receipt.gas_used);
+                if !receipt.status() {
+                    state.status = SettlementStatus::Failed;
+                }
+            }
+            Ok(None) if settled => state.status = SettlementStatus::Pending,
+            Ok(None) => {}
+            Err(e) => {
+                tracing::warn!(id, error = %e, "Can not read settlement receipt");
+            }
+        }
+    }
+    (StatusCode::OK, Json(state)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloy::primitives::Address;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Facilitator failing the first settlement attempt with an RPC error.
+    #[derive(Default)]
+    struct Flaky {
+        attempts: AtomicU32,
+    }
+
+    impl Facilitator for Flaky {
+        type Error = FacilitatorLocalError;
+
+        async fn verify(&self, _request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+            unimplemented!()
+        }
+
+        async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+            if self.attempts.fetch_add(1, Ordering::SeqCst) == 0 {
+                return Err(FacilitatorLocalError::ContractCall("timeout".into()));
+            }
+            Ok(SettleResponse {
+                success: true,
+                error_reason: None,
+                payer: Some(MixedAddress::Evm(Address::repeat_byte(3).into())),
+                transaction: Some(TransactionHash::Evm([1; 32])),
+                network: request.network(),
+                facilitator_version: Some("0.9.0".to_string()),
+                batch: None,
+                payment_id: None,
+                partial: None,
+                revert_reason: None,
+                signer: Some(MixedAddress::Evm(Address::repeat_byte(4).into())),
+                settlement_id: None,
+                simulated: false,
+            })
+        }
+
+        async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+            unimplemented!()
+        }
+    }
+
+    fn request() -> SettleRequest {
+        serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": "1",
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x0000000000000000000000000000000000000002",
+                "paymentId": "order-1"
+            }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures_and_settles_once() {
+        let request = request();
+        let settings = QueueSettings {
+            retry_delay: Duration::from_millis(10),
+            ..QueueSettings::default()
+        };
+        let queue =
+            SettlementQueue::new(Flaky::default(), Box::new(MemoryStore), settings).unwrap();
+        let mut events = queue.subscribe();
+        let response = queue.settle(&request).await.unwrap();
+        assert_eq!(
+            events.recv().await.unwrap().kind,
+            SettlementEventKind::Submitted
+        );
+        assert_eq!(
+            events.recv().await.unwrap().kind,
+            SettlementEventKind::Confirmed
+        );
+        assert!(response.success);
+        let record = queue.records().get("order-1").unwrap();
+        assert_eq!(record.status, QueueStatus::Settled);
+        assert_eq!(record.attempts, 2);
+        assert_eq!(response.settlement_id.as_deref(), Some(record.id.as_str()));
+        let state = SettlementState::from(record.clone());
+        assert_eq!(state.status, SettlementStatus::Confirmed);
+
+        let again = queue.settle(&request).await.unwrap();
+        assert_eq!(again.transaction, response.transaction);
+        // Answered again in full, not reconstructed from the queue entry.
+        assert_eq!(again.payer, response.payer);
+        assert_eq!(again.signer, response.signer);
+        assert_eq!(again.facilitator_version, response.facilitator_version);
+        assert_eq!(queue.inner.facilitator.attempts.load(Ordering::SeqCst), 2);
+
+        queue.inner.apply_reorg(&ReorgEvent {
+            network: Network::Base,
+            transaction: [1; 32].into(),
+            outcome: ReorgOutcome::Dropped {
+                error: "authorization is used".into(),
+            },
+        });
+        let record = queue.records().get("order-1").unwrap();
+        assert_eq!(record.status, QueueStatus::Failed);
+        assert_eq!(
+            events.recv().await.unwrap().kind,
+            SettlementEventKind::Failed
+        );
+        let state = SettlementState::from(record);
+        assert_eq!(state.status, SettlementStatus::Failed);
+    }
+
+    #[tokio::test]
+    async fn submissions_answer_before_settling() {
+        let settings = QueueSettings {
+            retry_delay: Duration::from_millis(10),
+            ..QueueSettings::default()
+        };
+        let queue =
+            SettlementQueue::new(Flaky::default(), Box::new(MemoryStore), settings).unwrap();
+        let mut events = queue.subscribe();
+        let response = queue.submit(&request()).await.unwrap();
+        assert!(!response.success);
+        assert!(matches!(
+            response.error_reason,
+            Some(FacilitatorErrorReason::SettlementPending)
+        ));
+        assert!(response.transaction.is_none());
+        let id = response.settlement_id.unwrap();
+
+        assert_eq!(
+            events.recv().await.unwrap().kind,
+            SettlementEventKind::Submitted
+        );
+        assert_eq!(
+            events.recv().await.unwrap().kind,
+            SettlementEventKind::Confirmed
+        );
+        assert_eq!(
+            queue.records().get(&id).unwrap().status,
+            QueueStatus::Settled
+        );
+        let again = queue.submit(&request()).await.unwrap();
+        assert!(again.success);
+        assert_eq!(again.settlement_id, Some(id));
+    }
+
+    #[tokio::test]
+    async fn failures_are_dead_lettered_until_redriven() {
+        let settings = QueueSettings {
+            max_attempts: 1,
+            ..QueueSettings::default()
+        };
+        let queue =
+            SettlementQueue::new(Flaky::default(), Box::new(MemoryStore), settings).unwrap();
+        let dead_letters = queue.dead_letters();
+        assert!(queue.settle(&request()).await.is_err());
+        let entries = dead_letters.list();
+        assert_eq!(entries.len(), 1);
+        let id = entries[0].record.id.clone();
+        assert_eq!(
+            entries[0].record.error.as_deref(),
+            Some("Invalid contract call: timeout")
+        );
+
+        let mut events = queue.subscribe();
+        let record = dead_letters.redrive(&id).unwrap();
+        assert_eq!(record.status, QueueStatus::Queued);
+        assert_eq!(
+            events.recv().await.unwrap().kind,
+            SettlementEventKind::Submitted
+        );
+        assert_eq!(
+            events.recv().await.unwrap().kind,
+            SettlementEventKind::Confirmed
+        );
+        assert!(dead_letters.list().is_empty());
+        assert!(dead_letters.discard(&id).is_none());
+    }
+
+    #[test]
+    fn schedules_only_within_the_authorization() {
+        let mut scheduled: SettleRequest = serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {
+                    "signature": format!("0x{}", "11".repeat(65)),
+                    "authorization": {
+                        "from": "0x0000000000000000000000000000000000000003",
+                        "to": "0x0000000000000000000000000000000000000001",
+                        "value": "1",
+                        "validAfter": "0",
+                        "validBefore": "2000",
+                        "nonce": format!("0x{}", "22".repeat(32))
+                    }
+                }
+            },
+            "paymentRequirements": request().payment_requirements,
+            "executeAfter": "1500"
+        }))
+        .unwrap();
+        assert!(is_scheduled(&scheduled, UnixTimestamp(1000)).unwrap());
+        assert!(!is_scheduled(&scheduled, UnixTimestamp(1500)).unwrap());
+        scheduled.execute_after = Some(UnixTimestamp(2000));
+        assert!(matches!(
+            is_scheduled(&scheduled, UnixTimestamp(1000)),
+            Err(FacilitatorLocalError::InvalidExecuteAfter(..))
+        ));
+
+        // Solana transactions state no expiry.
+        let mut unscheduled = request();
+        unscheduled.execute_after = Some(UnixTimestamp(1500));
+        assert!(is_scheduled(&unscheduled, UnixTimestamp(1000)).is_err());
+        unscheduled.execute_after = None;
+        assert!(!is_scheduled(&unscheduled, UnixTimestamp(1000)).unwrap());
+    }
+}
@@ -0,0 +1,213 @@
+//! Settlement status streaming over WebSocket.
+//!
+//! Clients following settlements, e.g. the `settlementId`s answered by `/settle` in async mode, would otherwise poll
+//! `GET /settlements/{id}`. `GET /ws` upgrades to a WebSocket instead, on which clients subscribe to settlements by
+//! settlement ID or `paymentId` and are sent the [`SettlementEvent`]s of the settlement queue as they happen, as JSON
+//! text messages `{"event", "at", "settlement"}`, the same as webhooks.
+//!
+//! Clients send `{"subscribe": ["<id>", …]}` and `{"unsubscribe": ["<id>", …]}` text messages. IDs not known yet
+//! are followed too, for settlements to come. A subscribed settlement that is already finished is answered with its
+//! `settlement.confirmed` or `settlement.failed` event right away, so that subscribing after the fact does not wait
+//! forever. Invalid messages, subscriptions beyond [`MAX_SUBSCRIPTIONS`], and events missed by a client too slow to
+//! read them are answered with a `{"error": "..."}` message; the connection stays open.
+
+use axum::Router;
+use axum::extract::State;
+use axum::extract::ws::{Message, WebSocket, WebSocketUpgrade};
+use axum::response::IntoResponse;
+use axum::routing::get;
+use serde::Deserialize;
+use std::collections::HashSet;
+use tokio::sync::broadcast;
+use tracing::instrument;
+
+use crate::settle_queue::{
+    QueueRecords, QueueStatus, SettlementEvent, SettlementEventKind, SettlementEvents,
+};
+use crate::types::ErrorResponse;
+
+/// Settlements one connection can be subscribed to at once.
+pub const MAX_SUBSCRIPTIONS: usize = 100;
+
+/// State of [`routes`]: the queue entries, for settlements finished before being subscribed to, and their events.
+#[derive(Clone)]
+pub struct SettlementStream {
+    pub records: QueueRecords,
+    pub events: SettlementEvents,
+}
+
+/// Message sent by a client.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+enum ClientMessage {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+}
+
+/// Settlement IDs and payment IDs a connection is subscribed to.
+#[derive(Debug, Default)]
+struct Subscriptions {
+    ids: HashSet<String>,
+}
+
+impl Subscriptions {
+    /// Whether `event` is about a subscribed settlement.
+    fn wants(&self, event: &SettlementEvent) -> bool {
+        self.ids.contains(&event.settlement.id)
+            || event
+                .settlement
+                .payment_id
+                .as_ref()
+                .is_some_and(|payment_id| self.ids.contains(&payment_id.0))
+    }
+}
+
+/// Route serving the settlement stream.
+pub fn routes() -> Router<SettlementStream> {
+    Router::new().route("/ws", get(get_ws))
+}
+
+/// `GET /ws`: WebSocket streaming the lifecycle events of subscribed settlements.
+///
+/// Send `{"subscribe": ["<settlementId>"]}` to be sent `settlement.submitted`, `settlement.confirmed` and
+/// `settlement.failed` events of that settlement as they happen, instead of polling `GET /settlements/{id}`.
+#[instrument(skip_all)]
+pub async fn get_ws(
+    State(stream): State<SettlementStream>,
+    upgrade: WebSocketUpgrade,
+) -> impl IntoResponse {
+    upgrade.on_upgrade(move |socket| serve(socket, stream))
+}
+
+/// Serves the events of `stream` on `socket` until either side closes it.
+async fn serve(mut socket: WebSocket, stream: SettlementStream) {
+    let mut events = stream.events.subscribe();
+    let mut subscriptions = Subscriptions::default();
+    loop {
+        let outgoing = tokio::select! {
+            message = socket.recv() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    handle(&stream.records, &mut subscriptions, text.as_str())
+                }
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                // Pings are answered by the socket itself.
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(event) if subscriptions.wants(&event) => vec![to_json(&event)],
+                Ok(_) => continue,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "WebSocket client missed settlement events");
+                    vec![error(format!("Missed {missed} settlement events"))]
+                }
+                Err(broadcast::error::RecvError::Closed) => return,
+            },
+        };
+        for message in outgoing {
+            if socket.send(Message::Text(message.into())).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Applies the client message `text` to `subscriptions`; the messages to answer it with.
+fn handle(records: &QueueRecords, subscriptions: &mut Subscriptions, text: &str) -> Vec<String> {
+    let message = match serde_json::from_str::<ClientMessage>(text) {
+        Ok(message) => message,
+        Err(e) => return vec![error(format!("Invalid message: {e}"))],
+    };
+    match message {
+        ClientMessage::Subscribe(ids) => {
+            let mut outgoing = Vec::new();
+            for id in ids {
+                if subscriptions.ids.contains(&id) {
+                    continue;
+                }
+                if subscriptions.ids.len() >= MAX_SUBSCRIPTIONS {
+                    outgoing.push(error(format!(
+                        "At most {MAX_SUBSCRIPTIONS} subscriptions per connection"
+                    )));
+                    break;
+                }
+                if let Some(event) = finished_event(records, &id) {
+                    outgoing.push(to_json(&event));
+                }
+                subscriptions.ids.insert(id);
+            }
+            outgoing
+        }
+        ClientMessage::Unsubscribe(ids) => {
+            for id in ids {
+                subscriptions.ids.remove(&id);
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// Last event of the settlement `id`, if it is finished.
+fn finished_event(records: &QueueRecords, id: &str) -> Option<SettlementEvent> {
+    let record = records.get(id)?;
+    let kind = match record.status {
+        QueueStatus::Settled => SettlementEventKind::Confirmed,
+        QueueStatus::Failed => SettlementEventKind::Failed,
+        QueueStatus::Queued | QueueStatus::Processing => return None,
+    };
+    Some(SettlementEvent {
+        kind,
+        at: record.updated_at,
+        settlement: record.into(),
+    })
+}
+
+fn to_json(event: &SettlementEvent) -> String {
+    serde_json::to_string(event).unwrap_or_default()
+}
+
+fn error(message: String) -> String {
+    serde_json::to_string(&ErrorResponse { error: message }).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::network::Network;
+    use crate::settle_queue::{SettlementState, SettlementStatus};
+    use crate::timestamp::UnixTimestamp;
+    use crate::types::PaymentId;
+
+    fn event(id: &str, payment_id: Option<&str>) -> SettlementEvent {
+        SettlementEvent {
+            kind: SettlementEventKind::Submitted,
+            at: UnixTimestamp(1_700_000_000),
+            settlement: SettlementState {
+                id: id.to_string(),
+                status: SettlementStatus::Pending,
+                network: Network::Base,
+                payment_id: payment_id.map(|p| PaymentId(p.to_string())),
+                payer: None,
+                transaction: None,
+                block_number: None,
+                gas_used: None,
+                error_reason: None,
+                error: None,
+                execute_after: None,
+            },
+        }
+    }
+
+    #[test]
+    fn streams_subscribed_settlements_only() {
+        assert_eq!(
+            serde_json::from_str::<ClientMessage>(r#"{"subscribe": ["a", "order-1"]}"#).unwrap(),
+            ClientMessage::Subscribe(vec!["a".to_string(), "order-1".to_string()])
+        );
+        let subscriptions = Subscriptions {
+            ids: HashSet::from(["a".to_string(), "order-1".to_string()]),
+        };
+        assert!(subscriptions.wants(&event("a", None)));
+        assert!(subscriptions.wants(&event("b", Some("order-1"))));
+        assert!(!subscriptions.wants(&event("c", Some("order-2"))));
+    }
+}
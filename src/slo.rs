@@ -0,0 +1,563 @@
+//! Latency SLO tracking with burn-rate alerts.
+//!
+//! Operators of public facilitators commit to latency objectives such as "99.9% of `/verify` calls answer within
+//! 300 ms". [`SloGate`] times every `verify` and `settle` call into per-minute, high-resolution histograms per
+//! endpoint and network, kept for an hour, and [`SloTracker::spawn`] evaluates them every
+//! [`EVALUATION_INTERVAL`] against the configured targets.
+//!
+//! The _burn rate_ of an objective is the share of calls slower than the threshold divided by the share allowed,
+//! `1 - objective`: at `1`, the error budget is spent exactly over the SLO period. Following the multi-window
+//! practice, an alert fires when the burn rate over both the last hour and the last 5 minutes exceeds
+//! `SLO_BURN_RATE_THRESHOLD`, and resolves when the 5-minute burn rate falls back below it. Alerts are logged,
+//! counted in the `x402.slo.alerts` metric and, with `SLO_ALERT_WEBHOOK_URL`, posted as JSON [`SloAlert`]s.
+//!
+//! `GET /slo` reports the p50, p95 and p99 latencies and the burn rates of every endpoint and network over the
+//! last hour. Every call is also recorded in the `x402.request.latency_ms` histogram, and burn rates in the
+//! `x402.slo.burn_rate` gauge.
+//!
+//! Environment variables used:
+//! - `SLO_TARGETS` — comma-separated `<endpoint>:<threshold ms>:<objective %>` targets, e.g.
+//!   `verify:300:99.9,settle:10000:99`. Latencies are not tracked if unset,
+//! - `SLO_BURN_RATE_THRESHOLD` — burn rate firing alerts (default: `14.4`, the budget of 30 days spent in 2),
+//! - `SLO_ALERT_WEBHOOK_URL` — URL alerts are posted to, subject to the outbound policy.
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio_util::sync::CancellationToken;
+use tracing::instrument;
+use url::Url;
+
+use crate::chain::FacilitatorLocalError;
+use crate::facilitator::Facilitator;
+use crate::from_env;
+use crate::network::Network;
+use crate::outbound::OutboundPolicy;
+use crate::timestamp::UnixTimestamp;
+use crate::types::{
+    SettleRequest, SettleResponse, SupportedPaymentKindsResponse, VerifyRequest, VerifyResponse,
+};
+
+/// Default burn rate firing alerts.
+const DEFAULT_BURN_RATE_THRESHOLD: f64 = 14.4;
+/// Interval between evaluations of the burn rates.
+pub const EVALUATION_INTERVAL: Duration = Duration::from_secs(30);
+/// Minutes of history kept, the long alerting window.
+const LONG_WINDOW_MINUTES: u64 = 60;
+/// The short alerting window, in minutes.
+const SHORT_WINDOW_MINUTES: u64 = 5;
+/// Ratio between the bounds of consecutive histogram buckets, for a resolution of 2%.
+const BUCKET_RATIO: f64 = 1.02;
+
+/// Facilitator endpoint with a latency objective.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Endpoint {
+    Verify,
+    Settle,
+}
+
+impl Display for Endpoint {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Endpoint::Verify => write!(f, "verify"),
+            Endpoint::Settle => write!(f, "settle"),
+        }
+    }
+}
+
+impl FromStr for Endpoint {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().trim_start_matches('/') {
+            "verify" => Ok(Endpoint::Verify),
+            "settle" => Ok(Endpoint::Settle),
+            other => Err(format!(
+                "unknown endpoint {other}, expected verify or settle"
+            )),
+        }
+    }
+}
+
+/// Share of the calls to `endpoint` that must answer within `threshold`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SloTarget {
+    pub endpoint: Endpoint,
+    pub threshold: Duration,
+    /// Between `0` and `1`, e.g. `0.999`.
+    pub objective: f64,
+}
+
+fn parse_targets(targets: &str) -> Result<Vec<SloTarget>, String> {
+    targets
+        .split(',')
+        .map(str::trim)
+        .filter(|target| !target.is_empty())
+        .map(|target| {
+            let mut parts = target.split(':');
+            let (Some(endpoint), Some(threshold), Some(objective), None) =
+                (parts.next(), parts.next(), parts.next(), parts.next())
+            else {
+                return Err(format!(
+                    "expected endpoint:threshold_ms:objective_percent, got {target}"
+                ));
+            };
+            let threshold = threshold
+                .trim()
+                .parse::<u64>()
+                .map_err(|e| format!("invalid threshold in {target}: {e}"))?;
+            let objective = objective
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("invalid objective in {target}: {e}"))?;
+            if !(objective > 0.0 && objective < 100.0) {
+                return Err(format!("objective of {target} must be between 0 and 100"));
+            }
+            Ok(SloTarget {
+                endpoint: endpoint.parse()?,
+                threshold: Duration::from_millis(threshold),
+                objective: objective / 100.0,
+            })
+        })
+        .collect()
+}
+
+fn bucket(latency: Duration) -> u16 {
+    let micros = latency.as_micros().max(1) as f64;
+    (micros.ln() / BUCKET_RATIO.ln()).min(u16::MAX as f64) as u16
+}
+
+/// Upper bound of a bucket, in milliseconds.
+fn bucket_bound_ms(bucket: u16) -> f64 {
+    BUCKET_RATIO.powi(bucket as i32 + 1) / 1000.0
+}
+
+/// Calls of one minute.
+#[derive(Debug, Default)]
+struct Minute {
+    minute: u64,
+    total: u64,
+    /// Calls slower than the threshold.
+    slow: u64,
+    buckets: BTreeMap<u16, u64>,
+}
+
+/// Last hour of calls to an endpoint on a network.
+#[derive(Debug, Default)]
+struct Series {
+    minutes: VecDeque<Minute>,
+    alerting: bool,
+}
+
+impl Series {
+    fn record(&mut self, minute: u64, latency: Duration, threshold: Option<Duration>) {
+        if self.minutes.back().is_none_or(|last| last.minute != minute) {
+            self.minutes.push_back(Minute {
+                minute,
+                ..Minute::default()
+            });
+        }
+        while self
+            .minutes
+            .front()
+            .is_some_and(|first| first.minute + LONG_WINDOW_MINUTES <= minute)
+        {
+            self.minutes.pop_front();
+        }
+        let current = self.minutes.back_mut().expect("pushed above");
+        current.total += 1;
+        if threshold.is_some_and(|threshold| latency > threshold) {
+            current.slow += 1;
+        }
+        *current.buckets.entry(bucket(latency)).or_default() += 1;
+    }
+
+    /// Calls and slow calls of the last `minutes` minutes.
+    fn counts(&self, now_minute: u64, minutes: u64) -> (u64, u64) {
+        self.minutes
+            .iter()
+            .filter(|m| m.minute + minutes > now_minute)
+            .fold((0, 0), |(total, slow), m| (total + m.total, slow + m.slow))
+    }
+
+    fn burn_rate(&self, now_minute: u64, minutes: u64, objective: f64) -> f64 {
+        match self.counts(now_minute, minutes) {
+            (0, _) => 0.0,
+            (total, slow) => (slow as f64 / total as f64) / (1.0 - objective),
+        }
+    }
+
+    /// Latency under which `quantile` of the calls of the last hour answered, in milliseconds.
+    fn quantile_ms(&self, now_minute: u64, quantile: f64) -> Option<f64> {
+        let mut buckets = BTreeMap::<u16, u64>::new();
+        for minute in self
+            .minutes
+            .iter()
+            .filter(|m| m.minute + LONG_WINDOW_MINUTES > now_minute)
+        {
+            for (bucket, count) in &minute.buckets {
+                *buckets.entry(*bucket).or_default() += count;
+            }
+        }
+        let total: u64 = buckets.values().sum();
+        let rank = (quantile * total as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        buckets.into_iter().find_map(|(bucket, count)| {
+            seen += count;
+            (seen >= rank).then(|| bucket_bound_ms(bucket))
+        })
+    }
+}
+
+/// Latencies of an endpoint on a network, as served by `GET /slo`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SloReport {
+    pub endpoint: Endpoint,
+    pub network: Network,
+    /// Calls of the last hour.
+    pub count: u64,
+    pub p50_ms: Option<f64>,
+    pub p95_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold_ms: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub objective: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burn_rate_5m: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burn_rate_1h: Option<f64>,
+    pub alerting: bool,
+}
+
+/// Change of an alert, as posted to `SLO_ALERT_WEBHOOK_URL`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SloAlert {
+    pub endpoint: Endpoint,
+    pub network: Network,
+    /// `firing` or `resolved`.
+    pub status: String,
+    pub threshold_ms: u64,
+    pub objective: f64,
+    pub burn_rate_5m: f64,
+    pub burn_rate_1h: f64,
+    pub at: UnixTimestamp,
+}
+
+struct SloTrackerInner {
+    targets: Vec<SloTarget>,
+    burn_rate_threshold: f64,
+    webhook: Option<(Url, reqwest::Client)>,
+    series: DashMap<(Endpoint, Network), Mutex<Series>>,
+}
+
+/// Latency histograms and alert states, shared between the [`SloGate`], the evaluation task and the [`routes`].
+///
+/// Cheap to clone: all clones share the same histograms.
+#[derive(Clone)]
+pub struct SloTracker {
+    inner: Arc<SloTrackerInner>,
+}
+
+impl SloTracker {
+    /// Tracker of `targets`, posting alerts with the given client to the given URL, if any.
+    pub fn new(
+        targets: Vec<SloTarget>,
+        burn_rate_threshold: f64,
+        webhook: Option<(Url, reqwest::Client)>,
+    ) -> Self {
+        Self {
+            inner: Arc::new(SloTrackerInner {
+                targets,
+                burn_rate_threshold,
+                webhook,
+                series: DashMap::new(),
+            }),
+        }
+    }
+
+    /// Reads the targets from `SLO_*` variables; `None` if `SLO_TARGETS` is unset.
+    pub fn from_env() -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let Ok(targets) = std::env::var(from_env::ENV_SLO_TARGETS) else {
+            return Ok(None);
+        };
+        let targets =
+            parse_targets(&targets).map_err(|e| format!("{}: {e}", from_env::ENV_SLO_TARGETS))?;
+        let burn_rate_threshold = match std::env::var(from_env::ENV_SLO_BURN_RATE_THRESHOLD) {
+            Ok(value) => value
+                .parse::<f64>()
+                .map_err(|e| format!("{}: {e}", from_env::ENV_SLO_BURN_RATE_THRESHOLD))?,
+            Err(_) => DEFAULT_BURN_RATE_THRESHOLD,
+        };
+        let webhook = match std::env::var(from_env::ENV_SLO_ALERT_WEBHOOK_URL) {
+            Ok(url) => {
+                let url = Url::parse(&url)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_SLO_ALERT_WEBHOOK_URL))?;
+                let outbound = OutboundPolicy::from_env()?;
+                outbound
+                    .validate_url(&url)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_SLO_ALERT_WEBHOOK_URL))?;
+                Some((url, outbound.http_client()))
+            }
+            Err(_) => None,
+        };
+        Ok(Some(Self::new(targets, burn_rate_threshold, webhook)))
+    }
+
+    fn target(&self, endpoint: Endpoint) -> Option<&SloTarget> {
+        self.inner
+            .targets
+            .iter()
+            .find(|target| target.endpoint == endpoint)
+    }
+
+    /// Records a call answered in `latency`.
+    pub fn record(&self, endpoint: Endpoint, network: Network, latency: Duration, now: u64) {
+        tracing::info!(
+            histogram.x402.request.latency_ms = latency.as_secs_f64() * 1000.0,
+            endpoint = %endpoint,
+            network = %network,
+        );
+        let threshold = self.target(endpoint).map(|target| target.threshold);
+        let series = self.inner.series.entry((endpoint, network)).or_default();
+        let mut series = series.lock().expect("SLO series lock poisoned");
+        series.record(now / 60, latency, threshold);
+    }
+
+    /// Latencies of every endpoint and network seen in the last hour.
+    pub fn report(&self, now: u64) -> Vec<SloReport> {
+        let now_minute = now / 60;
+        let mut reports = self
+            .inner
+            .series
+            .iter()
+            .map(|entry| {
+                let (endpoint, network) = *entry.key();
+                let series = entry.value().lock().expect("SLO series lock poisoned");
+                let target = self.target(endpoint);
+                let burn_rate = |minutes| {
+                    target.map(|target| series.burn_rate(now_minute, minutes, target.objective))
+                };
+                SloReport {
+                    endpoint,
+                    network,
+                    count: series.counts(now_minute, LONG_WINDOW_MINUTES).0,
+                    p50_ms: series.quantile_ms(now_minute, 0.50),
+                    p95_ms: series.quantile_ms(now_minute, 0.95),
+                    p99_ms: series.quantile_ms(now_minute, 0.99),
+                    threshold_ms: target.map(|target| target.threshold.as_millis() as u64),
+                    objective: target.map(|target| target.objective),
+                    burn_rate_5m: burn_rate(SHORT_WINDOW_MINUTES),
+                    burn_rate_1h: burn_rate(LONG_WINDOW_MINUTES),
+                    alerting: series.alerting,
+                }
+            })
+            .collect::<Vec<_>>();
+        reports.sort_by_key(|report| (report.endpoint.to_string(), report.network.to_string()));
+        reports
+    }
+
+    /// Updates the alert states from the burn rates, returning the alerts that changed.
+    fn evaluate(&self, now: u64) -> Vec<SloAlert> {
+        let now_minute = now / 60;
+        let threshold = self.inner.burn_rate_threshold;
+        let mut alerts = Vec::new();
+        for entry in self.inner.series.iter() {
+            let (endpoint, network) = *entry.key();
+            let Some(target) = self.target(endpoint) else {
+                continue;
+            };
+            let mut series = entry.value().lock().expect("SLO series lock poisoned");
+            let short = series.burn_rate(now_minute, SHORT_WINDOW_MINUTES, target.objective);
+            let long = series.burn_rate(now_minute, LONG_WINDOW_MINUTES, target.objective);
+            tracing::info!(
+                gauge.x402.slo.burn_rate = short,
+                endpoint = %endpoint,
+                network = %network,
+                window = "5m",
+            );
+            tracing::info!(
+                gauge.x402.slo.burn_rate = long,
+                endpoint = %endpoint,
+                network = %network,
+                window = "1h",
+            );
+            let alerting = if series.alerting {
+                short > threshold
+            } else {
+                short > threshold && long > threshold
+            };
+            if alerting == series.alerting {
+                continue;
+            }
+            series.alerting = alerting;
+            alerts.push(SloAlert {
+                endpoint,
+                network,
+                status: if alerting { "firing" } else { "resolved" }.to_string(),
+                threshold_ms: target.threshold.as_millis() as u64,
+                objective: target.objective,
+                burn_rate_5m: short,
+                burn_rate_1h: long,
+                at: UnixTimestamp(now),
+            });
+        }
+        alerts
+    }
+
+    async fn notify(&self, alert: &SloAlert) {
+        if alert.status == "firing" {
+            tracing::warn!(endpoint = %alert.endpoint, network = %alert.network, burn_rate_5m = alert.burn_rate_5m, burn_rate_1h = alert.burn_rate_1h, "Latency SLO burning too fast");
+        } else {
+            tracing::info!(endpoint = %alert.endpoint, network = %alert.network, "Latency SLO alert resolved");
+        }
+        tracing::info!(
+            monotonic_counter.x402.slo.alerts = 1,
+            endpoint = %alert.endpoint,
+            network = %alert.network,
+            status = %alert.status,
+        );
+        let Some((url, http)) = &self.inner.webhook else {
+            return;
+        };
+        let result = http
+            .post(url.clone())
+            .json(alert)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        if let Err(e) = result {
+            tracing::warn!(error = %e, "Failed to post SLO alert");
+        }
+    }
+
+    /// Evaluates the burn rates every [`EVALUATION_INTERVAL`] until cancelled.
+    pub fn spawn(self, cancellation_token: CancellationToken) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(EVALUATION_INTERVAL);
+            loop {
+                tokio::select! {
+                    _ = ticker.tick() => {}
+                    _ = cancellation_token.cancelled() => break,
+                }
+                let Ok(now) = UnixTimestamp::try_now() else {
+                    continue;
+                };
+                for alert in self.evaluate(now.seconds_since_epoch()) {
+                    self.notify(&alert).await;
+                }
+            }
+        })
+    }
+}
+
+/// [`Facilitator`] decorator timing `verify` and `settle` calls into an [`SloTracker`].
+///
+/// Passes every call through untimed when no tracker is configured.
+pub struct SloGate<F> {
+    facilitator: F,
+    tracker: Option<SloTracker>,
+}
+
+impl<F> SloGate<F> {
+    pub fn new(facilitator: F, tracker: Option<SloTracker>) -> Self {
+        Self {
+            facilitator,
+            tracker,
+        }
+    }
+
+    fn record(&self, endpoint: Endpoint, network: Network, started: Instant) {
+        if let Some(tracker) = &self.tracker {
+            let now = UnixTimestamp::try_now().map_or(0, |now| now.seconds_since_epoch());
+            tracker.record(endpoint, network, started.elapsed(), now);
+        }
+    }
+}
+
+impl<F> Facilitator for SloGate<F>
+where
+    F: Facilitator<Error = FacilitatorLocalError> + Sync + Send,
+{
+    type Error = FacilitatorLocalError;
+
+    async fn verify(&self, request: &VerifyRequest) -> Result<VerifyResponse, Self::Error> {
+        let started = Instant::now();
+        let response = self.facilitator.verify(request).await;
+        self.record(Endpoint::Verify, request.network(), started);
+        response
+    }
+
+    async fn settle(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        let started = Instant::now();
+        let response = self.facilitator.settle(request).await;
+        self.record(Endpoint::Settle, request.network(), started);
+        response
+    }
+
+    /// Passed through untimed: answering without settling says nothing of the settlement latency.
+    async fn submit(&self, request: &SettleRequest) -> Result<SettleResponse, Self::Error> {
+        self.facilitator.submit(request).await
+    }
+
+    async fn supported(&self) -> Result<SupportedPaymentKindsResponse, Self::Error> {
+        self.facilitator.supported().await
+    }
+}
+
+/// Routes reporting latencies against the SLO targets.
+pub fn routes() -> Router<SloTracker> {
+    Router::new().route("/slo", get(get_slo))
+}
+
+/// `GET /slo`: Latency percentiles and burn rates per endpoint and network over the last hour.
+#[instrument(skip_all)]
+pub async fn get_slo(State(tracker): State<SloTracker>) -> impl IntoResponse {
+    let now = UnixTimestamp::try_now().map_or(0, |now| now.seconds_since_epoch());
+    (StatusCode::OK, Json(tracker.report(now)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slow_calls_fire_and_resolve_alerts() {
+        let targets = parse_targets("verify:100:99").unwrap();
+        let tracker = SloTracker::new(targets, 14.4, None);
+        let network = Network::BaseSepolia;
+        let start = 60 * 1000;
+        for i in 0..100 {
+            let latency = Duration::from_millis(if i < 80 { 20 } else { 500 });
+            tracker.record(Endpoint::Verify, network, latency, start);
+        }
+        let report = &tracker.report(start)[0];
+        assert_eq!(report.count, 100);
+        assert!((20.0..20.5).contains(&report.p50_ms.unwrap()));
+        assert!((500.0..510.5).contains(&report.p99_ms.unwrap()));
+        assert!((report.burn_rate_1h.unwrap() - 20.0).abs() < 1e-9);
+
+        let alerts = tracker.evaluate(start);
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].status, "firing");
+        assert!(tracker.evaluate(start).is_empty());
+        // Five minutes later, the short window has no slow calls left.
+        let alerts = tracker.evaluate(start + 5 * 60);
+        assert_eq!(alerts[0].status, "resolved");
+
+        assert!(parse_targets("verify:100:100").is_err());
+        assert!(parse_targets("quote:100:99").is_err());
+    }
+}
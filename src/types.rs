@@ -7,16 +7,20 @@
 //! This module supports ERC-3009 style authorization for tokens (EIP-712 typed signatures),
 //! and provides serialization logic compatible with external clients.
 
-use alloy::primitives::{Bytes, U256};
+use aes_gcm_siv::aead::OsRng;
+use aes_gcm_siv::aead::rand_core::RngCore;
+use alloy::primitives::{Address, Bytes, U256};
 use alloy::{hex, sol};
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as b64;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD as b64_url;
 use once_cell::sync::Lazy;
 use regex::Regex;
 use rust_decimal::Decimal;
 use rust_decimal::prelude::{FromPrimitive, Zero};
 use serde::ser::SerializeStruct;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha2::{Digest, Sha256};
 use solana_sdk::bs58;
 use solana_sdk::pubkey::Pubkey;
 use std::borrow::Cow;
@@ -25,21 +29,28 @@ use std::fmt::{Debug, Display, Formatter};
 use std::ops::{Add, Div, Mul, Rem, Sub};
 use std::str::FromStr;
 use url::Url;
+use utoipa::ToSchema;
 
 use crate::network::Network;
 use crate::timestamp::UnixTimestamp;
 
-/// Represents the protocol version. Currently only version 1 is supported.
-#[derive(Debug, Copy, Clone)]
+/// Represents the protocol version.
+///
+/// Version 2 requests use the [`VerifyRequestV2`] envelope; they are converted to the version 1 types on arrival,
+/// and responses are converted back (see [`SettleResponse::into_v2`]).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub enum X402Version {
     /// Version `1`.
     V1,
+    /// Version `2`, with CAIP-2 network identifiers and the resource described once per payload.
+    V2,
 }
 
 impl Serialize for X402Version {
     fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
         match self {
             X402Version::V1 => serializer.serialize_u8(1),
+            X402Version::V2 => serializer.serialize_u8(2),
         }
     }
 }
@@ -48,6 +59,7 @@ impl Display for X402Version {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             X402Version::V1 => write!(f, "1"),
+            X402Version::V2 => write!(f, "2"),
         }
     }
 }
@@ -69,6 +81,7 @@ impl TryFrom<u8> for X402Version {
     fn try_from(value: u8) -> Result<Self, Self::Error> {
         match value {
             1 => Ok(X402Version::V1),
+            2 => Ok(X402Version::V2),
             _ => Err(X402VersionError(value)),
         }
     }
@@ -84,18 +97,43 @@ impl<'de> Deserialize<'de> for X402Version {
     }
 }
 
-/// Enumerates payment schemes. Only "exact" is supported in this implementation,
-/// meaning the amount to be transferred must match exactly.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+/// Enumerates payment schemes.
+///
+/// - `exact`: the amount to be transferred must match exactly, authorized via ERC-3009 on EVM.
+/// - `permit2`: the amount is pulled through Uniswap's Permit2 signature transfer, for ERC-20 tokens
+///   without ERC-3009. EVM only.
+/// - `permit`: the payer approves the facilitator with an EIP-2612 (or DAI-style) `permit` signature,
+///   and the amount is pulled with `transferFrom`. EVM only.
+/// - `native`: the payer pre-signs a transaction sending the native coin (ETH, AVAX…), which the
+///   facilitator broadcasts. EVM only.
+/// - `erc4337`: the payer's smart account signs an ERC-4337 UserOperation transferring the token,
+///   which the facilitator submits through a bundler. EVM only.
+/// - `upto`: the payer signs a Permit2 transfer of at most an amount, and the resource server settles
+///   only the portion it metered, given as `settleAmount`. EVM only.
+/// - `stream`: the payer keeps a Superfluid stream open to `payTo` and signs a claim of it per request;
+///   the facilitator checks the flow rate and solvency, no transfer is made. EVM only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum Scheme {
     Exact,
+    Permit2,
+    Permit,
+    Native,
+    Erc4337,
+    Upto,
+    Stream,
 }
 
 impl Display for Scheme {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let s = match self {
             Scheme::Exact => "exact",
+            Scheme::Permit2 => "permit2",
+            Scheme::Permit => "permit",
+            Scheme::Native => "native",
+            Scheme::Erc4337 => "erc4337",
+            Scheme::Upto => "upto",
+            Scheme::Stream => "stream",
         };
         write!(f, "{s}")
     }
@@ -159,7 +197,10 @@ impl Serialize for EvmSignature {
 ///
 /// Wrapper around `alloy::primitives::Address`, providing display/serialization support.
 /// Used throughout the protocol for typed Ethereum address handling.
-#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, Serialize, Deserialize)]
+///
+/// Parsing accepts all-lowercase and all-uppercase hex, while mixed-case input must carry
+/// a valid EIP-55 checksum. Serializes to the checksummed form.
+#[derive(Debug, Copy, Clone, Eq, Hash, PartialEq, Serialize)]
 pub struct EvmAddress(pub alloy::primitives::Address);
 
 impl Display for EvmAddress {
@@ -168,19 +209,45 @@ impl Display for EvmAddress {
     }
 }
 
-#[derive(Debug, thiserror::Error)]
-#[error("Failed to decode EVM address")]
-pub struct EvmAddressDecodingError;
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum EvmAddressDecodingError {
+    #[error("Failed to decode EVM address: expected 0x followed by 40 hex characters")]
+    InvalidFormat,
+    #[error("Invalid EIP-55 checksum for EVM address, did you mean {expected}?")]
+    InvalidChecksum { expected: String },
+}
 
 impl FromStr for EvmAddress {
     type Err = EvmAddressDecodingError;
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let address =
-            alloy::primitives::Address::from_str(s).map_err(|_| EvmAddressDecodingError)?;
+        let hex = s.strip_prefix("0x").unwrap_or(s);
+        if hex.len() != 40 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(EvmAddressDecodingError::InvalidFormat);
+        }
+        let address = alloy::primitives::Address::from_str(hex)
+            .map_err(|_| EvmAddressDecodingError::InvalidFormat)?;
+        let mixed_case = hex.bytes().any(|b| b.is_ascii_lowercase())
+            && hex.bytes().any(|b| b.is_ascii_uppercase());
+        if mixed_case {
+            let expected = address.to_checksum(None);
+            if expected[2..] != *hex {
+                return Err(EvmAddressDecodingError::InvalidChecksum { expected });
+            }
+        }
         Ok(Self(address))
     }
 }
 
+impl<'de> Deserialize<'de> for EvmAddress {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        EvmAddress::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 impl TryFrom<&str> for EvmAddress {
     type Error = EvmAddressDecodingError;
     fn try_from(value: &str) -> Result<Self, Self::Error> {
@@ -188,6 +255,74 @@ impl TryFrom<&str> for EvmAddress {
     }
 }
 
+/// Tron account address: a 20-byte account like an EVM address, written in base58check with the `0x41`
+/// prefix, e.g. `TR7NHqjeKQxGTCi8q8ZY4pL8otSzgjLj6t`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TronAddress(pub alloy::primitives::Address);
+
+impl TronAddress {
+    const PREFIX: u8 = 0x41;
+
+    /// 21 bytes of the address: the `0x41` prefix and the account.
+    fn prefixed(&self) -> [u8; 21] {
+        let mut bytes = [Self::PREFIX; 21];
+        bytes[1..].copy_from_slice(self.0.as_slice());
+        bytes
+    }
+
+    /// Hex form taken by the Tron HTTP API, e.g. `41a614f803…`.
+    pub fn to_hex(self) -> String {
+        hex::encode(self.prefixed())
+    }
+
+    fn checksum(bytes: &[u8]) -> [u8; 4] {
+        let hash = Sha256::digest(Sha256::digest(bytes));
+        [hash[0], hash[1], hash[2], hash[3]]
+    }
+}
+
+impl FromStr for TronAddress {
+    type Err = MixedAddressError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bytes = bs58::decode(s)
+            .into_vec()
+            .map_err(|_| MixedAddressError::InvalidAddressFormat)?;
+        let [prefixed @ .., c0, c1, c2, c3] = bytes.as_slice() else {
+            return Err(MixedAddressError::InvalidAddressFormat);
+        };
+        if prefixed.len() != 21
+            || prefixed[0] != Self::PREFIX
+            || Self::checksum(prefixed) != [*c0, *c1, *c2, *c3]
+        {
+            return Err(MixedAddressError::InvalidAddressFormat);
+        }
+        Ok(Self(alloy::primitives::Address::from_slice(&prefixed[1..])))
+    }
+}
+
+impl Display for TronAddress {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let prefixed = self.prefixed();
+        let mut bytes = prefixed.to_vec();
+        bytes.extend(Self::checksum(&prefixed));
+        f.write_str(&bs58::encode(bytes).into_string())
+    }
+}
+
+impl Serialize for TronAddress {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for TronAddress {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        TronAddress::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 impl From<EvmAddress> for alloy::primitives::Address {
     fn from(address: EvmAddress) -> Self {
         address.0
@@ -255,7 +390,7 @@ impl Serialize for HexEncodedNonce {
 
 /// EIP-712 structured data for ERC-3009-based authorization.
 /// Defines who can transfer how much USDC and when.
-#[derive(Debug, Copy, Clone, Serialize, Deserialize)]
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ExactEvmPayloadAuthorization {
     pub from: EvmAddress,
@@ -268,35 +403,413 @@ pub struct ExactEvmPayloadAuthorization {
 
 /// Full payload required to authorize an ERC-3009 transfer:
 /// includes the signature and the EIP-712 struct.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ExactEvmPayload {
     pub signature: EvmSignature,
     pub authorization: ExactEvmPayloadAuthorization,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// ERC-3009 function an [`ExactEvmPayload`] is signed for and settled with, from `extra.authorization` of the
+/// payment requirements: `transferWithAuthorization` (the default) or `receiveWithAuthorization`.
+///
+/// Only the recipient of a `receiveWithAuthorization` may submit it, so a signature seen in the mempool can not be
+/// front-run by another contract. `payTo` must then be an address the facilitator settles from.
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Erc3009Authorization {
+    #[default]
+    TransferWithAuthorization,
+    ReceiveWithAuthorization,
+}
+
+impl Erc3009Authorization {
+    /// Function selected by `extra.authorization` of `requirements`.
+    pub fn from_requirements(requirements: &PaymentRequirements) -> Result<Self, String> {
+        match requirements
+            .extra
+            .as_ref()
+            .and_then(|extra| extra.get("authorization"))
+        {
+            Some(value) => serde_json::from_value(value.clone())
+                .map_err(|e| format!("extra.authorization: {e}")),
+            None => Ok(Self::default()),
+        }
+    }
+}
+
+/// TIP-712 transfer authorization signed by a Tron payer, shaped like ERC-3009's but with Tron addresses.
+#[derive(Debug, Copy, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TronAuthorization {
+    pub from: TronAddress,
+    pub to: TronAddress,
+    pub value: TokenAmount,
+    pub valid_after: UnixTimestamp,
+    pub valid_before: UnixTimestamp,
+    pub nonce: HexEncodedNonce,
+}
+
+/// Payload of an `exact` payment on a Tron network: a signed [`TronAuthorization`] of a TRC-20 transfer.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TronPayload {
+    pub signature: EvmSignature,
+    pub authorization: TronAuthorization,
+}
+
+/// Token and maximum amount a Permit2 signature allows to be transferred.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Permit2TokenPermissions {
+    pub token: EvmAddress,
+    pub amount: TokenAmount,
+}
+
+/// EIP-712 `PermitWitnessTransferFrom` message signed by the payer for Permit2, less its [`Permit2Witness`].
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Permit2Permit {
+    pub permitted: Permit2TokenPermissions,
+    /// Address allowed to execute the transfer; one advertised by the facilitator in `/supported`.
+    pub spender: EvmAddress,
+    /// Unordered Permit2 nonce, as a decimal string.
+    pub nonce: TokenAmount,
+    pub deadline: UnixTimestamp,
+}
+
+/// Witness signed along with the Permit2 permit, binding the transfer to its recipient.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Permit2Witness {
+    /// Recipient of the transfer; must be `payTo` of the requirements.
+    pub to: EvmAddress,
+}
+
+/// Payload of the `permit2` scheme: a Permit2 signature transfer authorized by `owner`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct Permit2EvmPayload {
+    pub signature: EvmSignature,
+    pub owner: EvmAddress,
+    pub permit: Permit2Permit,
+    pub witness: Permit2Witness,
+}
+
+/// `permit` message signed by the payer, approving `spender` on the token itself.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PermitEvmAuthorization {
+    pub owner: EvmAddress,
+    /// Address allowed to pull the tokens; one advertised by the facilitator in `/supported`.
+    pub spender: EvmAddress,
+    /// Approved amount of an EIP-2612 permit. Omitted for DAI-style permits, which approve
+    /// an unlimited amount (`allowed = true`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<TokenAmount>,
+    /// Current `nonces(owner)` of the token, as a decimal string.
+    pub nonce: TokenAmount,
+    /// EIP-2612 `deadline`, or DAI `expiry` where `0` means no expiry.
+    pub deadline: UnixTimestamp,
+}
+
+/// Payload of the `permit` scheme: a token `permit` signature, followed by `transferFrom` at settlement.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PermitEvmPayload {
+    pub signature: EvmSignature,
+    pub permit: PermitEvmAuthorization,
+}
+
+/// Payload of the `native` scheme: a signed EVM transaction paying the native coin to `payTo`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NativeEvmPayload {
+    /// EIP-2718 encoded signed transaction, as a 0x-prefixed hex string.
+    pub signed_transaction: String,
+}
+
+/// ERC-4337 UserOperation for EntryPoint v0.7, in the unpacked form used by bundler JSON-RPC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperation {
+    #[schema(value_type = String)]
+    pub sender: Address,
+    #[schema(value_type = String)]
+    pub nonce: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub factory: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub factory_data: Option<Bytes>,
+    #[schema(value_type = String)]
+    pub call_data: Bytes,
+    #[schema(value_type = String)]
+    pub call_gas_limit: U256,
+    #[schema(value_type = String)]
+    pub verification_gas_limit: U256,
+    #[schema(value_type = String)]
+    pub pre_verification_gas: U256,
+    #[schema(value_type = String)]
+    pub max_fee_per_gas: U256,
+    #[schema(value_type = String)]
+    pub max_priority_fee_per_gas: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub paymaster: Option<Address>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub paymaster_verification_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub paymaster_post_op_gas_limit: Option<U256>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<String>)]
+    pub paymaster_data: Option<Bytes>,
+    #[schema(value_type = String)]
+    pub signature: Bytes,
+}
+
+/// Payload of the `erc4337` scheme: a signed UserOperation whose call transfers the token to `payTo`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct UserOperationEvmPayload {
+    pub user_operation: UserOperation,
+    pub entry_point: EvmAddress,
+}
+
+/// Payload of the `stream` scheme: a claim, signed by `sender`, of its Superfluid stream to `payTo`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamEvmPayload {
+    pub sender: EvmAddress,
+    /// EIP-191 signature of the claim message, see [`crate::chain::stream::claim_message`].
+    pub signature: EvmSignature,
+    pub valid_before: UnixTimestamp,
+}
+
+/// Payload of an `exact` payment on a Lightning network: a paid BOLT11 invoice and its preimage.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct LightningPayload {
+    pub invoice: String,
+    /// Hex-encoded payment preimage, revealed to the payer once the invoice is paid.
+    pub preimage: String,
+}
+
+/// Payload of an `exact` payment on a NEAR network: a NEP-366 signed delegate action calling `ft_transfer`.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct NearPayload {
+    /// Base64-encoded borsh `SignedDelegateAction`, relayed by the facilitator as a meta-transaction.
+    pub signed_delegate_action: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ExactSolanaPayload {
     pub transaction: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Deserializes as the first variant the payload is valid for, like an untagged enum. If it is valid for none, the
+/// error is that of the variant failing deepest into the payload, with the JSON path of the offending field, rather
+/// than "data did not match any variant".
+#[derive(Debug, Clone, Serialize, ToSchema)]
 #[serde(untagged)]
 pub enum ExactPaymentPayload {
     Evm(ExactEvmPayload),
+    Tron(TronPayload),
+    Permit2(Permit2EvmPayload),
+    Permit(PermitEvmPayload),
+    Native(NativeEvmPayload),
+    UserOperation(Box<UserOperationEvmPayload>),
+    Stream(StreamEvmPayload),
+    Lightning(LightningPayload),
+    Near(NearPayload),
     Solana(ExactSolanaPayload),
 }
 
+impl<'de> Deserialize<'de> for ExactPaymentPayload {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        type Attempt = dyn Fn(&serde_json::Value) -> Result<ExactPaymentPayload, PathError>;
+        fn variant<'a, T: Deserialize<'a>>(
+            value: &'a serde_json::Value,
+            wrap: fn(T) -> ExactPaymentPayload,
+        ) -> Result<ExactPaymentPayload, PathError> {
+            serde_path_to_error::deserialize(value).map(wrap)
+        }
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let attempts: [&Attempt; 10] = [
+            &|value| variant(value, Self::Evm),
+            &|value| variant(value, Self::Tron),
+            &|value| variant(value, Self::Permit2),
+            &|value| variant(value, Self::Permit),
+            &|value| variant(value, Self::Native),
+            &|value| variant(value, |payload| Self::UserOperation(Box::new(payload))),
+            &|value| variant(value, Self::Stream),
+            &|value| variant(value, Self::Lightning),
+            &|value| variant(value, Self::Near),
+            &|value| variant(value, Self::Solana),
+        ];
+        let mut deepest: Option<PathError> = None;
+        for attempt in attempts {
+            match attempt(&value) {
+                Ok(payload) => return Ok(payload),
+                Err(e)
+                    if deepest
+                        .as_ref()
+                        .is_none_or(|d| e.path().iter().count() > d.path().iter().count()) =>
+                {
+                    deepest = Some(e)
+                }
+                Err(_) => {}
+            }
+        }
+        let e = deepest.expect("every variant was attempted");
+        Err(serde::de::Error::custom(path_error_message(e)))
+    }
+}
+
+impl ExactPaymentPayload {
+    /// Time the signed authorization expires at, for payloads stating one.
+    pub fn valid_before(&self) -> Option<UnixTimestamp> {
+        match self {
+            ExactPaymentPayload::Evm(payload) => Some(payload.authorization.valid_before),
+            ExactPaymentPayload::Tron(payload) => Some(payload.authorization.valid_before),
+            ExactPaymentPayload::Permit2(payload) => Some(payload.permit.deadline),
+            // A DAI-style permit with a zero expiry never expires.
+            ExactPaymentPayload::Permit(payload) if payload.permit.deadline.0 == 0 => {
+                Some(UnixTimestamp(u64::MAX))
+            }
+            ExactPaymentPayload::Permit(payload) => Some(payload.permit.deadline),
+            ExactPaymentPayload::Stream(payload) => Some(payload.valid_before),
+            ExactPaymentPayload::Native(_)
+            | ExactPaymentPayload::UserOperation(_)
+            | ExactPaymentPayload::Lightning(_)
+            | ExactPaymentPayload::Near(_)
+            | ExactPaymentPayload::Solana(_) => None,
+        }
+    }
+}
+
+type PathError = serde_path_to_error::Error<serde_json::Error>;
+
+/// Deserializes `value`, buffered by a type told apart by its contents, with the JSON path of a failing field in the
+/// error message, which would otherwise be lost with the buffer.
+fn from_value_at_path<T: serde::de::DeserializeOwned>(
+    value: serde_json::Value,
+) -> Result<T, String> {
+    serde_path_to_error::deserialize(value).map_err(path_error_message)
+}
+
+/// `{path}: {message}` of `e`, joining the path of `e` with the one a nested buffered value put in front of its
+/// message, e.g. `paymentPayload.payload.authorization.to: Invalid EIP-55 checksum…`.
+fn path_error_message(e: PathError) -> String {
+    let outer = e.path().to_string();
+    let message = e.into_inner().to_string();
+    let is_path = |path: &str| {
+        !path.is_empty()
+            && path
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '[' | ']'))
+    };
+    let (inner, message) = match message.split_once(": ") {
+        Some((inner, message)) if is_path(inner) => (Some(inner), message),
+        _ => (None, message.as_str()),
+    };
+    match (outer.as_str(), inner) {
+        (".", None) => message.to_string(),
+        (".", Some(inner)) => format!("{inner}: {message}"),
+        (outer, None) => format!("{outer}: {message}"),
+        (outer, Some(inner)) if inner.starts_with('[') => format!("{outer}{inner}: {message}"),
+        (outer, Some(inner)) => format!("{outer}.{inner}: {message}"),
+    }
+}
+
 /// Describes a signed request to transfer a specific amount of funds on-chain.
 /// Includes the scheme, network, and signed payload contents.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Also deserializes from a version 2 [`PaymentPayloadV2`], e.g. in an `X-PAYMENT` header.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[serde(from = "VersionedPaymentPayload")]
 pub struct PaymentPayload {
     pub x402_version: X402Version,
     pub scheme: Scheme,
     pub network: Network,
     pub payload: ExactPaymentPayload,
+    /// Correlation id copied from the [`PaymentRequirements`] the payment answers.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<PaymentId>,
+}
+
+/// [`PaymentPayload`] in either version, told apart by the `accepted` requirements of version 2.
+#[allow(clippy::large_enum_variant)] // Converted to a PaymentPayload right after deserialization
+enum VersionedPaymentPayload {
+    V2(PaymentPayloadV2),
+    V1(PaymentPayloadV1),
+}
+
+impl<'de> Deserialize<'de> for VersionedPaymentPayload {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let payload = if value.get("accepted").is_some() {
+            from_value_at_path(value).map(VersionedPaymentPayload::V2)
+        } else {
+            from_value_at_path(value).map(VersionedPaymentPayload::V1)
+        };
+        payload.map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PaymentPayloadV1 {
+    x402_version: X402Version,
+    scheme: Scheme,
+    network: Network,
+    payload: ExactPaymentPayload,
+    #[serde(default)]
+    payment_id: Option<PaymentId>,
+}
+
+impl From<VersionedPaymentPayload> for PaymentPayload {
+    fn from(value: VersionedPaymentPayload) -> Self {
+        match value {
+            VersionedPaymentPayload::V2(payload) => payload.into(),
+            VersionedPaymentPayload::V1(payload) => PaymentPayload {
+                x402_version: payload.x402_version,
+                scheme: payload.scheme,
+                network: payload.network,
+                payload: payload.payload,
+                payment_id: payload.payment_id,
+            },
+        }
+    }
+}
+
+/// Correlation id of a payment, e.g. `pay_3f9c…`, generated when the 402 response is issued.
+///
+/// It travels with the requirements and the `X-PAYMENT` header through verification and settlement,
+/// and comes back in the settlement receipt, so one key ties the client, server, facilitator and chain together.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PaymentId(pub String);
+
+impl PaymentId {
+    /// A fresh random id.
+    pub fn random() -> Self {
+        let mut bytes = [0u8; 16];
+        OsRng.fill_bytes(&mut bytes);
+        Self(format!("pay_{}", hex::encode(bytes)))
+    }
+}
+
+impl Display for PaymentId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
 }
 
 /// Error returned when decoding a base64-encoded [`PaymentPayload`] fails.
@@ -329,6 +842,9 @@ impl TryFrom<Base64Bytes<'_>> for PaymentPayload {
 
 /// A precise on-chain token amount in base units (e.g., USDC with 6 decimals).
 /// Represented as a stringified `U256` in JSON to prevent precision loss.
+///
+/// Deserializes from either a decimal string or a non-negative JSON integer, as some clients
+/// emit numbers; always serializes as a decimal string, like the TypeScript SDK.
 #[derive(Debug, Copy, Clone, PartialEq, Ord, PartialOrd, Eq, Hash)]
 pub struct TokenAmount(pub U256);
 
@@ -605,9 +1121,46 @@ impl Zero for TokenAmount {
 
 impl<'de> Deserialize<'de> for TokenAmount {
     fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
-        let string = String::deserialize(deserializer)?;
-        let value = U256::from_str(&string).map_err(serde::de::Error::custom)?;
-        Ok(TokenAmount(value))
+        struct TokenAmountVisitor;
+
+        impl serde::de::Visitor<'_> for TokenAmountVisitor {
+            type Value = TokenAmount;
+
+            fn expecting(&self, f: &mut Formatter) -> fmt::Result {
+                f.write_str("a non-negative integer amount as a decimal string or a JSON integer")
+            }
+
+            fn visit_str<E: serde::de::Error>(self, v: &str) -> Result<Self::Value, E> {
+                if v.is_empty() || !v.bytes().all(|b| b.is_ascii_digit()) {
+                    return Err(E::custom(format!("invalid decimal amount: {v:?}")));
+                }
+                U256::from_str_radix(v, 10)
+                    .map(TokenAmount)
+                    .map_err(|_| E::custom(format!("amount overflows uint256: {v}")))
+            }
+
+            fn visit_u64<E: serde::de::Error>(self, v: u64) -> Result<Self::Value, E> {
+                Ok(TokenAmount(U256::from(v)))
+            }
+
+            fn visit_u128<E: serde::de::Error>(self, v: u128) -> Result<Self::Value, E> {
+                Ok(TokenAmount(U256::from(v)))
+            }
+
+            fn visit_i64<E: serde::de::Error>(self, v: i64) -> Result<Self::Value, E> {
+                u64::try_from(v)
+                    .map(|v| TokenAmount(U256::from(v)))
+                    .map_err(|_| E::custom(format!("amount must not be negative: {v}")))
+            }
+
+            fn visit_f64<E: serde::de::Error>(self, v: f64) -> Result<Self::Value, E> {
+                Err(E::custom(format!(
+                    "amount {v} is not an exact integer, send it as a decimal string"
+                )))
+            }
+        }
+
+        deserializer.deserialize_any(TokenAmountVisitor)
     }
 }
 
@@ -635,7 +1188,8 @@ impl From<u64> for TokenAmount {
     }
 }
 
-/// Represents either an EVM address (0x...), or an off-chain address, or Solana address.
+/// Represents either an EVM address (0x...), or an off-chain address, or Solana address, or NEAR account ID,
+/// or Tron address.
 /// The format is used for routing settlement.
 #[derive(Debug, Hash, Clone, PartialEq, Eq)]
 pub enum MixedAddress {
@@ -644,6 +1198,10 @@ pub enum MixedAddress {
     /// Off-chain address in `^[A-Za-z0-9][A-Za-z0-9-]{0,34}[A-Za-z0-9]$` format.
     Offchain(String),
     Solana(Pubkey),
+    /// NEAR account ID: a named account with a dot, e.g. `alice.near`, or a 64-character hex implicit account.
+    Near(String),
+    /// Tron address, base58check starting with `T`.
+    Tron(TronAddress),
 }
 
 #[macro_export]
@@ -682,6 +1240,8 @@ impl TryFrom<MixedAddress> for alloy::primitives::Address {
             MixedAddress::Evm(address) => Ok(address.into()),
             MixedAddress::Offchain(_) => Err(MixedAddressError::NotEvmAddress),
             MixedAddress::Solana(_) => Err(MixedAddressError::NotEvmAddress),
+            MixedAddress::Near(_) => Err(MixedAddressError::NotEvmAddress),
+            MixedAddress::Tron(_) => Err(MixedAddressError::NotEvmAddress),
         }
     }
 }
@@ -708,6 +1268,8 @@ impl TryInto<EvmAddress> for MixedAddress {
             MixedAddress::Evm(address) => Ok(address),
             MixedAddress::Offchain(_) => Err(MixedAddressError::NotEvmAddress),
             MixedAddress::Solana(_) => Err(MixedAddressError::NotEvmAddress),
+            MixedAddress::Near(_) => Err(MixedAddressError::NotEvmAddress),
+            MixedAddress::Tron(_) => Err(MixedAddressError::NotEvmAddress),
         }
     }
 }
@@ -718,6 +1280,8 @@ impl Display for MixedAddress {
             MixedAddress::Evm(address) => write!(f, "{address}"),
             MixedAddress::Offchain(address) => write!(f, "{address}"),
             MixedAddress::Solana(pubkey) => write!(f, "{pubkey}"),
+            MixedAddress::Near(account_id) => write!(f, "{account_id}"),
+            MixedAddress::Tron(address) => write!(f, "{address}"),
         }
     }
 }
@@ -731,17 +1295,33 @@ impl<'de> Deserialize<'de> for MixedAddress {
             Regex::new(r"^[A-Za-z0-9][A-Za-z0-9-]{0,34}[A-Za-z0-9]$")
                 .expect("Invalid regex for offchain address")
         });
+        static NEAR_ACCOUNT_ID_REGEX: Lazy<Regex> = Lazy::new(|| {
+            Regex::new(r"^(([a-z\d]+[-_])*[a-z\d]+\.)+([a-z\d]+[-_])*[a-z\d]+$|^[0-9a-f]{64}$")
+                .expect("Invalid regex for NEAR account ID")
+        });
 
         let s = String::deserialize(deserializer)?;
         // 1) EVM address (e.g., 0x... 20 bytes, hex)
-        if let Ok(addr) = EvmAddress::from_str(&s) {
-            return Ok(MixedAddress::Evm(addr));
+        match EvmAddress::from_str(&s) {
+            Ok(addr) => return Ok(MixedAddress::Evm(addr)),
+            Err(e @ EvmAddressDecodingError::InvalidChecksum { .. }) => {
+                return Err(serde::de::Error::custom(e));
+            }
+            Err(EvmAddressDecodingError::InvalidFormat) => {}
         }
         // 2) Solana Pubkey (base58, 32 bytes)
         if let Ok(pk) = Pubkey::from_str(&s) {
             return Ok(MixedAddress::Solana(pk));
         }
-        // 3) Off-chain address by regex
+        // 3) Tron address (base58check, 0x41 prefix)
+        if let Ok(address) = TronAddress::from_str(&s) {
+            return Ok(MixedAddress::Tron(address));
+        }
+        // 4) NEAR account ID, named with at least one dot or implicit
+        if s.len() <= 64 && NEAR_ACCOUNT_ID_REGEX.is_match(&s) {
+            return Ok(MixedAddress::Near(s));
+        }
+        // 5) Off-chain address by regex
         if OFFCHAIN_ADDRESS_REGEX.is_match(&s) {
             return Ok(MixedAddress::Offchain(s));
         }
@@ -758,6 +1338,8 @@ impl Serialize for MixedAddress {
             MixedAddress::Evm(addr) => serializer.serialize_str(&addr.to_string()),
             MixedAddress::Offchain(s) => serializer.serialize_str(s),
             MixedAddress::Solana(pubkey) => serializer.serialize_str(pubkey.to_string().as_str()),
+            MixedAddress::Near(account_id) => serializer.serialize_str(account_id),
+            MixedAddress::Tron(address) => serializer.collect_str(address),
         }
     }
 }
@@ -767,6 +1349,8 @@ pub enum TransactionHash {
     /// A 32-byte EVM transaction hash, encoded as 0x-prefixed hex string.
     Evm([u8; 32]),
     Solana([u8; 64]),
+    /// A 32-byte NEAR transaction hash, encoded as base58 string.
+    Near([u8; 32]),
 }
 
 impl<'de> Deserialize<'de> for TransactionHash {
@@ -786,12 +1370,14 @@ impl<'de> Deserialize<'de> for TransactionHash {
             return Ok(TransactionHash::Evm(array));
         }
 
-        // Solana: base58 string, decodes to exactly 64 bytes
-        if let Ok(bytes) = bs58::decode(&s).into_vec()
-            && bytes.len() == 64
-        {
-            let array: [u8; 64] = bytes.try_into().unwrap(); // safe after length check
-            return Ok(TransactionHash::Solana(array));
+        // Solana: base58 string, decodes to exactly 64 bytes; NEAR: base58 string of 32 bytes
+        if let Ok(bytes) = bs58::decode(&s).into_vec() {
+            if let Ok(array) = <[u8; 64]>::try_from(bytes.as_slice()) {
+                return Ok(TransactionHash::Solana(array));
+            }
+            if let Ok(array) = <[u8; 32]>::try_from(bytes.as_slice()) {
+                return Ok(TransactionHash::Near(array));
+            }
         }
 
         Err(serde::de::Error::custom("Invalid transaction hash format"))
@@ -809,6 +1395,10 @@ impl Serialize for TransactionHash {
                 let b58_string = bs58::encode(bytes).into_string();
                 serializer.serialize_str(&b58_string)
             }
+            TransactionHash::Near(bytes) => {
+                let b58_string = bs58::encode(bytes).into_string();
+                serializer.serialize_str(&b58_string)
+            }
         }
     }
 }
@@ -822,13 +1412,16 @@ impl Display for TransactionHash {
             TransactionHash::Solana(bytes) => {
                 write!(f, "{}", bs58::encode(bytes).into_string())
             }
+            TransactionHash::Near(bytes) => {
+                write!(f, "{}", bs58::encode(bytes).into_string())
+            }
         }
     }
 }
 
 /// Requirements set by the payment-gated endpoint for an acceptable payment.
 /// This includes min/max amounts, recipient, asset, network, and metadata.
-#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct PaymentRequirements {
     pub scheme: Scheme,
@@ -843,6 +1436,15 @@ pub struct PaymentRequirements {
     pub max_timeout_seconds: u64,
     pub asset: MixedAddress,
     pub extra: Option<serde_json::Value>,
+    /// Correlation id assigned when the 402 response was issued, echoed back by the client.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<PaymentId>,
+    /// Price in US dollars `maxAmountRequired` must match, checked with the facilitator's price oracle.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_usd: Option<MoneyAmount>,
+    /// Part of `maxAmountRequired` owed to the facilitator, at least the fee it charges the payer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facilitator_fee: Option<TokenAmount>,
 }
 
 impl PaymentRequirements {
@@ -874,12 +1476,28 @@ impl PaymentRequirements {
 
 /// Wrapper for a payment payload and requirements sent by the client to a facilitator
 /// to be verified.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Deserializes from both the version 1 envelope and the version 2 one ([`VerifyRequestV2`]), keeping
+/// `x402_version` so the response can be given in the version of the request. Always serializes as version 1.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
+#[serde(try_from = "VersionedVerifyRequest")]
 pub struct VerifyRequest {
     pub x402_version: X402Version,
     pub payment_payload: PaymentPayload,
     pub payment_requirements: PaymentRequirements,
+    /// Amount to settle for the `upto` scheme, at most the signed maximum; the full
+    /// `maxAmountRequired` if unset. Settlement only, rejected for other schemes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settle_amount: Option<TokenAmount>,
+    /// For the `upto` scheme, whether the resource server accepts settling only what the payer's balance and
+    /// allowance cover when they fall short, the response reporting the shortfall. Settlement only.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_partial: bool,
+    /// Time before which the payment must not be settled, within the expiry of its authorization. The facilitator
+    /// holds such settlements in its queue until then. Settlement only.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_after: Option<UnixTimestamp>,
 }
 
 impl Display for VerifyRequest {
@@ -896,13 +1514,256 @@ impl VerifyRequest {
     pub fn network(&self) -> Network {
         self.payment_payload.network
     }
+
+    /// Correlation id of the payment, from the payload or else the requirements.
+    pub fn payment_id(&self) -> Option<&PaymentId> {
+        self.payment_payload
+            .payment_id
+            .as_ref()
+            .or(self.payment_requirements.payment_id.as_ref())
+    }
 }
 
 /// Wrapper for a payment payload and requirements sent by the client
 /// to be used for settlement.
 pub type SettleRequest = VerifyRequest;
 
-#[derive(Debug, Serialize, Deserialize, thiserror::Error)]
+/// [`VerifyRequest`] in either envelope, told apart by the `accepted` requirements of version 2 payloads.
+enum VersionedVerifyRequest {
+    V2(VerifyRequestV2),
+    V1(VerifyRequestV1),
+}
+
+impl<'de> Deserialize<'de> for VersionedVerifyRequest {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        let request = if value.pointer("/paymentPayload/accepted").is_some() {
+            from_value_at_path(value).map(VersionedVerifyRequest::V2)
+        } else {
+            from_value_at_path(value).map(VersionedVerifyRequest::V1)
+        };
+        request.map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VerifyRequestV1 {
+    x402_version: X402Version,
+    payment_payload: PaymentPayload,
+    payment_requirements: PaymentRequirements,
+    #[serde(default)]
+    settle_amount: Option<TokenAmount>,
+    #[serde(default)]
+    allow_partial: bool,
+    #[serde(default)]
+    execute_after: Option<UnixTimestamp>,
+}
+
+impl TryFrom<VersionedVerifyRequest> for VerifyRequest {
+    type Error = X402ConversionError;
+
+    fn try_from(value: VersionedVerifyRequest) -> Result<Self, Self::Error> {
+        match value {
+            VersionedVerifyRequest::V2(request) => request.try_into(),
+            VersionedVerifyRequest::V1(request) => Ok(VerifyRequest {
+                x402_version: request.x402_version,
+                payment_payload: request.payment_payload,
+                payment_requirements: request.payment_requirements,
+                settle_amount: request.settle_amount,
+                allow_partial: request.allow_partial,
+                execute_after: request.execute_after,
+            }),
+        }
+    }
+}
+
+/// A version 2 envelope that has no version 1 equivalent.
+#[derive(Debug, thiserror::Error)]
+pub enum X402ConversionError {
+    #[error("x402Version 2 payload without resource")]
+    MissingResource,
+    /// The requirements the payer accepted are not those the payment is verified against.
+    #[error("accepted {0} differs from paymentRequirements")]
+    AcceptedMismatch(&'static str),
+}
+
+/// Resource a version 2 payment pays for, described once instead of in every requirement.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResourceInfo {
+    pub url: Url,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub mime_type: String,
+}
+
+impl From<&PaymentRequirements> for ResourceInfo {
+    fn from(requirements: &PaymentRequirements) -> Self {
+        Self {
+            url: requirements.resource.clone(),
+            description: requirements.description.clone(),
+            mime_type: requirements.mime_type.clone(),
+        }
+    }
+}
+
+/// Version 2 [`PaymentRequirements`]: the network is a CAIP-2 chain ID, the amount is `amount`, and the resource
+/// moved to [`ResourceInfo`].
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentRequirementsV2 {
+    pub scheme: Scheme,
+    #[serde(with = "crate::network::caip2")]
+    pub network: Network,
+    pub amount: TokenAmount,
+    pub asset: MixedAddress,
+    pub pay_to: MixedAddress,
+    pub max_timeout_seconds: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra: Option<serde_json::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<PaymentId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price_usd: Option<MoneyAmount>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facilitator_fee: Option<TokenAmount>,
+}
+
+impl From<&PaymentRequirements> for PaymentRequirementsV2 {
+    fn from(requirements: &PaymentRequirements) -> Self {
+        Self {
+            scheme: requirements.scheme,
+            network: requirements.network,
+            amount: requirements.max_amount_required,
+            asset: requirements.asset.clone(),
+            pay_to: requirements.pay_to.clone(),
+            max_timeout_seconds: requirements.max_timeout_seconds,
+            extra: requirements.extra.clone(),
+            payment_id: requirements.payment_id.clone(),
+            price_usd: requirements.price_usd.clone(),
+            facilitator_fee: requirements.facilitator_fee,
+        }
+    }
+}
+
+impl PaymentRequirementsV2 {
+    /// Version 1 requirements for `resource`.
+    pub fn into_v1(self, resource: ResourceInfo) -> PaymentRequirements {
+        PaymentRequirements {
+            scheme: self.scheme,
+            network: self.network,
+            max_amount_required: self.amount,
+            resource: resource.url,
+            description: resource.description,
+            mime_type: resource.mime_type,
+            output_schema: None,
+            pay_to: self.pay_to,
+            max_timeout_seconds: self.max_timeout_seconds,
+            asset: self.asset,
+            extra: self.extra,
+            payment_id: self.payment_id,
+            price_usd: self.price_usd,
+            facilitator_fee: self.facilitator_fee,
+        }
+    }
+}
+
+/// Version 2 [`PaymentPayload`], carrying the requirements it accepted instead of scheme and network.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PaymentPayloadV2 {
+    pub x402_version: X402Version,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resource: Option<ResourceInfo>,
+    pub accepted: PaymentRequirementsV2,
+    pub payload: ExactPaymentPayload,
+}
+
+impl From<PaymentPayloadV2> for PaymentPayload {
+    fn from(payload: PaymentPayloadV2) -> Self {
+        PaymentPayload {
+            x402_version: payload.x402_version,
+            scheme: payload.accepted.scheme,
+            network: payload.accepted.network,
+            payload: payload.payload,
+            payment_id: payload.accepted.payment_id,
+        }
+    }
+}
+
+/// Version 2 [`VerifyRequest`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyRequestV2 {
+    pub x402_version: X402Version,
+    pub payment_payload: PaymentPayloadV2,
+    pub payment_requirements: PaymentRequirementsV2,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settle_amount: Option<TokenAmount>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub allow_partial: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execute_after: Option<UnixTimestamp>,
+}
+
+impl TryFrom<VerifyRequestV2> for VerifyRequest {
+    type Error = X402ConversionError;
+
+    fn try_from(request: VerifyRequestV2) -> Result<Self, Self::Error> {
+        let resource = request
+            .payment_payload
+            .resource
+            .clone()
+            .ok_or(X402ConversionError::MissingResource)?;
+        let (accepted, requirements) = (
+            &request.payment_payload.accepted,
+            &request.payment_requirements,
+        );
+        let mismatch = [
+            ("scheme", accepted.scheme == requirements.scheme),
+            ("network", accepted.network == requirements.network),
+            ("payTo", accepted.pay_to == requirements.pay_to),
+            ("asset", accepted.asset == requirements.asset),
+            ("amount", accepted.amount == requirements.amount),
+        ]
+        .into_iter()
+        .find(|(_, matches)| !matches);
+        if let Some((field, _)) = mismatch {
+            return Err(X402ConversionError::AcceptedMismatch(field));
+        }
+        Ok(VerifyRequest {
+            x402_version: X402Version::V2,
+            payment_requirements: request.payment_requirements.into_v1(resource),
+            payment_payload: request.payment_payload.into(),
+            settle_amount: request.settle_amount,
+            allow_partial: request.allow_partial,
+            execute_after: request.execute_after,
+        })
+    }
+}
+
+impl From<&VerifyRequest> for VerifyRequestV2 {
+    fn from(request: &VerifyRequest) -> Self {
+        let requirements = PaymentRequirementsV2::from(&request.payment_requirements);
+        VerifyRequestV2 {
+            x402_version: X402Version::V2,
+            payment_payload: PaymentPayloadV2 {
+                x402_version: X402Version::V2,
+                resource: Some(ResourceInfo::from(&request.payment_requirements)),
+                accepted: requirements.clone(),
+                payload: request.payment_payload.payload.clone(),
+            },
+            payment_requirements: requirements,
+            settle_amount: request.settle_amount,
+            allow_partial: request.allow_partial,
+            execute_after: request.execute_after,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, thiserror::Error)]
 #[serde(untagged, rename_all = "camelCase")]
 pub enum FacilitatorErrorReason {
     /// Payer doesn't have sufficient funds.
@@ -921,22 +1782,191 @@ pub enum FacilitatorErrorReason {
     #[error("unexpected_settle_error")]
     #[serde(rename = "unexpected_settle_error")]
     UnexpectedSettleError,
+    /// Payer has not approved the Permit2 contract for enough of the token.
+    #[error("insufficient_allowance")]
+    #[serde(rename = "insufficient_allowance")]
+    InsufficientAllowance,
+    /// The payment reuses a nonce that was already settled.
+    #[error("nonce_reused")]
+    #[serde(rename = "nonce_reused")]
+    NonceReused,
+    /// The payment reuses the nonce of another one the facilitator is settling or has settled.
+    #[error("nonce_replayed")]
+    #[serde(rename = "nonce_replayed")]
+    NonceReplayed,
+    /// The token has paused all transfers.
+    #[error("token_paused")]
+    #[serde(rename = "token_paused")]
+    TokenPaused,
+    /// The authorization is not valid yet (`validAfter` in the future).
+    #[error("invalid_exact_evm_payload_authorization_valid_after")]
+    #[serde(rename = "invalid_exact_evm_payload_authorization_valid_after")]
+    InvalidValidAfter,
+    /// The authorization has expired (`validBefore` in the past).
+    #[error("invalid_exact_evm_payload_authorization_valid_before")]
+    #[serde(rename = "invalid_exact_evm_payload_authorization_valid_before")]
+    InvalidValidBefore,
+    /// The token rejected the signature of the authorization.
+    #[error("invalid_exact_evm_payload_signature")]
+    #[serde(rename = "invalid_exact_evm_payload_signature")]
+    InvalidSignature,
+    /// The token blacklisted the payer or the recipient.
+    #[error("address_blacklisted")]
+    #[serde(rename = "address_blacklisted")]
+    AddressBlacklisted,
+    /// The requested asset is not accepted by the facilitator on this network.
+    #[error("unsupported_asset")]
+    #[serde(rename = "unsupported_asset")]
+    UnsupportedAsset,
+    /// Settlement is waiting for human approval.
+    #[error("pending_approval")]
+    #[serde(rename = "pending_approval")]
+    PendingApproval,
+    /// Settlement is accepted and carried out in the background, to be followed by its `settlementId`.
+    #[error("settlement_pending")]
+    #[serde(rename = "settlement_pending")]
+    SettlementPending,
+    /// The `settleAmount` exceeds the signed maximum, or it or `allowPartial` is given for a scheme other than `upto`.
+    #[error("invalid_settle_amount")]
+    #[serde(rename = "invalid_settle_amount")]
+    InvalidSettleAmount,
+    /// The `executeAfter` of a settlement is not before the expiry of its authorization, or the payload has none.
+    #[error("invalid_execute_after")]
+    #[serde(rename = "invalid_execute_after")]
+    InvalidExecuteAfter,
+    /// The request breaks a verification rule or plugin set by the facilitator operator.
+    #[error("rule_violation")]
+    #[serde(rename = "rule_violation")]
+    RuleViolation,
+    /// Settling the payment would exceed the payer's daily budget.
+    #[error("budget_exceeded")]
+    #[serde(rename = "budget_exceeded")]
+    BudgetExceeded,
+    /// `maxAmountRequired` does not match `priceUsd` at the current price of the asset.
+    #[error("price_out_of_tolerance")]
+    #[serde(rename = "price_out_of_tolerance")]
+    PriceOutOfTolerance,
+    /// The token deducts a fee on transfer, so `payTo` would receive less than the signed value.
+    #[error("fee_on_transfer")]
+    #[serde(rename = "fee_on_transfer")]
+    FeeOnTransfer,
+    /// `facilitatorFee` of the requirements is below the fee the facilitator charges the payer.
+    #[error("insufficient_fee")]
+    #[serde(rename = "insufficient_fee")]
+    InsufficientFee,
     #[error("{0}")]
     FreeForm(String),
 }
 
 /// Returned from a facilitator after attempting to settle a payment on-chain.
 /// Indicates success/failure, transaction hash, and payer identity.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SettleResponse {
     pub success: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_reason: Option<FacilitatorErrorReason>,
-    pub payer: MixedAddress,
+    /// Account that paid, unless the payment was rejected before it was known.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<MixedAddress>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub transaction: Option<TransactionHash>,
     pub network: Network,
+    /// Version tag of the facilitator build that produced this receipt, e.g. `0.9.0+4b29d0f`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facilitator_version: Option<String>,
+    /// Position of this payment in a batched settlement transaction, if it was batched.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchPosition>,
+    /// Correlation id of the settled payment, if the request carried one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<PaymentId>,
+    /// Amounts of an `upto` settlement that moved less than requested, see [`VerifyRequest::allow_partial`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<PartialSettlement>,
+    /// Why the settlement would revert, if simulating it showed so and it was not broadcast.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    /// Account that sent the settlement transaction: one of the facilitator's signers, or its relayer.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<MixedAddress>,
+    /// ID of the settlement, to follow it at `GET /settlements/{id}`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement_id: Option<String>,
+    /// Whether the settlement was only simulated by a dry-run facilitator, and nothing was broadcast.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub simulated: bool,
+}
+
+impl SettleResponse {
+    /// The response in the version 2 format, with a CAIP-2 network.
+    pub fn into_v2(self) -> SettleResponseV2 {
+        SettleResponseV2 {
+            success: self.success,
+            error_reason: self.error_reason,
+            payer: self.payer,
+            transaction: self.transaction,
+            network: self.network,
+            facilitator_version: self.facilitator_version,
+            batch: self.batch,
+            payment_id: self.payment_id,
+            partial: self.partial,
+            revert_reason: self.revert_reason,
+            signer: self.signer,
+            settlement_id: self.settlement_id,
+            simulated: self.simulated,
+        }
+    }
+}
+
+/// Version 2 [`SettleResponse`].
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettleResponseV2 {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error_reason: Option<FacilitatorErrorReason>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payer: Option<MixedAddress>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction: Option<TransactionHash>,
+    #[serde(with = "crate::network::caip2")]
+    pub network: Network,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub facilitator_version: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub batch: Option<BatchPosition>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub payment_id: Option<PaymentId>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub partial: Option<PartialSettlement>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub revert_reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signer: Option<MixedAddress>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settlement_id: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub simulated: bool,
+}
+
+/// Amounts of a partial `upto` settlement, in the token's smallest unit.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialSettlement {
+    /// Amount transferred to `payTo`.
+    pub settled_amount: TokenAmount,
+    /// Part of the requested amount that could not be settled.
+    pub shortfall: TokenAmount,
+}
+
+/// Position of a payment among the transfers of a Multicall3 batch settlement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct BatchPosition {
+    /// Index of the payment's call in the batch transaction.
+    pub index: u32,
+    /// Number of payments settled by the transaction.
+    pub size: u32,
 }
 
 /// Error returned when encoding a [`SettleResponse`] into base64 fails.
@@ -972,7 +2002,11 @@ impl TryInto<Base64Bytes<'static>> for SettleResponse {
 #[derive(Debug)]
 pub enum VerifyResponse {
     /// The payload matches the requirements and passes all checks.
-    Valid { payer: MixedAddress },
+    Valid {
+        payer: MixedAddress,
+        /// What `payTo` will receive, if the token deducts a fee on transfer.
+        received_amount: Option<TokenAmount>,
+    },
     /// The payload was well-formed but failed verification due to the specified [`FacilitatorErrorReason`]
     Invalid {
         reason: FacilitatorErrorReason,
@@ -985,7 +2019,21 @@ impl VerifyResponse {
     ///
     /// Indicates that the provided payment payload has been validated against the payment requirements.
     pub fn valid(payer: MixedAddress) -> Self {
-        VerifyResponse::Valid { payer }
+        VerifyResponse::Valid {
+            payer,
+            received_amount: None,
+        }
+    }
+
+    /// Reports that `payTo` will receive `amount`, less than the signed value, on a valid response.
+    pub fn with_received_amount(mut self, amount: Option<TokenAmount>) -> Self {
+        if let VerifyResponse::Valid {
+            received_amount, ..
+        } = &mut self
+        {
+            *received_amount = amount;
+        }
+        self
     }
 
     /// Constructs a failed verification response with the given `payer` address and error `reason`.
@@ -1003,14 +2051,20 @@ impl Serialize for VerifyResponse {
         S: Serializer,
     {
         let mut s = match self {
-            VerifyResponse::Valid { .. } => serializer.serialize_struct("VerifyResponse", 2)?,
+            VerifyResponse::Valid { .. } => serializer.serialize_struct("VerifyResponse", 3)?,
             VerifyResponse::Invalid { .. } => serializer.serialize_struct("VerifyResponse", 3)?,
         };
 
         match self {
-            VerifyResponse::Valid { payer } => {
+            VerifyResponse::Valid {
+                payer,
+                received_amount,
+            } => {
                 s.serialize_field("isValid", &true)?;
                 s.serialize_field("payer", payer)?;
+                if let Some(received_amount) = received_amount {
+                    s.serialize_field("receivedAmount", received_amount)?
+                }
             }
             VerifyResponse::Invalid { reason, payer } => {
                 s.serialize_field("isValid", &false)?;
@@ -1038,6 +2092,8 @@ impl<'de> Deserialize<'de> for VerifyResponse {
             payer: Option<MixedAddress>,
             #[serde(default)]
             invalid_reason: Option<FacilitatorErrorReason>,
+            #[serde(default)]
+            received_amount: Option<TokenAmount>,
         }
 
         let raw = Raw::deserialize(deserializer)?;
@@ -1047,7 +2103,10 @@ impl<'de> Deserialize<'de> for VerifyResponse {
                 None => Err(serde::de::Error::custom(
                     "`payer` must be present when `isValid` is true",
                 )),
-                Some(payer) => Ok(VerifyResponse::Valid { payer }),
+                Some(payer) => Ok(VerifyResponse::Valid {
+                    payer,
+                    received_amount: raw.received_amount,
+                }),
             },
             (false, Some(reason)) => Ok(VerifyResponse::Invalid {
                 payer: raw.payer,
@@ -1065,7 +2124,7 @@ impl<'de> Deserialize<'de> for VerifyResponse {
 
 /// A simple error structure returned on unexpected or fatal server errors.
 /// Used when no structured protocol-level response is appropriate.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct ErrorResponse {
     pub error: String,
@@ -1102,7 +2161,7 @@ impl<'a> From<&'a [u8]> for Base64Bytes<'a> {
 
 /// Represents a price-like numeric value in human-readable currency format.
 /// Accepts strings like "$0.01", "1,000", "€20", or raw numbers.
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct MoneyAmount(pub Decimal);
 
 impl MoneyAmount {
@@ -1247,6 +2306,19 @@ impl Display for MoneyAmount {
     }
 }
 
+impl Serialize for MoneyAmount {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for MoneyAmount {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        MoneyAmount::from_str(&s).map_err(serde::de::Error::custom)
+    }
+}
+
 /// Metadata required to identify a token in EIP-712 typed data signatures.
 ///
 /// This struct contains the `name` and `version` fields used in the EIP-712 domain separator,
@@ -1260,6 +2332,8 @@ impl Display for MoneyAmount {
 pub struct TokenDeploymentEip712 {
     pub name: String,
     pub version: String,
+    /// Whether the domain carries the chain ID as `salt` instead of `chainId`, as Polygon PoS bridged tokens do.
+    pub chain_id_as_salt: bool,
 }
 
 /// Represents a fungible token identified by its address and network,
@@ -1321,6 +2395,7 @@ impl Display for TokenAsset {
 ///     eip712: TokenDeploymentEip712 {
 ///         name: "MyToken".into(),
 ///         version: "1".into(),
+///         chain_id_as_salt: false,
 ///     },
 /// };
 ///
@@ -1394,7 +2469,7 @@ impl Display for PaymentRequiredResponse {
     }
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SupportedPaymentKind {
     pub x402_version: X402Version,
@@ -1404,17 +2479,218 @@ pub struct SupportedPaymentKind {
     pub extra: Option<SupportedPaymentKindExtra>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct SupportedPaymentKindExtra {
     pub fee_payer: MixedAddress,
+    /// Address to name as `spender` in Permit2 and `permit` signatures, for the `permit2` and `permit` schemes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spender: Option<MixedAddress>,
+    /// Token accepted by this kind, for networks with several known USDC deployments or configured SPL mints.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<MixedAddress>,
+    /// Every account settlements may be sent from, when the facilitator spreads them over several signers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub signers: Vec<MixedAddress>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+#[allow(dead_code)] // Public for consumption by downstream crates.
+pub struct SupportedPaymentKindsResponse {
+    pub kinds: Vec<SupportedPaymentKind>,
+    /// Networks payments can be signed on to pay requirements of another network, see [`crate::routing`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<NetworkRoute>,
+    /// Ongoing and upcoming maintenance windows, see [`crate::maintenance`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub maintenance: Vec<MaintenanceWindow>,
+}
+
+/// Period during which the facilitator, or one of its networks, is expected to be unavailable.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct MaintenanceWindow {
+    pub start: UnixTimestamp,
+    pub end: UnixTimestamp,
+    /// Network under maintenance; the whole facilitator if `None`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network: Option<Network>,
+}
+
+/// Payments signed on `source` may pay requirements on `destination`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+pub struct NetworkRoute {
+    pub source: Network,
+    pub destination: Network,
+}
+
+impl SupportedPaymentKindsResponse {
+    /// Adds a version 2 kind, with the CAIP-2 network, after every version 1 kind.
+    pub fn with_v2_kinds(self) -> Self {
+        let Self {
+            kinds,
+            routes,
+            maintenance,
+        } = self;
+        let kinds = kinds
+            .into_iter()
+            .flat_map(|kind| {
+                let v2 = (kind.x402_version == X402Version::V1)
+                    .then(|| Network::from_str(&kind.network).ok())
+                    .flatten()
+                    .map(|network| SupportedPaymentKind {
+                        x402_version: X402Version::V2,
+                        network: network.caip2(),
+                        ..kind.clone()
+                    });
+                std::iter::once(kind).chain(v2)
+            })
+            .collect();
+        Self {
+            kinds,
+            routes,
+            maintenance,
+        }
+    }
+}
+
+/// Opaque continuation token for cursor-based pagination.
+///
+/// A cursor encodes the position of the last item returned on a page as a URL-safe base64 string.
+/// Clients must treat it as opaque and pass it back verbatim to fetch the next page.
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Cursor(pub String);
+
+impl Cursor {
+    /// Builds a cursor pointing right after the item identified by `key`.
+    pub fn from_key(key: &str) -> Self {
+        Cursor(b64_url.encode(key.as_bytes()))
+    }
+
+    /// Decodes the item key this cursor points after.
+    pub fn key(&self) -> Result<String, PaginationError> {
+        let bytes = b64_url
+            .decode(self.0.as_bytes())
+            .map_err(|_| PaginationError::InvalidCursor)?;
+        String::from_utf8(bytes).map_err(|_| PaginationError::InvalidCursor)
+    }
 }
 
+impl Display for Cursor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Errors produced while resolving a [`PageRequest`] against a listing.
+#[derive(Debug, thiserror::Error)]
+pub enum PaginationError {
+    /// The cursor is malformed or no longer points to a known item.
+    #[error("Invalid or expired cursor")]
+    InvalidCursor,
+}
+
+/// Query parameters accepted by list endpoints: `?cursor=...&limit=...`.
+///
+/// `limit` defaults to [`PageRequest::DEFAULT_LIMIT`] and is clamped to `1..=`[`PageRequest::MAX_LIMIT`].
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PageRequest {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cursor: Option<Cursor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+}
+
+#[allow(dead_code)] // Public for consumption by downstream crates.
+impl PageRequest {
+    /// Page size used when the client does not pass `limit`.
+    pub const DEFAULT_LIMIT: usize = 50;
+    /// Largest page size a client may request.
+    pub const MAX_LIMIT: usize = 500;
+
+    /// Effective page size after applying defaults and limits.
+    pub fn limit(&self) -> usize {
+        self.limit
+            .unwrap_or(Self::DEFAULT_LIMIT)
+            .clamp(1, Self::MAX_LIMIT)
+    }
+
+    /// Cuts a page out of `items`, which must be in a stable order across requests.
+    ///
+    /// `key` returns a unique, stable identifier for an item; it is what the cursor encodes.
+    ///
+    /// # Errors
+    /// Returns [`PaginationError::InvalidCursor`] if the cursor does not decode
+    /// or does not match any item in the listing.
+    pub fn paginate<T, K, I, F>(&self, items: I, key: F) -> Result<Page<T>, PaginationError>
+    where
+        I: IntoIterator<Item = T>,
+        F: Fn(&T) -> K,
+        K: ToString,
+    {
+        let limit = self.limit();
+        let mut items = items.into_iter();
+        if let Some(cursor) = &self.cursor {
+            let after = cursor.key()?;
+            items
+                .by_ref()
+                .find(|item| key(item).to_string() == after)
+                .ok_or(PaginationError::InvalidCursor)?;
+        }
+        let page = items.by_ref().take(limit).collect::<Vec<_>>();
+        let has_more = items.next().is_some();
+        let next_cursor = if has_more {
+            page.last()
+                .map(|last| Cursor::from_key(&key(last).to_string()))
+        } else {
+            None
+        };
+        Ok(Page {
+            items: page,
+            next_cursor,
+            next: None,
+        })
+    }
+}
+
+/// A single page of a cursor-paginated listing.
+///
+/// When more items are available, `nextCursor` is set, and `next` carries a ready-to-use link
+/// to the following page if the endpoint knows its own URL (see [`Page::with_next_link`]).
 #[derive(Clone, Debug, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<Cursor>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub next: Option<Url>,
+}
+
 #[allow(dead_code)] // Public for consumption by downstream crates.
-pub struct SupportedPaymentKindsResponse {
-    pub kinds: Vec<SupportedPaymentKind>,
+impl<T> Page<T> {
+    /// Fills in the `next` link by appending `cursor` and `limit` query parameters to `base`.
+    pub fn with_next_link(mut self, base: &Url, request: &PageRequest) -> Self {
+        self.next = self.next_cursor.as_ref().map(|cursor| {
+            let mut next = base.clone();
+            next.query_pairs_mut()
+                .clear()
+                .append_pair("cursor", &cursor.0)
+                .append_pair("limit", &request.limit().to_string());
+            next
+        });
+        self
+    }
+
+    /// Transforms every item on the page, preserving the pagination metadata.
+    pub fn map<U, F: FnMut(T) -> U>(self, f: F) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            next: self.next,
+        }
+    }
 }
 
 sol!(
@@ -1436,4 +2712,299 @@ sol!(
         uint256 validBefore;
         bytes32 nonce;
     }
+
+    /// Solidity-compatible struct definition for ERC-3009 `receiveWithAuthorization`.
+    ///
+    /// Same fields as [`TransferWithAuthorization`], under another EIP-712 type, so that a signature
+    /// for one function can not be used with the other.
+    #[derive(Serialize, Deserialize)]
+    struct ReceiveWithAuthorization {
+        address from;
+        address to;
+        uint256 value;
+        uint256 validAfter;
+        uint256 validBefore;
+        bytes32 nonce;
+    }
 );
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn token_amount_accepts_strings_and_integers() {
+        let parse = |json: &str| serde_json::from_str::<TokenAmount>(json);
+        assert_eq!(parse("\"10000\"").unwrap(), TokenAmount::from(10000u128));
+        assert_eq!(parse("10000").unwrap(), TokenAmount::from(10000u128));
+        assert_eq!(
+            serde_json::to_string(&parse("10000").unwrap()).unwrap(),
+            "\"10000\""
+        );
+        assert_eq!(parse(&format!("\"{}\"", U256::MAX)).unwrap().0, U256::MAX);
+        for invalid in [
+            "-1",
+            "1.5",
+            "1e30",
+            "\"\"",
+            "\"0x10\"",
+            "\"-1\"",
+            "\"115792089237316195423570985008687907853269984665640564039457584007913129639936\"",
+        ] {
+            assert!(parse(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn payment_id_travels_with_the_request() {
+        let mut request: VerifyRequest = serde_json::from_value(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {"transaction": "AA=="},
+                "paymentId": "pay_1"
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": "1",
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": "0x0000000000000000000000000000000000000001",
+                "maxTimeoutSeconds": 60,
+                "asset": "0x0000000000000000000000000000000000000002",
+                "extra": null,
+                "paymentId": "pay_2"
+            }
+        }))
+        .unwrap();
+        assert_eq!(request.payment_id(), Some(&PaymentId("pay_1".to_string())));
+        request.payment_payload.payment_id = None;
+        assert_eq!(request.payment_id(), Some(&PaymentId("pay_2".to_string())));
+        request.payment_requirements.payment_id = None;
+        assert_eq!(request.payment_id(), None);
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json["paymentRequirements"].get("paymentId").is_none());
+        assert!(PaymentId::random().0.starts_with("pay_"));
+    }
+
+    #[test]
+    fn v2_requests_convert_to_v1() {
+        let requirements = serde_json::json!({
+            "scheme": "exact",
+            "network": "eip155:8453",
+            "amount": "1000",
+            "asset": "0x0000000000000000000000000000000000000002",
+            "payTo": "0x0000000000000000000000000000000000000001",
+            "maxTimeoutSeconds": 60,
+        });
+        let request: VerifyRequest = serde_json::from_value(serde_json::json!({
+            "x402Version": 2,
+            "paymentPayload": {
+                "x402Version": 2,
+                "resource": {"url": "https://example.com/paid", "mimeType": "application/json"},
+                "accepted": requirements,
+                "payload": {"transaction": "AA=="}
+            },
+            "paymentRequirements": requirements
+        }))
+        .unwrap();
+        assert_eq!(request.x402_version, X402Version::V2);
+        assert_eq!(request.network(), Network::Base);
+        assert_eq!(request.payment_payload.scheme, Scheme::Exact);
+        assert_eq!(
+            request.payment_requirements.max_amount_required,
+            TokenAmount::from(1000u64)
+        );
+        assert_eq!(
+            request.payment_requirements.resource.as_str(),
+            "https://example.com/paid"
+        );
+
+        let v2 = serde_json::to_value(VerifyRequestV2::from(&request)).unwrap();
+        assert_eq!(v2["paymentRequirements"], requirements);
+        for (field, value) in [
+            ("payTo", "0x0000000000000000000000000000000000000003"),
+            ("asset", "0x0000000000000000000000000000000000000003"),
+            ("amount", "1"),
+        ] {
+            let mut accepted = requirements.clone();
+            accepted[field] = serde_json::json!(value);
+            let mut mismatched = v2.clone();
+            mismatched["paymentPayload"]["accepted"] = accepted;
+            let error = serde_json::from_value::<VerifyRequest>(mismatched).unwrap_err();
+            assert!(
+                error
+                    .to_string()
+                    .contains(&format!("accepted {field} differs")),
+                "{error}"
+            );
+        }
+        let settled = SettleResponse {
+            success: true,
+            error_reason: None,
+            payer: Some(MixedAddress::Near("alice.near".to_string())),
+            transaction: None,
+            network: Network::Solana,
+            facilitator_version: None,
+            batch: None,
+            payment_id: None,
+            partial: None,
+            revert_reason: None,
+            signer: None,
+            settlement_id: None,
+            simulated: false,
+        };
+        assert_eq!(
+            serde_json::to_value(settled.into_v2()).unwrap()["network"],
+            "solana:5eykt4UsFv8P8NJdTREpY1vzqKqZKvdp"
+        );
+    }
+
+    #[test]
+    fn permit_payloads_are_not_confused_with_permit2() {
+        let permit = serde_json::json!({
+            "signature": "0x00",
+            "permit": {
+                "owner": "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+                "spender": "0x036CbD53842c5426634e7929541eC2318f3dCF7e",
+                "value": "1000",
+                "nonce": "0",
+                "deadline": "1700000000",
+            }
+        });
+        assert!(matches!(
+            serde_json::from_value::<ExactPaymentPayload>(permit).unwrap(),
+            ExactPaymentPayload::Permit(_)
+        ));
+    }
+
+    #[test]
+    fn evm_address_checksum_is_enforced_on_mixed_case() {
+        let checksummed = "0x036CbD53842c5426634e7929541eC2318f3dCF7e";
+        let expected = EvmAddress::from_str(checksummed).unwrap();
+        assert_eq!(
+            EvmAddress::from_str(&checksummed.to_lowercase()).unwrap(),
+            expected
+        );
+        assert_eq!(
+            EvmAddress::from_str(&format!("0x{}", checksummed[2..].to_uppercase())).unwrap(),
+            expected
+        );
+        assert_eq!(
+            EvmAddress::from_str("0x036cbD53842c5426634e7929541eC2318f3dCF7e"),
+            Err(EvmAddressDecodingError::InvalidChecksum {
+                expected: checksummed.to_string()
+            })
+        );
+        for garbage in [
+            "",
+            "0x",
+            "0x036c",
+            "0x036CbD53842c5426634e7929541eC2318f3dCF7é",
+            "0xzz",
+        ] {
+            assert_eq!(
+                EvmAddress::from_str(garbage),
+                Err(EvmAddressDecodingError::InvalidFormat)
+            );
+        }
+        let error =
+            serde_json::from_str::<MixedAddress>("\"0x036cbD53842c5426634e7929541eC2318f3dCF7e\"")
+                .unwrap_err();
+        assert!(error.to_string().contains(checksummed));
+
+        let error = serde_json::from_value::<VerifyRequest>(serde_json::json!({
+            "x402Version": 1,
+            "paymentPayload": {
+                "x402Version": 1,
+                "scheme": "exact",
+                "network": "base",
+                "payload": {
+                    "signature": format!("0x{}", "11".repeat(65)),
+                    "authorization": {
+                        "from": "0x0000000000000000000000000000000000000003",
+                        "to": "0x036cbD53842c5426634e7929541eC2318f3dCF7e",
+                        "value": "1",
+                        "validAfter": "0",
+                        "validBefore": "2000",
+                        "nonce": format!("0x{}", "22".repeat(32))
+                    }
+                }
+            },
+            "paymentRequirements": {
+                "scheme": "exact",
+                "network": "base",
+                "maxAmountRequired": "1",
+                "resource": "https://example.com/paid",
+                "description": "",
+                "mimeType": "application/json",
+                "payTo": checksummed,
+                "maxTimeoutSeconds": 60,
+                "asset": checksummed
+            }
+        }))
+        .unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("paymentPayload.payload.authorization.to: Invalid EIP-55 checksum"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn paginate_walks_all_items_with_cursors() {
+        let items: Vec<u32> = (1..=5).collect();
+        let mut request = PageRequest {
+            cursor: None,
+            limit: Some(2),
+        };
+        let mut seen = Vec::new();
+        loop {
+            let page = request
+                .paginate(items.iter().copied(), |item| *item)
+                .expect("valid page");
+            seen.extend(page.items.iter().copied());
+            match page.next_cursor {
+                Some(cursor) => request.cursor = Some(cursor),
+                None => break,
+            }
+        }
+        assert_eq!(seen, items);
+    }
+
+    #[test]
+    fn paginate_rejects_unknown_cursor() {
+        let request = PageRequest {
+            cursor: Some(Cursor::from_key("42")),
+            limit: None,
+        };
+        let result = request.paginate(vec![1u32, 2, 3], |item| *item);
+        assert!(matches!(result, Err(PaginationError::InvalidCursor)));
+    }
+
+    #[test]
+    fn next_link_carries_cursor_and_limit() {
+        let request = PageRequest {
+            cursor: None,
+            limit: Some(1),
+        };
+        let base = Url::parse("https://facilitator.example/settlements").unwrap();
+        let page = request
+            .paginate(vec!["a", "b"], |item| *item)
+            .unwrap()
+            .with_next_link(&base, &request);
+        let next = page.next.expect("next link");
+        assert_eq!(
+            next.as_str(),
+            format!(
+                "https://facilitator.example/settlements?cursor={}&limit=1",
+                Cursor::from_key("a")
+            )
+        );
+    }
+}
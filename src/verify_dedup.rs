@@ -0,0 +1,216 @@
+//! Answers repeated identical `/verify` requests from a short-lived cache.
+//!
+//! Some clients re-verify the same payment in a tight loop, each time costing several RPC calls. With
+//! [`VerifyDedup`], time is cut into buckets of `VERIFY_DEDUP_WINDOW_MS`: the first `POST /verify` of a given body
+//! in a bucket is verified as usual, and identical requests in the same bucket get its answer again, without
+//! reaching the facilitator. Duplicates are counted in the `x402.verify.duplicates` metric.
+//!
+//! Requests are identical if their body, `Content-Type` and `Accept` are. Only `200` answers, valid or invalid, are
+//! reused; errors and timeouts are not, so a transient RPC failure is retried by the next request. As answers are
+//! reused for at most one window, keep it short: a payment verified invalid for lack of funds stays so until the
+//! next bucket, even if the payer tops up in between.
+//!
+//! Environment variables used:
+//! - `VERIFY_DEDUP_WINDOW_MS` — length of a bucket in milliseconds. Requests are not deduplicated if unset or `0`.
+
+use axum::Json;
+use axum::body::{Body, Bytes};
+use axum::extract::{Request, State};
+use axum::http::{HeaderMap, Method, StatusCode, header};
+use axum::middleware::Next;
+use axum::response::{IntoResponse, Response};
+use dashmap::DashMap;
+use sha2::{Digest, Sha256};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::from_env;
+use crate::types::ErrorResponse;
+
+/// Largest request body considered; bigger requests are rejected with `413 Payload Too Large`.
+const MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+
+/// A `/verify` answer, as sent to the first request of its bucket.
+struct CachedAnswer {
+    bucket: u64,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+struct VerifyDedupInner {
+    window: Duration,
+    /// Bucket of the answers in `answers`; older ones are dropped when it changes.
+    bucket: AtomicU64,
+    answers: DashMap<[u8; 32], CachedAnswer>,
+}
+
+/// Cache of `/verify` answers by request hash, for the current time bucket.
+///
+/// Cheap to clone: all clones share the same answers.
+#[derive(Clone)]
+pub struct VerifyDedup {
+    inner: Arc<VerifyDedupInner>,
+}
+
+impl VerifyDedup {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            inner: Arc::new(VerifyDedupInner {
+                window,
+                bucket: AtomicU64::new(0),
+                answers: DashMap::new(),
+            }),
+        }
+    }
+
+    /// Reads the bucket length from `VERIFY_DEDUP_WINDOW_MS`; `None` if unset or `0`.
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let Ok(value) = std::env::var(from_env::ENV_VERIFY_DEDUP_WINDOW_MS) else {
+            return Ok(None);
+        };
+        let window_ms = value
+            .parse::<u64>()
+            .map_err(|e| format!("{}: {e}", from_env::ENV_VERIFY_DEDUP_WINDOW_MS))?;
+        Ok((window_ms > 0).then(|| Self::new(Duration::from_millis(window_ms))))
+    }
+
+    /// Current time bucket, dropping the answers of past ones when it changes.
+    fn bucket(&self, now: SystemTime) -> u64 {
+        let elapsed = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let bucket = (elapsed.as_millis() / self.inner.window.as_millis()) as u64;
+        if self.inner.bucket.swap(bucket, Ordering::Relaxed) != bucket {
+            self.inner
+                .answers
+                .retain(|_, answer| answer.bucket == bucket);
+        }
+        bucket
+    }
+
+    fn get(&self, key: &[u8; 32], bucket: u64) -> Option<(HeaderMap, Bytes)> {
+        self.inner
+            .answers
+            .get(key)
+            .filter(|answer| answer.bucket == bucket)
+            .map(|answer| (answer.headers.clone(), answer.body.clone()))
+    }
+
+    fn put(&self, key: [u8; 32], bucket: u64, headers: HeaderMap, body: Bytes) {
+        self.inner.answers.insert(
+            key,
+            CachedAnswer {
+                bucket,
+                headers,
+                body,
+            },
+        );
+    }
+}
+
+/// Hash identifying a request: its body and the headers the answer depends on.
+fn request_key(headers: &HeaderMap, body: &[u8]) -> [u8; 32] {
+    let mut hasher = Sha256::new();
+    for name in [header::CONTENT_TYPE, header::ACCEPT] {
+        let value = headers.get(&name).map(|value| value.as_bytes());
+        hasher.update(value.unwrap_or_default());
+        hasher.update([0]);
+    }
+    hasher.update(body);
+    hasher.finalize().into()
+}
+
+/// Serves `POST /verify` and `POST /v1/verify` from the [`VerifyDedup`] cache if the same request was answered in
+/// the current bucket.
+pub async fn dedup_verify(
+    State(dedup): State<VerifyDedup>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    if request.method() != Method::POST || path.strip_prefix("/v1").unwrap_or(path) != "/verify" {
+        return next.run(request).await;
+    }
+    let (parts, body) = request.into_parts();
+    let bytes: Bytes = match axum::body::to_bytes(body, MAX_PAYLOAD_BYTES).await {
+        Ok(bytes) => bytes,
+        Err(_) => {
+            return (
+                StatusCode::PAYLOAD_TOO_LARGE,
+                Json(ErrorResponse {
+                    error: "Request body too large".to_string(),
+                }),
+            )
+                .into_response();
+        }
+    };
+    let key = request_key(&parts.headers, &bytes);
+    let bucket = dedup.bucket(SystemTime::now());
+    if let Some((headers, body)) = dedup.get(&key, bucket) {
+        tracing::info!(monotonic_counter.x402.verify.duplicates = 1);
+        return (StatusCode::OK, headers, body).into_response();
+    }
+    let response = next
+        .run(Request::from_parts(parts, Body::from(bytes)))
+        .await;
+    if response.status() != StatusCode::OK {
+        return response;
+    }
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            tracing::warn!(error = %e, "Failed to read verify response");
+            return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+        }
+    };
+    dedup.put(key, bucket, parts.headers.clone(), body.clone());
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::Router;
+    use axum::routing::post;
+    use std::sync::atomic::AtomicUsize;
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn identical_requests_are_answered_once_per_bucket() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counted = calls.clone();
+        let handler = move |body: Bytes| {
+            let calls = counted.clone();
+            async move {
+                calls.fetch_add(1, Ordering::SeqCst);
+                body
+            }
+        };
+        let app = Router::new()
+            .route("/verify", post(handler.clone()))
+            .route("/v1/verify", post(handler))
+            .route_layer(axum::middleware::from_fn_with_state(
+                VerifyDedup::new(Duration::from_secs(3600)),
+                dedup_verify,
+            ));
+        let verify = |path: &'static str, body: &'static str| {
+            Request::post(path)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body))
+                .unwrap()
+        };
+        let requests = [
+            ("/verify", "{\"a\":1}"),
+            ("/v1/verify", "{\"a\":1}"),
+            ("/verify", "{\"a\":2}"),
+        ];
+        for (path, body) in requests {
+            let response = app.clone().oneshot(verify(path, body)).await.unwrap();
+            let answer = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            assert_eq!(answer, body.as_bytes());
+        }
+        assert_eq!(calls.load(Ordering::SeqCst), 2);
+    }
+}
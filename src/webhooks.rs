@@ -0,0 +1,176 @@
+//! Signed webhook notifications of settlement lifecycle events.
+//!
+//! Resource servers fulfilling orders asynchronously need not poll `GET /settlements/{id}`: every
+//! [`SettlementEvent`] of the settlement queue is posted as JSON to each URL of `SETTLEMENT_WEBHOOK_URLS`:
+//! - `settlement.submitted` — the settlement is queued, before its transaction is sent,
+//! - `settlement.confirmed` — its transaction is mined successfully,
+//! - `settlement.failed` — it failed, or a reorganization undid it.
+//!
+//! The body is signed as an EIP-191 personal message by the facilitator identity key, see [`crate::identity`]:
+//! the signature is sent in the `X-Facilitator-Signature` header and the signing address in
+//! `X-Facilitator-Identity`, to be checked against the keys published at `GET /.well-known/x402-facilitator`.
+//!
+//! Deliveries failing (connection errors, non-2xx answers) are attempted up to [`MAX_ATTEMPTS`] times, 1, 2, 4…
+//! seconds apart; events are not persisted, so deliveries still pending at shutdown are lost. Receivers should
+//! be idempotent: an event may be delivered more than once, and `settlement.id` identifies the settlement.
+//! Deliveries are counted in the `x402.webhook.deliveries` metric, by outcome.
+//!
+//! Environment variables used:
+//! - `SETTLEMENT_WEBHOOK_URLS` — comma-separated URLs events are posted to, subject to the outbound policy.
+
+use alloy::hex;
+use std::time::Duration;
+use tokio::sync::broadcast;
+use tokio_util::sync::CancellationToken;
+use url::Url;
+
+use crate::from_env;
+use crate::identity::FacilitatorIdentity;
+use crate::outbound::OutboundPolicy;
+use crate::settle_queue::SettlementEvent;
+
+/// Attempts of a delivery, the first included.
+pub const MAX_ATTEMPTS: u32 = 5;
+/// Delay before the first retry, doubled on every further one.
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+/// Header carrying the signature of the body.
+pub const SIGNATURE_HEADER: &str = "X-Facilitator-Signature";
+/// Header carrying the identity address that signed the body.
+pub const IDENTITY_HEADER: &str = "X-Facilitator-Identity";
+
+/// Endpoints settlement events are posted to, see the [module docs](self).
+#[derive(Clone)]
+pub struct Webhooks {
+    urls: Vec<Url>,
+    http: reqwest::Client,
+    identity: FacilitatorIdentity,
+}
+
+impl Webhooks {
+    /// Webhooks posting to `urls` with `http`, signed by `identity`.
+    pub fn new(urls: Vec<Url>, http: reqwest::Client, identity: FacilitatorIdentity) -> Self {
+        Self {
+            urls,
+            http,
+            identity,
+        }
+    }
+
+    /// Reads the URLs from `SETTLEMENT_WEBHOOK_URLS`; `None` if unset or empty.
+    pub fn from_env(
+        identity: FacilitatorIdentity,
+    ) -> Result<Option<Self>, Box<dyn std::error::Error>> {
+        let Ok(value) = std::env::var(from_env::ENV_SETTLEMENT_WEBHOOK_URLS) else {
+            return Ok(None);
+        };
+        let outbound = OutboundPolicy::from_env()?;
+        let urls = value
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(|url| {
+                let url = Url::parse(url)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_SETTLEMENT_WEBHOOK_URLS))?;
+                outbound
+                    .validate_url(&url)
+                    .map_err(|e| format!("{}: {e}", from_env::ENV_SETTLEMENT_WEBHOOK_URLS))?;
+                Ok(url)
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        if urls.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self::new(urls, outbound.http_client(), identity)))
+    }
+
+    /// Posts every event of `events` to every endpoint until cancelled.
+    pub fn spawn(
+        self,
+        mut events: broadcast::Receiver<SettlementEvent>,
+        cancellation_token: CancellationToken,
+    ) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(async move {
+            loop {
+                let event = tokio::select! {
+                    _ = cancellation_token.cancelled() => break,
+                    event = events.recv() => event,
+                };
+                match event {
+                    Ok(event) => self.notify(&event),
+                    Err(broadcast::error::RecvError::Lagged(missed)) => {
+                        tracing::warn!(missed, "Missed settlement events, webhooks not sent");
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        })
+    }
+
+    /// Signs `event` and spawns its delivery to every endpoint.
+    fn notify(&self, event: &SettlementEvent) {
+        let signed = serde_json::to_vec(event)
+            .map_err(|e| e.to_string())
+            .and_then(|body| {
+                let signature = self.identity.sign(&body).map_err(|e| e.to_string())?;
+                Ok((body, signature))
+            });
+        let (body, signature) = match signed {
+            Ok(signed) => signed,
+            Err(e) => {
+                tracing::error!(id = event.settlement.id, error = %e, "Failed to sign webhook payload");
+                return;
+            }
+        };
+        let signature_header = format!("0x{}", hex::encode(&signature.signature.0));
+        let identity_header = signature.address.to_string();
+        for url in &self.urls {
+            let request = self
+                .http
+                .post(url.clone())
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, &signature_header)
+                .header(IDENTITY_HEADER, &identity_header)
+                .body(body.clone());
+            let id = event.settlement.id.clone();
+            let kind = event.kind;
+            let url = url.clone();
+            tokio::spawn(async move {
+                let outcome = match deliver(request).await {
+                    Ok(()) => "delivered",
+                    Err(e) => {
+                        tracing::warn!(id, event = ?kind, url = %url, error = %e, "Failed to deliver settlement webhook");
+                        "failed"
+                    }
+                };
+                tracing::info!(
+                    monotonic_counter.x402.webhook.deliveries = 1,
+                    event = ?kind,
+                    outcome,
+                );
+            });
+        }
+    }
+}
+
+/// Sends `request` until it is answered with a success status, up to [`MAX_ATTEMPTS`] times.
+pub(crate) async fn deliver(request: reqwest::RequestBuilder) -> Result<(), reqwest::Error> {
+    let mut attempt = 1;
+    loop {
+        // Bodies are in memory, so requests can always be cloned.
+        let result = request
+            .try_clone()
+            .expect("request is cloneable")
+            .send()
+            .await
+            .and_then(|response| response.error_for_status());
+        match result {
+            Ok(_) => return Ok(()),
+            Err(e) if attempt >= MAX_ATTEMPTS => return Err(e),
+            Err(_) => {
+                tokio::time::sleep(RETRY_DELAY * 2u32.pow(attempt - 1)).await;
+                attempt += 1;
+            }
+        }
+    }
+}